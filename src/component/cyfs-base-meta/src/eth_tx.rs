@@ -0,0 +1,376 @@
+use crate::*;
+use cyfs_base::*;
+use primitive_types::{H160, U256};
+use rlp::{DecoderError, Rlp, RlpStream};
+use sha3::{Digest, Keccak256};
+
+// 以太坊工具链通过eth_chainId区分网络，meta链使用固定的chain id
+pub const META_ETH_CHAIN_ID: u64 = 0x4359;
+
+// EIP-1559交易的类型前缀
+const ETH_TX_TYPE_DYNAMIC_FEE: u8 = 0x02;
+
+/*
+以太坊签名交易到MetaTx的映射
+1. 只支持带chain id的EIP-155 legacy交易和EIP-1559交易，chain id必须是META_ETH_CHAIN_ID，不支持创建合约
+2. 映射出的MetaTx: caller为发送方对应的meta账户，nonce为以太坊nonce+1，gas_coin_id为0，唯一的body为CallContractTx
+3. MetaTx没有desc签名，body的data保存原始的以太坊交易；校验时从以太坊交易恢复secp256k1公钥和caller账户的公钥比较，
+   并用以太坊交易重新映射出desc，和MetaTx的desc完全一致才算通过，desc里的每个字段都不能被篡改
+*/
+#[derive(Clone, Debug)]
+pub struct EthRawTx {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub gas_price: U256,
+    pub gas_limit: U256,
+    pub to: Option<H160>,
+    pub value: U256,
+    pub data: Vec<u8>,
+
+    signing_hash: [u8; 32],
+    recovery_id: u8,
+    signature: [u8; 64],
+}
+
+fn invalid_tx(msg: impl std::fmt::Display) -> BuckyError {
+    let msg = format!("invalid eth raw tx: {}", msg);
+    log::warn!("{}", msg);
+    BuckyError::new(BuckyErrorCode::InvalidData, msg)
+}
+
+fn rlp_error(e: DecoderError) -> BuckyError {
+    invalid_tx(format!("rlp decode failed, {}", e))
+}
+
+fn keccak(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&Keccak256::digest(data));
+    hash
+}
+
+fn decode_u256(item: &Rlp) -> BuckyResult<U256> {
+    let data = item.data().map_err(rlp_error)?;
+    if data.len() > 32 || (data.len() > 0 && data[0] == 0) {
+        return Err(invalid_tx("non-canonical integer"));
+    }
+    Ok(U256::from_big_endian(data))
+}
+
+fn decode_u64(item: &Rlp) -> BuckyResult<u64> {
+    let value = decode_u256(item)?;
+    if value > U256::from(u64::MAX) {
+        return Err(invalid_tx(format!("integer overflow: {}", value)));
+    }
+    Ok(value.as_u64())
+}
+
+fn decode_to(item: &Rlp) -> BuckyResult<Option<H160>> {
+    let data = item.data().map_err(rlp_error)?;
+    match data.len() {
+        0 => Ok(None),
+        20 => Ok(Some(H160::from_slice(data))),
+        len => Err(invalid_tx(format!("invalid to address length: {}", len))),
+    }
+}
+
+fn decode_signature(r: &Rlp, s: &Rlp) -> BuckyResult<[u8; 64]> {
+    let mut signature = [0u8; 64];
+    decode_u256(r)?.to_big_endian(&mut signature[..32]);
+    decode_u256(s)?.to_big_endian(&mut signature[32..]);
+    Ok(signature)
+}
+
+// 整个buf必须正好是一个包含count项的list
+fn open_list(buf: &[u8], count: usize) -> BuckyResult<Rlp> {
+    let rlp = Rlp::new(buf);
+    let info = rlp.payload_info().map_err(rlp_error)?;
+    if !rlp.is_list() || info.header_len + info.value_len != buf.len() {
+        return Err(invalid_tx("tx should be a single rlp list"));
+    }
+
+    let item_count = rlp.item_count().map_err(rlp_error)?;
+    if item_count != count {
+        return Err(invalid_tx(format!(
+            "tx should have {} fields, got {}",
+            count, item_count
+        )));
+    }
+
+    Ok(rlp)
+}
+
+// secp256k1公钥对应的以太坊地址，非secp256k1公钥没有以太坊地址
+pub fn eth_address_of_public_key(public_key: &PublicKey) -> Option<H160> {
+    match public_key {
+        PublicKey::Secp256k1(key) => {
+            let raw = key.serialize();
+            let hash = keccak(&raw[1..]);
+            Some(H160::from_slice(&hash[12..]))
+        }
+        _ => None,
+    }
+}
+
+impl EthRawTx {
+    pub fn decode(buf: &[u8]) -> BuckyResult<Self> {
+        match buf.first() {
+            Some(&ETH_TX_TYPE_DYNAMIC_FEE) => Self::decode_dynamic_fee(&buf[1..]),
+            Some(v) if *v >= 0xc0 => Self::decode_legacy(buf),
+            Some(v) => Err(invalid_tx(format!("tx type not supported: {}", v))),
+            None => Err(invalid_tx("empty tx")),
+        }
+    }
+
+    // [nonce, gas_price, gas_limit, to, value, data, v, r, s]
+    fn decode_legacy(buf: &[u8]) -> BuckyResult<Self> {
+        let rlp = open_list(buf, 9)?;
+        let at = |i| rlp.at(i).map_err(rlp_error);
+
+        // EIP-155: v = chain_id * 2 + 35 + recovery_id，没有chain id的交易可以被跨链重放，不接受
+        let v = decode_u64(&at(6)?)?;
+        if v < 35 {
+            return Err(invalid_tx("tx without chain id is not accepted"));
+        }
+        let chain_id = (v - 35) / 2;
+        let recovery_id = ((v - 35) % 2) as u8;
+
+        let mut stream = RlpStream::new_list(9);
+        for i in 0..6 {
+            stream.append_raw(at(i)?.as_raw(), 1);
+        }
+        stream.append(&chain_id);
+        stream.append(&0u8);
+        stream.append(&0u8);
+        let signing_hash = keccak(&stream.out());
+
+        Ok(Self {
+            chain_id,
+            nonce: decode_u64(&at(0)?)?,
+            gas_price: decode_u256(&at(1)?)?,
+            gas_limit: decode_u256(&at(2)?)?,
+            to: decode_to(&at(3)?)?,
+            value: decode_u256(&at(4)?)?,
+            data: at(5)?.data().map_err(rlp_error)?.to_vec(),
+            signing_hash,
+            recovery_id,
+            signature: decode_signature(&at(7)?, &at(8)?)?,
+        })
+    }
+
+    // 0x02 || [chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit, to, value, data, access_list, y_parity, r, s]
+    fn decode_dynamic_fee(payload: &[u8]) -> BuckyResult<Self> {
+        let rlp = open_list(payload, 12)?;
+        let at = |i| rlp.at(i).map_err(rlp_error);
+
+        let y_parity = decode_u64(&at(9)?)?;
+        if y_parity > 1 {
+            return Err(invalid_tx(format!("invalid y parity: {}", y_parity)));
+        }
+
+        let mut stream = RlpStream::new_list(9);
+        for i in 0..9 {
+            stream.append_raw(at(i)?.as_raw(), 1);
+        }
+        let mut unsigned = vec![ETH_TX_TYPE_DYNAMIC_FEE];
+        unsigned.extend_from_slice(&stream.out());
+        let signing_hash = keccak(&unsigned);
+
+        // meta链没有小费，按max_fee_per_gas作为gas price
+        Ok(Self {
+            chain_id: decode_u64(&at(0)?)?,
+            nonce: decode_u64(&at(1)?)?,
+            gas_price: decode_u256(&at(3)?)?,
+            gas_limit: decode_u256(&at(4)?)?,
+            to: decode_to(&at(5)?)?,
+            value: decode_u256(&at(6)?)?,
+            data: at(7)?.data().map_err(rlp_error)?.to_vec(),
+            signing_hash,
+            recovery_id: y_parity as u8,
+            signature: decode_signature(&at(10)?, &at(11)?)?,
+        })
+    }
+
+    pub fn recover_public_key(&self) -> BuckyResult<PublicKey> {
+        let message = secp256k1::Message::parse(&self.signing_hash);
+        let signature = secp256k1::Signature::parse(&self.signature);
+        let recovery_id = secp256k1::RecoveryId::parse(self.recovery_id)
+            .map_err(|e| invalid_tx(format!("invalid recovery id: {:?}", e)))?;
+        let key = secp256k1::recover(&message, &signature, &recovery_id)
+            .map_err(|e| invalid_tx(format!("recover public key failed: {:?}", e)))?;
+
+        Ok(PublicKey::Secp256k1(key))
+    }
+
+    pub fn sender(&self) -> BuckyResult<H160> {
+        let public_key = self.recover_public_key()?;
+        Ok(eth_address_of_public_key(&public_key).unwrap())
+    }
+
+    // 映射成MetaTx，caller为发送方的meta账户，address为to对应的合约
+    pub fn to_meta_tx(&self, raw: &[u8], caller: ObjectId, address: ObjectId) -> BuckyResult<MetaTx> {
+        if self.chain_id != META_ETH_CHAIN_ID {
+            return Err(invalid_tx(format!(
+                "chain id unmatch: expect={}, got={}",
+                META_ETH_CHAIN_ID, self.chain_id
+            )));
+        }
+
+        let to = self
+            .to
+            .ok_or_else(|| invalid_tx("contract creation is not supported"))?;
+        if &address.as_slice()[12..] != to.as_bytes() {
+            return Err(invalid_tx(format!(
+                "contract address unmatch: to={:?}, address={}",
+                to, address
+            )));
+        }
+
+        if self.nonce >= i64::MAX as u64 {
+            return Err(invalid_tx(format!("nonce overflow: {}", self.nonce)));
+        }
+        if self.value > U256::from(u64::MAX) {
+            return Err(invalid_tx(format!("value overflow: {}", self.value)));
+        }
+        if self.gas_price > U256::from(u16::MAX) {
+            return Err(invalid_tx(format!("gas price too high: {}", self.gas_price)));
+        }
+        let max_fee = std::cmp::min(self.gas_limit, U256::from(u32::MAX)).as_u32();
+
+        let body = MetaTxBody::CallContract(CallContractTx {
+            address,
+            value: self.value.as_u64(),
+            data: self.data.clone(),
+        });
+        let tx = MetaTx::new(
+            self.nonce as i64 + 1,
+            TxCaller::Id(caller),
+            0,
+            self.gas_price.as_u32() as u16,
+            max_fee,
+            None,
+            body,
+            raw.to_vec(),
+        )
+        .no_create_time()
+        .build();
+
+        Ok(tx)
+    }
+
+    // 校验body里带以太坊交易的MetaTx，public_key为caller账户的公钥
+    pub fn verify_meta_tx(tx: &MetaTx, public_key: &PublicKey) -> BuckyResult<bool> {
+        let raw = match tx.body() {
+            Some(body) => &body.content().data,
+            None => return Ok(false),
+        };
+
+        let eth_tx = Self::decode(raw)?;
+        if eth_tx.recover_public_key()? != *public_key {
+            log::warn!(
+                "eth raw tx signer unmatch caller's public key: tx={}",
+                tx.desc().calculate_id()
+            );
+            return Ok(false);
+        }
+
+        let content = tx.desc().content();
+        let caller = match &content.caller {
+            TxCaller::Id(id) => id.clone(),
+            _ => return Ok(false),
+        };
+        let address = match content.body.get_obj().as_slice() {
+            [MetaTxBody::CallContract(call)] => call.address.clone(),
+            _ => return Ok(false),
+        };
+
+        let expect = eth_tx.to_meta_tx(raw, caller, address)?;
+        Ok(expect.desc().calculate_id() == tx.desc().calculate_id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign_legacy(secret: &secp256k1::SecretKey, nonce: u64, to: &H160, data: &[u8]) -> Vec<u8> {
+        let fields = |stream: &mut RlpStream| {
+            stream.append(&nonce);
+            stream.append(&10u64);
+            stream.append(&100000u64);
+            stream.append(&to.as_bytes().to_vec());
+            stream.append(&1u64);
+            stream.append(&data.to_vec());
+        };
+
+        let mut stream = RlpStream::new_list(9);
+        fields(&mut stream);
+        stream.append(&META_ETH_CHAIN_ID);
+        stream.append(&0u8);
+        stream.append(&0u8);
+        let hash = keccak(&stream.out());
+
+        let (signature, recovery_id) = secp256k1::sign(&secp256k1::Message::parse(&hash), secret);
+        let signature = signature.serialize();
+
+        let mut stream = RlpStream::new_list(9);
+        fields(&mut stream);
+        stream.append(&(META_ETH_CHAIN_ID * 2 + 35 + recovery_id.serialize() as u64));
+        for part in [&signature[..32], &signature[32..]].iter() {
+            let start = part.iter().position(|v| *v != 0).unwrap_or(part.len());
+            stream.append(&part[start..].to_vec());
+        }
+        stream.out().to_vec()
+    }
+
+    #[test]
+    fn test_eth_raw_tx() {
+        let private_key = PrivateKey::generate_secp256k1().unwrap();
+        let secret = match &private_key {
+            PrivateKey::Secp256k1(secret) => secret.clone(),
+            _ => unreachable!(),
+        };
+        let public_key = private_key.public();
+
+        let contract = ObjectId::default();
+        let to = H160::from_slice(&contract.as_slice()[12..]);
+        let raw = sign_legacy(&secret, 3, &to, &[1, 2, 3]);
+
+        let eth_tx = EthRawTx::decode(&raw).unwrap();
+        assert_eq!(eth_tx.chain_id, META_ETH_CHAIN_ID);
+        assert_eq!(eth_tx.nonce, 3);
+        assert_eq!(eth_tx.to, Some(to));
+        assert_eq!(eth_tx.recover_public_key().unwrap(), public_key);
+        assert_eq!(
+            eth_tx.sender().unwrap(),
+            eth_address_of_public_key(&public_key).unwrap()
+        );
+
+        let caller = ObjectId::from([1u8; 32]);
+        let tx = eth_tx.to_meta_tx(&raw, caller.clone(), contract.clone()).unwrap();
+        assert_eq!(tx.desc().content().nonce, 4);
+        assert!(EthRawTx::verify_meta_tx(&tx, &public_key).unwrap());
+
+        // 其它账户的公钥不能通过
+        let other = PrivateKey::generate_secp256k1().unwrap().public();
+        assert!(!EthRawTx::verify_meta_tx(&tx, &other).unwrap());
+
+        // 篡改desc字段后不能通过
+        let forged = MetaTx::new(
+            tx.desc().content().nonce,
+            TxCaller::Id(caller),
+            0,
+            tx.desc().content().gas_price,
+            tx.desc().content().max_fee + 1,
+            None,
+            tx.desc().content().body.get_obj()[0].clone(),
+            raw.clone(),
+        )
+        .no_create_time()
+        .build();
+        assert!(!EthRawTx::verify_meta_tx(&forged, &public_key).unwrap());
+
+        // to和合约地址不一致
+        let other_contract = ObjectId::from([2u8; 32]);
+        assert!(eth_tx.to_meta_tx(&raw, ObjectId::default(), other_contract).is_err());
+    }
+}
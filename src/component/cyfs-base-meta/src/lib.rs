@@ -4,6 +4,7 @@ pub use block::*;
 pub use code::*;
 pub use config::*;
 pub use contract::*;
+pub use eth_tx::*;
 pub use event::*;
 pub use extension::*;
 pub use group::*;
@@ -18,6 +19,7 @@ mod block;
 mod code;
 mod config;
 mod contract;
+mod eth_tx;
 mod event;
 pub mod evm_def;
 mod extension;
@@ -121,3 +121,37 @@ pub struct ERC20ContractTxResponse {
     pub create_time: u64,
     pub result: i32,
 }
+
+// 合约日志的过滤条件，topics按位置匹配，None表示该位置不限制
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetContractLogsRequest {
+    pub address: Option<String>,
+    pub topics: Vec<Option<String>>,
+    pub from_block: Option<i64>,
+    pub to_block: Option<i64>,
+    // 只返回id大于after_id的日志，用于增量拉取
+    pub after_id: Option<i64>,
+    pub offset: i64,
+    pub length: i64,
+}
+
+// 长轮询订阅新日志，没有匹配的日志时最多等待timeout_secs秒
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WaitContractLogsRequest {
+    pub filter: GetContractLogsRequest,
+    pub timeout_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SPVContractLog {
+    // spv本地的自增序号，可以作为after_id继续拉取
+    pub id: i64,
+    pub address: String,
+    pub topics: Vec<String>,
+    pub data: String,
+    pub tx_hash: String,
+    pub height: i64,
+    pub tx_index: i64,
+    pub log_index: i64,
+    pub create_time: u64,
+}
@@ -583,6 +583,22 @@ impl Receipt {
     }
 }
 
+// The result of executing a tx on the tip state without committing it
+#[derive(Clone, Debug, RawEncode, RawDecode)]
+pub struct SimulateTxResult {
+    // receipt.fee_used is the fee units FeeCounter would charge, evm return data and logs are also in it
+    pub receipt: Receipt,
+
+    // the fee units actually consumed by the tx bodies
+    pub fee_consumed: u32,
+
+    // the coins will be deducted from caller's balance, fee_used * gas_price
+    pub fee: i64,
+
+    // the tip block number which the tx is executed on
+    pub block_number: i64,
+}
+
 #[derive(Clone, Debug, RawEncode, RawDecode)]
 pub struct SetConfigTx {
     pub key: String,
@@ -838,14 +854,12 @@ impl MetaTxTrait for NamedObjectBase<MetaTxType> {
             return Ok(true);
         }
         let desc_signs = self.signs().desc_signs();
-        if desc_signs.is_none() {
-            return Ok(false);
+        if desc_signs.is_none() || desc_signs.as_ref().unwrap().len() == 0 {
+            // 没有desc签名的交易只能是由以太坊签名交易映射而来
+            return Ok(EthRawTx::verify_meta_tx(self, &public_key).unwrap_or(false));
         }
 
         let signs = desc_signs.as_ref().unwrap();
-        if signs.len() == 0 {
-            return Ok(false);
-        }
 
         let sign = signs.get(0).unwrap();
         let verifier = RsaCPUObjectVerifier::new(public_key);
@@ -861,14 +875,12 @@ impl MetaTxTrait for NamedObjectBase<MetaTxType> {
             return Ok(true);
         }
         let desc_signs = self.signs().desc_signs();
-        if desc_signs.is_none() {
-            return Ok(false);
+        if desc_signs.is_none() || desc_signs.as_ref().unwrap().len() == 0 {
+            // 没有desc签名的交易只能是由以太坊签名交易映射而来
+            return Ok(EthRawTx::verify_meta_tx(self, &public_key).unwrap_or(false));
         }
 
         let signs = desc_signs.as_ref().unwrap();
-        if signs.len() == 0 {
-            return Ok(false);
-        }
 
         let sign = signs.get(0).unwrap();
         let verifier = RsaCPUObjectVerifier::new(public_key);
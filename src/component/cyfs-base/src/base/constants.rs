@@ -5,6 +5,7 @@ pub const ACC_SERVICE_NAME: &str = "acc-service";
 pub const APP_MANAGER_NAME: &str = "app-manager";
 pub const CHUNK_MANAGER_NAME: &str = "chunk-manager";
 pub const FILE_MANAGER_NAME: &str = "file-manager";
+pub const S3_GATEWAY_NAME: &str = "s3-gateway";
 pub const REBUILD_MANAGER_NAME: &str = "rebuild-manager";
 pub const CYFS_RUNTIME_NAME: &str = "cyfs-runtime";
 pub const CYFS_MONITOR_DAEMON: &str = "cyfs-monitor-daemon";
@@ -13,6 +13,10 @@ pub const CYFS_FRIENDS_OPTION_PATH: &str = "/user/friends/option";
 pub const CYFS_APP_LOCAL_LIST_PATH: &str = "/app/manager/local_list";
 pub const CYFS_APP_LOCAL_STATUS_PATH: &str = "/app/${DecAppId}/local_status";
 
+// Erasure coded files and their fragments index, in system dec's global state
+pub const CYFS_ERASURE_FILES_PATH: &str = "/.cyfs/erasure/files";
+pub const CYFS_ERASURE_INDEX_PATH: &str = "/.cyfs/erasure/index";
+
 // Known zones in local-cache
 pub const CYFS_KNOWN_ZONES_PATH: &str = "/data/known-zones";
 
@@ -36,6 +36,12 @@ pub const OOD_DAEMON_LOCAL_STATUS_PORT: u16 = 1330;
 // cyfs-backup tool http service port
 pub const OOD_BACKUP_TOOL_SERVICE_PORT: u16 = 1331;
 
+// s3-gateway's s3 compatible http service port
+pub const S3_GATEWAY_PORT: u16 = 1332;
+
+// cyfs-runtime's webdav service port, only listen on loopback
+pub const CYFS_RUNTIME_WEBDAV_PORT: u16 = 1333;
+
 // bdt协议栈的默认绑定端口
 pub const OOD_BDT_STACK_PORT: u16 = 8050;
 pub const CYFS_RUNTIME_BDT_STACK_PORT: u16 = 8051;
@@ -845,7 +845,15 @@ impl ObjectFormat for DiffDescContent {
         map.into()
     }
 }
-object_format_empty_impl!(DiffBodyContent);
+impl ObjectFormat for DiffBodyContent {
+    fn format_json(&self) -> serde_json::Value {
+        let mut map = serde_json::Map::new();
+
+        JsonCodecHelper::encode_option_string_field(&mut map, "base_file", self.base_file().as_ref());
+
+        map.into()
+    }
+}
 
 // ProofOfService
 object_format_not_impl!(ProofOfServiceDescContent<ProofData>);
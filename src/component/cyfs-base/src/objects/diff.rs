@@ -92,13 +92,36 @@ impl DescContent for DiffDescContent {
     type PublicKeyType = SubDescNone;
 }
 
-#[derive(Clone, Debug, RawEncode, RawDecode)]
+#[derive(Clone, Debug, RawEncode)]
 pub struct DiffBodyContent {
-    // ignore
+    // 差异所基于的旧版本文件，持有旧版本的一方只需要获取diff_list里面的chunk
+    base_file: Option<FileId>,
 }
 
 impl BodyContent for DiffBodyContent {}
 
+// 旧版本的body为空，需要兼容解码为没有base_file
+impl<'de> RawDecode<'de> for DiffBodyContent {
+    fn raw_decode(buf: &'de [u8]) -> BuckyResult<(Self, &'de [u8])> {
+        if buf.len() == 0 {
+            return Ok((Self { base_file: None }, buf));
+        }
+
+        let (base_file, buf) = Option::<FileId>::raw_decode(buf).map_err(|e| {
+            log::error!("DiffBodyContent::raw_decode/base_file error:{}", e);
+            e
+        })?;
+
+        Ok((Self { base_file }, buf))
+    }
+}
+
+impl DiffBodyContent {
+    pub fn base_file(&self) -> &Option<FileId> {
+        &self.base_file
+    }
+}
+
 pub type DiffType = NamedObjType<DiffDescContent, DiffBodyContent>;
 pub type DiffBuilder = NamedObjectBuilder<DiffDescContent, DiffBodyContent>;
 
@@ -115,14 +138,23 @@ impl DiffDesc {
 impl Diff {
     pub fn new(file_id: FileId, diff_list: Vec<ChunkId>) -> DiffBuilder {
         let desc_content = DiffDescContent::new(file_id, diff_list);
-        let body_content = DiffBodyContent {};
+        let body_content = DiffBodyContent { base_file: None };
+        DiffBuilder::new(desc_content, body_content)
+    }
+
+    // file_id相对base_file的差异，diff_list为base_file里面不存在的chunk
+    pub fn new_with_base(base_file: FileId, file_id: FileId, diff_list: Vec<ChunkId>) -> DiffBuilder {
+        let desc_content = DiffDescContent::new(file_id, diff_list);
+        let body_content = DiffBodyContent {
+            base_file: Some(base_file),
+        };
         DiffBuilder::new(desc_content, body_content)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{ChunkId, Diff, FileId, RawConvertTo, RawFrom};
+    use crate::{ChunkId, Diff, DiffBodyContent, FileId, NamedObject, RawConvertTo, RawDecode, RawFrom};
 
     #[test]
     fn diff() {
@@ -130,5 +162,17 @@ mod test {
 
         let buf = action.to_vec().unwrap();
         let _obj = Diff::clone_from_slice(&buf).unwrap();
+
+        let diff = Diff::new_with_base(FileId::default(), FileId::default(), vec![]).build();
+        let buf = diff.to_vec().unwrap();
+        let obj = Diff::clone_from_slice(&buf).unwrap();
+        assert_eq!(
+            obj.body().as_ref().unwrap().content().base_file(),
+            &Some(FileId::default())
+        );
+
+        // 旧版本的空body
+        let (body, _) = DiffBodyContent::raw_decode(&[]).unwrap();
+        assert!(body.base_file().is_none());
     }
 }
@@ -58,4 +58,7 @@ pub struct BdtStackParams {
 
     // sn ping interval in seconds, default is 25s
     pub ping_interval: Option<u32>,
+
+    // compress chunks in local storage and pieces on ndn channel with zstd, default is false
+    pub chunk_compress: Option<bool>,
 }
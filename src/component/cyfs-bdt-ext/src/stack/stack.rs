@@ -5,7 +5,7 @@ use crate::sn::BdtStackSNHelper;
 
 use cyfs_base::*;
 use cyfs_bdt::*;
-use cyfs_chunk_cache::{ChunkManager, ChunkManagerRef};
+use cyfs_chunk_cache::{ChunkCompressConfig, ChunkManager, ChunkManagerRef};
 use cyfs_lib::*;
 use std::sync::Arc;
 
@@ -16,12 +16,13 @@ impl BdtStackHelper {
         isolate: &str,
         noc: NamedObjectCacheRef,
         device_manager: Box<dyn DeviceCache>,
+        chunk_compress: bool,
     ) -> BuckyResult<NamedDataComponents> {
         // 初始化data cache和tracker
         let ndc = Self::init_ndc(isolate)?;
         let tracker = Self::init_tracker(isolate)?;
 
-        let chunk_manager = Self::init_chunk_manager(isolate).await?;
+        let chunk_manager = Self::init_chunk_manager(isolate, chunk_compress).await?;
 
         let context_manager = ContextManager::new(noc.clone(), device_manager);
 
@@ -43,9 +44,14 @@ impl BdtStackHelper {
         TrackerCacheManager::create_tracker_cache(isolate)
     }
 
-    async fn init_chunk_manager(isolate: &str) -> BuckyResult<ChunkManagerRef> {
+    async fn init_chunk_manager(isolate: &str, compress: bool) -> BuckyResult<ChunkManagerRef> {
         let chunk_manager = Arc::new(ChunkManager::new());
-        match chunk_manager.init(isolate).await {
+        let compress = if compress {
+            Some(ChunkCompressConfig::default())
+        } else {
+            None
+        };
+        match chunk_manager.init_with_compress(isolate, compress).await {
             Ok(()) => {
                 info!("init chunk manager success!");
                 Ok(chunk_manager)
@@ -80,6 +86,10 @@ impl BdtStackHelper {
             bdt_params.config.sn_client.ping.interval = std::time::Duration::from_secs(ping_interval as u64);
         }
 
+        if let Some(chunk_compress) = params.chunk_compress {
+            bdt_params.config.ndn.channel.piece_compress = chunk_compress;
+        }
+
         // select sn_list via the sn_mode config
         let wait_online;
         let sn_list = match params.sn_mode {
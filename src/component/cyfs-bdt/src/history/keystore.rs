@@ -1,5 +1,5 @@
 use cyfs_base::*;
-use std::collections::{HashMap, LinkedList};
+use std::collections::{HashMap, HashSet, LinkedList};
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
 use std::sync::{Arc};
 use std::cell::RefCell;
@@ -16,6 +16,8 @@ const MIX_HASH_LIVE_MINUTES: usize = 31;
 pub struct Keystore {
     local_encryptor: Arc<(PrivateKey, DeviceDesc, RsaCPUObjectSigner)>,
     key_manager: Arc<Mutex<KeyManager>>,
+    // 按owner分别保存的被吊销设备，本地协议栈拒绝和这些设备建立连接
+    revoked: Arc<Mutex<HashMap<ObjectId, HashSet<DeviceId>>>>,
 }
 
 unsafe impl Send for Keystore {}
@@ -64,6 +66,7 @@ impl Keystore {
         Keystore {
             local_encryptor: Arc::new((private_key, const_info, signer)),
             key_manager: Arc::new(Mutex::new(KeyManager::new(config))),
+            revoked: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -119,6 +122,30 @@ impl Keystore {
         let mut mgr = self.key_manager.lock().unwrap();
         mgr.reset_peer(device_id);
     }
+
+    pub fn is_revoked(&self, device_id: &DeviceId) -> bool {
+        self.revoked
+            .lock()
+            .unwrap()
+            .values()
+            .any(|list| list.contains(device_id))
+    }
+
+    // 用owner最新的吊销列表替换该owner之前的列表，新吊销的设备需要清除已经协商的key
+    pub fn update_revoked(&self, owner_id: &ObjectId, list: Vec<DeviceId>) {
+        let added: Vec<DeviceId> = {
+            let mut revoked = self.revoked.lock().unwrap();
+            let prev = revoked.entry(owner_id.clone()).or_insert_with(HashSet::new);
+            let added = list.iter().filter(|id| !prev.contains(id)).cloned().collect();
+            *prev = list.into_iter().collect();
+            added
+        };
+
+        for device_id in added {
+            warn!("keystore revoke peer {}, owner={}", device_id, owner_id);
+            self.reset_peer(&device_id);
+        }
+    }
 }
 
 struct KeyManager {
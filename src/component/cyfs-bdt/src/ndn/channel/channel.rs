@@ -1,7 +1,7 @@
 use log::*;
 use std::{
     convert::TryFrom, 
-    sync::{RwLock},
+    sync::{RwLock, atomic::{AtomicU64, Ordering}},
     collections::{BTreeMap, LinkedList}, 
     time::Duration, 
 };
@@ -39,7 +39,45 @@ pub struct Config {
     pub msl: Duration, 
     pub udp: udp::Config, 
     pub history_speed: HistorySpeedConfig, 
-    pub reserve_timeout: Duration
+    pub reserve_timeout: Duration, 
+    // 开启后下载时请求对端压缩piece负载，上传时响应对端的压缩请求
+    pub piece_compress: bool
+}
+
+
+// piece压缩传输的统计，只计入实际压缩过的piece
+#[derive(Default)]
+pub struct PieceCompressStat {
+    upload_raw: AtomicU64, 
+    upload_wire: AtomicU64, 
+    download_raw: AtomicU64, 
+    download_wire: AtomicU64, 
+}
+
+impl std::fmt::Display for PieceCompressStat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CompressUploadSaved:{}, CompressDownloadSaved:{}", self.upload_saved(), self.download_saved())
+    }
+}
+
+impl PieceCompressStat {
+    pub(super) fn on_upload(&self, raw: usize, wire: usize) {
+        self.upload_raw.fetch_add(raw as u64, Ordering::SeqCst);
+        self.upload_wire.fetch_add(wire as u64, Ordering::SeqCst);
+    }
+
+    pub(super) fn on_download(&self, raw: usize, wire: usize) {
+        self.download_raw.fetch_add(raw as u64, Ordering::SeqCst);
+        self.download_wire.fetch_add(wire as u64, Ordering::SeqCst);
+    }
+
+    pub fn upload_saved(&self) -> u64 {
+        self.upload_raw.load(Ordering::SeqCst).saturating_sub(self.upload_wire.load(Ordering::SeqCst))
+    }
+
+    pub fn download_saved(&self) -> u64 {
+        self.download_raw.load(Ordering::SeqCst).saturating_sub(self.download_wire.load(Ordering::SeqCst))
+    }
 }
 
 
@@ -154,6 +192,8 @@ impl DownloadState {
 
 struct UploadState {
     canceled: BTreeMap<TempSeq, (UploadSession, Timestamp)>, 
+    // 请求了压缩传输、还没有创建上传会话的interest
+    compress_interests: BTreeMap<TempSeq, Timestamp>, 
     cur_speed: u32, 
     history_speed: HistorySpeed, 
 }
@@ -163,6 +203,7 @@ impl UploadState {
     fn new(history_speed: HistorySpeed) -> Self {
         Self {
             canceled: BTreeMap::new(), 
+            compress_interests: BTreeMap::new(), 
             cur_speed: 0, 
             history_speed
         }
@@ -187,6 +228,8 @@ impl UploadState {
         for id in to_remove {
             self.canceled.remove(&id);
         }
+
+        self.compress_interests.retain(|_, when| now <= *when || (now - *when) <= 2 * msl.as_micros() as u64);
     }
 }
 
@@ -198,6 +241,7 @@ struct ChannelImpl {
     command_seq: TempSeqGenerator,  
     download_seq: TempSeqGenerator, 
     state: RwLock<StateImpl>, 
+    compress_stat: Arc<PieceCompressStat>, 
 }
 
 #[derive(Clone)]
@@ -222,7 +266,8 @@ impl Channel {
     pub fn new(
         weak_stack: WeakStack, 
         tunnel: TunnelGuard, 
-        command_tunnel: DatagramTunnelGuard
+        command_tunnel: DatagramTunnelGuard, 
+        compress_stat: Arc<PieceCompressStat>
     ) -> Self {
         let stack = Stack::from(&weak_stack);
         let config = stack.config().ndn.channel.clone();
@@ -238,6 +283,7 @@ impl Channel {
                 tunnels: vec![]
             }), 
             config, 
+            compress_stat, 
         }))
     }
 
@@ -257,6 +303,10 @@ impl Channel {
         &self.0.config
     }
 
+    pub(super) fn compress_stat(&self) -> &PieceCompressStat {
+        &self.0.compress_stat
+    }
+
     fn default_tunnel(&self) -> BuckyResult<DynamicChannelTunnel> {
         self.tunnel_of(self.0.tunnel.default_tunnel()?)
     }
@@ -281,6 +331,8 @@ impl Channel {
     ) -> BuckyResult<UploadSession> {
         let tunnel = self.default_tunnel()?;
         let session = UploadSession::new(chunk, session_id, piece_type, tunnel.upload_state(encoder), self.clone());
+        let compress = self.0.state.write().unwrap().upload.compress_interests.remove(session.session_id()).is_some();
+        session.set_compress(compress);
         tunnel.uploaders().add(session.clone());
 
         {
@@ -480,6 +532,9 @@ impl Channel {
             info!("{} ignore {:?} for upload session exists", self, command);
             session.on_interest(self, command)
         } else {
+            if self.config().piece_compress && command.compress == Some(PieceCompressType::Zstd) {
+                self.0.state.write().unwrap().upload.compress_interests.insert(command.session_id.clone(), bucky_time_now());
+            }
             let stack = self.stack();
             stack.ndn().event_handler().on_newly_interest(&self.stack(), command, self).await
         }
@@ -503,6 +558,9 @@ impl Channel {
         match cmd_code {
             PackageCmdCode::PieceData => {
                 let piece = PieceData::decode_from_raw_data(buf)?;
+                if let Some(compressed_len) = piece.compressed_len {
+                    self.compress_stat().on_download(piece.data.len(), compressed_len);
+                }
                 let _ = tunnel.on_piece_data(&piece)?;
                 self.on_piece_data(piece, &tunnel)
             }, 
@@ -184,6 +184,7 @@ impl DownloadSession {
                 referer: self.referer().clone(), 
                 group_path: self.group_path().clone(), 
                 from: None, 
+                compress: Self::compress_type(&channel), 
             };
             info!("{} sent {:?}", self, interest);
             channel.interest(interest);
@@ -398,6 +399,14 @@ impl DownloadSession {
         Ok(())
     }
 
+    fn compress_type(channel: &Channel) -> Option<PieceCompressType> {
+        if channel.config().piece_compress {
+            Some(PieceCompressType::Zstd)
+        } else {
+            None
+        }
+    }
+
     fn resend_interest(&self, channel: &Channel) -> BuckyResult<()> {
         let interest = Interest {
             session_id: self.session_id().clone(), 
@@ -405,7 +414,8 @@ impl DownloadSession {
             prefer_type: self.source().codec_desc.clone(), 
             referer: self.referer().clone(), 
             from: None, 
-            group_path: None
+            group_path: None, 
+            compress: Self::compress_type(channel), 
         };
         info!("{} sent {:?}", self, interest);
         channel.interest(interest);
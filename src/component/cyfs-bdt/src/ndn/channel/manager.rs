@@ -17,7 +17,7 @@ use super::super::{
     types::*
 };
 use super::{
-    channel::{Channel},
+    channel::{Channel, PieceCompressStat},
 };
 
 struct ChannelGuard {
@@ -60,7 +60,8 @@ struct Channels {
 struct ManagerImpl {
     stack: WeakStack, 
     command_tunnel: DatagramTunnelGuard, 
-    channels: RwLock<Channels>
+    channels: RwLock<Channels>, 
+    compress_stat: Arc<PieceCompressStat>
 }
 
 #[derive(Clone)]
@@ -87,6 +88,7 @@ impl ChannelManager {
                 upload_cur_speed: 0, 
                 entries: BTreeMap::new()
             }), 
+            compress_stat: Arc::new(PieceCompressStat::default()), 
         }));
         
         {
@@ -111,7 +113,11 @@ impl ChannelManager {
             upload_session_count += guard.channel.upload_session_count();
         }
 
-        format!("ChannelCount: {}, UploadSessionCount:{}, DownloadSessionCount:{}", channel_count, upload_session_count, download_session_count)
+        format!("ChannelCount: {}, UploadSessionCount:{}, DownloadSessionCount:{}, {}", channel_count, upload_session_count, download_session_count, self.0.compress_stat)
+    }
+
+    pub fn compress_stat(&self) -> &PieceCompressStat {
+        &self.0.compress_stat
     }
 
     pub fn channel_of(&self, remote: &DeviceId) -> Option<Channel> {
@@ -131,7 +137,8 @@ impl ChannelManager {
             let channel = Channel::new(
                 self.0.stack.clone(), 
                 tunnel, 
-                self.0.command_tunnel.clone());
+                self.0.command_tunnel.clone(), 
+                self.0.compress_stat.clone());
             channels.entries.insert(remote, ChannelGuard { reserving: None, channel: channel.clone() });
 
             channel
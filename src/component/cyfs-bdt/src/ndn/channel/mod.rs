@@ -8,5 +8,5 @@ mod manager;
 
 pub use download::*;
 pub use upload::*;
-pub use channel::{Channel, ChannelState, Config};
+pub use channel::{Channel, ChannelState, Config, PieceCompressStat};
 pub use manager::ChannelManager;
\ No newline at end of file
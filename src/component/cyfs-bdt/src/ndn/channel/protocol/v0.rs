@@ -173,6 +173,47 @@ impl FlagsCounter {
 }
 
 
+// piece负载的压缩方式；下载方在Interest中声明，不支持的旧版本会忽略该字段，按原始数据传输
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PieceCompressType {
+    Zstd = 1,
+}
+
+impl TryFrom<u8> for PieceCompressType {
+    type Error = BuckyError;
+    fn try_from(v: u8) -> std::result::Result<Self, Self::Error> {
+        match v {
+            1u8 => Ok(Self::Zstd),
+            _ => Err(BuckyError::new(
+                BuckyErrorCode::InvalidParam,
+                format!("invalid piece compress type {}", v),
+            )),
+        }
+    }
+}
+
+impl RawEncode for PieceCompressType {
+    fn raw_measure(&self, _purpose: &Option<RawEncodePurpose>) -> BuckyResult<usize> {
+        Ok(u8::raw_bytes().unwrap())
+    }
+
+    fn raw_encode<'a>(
+        &self,
+        buf: &'a mut [u8],
+        purpose: &Option<RawEncodePurpose>,
+    ) -> BuckyResult<&'a mut [u8]> {
+        (*self as u8).raw_encode(buf, purpose)
+    }
+}
+
+impl<'de> RawDecode<'de> for PieceCompressType {
+    fn raw_decode(buf: &'de [u8]) -> BuckyResult<(Self, &'de [u8])> {
+        let (code, buf) = u8::raw_decode(buf)?;
+        Ok((Self::try_from(code)?, buf))
+    }
+}
+
+
 #[derive(Debug, Clone)]
 pub struct Interest {
     pub session_id: TempSeq, 
@@ -180,7 +221,8 @@ pub struct Interest {
     pub prefer_type: ChunkCodecDesc, 
     pub referer: Option<String>,
     pub from: Option<DeviceId>, 
-    pub group_path: Option<String>
+    pub group_path: Option<String>, 
+    pub compress: Option<PieceCompressType>
     // pub link_url: Option<String>,
     // flow_id:Option<u32>,
     // priority: Option<u8>,
@@ -210,7 +252,8 @@ impl RawEncodeWithContext<DatagramOptions> for Interest {
         let buf = context.encode(buf, &self.prefer_type)?;
         let buf = context.option_encode(buf, &self.referer, flags.next())?;
         let buf = context.option_encode(buf, &self.from, flags.next())?;
-        let _ = context.option_encode(buf, &self.group_path, flags.next())?;
+        let buf = context.option_encode(buf, &self.group_path, flags.next())?;
+        let _ = context.option_encode(buf, &self.compress, flags.next())?;
         context.finish(enc_buf)
     }
 }
@@ -229,6 +272,7 @@ impl<'de> RawDecodeWithContext<'de, &DatagramOptions> for Interest {
         let (referer, buf) = context.option_decode(buf, flags.next())?;
         let (from, buf) = context.option_decode(buf, flags.next())?;
         let (group_path, buf) = context.option_decode(buf, flags.next())?;
+        let (compress, buf) = context.option_decode(buf, flags.next())?;
         Ok((
             Self {
                 session_id, 
@@ -236,7 +280,8 @@ impl<'de> RawDecodeWithContext<'de, &DatagramOptions> for Interest {
                 prefer_type, 
                 referer,
                 from, 
-                group_path
+                group_path, 
+                compress
             },
             buf,
         ))
@@ -252,6 +297,7 @@ impl JsonCodec<Interest> for Interest {
         JsonCodecHelper::encode_option_string_field(&mut obj, "referer", self.referer.as_ref());
         JsonCodecHelper::encode_option_string_field(&mut obj, "from", self.from.as_ref());
         JsonCodecHelper::encode_option_string_field(&mut obj, "group_path", self.group_path.as_ref());
+        JsonCodecHelper::encode_option_number_field(&mut obj, "compress", self.compress.map(|c| c as u8));
         obj
     }
 
@@ -264,6 +310,8 @@ impl JsonCodec<Interest> for Interest {
             referer: JsonCodecHelper::decode_option_string_field(obj, "referer")?, 
             from: JsonCodecHelper::decode_option_string_field(obj, "from")?, 
             group_path: JsonCodecHelper::decode_option_string_field(obj, "group_path")?, 
+            compress: JsonCodecHelper::decode_option_int_field::<u8>(obj, "compress")?
+                .map(PieceCompressType::try_from).transpose()?, 
         })
     }
 }
@@ -277,7 +325,8 @@ fn encode_protocol_ineterest() {
         prefer_type: ChunkCodecDesc::Stream(None, None, None), 
         referer: Some("referer".to_owned()), 
         from: None, 
-        group_path: None
+        group_path: None, 
+        compress: Some(PieceCompressType::Zstd)
     };
 
     let mut buf = [0u8; 1500]; 
@@ -289,6 +338,7 @@ fn encode_protocol_ineterest() {
     let (dst, _) = Interest::raw_decode_with_context(dec, &mut options).unwrap();
     assert_eq!(src.chunk, dst.chunk);
     assert_eq!(src.referer, dst.referer);
+    assert_eq!(src.compress, dst.compress);
 }
 
 
@@ -398,8 +448,13 @@ pub struct PieceData {
     pub chunk: ChunkId, 
     pub desc: PieceDesc, 
     pub data: Vec<u8>,
+    // 负载压缩传输时线路上的压缩后长度，data始终为解压后的原始数据
+    pub compressed_len: Option<usize>,
 }
 
+// PieceDesc类型码的最高位标记负载经过zstd压缩
+const PIECE_DESC_COMPRESSED_FLAG: u8 = 0x80;
+
 impl Package for PieceData {
     fn version(&self) -> u8 {
         0
@@ -434,6 +489,28 @@ impl PieceData {
         desc.raw_encode(buf, &None)
     }
 
+    fn desc_code_offset() -> usize {
+        u8::raw_bytes().unwrap()
+            + TempSeq::raw_bytes().unwrap()
+            + TempSeq::raw_bytes().unwrap()
+            + ChunkId::raw_bytes().unwrap()
+    }
+
+    // 压缩已经编码好的piece的负载，返回压缩后的总长度；压缩没有收益时返回None，buf保持不变
+    pub fn compress_payload(buf: &mut [u8], len: usize) -> Option<usize> {
+        let header_len = u8::raw_bytes().unwrap() + Self::max_header_len();
+        if len <= header_len {
+            return None;
+        }
+        let data = zstd::bulk::compress(&buf[header_len..len], 1).ok()?;
+        if data.len() >= len - header_len {
+            return None;
+        }
+        buf[header_len..header_len + data.len()].copy_from_slice(&data);
+        buf[Self::desc_code_offset()] |= PIECE_DESC_COMPRESSED_FLAG;
+        Some(header_len + data.len())
+    }
+
     pub fn reset_estimate(buf: &mut [u8], est_seq: TempSeq) {
         let _ = est_seq.raw_encode(&mut buf[u8::raw_bytes().unwrap()..], &None).unwrap();
     }
@@ -451,14 +528,36 @@ impl PieceData {
         })?;
         let (session_id, buf) = TempSeq::raw_decode(buf)?;
         let (chunk, buf) = ChunkId::raw_decode(buf)?;
-        let (desc, data) = PieceDesc::raw_decode(buf)?;
+        let compressed = buf.len() > 0 && (buf[0] & PIECE_DESC_COMPRESSED_FLAG) != 0;
+        let (desc, data) = if compressed {
+            let desc_len = PieceDesc::raw_bytes().unwrap();
+            if buf.len() < desc_len {
+                return Err(BuckyError::new(BuckyErrorCode::InvalidData, "piece data too short"));
+            }
+            let mut desc_buf = Vec::from(&buf[..desc_len]);
+            desc_buf[0] &= !PIECE_DESC_COMPRESSED_FLAG;
+            let (desc, _) = PieceDesc::raw_decode(&desc_buf)?;
+            (desc, &buf[desc_len..])
+        } else {
+            PieceDesc::raw_decode(buf)?
+        };
+
+        let (data, compressed_len) = if compressed {
+            let raw = zstd::bulk::decompress(data, Self::max_payload()).map_err(|e| {
+                BuckyError::new(BuckyErrorCode::InvalidData, format!("decompress piece data failed, {}", e))
+            })?;
+            (raw, Some(data.len()))
+        } else {
+            //FIXME: 这里有机会减少一次拷贝
+            (Vec::from(data), None)
+        };
         Ok(Self {
             est_seq,
             session_id, 
             chunk,
             desc,  
-            //FIXME: 这里有机会减少一次拷贝
-            data: Vec::from(data),
+            data,
+            compressed_len,
         })
     }
 }
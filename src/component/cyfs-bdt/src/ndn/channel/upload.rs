@@ -1,7 +1,7 @@
 use log::*;
 use std::{
     ops::Range, 
-    sync::{RwLock}
+    sync::{RwLock, atomic::{AtomicBool, AtomicU32, Ordering}}
 };
 use async_std::{
     sync::Arc, 
@@ -46,8 +46,13 @@ struct SessionImpl {
     session_id: TempSeq, 
     piece_type: ChunkCodecDesc, 
     state: RwLock<StateImpl>, 
+    compress: AtomicBool, 
+    compress_miss: AtomicU32, 
 }
 
+// 连续压缩无收益的piece数超过该值后，该会话不再尝试压缩
+const MAX_COMPRESS_MISS: u32 = 16;
+
 #[derive(Clone)]
 pub struct UploadSession(Arc<SessionImpl>);
 
@@ -81,6 +86,8 @@ impl UploadSession {
                 }),
                 control_state: NdnTaskControlState::Normal
             }), 
+            compress: AtomicBool::new(false), 
+            compress_miss: AtomicU32::new(0), 
         }))
     }
 
@@ -101,6 +108,25 @@ impl UploadSession {
     }
 
 
+    pub(super) fn set_compress(&self, compress: bool) {
+        self.0.compress.store(compress, Ordering::SeqCst);
+        self.0.compress_miss.store(0, Ordering::SeqCst);
+    }
+
+    fn compress_piece(&self, buf: &mut [u8], len: usize) -> Option<usize> {
+        if !self.0.compress.load(Ordering::SeqCst) {
+            return None;
+        }
+        let compressed = PieceData::compress_payload(buf, len);
+        if compressed.is_some() {
+            self.0.compress_miss.store(0, Ordering::SeqCst);
+        } else if self.0.compress_miss.fetch_add(1, Ordering::SeqCst) + 1 >= MAX_COMPRESS_MISS {
+            debug!("{} stop compressing pieces for no gain", self);
+            self.0.compress.store(false, Ordering::SeqCst);
+        }
+        compressed
+    }
+
     pub(super) fn next_piece(&self, buf: &mut [u8]) -> BuckyResult<usize> {
         let encoder = {
             let state = self.0.state.read().unwrap();
@@ -114,6 +140,11 @@ impl UploadSession {
         if let Some(encoder) = encoder {
             match encoder.next_piece(self.session_id(), buf) {
                 Ok(len) => {
+                    let compressed = if len > 0 {
+                        self.compress_piece(buf, len)
+                    } else {
+                        None
+                    };
                     let mut state = self.0.state.write().unwrap();
                     match &mut state.task_state {
                         TaskStateImpl::Uploading(uploading) => {
@@ -121,7 +152,12 @@ impl UploadSession {
                                 uploading.speed_counter.on_recv(len);
                                 uploading.uploaded += len as u64;
                             }
-                            Ok(len)
+                            if let Some(compressed) = compressed {
+                                uploading.channel.compress_stat().on_upload(len, compressed);
+                                Ok(compressed)
+                            } else {
+                                Ok(len)
+                            }
                         },
                         _ => {
                             Err(BuckyError::new(BuckyErrorCode::ErrorState, "not uploading"))
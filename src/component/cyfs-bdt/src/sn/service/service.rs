@@ -161,6 +161,11 @@ impl SnService {
         &self.0.key_store
     }
 
+    // 更新owner吊销的设备列表，sn不再为这些设备提供ping和call服务
+    pub fn update_revoked(&self, owner_id: &ObjectId, list: Vec<DeviceId>) {
+        self.key_store().update_revoked(owner_id, list);
+    }
+
     fn resend_queue(&self) -> &ResendQueue {
         self.0.resend_queue.as_ref().unwrap()
     }
@@ -302,6 +307,14 @@ impl SnService {
         encryptor: Option<(&MixAesKey, &DeviceId)>,
         send_time: Timestamp,
     ) {
+        let from_peer_id = ping_req.from_peer_id.as_ref().or(encryptor.map(|(_, id)| id));
+        if let Some(from_peer_id) = from_peer_id {
+            if self.key_store().is_revoked(from_peer_id) {
+                warn!("[ping from {} seq({})] ignore for revoked.", from_peer_id, ping_req.seq.value());
+                return;
+            }
+        }
+
         if resp_sender.local().unwrap().is_ipv4() {
             self.handle_ipv4_ping(ping_req, resp_sender, encryptor, send_time);
         } else {
@@ -553,6 +566,11 @@ impl SnService {
             call_req.seq.value()
         );
         info!("{}.", log_key);
+        if self.key_store().is_revoked(from_peer_id) || self.key_store().is_revoked(&call_req.to_peer_id) {
+            warn!("{} ignore for revoked.", log_key);
+            return;
+        }
+
         // if let IsAcceptClient::Refuse = self.contract.verify_auth(&call_req.to_peer_id) {
         //     warn!("{} refused by contract.", log_key);
         //     send_responce(self,
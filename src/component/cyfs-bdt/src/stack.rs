@@ -177,7 +177,8 @@ impl StackConfig {
                         attenuation: 0.5, 
                         expire: Duration::from_secs(20),  
                         atomic: Duration::from_secs(1)
-                    }
+                    }, 
+                    piece_compress: false
                 }, 
                 chunk: ndn::chunk::Config{
                     raw_caches: RawCacheConfig {
@@ -97,8 +97,20 @@ impl TunnelManager {
         stack.config().tunnel.clone()
     }
 
+    fn check_revoked(&self, remote: &DeviceId) -> Result<(), BuckyError> {
+        let stack = Stack::from(&self.0.stack);
+        if stack.keystore().is_revoked(remote) {
+            let msg = format!("{} reject revoked remote {}", self, remote);
+            warn!("{}", msg);
+            Err(BuckyError::new(BuckyErrorCode::PermissionDenied, msg))
+        } else {
+            Ok(())
+        }
+    }
+
     pub(crate) fn create_container(&self, remote_const: &DeviceDesc) -> Result<TunnelGuard, BuckyError> {
         let remote = remote_const.device_id();
+        self.check_revoked(&remote)?;
         debug!("{} create new tunnel container of remote {}", self, remote);
         let mut entries = self.0.entries.write().unwrap();
         if let Some(tunnel) = entries.get(&remote) {
@@ -139,6 +151,7 @@ impl fmt::Display for TunnelManager {
 impl OnUdpPackageBox for TunnelManager {
     fn on_udp_package_box(&self, package_box: udp::UdpPackageBox) -> Result<(), BuckyError> {
         trace!("{} on_udp_package_box from remote {}", self, package_box.as_ref().remote());
+        self.check_revoked(package_box.as_ref().remote())?;
         if let Some(tunnel) = self.container_of(package_box.as_ref().remote()) {
             tunnel.on_udp_package_box(package_box)
         } else {
@@ -174,6 +187,7 @@ impl OnUdpRawData<(udp::Interface,DeviceId, MixAesKey, Endpoint)> for TunnelMana
 
 impl OnTcpInterface for TunnelManager {
     fn on_tcp_interface(&self, interface: tcp::AcceptInterface, first_box: PackageBox) -> Result<OnPackageResult, BuckyError> {
+        self.check_revoked(first_box.remote())?;
         //全部转给tunnel container
         if let Some(tunnel) = self.container_of(first_box.remote()) {
             tunnel.on_tcp_interface(interface, first_box)
@@ -197,6 +211,7 @@ impl OnTcpInterface for TunnelManager {
 impl PingClientCalledEvent<PackageBox> for TunnelManager {
     fn on_called(&self, called: &SnCalled, caller_box: PackageBox) -> Result<(), BuckyError> {
         debug!("{} on_called from remote {} sequence {:?}", self, called.peer_info.desc().device_id(), called.seq);
+        self.check_revoked(&called.peer_info.desc().device_id())?;
         let first_package = &caller_box.packages_no_exchange()[0];
         if first_package.cmd_code() != PackageCmdCode::SynTunnel {
             debug!("{} ignore udp package box from remote:{}, for first package is {:?}", self, called.peer_info.desc().device_id(), first_package.cmd_code());
@@ -16,4 +16,6 @@ pub trait ChunkCache: Send + Sync
     async fn put_chunk(&self, chunk_id: &ChunkId, chunk: Box<dyn Chunk>) -> BuckyResult<()>;
     async fn is_exist(&self, chunk_id: &ChunkId) -> bool;
     async fn get_chunk_meta(&self, chunk_id: &ChunkId, chunk_type: ChunkType) -> BuckyResult<ChunkMeta>;
+    // 当前占用的磁盘空间，用于配额检查
+    async fn used_size(&self) -> BuckyResult<u64>;
 }
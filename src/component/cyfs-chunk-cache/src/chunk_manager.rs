@@ -3,6 +3,7 @@ use std::sync::{Arc, Once, RwLock};
 use cyfs_chunk_lib::{Chunk, ChunkMeta, ChunkMut};
 use cyfs_base::*;
 use crate::{ChunkCache, LocalChunkCache, SingleDiskChunkCache, DiskScanner, ChunkType};
+use crate::compress::{ChunkCompressConfig, ChunkCompressStatInfo, ChunkCompressor, ChunkCompressorRef};
 
 static mut CHUNK_MANAGER_INSTANCE: Option<ChunkManager> = None;
 static CHUNK_MANAGER_INIT: Once = Once::new();
@@ -16,7 +17,8 @@ impl DiskScanner for CYFSDiskScanner {
 }
 
 pub struct ChunkManager {
-    chunk_cache: RwLock<Option<Arc<dyn ChunkCache>>>
+    chunk_cache: RwLock<Option<Arc<dyn ChunkCache>>>,
+    compressor: RwLock<Option<ChunkCompressorRef>>,
 }
 
 pub type ChunkManagerRef = Arc<ChunkManager>;
@@ -24,12 +26,20 @@ pub type ChunkManagerRef = Arc<ChunkManager>;
 impl ChunkManager {
     pub fn new() -> Self {
         Self {
-            chunk_cache: RwLock::new(None)
+            chunk_cache: RwLock::new(None),
+            compressor: RwLock::new(None),
         }
     }
 
     pub async fn init(&self, isolate: &str) -> BuckyResult<()> {
-        let chunk_cache: Arc<dyn ChunkCache> = Arc::new(LocalChunkCache::<SingleDiskChunkCache, CYFSDiskScanner>::new(isolate, CYFSDiskScanner).await?);
+        self.init_with_compress(isolate, None).await
+    }
+
+    // 指定压缩配置时，可压缩的chunk以zstd格式保存
+    pub async fn init_with_compress(&self, isolate: &str, compress: Option<ChunkCompressConfig>) -> BuckyResult<()> {
+        let compressor = compress.map(|config| Arc::new(ChunkCompressor::new(config)));
+        let chunk_cache: Arc<dyn ChunkCache> = Arc::new(LocalChunkCache::<SingleDiskChunkCache, CYFSDiskScanner>::new_with_compress(isolate, CYFSDiskScanner, compressor.clone()).await?);
+        *self.compressor.write().unwrap() = compressor;
         {
             let mut slot = self.chunk_cache.write().unwrap();
             assert!(slot.is_none());
@@ -39,6 +49,11 @@ impl ChunkManager {
         Ok(())
     }
 
+    // 未开启压缩时返回None
+    pub fn compress_stat(&self) -> Option<ChunkCompressStatInfo> {
+        self.compressor.read().unwrap().as_ref().map(|compressor| compressor.stat())
+    }
+
     pub async fn get_chunk(&self, chunk_id: &ChunkId, chunk_type: ChunkType) -> BuckyResult<Box<dyn Chunk>> {
         let chunk_cache = {
             let chunk_cache = self.chunk_cache.read().unwrap();
@@ -86,6 +101,14 @@ impl ChunkManager {
         };
         chunk_cache.get_chunk_meta(chunk_id, chunk_type).await
     }
+
+    pub async fn used_size(&self) -> BuckyResult<u64> {
+        let chunk_cache = {
+            let chunk_cache = self.chunk_cache.read().unwrap();
+            chunk_cache.as_ref().unwrap().clone()
+        };
+        chunk_cache.used_size().await
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,172 @@
+use cyfs_base::*;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/*
+chunk的透明压缩存储
+ChunkId始终基于原始数据计算；可压缩的chunk以zstd格式保存在同名的.zst文件中，读取时解压还原
+压缩收益不足的chunk仍然按原始数据保存，不影响mmap读取
+*/
+
+#[derive(Clone, Debug)]
+pub struct ChunkCompressConfig {
+    // zstd压缩级别
+    pub level: i32,
+    // 小于该长度的chunk不尝试压缩
+    pub min_len: usize,
+    // 压缩后至少要节省的百分比，否则按原始数据保存
+    pub min_saving_percent: u8,
+}
+
+impl Default for ChunkCompressConfig {
+    fn default() -> Self {
+        Self {
+            level: 3,
+            min_len: 4096,
+            min_saving_percent: 10,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ChunkCompressStatInfo {
+    // 压缩保存的chunk数
+    pub compressed_count: u64,
+    // 按原始数据保存的chunk数
+    pub raw_count: u64,
+    // 写入chunk的原始字节数
+    pub raw_bytes: u64,
+    // 实际落盘的字节数
+    pub stored_bytes: u64,
+}
+
+impl ChunkCompressStatInfo {
+    pub fn saved_bytes(&self) -> u64 {
+        self.raw_bytes.saturating_sub(self.stored_bytes)
+    }
+}
+
+pub(crate) struct ChunkCompressor {
+    config: ChunkCompressConfig,
+
+    compressed_count: AtomicU64,
+    raw_count: AtomicU64,
+    raw_bytes: AtomicU64,
+    stored_bytes: AtomicU64,
+}
+
+pub(crate) type ChunkCompressorRef = Arc<ChunkCompressor>;
+
+impl ChunkCompressor {
+    pub fn new(config: ChunkCompressConfig) -> Self {
+        Self {
+            config,
+            compressed_count: AtomicU64::new(0),
+            raw_count: AtomicU64::new(0),
+            raw_bytes: AtomicU64::new(0),
+            stored_bytes: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &ChunkCompressConfig {
+        &self.config
+    }
+
+    pub fn compressed_path(file_path: &Path) -> PathBuf {
+        let mut path = file_path.as_os_str().to_owned();
+        path.push(".zst");
+        PathBuf::from(path)
+    }
+
+    // 压缩收益满足配置时返回压缩后的数据，否则返回None，调用方按原始数据保存
+    pub fn compress(&self, chunk_id: &ChunkId, data: &[u8]) -> Option<Vec<u8>> {
+        if data.len() < self.config.min_len {
+            self.on_raw(data.len());
+            return None;
+        }
+
+        let buf = match zstd::bulk::compress(data, self.config.level) {
+            Ok(buf) => buf,
+            Err(e) => {
+                log::warn!("compress chunk failed! chunk={}, {}", chunk_id, e);
+                self.on_raw(data.len());
+                return None;
+            }
+        };
+
+        let limit = data.len() * (100 - self.config.min_saving_percent.min(100) as usize) / 100;
+        if buf.len() > limit {
+            self.on_raw(data.len());
+            return None;
+        }
+
+        self.compressed_count.fetch_add(1, Ordering::SeqCst);
+        self.raw_bytes.fetch_add(data.len() as u64, Ordering::SeqCst);
+        self.stored_bytes.fetch_add(buf.len() as u64, Ordering::SeqCst);
+
+        Some(buf)
+    }
+
+    // 解压不依赖配置，关闭压缩后已有的.zst文件仍然可以读取
+    pub fn decompress(chunk_id: &ChunkId, data: &[u8]) -> BuckyResult<Vec<u8>> {
+        let buf = zstd::bulk::decompress(data, chunk_id.len()).map_err(|e| {
+            let msg = format!("decompress chunk failed! chunk={}, {}", chunk_id, e);
+            log::error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidData, msg)
+        })?;
+
+        if buf.len() != chunk_id.len() {
+            let msg = format!(
+                "decompress chunk but got mismatched length! chunk={}, len={}, got={}",
+                chunk_id,
+                chunk_id.len(),
+                buf.len()
+            );
+            log::error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidData, msg));
+        }
+
+        Ok(buf)
+    }
+
+    pub fn stat(&self) -> ChunkCompressStatInfo {
+        ChunkCompressStatInfo {
+            compressed_count: self.compressed_count.load(Ordering::SeqCst),
+            raw_count: self.raw_count.load(Ordering::SeqCst),
+            raw_bytes: self.raw_bytes.load(Ordering::SeqCst),
+            stored_bytes: self.stored_bytes.load(Ordering::SeqCst),
+        }
+    }
+
+    fn on_raw(&self, len: usize) {
+        self.raw_count.fetch_add(1, Ordering::SeqCst);
+        self.raw_bytes.fetch_add(len as u64, Ordering::SeqCst);
+        self.stored_bytes.fetch_add(len as u64, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod test_compress {
+    use super::*;
+
+    #[test]
+    fn test_compress() {
+        let compressor = ChunkCompressor::new(ChunkCompressConfig::default());
+
+        let data = vec![7u8; 1024 * 64];
+        let chunk_id = ChunkId::calculate_sync(&data).unwrap();
+        let buf = compressor.compress(&chunk_id, &data).unwrap();
+        assert!(buf.len() < data.len());
+        assert_eq!(ChunkCompressor::decompress(&chunk_id, &buf).unwrap(), data);
+
+        let data: Vec<u8> = (0..1024 * 64).map(|_| rand::random::<u8>()).collect();
+        let chunk_id = ChunkId::calculate_sync(&data).unwrap();
+        assert!(compressor.compress(&chunk_id, &data).is_none());
+
+        let stat = compressor.stat();
+        assert_eq!(stat.compressed_count, 1);
+        assert_eq!(stat.raw_count, 1);
+        assert!(stat.saved_bytes() > 0);
+    }
+}
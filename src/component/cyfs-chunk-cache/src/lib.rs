@@ -1,6 +1,7 @@
 mod cached_file;
 mod chunk_cache;
 mod chunk_manager;
+mod compress;
 mod local_chunk_cache;
 mod local_file;
 mod old_base36;
@@ -8,6 +9,7 @@ mod old_base36;
 pub use cached_file::*;
 pub use chunk_cache::*;
 pub use chunk_manager::*;
+pub use compress::{ChunkCompressConfig, ChunkCompressStatInfo};
 pub use cyfs_chunk_lib::*;
 pub use local_chunk_cache::*;
 pub use local_file::*;
@@ -39,7 +41,7 @@ pub async fn create_local_chunk_cache(
         }
     }
 
-    let cache: SingleDiskChunkCache = SingleDiskChunkCache::new(chunk_dir);
+    let cache: SingleDiskChunkCache = SingleDiskChunkCache::new(chunk_dir, None);
 
     Ok(Box::new(cache))
 }
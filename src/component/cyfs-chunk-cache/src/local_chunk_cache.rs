@@ -1,3 +1,4 @@
+use crate::compress::{ChunkCompressor, ChunkCompressorRef};
 use crate::{Chunk, ChunkCache, ChunkMut, ChunkType, MMapChunk, MMapChunkMut, MemChunk};
 use cyfs_base::*;
 use cyfs_chunk_lib::{ChunkMeta};
@@ -189,12 +190,21 @@ pub(crate) struct LocalChunkCache<CACHE: TSingleDiskChunkCache + ChunkCache, SCA
     cache_meta: Mutex<LocalChunkCacheMeta>,
     scanner: SCANNER,
     isolate: String,
+    compressor: Option<ChunkCompressorRef>,
 }
 
 impl<CACHE: TSingleDiskChunkCache + ChunkCache, SCANNER: DiskScanner>
     LocalChunkCache<CACHE, SCANNER>
 {
     pub async fn new(isolate: &str, scanner: SCANNER) -> BuckyResult<Self> {
+        Self::new_with_compress(isolate, scanner, None).await
+    }
+
+    pub async fn new_with_compress(
+        isolate: &str,
+        scanner: SCANNER,
+        compressor: Option<ChunkCompressorRef>,
+    ) -> BuckyResult<Self> {
         let obj = Self {
             disk_cache_list: RwLock::new(Vec::new()),
             cache_meta: Mutex::new(LocalChunkCacheMeta::new()),
@@ -204,6 +214,7 @@ impl<CACHE: TSingleDiskChunkCache + ChunkCache, SCANNER: DiskScanner>
             } else {
                 isolate.to_string()
             },
+            compressor,
         };
         obj.refresh_cache().await?;
         Ok(obj)
@@ -237,7 +248,7 @@ impl<CACHE: TSingleDiskChunkCache + ChunkCache, SCANNER: DiskScanner>
             }
             let cache = match self.get_cache(path.as_path()) {
                 Some(cache) => cache,
-                None => Arc::new(CACHE::new(path.to_path_buf())),
+                None => Arc::new(CACHE::new(path.to_path_buf(), self.compressor.clone())),
             };
             let cache_meta = cache.get_local_cache_meta()?;
             let weight = (space / 1024 / 1024 / 1024) as u32;
@@ -387,7 +398,10 @@ impl<CACHE: TSingleDiskChunkCache + ChunkCache, SCANNER: DiskScanner>
             let mut max = f64::min_value();
             let mut max_cache = None;
             for (cache_path, weight) in record.list.iter() {
-                let tmp_cache = Arc::new(CACHE::new(PathBuf::from(cache_path.to_string())));
+                let tmp_cache = Arc::new(CACHE::new(
+                    PathBuf::from(cache_path.to_string()),
+                    self.compressor.clone(),
+                ));
                 let hash = Self::hash(chunk_id, tmp_cache.get_cache_id());
                 let v = (hash as f64 / u64::MAX as f64).ln() / (*weight as f64);
                 if v > max {
@@ -416,7 +430,10 @@ impl<CACHE: TSingleDiskChunkCache + ChunkCache, SCANNER: DiskScanner>
             let mut max = f64::min_value();
             let mut max_cache = None;
             for (cache_path, weight) in record.list.iter() {
-                let tmp_cache = Arc::new(CACHE::new(PathBuf::from(cache_path.to_string())));
+                let tmp_cache = Arc::new(CACHE::new(
+                    PathBuf::from(cache_path.to_string()),
+                    self.compressor.clone(),
+                ));
                 let hash = Self::hash(chunk_id, tmp_cache.get_cache_id());
                 let v = (hash as f64 / u64::MAX as f64).ln() / (*weight as f64);
                 if v > max {
@@ -515,10 +532,24 @@ impl<CACHE: TSingleDiskChunkCache + ChunkCache, SCANNER: DiskScanner> ChunkCache
             }
         }
     }
+
+    async fn used_size(&self) -> BuckyResult<u64> {
+        let cache_list: Vec<Arc<CACHE>> = {
+            let disk_cache_list = self.disk_cache_list.read().unwrap();
+            disk_cache_list.iter().map(|(cache, _)| cache.clone()).collect()
+        };
+
+        let mut size = 0;
+        for cache in cache_list {
+            size += cache.used_size().await?;
+        }
+
+        Ok(size)
+    }
 }
 
 pub(crate) trait TSingleDiskChunkCache {
-    fn new(path: PathBuf) -> Self;
+    fn new(path: PathBuf, compressor: Option<ChunkCompressorRef>) -> Self;
     fn get_cache_id(&self) -> &HashValue;
     fn get_cache_path(&self) -> &Path;
     fn get_local_cache_meta(&self) -> BuckyResult<LocalChunkCacheMeta>;
@@ -528,6 +559,7 @@ pub(crate) trait TSingleDiskChunkCache {
 pub struct SingleDiskChunkCache {
     path: PathBuf,
     cache_id: HashValue,
+    compressor: Option<ChunkCompressorRef>,
 
     #[cfg(target_os = "windows")]
     upgrade: super::old_base36::ChunkStorageUpgrade,
@@ -595,13 +627,115 @@ impl SingleDiskChunkCache {
                 );
                 BuckyError::from(e)
             });
+
+        let compressed_path = ChunkCompressor::compressed_path(&file_path);
+        if compressed_path.exists() {
+            let _ = async_std::fs::remove_file(compressed_path.as_path()).await;
+        }
         Ok(())
     }
 
+    // 读取压缩保存的chunk，不存在时返回None
+    async fn read_compressed(&self, chunk_id: &ChunkId, file_path: &Path) -> BuckyResult<Option<Vec<u8>>> {
+        let compressed_path = ChunkCompressor::compressed_path(file_path);
+        if !compressed_path.exists() {
+            return Ok(None);
+        }
+
+        let buf = async_std::fs::read(compressed_path.as_path())
+            .await
+            .map_err(|e| {
+                let msg = format!(
+                    "open chunk's compressed file error! chunk={}, file={}, {}",
+                    chunk_id,
+                    compressed_path.display(),
+                    e
+                );
+                log::error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::IoError, msg)
+            })?;
+
+        let buf = ChunkCompressor::decompress(chunk_id, &buf)?;
+        Ok(Some(buf))
+    }
+
+    async fn read_all(chunk_id: &ChunkId, mut chunk: Box<dyn Chunk>) -> BuckyResult<Vec<u8>> {
+        let mut buf = vec![0u8; chunk_id.len()];
+        let mut pos = 0;
+        while pos < buf.len() {
+            let bytes = chunk.read(&mut buf[pos..]).await?;
+            if bytes == 0 {
+                break;
+            }
+            pos += bytes;
+        }
+
+        if pos != chunk_id.len() {
+            let msg = format!("mismatched chunk length! chunk={}, len={}, got={}", chunk_id, chunk_id.len(), pos);
+            log::error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidData, msg));
+        }
+
+        let actual_id = ChunkId::calculate_sync(&buf)?;
+        if actual_id != *chunk_id {
+            let msg = format!("mismatched chunk hash value! chunk={}, len={}, got={}", chunk_id, chunk_id.len(), actual_id);
+            log::error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidData, msg));
+        }
+
+        Ok(buf)
+    }
+
+    // 尝试压缩保存，返回false表示压缩收益不足，调用方按原始数据保存
+    async fn put_compressed(
+        &self,
+        compressor: &ChunkCompressor,
+        chunk_id: &ChunkId,
+        buf: &[u8],
+        file_path: &Path,
+    ) -> BuckyResult<bool> {
+        let data = match compressor.compress(chunk_id, buf) {
+            Some(data) => data,
+            None => return Ok(false),
+        };
+
+        // .zst文件存在即视为chunk存在，先写临时文件再改名，避免中途失败留下不完整的压缩文件
+        let compressed_path = ChunkCompressor::compressed_path(file_path);
+        let mut tmp_path = compressed_path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let ret = match async_std::fs::write(tmp_path.as_path(), &data).await {
+            Ok(()) => async_std::fs::rename(tmp_path.as_path(), compressed_path.as_path()).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = ret {
+            let _ = async_std::fs::remove_file(tmp_path.as_path()).await;
+
+            let msg = format!(
+                "put chunk to compressed file failed! chunk={}, file={}, {}",
+                chunk_id,
+                compressed_path.display(),
+                e
+            );
+            log::error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::IoError, msg));
+        }
+
+        log::info!(
+            "put chunk to compressed file complete! chunk={}, file={}, len={}, stored={}",
+            chunk_id,
+            compressed_path.display(),
+            buf.len(),
+            data.len()
+        );
+        Ok(true)
+    }
+
     fn chunk_exist(&self, chunk_id: &ChunkId) -> bool {
         let file_path = self.get_file_path(chunk_id, false);
         if !file_path.exists() {
-            return false;
+            return ChunkCompressor::compressed_path(&file_path).exists();
         }
 
         let file_meta = match std::fs::metadata(file_path.as_path()) {
@@ -687,7 +821,7 @@ impl SingleDiskChunkCache {
 }
 
 impl TSingleDiskChunkCache for SingleDiskChunkCache {
-    fn new(path: PathBuf) -> Self {
+    fn new(path: PathBuf, compressor: Option<ChunkCompressorRef>) -> Self {
         let cache_id = hash_data(path.to_string_lossy().to_string().as_bytes());
         Self {
             #[cfg(target_os = "windows")]
@@ -695,6 +829,7 @@ impl TSingleDiskChunkCache for SingleDiskChunkCache {
 
             path,
             cache_id,
+            compressor,
         }
     }
 
@@ -733,6 +868,12 @@ impl ChunkCache for SingleDiskChunkCache {
         log::debug!("SingleDiskChunkCache get_chunk {}", chunk_id.to_string());
         let file_path = self.get_file_path(chunk_id, false);
         if !file_path.exists() {
+            // 压缩保存的chunk只能解压到内存
+            if let Some(buf) = self.read_compressed(chunk_id, &file_path).await? {
+                let chunk: Box<dyn Chunk> = Box::new(MemChunk::from(buf));
+                return Ok(chunk);
+            }
+
             #[cfg(target_os = "windows")]
             {
                 if !self.upgrade.try_update(&file_path, chunk_id) {
@@ -784,7 +925,7 @@ impl ChunkCache for SingleDiskChunkCache {
     async fn new_chunk(&self, chunk_id: &ChunkId) -> BuckyResult<Box<dyn ChunkMut>> {
         let file_path = self.get_file_path(chunk_id, true);
         log::info!("new chunk {}", file_path.to_string_lossy().to_string());
-        if file_path.exists() {
+        if file_path.exists() || ChunkCompressor::compressed_path(&file_path).exists() {
             let msg = format!(
                 "[{}:{}] file {} exist",
                 file!(),
@@ -802,9 +943,13 @@ impl ChunkCache for SingleDiskChunkCache {
 
     async fn delete_chunk(&self, chunk_id: &ChunkId) -> BuckyResult<()> {
         let file_path = self.get_file_path(chunk_id, false);
+        let compressed_path = ChunkCompressor::compressed_path(&file_path);
         if file_path.exists() {
             let _ = async_std::fs::remove_file(file_path).await;
         }
+        if compressed_path.exists() {
+            let _ = async_std::fs::remove_file(compressed_path).await;
+        }
         Ok(())
     }
 
@@ -821,7 +966,7 @@ impl ChunkCache for SingleDiskChunkCache {
         let file_path = self.get_file_path(chunk_id, true);
         // log::info!("will put chunk, chunk={}, len={}, local file={}", chunk_id, chunk_id.len(), file_path.display());
 
-        if file_path.exists() {
+        if file_path.exists() || ChunkCompressor::compressed_path(&file_path).exists() {
             let msg = format!(
                 "put chunk but local file already exist! chunk={}, file={},",
                 chunk_id,
@@ -831,6 +976,18 @@ impl ChunkCache for SingleDiskChunkCache {
             return Ok(());
         }
 
+        if let Some(compressor) = &self.compressor {
+            if chunk_len >= compressor.config().min_len {
+                chunk.as_mut().seek(std::io::SeekFrom::Start(0)).await?;
+                let buf = Self::read_all(chunk_id, chunk).await?;
+                if self.put_compressed(compressor, chunk_id, &buf, &file_path).await? {
+                    return Ok(());
+                }
+
+                chunk = Box::new(MemChunk::from(buf));
+            }
+        }
+
         let mut file = async_std::fs::OpenOptions::new()
             .read(true)
             .write(true)
@@ -923,6 +1080,10 @@ impl ChunkCache for SingleDiskChunkCache {
         log::debug!("SingleDiskChunkCache get_chunk {}", chunk_id.to_string());
         let file_path = self.get_file_path(chunk_id, false);
         if !file_path.exists() {
+            if let Some(buf) = self.read_compressed(chunk_id, &file_path).await? {
+                return Ok(ChunkMeta::MemChunk(buf));
+            }
+
             let msg = format!(
                 "local chunk file not exists! chunk={}, file={}",
                 file_path.display(),
@@ -954,10 +1115,15 @@ impl ChunkCache for SingleDiskChunkCache {
             }
         }
     }
+
+    async fn used_size(&self) -> BuckyResult<u64> {
+        get_path_size(self.path.clone()).await
+    }
 }
 
 #[cfg(test)]
 mod test_local_chunk_cache {
+    use crate::compress::ChunkCompressorRef;
     use crate::{
         Chunk, ChunkCache, ChunkMut, ChunkRead, ChunkType, ChunkWrite, DiskScanner,
         LocalChunkCache, LocalChunkCacheMeta, TSingleDiskChunkCache,
@@ -1047,7 +1213,7 @@ mod test_local_chunk_cache {
     }
 
     impl TSingleDiskChunkCache for SingleDiskChunkCacheMock {
-        fn new(path: PathBuf) -> Self {
+        fn new(path: PathBuf, _compressor: Option<ChunkCompressorRef>) -> Self {
             let cache_id = hash_data(path.to_string_lossy().to_string().as_bytes());
             Self {
                 path,
@@ -1151,6 +1317,17 @@ mod test_local_chunk_cache {
         ) -> BuckyResult<ChunkMeta> {
             todo!()
         }
+
+        async fn used_size(&self) -> BuckyResult<u64> {
+            let size = self
+                .chunk_map
+                .lock()
+                .unwrap()
+                .keys()
+                .map(|chunk_id| chunk_id.len() as u64)
+                .sum();
+            Ok(size)
+        }
     }
     pub struct ChunkMock {
         buf: Vec<u8>,
@@ -90,6 +90,8 @@ pub enum SubErrorCode {
     AssignContainerIpFailed = 10,
     RegisterAppFailed = 11,
     PubDirFailed = 12,
+    MigrationFailed = 13,
+    DependencyNotReady = 14,
     Unknown = 255,
 }
 
@@ -109,6 +111,8 @@ impl fmt::Display for SubErrorCode {
             &SubErrorCode::AssignContainerIpFailed => write!(f, "AssignContainerIpFailed"),
             &SubErrorCode::RegisterAppFailed => write!(f, "RegisterAppFailed"),
             &SubErrorCode::PubDirFailed => write!(f, "PubDirFailed"),
+            &SubErrorCode::MigrationFailed => write!(f, "MigrationFailed"),
+            &SubErrorCode::DependencyNotReady => write!(f, "DependencyNotReady"),
             &SubErrorCode::Unknown => write!(f, "Unknown"),
         }
     }
@@ -156,6 +160,74 @@ impl std::convert::From<u8> for PermissionState {
     }
 }
 
+// 由app-manager的健康检查得出，Unknown表示app没有运行或者还没检查过
+#[derive(Clone, Copy, Eq, PartialEq, Debug, IntEnum, Serialize)]
+#[repr(u8)]
+pub enum AppHealthStatus {
+    Unknown = 0,
+    NotReady = 1, //存活但是就绪探针未通过
+    Ready = 2,
+    Unhealthy = 3, //存活探针失败
+}
+
+impl fmt::Display for AppHealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &AppHealthStatus::Unknown => write!(f, "Unknown"),
+            &AppHealthStatus::NotReady => write!(f, "NotReady"),
+            &AppHealthStatus::Ready => write!(f, "Ready"),
+            &AppHealthStatus::Unhealthy => write!(f, "Unhealthy"),
+        }
+    }
+}
+
+impl std::convert::From<u8> for AppHealthStatus {
+    fn from(value: u8) -> Self {
+        match Self::from_int(value) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("unknown AppHealthStatus value: {} {}", value, e);
+                Self::Unknown
+            }
+        }
+    }
+}
+
+// 升级时app数据迁移的状态
+#[derive(Clone, Copy, Eq, PartialEq, Debug, IntEnum, Serialize)]
+#[repr(u8)]
+pub enum AppMigrationStatus {
+    None = 0,
+    Migrating = 1,
+    Success = 2,
+    RolledBack = 3, //迁移失败，数据已经回滚到迁移前
+    Failed = 4,     //迁移失败，并且回滚也失败了
+}
+
+impl fmt::Display for AppMigrationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &AppMigrationStatus::None => write!(f, "None"),
+            &AppMigrationStatus::Migrating => write!(f, "Migrating"),
+            &AppMigrationStatus::Success => write!(f, "Success"),
+            &AppMigrationStatus::RolledBack => write!(f, "RolledBack"),
+            &AppMigrationStatus::Failed => write!(f, "Failed"),
+        }
+    }
+}
+
+impl std::convert::From<u8> for AppMigrationStatus {
+    fn from(value: u8) -> Self {
+        match Self::from_int(value) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("unknown AppMigrationStatus value: {} {}", value, e);
+                Self::None
+            }
+        }
+    }
+}
+
 //state表示是否通过 -1:未处理,0:不同意，1：同意
 #[derive(Clone, Debug, Serialize)]
 pub struct PermissionNode {
@@ -175,6 +247,10 @@ pub struct AppLocalStatusDesc {
     last_status_update_time: u64,
     sub_error: SubErrorCode,
     auto_update: bool,
+    restart_count: u32,
+    health: AppHealthStatus,
+    data_version: u32,
+    migration_status: AppMigrationStatus,
 }
 impl DescContent for AppLocalStatusDesc {
     fn obj_type() -> u16 {
@@ -213,6 +289,10 @@ impl ProtobufTransform<protos::AppLocalStatusDesc> for AppLocalStatusDesc {
             last_status_update_time: value.last_status_update_time.parse::<u64>()?,
             sub_error: ProtobufCodecHelper::decode_value(value.sub_error as u8)?,
             auto_update: value.auto_update,
+            restart_count: value.restart_count,
+            health: ProtobufCodecHelper::decode_value(value.health as u8)?,
+            data_version: value.data_version,
+            migration_status: ProtobufCodecHelper::decode_value(value.migration_status as u8)?,
         };
         if value.version.is_some() {
             ret.version = Some(value.version.unwrap());
@@ -243,6 +323,10 @@ impl ProtobufTransform<&AppLocalStatusDesc> for protos::AppLocalStatusDesc {
             last_status_update_time: value.last_status_update_time.to_string(),
             sub_error: value.sub_error as u32,
             auto_update: value.auto_update,
+            restart_count: value.restart_count,
+            health: value.health as u32,
+            data_version: value.data_version,
+            migration_status: value.migration_status as u32,
         };
         ret.id = value.id.to_vec()?;
         if let Some(dir) = &value.web_dir {
@@ -288,6 +372,12 @@ pub trait AppLocalStatusObj {
     fn last_status_update_time(&self) -> u64;
     fn sub_error(&self) -> SubErrorCode;
     fn auto_update(&self) -> bool;
+    //被app-manager自动重启的次数
+    fn restart_count(&self) -> u32;
+    fn health(&self) -> AppHealthStatus;
+    //app数据的版本，由package.cfg的data_version声明
+    fn data_version(&self) -> u32;
+    fn migration_status(&self) -> AppMigrationStatus;
 
     fn set_status(&mut self, status: AppLocalStatusCode);
     fn set_web_dir(&mut self, web_dir: Option<ObjectId>);
@@ -301,6 +391,10 @@ pub trait AppLocalStatusObj {
     fn set_sub_error(&mut self, code: SubErrorCode);
     //return old auto_update value
     fn set_auto_update(&mut self, auto_update: bool) -> bool;
+    fn set_restart_count(&mut self, count: u32);
+    fn set_health(&mut self, health: AppHealthStatus);
+    fn set_data_version(&mut self, data_version: u32);
+    fn set_migration_status(&mut self, status: AppMigrationStatus);
 
     fn output(&self) -> String;
 }
@@ -323,6 +417,10 @@ impl AppLocalStatusObj for AppLocalStatus {
             last_status_update_time: bucky_time_now(),
             sub_error: SubErrorCode::None,
             auto_update: true,
+            restart_count: 0,
+            health: AppHealthStatus::Unknown,
+            data_version: 0,
+            migration_status: AppMigrationStatus::None,
         };
         let body = AppLocalStatusBody {};
         AppLocalStatusBuilder::new(desc, body)
@@ -347,6 +445,22 @@ impl AppLocalStatusObj for AppLocalStatus {
         self.desc().content().auto_update
     }
 
+    fn restart_count(&self) -> u32 {
+        self.desc().content().restart_count
+    }
+
+    fn health(&self) -> AppHealthStatus {
+        self.desc().content().health
+    }
+
+    fn data_version(&self) -> u32 {
+        self.desc().content().data_version
+    }
+
+    fn migration_status(&self) -> AppMigrationStatus {
+        self.desc().content().migration_status
+    }
+
     fn last_status_update_time(&self) -> u64 {
         self.desc().content().last_status_update_time
     }
@@ -456,6 +570,22 @@ impl AppLocalStatusObj for AppLocalStatus {
         old_value
     }
 
+    fn set_restart_count(&mut self, count: u32) {
+        self.desc_mut().content_mut().restart_count = count;
+    }
+
+    fn set_health(&mut self, health: AppHealthStatus) {
+        self.desc_mut().content_mut().health = health;
+    }
+
+    fn set_data_version(&mut self, data_version: u32) {
+        self.desc_mut().content_mut().data_version = data_version;
+    }
+
+    fn set_migration_status(&mut self, status: AppMigrationStatus) {
+        self.desc_mut().content_mut().migration_status = status;
+    }
+
     fn output(&self) -> String {
         let app_id = self.app_id();
         let status = self.status();
@@ -465,8 +595,9 @@ impl AppLocalStatusObj for AppLocalStatus {
         let self_id = self.desc().calculate_id();
         let auto_update = self.auto_update();
         format!(
-            "[AppLocalStatus] appid:{} statusid:{}, status:{}, ver:{:?}, auto_update:{}, sub err:{}",
-            app_id, self_id, status, ver, auto_update, sub_err
+            "[AppLocalStatus] appid:{} statusid:{}, status:{}, ver:{:?}, auto_update:{}, sub err:{}, restart:{}, health:{}, data ver:{}, migration:{}",
+            app_id, self_id, status, ver, auto_update, sub_err, self.restart_count(), self.health(),
+            self.data_version(), self.migration_status()
         )
     }
 }
@@ -37,12 +37,32 @@ pub struct ModifyAcl {
     pub dec_list: HashMap<String, DecAclInfo>,
 }
 
+// action: install/upgrade/start/keep
+#[derive(Clone, Debug, ProtobufTransform, Serialize)]
+#[cyfs_protobuf_type(crate::codec::protos::AppInstallPlanItem)]
+pub struct AppInstallPlanItem {
+    pub dec_id: String,
+    pub version: String,
+    pub action: String,
+    pub required_by: String,
+}
+
+// 安装dec_id时需要先处理的依赖，按执行顺序排列
+#[derive(Clone, Debug, ProtobufTransform, Serialize)]
+#[cyfs_protobuf_type(crate::codec::protos::AppInstallPlan)]
+pub struct AppInstallPlan {
+    pub dec_id: String,
+    pub version: String,
+    pub items: Vec<AppInstallPlanItem>,
+}
+
 #[derive(Clone, ProtobufTransform, Serialize)]
 #[cyfs_protobuf_type(crate::codec::protos::app_manager_action_desc::AppManagerActionEnum)]
 pub enum AppManagerActionEnum {
     RegisterDec(RegisterDec),
     UnregisterDec(UnregisterDec),
     ModifyAcl(ModifyAcl),
+    InstallPlan(AppInstallPlan),
 }
 
 #[derive(Clone, ProtobufEncode, ProtobufDecode, ProtobufTransform, Serialize)]
@@ -92,6 +112,8 @@ pub trait AppManagerActionObj {
 
     fn create_modify_acl(owner: ObjectId, dec_list: HashMap<String, DecAclInfo>) -> Self;
 
+    fn create_install_plan(owner: ObjectId, plan: AppInstallPlan) -> Self;
+
     fn action(&self) -> &AppManagerActionEnum;
 }
 
@@ -139,6 +161,18 @@ impl AppManagerActionObj for AppManagerAction {
             .build()
     }
 
+    fn create_install_plan(owner: ObjectId, plan: AppInstallPlan) -> Self {
+        let action = AppManagerActionEnum::InstallPlan(plan);
+
+        let desc = AppManagerActionDesc {
+            app_manager_action_enum: action,
+        };
+        let body = AppManagerActionBody {};
+        AppManagerActionBuilder::new(desc, body)
+            .owner(owner)
+            .build()
+    }
+
     fn action(&self) -> &AppManagerActionEnum {
         &self.desc().content().app_manager_action_enum
     }
@@ -35,6 +35,8 @@ pub struct DecAppContent {
     desc: Option<String>,
     source_desc: HashMap<String, String>,
     tags: HashMap<String, String>,
+    // version -> (依赖的dec_id -> semver要求)
+    deps: HashMap<String, HashMap<ObjectId, String>>,
 }
 
 impl BodyContent for DecAppContent {
@@ -60,12 +62,20 @@ impl ProtobufTransform<protos::DecAppContent> for DecAppContent {
             tags.insert(item.key, item.value);
         }
 
+        let mut deps: HashMap<String, HashMap<ObjectId, String>> = HashMap::new();
+        for item in value.deps {
+            deps.entry(item.version)
+                .or_default()
+                .insert(ObjectId::clone_from_slice(item.dec_id.as_slice())?, item.req_semver);
+        }
+
         let mut ret = DecAppContent {
             source,
             source_desc,
             icon: None,
             desc: None,
             tags,
+            deps,
         };
 
         if value.icon.is_some() {
@@ -102,12 +112,29 @@ impl ProtobufTransform<&DecAppContent> for protos::DecAppContent {
             tags.push(protos::StringStringMapItem { key: k, value: v });
         }
 
+        let deps_map: BTreeMap<&String, BTreeMap<&ObjectId, &String>> = value
+            .deps
+            .iter()
+            .map(|(version, deps)| (version, deps.iter().collect()))
+            .collect();
+        let mut deps = vec![];
+        for (version, dep_list) in deps_map {
+            for (dec_id, req_semver) in dep_list {
+                deps.push(protos::DecAppDependency {
+                    version: version.to_owned(),
+                    dec_id: dec_id.to_vec()?,
+                    req_semver: req_semver.to_owned(),
+                });
+            }
+        }
+
         let mut ret = Self {
             source,
             source_desc,
             icon: None,
             desc: None,
             tags,
+            deps,
         };
 
         if let Some(icon) = &value.icon {
@@ -153,6 +180,10 @@ pub trait DecAppObj {
     fn remove_tag(&mut self, tag: &str);
     fn tags(&self) -> &HashMap<String, String>;
 
+    // 某个版本依赖的其它dec app, dec_id -> semver要求
+    fn find_deps(&self, version: &str) -> Option<&HashMap<ObjectId, String>>;
+    fn set_deps(&mut self, version: String, deps: HashMap<ObjectId, String>);
+
     fn generate_id(owner: ObjectId, id: &str) -> ObjectId;
 }
 
@@ -190,6 +221,7 @@ impl DecAppObj for DecApp {
             desc: None,
             source_desc: HashMap::new(),
             tags: HashMap::new(),
+            deps: HashMap::new(),
         };
         let desc = DecAppDescContent { id: id.to_owned() };
         DecAppBuilder::new(desc, body)
@@ -325,6 +357,10 @@ impl DecAppObj for DecApp {
             .content_mut()
             .source_desc
             .remove(version);
+        self.body_mut_expect("")
+            .content_mut()
+            .deps
+            .remove(version);
         self.body_mut_expect("")
             .increase_update_time(bucky_time_now());
     }
@@ -332,6 +368,7 @@ impl DecAppObj for DecApp {
     fn clear_source(&mut self) {
         self.body_mut_expect("").content_mut().source.clear();
         self.body_mut_expect("").content_mut().source_desc.clear();
+        self.body_mut_expect("").content_mut().deps.clear();
         self.body_mut_expect("")
             .increase_update_time(bucky_time_now());
     }
@@ -381,6 +418,21 @@ impl DecAppObj for DecApp {
         &self.body_expect("").content().tags
     }
 
+    fn find_deps(&self, version: &str) -> Option<&HashMap<ObjectId, String>> {
+        self.body_expect("").content().deps.get(version)
+    }
+
+    fn set_deps(&mut self, version: String, deps: HashMap<ObjectId, String>) {
+        let content = self.body_mut_expect("").content_mut();
+        if deps.is_empty() {
+            content.deps.remove(&version);
+        } else {
+            content.deps.insert(version, deps);
+        }
+        self.body_mut_expect("")
+            .increase_update_time(bucky_time_now());
+    }
+
     fn generate_id(owner: ObjectId, id: &str) -> ObjectId {
         Self::create(owner, id).desc().calculate_id()
     }
@@ -74,10 +74,44 @@ impl ObjectFormatAutoWithSerde for TransContextBodyContent {}
 
 impl ObjectFormatAutoWithSerde for ZoneDescContent {}
 impl ObjectFormatAutoWithSerde for ZoneBodyContent {}
+impl ObjectFormatAutoWithSerde for ZoneOODEpochDescContent {}
+impl ObjectFormatAutoWithSerde for ZoneOODEpochBody {}
+impl ObjectFormatAutoWithSerde for KeyRotationDescContent {}
+impl ObjectFormat for KeyRotationBodyContent {
+    fn format_json(&self) -> Value {
+        let mut map = serde_json::Map::new();
+        map.insert("old_key".to_owned(), self.old_key.format_json());
+        map.insert("new_key".to_owned(), self.new_key.format_json());
+        map.insert("effective_time".to_owned(), self.effective_time.into());
+        map.into()
+    }
+}
+impl ObjectFormatAutoWithSerde for DeviceRevocationListDescContent {}
+impl ObjectFormatAutoWithSerde for DeviceRevocationListBodyContent {}
+impl ObjectFormat for SealedObjectDescContent {
+    fn format_json(&self) -> Value {
+        let mut map = serde_json::Map::new();
+        let recipients: Vec<Value> = self
+            .recipients
+            .iter()
+            .map(|v| v.recipient.to_string().into())
+            .collect();
+        map.insert("recipients".to_owned(), recipients.into());
+        if let Some(data_len) = self.data_len {
+            map.insert("data_len".to_owned(), data_len.into());
+        }
+        map.insert("sealed_len".to_owned(), self.data.len().into());
+        map.into()
+    }
+}
 
 
 pub fn register_core_objects_format() {
     FORMAT_FACTORY.register(CoreObjectType::Zone, format_json::<Zone>);
+    FORMAT_FACTORY.register(CoreObjectType::ZoneOODEpoch, format_json::<ZoneOODEpoch>);
+    FORMAT_FACTORY.register(CoreObjectType::KeyRotation, format_json::<KeyRotation>);
+    FORMAT_FACTORY.register(CoreObjectType::DeviceRevocationList, format_json::<DeviceRevocationList>);
+    FORMAT_FACTORY.register(CoreObjectType::SealedObject, format_json::<SealedObject>);
     FORMAT_FACTORY.register(CoreObjectType::Storage, format_json::<Storage>);
     FORMAT_FACTORY.register(CoreObjectType::Text, format_json::<Text>);
 
@@ -9,6 +9,16 @@ pub enum CoreObjectType {
     // admin control
     Admin = 33,
 
+    // active/standby模式下当前的主ood
+    ZoneOODEpoch = 34,
+
+    // 密钥轮换记录和设备吊销列表
+    KeyRotation = 35,
+    DeviceRevocationList = 36,
+
+    // 只有指定接收者可以解密的加密对象
+    SealedObject = 37,
+
     // 基于object的存储
     Storage = 40,
 
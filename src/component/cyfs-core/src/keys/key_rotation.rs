@@ -0,0 +1,293 @@
+use crate::codec::*;
+use crate::coreobj::CoreObjectType;
+use cyfs_base::*;
+use serde::Serialize;
+
+// 密钥轮换记录，subject是People/Device对象，seq从1开始连续递增
+// desc里只有subject和seq，不带create_time，其它设备可以直接算出第n次轮换的对象id去查询
+#[derive(Debug, Clone, ProtobufEncode, ProtobufDecode, ProtobufTransform, Serialize)]
+#[cyfs_protobuf_type(crate::codec::protos::KeyRotationDescContent)]
+pub struct KeyRotationDescContent {
+    subject: ObjectId,
+    seq: u32,
+}
+
+impl DescContent for KeyRotationDescContent {
+    fn obj_type() -> u16 {
+        CoreObjectType::KeyRotation as u16
+    }
+
+    fn format(&self) -> u8 {
+        OBJECT_CONTENT_CODEC_FORMAT_PROTOBUF
+    }
+
+    type OwnerType = Option<ObjectId>;
+    type AreaType = SubDescNone;
+    type AuthorType = SubDescNone;
+    type PublicKeyType = SubDescNone;
+}
+
+// body由旧密钥签名，device也可以由owner签名(旧密钥丢失的情况)
+#[derive(Clone, Debug, ProtobufEncode, ProtobufDecode, ProtobufTransformType)]
+#[cyfs_protobuf_type(crate::codec::protos::KeyRotationBodyContent)]
+pub struct KeyRotationBodyContent {
+    pub(crate) old_key: PublicKey,
+    pub(crate) new_key: PublicKey,
+
+    // 早于effective_time的签名使用旧密钥校验，有可信时间时以可信时间为准，否则使用签名里的sign_time
+    pub(crate) effective_time: u64,
+}
+
+impl BodyContent for KeyRotationBodyContent {
+    fn format(&self) -> u8 {
+        OBJECT_CONTENT_CODEC_FORMAT_PROTOBUF
+    }
+}
+
+impl ProtobufTransform<protos::KeyRotationBodyContent> for KeyRotationBodyContent {
+    fn transform(value: protos::KeyRotationBodyContent) -> BuckyResult<Self> {
+        Ok(Self {
+            old_key: PublicKey::clone_from_slice(&value.old_key)?,
+            new_key: PublicKey::clone_from_slice(&value.new_key)?,
+            effective_time: value.effective_time,
+        })
+    }
+}
+
+impl ProtobufTransform<&KeyRotationBodyContent> for protos::KeyRotationBodyContent {
+    fn transform(value: &KeyRotationBodyContent) -> BuckyResult<Self> {
+        Ok(Self {
+            old_key: value.old_key.to_vec()?,
+            new_key: value.new_key.to_vec()?,
+            effective_time: value.effective_time,
+        })
+    }
+}
+
+type KeyRotationType = NamedObjType<KeyRotationDescContent, KeyRotationBodyContent>;
+type KeyRotationBuilder = NamedObjectBuilder<KeyRotationDescContent, KeyRotationBodyContent>;
+
+pub type KeyRotationId = NamedObjectId<KeyRotationType>;
+pub type KeyRotation = NamedObjectBase<KeyRotationType>;
+
+pub trait KeyRotationObj {
+    fn create(
+        subject: ObjectId,
+        seq: u32,
+        old_key: PublicKey,
+        new_key: PublicKey,
+        effective_time: u64,
+    ) -> Self;
+
+    fn rotation_id(subject: &ObjectId, seq: u32) -> ObjectId;
+
+    fn subject(&self) -> &ObjectId;
+    fn seq(&self) -> u32;
+    fn old_key(&self) -> &PublicKey;
+    fn new_key(&self) -> &PublicKey;
+    fn effective_time(&self) -> u64;
+}
+
+impl KeyRotationObj for KeyRotation {
+    fn create(
+        subject: ObjectId,
+        seq: u32,
+        old_key: PublicKey,
+        new_key: PublicKey,
+        effective_time: u64,
+    ) -> Self {
+        let desc = KeyRotationDescContent { subject, seq };
+        let body = KeyRotationBodyContent {
+            old_key,
+            new_key,
+            effective_time,
+        };
+
+        KeyRotationBuilder::new(desc, body).no_create_time().build()
+    }
+
+    fn rotation_id(subject: &ObjectId, seq: u32) -> ObjectId {
+        let desc = KeyRotationDescContent {
+            subject: subject.to_owned(),
+            seq,
+        };
+
+        NamedObjectDescBuilder::new(KeyRotationDescContent::obj_type(), desc)
+            .option_create_time(None)
+            .build()
+            .calculate_id()
+    }
+
+    fn subject(&self) -> &ObjectId {
+        &self.desc().content().subject
+    }
+
+    fn seq(&self) -> u32 {
+        self.desc().content().seq
+    }
+
+    fn old_key(&self) -> &PublicKey {
+        &self.body().as_ref().unwrap().content().old_key
+    }
+
+    fn new_key(&self) -> &PublicKey {
+        &self.body().as_ref().unwrap().content().new_key
+    }
+
+    fn effective_time(&self) -> u64 {
+        self.body().as_ref().unwrap().content().effective_time
+    }
+}
+
+// 某个对象的密钥轮换链，origin是对象desc里的原始公钥
+// 签名校验需要异步，由调用方在append之前完成
+#[derive(Clone, Debug)]
+pub struct KeyRotationChain {
+    subject: ObjectId,
+    origin: PublicKey,
+    list: Vec<KeyRotation>,
+}
+
+impl KeyRotationChain {
+    pub fn new(subject: ObjectId, origin: PublicKey) -> Self {
+        Self {
+            subject,
+            origin,
+            list: vec![],
+        }
+    }
+
+    pub fn subject(&self) -> &ObjectId {
+        &self.subject
+    }
+
+    pub fn origin(&self) -> &PublicKey {
+        &self.origin
+    }
+
+    pub fn list(&self) -> &Vec<KeyRotation> {
+        &self.list
+    }
+
+    pub fn next_seq(&self) -> u32 {
+        self.list.len() as u32 + 1
+    }
+
+    // 当前有效的公钥，也是下一次轮换需要的签名密钥
+    pub fn current(&self) -> &PublicKey {
+        self.list
+            .last()
+            .map(|rotation| rotation.new_key())
+            .unwrap_or(&self.origin)
+    }
+
+    // time时刻有效的公钥
+    pub fn key_at(&self, time: u64) -> &PublicKey {
+        let mut key = &self.origin;
+        for rotation in &self.list {
+            if rotation.effective_time() > time {
+                break;
+            }
+            key = rotation.new_key();
+        }
+
+        key
+    }
+
+    // 检查轮换记录能否接在链的末尾
+    pub fn check_next(&self, rotation: &KeyRotation) -> BuckyResult<()> {
+        if *rotation.subject() != self.subject {
+            let msg = format!(
+                "key rotation's subject unmatch! expect={}, got={}",
+                self.subject,
+                rotation.subject()
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+        }
+
+        if rotation.seq() != self.next_seq() {
+            let msg = format!(
+                "key rotation's seq unmatch! subject={}, expect={}, got={}",
+                self.subject,
+                self.next_seq(),
+                rotation.seq()
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+        }
+
+        if rotation.old_key() != self.current() {
+            let msg = format!(
+                "key rotation's old key unmatch current key! subject={}, seq={}",
+                self.subject,
+                rotation.seq()
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+        }
+
+        if let Some(last) = self.list.last() {
+            if rotation.effective_time() < last.effective_time() {
+                let msg = format!(
+                    "key rotation's effective time before last rotation! subject={}, seq={}",
+                    self.subject,
+                    rotation.seq()
+                );
+                warn!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn append(&mut self, rotation: KeyRotation) -> BuckyResult<()> {
+        self.check_next(&rotation)?;
+        self.list.push(rotation);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use cyfs_base::*;
+
+    use std::str::FromStr;
+
+    #[test]
+    fn test() {
+        let subject = ObjectId::from_str("5aSixgLtjoYcAFH9isc6KCqDgKfTJ8jpgASAoiRz5NLk").unwrap();
+        let key1 = PrivateKey::generate_rsa(1024).unwrap().public();
+        let key2 = PrivateKey::generate_rsa(1024).unwrap().public();
+        let key3 = PrivateKey::generate_rsa(1024).unwrap().public();
+
+        let rotation = KeyRotation::create(subject.clone(), 1, key1.clone(), key2.clone(), 100);
+        let buf = rotation.to_vec().unwrap();
+        let rotation = KeyRotation::clone_from_slice(&buf).unwrap();
+        assert_eq!(rotation.seq(), 1);
+        assert_eq!(rotation.new_key(), &key2);
+        assert_eq!(
+            KeyRotation::rotation_id(&subject, 1),
+            rotation.desc().calculate_id()
+        );
+
+        let mut chain = KeyRotationChain::new(subject.clone(), key1.clone());
+        chain.append(rotation).unwrap();
+
+        // old key必须是当前的key
+        let invalid = KeyRotation::create(subject.clone(), 2, key1.clone(), key3.clone(), 200);
+        assert!(chain.check_next(&invalid).is_err());
+
+        let rotation = KeyRotation::create(subject.clone(), 2, key2.clone(), key3.clone(), 200);
+        chain.append(rotation).unwrap();
+
+        assert_eq!(chain.key_at(50), &key1);
+        assert_eq!(chain.key_at(100), &key2);
+        assert_eq!(chain.key_at(150), &key2);
+        assert_eq!(chain.key_at(300), &key3);
+        assert_eq!(chain.current(), &key3);
+    }
+}
@@ -0,0 +1,5 @@
+mod key_rotation;
+mod revocation;
+
+pub use key_rotation::*;
+pub use revocation::*;
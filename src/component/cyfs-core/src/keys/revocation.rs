@@ -0,0 +1,199 @@
+use crate::codec::*;
+use crate::coreobj::CoreObjectType;
+use cyfs_base::*;
+use serde::Serialize;
+
+// owner维护的设备吊销列表，每个owner只有一个，body由owner签名，通过body的update_time更新
+#[derive(Debug, Clone, ProtobufEncode, ProtobufDecode, ProtobufTransform, Serialize)]
+#[cyfs_protobuf_type(crate::codec::protos::DeviceRevocationListDescContent)]
+pub struct DeviceRevocationListDescContent {
+    owner: ObjectId,
+}
+
+impl DescContent for DeviceRevocationListDescContent {
+    fn obj_type() -> u16 {
+        CoreObjectType::DeviceRevocationList as u16
+    }
+
+    fn format(&self) -> u8 {
+        OBJECT_CONTENT_CODEC_FORMAT_PROTOBUF
+    }
+
+    type OwnerType = Option<ObjectId>;
+    type AreaType = SubDescNone;
+    type AuthorType = SubDescNone;
+    type PublicKeyType = SubDescNone;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevokedDevice {
+    pub device_id: DeviceId,
+
+    // 吊销时间，之后该设备的签名都是无效的
+    pub revoke_time: u64,
+    pub reason: Option<String>,
+}
+
+#[derive(
+    Clone, Debug, Default, ProtobufEncode, ProtobufDecode, ProtobufTransformType, Serialize,
+)]
+#[cyfs_protobuf_type(crate::codec::protos::DeviceRevocationListBodyContent)]
+pub struct DeviceRevocationListBodyContent {
+    list: Vec<RevokedDevice>,
+}
+
+impl BodyContent for DeviceRevocationListBodyContent {
+    fn format(&self) -> u8 {
+        OBJECT_CONTENT_CODEC_FORMAT_PROTOBUF
+    }
+}
+
+impl ProtobufTransform<protos::DeviceRevocationListBodyContent>
+    for DeviceRevocationListBodyContent
+{
+    fn transform(value: protos::DeviceRevocationListBodyContent) -> BuckyResult<Self> {
+        let mut list = Vec::with_capacity(value.list.len());
+        for item in value.list {
+            list.push(RevokedDevice {
+                device_id: ProtobufCodecHelper::decode_buf(item.device_id)?,
+                revoke_time: item.revoke_time,
+                reason: item.reason,
+            });
+        }
+
+        Ok(Self { list })
+    }
+}
+
+impl ProtobufTransform<&DeviceRevocationListBodyContent>
+    for protos::DeviceRevocationListBodyContent
+{
+    fn transform(value: &DeviceRevocationListBodyContent) -> BuckyResult<Self> {
+        let mut list = Vec::with_capacity(value.list.len());
+        for item in &value.list {
+            list.push(protos::RevokedDeviceItem {
+                device_id: item.device_id.to_vec()?,
+                revoke_time: item.revoke_time,
+                reason: item.reason.clone(),
+            });
+        }
+
+        Ok(Self { list })
+    }
+}
+
+type DeviceRevocationListType =
+    NamedObjType<DeviceRevocationListDescContent, DeviceRevocationListBodyContent>;
+type DeviceRevocationListBuilder =
+    NamedObjectBuilder<DeviceRevocationListDescContent, DeviceRevocationListBodyContent>;
+
+pub type DeviceRevocationListId = NamedObjectId<DeviceRevocationListType>;
+pub type DeviceRevocationList = NamedObjectBase<DeviceRevocationListType>;
+
+pub trait DeviceRevocationListObj {
+    fn create(owner: ObjectId) -> Self;
+
+    fn list_id(owner: &ObjectId) -> ObjectId;
+
+    fn owner(&self) -> &ObjectId;
+    fn list(&self) -> &Vec<RevokedDevice>;
+
+    fn revoke_time(&self, device_id: &DeviceId) -> Option<u64>;
+    // time需要是签名方无法控制的可信时间
+    fn is_revoked(&self, device_id: &DeviceId, time: u64) -> bool;
+
+    // 返回false表示已经在列表里
+    fn revoke(&mut self, device_id: DeviceId, revoke_time: u64, reason: Option<String>) -> bool;
+}
+
+impl DeviceRevocationListObj for DeviceRevocationList {
+    fn create(owner: ObjectId) -> Self {
+        let desc = DeviceRevocationListDescContent {
+            owner: owner.clone(),
+        };
+
+        DeviceRevocationListBuilder::new(desc, DeviceRevocationListBodyContent::default())
+            .owner(owner)
+            .no_create_time()
+            .build()
+    }
+
+    fn list_id(owner: &ObjectId) -> ObjectId {
+        let desc = DeviceRevocationListDescContent {
+            owner: owner.to_owned(),
+        };
+
+        NamedObjectDescBuilder::new(DeviceRevocationListDescContent::obj_type(), desc)
+            .option_create_time(None)
+            .owner(owner.to_owned())
+            .build()
+            .calculate_id()
+    }
+
+    fn owner(&self) -> &ObjectId {
+        &self.desc().content().owner
+    }
+
+    fn list(&self) -> &Vec<RevokedDevice> {
+        &self.body().as_ref().unwrap().content().list
+    }
+
+    fn revoke_time(&self, device_id: &DeviceId) -> Option<u64> {
+        self.list()
+            .iter()
+            .find(|item| item.device_id == *device_id)
+            .map(|item| item.revoke_time)
+    }
+
+    fn is_revoked(&self, device_id: &DeviceId, time: u64) -> bool {
+        match self.revoke_time(device_id) {
+            Some(revoke_time) => time >= revoke_time,
+            None => false,
+        }
+    }
+
+    fn revoke(&mut self, device_id: DeviceId, revoke_time: u64, reason: Option<String>) -> bool {
+        if self.revoke_time(&device_id).is_some() {
+            return false;
+        }
+
+        let body = self.body_mut().as_mut().unwrap();
+        body.content_mut().list.push(RevokedDevice {
+            device_id,
+            revoke_time,
+            reason,
+        });
+        body.increase_update_time(bucky_time_now());
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use cyfs_base::*;
+
+    use std::str::FromStr;
+
+    #[test]
+    fn test() {
+        let owner = ObjectId::from_str("5aSixgLtjoYcAFH9isc6KCqDgKfTJ8jpgASAoiRz5NLk").unwrap();
+        let device = DeviceId::from_str("5aSixgPJLRApy31v15U8mM7cVSndc8kjbECXSfP9o6Ef").unwrap();
+
+        let mut list = DeviceRevocationList::create(owner.clone());
+        assert_eq!(
+            DeviceRevocationList::list_id(&owner),
+            list.desc().calculate_id()
+        );
+
+        assert!(list.revoke(device.clone(), 100, Some("lost".to_owned())));
+        assert!(!list.revoke(device.clone(), 200, None));
+
+        let buf = list.to_vec().unwrap();
+        let list = DeviceRevocationList::clone_from_slice(&buf).unwrap();
+        assert_eq!(list.revoke_time(&device), Some(100));
+        assert!(!list.is_revoked(&device, 99));
+        assert!(list.is_revoked(&device, 100));
+    }
+}
@@ -6,7 +6,9 @@ pub use codec::*;
 pub use common::*;
 pub use coreobj::*;
 pub use group::*;
+pub use keys::*;
 pub use nft::*;
+pub use sealed::*;
 pub use storage::*;
 pub use trans::*;
 pub use zone::*;
@@ -16,8 +18,10 @@ pub mod codec;
 mod common;
 mod coreobj;
 mod group;
+mod keys;
 pub mod im;
 mod nft;
+mod sealed;
 mod storage;
 mod trans;
 mod zone;
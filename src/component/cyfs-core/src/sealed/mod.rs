@@ -0,0 +1,3 @@
+mod sealed_object;
+
+pub use sealed_object::*;
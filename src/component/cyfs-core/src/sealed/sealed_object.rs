@@ -0,0 +1,303 @@
+use crate::codec::*;
+use crate::coreobj::CoreObjectType;
+use cyfs_base::*;
+
+/*
+加密对象
+1. 随机生成内容密钥(AesKey)加密内部对象的object_raw，只有recipients可以解出内容密钥
+2. 每个recipient使用自己的公钥交换出一个临时密钥(rsa/secp256k1都支持)，再用临时密钥加密内容密钥
+3. 加密的file，内部对象是chunk_list为密文chunk的File，每个chunk使用同一个内容密钥单独加密，data_len为明文长度
+4. 每段密文都以随机iv开头，内容密钥只使用其中的aes key部分，相同的明文加密后也不相同
+*/
+#[derive(Debug, Clone)]
+pub struct SealedRecipient {
+    pub recipient: ObjectId,
+
+    // 公钥交换数据 + 临时密钥加密后的内容密钥
+    pub encrypted_key: Vec<u8>,
+}
+
+#[derive(Clone, Debug, ProtobufEncode, ProtobufDecode, ProtobufTransformType)]
+#[cyfs_protobuf_type(crate::codec::protos::SealedObjectDescContent)]
+pub struct SealedObjectDescContent {
+    pub(crate) recipients: Vec<SealedRecipient>,
+    pub(crate) data_len: Option<u64>,
+    pub(crate) data: Vec<u8>,
+}
+
+impl DescContent for SealedObjectDescContent {
+    fn obj_type() -> u16 {
+        CoreObjectType::SealedObject as u16
+    }
+
+    fn format(&self) -> u8 {
+        OBJECT_CONTENT_CODEC_FORMAT_PROTOBUF
+    }
+
+    type OwnerType = Option<ObjectId>;
+    type AreaType = SubDescNone;
+    type AuthorType = SubDescNone;
+    type PublicKeyType = SubDescNone;
+}
+
+impl ProtobufTransform<protos::SealedObjectDescContent> for SealedObjectDescContent {
+    fn transform(value: protos::SealedObjectDescContent) -> BuckyResult<Self> {
+        let mut recipients = Vec::with_capacity(value.recipients.len());
+        for item in value.recipients {
+            recipients.push(SealedRecipient {
+                recipient: ProtobufCodecHelper::decode_buf(item.recipient)?,
+                encrypted_key: item.encrypted_key,
+            });
+        }
+
+        Ok(Self {
+            recipients,
+            data_len: value.data_len,
+            data: value.data,
+        })
+    }
+}
+
+impl ProtobufTransform<&SealedObjectDescContent> for protos::SealedObjectDescContent {
+    fn transform(value: &SealedObjectDescContent) -> BuckyResult<Self> {
+        let mut recipients = Vec::with_capacity(value.recipients.len());
+        for item in &value.recipients {
+            recipients.push(protos::SealedRecipientItem {
+                recipient: item.recipient.to_vec()?,
+                encrypted_key: item.encrypted_key.clone(),
+            });
+        }
+
+        Ok(Self {
+            recipients,
+            data_len: value.data_len,
+            data: value.data.clone(),
+        })
+    }
+}
+
+type SealedObjectType = NamedObjType<SealedObjectDescContent, EmptyProtobufBodyContent>;
+type SealedObjectBuilder = NamedObjectBuilder<SealedObjectDescContent, EmptyProtobufBodyContent>;
+
+pub type SealedObjectId = NamedObjectId<SealedObjectType>;
+pub type SealedObject = NamedObjectBase<SealedObjectType>;
+
+pub trait SealedObjectObj {
+    // 使用随机的内容密钥加密对象，返回内容密钥用以继续加密file的chunk
+    fn seal(
+        owner: Option<ObjectId>,
+        recipients: &[(ObjectId, PublicKey)],
+        object_raw: &[u8],
+        data_len: Option<u64>,
+    ) -> BuckyResult<(Self, AesKey)>
+    where
+        Self: Sized;
+
+    fn seal_with_key(
+        owner: Option<ObjectId>,
+        key: &AesKey,
+        recipients: &[(ObjectId, PublicKey)],
+        object_raw: &[u8],
+        data_len: Option<u64>,
+    ) -> BuckyResult<Self>
+    where
+        Self: Sized;
+
+    fn recipients(&self) -> &Vec<SealedRecipient>;
+    fn is_recipient(&self, id: &ObjectId) -> bool;
+
+    // 加密file的明文长度，为空表示内部对象的chunk没有加密
+    fn data_len(&self) -> Option<u64>;
+
+    // 使用recipient的私钥解出内容密钥
+    fn open_key(&self, recipient: &ObjectId, secret: &PrivateKey) -> BuckyResult<AesKey>;
+
+    // 解密得到内部对象的object_raw
+    fn open(&self, key: &AesKey) -> BuckyResult<Vec<u8>>;
+}
+
+impl SealedObjectObj for SealedObject {
+    fn seal(
+        owner: Option<ObjectId>,
+        recipients: &[(ObjectId, PublicKey)],
+        object_raw: &[u8],
+        data_len: Option<u64>,
+    ) -> BuckyResult<(Self, AesKey)> {
+        let key = AesKey::random();
+        let obj = Self::seal_with_key(owner, &key, recipients, object_raw, data_len)?;
+
+        Ok((obj, key))
+    }
+
+    fn seal_with_key(
+        owner: Option<ObjectId>,
+        key: &AesKey,
+        recipients: &[(ObjectId, PublicKey)],
+        object_raw: &[u8],
+        data_len: Option<u64>,
+    ) -> BuckyResult<Self> {
+        if recipients.is_empty() {
+            let msg = format!("seal object but recipients is empty!");
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        let mut list = Vec::with_capacity(recipients.len());
+        for (recipient, pk) in recipients {
+            let (exchange_key, mut encrypted_key) = pk.gen_aeskey_and_encrypt()?;
+            encrypted_key.extend_from_slice(&seal_data(&exchange_key, key.as_slice())?);
+
+            list.push(SealedRecipient {
+                recipient: recipient.to_owned(),
+                encrypted_key,
+            });
+        }
+
+        let desc = SealedObjectDescContent {
+            recipients: list,
+            data_len,
+            data: seal_data(key, object_raw)?,
+        };
+
+        let obj = SealedObjectBuilder::new(desc, EmptyProtobufBodyContent {})
+            .option_owner(owner)
+            .build();
+
+        Ok(obj)
+    }
+
+    fn recipients(&self) -> &Vec<SealedRecipient> {
+        &self.desc().content().recipients
+    }
+
+    fn is_recipient(&self, id: &ObjectId) -> bool {
+        self.recipients().iter().any(|v| v.recipient == *id)
+    }
+
+    fn data_len(&self) -> Option<u64> {
+        self.desc().content().data_len
+    }
+
+    fn open_key(&self, recipient: &ObjectId, secret: &PrivateKey) -> BuckyResult<AesKey> {
+        let item = self
+            .recipients()
+            .iter()
+            .find(|v| v.recipient == *recipient)
+            .ok_or_else(|| {
+                let msg = format!(
+                    "not recipient of sealed object! obj={}, recipient={}",
+                    self.desc().calculate_id(),
+                    recipient
+                );
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::PermissionDenied, msg)
+            })?;
+
+        let (encrypted_key, exchange_key) = secret.decrypt_aeskey_data(&item.encrypted_key)?;
+        let exchange_key = AesKey::from(exchange_key);
+        let key = unseal_data(&exchange_key, encrypted_key)?;
+        if key.len() != AesKey::raw_bytes().unwrap() {
+            let msg = format!(
+                "invalid sealed object key! obj={}, recipient={}, len={}",
+                self.desc().calculate_id(),
+                recipient,
+                key.len()
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidData, msg));
+        }
+
+        Ok(AesKey::from(key))
+    }
+
+    fn open(&self, key: &AesKey) -> BuckyResult<Vec<u8>> {
+        unseal_data(key, &self.desc().content().data)
+    }
+}
+
+// 每段密文开头的iv长度
+const SEALED_IV_LEN: usize = 16;
+
+// 长度为len的明文加密后的长度
+pub fn sealed_data_len(len: usize) -> usize {
+    SEALED_IV_LEN + AesKey::padded_len(len)
+}
+
+// 内容密钥的aes key部分加上iv
+fn sealed_key(key: &AesKey, iv: &[u8]) -> AesKey {
+    let mut ret = key.clone();
+    ret.as_mut_slice()[32..].copy_from_slice(iv);
+    ret
+}
+
+// 使用内容密钥加密一段数据，加密file时每个chunk单独调用
+pub fn seal_data(key: &AesKey, data: &[u8]) -> BuckyResult<Vec<u8>> {
+    let iv = AesKey::random().as_slice()[32..].to_vec();
+
+    let mut buf = vec![0u8; sealed_data_len(data.len())];
+    buf[..SEALED_IV_LEN].copy_from_slice(&iv);
+    buf[SEALED_IV_LEN..SEALED_IV_LEN + data.len()].copy_from_slice(data);
+
+    let len = sealed_key(key, &iv).inplace_encrypt(&mut buf[SEALED_IV_LEN..], data.len())?;
+    buf.truncate(SEALED_IV_LEN + len);
+
+    Ok(buf)
+}
+
+pub fn unseal_data(key: &AesKey, data: &[u8]) -> BuckyResult<Vec<u8>> {
+    if data.len() < SEALED_IV_LEN {
+        let msg = format!("invalid sealed data len: {}", data.len());
+        error!("{}", msg);
+        return Err(BuckyError::new(BuckyErrorCode::InvalidData, msg));
+    }
+
+    let (iv, data) = data.split_at(SEALED_IV_LEN);
+    let mut buf = data.to_vec();
+
+    let len = sealed_key(key, iv).inplace_decrypt(&mut buf, data.len())?;
+    buf.truncate(len);
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_seal() {
+        let rsa = PrivateKey::generate_rsa(1024).unwrap();
+        let secp = PrivateKey::generate_secp256k1().unwrap();
+        let rsa_id = ObjectId::default();
+        let secp_id = ObjectId::from_str("5aSixgPJLRApy31v15U8mM7cVSndc8kjbECXSfP9o6Ef").unwrap();
+        let recipients = vec![
+            (rsa_id.clone(), rsa.public()),
+            (secp_id.clone(), secp.public()),
+        ];
+
+        let data = b"sealed object data".to_vec();
+        let (obj, key) = SealedObject::seal(None, &recipients, &data, None).unwrap();
+        let obj = SealedObject::clone_from_slice(&obj.to_vec().unwrap()).unwrap();
+
+        let rsa_key = obj.open_key(&rsa_id, &rsa).unwrap();
+        assert_eq!(rsa_key, key);
+        assert_eq!(obj.open(&rsa_key).unwrap(), data);
+
+        let secp_key = obj.open_key(&secp_id, &secp).unwrap();
+        assert_eq!(secp_key, key);
+
+        assert!(obj.is_recipient(&secp_id));
+        assert!(obj.open_key(&secp_id, &rsa).is_err());
+
+        let chunk = vec![7u8; 4096];
+        let sealed = seal_data(&key, &chunk).unwrap();
+        assert_eq!(sealed.len(), sealed_data_len(4096));
+        assert_eq!(sealed.len(), 4096 + 32);
+        assert_eq!(unseal_data(&key, &sealed).unwrap(), chunk);
+
+        // 相同的明文每次加密的结果都不同
+        assert_ne!(seal_data(&key, &chunk).unwrap(), sealed);
+        assert!(unseal_data(&key, &sealed[..8]).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+mod ood_epoch;
 mod zone;
 
+pub use ood_epoch::*;
 pub use zone::*;
\ No newline at end of file
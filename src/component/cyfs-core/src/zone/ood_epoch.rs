@@ -0,0 +1,113 @@
+use crate::codec::*;
+use crate::coreobj::CoreObjectType;
+use cyfs_base::*;
+use serde::Serialize;
+
+// active/standby模式下，zone当前的主ood
+// epoch基于owner对象的某个版本(owner_update_time)，从0开始单调递增，由接任的主ood签名
+#[derive(Debug, Clone, ProtobufEncode, ProtobufDecode, ProtobufTransform, Serialize)]
+#[cyfs_protobuf_type(crate::codec::protos::ZoneOodEpochDescContent)]
+pub struct ZoneOODEpochDescContent {
+    owner: ObjectId,
+    owner_update_time: u64,
+    epoch: u64,
+    active_ood: ObjectId,
+}
+
+impl DescContent for ZoneOODEpochDescContent {
+    fn obj_type() -> u16 {
+        CoreObjectType::ZoneOODEpoch as u16
+    }
+
+    fn format(&self) -> u8 {
+        OBJECT_CONTENT_CODEC_FORMAT_PROTOBUF
+    }
+
+    type OwnerType = Option<ObjectId>;
+    type AreaType = SubDescNone;
+    type AuthorType = SubDescNone;
+    type PublicKeyType = SubDescNone;
+}
+
+#[derive(Clone, Default, ProtobufEmptyEncode, ProtobufEmptyDecode, Serialize)]
+pub struct ZoneOODEpochBody {}
+
+impl BodyContent for ZoneOODEpochBody {
+    fn format(&self) -> u8 {
+        OBJECT_CONTENT_CODEC_FORMAT_PROTOBUF
+    }
+}
+
+type ZoneOODEpochType = NamedObjType<ZoneOODEpochDescContent, ZoneOODEpochBody>;
+type ZoneOODEpochBuilder = NamedObjectBuilder<ZoneOODEpochDescContent, ZoneOODEpochBody>;
+
+pub type ZoneOODEpochId = NamedObjectId<ZoneOODEpochType>;
+pub type ZoneOODEpoch = NamedObjectBase<ZoneOODEpochType>;
+
+pub trait ZoneOODEpochObj {
+    fn create(owner: ObjectId, owner_update_time: u64, epoch: u64, active_ood: DeviceId) -> Self;
+
+    fn owner(&self) -> &ObjectId;
+    fn owner_update_time(&self) -> u64;
+    fn epoch(&self) -> u64;
+    fn active_ood(&self) -> BuckyResult<DeviceId>;
+}
+
+// 不带create_time，同样的参数得到同样的id，其它设备可以直接算出某个epoch的对象id去查询
+impl ZoneOODEpochObj for ZoneOODEpoch {
+    fn create(owner: ObjectId, owner_update_time: u64, epoch: u64, active_ood: DeviceId) -> Self {
+        let desc = ZoneOODEpochDescContent {
+            owner: owner.clone(),
+            owner_update_time,
+            epoch,
+            active_ood: active_ood.object_id().to_owned(),
+        };
+
+        ZoneOODEpochBuilder::new(desc, ZoneOODEpochBody {})
+            .owner(owner)
+            .no_create_time()
+            .build()
+    }
+
+    fn owner(&self) -> &ObjectId {
+        &self.desc().content().owner
+    }
+
+    fn owner_update_time(&self) -> u64 {
+        self.desc().content().owner_update_time
+    }
+
+    fn epoch(&self) -> u64 {
+        self.desc().content().epoch
+    }
+
+    fn active_ood(&self) -> BuckyResult<DeviceId> {
+        DeviceId::try_from(&self.desc().content().active_ood)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use cyfs_base::*;
+
+    use std::str::FromStr;
+
+    #[test]
+    fn test() {
+        let owner = ObjectId::from_str("5aSixgLtjoYcAFH9isc6KCqDgKfTJ8jpgASAoiRz5NLk").unwrap();
+        let ood = DeviceId::from_str("5aSixgPXvhR4puWzFCHqvUXrjFWjxbq4y3thJVgZg6ty").unwrap();
+
+        let epoch = ZoneOODEpoch::create(owner.clone(), 100, 3, ood.clone());
+        let buf = epoch.to_vec().unwrap();
+        let epoch2 = ZoneOODEpoch::clone_from_slice(&buf).unwrap();
+
+        assert_eq!(epoch2.owner(), &owner);
+        assert_eq!(epoch2.owner_update_time(), 100);
+        assert_eq!(epoch2.epoch(), 3);
+        assert_eq!(epoch2.active_ood().unwrap(), ood);
+
+        let epoch3 = ZoneOODEpoch::create(owner, 100, 3, ood);
+        assert_eq!(epoch3.desc().calculate_id(), epoch.desc().calculate_id());
+    }
+}
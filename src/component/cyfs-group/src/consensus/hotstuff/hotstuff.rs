@@ -232,6 +232,12 @@ impl Hotstuff {
         self.state_pusher.request_last_state(remote).await;
     }
 
+    pub async fn set_state_subscriber_allow_list(&self, allow_list: Vec<ObjectId>) {
+        self.state_pusher
+            .set_subscriber_allow_list(allow_list)
+            .await;
+    }
+
     pub async fn on_query_state(&self, sub_path: String, remote: ObjectId) {
         let msg = format!(
             "[hotstuff] local: {:?}, on_query_state: sub_path: {}, remote: {:?}.",
@@ -19,6 +19,9 @@ pub const SYNCHRONIZER_TIMEOUT: u64 = 500;
 pub const SYNCHRONIZER_TRY_TIMES: usize = 3;
 pub const CLIENT_POLL_TIMEOUT: Duration = Duration::from_millis(5000);
 pub const STATE_NOTIFY_COUNT_PER_ROUND: usize = 8;
+pub const STATE_SUBSCRIBE_INTERVAL: Duration = Duration::from_secs(300); // the non-member devices renew the subscription for state
+pub const STATE_SUBSCRIBE_EXPIRE: Duration = Duration::from_secs(900);
+pub const STATE_SUBSCRIBERS_LIMIT: usize = 64; // the max count of non-member devices subscribed the state
 pub const NET_PROTOCOL_VPORT: u16 = 2048;
 pub const MEMORY_CACHE_SIZE: usize = 1024;
 pub const MEMORY_CACHE_DURATION: Duration = Duration::from_secs(300);
@@ -2,8 +2,8 @@ use std::{collections::HashMap, sync::Arc};
 
 use async_std::sync::RwLock;
 use cyfs_base::{
-    BuckyErrorCode, BuckyResult, GroupId, NamedObject, ObjectDesc, ObjectId, OwnerObjectDesc,
-    RawConvertTo, RawFrom, RsaCPUObjectSigner, TypelessCoreObject,
+    BuckyError, BuckyErrorCode, BuckyResult, GroupId, NamedObject, ObjectDesc, ObjectId,
+    OwnerObjectDesc, RawConvertTo, RawFrom, RsaCPUObjectSigner, TypelessCoreObject,
 };
 use cyfs_bdt::{DatagramTunnelGuard, StackGuard};
 use cyfs_core::{DecAppId, GroupConsensusBlock, GroupConsensusBlockObject, GroupRPath};
@@ -102,18 +102,8 @@ impl GroupManager {
         dec_id: &ObjectId,
         rpath: &str,
     ) -> BuckyResult<RPathClient> {
-        {
-            // read
-            let raw = self.read().await;
-            let found = raw
-                .client_by_group
-                .get(group_id)
-                .map_or(None, |by_dec| by_dec.get(dec_id))
-                .map_or(None, |by_rpath| by_rpath.get(rpath));
-
-            if let Some(found) = found {
-                return Ok(found.clone());
-            }
+        if let Some(found) = self.find_rpath_client(group_id, dec_id, rpath).await {
+            return Ok(found);
         }
 
         {
@@ -169,8 +159,16 @@ impl GroupManager {
         }
     }
 
-    pub async fn set_sync_path(&self, _dec_id: &str, _path: String) -> BuckyResult<()> {
-        unimplemented!()
+    // mirror the state of the rpath at the sub-path to local global state, it's used for the non-member devices.
+    pub async fn set_sync_path(
+        &self,
+        group_id: &ObjectId,
+        dec_id: &ObjectId,
+        rpath: &str,
+        sub_path: String,
+    ) -> BuckyResult<()> {
+        let client = self.rpath_client(group_id, dec_id, rpath).await?;
+        client.set_sync_path(sub_path).await
     }
 
     // return Vec<GroupId>
@@ -186,6 +184,21 @@ impl GroupManager {
         unimplemented!()
     }
 
+    // only the clients created by the local dec, the message from remote should not create any client
+    async fn find_rpath_client(
+        &self,
+        group_id: &ObjectId,
+        dec_id: &ObjectId,
+        rpath: &str,
+    ) -> Option<RPathClient> {
+        let raw = self.read().await;
+        raw.client_by_group
+            .get(group_id)
+            .map_or(None, |by_dec| by_dec.get(dec_id))
+            .map_or(None, |by_rpath| by_rpath.get(rpath))
+            .cloned()
+    }
+
     fn local_info(&self) -> &LocalInfo {
         &self.0 .0
     }
@@ -205,7 +218,7 @@ impl GroupManager {
     ) -> BuckyResult<()> {
         match msg {
             HotstuffPackage::Block(block) => {
-                let rpath = block.rpath();
+                let rpath = block.rpath().clone();
                 let service = self
                     .find_rpath_service_inner(
                         rpath.group_id(),
@@ -215,19 +228,40 @@ impl GroupManager {
                         Some(&block),
                         Some(&remote),
                     )
-                    .await
-                    .map_err(|err| {
-                        log::error!(
-                            "new msg(Block) received, and find rpath service failed, {:?}. local: {}, err: {:?}",
+                    .await;
+
+                match service {
+                    Ok(service) => {
+                        service
+                            .on_message(HotstuffMessage::Block(block), remote)
+                            .await;
+                    }
+                    Err(err) => {
+                        log::debug!(
+                            "new msg(Block) received, and find rpath service failed, will try deliver it to client, {:?}. local: {}, err: {:?}",
                             rpath,
                             self.local_info().bdt_stack.local_device_id(),
                             err
                         );
-                        err
-                    })?;
-                service
-                    .on_message(HotstuffMessage::Block(block), remote)
-                    .await;
+
+                        let client = self
+                            .find_rpath_client(rpath.group_id(), rpath.dec_id(), rpath.rpath())
+                            .await
+                            .ok_or_else(|| {
+                                let msg = format!(
+                                    "new msg(Block) received, and no rpath client for it, {:?}. local: {}, remote: {}",
+                                    rpath,
+                                    self.local_info().bdt_stack.local_device_id(),
+                                    remote
+                                );
+                                log::warn!("{}", msg);
+                                BuckyError::new(BuckyErrorCode::NotFound, msg)
+                            })?;
+                        client
+                            .on_message(HotstuffMessage::Block(block), remote)
+                            .await;
+                    }
+                }
             }
             HotstuffPackage::BlockVote(target, vote) => {
                 let rpath = target.check_rpath();
@@ -354,18 +388,33 @@ impl GroupManager {
                     .on_message(HotstuffMessage::LastStateRequest, remote)
                     .await;
             }
-            HotstuffPackage::StateChangeNotify(_header_block, _qc_block) => {
-                // TODO: unimplemented
-                // let rpath = header_block.rpath();
-                // let client = self
-                //     .rpath_client(rpath.group_id(), rpath.dec_id(), rpath.rpath())
-                //     .await?;
-                // client
-                //     .on_message(
-                //         HotstuffMessage::StateChangeNotify(header_block, qc_block),
-                //         remote,
-                //     )
-                //     .await;
+            HotstuffPackage::StateChangeNotify(header_block, qc_block) => {
+                // only the client exist will receive the notify, it's created by `set_sync_path` or the app
+                let rpath = header_block.rpath();
+                let client = {
+                    let raw = self.read().await;
+                    raw.client_by_group
+                        .get(rpath.group_id())
+                        .map_or(None, |by_dec| by_dec.get(rpath.dec_id()))
+                        .map_or(None, |by_rpath| by_rpath.get(rpath.rpath()))
+                        .cloned()
+                };
+
+                match client {
+                    Some(client) => {
+                        client
+                            .on_message(
+                                HotstuffMessage::StateChangeNotify(header_block, qc_block),
+                                remote,
+                            )
+                            .await
+                    }
+                    None => log::debug!(
+                        "new msg(StateChangeNotify) received, and no rpath client, {:?}. local: {}",
+                        rpath,
+                        self.local_info().bdt_stack.local_device_id()
+                    ),
+                }
             }
             HotstuffPackage::ProposalResult(_proposal_id, _result) => {
                 // TODO: unimplemented
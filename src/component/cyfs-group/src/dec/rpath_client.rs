@@ -1,17 +1,23 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
 use cyfs_base::{
     BuckyError, BuckyErrorCode, BuckyResult, GroupMemberScope, NamedObject, ObjectDesc, ObjectId,
     RawConvertTo,
 };
-use cyfs_core::{GroupConsensusBlock, GroupProposal, GroupProposalObject, GroupRPath};
+use cyfs_core::{
+    GroupConsensusBlock, GroupConsensusBlockObject, GroupProposal, GroupProposalObject, GroupRPath,
+};
+use cyfs_group_lib::GroupRPathStatus;
 use cyfs_lib::{GlobalStateRawProcessorRef, NONObjectInfo};
 use rand::Rng;
 
 use crate::{
-    dec_state::{DecStateRequestor, DecStateSynchronizer},
+    dec_state::{CallReplyWaiter, DecStateRequestor, DecStateSynchronizer},
     storage::{DecStorage, GroupShellManager},
-    Committee, HotstuffMessage, CLIENT_POLL_TIMEOUT,
+    Committee, HotstuffMessage, SyncBound, CLIENT_POLL_TIMEOUT, STATE_SUBSCRIBE_INTERVAL,
 };
 
 struct RPathClientRaw {
@@ -22,6 +28,9 @@ struct RPathClientRaw {
     network_sender: crate::network::Sender,
     state_sync: DecStateSynchronizer,
     state_requestor: DecStateRequestor,
+    committee: Committee,
+    dec_store: DecStorage,
+    is_subscribed: AtomicBool,
 }
 
 #[derive(Clone)]
@@ -36,7 +45,8 @@ impl RPathClient {
         shell_mgr: GroupShellManager,
         network_sender: crate::network::Sender,
     ) -> BuckyResult<Self> {
-        let dec_store = DecStorage::load(state_processor).await?;
+        let dec_store =
+            DecStorage::load(state_processor, rpath.clone(), non_driver.clone()).await?;
         let committee = Committee::new(
             rpath.group_id().clone(),
             non_driver.clone(),
@@ -56,13 +66,15 @@ impl RPathClient {
         let state_requestor = DecStateRequestor::new(
             local_device_id,
             rpath.clone(),
-            committee,
+            committee.clone(),
             network_sender.clone(),
             non_driver.clone(),
             dec_store.clone(),
         );
 
         let raw = RPathClientRaw {
+            dec_store,
+            is_subscribed: AtomicBool::new(false),
             rpath,
             non_driver,
             network_sender,
@@ -70,6 +82,7 @@ impl RPathClient {
             state_sync,
             state_requestor,
             shell_mgr,
+            committee,
         };
 
         Ok(Self(Arc::new(raw)))
@@ -168,7 +181,64 @@ impl RPathClient {
         Ok(())
     }
 
-    pub async fn get_by_path(&self, sub_path: &str) -> BuckyResult<Option<NONObjectInfo>> {
+    // mirror the state at the sub-path to the global state of local device,
+    // the members will push the new state to the local device, and it will be verified with the QC.
+    pub async fn set_sync_path(&self, sub_path: String) -> BuckyResult<()> {
+        if !self.0.dec_store.add_sync_path(sub_path).await {
+            return Ok(());
+        }
+
+        if !self.0.is_subscribed.swap(true, Ordering::SeqCst) {
+            let client = self.clone();
+            async_std::task::spawn(async move {
+                loop {
+                    if let Err(err) = client.subscribe_state().await {
+                        log::warn!(
+                            "subscribe state for {:?} failed, err: {:?}",
+                            client.0.rpath,
+                            err
+                        );
+                    }
+                    async_std::task::sleep(STATE_SUBSCRIBE_INTERVAL).await;
+                }
+            });
+        } else {
+            self.subscribe_state().await?;
+        }
+
+        Ok(())
+    }
+
+    // the state is pushed by the leader, so subscribe to all the oods
+    async fn subscribe_state(&self) -> BuckyResult<()> {
+        let group = self
+            .0
+            .shell_mgr
+            .get_group(self.0.rpath.group_id(), None, None)
+            .await?;
+
+        let oods: Vec<ObjectId> = group
+            .ood_list_with_distance(&self.0.local_device_id)
+            .into_iter()
+            .map(|id| id.clone())
+            .collect();
+        self.0
+            .network_sender
+            .broadcast(
+                HotstuffMessage::LastStateRequest,
+                self.0.rpath.clone(),
+                oods.as_slice(),
+            )
+            .await;
+        Ok(())
+    }
+
+    // the value is returned with the proof(the ObjectMap path from the root state to the value, and the block header signed by the QC),
+    // and it has been verified with the members of the group.
+    pub async fn get_by_path(
+        &self,
+        sub_path: &str,
+    ) -> BuckyResult<(Option<NONObjectInfo>, GroupRPathStatus)> {
         let group = self
             .0
             .shell_mgr
@@ -217,13 +287,135 @@ impl RPathClient {
         Err(err)
     }
 
-    pub async fn get_block(&self, _height: Option<u64>) -> BuckyResult<GroupConsensusBlock> {
-        unimplemented!()
+    // get the committed block at the height, or the header block if the height is None
+    pub async fn get_block(&self, height: Option<u64>) -> BuckyResult<GroupConsensusBlock> {
+        // the header block is signed by the QC in the verifiable state
+        let (_, header_status) = self.get_by_path("").await?;
+        let header_height = header_status.block_desc.content().height();
+        let height = Self::committed_height(height, header_height)?;
+
+        let group = self
+            .0
+            .shell_mgr
+            .get_group(self.0.rpath.group_id(), None, None)
+            .await?;
+        let members =
+            group.select_members_with_distance(&self.0.local_device_id, GroupMemberScope::All);
+
+        // the block is signed by the QC in the next block
+        let max_height = std::cmp::min(height + 1, header_height);
+        let waiter = self.0.state_requestor.wait_block(height).await;
+        let next_waiter = if max_height > height {
+            Some(self.0.state_requestor.wait_block(max_height).await)
+        } else {
+            None
+        };
+
+        let mut exe_result = None;
+
+        for member in members {
+            self.0
+                .network_sender
+                .post_message(
+                    HotstuffMessage::SyncRequest(
+                        SyncBound::Height(height),
+                        SyncBound::Height(max_height),
+                    ),
+                    self.0.rpath.clone(),
+                    member,
+                )
+                .await;
+
+            let (block, remote) = match Self::wait_block(&waiter).await {
+                Some(block) => block,
+                None => continue,
+            };
+
+            match self
+                .verify_block(&block, remote, &header_status, next_waiter.as_ref())
+                .await
+            {
+                Ok(_) => return Ok(block),
+                Err(e) => exe_result = Some(e),
+            }
+        }
+
+        let err = exe_result.map_or(BuckyError::new(BuckyErrorCode::Timeout, "timeout"), |e| e);
+        Err(err)
+    }
+
+    // the blocks higher than the verified header are not committed
+    fn committed_height(height: Option<u64>, header_height: u64) -> BuckyResult<u64> {
+        let height = height.unwrap_or(header_height);
+        if height > header_height {
+            let msg = format!(
+                "the block is not committed, height: {}, header: {}",
+                height, header_height
+            );
+            log::warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+        }
+        Ok(height)
+    }
+
+    async fn wait_block(
+        waiter: &CallReplyWaiter<(GroupConsensusBlock, ObjectId)>,
+    ) -> Option<(GroupConsensusBlock, ObjectId)> {
+        match async_std::future::timeout(CLIENT_POLL_TIMEOUT, waiter.wait()).await {
+            Ok(Ok(block)) => Some(block),
+            _ => None,
+        }
+    }
+
+    async fn verify_block(
+        &self,
+        block: &GroupConsensusBlock,
+        remote: ObjectId,
+        header_status: &GroupRPathStatus,
+        next_waiter: Option<&CallReplyWaiter<(GroupConsensusBlock, ObjectId)>>,
+    ) -> BuckyResult<()> {
+        let block_id = block.block_id().object_id();
+        let next_waiter = match next_waiter {
+            Some(next_waiter) => next_waiter,
+            None => {
+                // it's the header block
+                if block_id != &header_status.block_desc.object_id() {
+                    let msg = format!(
+                        "the header block is unmatch, expect: {}, got: {}, remote: {}",
+                        header_status.block_desc.object_id(),
+                        block_id,
+                        remote
+                    );
+                    log::warn!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+                }
+                return Ok(());
+            }
+        };
+
+        let (next_block, next_remote) = Self::wait_block(next_waiter)
+            .await
+            .ok_or_else(|| BuckyError::new(BuckyErrorCode::Timeout, "wait next block timeout"))?;
+
+        let qc = next_block.qc().as_ref().ok_or_else(|| {
+            let msg = format!(
+                "the next block({}) has no qc for block({})",
+                next_block.block_id(),
+                block_id
+            );
+            log::warn!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidData, msg)
+        })?;
+
+        self.0
+            .committee
+            .verify_block_desc_with_qc(block.named_object().desc(), qc, next_remote)
+            .await
     }
 
     pub(crate) async fn on_message(&self, msg: HotstuffMessage, remote: ObjectId) {
         match msg {
-            HotstuffMessage::Block(_block) => unreachable!(),
+            HotstuffMessage::Block(block) => self.0.state_requestor.on_block(block, remote).await,
             HotstuffMessage::BlockVote(_vote) => unreachable!(),
             HotstuffMessage::TimeoutVote(_vote) => unreachable!(),
             HotstuffMessage::Timeout(_tc) => unreachable!(),
@@ -256,3 +448,22 @@ impl RPathClient {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use cyfs_base::BuckyErrorCode;
+
+    use super::RPathClient;
+
+    #[test]
+    fn test_committed_height() {
+        assert_eq!(RPathClient::committed_height(None, 10).unwrap(), 10);
+        assert_eq!(RPathClient::committed_height(Some(3), 10).unwrap(), 3);
+        assert_eq!(
+            RPathClient::committed_height(Some(11), 10)
+                .unwrap_err()
+                .code(),
+            BuckyErrorCode::NotFound
+        );
+    }
+}
@@ -98,6 +98,15 @@ impl RPathService {
         )
     }
 
+    // allow the non-member devices(or the devices of the owners in the list) to subscribe the state, such as the auditors.
+    // the devices of the members are always allowed.
+    pub async fn set_state_subscriber_allow_list(&self, allow_list: Vec<ObjectId>) {
+        self.0
+            .hotstuff
+            .set_state_subscriber_allow_list(allow_list)
+            .await
+    }
+
     pub fn select_branch(&self, _block_id: ObjectId, _source: ObjectId) -> BuckyResult<()> {
         unimplemented!()
     }
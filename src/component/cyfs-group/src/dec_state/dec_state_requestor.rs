@@ -2,8 +2,10 @@
 
 use std::sync::Arc;
 
-use cyfs_base::{BuckyResult, ObjectId};
-use cyfs_core::GroupRPath;
+use cyfs_base::{BuckyError, BuckyErrorCode, BuckyResult, ObjectId};
+use cyfs_core::{
+    GroupConsensusBlock, GroupConsensusBlockDescContent, GroupConsensusBlockObject, GroupRPath,
+};
 use cyfs_group_lib::GroupRPathStatus;
 use cyfs_lib::NONObjectInfo;
 use futures::FutureExt;
@@ -15,12 +17,17 @@ use super::{CallReplyNotifier, CallReplyWaiter};
 enum DecStateRequestorMessage {
     QueryState(String),                                     // sub-path
     VerifiableState(String, BuckyResult<GroupRPathStatus>), // (sub-path, result)
+    Block(GroupConsensusBlock),
 }
 
+// (value, proof)
+pub(crate) type VerifiableValue = (Option<NONObjectInfo>, GroupRPathStatus);
+
 struct DecStateRequestorRaw {
     local_device_id: ObjectId,
     tx_dec_state_req_message: async_std::channel::Sender<(DecStateRequestorMessage, ObjectId)>,
-    query_state_notifier: CallReplyNotifier<String, BuckyResult<Option<NONObjectInfo>>>,
+    query_state_notifier: CallReplyNotifier<String, BuckyResult<VerifiableValue>>,
+    block_notifier: CallReplyNotifier<u64, (GroupConsensusBlock, ObjectId)>,
 }
 
 #[derive(Clone)]
@@ -37,6 +44,7 @@ impl DecStateRequestor {
     ) -> Self {
         let (tx, rx) = async_std::channel::bounded(CHANNEL_CAPACITY);
         let notifier = CallReplyNotifier::new();
+        let block_notifier = CallReplyNotifier::new();
 
         let mut runner = DecStateRequestorRunner::new(
             local_device_id,
@@ -47,6 +55,7 @@ impl DecStateRequestor {
             network_sender,
             non_driver,
             notifier.clone(),
+            block_notifier.clone(),
         );
 
         async_std::task::spawn(async move { runner.run().await });
@@ -55,16 +64,25 @@ impl DecStateRequestor {
             local_device_id,
             tx_dec_state_req_message: tx,
             query_state_notifier: notifier,
+            block_notifier,
         }))
     }
 
     pub async fn wait_query_state(
         &self,
         sub_path: String,
-    ) -> CallReplyWaiter<BuckyResult<Option<NONObjectInfo>>> {
+    ) -> CallReplyWaiter<BuckyResult<VerifiableValue>> {
         self.0.query_state_notifier.prepare(sub_path).await
     }
 
+    // wait the block at the height, it should be verified by the caller
+    pub async fn wait_block(
+        &self,
+        height: u64,
+    ) -> CallReplyWaiter<(GroupConsensusBlock, ObjectId)> {
+        self.0.block_notifier.prepare(height).await
+    }
+
     pub async fn on_query_state(&self, sub_path: String, remote: ObjectId) {
         if let Err(err) = self
             .0
@@ -97,6 +115,18 @@ impl DecStateRequestor {
             log::warn!("post verifiable state command to processor failed will ignore it, sub_path: {}, rmote: {}, err: {:?}", sub_path, remote, err);
         }
     }
+
+    pub async fn on_block(&self, block: GroupConsensusBlock, remote: ObjectId) {
+        let block_id = block.block_id().clone();
+        if let Err(err) = self
+            .0
+            .tx_dec_state_req_message
+            .send((DecStateRequestorMessage::Block(block), remote))
+            .await
+        {
+            log::warn!("post block command to processor failed will ignore it, block: {}, rmote: {}, err: {:?}", block_id, remote, err);
+        }
+    }
 }
 
 struct DecStateRequestorRunner {
@@ -109,7 +139,11 @@ struct DecStateRequestorRunner {
 
     network_sender: crate::network::Sender,
     non_driver: crate::network::NONDriverHelper,
-    query_state_notifier: CallReplyNotifier<String, BuckyResult<Option<NONObjectInfo>>>,
+    query_state_notifier: CallReplyNotifier<String, BuckyResult<VerifiableValue>>,
+    block_notifier: CallReplyNotifier<u64, (GroupConsensusBlock, ObjectId)>,
+
+    // the max height verified, the older state will be rejected
+    max_verified_height: u64,
 }
 
 impl DecStateRequestorRunner {
@@ -124,7 +158,8 @@ impl DecStateRequestorRunner {
         store: DecStorage,
         network_sender: crate::network::Sender,
         non_driver: crate::network::NONDriverHelper,
-        query_state_notifier: CallReplyNotifier<String, BuckyResult<Option<NONObjectInfo>>>,
+        query_state_notifier: CallReplyNotifier<String, BuckyResult<VerifiableValue>>,
+        block_notifier: CallReplyNotifier<u64, (GroupConsensusBlock, ObjectId)>,
     ) -> Self {
         Self {
             local_device_id,
@@ -133,6 +168,8 @@ impl DecStateRequestorRunner {
             // timer: Timer::new(SYNCHRONIZER_TIMEOUT),
             store,
             query_state_notifier,
+            block_notifier,
+            max_verified_height: 0,
             network_sender,
             non_driver,
             committee,
@@ -161,7 +198,7 @@ impl DecStateRequestorRunner {
                 let result = self
                     .check_sub_path_value(sub_path.as_str(), &result, &remote)
                     .await
-                    .map(|r| r.cloned());
+                    .map(|value| (value.cloned(), result.clone()));
 
                 log::debug!(
                     "handle_verifiable_state sub_path: {}, result: {:?}",
@@ -175,11 +212,14 @@ impl DecStateRequestorRunner {
     }
 
     async fn check_sub_path_value<'a>(
-        &self,
+        &mut self,
         sub_path: &str,
         verifiable_status: &'a GroupRPathStatus,
         remote: &ObjectId,
     ) -> BuckyResult<Option<&'a NONObjectInfo>> {
+        let desc = verifiable_status.block_desc.content();
+        Self::check_status_desc(&self.rpath, self.max_verified_height, desc, remote)?;
+
         self.committee
             .verify_block_desc_with_qc(
                 &verifiable_status.block_desc,
@@ -188,8 +228,58 @@ impl DecStateRequestorRunner {
             )
             .await?;
 
-        self.store
+        let value = self
+            .store
             .check_sub_path_value(sub_path, verifiable_status)
+            .await?;
+
+        self.max_verified_height = desc.height();
+        Ok(value)
+    }
+
+    // the state should be for this rpath, and not older than the state verified before
+    fn check_status_desc(
+        rpath: &GroupRPath,
+        max_verified_height: u64,
+        desc: &GroupConsensusBlockDescContent,
+        remote: &ObjectId,
+    ) -> BuckyResult<()> {
+        if desc.rpath() != rpath {
+            let msg = format!(
+                "the rpath of verifiable state is unmatch, expect: {:?}, got: {:?}",
+                rpath,
+                desc.rpath()
+            );
+            log::warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+        }
+
+        if desc.height() < max_verified_height {
+            let msg = format!(
+                "the verifiable state is expired, height: {}, verified: {}, remote: {}",
+                desc.height(),
+                max_verified_height,
+                remote
+            );
+            log::warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Expired, msg));
+        }
+
+        Ok(())
+    }
+
+    async fn handle_block(&mut self, block: GroupConsensusBlock, remote: ObjectId) {
+        if block.rpath() != &self.rpath || !block.check() {
+            log::warn!(
+                "handle_block ignore the invalid block: {}, remote: {}",
+                block.block_id(),
+                remote
+            );
+            return;
+        }
+
+        self.block_notifier
+            .reply(&block.height(), (block, remote))
             .await
     }
 
@@ -199,6 +289,7 @@ impl DecStateRequestorRunner {
                 message = self.rx_dec_state_req_message.recv().fuse() => match message {
                     Ok((DecStateRequestorMessage::QueryState(sub_path), remote)) => self.handle_query_state(sub_path, remote).await,
                     Ok((DecStateRequestorMessage::VerifiableState(sub_path, result), remote)) => self.handle_verifiable_state(sub_path, result, remote).await,
+                    Ok((DecStateRequestorMessage::Block(block), remote)) => self.handle_block(block, remote).await,
                     Err(e) => {
                         log::warn!("[dec-state-sync] rx closed, err: {:?}.", e);
                     },
@@ -208,3 +299,44 @@ impl DecStateRequestorRunner {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use cyfs_base::{BuckyErrorCode, NamedObject, ObjectId};
+    use cyfs_core::{GroupConsensusBlock, GroupConsensusBlockObject, GroupRPath};
+
+    use super::DecStateRequestorRunner;
+
+    fn new_block(rpath: &str, height: u64) -> GroupConsensusBlock {
+        GroupConsensusBlock::create(
+            GroupRPath::new(ObjectId::default(), ObjectId::default(), rpath.to_string()),
+            vec![],
+            None,
+            height,
+            ObjectId::default(),
+            height,
+            ObjectId::default(),
+            None,
+            None,
+            ObjectId::default(),
+        )
+    }
+
+    #[test]
+    fn test_check_status_desc() {
+        let rpath = GroupRPath::new(ObjectId::default(), ObjectId::default(), "a".to_string());
+        let remote = ObjectId::default();
+
+        let block = new_block("a", 5);
+        let desc = block.named_object().desc().content();
+        assert!(DecStateRequestorRunner::check_status_desc(&rpath, 5, desc, &remote).is_ok());
+
+        let err = DecStateRequestorRunner::check_status_desc(&rpath, 6, desc, &remote).unwrap_err();
+        assert_eq!(err.code(), BuckyErrorCode::Expired);
+
+        let block = new_block("b", 5);
+        let desc = block.named_object().desc().content();
+        let err = DecStateRequestorRunner::check_status_desc(&rpath, 0, desc, &remote).unwrap_err();
+        assert_eq!(err.code(), BuckyErrorCode::Unmatch);
+    }
+}
@@ -1,9 +1,13 @@
 // notify the members when the state of rpath changed
 
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
 
 use cyfs_base::{
-    BuckyError, GroupMemberScope, NamedObject, ObjectDesc, ObjectId, OwnerObjectDesc, RawDecode,
+    BuckyError, Group, GroupMemberScope, NamedObject, ObjectDesc, ObjectId, OwnerObjectDesc,
+    RawDecode,
 };
 use cyfs_core::{GroupConsensusBlock, GroupConsensusBlockObject, GroupProposal, GroupRPath};
 use cyfs_lib::NONObjectInfo;
@@ -11,12 +15,14 @@ use futures::FutureExt;
 
 use crate::{
     storage::GroupShellManager, HotstuffMessage, CHANNEL_CAPACITY, STATE_NOTIFY_COUNT_PER_ROUND,
+    STATE_SUBSCRIBERS_LIMIT, STATE_SUBSCRIBE_EXPIRE,
 };
 
 enum StatePushMessage {
     ProposalResult(GroupProposal, BuckyError),
     BlockCommit(GroupConsensusBlock, GroupConsensusBlock), // <header, qc>
     LastStateRequest(ObjectId),
+    SetSubscriberAllowList(HashSet<ObjectId>),
     DelayBroadcast,
 }
 
@@ -105,6 +111,19 @@ impl StatePusher {
         }
     }
 
+    // the non-member devices(or their owners) allowed to subscribe the state, such as the auditors
+    pub async fn set_subscriber_allow_list(&self, allow_list: Vec<ObjectId>) {
+        if let Err(err) = self
+            .tx_notifier
+            .send(StatePushMessage::SetSubscriberAllowList(
+                allow_list.into_iter().collect(),
+            ))
+            .await
+        {
+            log::warn!("post subscriber allow-list failed, err: {:?}", err);
+        }
+    }
+
     pub async fn request_last_state(&self, remote: ObjectId) {
         if let Err(err) = self
             .tx_notifier
@@ -140,6 +159,10 @@ struct StateChanggeRunner {
     delay_notify_times: usize,
     // timer: Timer,
     request_last_state_remotes: HashSet<ObjectId>,
+    // the devices(non-member) of the members or in the allow-list subscribed the state, <remote, last-request-time>
+    subscribers: HashMap<ObjectId, Instant>,
+    // the devices or their owners allowed to subscribe the state besides the members
+    subscriber_allow_list: HashSet<ObjectId>,
     notify_progress: Option<HeaderBlockNotifyProgress>,
 }
 
@@ -164,6 +187,8 @@ impl StateChanggeRunner {
             notify_progress: None,
             local_id,
             request_last_state_remotes: HashSet::new(),
+            subscribers: HashMap::new(),
+            subscriber_allow_list: HashSet::new(),
             shell_mgr,
         }
     }
@@ -311,15 +336,120 @@ impl StateChanggeRunner {
             }
         }
 
+        // push the new header to the subscribers
+        let now = Instant::now();
+        self.subscribers
+            .retain(|_, last_time| now.duration_since(*last_time) < STATE_SUBSCRIBE_EXPIRE);
+        let subscribers: Vec<ObjectId> = self.subscribers.keys().cloned().collect();
+        self.request_last_state_remotes.extend(subscribers);
+
         self.delay_notify(true).await;
     }
 
     async fn request_last_state(&mut self, remote: ObjectId) {
+        let is_member = self
+            .notify_progress
+            .as_ref()
+            .map_or(false, |progress| progress.members.contains(&remote));
+        if !is_member {
+            self.subscribe(remote).await;
+        }
+
         if self.request_last_state_remotes.insert(remote) {
             self.delay_notify(true).await;
         }
     }
 
+    // only the devices owned by the members or in the allow-list can subscribe the state, the others will be answered only once
+    async fn subscribe(&mut self, remote: ObjectId) {
+        if let Some(last_time) = self.subscribers.get_mut(&remote) {
+            *last_time = Instant::now();
+            return;
+        }
+
+        let now = Instant::now();
+        self.subscribers
+            .retain(|_, last_time| now.duration_since(*last_time) < STATE_SUBSCRIBE_EXPIRE);
+        if self.subscribers.len() >= STATE_SUBSCRIBERS_LIMIT {
+            log::warn!(
+                "subscribe state for {:?} ignored for too many subscribers, remote: {}",
+                self.rpath,
+                remote
+            );
+            return;
+        }
+
+        let group = self
+            .shell_mgr
+            .get_group(self.rpath.group_id(), None, None)
+            .await
+            .ok();
+        if !self.is_authorized_subscriber(group.as_ref(), &remote).await {
+            log::warn!(
+                "subscribe state for {:?} ignored for the remote is not a device of the members or in the allow-list, remote: {}",
+                self.rpath,
+                remote
+            );
+            return;
+        }
+
+        self.subscribers.insert(remote, now);
+    }
+
+    // the subscribers removed from the allow-list will not be notified any more
+    async fn set_subscriber_allow_list(&mut self, allow_list: HashSet<ObjectId>) {
+        log::info!(
+            "set state subscriber allow-list for {:?}, count: {}",
+            self.rpath,
+            allow_list.len()
+        );
+
+        self.subscriber_allow_list = allow_list;
+
+        let group = self
+            .shell_mgr
+            .get_group(self.rpath.group_id(), None, None)
+            .await
+            .ok();
+        let subscribers: Vec<ObjectId> = self.subscribers.keys().cloned().collect();
+        for remote in subscribers {
+            if !self.is_authorized_subscriber(group.as_ref(), &remote).await {
+                self.subscribers.remove(&remote);
+            }
+        }
+    }
+
+    async fn is_authorized_subscriber(&self, group: Option<&Group>, remote: &ObjectId) -> bool {
+        if Self::is_authorized_subscriber_of(group, &self.subscriber_allow_list, remote, None) {
+            return true;
+        }
+
+        match self.non_driver.get_device(remote).await {
+            Ok(device) => Self::is_authorized_subscriber_of(
+                group,
+                &self.subscriber_allow_list,
+                remote,
+                device.desc().owner().as_ref(),
+            ),
+            Err(_) => false,
+        }
+    }
+
+    fn is_authorized_subscriber_of(
+        group: Option<&Group>,
+        allow_list: &HashSet<ObjectId>,
+        remote: &ObjectId,
+        owner: Option<&ObjectId>,
+    ) -> bool {
+        let is_authorized = |id: &ObjectId| {
+            allow_list.contains(id)
+                || group.map_or(false, |group| {
+                    group.members().contains_key(id) || group.admins().contains_key(id)
+                })
+        };
+        is_authorized(remote) || owner.map_or(false, is_authorized)
+    }
+
     async fn try_notify_block_commit(&mut self) {
         self.delay_notify_times -= 1;
 
@@ -395,6 +525,9 @@ impl StateChanggeRunner {
                     Ok(StatePushMessage::LastStateRequest(remote)) => {
                         self.request_last_state(remote).await;
                     },
+                    Ok(StatePushMessage::SetSubscriberAllowList(allow_list)) => {
+                        self.set_subscriber_allow_list(allow_list).await;
+                    },
                     Ok(StatePushMessage::DelayBroadcast) => {
                         self.try_notify_block_commit().await;
                     },
@@ -407,3 +540,69 @@ impl StateChanggeRunner {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use cyfs_base::{Area, Group, GroupMember, ObjectId, ObjectIdDataBuilder};
+
+    use super::StateChanggeRunner;
+
+    fn new_id(name: &str) -> ObjectId {
+        ObjectIdDataBuilder::new().data(name).build().unwrap()
+    }
+
+    #[test]
+    fn test_is_authorized_subscriber() {
+        let admin = new_id("admin");
+        let member = new_id("member");
+        let other = new_id("other");
+        let device = new_id("device");
+        let auditor = new_id("auditor");
+        let auditor_device = new_id("auditor-device");
+
+        let mut group = Group::new_simple_group(
+            None,
+            vec![GroupMember::from_member_id(admin)],
+            Area::default(),
+        )
+        .build();
+        group.set_members(vec![GroupMember::from_member_id(member)]);
+        let group = Some(&group);
+
+        let empty = HashSet::new();
+        let is_authorized =
+            |allow_list: &HashSet<ObjectId>, remote: &ObjectId, owner: Option<&ObjectId>| {
+                StateChanggeRunner::is_authorized_subscriber_of(group, allow_list, remote, owner)
+            };
+
+        assert!(is_authorized(&empty, &admin, None));
+        assert!(is_authorized(&empty, &member, None));
+        assert!(is_authorized(&empty, &device, Some(&member)));
+        assert!(!is_authorized(&empty, &device, Some(&other)));
+        assert!(!is_authorized(&empty, &device, None));
+        assert!(!is_authorized(&empty, &auditor_device, Some(&auditor)));
+
+        // the non-member auditors in the allow-list, by owner or by device
+        let allow_list: HashSet<ObjectId> = vec![auditor].into_iter().collect();
+        assert!(is_authorized(&allow_list, &auditor_device, Some(&auditor)));
+        assert!(is_authorized(&allow_list, &auditor, None));
+        assert!(!is_authorized(&allow_list, &device, Some(&other)));
+
+        let allow_list: HashSet<ObjectId> = vec![device].into_iter().collect();
+        assert!(is_authorized(&allow_list, &device, Some(&other)));
+        assert!(!is_authorized(&allow_list, &auditor_device, Some(&auditor)));
+
+        // the allow-list works without the group
+        assert!(StateChanggeRunner::is_authorized_subscriber_of(
+            None,
+            &allow_list,
+            &device,
+            None
+        ));
+        assert!(!StateChanggeRunner::is_authorized_subscriber_of(
+            None, &empty, &member, None
+        ));
+    }
+}
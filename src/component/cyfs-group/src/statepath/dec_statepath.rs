@@ -0,0 +1,55 @@
+use cyfs_core::GroupRPath;
+
+use crate::STATE_PATH_SEPARATOR;
+
+pub const DEC_STATE_PATH_SYNC: &str = ".group-sync";
+
+// the paths in the global state of the non-member devices, where the group state mirrored
+pub struct DecStatePath {
+    root: String,
+}
+
+impl DecStatePath {
+    pub fn new(rpath: &GroupRPath) -> Self {
+        Self {
+            root: Self::join(&[
+                "",
+                DEC_STATE_PATH_SYNC,
+                rpath.group_id().to_string().as_str(),
+                rpath.rpath(),
+            ]),
+        }
+    }
+
+    pub fn join(fields: &[&str]) -> String {
+        fields.join(STATE_PATH_SEPARATOR)
+    }
+
+    pub fn sync_path(&self, sub_path: &str) -> String {
+        let sub_path = sub_path.trim_matches('/');
+        if sub_path.len() == 0 {
+            self.root.clone()
+        } else {
+            Self::join(&[self.root.as_str(), sub_path])
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cyfs_base::ObjectId;
+    use cyfs_core::GroupRPath;
+
+    use super::DecStatePath;
+
+    #[test]
+    fn test_sync_path() {
+        let group_id = ObjectId::default();
+        let rpath = GroupRPath::new(group_id, ObjectId::default(), "rpath".to_string());
+        let path = DecStatePath::new(&rpath);
+
+        let root = format!("/.group-sync/{}/rpath", group_id);
+        assert_eq!(path.sync_path("/"), root);
+        assert_eq!(path.sync_path("/a/b/"), format!("{}/a/b", root));
+    }
+}
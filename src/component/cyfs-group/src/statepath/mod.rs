@@ -2,5 +2,6 @@ mod dec_statepath;
 mod group_shell_statepath;
 mod group_statepath;
 
+pub(crate) use dec_statepath::*;
 pub(crate) use group_shell_statepath::*;
 pub(crate) use group_statepath::*;
@@ -1,15 +1,17 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use async_std::sync::RwLock;
 use cyfs_base::{
-    BuckyError, BuckyErrorCode, BuckyResult, ObjectId, ObjectMap, ObjectMapOpEnvMemoryCache,
-    ObjectTypeCode, RawDecode,
+    BuckyError, BuckyErrorCode, BuckyResult, ObjectId, ObjectMap, ObjectMapContentItem,
+    ObjectMapOpEnvCacheRef, ObjectMapOpEnvMemoryCache, ObjectTypeCode, RawConvertTo, RawDecode,
 };
-use cyfs_core::{GroupConsensusBlock, HotstuffBlockQC};
+use cyfs_core::{GroupConsensusBlock, GroupConsensusBlockObject, GroupRPath, HotstuffBlockQC};
 use cyfs_group_lib::GroupRPathStatus;
 use cyfs_lib::{GlobalStateRawProcessorRef, NONObjectInfo};
 
-use crate::STATE_PATH_SEPARATOR;
+use crate::{network::NONDriverHelper, DecStatePath, STATE_PATH_SEPARATOR};
+
+const COMPLETE_TREE_CACHE_LIMIT: usize = 4096;
 
 #[derive(Clone)]
 pub struct DecStorageCache {
@@ -22,31 +24,227 @@ pub struct DecStorageCache {
 pub struct DecStorage {
     cache: Arc<RwLock<Option<DecStorageCache>>>,
     pub state_processor: GlobalStateRawProcessorRef,
+    rpath: GroupRPath,
+    non_driver: NONDriverHelper,
+    // the sub-paths mirrored from the group state
+    sync_paths: Arc<RwLock<HashSet<String>>>,
+    // the sub-trees all the branches downloaded, the ObjectMap is immutable so it's always complete
+    complete_trees: Arc<RwLock<HashSet<ObjectId>>>,
+    state_path: Arc<DecStatePath>,
 }
 
 impl DecStorage {
-    pub async fn load(state_processor: GlobalStateRawProcessorRef) -> BuckyResult<Self> {
-        // unimplemented!();
+    pub async fn load(
+        state_processor: GlobalStateRawProcessorRef,
+        rpath: GroupRPath,
+        non_driver: NONDriverHelper,
+    ) -> BuckyResult<Self> {
+        let state_path = Arc::new(DecStatePath::new(&rpath));
         let obj = Self {
             cache: Arc::new(RwLock::new(None)),
             state_processor,
+            rpath,
+            non_driver,
+            sync_paths: Arc::new(RwLock::new(HashSet::new())),
+            complete_trees: Arc::new(RwLock::new(HashSet::new())),
+            state_path,
         };
 
         Ok(obj)
     }
 
+    // return false if the sub-path is already in syncing
+    pub async fn add_sync_path(&self, sub_path: String) -> bool {
+        let sub_path = format!("{}{}", STATE_PATH_SEPARATOR, sub_path.trim_matches('/'));
+        let is_new = self.sync_paths.write().await.insert(sub_path);
+        if is_new {
+            // sync the new sub-path with the next header
+            *self.cache.write().await = None;
+        }
+        is_new
+    }
+
     pub async fn cur_state(&self) -> Option<DecStorageCache> {
         let cur = self.cache.read().await;
         (*cur).clone()
     }
 
+    // the header_block should be verified with the qc by the caller
     pub async fn sync(
         &self,
-        _header_block: &GroupConsensusBlock,
-        _qc: &HotstuffBlockQC,
-        _remote: ObjectId,
+        header_block: &GroupConsensusBlock,
+        qc: &HotstuffBlockQC,
+        remote: ObjectId,
     ) -> BuckyResult<()> {
-        unimplemented!()
+        if let Some(cur) = self.cache.read().await.as_ref() {
+            if cur.header_block.height() >= header_block.height() {
+                return Ok(());
+            }
+        }
+
+        let sync_paths: Vec<String> = self.sync_paths.read().await.iter().cloned().collect();
+        for sub_path in sync_paths.iter() {
+            self.sync_sub_path(header_block.result_state_id(), sub_path.as_str(), &remote)
+                .await
+                .map_err(|err| {
+                    log::warn!(
+                        "sync sub-path {} for {:?} at height {} from {} failed, err: {:?}",
+                        sub_path,
+                        self.rpath,
+                        header_block.height(),
+                        remote,
+                        err
+                    );
+                    err
+                })?;
+        }
+
+        let mut cache = self.cache.write().await;
+        *cache = Some(DecStorageCache {
+            state: header_block.result_state_id().clone(),
+            header_block: header_block.clone(),
+            qc: qc.clone(),
+        });
+
+        Ok(())
+    }
+
+    async fn sync_sub_path(
+        &self,
+        root_state_id: &Option<ObjectId>,
+        sub_path: &str,
+        remote: &ObjectId,
+    ) -> BuckyResult<()> {
+        let root_cache = self.state_processor.root_cache();
+        let cache = ObjectMapOpEnvMemoryCache::new_ref(root_cache.clone());
+
+        let mut state_id = root_state_id.clone();
+        for folder in sub_path.split(STATE_PATH_SEPARATOR) {
+            if folder.len() == 0 {
+                continue;
+            }
+
+            let parent_state_id = match state_id {
+                Some(parent_state_id) => parent_state_id,
+                None => break,
+            };
+
+            let parent_state = self.download_object(&parent_state_id, remote).await?;
+            if ObjectTypeCode::ObjectMap != parent_state.object().obj_type_code() {
+                let msg = format!(
+                    "unmatch object type at path {} in folder {}, expect: ObjectMap, got: {:?}",
+                    sub_path,
+                    folder,
+                    parent_state.object().obj_type_code()
+                );
+                log::warn!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+            }
+
+            let (parent, _) = ObjectMap::raw_decode(parent_state.object_raw.as_slice())?;
+            state_id = parent.get_by_key(&cache, folder).await?;
+        }
+
+        if let Some(state_id) = state_id.as_ref() {
+            self.download_sub_tree(state_id, remote).await?;
+        }
+
+        // mount the state to the global state of local device
+        let root_manager = self
+            .state_processor
+            .get_dec_root_manager(self.rpath.dec_id(), true)
+            .await?;
+        let op_env = root_manager.create_op_env(None)?;
+        let path = self.state_path.sync_path(sub_path);
+        match state_id.as_ref() {
+            Some(state_id) => {
+                op_env
+                    .set_with_path(path.as_str(), state_id, &None, true)
+                    .await?;
+            }
+            None => {
+                op_env.remove_with_path(path.as_str(), &None).await?;
+            }
+        }
+        op_env.commit().await?;
+
+        log::debug!(
+            "sync sub-path {} for {:?} to {}, state: {:?}",
+            sub_path,
+            self.rpath,
+            path,
+            state_id
+        );
+
+        Ok(())
+    }
+
+    async fn download_object(
+        &self,
+        object_id: &ObjectId,
+        remote: &ObjectId,
+    ) -> BuckyResult<NONObjectInfo> {
+        if let Ok(obj) = self.non_driver.get_object(object_id, None).await {
+            return Ok(obj);
+        }
+
+        let obj = self.non_driver.get_object(object_id, Some(remote)).await?;
+        Self::verify_state(object_id, &obj)?;
+        self.non_driver.put_object(obj.clone()).await?;
+        Ok(obj)
+    }
+
+    #[async_recursion::async_recursion]
+    async fn download_sub_tree(&self, root_id: &ObjectId, remote: &ObjectId) -> BuckyResult<()> {
+        // the root maybe saved in the last sync which is interrupted before all the branches downloaded,
+        // so walk the branches until it's marked complete.
+        if root_id.is_data() || self.complete_trees.read().await.contains(root_id) {
+            return Ok(());
+        }
+
+        let obj = self.download_object(root_id, remote).await?;
+        if obj.object().obj_type_code() != ObjectTypeCode::ObjectMap {
+            return Ok(());
+        }
+
+        let root_manager = self
+            .state_processor
+            .get_dec_root_manager(self.rpath.dec_id(), true)
+            .await?;
+        let single_op_env = root_manager.create_single_op_env(None)?;
+        single_op_env.load(root_id).await?;
+
+        loop {
+            let branchs = single_op_env.next(16).await?;
+            for branch in branchs.list.iter() {
+                let branch_id = match branch {
+                    ObjectMapContentItem::DiffMap(diff_map) => match diff_map.1.altered.as_ref() {
+                        Some(branch_id) => branch_id,
+                        None => continue,
+                    },
+                    ObjectMapContentItem::Map(map) => &map.1,
+                    ObjectMapContentItem::DiffSet(diff_set) => match diff_set.altered.as_ref() {
+                        Some(branch_id) => branch_id,
+                        None => continue,
+                    },
+                    ObjectMapContentItem::Set(set) => set,
+                };
+                self.download_sub_tree(branch_id, remote).await?;
+            }
+
+            if branchs.list.len() < 16 {
+                break;
+            }
+        }
+
+        let mut complete_trees = self.complete_trees.write().await;
+        if complete_trees.len() >= COMPLETE_TREE_CACHE_LIMIT {
+            // the complete trees will be walked again, but the objects will not be downloaded again
+            complete_trees.clear();
+        }
+        complete_trees.insert(root_id.clone());
+
+        Ok(())
     }
 
     pub async fn get_by_path(&self, _path: &str) -> BuckyResult<GroupRPathStatus> {
@@ -57,6 +255,17 @@ impl DecStorage {
         &self,
         sub_path: &str,
         verifiable_status: &'a GroupRPathStatus,
+    ) -> BuckyResult<Option<&'a NONObjectInfo>> {
+        let root_cache = self.state_processor.root_cache();
+        let cache = ObjectMapOpEnvMemoryCache::new_ref(root_cache.clone());
+
+        Self::check_sub_path_value_with_cache(&cache, sub_path, verifiable_status).await
+    }
+
+    async fn check_sub_path_value_with_cache<'a>(
+        cache: &ObjectMapOpEnvCacheRef,
+        sub_path: &str,
+        verifiable_status: &'a GroupRPathStatus,
     ) -> BuckyResult<Option<&'a NONObjectInfo>> {
         let block_desc = &verifiable_status.block_desc;
 
@@ -65,9 +274,6 @@ impl DecStorage {
             None => return Ok(None),
         };
 
-        let root_cache = self.state_processor.root_cache();
-        let cache = ObjectMapOpEnvMemoryCache::new_ref(root_cache.clone());
-
         for folder in sub_path.split(STATE_PATH_SEPARATOR) {
             if folder.len() == 0 {
                 continue;
@@ -77,6 +283,7 @@ impl DecStorage {
                 Some(state) => state,
                 None => return Ok(None),
             };
+            Self::verify_state(&parent_state_id, parent_state)?;
 
             if ObjectTypeCode::ObjectMap != parent_state.object().obj_type_code() {
                 let msg = format!(
@@ -101,7 +308,7 @@ impl DecStorage {
 
             assert_eq!(remain.len(), 0);
 
-            let sub_map_id = parent.get_by_key(&cache, folder).await?;
+            let sub_map_id = parent.get_by_key(cache, folder).await?;
             log::debug!("get sub-folder {} result: {:?}", folder, sub_map_id);
 
             match sub_map_id {
@@ -115,6 +322,171 @@ impl DecStorage {
             }
         }
 
-        Ok(verifiable_status.status_map.get(&parent_state_id))
+        match verifiable_status.status_map.get(&parent_state_id) {
+            Some(state) => {
+                Self::verify_state(&parent_state_id, state)?;
+                Ok(Some(state))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // the object in status_map should match with the id, the member can't forge any state
+    fn verify_state(state_id: &ObjectId, state: &NONObjectInfo) -> BuckyResult<()> {
+        if &state.object_id != state_id {
+            let msg = format!(
+                "unmatch state id, expect: {}, got: {}",
+                state_id, state.object_id
+            );
+            log::warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+        }
+
+        if state_id.is_data() {
+            if state.object_raw != state_id.to_vec()? {
+                let msg = format!("unmatch data state: {}", state_id);
+                log::warn!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+            }
+            Ok(())
+        } else {
+            state.verify()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use cyfs_base::{
+        AccessString, BuckyResult, NamedObject, ObjectId, ObjectMap, ObjectMapCacheItem,
+        ObjectMapNOCCache, ObjectMapOpEnvCacheRef, ObjectMapOpEnvMemoryCache,
+        ObjectMapRootMemoryCache, ObjectMapSimpleContentType, RawConvertTo,
+    };
+    use cyfs_core::{GroupConsensusBlock, GroupConsensusBlockObject, GroupRPath};
+    use cyfs_group_lib::GroupRPathStatus;
+    use cyfs_lib::NONObjectInfo;
+
+    use super::DecStorage;
+
+    // the maps are all simple, nothing will be loaded from the noc
+    struct EmptyNOCCache;
+
+    #[async_trait::async_trait]
+    impl ObjectMapNOCCache for EmptyNOCCache {
+        async fn exists(&self, _dec: Option<ObjectId>, _object_id: &ObjectId) -> BuckyResult<bool> {
+            Ok(false)
+        }
+
+        async fn get_object_map_ex(
+            &self,
+            _dec: Option<ObjectId>,
+            _object_id: &ObjectId,
+        ) -> BuckyResult<Option<ObjectMapCacheItem>> {
+            Ok(None)
+        }
+
+        async fn put_object_map(
+            &self,
+            _dec: Option<ObjectId>,
+            _object_id: ObjectId,
+            _object: ObjectMap,
+            _access: Option<AccessString>,
+        ) -> BuckyResult<()> {
+            Ok(())
+        }
+    }
+
+    fn new_cache() -> ObjectMapOpEnvCacheRef {
+        let root_cache = ObjectMapRootMemoryCache::new_default_ref(
+            None,
+            std::sync::Arc::new(Box::new(EmptyNOCCache)),
+        );
+        ObjectMapOpEnvMemoryCache::new_ref(root_cache)
+    }
+
+    fn new_map(content_type: ObjectMapSimpleContentType) -> ObjectMap {
+        ObjectMap::new(content_type, None, None)
+            .no_create_time()
+            .build()
+    }
+
+    fn to_info(map: &ObjectMap) -> NONObjectInfo {
+        NONObjectInfo::new_from_object_raw(map.to_vec().unwrap()).unwrap()
+    }
+
+    fn new_status(root_state_id: ObjectId, states: Vec<NONObjectInfo>) -> GroupRPathStatus {
+        let rpath = GroupRPath::new(ObjectId::default(), ObjectId::default(), "test".to_string());
+        let block = GroupConsensusBlock::create(
+            rpath,
+            vec![],
+            Some(root_state_id),
+            1,
+            ObjectId::default(),
+            1,
+            ObjectId::default(),
+            None,
+            None,
+            ObjectId::default(),
+        );
+
+        GroupRPathStatus {
+            block_desc: block.named_object().desc().clone(),
+            certificate: Default::default(),
+            status_map: states
+                .into_iter()
+                .map(|state| (state.object_id.clone(), state))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn test_check_sub_path_value() {
+        async_std::task::block_on(async {
+            let cache = new_cache();
+
+            // root/a/b = value
+            let value = new_map(ObjectMapSimpleContentType::Set);
+            let value_id = value.flush_id();
+            let mut sub = new_map(ObjectMapSimpleContentType::Map);
+            sub.insert_with_key(&cache, "b", &value_id).await.unwrap();
+            let sub_id = sub.flush_id();
+            let mut root = new_map(ObjectMapSimpleContentType::Map);
+            root.insert_with_key(&cache, "a", &sub_id).await.unwrap();
+            let root_id = root.flush_id();
+
+            let status = new_status(
+                root_id,
+                vec![to_info(&root), to_info(&sub), to_info(&value)],
+            );
+            let found = DecStorage::check_sub_path_value_with_cache(&cache, "/a/b", &status)
+                .await
+                .unwrap();
+            assert_eq!(found.unwrap().object_id, value_id);
+
+            let found = DecStorage::check_sub_path_value_with_cache(&cache, "/a/c", &status)
+                .await
+                .unwrap();
+            assert!(found.is_none());
+
+            // the value is not in the proof
+            let status = new_status(root_id, vec![to_info(&root), to_info(&sub)]);
+            let found = DecStorage::check_sub_path_value_with_cache(&cache, "/a/b", &status)
+                .await
+                .unwrap();
+            assert!(found.is_none());
+
+            // a forged value under the id of the real value
+            let mut forged = to_info(&sub);
+            forged.object_id = value_id.clone();
+            let mut status = new_status(root_id, vec![to_info(&root), to_info(&sub)]);
+            status.status_map.insert(value_id, forged);
+            assert!(
+                DecStorage::check_sub_path_value_with_cache(&cache, "/a/b", &status)
+                    .await
+                    .is_err()
+            );
+        });
     }
 }
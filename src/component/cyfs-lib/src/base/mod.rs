@@ -9,6 +9,7 @@ mod requestor_helper;
 mod select_request;
 mod source;
 mod tcp_listener;
+mod web_helper;
 mod zone;
 
 pub use config::*;
@@ -21,4 +22,5 @@ pub use requestor_helper::*;
 pub use select_request::*;
 pub use source::*;
 pub use tcp_listener::*;
+pub use web_helper::*;
 pub use zone::*;
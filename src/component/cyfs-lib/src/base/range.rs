@@ -337,6 +337,53 @@ impl RequestorRangeHelper {
         format!("bytes */{}", size)
     }
 
+    // Parse the http Range header with single range: bytes=start-end, bytes=start- or bytes=-suffix_len.
+    // Multiple ranges and other units return None and the header should be ignored, the unsatisfiable range
+    // returns RangeNotSatisfiable
+    pub fn parse_single_range(value: &str, size: u64) -> BuckyResult<Option<Range<u64>>> {
+        let invalid = || {
+            let msg = format!("invalid range: {}, size={}", value, size);
+            warn!("{}", msg);
+            BuckyError::new(BuckyErrorCode::RangeNotSatisfiable, msg)
+        };
+
+        let spec = match value.trim().strip_prefix("bytes=") {
+            Some(spec) if !spec.contains(',') => spec,
+            _ => return Ok(None),
+        };
+
+        let (start, end) = spec.split_once('-').ok_or_else(invalid)?;
+        let range = match (start.trim(), end.trim()) {
+            ("", suffix) => {
+                let len: u64 = suffix.parse().map_err(|_| invalid())?;
+                if len == 0 {
+                    return Err(invalid());
+                }
+                size.saturating_sub(len)..size
+            }
+            (start, "") => {
+                let start: u64 = start.parse().map_err(|_| invalid())?;
+                start..size
+            }
+            (start, end) => {
+                let start: u64 = start.parse().map_err(|_| invalid())?;
+                let end: u64 = end.parse().map_err(|_| invalid())?;
+                if end < start {
+                    return Err(invalid());
+                }
+
+                // end is inclusive and may be u64::MAX
+                start..std::cmp::min(end.saturating_add(1), size)
+            }
+        };
+
+        if range.start >= size {
+            return Err(invalid());
+        }
+
+        Ok(Some(range))
+    }
+
     pub fn new_range_response(range_resp: &NDNDataResponseRange) -> http_types::Response {
         let mut resp = match range_resp {
             NDNDataResponseRange::Range((ranges, len)) => {
@@ -371,3 +418,31 @@ impl RequestorRangeHelper {
         resp
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_single_range() {
+        let parse = |v: &str| RequestorRangeHelper::parse_single_range(v, 100);
+
+        assert_eq!(parse("bytes=0-9").unwrap(), Some(0..10));
+        assert_eq!(parse("bytes=90-").unwrap(), Some(90..100));
+        assert_eq!(parse("bytes=-10").unwrap(), Some(90..100));
+        assert_eq!(parse("bytes=-1000").unwrap(), Some(0..100));
+        assert_eq!(parse("bytes=50-1000").unwrap(), Some(50..100));
+        assert_eq!(
+            parse(&format!("bytes=0-{}", u64::MAX)).unwrap(),
+            Some(0..100)
+        );
+
+        assert_eq!(parse("bytes=0-1,5-6").unwrap(), None);
+        assert_eq!(parse("items=0-1").unwrap(), None);
+
+        for v in ["bytes=100-", "bytes=9-0", "bytes=-0", "bytes=a-b", "bytes=10"] {
+            let e = parse(v).unwrap_err();
+            assert_eq!(e.code(), BuckyErrorCode::RangeNotSatisfiable);
+        }
+    }
+}
@@ -12,4 +12,7 @@ pub const CYFS_REQUEST_FLAG_LIST_DIR: u32 = 0x01 << 2;
 //// NDN request flags
 ///
 // get_data/trans_task，target object is file/dir, 跨device请求直接使用chunk级别的acl，不再使用所属的file/dir
-pub const CYFS_REQUEST_FLAG_CHUNK_LEVEL_ACL: u32 = 0x01 << 3;
\ No newline at end of file
+pub const CYFS_REQUEST_FLAG_CHUNK_LEVEL_ACL: u32 = 0x01 << 3;
+
+// get_object/get_data，目标为SealedObject时不透明解密，直接返回密文
+pub const CYFS_REQUEST_FLAG_KEEP_SEALED: u32 = 0x01 << 4;
\ No newline at end of file
@@ -0,0 +1,30 @@
+use cyfs_base::*;
+
+use chrono::{DateTime, Utc};
+
+// Helpers shared by the http based protocol gateways over cyfs, such as s3 and webdav
+pub struct WebHelper;
+
+impl WebHelper {
+    // The IMF-fixdate in http headers, such as Last-Modified: Wed, 21 Oct 2015 07:28:00 GMT
+    pub fn http_date(time: u64) -> String {
+        let time: DateTime<Utc> = bucky_time_to_system_time(time).into();
+        time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+    }
+
+    pub fn xml_escape(value: &str) -> String {
+        let mut ret = String::with_capacity(value.len());
+        for c in value.chars() {
+            match c {
+                '&' => ret.push_str("&amp;"),
+                '<' => ret.push_str("&lt;"),
+                '>' => ret.push_str("&gt;"),
+                '"' => ret.push_str("&quot;"),
+                '\'' => ret.push_str("&apos;"),
+                _ => ret.push(c),
+            }
+        }
+
+        ret
+    }
+}
@@ -5,9 +5,11 @@ mod output_request_codec;
 mod processor;
 mod request;
 mod requestor;
+mod sealed;
 
 pub use input_request::*;
 pub use output_request::*;
 pub use processor::*;
 pub use request::*;
 pub use requestor::*;
+pub use sealed::*;
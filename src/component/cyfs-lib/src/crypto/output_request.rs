@@ -59,6 +59,11 @@ pub const CRYPTO_REQUEST_FLAG_SIGN_SET_BODY: u32 = 0x01 << 4;
 pub const CRYPTO_REQUEST_FLAG_SIGN_PUSH_DESC: u32 = 0x01 << 5;
 pub const CRYPTO_REQUEST_FLAG_SIGN_PUSH_BODY: u32 = 0x01 << 6;
 
+// M-of-N门限签名：向owner(People/Group)的ood_list逐个收集设备签名，达到门限后返回Signed，否则返回Pending和已收集到签名的对象
+pub const CRYPTO_REQUEST_FLAG_SIGN_BY_THRESHOLD: u32 = 0x01 << 7;
+// 门限签名分发给单个签名设备的请求，由发起方协议栈使用
+pub const CRYPTO_REQUEST_FLAG_SIGN_THRESHOLD_PART: u32 = 0x01 << 8;
+
 pub struct CryptoSignObjectOutputRequest {
     pub common: CryptoOutputRequestCommon,
 
@@ -206,6 +211,9 @@ pub enum VerifyObjectType {
 
     // 校验指定的签名是否有效
     Sign(VerifySigns),
+
+    // 校验是否有owner的M-of-N门限签名
+    Threshold,
 }
 
 impl VerifyObjectType {
@@ -215,6 +223,7 @@ impl VerifyObjectType {
             Self::Own => "own",
             Self::Object(_) => "object",
             Self::Sign(_) => "sign",
+            Self::Threshold => "threshold",
         }
     }
 }
@@ -272,6 +281,15 @@ impl CryptoVerifyObjectOutputRequest {
         }
     }
 
+    pub fn new_verify_by_threshold(sign_type: VerifySignType, object: NONObjectInfo) -> Self {
+        Self {
+            common: CryptoOutputRequestCommon::default(),
+            sign_type,
+            object,
+            sign_object: VerifyObjectType::Threshold,
+        }
+    }
+
     pub fn new_verify_by_signs(
         sign_type: VerifySignType,
         object: NONObjectInfo,
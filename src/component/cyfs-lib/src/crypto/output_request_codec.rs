@@ -160,7 +160,7 @@ impl JsonCodec<VerifyObjectType> for VerifyObjectType {
         JsonCodecHelper::encode_string_field(&mut obj, "type", self);
 
         match &self {
-            VerifyObjectType::Owner | VerifyObjectType::Own => {}
+            VerifyObjectType::Owner | VerifyObjectType::Own | VerifyObjectType::Threshold => {}
             VerifyObjectType::Object(sign_object) => {
                 JsonCodecHelper::encode_field(&mut obj, "sign_object", sign_object);
             }
@@ -177,6 +177,7 @@ impl JsonCodec<VerifyObjectType> for VerifyObjectType {
         let ret = match sign_object_type.as_str() {
             "owner" => VerifyObjectType::Owner,
             "own" => VerifyObjectType::Own,
+            "threshold" => VerifyObjectType::Threshold,
             "object" => {
                 let sign_object = JsonCodecHelper::decode_field(obj, "sign_object")?;
                 VerifyObjectType::Object(sign_object)
@@ -70,7 +70,7 @@ impl CryptoRequestor {
 
         let verify_type = req.sign_object.as_str();
         match &req.sign_object {
-            VerifyObjectType::Owner | VerifyObjectType::Own => {}
+            VerifyObjectType::Owner | VerifyObjectType::Own | VerifyObjectType::Threshold => {}
             VerifyObjectType::Object(sign_object) => {
                 http_req.insert_header(
                     cyfs_base::CYFS_SIGN_OBJ_ID,
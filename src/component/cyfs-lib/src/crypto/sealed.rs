@@ -0,0 +1,174 @@
+use crate::non::*;
+use cyfs_base::*;
+use cyfs_core::*;
+
+use async_std::io::{ReadExt, WriteExt};
+use std::path::Path;
+
+// aes的分组长度，明文chunk按分组对齐时加密后的长度固定为sealed_data_len(chunk_size)
+const SEALED_BLOCK_SIZE: u32 = 16;
+
+/*
+加密对象和加密文件的辅助方法
+1. People接收者展开为自身以及ood_list里的设备，Group没有单一公钥只展开为ood_list里的设备，ood协议栈使用设备私钥为zone内的请求透明解密
+2. 加密文件：先用内容密钥按chunk加密到新文件，再以sealed_chunk_size发布得到密文File，最后用同一个内容密钥把File加密为SealedObject
+*/
+pub struct SealedObjectHelper;
+
+impl SealedObjectHelper {
+    pub async fn resolve_recipients(
+        non: &NONOutputProcessorRef,
+        recipients: &[ObjectId],
+    ) -> BuckyResult<Vec<(ObjectId, PublicKey)>> {
+        let mut list: Vec<(ObjectId, PublicKey)> = vec![];
+        for id in recipients {
+            let object = Self::get_object(non, id).await?;
+
+            // Group没有自身的单一公钥，只展开为ood设备
+            let type_code = id.obj_type_code();
+            if type_code != ObjectTypeCode::Group {
+                Self::append_recipient(&mut list, id, &object)?;
+            }
+
+            match type_code {
+                ObjectTypeCode::People | ObjectTypeCode::Group => {
+                    let ood_list = object.ood_list()?;
+                    if type_code == ObjectTypeCode::Group && ood_list.is_empty() {
+                        let msg = format!("sealed object group recipient has no ood: {}", id);
+                        error!("{}", msg);
+                        return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+                    }
+
+                    for device_id in ood_list {
+                        let device = Self::get_object(non, device_id.object_id()).await?;
+                        Self::append_recipient(&mut list, device_id.object_id(), &device)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(list)
+    }
+
+    fn append_recipient(
+        list: &mut Vec<(ObjectId, PublicKey)>,
+        id: &ObjectId,
+        object: &AnyNamedObject,
+    ) -> BuckyResult<()> {
+        if list.iter().any(|(v, _)| v == id) {
+            return Ok(());
+        }
+
+        match object.public_key() {
+            Some(PublicKeyRef::Single(pk)) => {
+                list.push((id.to_owned(), pk.to_owned()));
+                Ok(())
+            }
+            _ => {
+                let msg = format!("sealed object recipient has no single public key: {}", id);
+                error!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::NotSupport, msg))
+            }
+        }
+    }
+
+    async fn get_object(
+        non: &NONOutputProcessorRef,
+        object_id: &ObjectId,
+    ) -> BuckyResult<AnyNamedObject> {
+        let req = NONGetObjectOutputRequest::new_router(None, object_id.to_owned(), None);
+        let resp = non.get_object(req).await.map_err(|e| {
+            error!(
+                "get sealed object recipient failed! recipient={}, {}",
+                object_id, e
+            );
+            e
+        })?;
+
+        let (object, _) = AnyNamedObject::raw_decode(&resp.object.object_raw)?;
+        Ok(object)
+    }
+
+    // 加密后的chunk长度，发布密文文件时使用
+    pub fn sealed_chunk_size(chunk_size: u32) -> u32 {
+        sealed_data_len(chunk_size as usize) as u32
+    }
+
+    // 按chunk加密source文件并写入target，返回明文长度
+    pub async fn seal_file(
+        key: &AesKey,
+        source: &Path,
+        target: &Path,
+        chunk_size: u32,
+    ) -> BuckyResult<u64> {
+        if chunk_size == 0 || chunk_size % SEALED_BLOCK_SIZE != 0 {
+            let msg = format!(
+                "seal file chunk size should be multiple of {}: {}",
+                SEALED_BLOCK_SIZE, chunk_size
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        let mut reader = async_std::fs::File::open(source).await.map_err(|e| {
+            let msg = format!(
+                "open file for seal failed! file={}, {}",
+                source.display(),
+                e
+            );
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
+        let mut writer = async_std::fs::File::create(target).await.map_err(|e| {
+            let msg = format!(
+                "create sealed file failed! file={}, {}",
+                target.display(),
+                e
+            );
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
+
+        let mut buf = vec![0u8; chunk_size as usize];
+        let mut total = 0;
+        loop {
+            let len = Self::read_chunk(&mut reader, &mut buf).await?;
+            if len == 0 {
+                break;
+            }
+
+            let sealed = seal_data(key, &buf[..len])?;
+            writer.write_all(&sealed).await?;
+            total += len as u64;
+
+            if len < buf.len() {
+                break;
+            }
+        }
+
+        writer.flush().await?;
+
+        info!(
+            "seal file complete: source={}, target={}, len={}",
+            source.display(),
+            target.display(),
+            total
+        );
+
+        Ok(total)
+    }
+
+    async fn read_chunk(reader: &mut async_std::fs::File, buf: &mut [u8]) -> BuckyResult<usize> {
+        let mut len = 0;
+        while len < buf.len() {
+            let ret = reader.read(&mut buf[len..]).await?;
+            if ret == 0 {
+                break;
+            }
+            len += ret;
+        }
+
+        Ok(len)
+    }
+}
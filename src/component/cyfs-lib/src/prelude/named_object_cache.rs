@@ -8,7 +8,7 @@ use std::sync::Arc;
 pub const CYFS_NOC_FLAG_DELETE_WITH_QUERY: u32 = 0x01 << 1;
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum NamedObjectStorageCategory {
     Storage = 0,
     Cache = 1,
@@ -89,6 +89,9 @@ impl NamedObjectCacheUpdateObjectMetaRequest {
 
 pub const NAMED_OBJECT_CACHE_GET_OBJECT_FLAG_NO_UPDATE_LAST_ACCESS: u32 = 0x01;
 
+// only load the meta, the object in the response is always None, for internal use only
+pub const NAMED_OBJECT_CACHE_GET_OBJECT_FLAG_META_ONLY: u32 = 0x02;
+
 // get_object
 #[derive(Clone)]
 pub struct NamedObjectCacheGetObjectRequest {
@@ -109,6 +112,14 @@ impl NamedObjectCacheGetObjectRequest {
     pub fn is_no_update_last_access(&self) -> bool {
         self.flags & NAMED_OBJECT_CACHE_GET_OBJECT_FLAG_NO_UPDATE_LAST_ACCESS == NAMED_OBJECT_CACHE_GET_OBJECT_FLAG_NO_UPDATE_LAST_ACCESS
     }
+
+    pub fn set_meta_only(&mut self) {
+        self.flags |= NAMED_OBJECT_CACHE_GET_OBJECT_FLAG_META_ONLY;
+    }
+
+    pub fn is_meta_only(&self) -> bool {
+        self.flags & NAMED_OBJECT_CACHE_GET_OBJECT_FLAG_META_ONLY == NAMED_OBJECT_CACHE_GET_OBJECT_FLAG_META_ONLY
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -199,21 +210,61 @@ pub struct NamedObjectCacheCheckObjectAccessRequest {
 pub struct NamedObjectCacheStat {
     pub count: u64,
     pub storage_size: u64,
+
+    // 按存储类别和dec统计的对象数和对象大小，用于配额检查
+    #[serde(default)]
+    pub usage: Vec<NamedObjectCacheUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedObjectCacheUsage {
+    pub storage_category: NamedObjectStorageCategory,
+    pub dec_id: ObjectId,
+
+    pub count: u64,
+    pub size: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct NamedObjectCacheSelectObjectFilter {
     pub obj_type: Option<u16>,
+
+    pub storage_category: Option<NamedObjectStorageCategory>,
+    pub create_dec_id: Option<ObjectId>,
+
+    // only select the objects which last_access_time < last_access_before
+    pub last_access_before: Option<u64>,
 }
 
 impl Default for NamedObjectCacheSelectObjectFilter {
     fn default() -> Self {
         Self {
             obj_type: None,
+            storage_category: None,
+            create_dec_id: None,
+            last_access_before: None,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NamedObjectCacheSelectObjectOrder {
+    // insert_time desc
+    InsertTime,
+
+    // last_access_time asc, the least recently used first
+    LastAccessTime,
+
+    // access_count asc then last_access_time asc, the least frequently used first
+    AccessCount,
+}
+
+impl Default for NamedObjectCacheSelectObjectOrder {
+    fn default() -> Self {
+        Self::InsertTime
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NamedObjectCacheSelectObjectOption {
     // The number of readings per page
@@ -221,6 +272,8 @@ pub struct NamedObjectCacheSelectObjectOption {
 
     // The page number currently read, starting from 0
     pub page_index: usize,
+
+    pub order: NamedObjectCacheSelectObjectOrder,
 }
 
 impl Default for NamedObjectCacheSelectObjectOption {
@@ -228,6 +281,7 @@ impl Default for NamedObjectCacheSelectObjectOption {
         Self {
             page_size: 1024,
             page_index: 0,
+            order: NamedObjectCacheSelectObjectOrder::default(),
         }
     }
 }
@@ -313,6 +367,13 @@ pub trait NamedObjectCache: Sync + Send {
         req: &NamedObjectCacheSelectObjectRequest,
     ) -> BuckyResult<NamedObjectCacheSelectObjectResponse>;
 
+    // for internal use only, read the stored blob without decoding, such as keep a corrupted blob for analysis
+    async fn get_object_blob(&self, object_id: &ObjectId) -> BuckyResult<Option<Vec<u8>>> {
+        let msg = format!("get object blob not support! obj={}", object_id);
+        warn!("{}", msg);
+        Err(BuckyError::new(BuckyErrorCode::NotSupport, msg))
+    }
+
     fn bind_object_meta_access_provider(
         &self,
         object_meta_access_provider: NamedObjectCacheObjectMetaAccessProviderRef,
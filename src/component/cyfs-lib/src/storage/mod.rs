@@ -1,12 +1,14 @@
 mod remote_noc;
 mod remote_storage;
 mod state_storage;
+mod state_file_storage;
 mod storage;
 mod collection;
 mod state_view;
 
 pub use remote_storage::*;
 pub use state_storage::*;
+pub use state_file_storage::*;
 pub use storage::*;
 pub use collection::*;
 pub use state_view::*;
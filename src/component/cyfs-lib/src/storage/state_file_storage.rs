@@ -0,0 +1,174 @@
+use crate::*;
+use cyfs_base::*;
+
+use std::ops::Range;
+use std::path::Path;
+
+// Files published to the chunk cache and mapped to the paths in a dec's global state, the values
+// are the File objects and the dirs are object_maps. Shared by the file gateways such as s3 and webdav
+pub struct StateFileStorage {
+    stack: SharedCyfsStack,
+    dec_id: ObjectId,
+    owner: ObjectId,
+    chunk_size: u32,
+}
+
+impl StateFileStorage {
+    pub fn new(stack: SharedCyfsStack, dec_id: ObjectId, chunk_size: u32) -> Self {
+        let device = stack.local_device();
+        let owner = match device.desc().owner() {
+            Some(owner) => owner.to_owned(),
+            None => device.desc().device_id().object_id().to_owned(),
+        };
+
+        Self {
+            stack,
+            dec_id,
+            owner,
+            chunk_size,
+        }
+    }
+
+    pub fn state_stub(&self) -> GlobalStateStub {
+        self.stack.root_state_stub(None, Some(self.dec_id.clone()))
+    }
+
+    fn accessor_stub(&self) -> GlobalStateAccessorStub {
+        self.stack
+            .root_state_accessor_stub(None, Some(self.dec_id.clone()))
+    }
+
+    pub async fn get_by_path(&self, path: &str) -> BuckyResult<Option<ObjectId>> {
+        let op_env = self.state_stub().create_path_op_env().await?;
+        let ret = op_env.get_by_path(path).await;
+        let _ = op_env.abort().await;
+
+        ret
+    }
+
+    // List the (name, value) of the dir, the non map items will be ignored
+    pub async fn list(&self, path: &str) -> BuckyResult<Vec<(String, ObjectId)>> {
+        let list = self.accessor_stub().list(path).await?;
+
+        let mut ret = Vec::with_capacity(list.len());
+        for item in list {
+            match item {
+                ObjectMapContentItem::Map((name, id)) => ret.push((name, id)),
+                _ => {
+                    debug!(
+                        "non map item in state path will be ignored: path={}, {}",
+                        path, item
+                    );
+                }
+            }
+        }
+
+        Ok(ret)
+    }
+
+    pub async fn load_object(&self, object_id: &ObjectId) -> BuckyResult<NONObjectInfo> {
+        let req = NONGetObjectOutputRequest::new_noc(object_id.to_owned(), None);
+        let resp = self
+            .stack
+            .non_service()
+            .get_object(req)
+            .await
+            .map_err(|e| {
+                error!("get object from noc failed! id={}, {}", object_id, e);
+                e
+            })?;
+
+        Ok(resp.object)
+    }
+
+    pub async fn load_file(&self, file_id: &ObjectId) -> BuckyResult<File> {
+        let object = self.load_object(file_id).await?;
+        File::clone_from_slice(&object.object_raw)
+    }
+
+    pub async fn read(
+        &self,
+        file_id: &ObjectId,
+        range: Option<Range<u64>>,
+    ) -> BuckyResult<NDNGetDataOutputResponse> {
+        let mut req = NDNGetDataOutputRequest::new_router(None, file_id.to_owned(), None);
+        if let Some(range) = range {
+            req.range = Some(NDNDataRequestRange::new_range(vec![range]));
+        }
+
+        self.stack.ndn_service().get_data(req).await.map_err(|e| {
+            error!("get data of file failed! file={}, {}", file_id, e);
+            e
+        })
+    }
+
+    // Build the File object from the local file and publish it in one pass, return the file id
+    pub async fn publish(&self, local_path: &Path) -> BuckyResult<ObjectId> {
+        let req = TransPublishFileOutputRequest {
+            common: NDNOutputRequestCommon::new(NDNAPILevel::NDC),
+            owner: self.owner.clone(),
+            local_path: local_path.to_owned(),
+            chunk_size: self.chunk_size,
+            // The local file is a temp file, so the chunks must be copied to the chunk cache
+            chunk_method: TransPublishChunkMethod::Copy,
+            chunk_cdc: None,
+            access: None,
+            file_id: None,
+            base_file_id: None,
+            dirs: None,
+        };
+
+        let resp = self.stack.trans().publish_file(req).await.map_err(|e| {
+            error!("publish file failed! file={}, {}", local_path.display(), e);
+            e
+        })?;
+
+        Ok(resp.file_id)
+    }
+
+    // Set the value of the path and create the missing parent dirs, return the prev value
+    pub async fn set(&self, path: &str, value: &ObjectId) -> BuckyResult<Option<ObjectId>> {
+        let op_env = self.state_stub().create_path_op_env().await?;
+        let prev = match op_env.set_with_path(path, value, None, true).await {
+            Ok(prev) => prev,
+            Err(e) => {
+                error!("set file to global state failed! path={}, {}", path, e);
+                let _ = op_env.abort().await;
+                return Err(e);
+            }
+        };
+
+        op_env.commit().await?;
+        Ok(prev)
+    }
+
+    pub async fn create_dir(&self, path: &str) -> BuckyResult<()> {
+        let op_env = self.state_stub().create_path_op_env().await?;
+        if op_env.get_by_path(path).await?.is_some() {
+            let _ = op_env.abort().await;
+
+            let msg = format!("state path already exists: {}", path);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::AlreadyExists, msg));
+        }
+
+        op_env
+            .create_new_with_path(path, ObjectMapSimpleContentType::Map)
+            .await?;
+        op_env.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn remove(&self, path: &str) -> BuckyResult<Option<ObjectId>> {
+        let op_env = self.state_stub().create_path_op_env().await?;
+        let prev = op_env.remove_with_path(path, None).await?;
+        if prev.is_none() {
+            let _ = op_env.abort().await;
+            return Ok(None);
+        }
+
+        op_env.commit().await?;
+        Ok(prev)
+    }
+}
@@ -3,6 +3,7 @@ use crate::{NDNInputRequestCommon, TransTaskControlAction, TransTaskInfo, TransT
 use cyfs_base::{*};
 use cyfs_core::TransContext;
 use cyfs_util::cache::FileDirRef;
+use cyfs_util::CdcChunkParams;
 
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -139,10 +140,14 @@ pub struct TransPublishFileInputRequest {
     pub chunk_size: u32,
     // how to deal with chunk position tracker record 
     pub chunk_method: TransPublishChunkMethod, 
+    // 按内容切分chunk，设置后忽略chunk_size
+    pub chunk_cdc: Option<CdcChunkParams>,
 
     pub access: Option<AccessString>,
     
     pub file_id: Option<ObjectId>,
+    // 旧版本的文件，和旧版本相同的chunk直接复用
+    pub base_file_id: Option<ObjectId>,
     // 关联的dirs
     pub dirs: Option<Vec<FileDirRef>>,
 }
@@ -157,6 +162,7 @@ pub struct TransQueryTasksInputRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TransPublishFileInputResponse {
     pub file_id: ObjectId,
+    pub diff_id: Option<ObjectId>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -3,6 +3,7 @@ use cyfs_base::*;
 
 use cyfs_bdt::{NdnTaskControlState, NdnTaskState};
 use cyfs_core::TransContext;
+use cyfs_util::CdcChunkParams;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::path::PathBuf;
@@ -208,11 +209,18 @@ pub struct TransPublishFileOutputRequest {
 
     pub chunk_method: TransPublishChunkMethod, 
 
+    // Split the file by content-defined chunking instead of the fixed chunk_size, only used when the file object is calc internally
+    pub chunk_cdc: Option<CdcChunkParams>,
+
     pub access: Option<AccessString>,
     
     // The object_id of the file object to be published, if set, the file object is no longer calc internally and will direct load from NOC
     pub file_id: Option<ObjectId>,
 
+    // The previous version of the file, the chunks same as the base file will be reused and only the new chunks will be recorded,
+    // and a diff object will be saved to NOC
+    pub base_file_id: Option<ObjectId>,
+
     // The related objects
     pub dirs: Option<Vec<FileDirRef>>,
 }
@@ -220,6 +228,9 @@ pub struct TransPublishFileOutputRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TransPublishFileOutputResponse {
     pub file_id: ObjectId,
+
+    // The diff object relative to the base file, if base_file_id is specified
+    pub diff_id: Option<ObjectId>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -293,7 +293,9 @@ impl JsonCodec<TransPublishFileOutputRequest> for TransPublishFileOutputRequest
             Value::String(self.chunk_size.to_string()),
         );
         JsonCodecHelper::encode_option_string_field(&mut obj, "chunk_method", Some(format!("{:?}", self.chunk_method)).as_ref());
+        JsonCodecHelper::encode_option_field(&mut obj, "chunk_cdc", self.chunk_cdc.as_ref());
         JsonCodecHelper::encode_option_string_field(&mut obj, "file_id", self.file_id.as_ref());
+        JsonCodecHelper::encode_option_string_field(&mut obj, "base_file_id", self.base_file_id.as_ref());
 
         if let Some(dirs) = &self.dirs {
             let node = JsonCodecHelper::encode_to_array(dirs);
@@ -317,8 +319,10 @@ impl JsonCodec<TransPublishFileOutputRequest> for TransPublishFileOutputRequest
             local_path: JsonCodecHelper::decode_string_field(&obj, "local_path")?,
             chunk_size: JsonCodecHelper::decode_int_field(&obj, "chunk_size")?,
             chunk_method: JsonCodecHelper::decode_option_string_field(&obj, "chunk_method")?.unwrap_or(TransPublishChunkMethod::Track), 
+            chunk_cdc: JsonCodecHelper::decode_option_field(obj, "chunk_cdc")?,
             dirs: JsonCodecHelper::decode_option_array_field(&obj, "dirs")?,
             file_id: JsonCodecHelper::decode_option_string_field(obj, "file_id")?,
+            base_file_id: JsonCodecHelper::decode_option_string_field(obj, "base_file_id")?,
             access,
         })
     }
@@ -341,8 +345,10 @@ impl JsonCodec<TransPublishFileInputRequest> for TransPublishFileInputRequest {
         JsonCodecHelper::encode_string_field(&mut obj, "local_path", local_path);
         JsonCodecHelper::encode_number_field(&mut obj, "chunk_size", self.chunk_size);
         JsonCodecHelper::encode_option_string_field(&mut obj, "chunk_method", Some(format!("{:?}", self.chunk_method)).as_ref());
+        JsonCodecHelper::encode_option_field(&mut obj, "chunk_cdc", self.chunk_cdc.as_ref());
 
         JsonCodecHelper::encode_option_string_field(&mut obj, "file_id", self.file_id.as_ref());
+        JsonCodecHelper::encode_option_string_field(&mut obj, "base_file_id", self.base_file_id.as_ref());
 
         JsonCodecHelper::encode_as_option_list(&mut obj, "dirs", self.dirs.as_ref());
 
@@ -363,8 +369,10 @@ impl JsonCodec<TransPublishFileInputRequest> for TransPublishFileInputRequest {
             local_path: JsonCodecHelper::decode_string_field(&obj, "local_path")?,
             chunk_size: JsonCodecHelper::decode_int_field(&obj, "chunk_size")?,
             chunk_method: JsonCodecHelper::decode_option_string_field(&obj, "chunk_method")?.unwrap_or(TransPublishChunkMethod::Track), 
+            chunk_cdc: JsonCodecHelper::decode_option_field(obj, "chunk_cdc")?,
             dirs: JsonCodecHelper::decode_option_array_field(&obj, "dirs")?,
             file_id: JsonCodecHelper::decode_option_string_field(obj, "file_id")?,
+            base_file_id: JsonCodecHelper::decode_option_string_field(obj, "base_file_id")?,
             access,
         })
     }
@@ -1,7 +1,7 @@
 use super::output_request::*;
 use crate::{base::*, TransPublishChunkMethod};
 use cyfs_base::*;
-use cyfs_util::SystemInfoUpdater;
+use cyfs_util::{CdcChunkParams, SystemInfoUpdater};
 
 use std::path::PathBuf;
 
@@ -58,6 +58,13 @@ pub struct UtilGetNOCInfoInputRequest {
 
 pub type UtilGetNOCInfoInputResponse = UtilGetNOCInfoOutputResponse;
 
+// get_scrub_status
+pub struct UtilGetScrubStatusInputRequest {
+    pub common: UtilInputRequestCommon,
+}
+
+pub type UtilGetScrubStatusInputResponse = UtilGetScrubStatusOutputResponse;
+
 // get_device_static_info
 pub struct UtilGetDeviceStaticInfoInputRequest {
     pub common: UtilInputRequestCommon,
@@ -101,6 +108,7 @@ pub struct UtilBuildFileInputRequest {
     pub owner: ObjectId,
     pub chunk_size: u32,
     pub chunk_method: TransPublishChunkMethod,
+    pub chunk_cdc: Option<CdcChunkParams>,
     pub access: Option<AccessString>,
 }
 
@@ -4,7 +4,7 @@ use cyfs_base::*;
 use cyfs_core::ZoneId;
 use cyfs_core::*;
 use cyfs_bdt::SnStatus;
-use cyfs_util::SystemInfoUpdater;
+use cyfs_util::{CdcChunkParams, SystemInfoUpdater};
 use std::convert::TryFrom;
 
 use serde::{Deserialize, Serialize};
@@ -285,6 +285,93 @@ impl Display for UtilGetNOCInfoOutputResponse {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ScrubIssueKind {
+    // object's blob can't be decoded or the content doesn't match the object_id
+    ObjectCorrupted,
+    // object's meta is still in noc but the blob is missing
+    ObjectMissing,
+    // object's content is ok but no valid owner sign found
+    ObjectSignInvalid,
+    // chunk's content in chunk cache doesn't match the chunk_id
+    ChunkCorrupted,
+    // chunk can't be read from chunk cache, will be checked again in the next round
+    ChunkReadFailed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScrubIssue {
+    pub id: ObjectId,
+    pub kind: ScrubIssueKind,
+    pub msg: String,
+
+    // the corrupted data has been moved to quarantine dir
+    pub quarantined: bool,
+
+    // where the data repaired from, device id or "meta"
+    pub repaired_from: Option<String>,
+
+    pub time: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ScrubStatus {
+    pub enable: bool,
+    pub running: bool,
+
+    // the round count since stack started
+    pub round: u64,
+    pub round_start_time: u64,
+    pub last_round_complete_time: u64,
+
+    // progress of current round
+    pub objects_checked: u64,
+    pub chunks_checked: u64,
+    pub bytes_checked: u64,
+
+    // total count since stack started
+    pub corrupted: u64,
+    pub quarantined: u64,
+    pub repaired: u64,
+
+    // latest issues
+    pub issues: Vec<ScrubIssue>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UtilGetScrubStatusOutputRequest {
+    pub common: UtilOutputRequestCommon,
+}
+
+impl Display for UtilGetScrubStatusOutputRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "common: {}", self.common)
+    }
+}
+
+impl UtilGetScrubStatusOutputRequest {
+    pub fn new() -> Self {
+        Self {
+            common: UtilOutputRequestCommon::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UtilGetScrubStatusOutputResponse {
+    pub status: ScrubStatus,
+}
+
+impl Display for UtilGetScrubStatusOutputResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "round: {}, running: {}, corrupted: {}, repaired: {}",
+            self.status.round, self.status.running, self.status.corrupted, self.status.repaired
+        )
+    }
+}
+
 // 设备的一些静态信息
 #[derive(Debug, Clone)]
 pub struct DeviceStaticInfo {
@@ -545,6 +632,8 @@ pub struct UtilBuildFileOutputRequest {
     pub owner: ObjectId,
     pub chunk_size: u32, 
     pub chunk_method: TransPublishChunkMethod, 
+    // 按内容切分chunk，设置后忽略chunk_size
+    pub chunk_cdc: Option<CdcChunkParams>,
     pub access: Option<AccessString>,
 }
 
@@ -247,6 +247,7 @@ impl JsonCodec<UtilBuildFileOutputRequest> for UtilBuildFileOutputRequest {
         JsonCodecHelper::encode_string_field(&mut obj, "owner", &self.owner);
         JsonCodecHelper::encode_number_field(&mut obj, "chunk_size", self.chunk_size);
         JsonCodecHelper::encode_option_string_field(&mut obj, "chunk_method", Some(format!("{:?}", self.chunk_method)).as_ref());
+        JsonCodecHelper::encode_option_field(&mut obj, "chunk_cdc", self.chunk_cdc.as_ref());
 
         if let Some(access) = &self.access {
             JsonCodecHelper::encode_number_field(&mut obj, "access", access.value());
@@ -270,6 +271,7 @@ impl JsonCodec<UtilBuildFileOutputRequest> for UtilBuildFileOutputRequest {
             owner: JsonCodecHelper::decode_string_field(obj, "owner")?,
             chunk_size: JsonCodecHelper::decode_int_field(obj, "chunk_size")?, 
             chunk_method: JsonCodecHelper::decode_option_string_field(&obj, "chunk_method")?.unwrap_or_default(), 
+            chunk_cdc: JsonCodecHelper::decode_option_field(obj, "chunk_cdc")?,
             access,
         })
     }
@@ -24,6 +24,9 @@ pub trait UtilOutputProcessor: Sync + Send + 'static {
     async fn get_noc_info(&self, req: UtilGetNOCInfoOutputRequest)
         -> BuckyResult<UtilGetNOCInfoOutputResponse>;
 
+    async fn get_scrub_status(&self, req: UtilGetScrubStatusOutputRequest)
+        -> BuckyResult<UtilGetScrubStatusOutputResponse>;
+
     async fn get_network_access_info(&self, req: UtilGetNetworkAccessInfoOutputRequest)
         -> BuckyResult<UtilGetNetworkAccessInfoOutputResponse>;
 
@@ -19,6 +19,9 @@ pub type UtilGetOODStatusResponse = UtilGetOODStatusOutputResponse;
 pub type UtilGetNOCInfoRequest = UtilGetNOCInfoOutputRequest;
 pub type UtilGetNOCInfoResponse = UtilGetNOCInfoOutputResponse;
 
+pub type UtilGetScrubStatusRequest = UtilGetScrubStatusOutputRequest;
+pub type UtilGetScrubStatusResponse = UtilGetScrubStatusOutputResponse;
+
 pub type UtilGetDeviceStaticInfoRequest = UtilGetDeviceStaticInfoOutputRequest;
 pub type UtilGetDeviceStaticInfoResponse = UtilGetDeviceStaticInfoOutputResponse;
 
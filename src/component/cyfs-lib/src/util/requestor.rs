@@ -256,6 +256,38 @@ impl UtilRequestor {
         }
     }
 
+    fn encode_get_scrub_status_request(&self, req: UtilGetScrubStatusRequest) -> Request {
+        let url = self.service_url.join("scrub_status").unwrap();
+        let mut http_req = Request::new(Method::Get, url);
+        self.encode_common_headers(&req.common, &mut http_req);
+
+        http_req
+    }
+
+    pub async fn get_scrub_status(
+        &self,
+        req: UtilGetScrubStatusRequest,
+    ) -> BuckyResult<UtilGetScrubStatusResponse> {
+        let http_req = self.encode_get_scrub_status_request(req);
+
+        let mut resp = self.requestor.request(http_req).await?;
+
+        if resp.status().is_success() {
+            let resp = resp.body_json().await.map_err(|e| {
+                let msg = format!("parse get_scrub_status resp body error! err={}", e);
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::InvalidData, msg)
+            })?;
+
+            Ok(resp)
+        } else {
+            let e = RequestorHelper::error_from_resp(&mut resp).await;
+            error!("util get_scrub_status failed: status={}, {}", resp.status(), e);
+
+            Err(e)
+        }
+    }
+
     fn encode_get_network_access_info_request(
         &self,
         req: UtilGetNetworkAccessInfoRequest,
@@ -543,6 +575,13 @@ impl UtilOutputProcessor for UtilRequestor {
         Self::get_noc_info(&self, req).await
     }
 
+    async fn get_scrub_status(
+        &self,
+        req: UtilGetScrubStatusRequest,
+    ) -> BuckyResult<UtilGetScrubStatusResponse> {
+        Self::get_scrub_status(&self, req).await
+    }
+
     async fn get_network_access_info(
         &self,
         req: UtilGetNetworkAccessInfoRequest,
@@ -1018,6 +1018,15 @@ impl MetaClient {
         self.request_miner(req, &mut Vec::new()).await
     }
 
+    // 在tip状态上预执行交易但不上链，返回receipt和实际会扣除的手续费，交易可以不签名
+    pub async fn simulate_tx(&self, tx: MetaTx) -> BuckyResult<SimulateTxResult> {
+        let body = tx.encode_to_vec(true)?;
+        let url = self.gen_url("simulate");
+        let mut req = Request::new(Method::Post, url);
+        req.set_body(body);
+        self.request_miner(req, &mut Vec::new()).await
+    }
+
     pub async fn create_contract(
         &self,
         caller: &StandardObject,
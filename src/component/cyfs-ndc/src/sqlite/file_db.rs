@@ -1788,14 +1788,25 @@ impl SqliteDBDataCache {
             querys.push(query);
         }
 
+        if let Some(last_access_before) = req.filter.last_access_before {
+            params.push(Box::new(last_access_before));
+
+            let query = format!("last_access_time<?{}", params.len());
+            querys.push(query);
+        }
+
         let sql = if querys.len() > 0 {
             "SELECT chunk_id FROM chunk WHERE ".to_owned() + &querys.join(" AND ")
         } else {
             "SELECT chunk_id FROM chunk ".to_owned()
         };
 
-        // Sort by insert_time, decrease
-        let sql = sql + " ORDER BY insert_time DESC ";
+        let sql = match req.opt.order {
+            // Sort by insert_time, decrease
+            SelectChunkOrder::InsertTime => sql + " ORDER BY insert_time DESC ",
+            // LRU: the least recently used first
+            SelectChunkOrder::LastAccessTime => sql + " ORDER BY last_access_time ASC ",
+        };
 
         // Add pagination
         let sql = sql
@@ -18,6 +18,9 @@ pub struct BlobStorageDeleteObjectResponse {
 pub trait BlobStorage: Send + Sync {
     async fn put_object(&self, data: NONObjectInfo) -> BuckyResult<()>;
     async fn get_object(&self, object_id: &ObjectId) -> BuckyResult<Option<NONObjectInfo>>;
+
+    // the stored data without decoding
+    async fn get_object_raw(&self, object_id: &ObjectId) -> BuckyResult<Option<Vec<u8>>>;
     async fn delete_object(&self, object_id: &ObjectId, flags: u32) -> BuckyResult<BlobStorageDeleteObjectResponse>;
     async fn exists_object(&self, object_id: &ObjectId) -> BuckyResult<bool>;
     async fn stat(&self) -> BuckyResult<BlobStorageStat>;
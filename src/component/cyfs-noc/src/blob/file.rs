@@ -69,7 +69,14 @@ impl FileBlobStorage {
     }
 
     async fn load_object(&self, path: &Path) -> BuckyResult<NONObjectInfo> {
-        let object_raw = async_std::fs::read(&path).await.map_err(|e| {
+        let object_raw = Self::load_object_raw(path).await?;
+
+        let info = NONObjectInfo::new_from_object_raw(object_raw)?;
+        Ok(info)
+    }
+
+    async fn load_object_raw(path: &Path) -> BuckyResult<Vec<u8>> {
+        async_std::fs::read(&path).await.map_err(|e| {
             let msg = format!(
                 "read object blob from file error! path={}, {}",
                 path.display(),
@@ -77,10 +84,7 @@ impl FileBlobStorage {
             );
             error!("{}", msg);
             BuckyError::new(BuckyErrorCode::IoError, msg)
-        })?;
-
-        let info = NONObjectInfo::new_from_object_raw(object_raw)?;
-        Ok(info)
+        })
     }
 
     fn write_sync<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> std::io::Result<()> {
@@ -147,6 +151,17 @@ impl BlobStorage for FileBlobStorage {
         Ok(Some(info))
     }
 
+    async fn get_object_raw(&self, object_id: &ObjectId) -> BuckyResult<Option<Vec<u8>>> {
+        let path = self.get_full_path(object_id, false).await?;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let object_raw = Self::load_object_raw(&path).await?;
+
+        Ok(Some(object_raw))
+    }
+
     async fn delete_object(
         &self,
         object_id: &ObjectId,
@@ -153,6 +153,11 @@ impl NamedObjectCache for NamedObjectCacheMemoryCache {
         &self,
         req: &NamedObjectCacheGetObjectRequest,
     ) -> BuckyResult<Option<NamedObjectCacheObjectRawData>> {
+        // meta only result has no object, never cached
+        if req.is_meta_only() {
+            return self.next.get_object_raw(req).await;
+        }
+
         let cache_item = self.get(req).await?;
         if cache_item.is_some() {
             if !req.is_no_update_last_access() {
@@ -246,6 +251,10 @@ impl NamedObjectCache for NamedObjectCacheMemoryCache {
         self.next.select_object(req).await
     }
 
+    async fn get_object_blob(&self, object_id: &ObjectId) -> BuckyResult<Option<Vec<u8>>> {
+        self.next.get_object_blob(object_id).await
+    }
+
     fn bind_object_meta_access_provider(
         &self,
         object_meta_access_provider: NamedObjectCacheObjectMetaAccessProviderRef,
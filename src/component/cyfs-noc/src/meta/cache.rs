@@ -158,6 +158,18 @@ impl NamedObjectMeta for NamedObjectMetaWithAccessCache {
     ) -> BuckyResult<NamedObjectMetaSelectObjectResponse> {
         self.next.select_object(req).await
     }
+
+    async fn select_unsized_objects(
+        &self,
+        after: Option<&ObjectId>,
+        limit: usize,
+    ) -> BuckyResult<Vec<ObjectId>> {
+        self.next.select_unsized_objects(after, limit).await
+    }
+
+    async fn update_object_size(&self, object_id: &ObjectId, object_size: u64) -> BuckyResult<()> {
+        self.next.update_object_size(object_id, object_size).await
+    }
     
     fn bind_object_meta_access_provider(
         &self,
@@ -30,6 +30,7 @@ pub struct NamedObjectMetaPutObjectRequest {
     pub body_prev_version: Option<HashValue>,
     pub ref_objs: Option<Vec<ObjectLink>>,
     pub nonce: Option<u128>,
+    pub object_size: u64,
 
     pub storage_category: NamedObjectStorageCategory,
     pub context: Option<String>,
@@ -149,6 +150,8 @@ pub type NamedObjectMetaCheckObjectAccessRequest = NamedObjectCacheCheckObjectAc
 pub struct NamedObjectMetaStat {
     pub count: u64,
     pub storage_size: u64,
+
+    pub usage: Vec<NamedObjectCacheUsage>,
 }
 
 pub type NamedObjectMetaSelectObjectRequest = NamedObjectCacheSelectObjectRequest;
@@ -195,6 +198,15 @@ pub trait NamedObjectMeta: Sync + Send {
         req: &NamedObjectMetaSelectObjectRequest,
     ) -> BuckyResult<NamedObjectMetaSelectObjectResponse>;
 
+    // object_size为0的对象(version 2之前缓存的对象)，按object_id排序，从after之后开始
+    async fn select_unsized_objects(
+        &self,
+        after: Option<&ObjectId>,
+        limit: usize,
+    ) -> BuckyResult<Vec<ObjectId>>;
+
+    async fn update_object_size(&self, object_id: &ObjectId, object_size: u64) -> BuckyResult<()>;
+
     fn bind_object_meta_access_provider(
         &self,
         object_meta_access_provider: NamedObjectCacheObjectMetaAccessProviderRef,
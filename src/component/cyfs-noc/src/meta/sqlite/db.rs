@@ -376,6 +376,8 @@ impl SqliteMetaStorage {
 
         debug!("noc meta count objects {}", ret);
 
+        let usage = self.stat_usage()?;
+
         let meta = async_std::fs::metadata(&self.data_file)
             .await
             .map_err(|e| {
@@ -391,25 +393,93 @@ impl SqliteMetaStorage {
         let stat = NamedObjectMetaStat {
             count: ret as u64,
             storage_size: meta.len(),
+            usage,
         };
 
         Ok(stat)
     }
 
+    // 按storage_category和create_dec_id分组统计，用于配额检查
+    fn stat_usage(&self) -> BuckyResult<Vec<NamedObjectCacheUsage>> {
+        const STAT_USAGE_SQL: &str = r#"
+            SELECT storage_category, create_dec_id, COUNT(*), SUM(object_size) 
+            FROM data_namedobject_meta GROUP BY storage_category, create_dec_id
+        "#;
+
+        let (conn, _lock) = self.conn.get_read_conn()?;
+        let mut stmt = conn.prepare(STAT_USAGE_SQL).map_err(|e| {
+            let msg = format!("prepare stat usage sql error: {}", e);
+            error!("{}", msg);
+
+            BuckyError::new(BuckyErrorCode::SqliteError, msg)
+        })?;
+
+        let mut rows = stmt.query([]).map_err(|e| {
+            let msg = format!("noc meta stat usage error! {}", e);
+            error!("{}", msg);
+
+            BuckyError::new(BuckyErrorCode::SqliteError, msg)
+        })?;
+
+        let mut list = Vec::new();
+        while let Some(row) = rows.next()? {
+            let (storage_category, dec_id, count, size) = (|| -> rusqlite::Result<_> {
+                let storage_category: Option<u8> = row.get(0)?;
+                let dec_id: Option<String> = row.get(1)?;
+                let count: i64 = row.get(2)?;
+                let size: Option<i64> = row.get(3)?;
+                Ok((storage_category, dec_id, count, size))
+            })()
+            .map_err(|e| {
+                let msg = format!("get usage from query row failed! {}", e);
+                error!("{}", msg);
+
+                BuckyError::new(BuckyErrorCode::SqliteError, msg)
+            })?;
+
+            let storage_category = match storage_category {
+                Some(v) => match NamedObjectStorageCategory::try_from(v) {
+                    Ok(v) => v,
+                    Err(_) => continue,
+                },
+                None => NamedObjectStorageCategory::default(),
+            };
+
+            // create_dec_id在插入时总会设置
+            let dec_id = match dec_id.as_deref().map(ObjectId::from_str) {
+                Some(Ok(v)) => v,
+                Some(Err(e)) => {
+                    error!("invalid create_dec_id str: {:?}, {}", dec_id, e);
+                    continue;
+                }
+                None => continue,
+            };
+
+            list.push(NamedObjectCacheUsage {
+                storage_category,
+                dec_id,
+                count: count as u64,
+                size: size.unwrap_or(0) as u64,
+            });
+        }
+
+        Ok(list)
+    }
+
     fn insert_new(&self, req: &NamedObjectMetaPutObjectRequest) -> BuckyResult<usize> {
         const INSERT_NEW_SQL: &str = r#"INSERT INTO data_namedobject_meta 
         (   object_id, owner_id, object_type, 
             create_dec_id, insert_time, update_time, 
             object_create_time, object_update_time, object_expired_time,
             author, dec_id, prev, body_prev_version, ref_objs, 
-            nonce, difficulty,
+            nonce, difficulty, object_size, access_count,
             storage_category, context, last_access_time, last_access_rpath, access
         ) VALUES
         (   :object_id, :owner_id, :object_type,
             :create_dec_id, :insert_time, :update_time, 
             :object_create_time, :object_update_time, :object_expired_time,
             :author, :dec_id, :prev, :body_prev_version, :ref_objs, 
-            :nonce, :difficulty,
+            :nonce, :difficulty, :object_size, 0,
             :storage_category, :context, :last_access_time, :last_access_rpath, :access
         ) "#;
 
@@ -435,6 +505,7 @@ impl SqliteMetaStorage {
 
             ":nonce": req.nonce.as_ref().map(|v| v.to_be_bytes()),
             ":difficulty": 0,
+            ":object_size": req.object_size,
 
             ":storage_category": req.storage_category.as_u8(),
 
@@ -608,6 +679,7 @@ impl SqliteMetaStorage {
             context = :context,
             last_access_time = :last_access_time, last_access_rpath = :last_access_rpath,
             body_prev_version = :body_prev_version,
+            object_size = :object_size,
             access = :access
             WHERE object_id = :object_id 
             AND object_update_time = :current_object_update_time 
@@ -626,6 +698,7 @@ impl SqliteMetaStorage {
             ":current_update_time": current_info.update_time,
             ":current_insert_time": current_info.insert_time,
             ":body_prev_version": req.body_prev_version.as_ref().map(|v| v.as_slice()),
+            ":object_size": req.object_size,
             ":access": req.access_string,
         };
 
@@ -817,7 +890,8 @@ impl SqliteMetaStorage {
         req: &NamedObjectMetaUpdateLastAccessRequest,
     ) -> BuckyResult<usize> {
         const UPDATE_SQL: &str = r#"
-        UPDATE data_namedobject_meta SET last_access_time = :last_access_time, last_access_rpath = :last_access_rpath 
+        UPDATE data_namedobject_meta SET last_access_time = :last_access_time, last_access_rpath = :last_access_rpath, 
+            access_count = access_count + 1 
             WHERE object_id = :object_id 
             AND last_access_time <= :last_access_time
         "#;
@@ -1316,14 +1390,45 @@ impl SqliteMetaStorage {
             querys.push(query);
         }
 
+        if let Some(storage_category) = &req.filter.storage_category {
+            params.push(Box::new(storage_category.as_u8()));
+
+            let query = format!("storage_category=?{}", params.len());
+            querys.push(query);
+        }
+
+        if let Some(dec_id) = &req.filter.create_dec_id {
+            params.push(Box::new(dec_id.to_string()));
+
+            let query = format!("create_dec_id=?{}", params.len());
+            querys.push(query);
+        }
+
+        if let Some(last_access_before) = req.filter.last_access_before {
+            params.push(Box::new(last_access_before));
+
+            let query = format!("last_access_time<?{}", params.len());
+            querys.push(query);
+        }
+
         let sql = if querys.len() > 0 {
             "SELECT object_id FROM data_namedobject_meta WHERE ".to_owned() + &querys.join(" AND ")
         } else {
             "SELECT object_id FROM data_namedobject_meta ".to_owned()
         };
 
-        // Sort by insert_time, decrease
-        let sql = sql + " ORDER BY insert_time DESC ";
+        let sql = match req.opt.order {
+            // Sort by insert_time, decrease
+            NamedObjectCacheSelectObjectOrder::InsertTime => sql + " ORDER BY insert_time DESC ",
+            // LRU: the least recently used first
+            NamedObjectCacheSelectObjectOrder::LastAccessTime => {
+                sql + " ORDER BY last_access_time ASC "
+            }
+            // LFU: the least frequently used first
+            NamedObjectCacheSelectObjectOrder::AccessCount => {
+                sql + " ORDER BY access_count ASC, last_access_time ASC "
+            }
+        };
 
         // Add pagination
         let sql = sql
@@ -1386,6 +1491,78 @@ impl SqliteMetaStorage {
 
         Ok(resp)
     }
+
+    fn select_unsized_objects(
+        &self,
+        after: Option<&ObjectId>,
+        limit: usize,
+    ) -> BuckyResult<Vec<ObjectId>> {
+        const SELECT_SQL: &str = r#"
+            SELECT object_id FROM data_namedobject_meta 
+            WHERE object_size = 0 AND object_id > :after 
+            ORDER BY object_id LIMIT :limit
+        "#;
+
+        let params = named_params! {
+            ":after": after.map(|id| id.to_string()).unwrap_or_default(),
+            ":limit": limit as i64,
+        };
+
+        let (conn, _lock) = self.conn.get_read_conn()?;
+        let mut stmt = conn.prepare(SELECT_SQL).map_err(|e| {
+            let msg = format!("prepare select unsized objects sql error: {}", e);
+            error!("{}", msg);
+
+            BuckyError::new(BuckyErrorCode::SqliteError, msg)
+        })?;
+
+        let mut rows = stmt.query(params).map_err(|e| {
+            let msg = format!("noc meta select unsized objects error! {}", e);
+            error!("{}", msg);
+
+            BuckyError::new(BuckyErrorCode::SqliteError, msg)
+        })?;
+
+        let mut list = Vec::new();
+        while let Some(row) = rows.next()? {
+            let object_id: String = row.get(0).map_err(|e| {
+                let msg = format!("get object_id from query row failed! {}", e);
+                error!("{}", msg);
+
+                BuckyError::new(BuckyErrorCode::SqliteError, msg)
+            })?;
+
+            match ObjectId::from_str(&object_id) {
+                Ok(object_id) => list.push(object_id),
+                Err(e) => {
+                    error!("invalid object_id str: {}, {}", object_id, e);
+                }
+            }
+        }
+
+        Ok(list)
+    }
+
+    fn update_object_size(&self, object_id: &ObjectId, object_size: u64) -> BuckyResult<()> {
+        const UPDATE_SQL: &str = r#"
+            UPDATE data_namedobject_meta SET object_size = :object_size WHERE object_id = :object_id
+        "#;
+
+        let params = named_params! {
+            ":object_size": object_size,
+            ":object_id": object_id.to_string(),
+        };
+
+        let (conn, _lock) = self.conn.get_write_conn()?;
+        conn.execute(UPDATE_SQL, params).map_err(|e| {
+            let msg = format!("noc meta update object size error: {} {}", object_id, e);
+            error!("{}", msg);
+
+            BuckyError::new(BuckyErrorCode::SqliteError, msg)
+        })?;
+
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -1452,6 +1629,18 @@ impl NamedObjectMeta for SqliteMetaStorage {
         Self::select(self, req).await
     }
 
+    async fn select_unsized_objects(
+        &self,
+        after: Option<&ObjectId>,
+        limit: usize,
+    ) -> BuckyResult<Vec<ObjectId>> {
+        Self::select_unsized_objects(&self, after, limit)
+    }
+
+    async fn update_object_size(&self, object_id: &ObjectId, object_size: u64) -> BuckyResult<()> {
+        Self::update_object_size(&self, object_id, object_size)
+    }
+
     fn bind_object_meta_access_provider(
         &self,
         object_meta_access_provider: NamedObjectCacheObjectMetaAccessProviderRef,
@@ -1,6 +1,6 @@
 // 当前的数据库版本
-pub(super) const CURRENT_VERSION: i32 = 1;
-const SET_DB_VERSION: &'static str = concat!("PRAGMA USER_VERSION = ", 1);
+pub(super) const CURRENT_VERSION: i32 = 2;
+const SET_DB_VERSION: &'static str = concat!("PRAGMA USER_VERSION = ", 2);
 
 pub(super) const DATA_NAMEDOBJECT_META_INIT: &'static str = r#"
 CREATE TABLE IF NOT EXISTS data_namedobject_meta (
@@ -35,7 +35,11 @@ CREATE TABLE IF NOT EXISTS data_namedobject_meta (
     ref_objs BLOB,
 
     nonce BLOB,
-    difficulty INTEGER
+    difficulty INTEGER,
+
+    /* version 2 */
+    object_size INTEGER DEFAULT 0,
+    access_count INTEGER DEFAULT 0
 );"#;
 
 pub(super) const DATA_NAMEDOBJECT_META_INSERT_TIME_INDEX: &'static str = r#"
@@ -46,10 +50,15 @@ pub(super) const DATA_NAMEDOBJECT_META_INSERT_LAST_ACCESS_INDEX: &'static str =
 CREATE INDEX IF NOT EXISTS `data_namedobject_meta_last_access_time_index` on `data_namedobject_meta` (`last_access_time`);
 "#;
 
-pub(super) const INIT_NAMEDOBJECT_META_SQL_LIST: [&'static str; 4] = [
+pub(super) const DATA_NAMEDOBJECT_META_CATEGORY_DEC_INDEX: &'static str = r#"
+CREATE INDEX IF NOT EXISTS `data_namedobject_meta_category_dec_index` on `data_namedobject_meta` (`storage_category`, `create_dec_id`);
+"#;
+
+pub(super) const INIT_NAMEDOBJECT_META_SQL_LIST: [&'static str; 5] = [
     DATA_NAMEDOBJECT_META_INIT,
     DATA_NAMEDOBJECT_META_INSERT_TIME_INDEX,
     DATA_NAMEDOBJECT_META_INSERT_LAST_ACCESS_INDEX,
+    DATA_NAMEDOBJECT_META_CATEGORY_DEC_INDEX,
     SET_DB_VERSION,
 ];
 
@@ -66,7 +75,15 @@ ALTER TABLE `data_namedobject_meta` ADD COLUMN nonce BLOB DEFAULT NULL;
 ALTER TABLE `data_namedobject_meta` ADD COLUMN difficulty BLOB DEFAULT 0;
 "#;
 
+// version 2 alters, for quota and eviction
+pub(super) const DATA_NAMEDOBJECT_META_UPDATE_2: &'static str = r#"
+ALTER TABLE `data_namedobject_meta` ADD COLUMN object_size INTEGER DEFAULT 0;
+ALTER TABLE `data_namedobject_meta` ADD COLUMN access_count INTEGER DEFAULT 0;
+CREATE INDEX IF NOT EXISTS `data_namedobject_meta_category_dec_index` on `data_namedobject_meta` (`storage_category`, `create_dec_id`);
+"#;
+
 // For all version upgrades, MAIN_TABLE_UPDATE_LIST[CURRENT_VERSION - 1] is the corresponding upgrade sql
 pub(super) const MAIN_TABLE_UPDATE_LIST: [[&'static str; 1]; CURRENT_VERSION as usize] = [
-    [DATA_NAMEDOBJECT_META_UPDATE_1]
+    [DATA_NAMEDOBJECT_META_UPDATE_1],
+    [DATA_NAMEDOBJECT_META_UPDATE_2],
 ];
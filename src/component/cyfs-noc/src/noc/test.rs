@@ -200,13 +200,40 @@ async fn test_noc() {
 
     // select
     let select_req = NamedObjectCacheSelectObjectRequest {
-        filter: NamedObjectCacheSelectObjectFilter { obj_type: None },
+        filter: NamedObjectCacheSelectObjectFilter::default(),
         opt: NamedObjectCacheSelectObjectOption::default(),
     };
 
     let resp = noc.select_object(&select_req).await.unwrap();
     info!("select result: {:?}", resp);
 
+    // select cached objects for evict, least recently used first
+    let select_req = NamedObjectCacheSelectObjectRequest {
+        filter: NamedObjectCacheSelectObjectFilter {
+            storage_category: Some(NamedObjectStorageCategory::Cache),
+            last_access_before: Some(bucky_time_now() + 1),
+            ..Default::default()
+        },
+        opt: NamedObjectCacheSelectObjectOption {
+            order: NamedObjectCacheSelectObjectOrder::LastAccessTime,
+            ..Default::default()
+        },
+    };
+
+    let resp = noc.select_object(&select_req).await.unwrap();
+    assert!(resp.list.iter().any(|item| item.object_id == object_id));
+
+    // stat usage by storage category and dec
+    let stat = noc.stat().await.unwrap();
+    info!("stat result: {:?}", stat);
+    let usage = stat
+        .usage
+        .iter()
+        .find(|item| item.storage_category == NamedObjectStorageCategory::Cache)
+        .unwrap();
+    assert!(usage.count > 0);
+    assert!(usage.size > 0);
+
     // delete by system
     let delete_req = NamedObjectCacheDeleteObjectRequest {
         source: RequestSourceInfo::new_local_system(),
@@ -229,7 +256,7 @@ async fn test_noc() {
 
     // select
     let select_req = NamedObjectCacheSelectObjectRequest {
-        filter: NamedObjectCacheSelectObjectFilter { obj_type: None },
+        filter: NamedObjectCacheSelectObjectFilter::default(),
         opt: NamedObjectCacheSelectObjectOption::default(),
     };
 
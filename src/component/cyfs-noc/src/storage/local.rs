@@ -6,9 +6,12 @@ use cyfs_lib::*;
 use std::path::Path;
 use std::sync::Arc;
 
+// 每次补齐object_size的对象数量
+const BACKFILL_OBJECT_SIZE_PAGE: usize = 256;
+
 pub struct NamedObjectLocalStorage {
     meta: NamedObjectMetaRef,
-    blob: Box<dyn BlobStorage>,
+    blob: Arc<Box<dyn BlobStorage>>,
 }
 
 impl NamedObjectLocalStorage {
@@ -31,13 +34,73 @@ impl NamedObjectLocalStorage {
         }
 
         // Init blob module
-        let blob = create_blob_storage(&dir).await?;
+        let blob = Arc::new(create_blob_storage(&dir).await?);
 
         let meta = Self::init_meta(&dir)?;
 
+        // version 2之前缓存的对象没有object_size，后台从blob里补齐，否则配额统计和淘汰看不到这些对象
+        {
+            let meta = meta.clone();
+            let blob = blob.clone();
+            async_std::task::spawn(async move {
+                Self::backfill_object_size(meta, blob).await;
+            });
+        }
+
         Ok(Self { blob, meta })
     }
 
+    async fn backfill_object_size(meta: NamedObjectMetaRef, blob: Arc<Box<dyn BlobStorage>>) {
+        let mut after: Option<ObjectId> = None;
+        let mut count = 0;
+        loop {
+            let list = match meta
+                .select_unsized_objects(after.as_ref(), BACKFILL_OBJECT_SIZE_PAGE)
+                .await
+            {
+                Ok(list) => list,
+                Err(e) => {
+                    error!("select unsized objects from noc meta failed! {}", e);
+                    break;
+                }
+            };
+
+            for object_id in list.iter() {
+                match blob.get_object(object_id).await {
+                    Ok(Some(data)) => {
+                        let object_size = data.object_raw.len() as u64;
+                        if let Err(e) = meta.update_object_size(object_id, object_size).await {
+                            error!("backfill noc object size failed! obj={}, {}", object_id, e);
+                        } else {
+                            count += 1;
+                        }
+                    }
+                    Ok(None) => {
+                        warn!(
+                            "backfill noc object size but blob not found! obj={}",
+                            object_id
+                        );
+                    }
+                    Err(e) => {
+                        error!(
+                            "backfill noc object size but load blob failed! obj={}, {}",
+                            object_id, e
+                        );
+                    }
+                }
+            }
+
+            if list.len() < BACKFILL_OBJECT_SIZE_PAGE {
+                break;
+            }
+            after = list.last().cloned();
+        }
+
+        if count > 0 {
+            info!("backfill noc object size complete! count={}", count);
+        }
+    }
+
     pub fn meta(&self) -> &NamedObjectMetaRef {
         &self.meta
     }
@@ -164,6 +227,7 @@ impl NamedObjectLocalStorage {
             body_prev_version,
             ref_objs,
             nonce,
+            object_size: request.object.object_raw.len() as u64,
 
             storage_category: request.storage_category,
             context: request.context.clone(),
@@ -299,6 +363,9 @@ impl NamedObjectLocalStorage {
         }
 
         let mut meta = meta_ret.unwrap();
+        if req.is_meta_only() {
+            return Ok(Some(NamedObjectCacheObjectRawData { object: None, meta }));
+        }
 
         // try get object data from blob
         let blob_ret = self.blob.get_object(&meta.object_id).await?;
@@ -470,6 +537,7 @@ impl NamedObjectLocalStorage {
         let resp = NamedObjectCacheStat {
             count: meta.count,
             storage_size: meta.storage_size + blob.storage_size,
+            usage: meta.usage,
         };
 
         Ok(resp)
@@ -538,6 +606,10 @@ impl NamedObjectCache for NamedObjectLocalStorage {
         Self::select_object(self, req).await
     }
 
+    async fn get_object_blob(&self, object_id: &ObjectId) -> BuckyResult<Option<Vec<u8>>> {
+        self.blob.get_object_raw(object_id).await
+    }
+
     fn bind_object_meta_access_provider(
         &self,
         object_meta_access_provider: NamedObjectCacheObjectMetaAccessProviderRef,
@@ -199,6 +199,18 @@ impl NamedObjectCache for NamedObjectCacheSerializer {
         self.next.select_object(req).await
     }
 
+    async fn get_object_blob(&self, object_id: &ObjectId) -> BuckyResult<Option<Vec<u8>>> {
+        let lock = self.acquire_lock(object_id);
+        let ret = {
+            let _guard = lock.lock.lock().await;
+            self.next.get_object_blob(object_id).await
+        };
+
+        self.leave_lock(object_id, lock);
+
+        ret
+    }
+
     fn bind_object_meta_access_provider(
         &self,
         object_meta_access_provider: NamedObjectCacheObjectMetaAccessProviderRef,
@@ -34,6 +34,9 @@ pub(crate) struct BdtParams {
 
     // sn ping interval in seconds, default is 25s
     pub ping_interval: Option<u32>,
+
+    // compress chunks in local storage and pieces on ndn channel with zstd, default is false
+    pub chunk_compress: Option<bool>,
 }
 
 impl Default for BdtParams {
@@ -46,6 +49,7 @@ impl Default for BdtParams {
             udp_sn_only: None,
             sn_mode: SNMode::default(),
             ping_interval: None,
+            chunk_compress: None,
         }
     }
 }
@@ -141,6 +145,9 @@ impl BdtConfigLoader {
                 "ping_interval" => {
                     self.params.ping_interval = Some(TomlHelper::decode_to_int(v)?);
                 }
+                "chunk_compress" => {
+                    self.params.chunk_compress = Some(TomlHelper::decode_from_boolean(v)?);
+                }
                 _ => {
                     warn!("unknown stack.bdt.config field: {}", k.as_str());
                 }
@@ -41,6 +41,7 @@ device = "${device_file_name}"
 #udp_sn_only = false
 #sn_mode = "normal"
 #ping_interval = 25
+#chunk_compress = false
 
 ${endpoints}
 "#;
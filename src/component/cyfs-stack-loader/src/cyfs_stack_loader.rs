@@ -1,10 +1,11 @@
 use crate::bdt_loader::*;
 use crate::ListenerUtil;
-use cyfs_base::{BuckyError, BuckyErrorCode, BuckyResult};
+use cyfs_base::{BuckyError, BuckyErrorCode, BuckyResult, ObjectId};
 use cyfs_stack::CyfsStackParams;
 use cyfs_util::TomlHelper;
 
 use std::net::SocketAddr;
+use std::str::FromStr;
 
 // 配置的默认协议栈的缺省名字
 const DEFAULT_BDT_STACK_ID: &str = "default";
@@ -84,6 +85,39 @@ impl CyfsStackConfigLoader {
                     self.load_front(v.as_table().unwrap())?;
                 }
 
+                "erasure" => {
+                    if !v.is_table() {
+                        let msg = format!("invalid non stack.erasure field format: {:?}", v);
+                        error!("{}", msg);
+
+                        return Err(BuckyError::new(BuckyErrorCode::InvalidFormat, msg));
+                    }
+
+                    self.load_erasure(v.as_table().unwrap())?;
+                }
+
+                "scrub" => {
+                    if !v.is_table() {
+                        let msg = format!("invalid non stack.scrub field format: {:?}", v);
+                        error!("{}", msg);
+
+                        return Err(BuckyError::new(BuckyErrorCode::InvalidFormat, msg));
+                    }
+
+                    self.load_scrub(v.as_table().unwrap())?;
+                }
+
+                "evict" => {
+                    if !v.is_table() {
+                        let msg = format!("invalid non stack.evict field format: {:?}", v);
+                        error!("{}", msg);
+
+                        return Err(BuckyError::new(BuckyErrorCode::InvalidFormat, msg));
+                    }
+
+                    self.load_evict(v.as_table().unwrap())?;
+                }
+
                 "noc" => {
                     if !v.is_table() {
                         let msg = format!("invalid non stack.noc field format: {:?}", v);
@@ -186,6 +220,122 @@ impl CyfsStackConfigLoader {
         Ok(())
     }
 
+    fn load_erasure(&mut self, node: &toml::value::Table) -> BuckyResult<()> {
+        for (k, v) in node {
+            match k.as_str() {
+                "enable" => {
+                    self.params.cyfs_stack_params.erasure.enable = TomlHelper::decode_from_boolean(v)?;
+                }
+                "data_fragments" => {
+                    self.params.cyfs_stack_params.erasure.data_fragments = TomlHelper::decode_to_int(v)?;
+                }
+                "parity_fragments" => {
+                    self.params.cyfs_stack_params.erasure.parity_fragments = TomlHelper::decode_to_int(v)?;
+                }
+                "check_interval_secs" => {
+                    self.params.cyfs_stack_params.erasure.check_interval_secs = TomlHelper::decode_to_int(v)?;
+                }
+                _ => {
+                    warn!("unknown non stack.erasure field: {}", k.as_str());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_scrub(&mut self, node: &toml::value::Table) -> BuckyResult<()> {
+        for (k, v) in node {
+            match k.as_str() {
+                "enable" => {
+                    self.params.cyfs_stack_params.scrub.enable = TomlHelper::decode_from_boolean(v)?;
+                }
+                "interval_secs" => {
+                    self.params.cyfs_stack_params.scrub.interval_secs = TomlHelper::decode_to_int(v)?;
+                }
+                "max_bytes_per_sec" => {
+                    self.params.cyfs_stack_params.scrub.max_bytes_per_sec = TomlHelper::decode_to_int(v)?;
+                }
+                "max_items_per_sec" => {
+                    self.params.cyfs_stack_params.scrub.max_items_per_sec = TomlHelper::decode_to_int(v)?;
+                }
+                "repair" => {
+                    self.params.cyfs_stack_params.scrub.repair = TomlHelper::decode_from_boolean(v)?;
+                }
+                _ => {
+                    warn!("unknown non stack.scrub field: {}", k.as_str());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_evict(&mut self, node: &toml::value::Table) -> BuckyResult<()> {
+        for (k, v) in node {
+            match k.as_str() {
+                "enable" => {
+                    self.params.cyfs_stack_params.evict.enable = TomlHelper::decode_from_boolean(v)?;
+                }
+                "interval_secs" => {
+                    self.params.cyfs_stack_params.evict.interval_secs = TomlHelper::decode_to_int(v)?;
+                }
+                "policy" => {
+                    self.params.cyfs_stack_params.evict.policy = TomlHelper::decode_from_string(v)?;
+                }
+                "noc_cache_budget" => {
+                    self.params.cyfs_stack_params.evict.noc_cache_budget = TomlHelper::decode_to_int(v)?;
+                }
+                "noc_storage_budget" => {
+                    self.params.cyfs_stack_params.evict.noc_storage_budget = TomlHelper::decode_to_int(v)?;
+                }
+                "dec_cache_budget" => {
+                    // dec_cache_budget = 1024 or dec_cache_budget = { default = 1024, "{dec_id}" = 2048 }
+                    if let Some(table) = v.as_table() {
+                        self.load_evict_dec_budget(table)?;
+                    } else {
+                        self.params.cyfs_stack_params.evict.dec_cache_budget = TomlHelper::decode_to_int(v)?;
+                    }
+                }
+                "chunk_cache_budget" => {
+                    self.params.cyfs_stack_params.evict.chunk_cache_budget = TomlHelper::decode_to_int(v)?;
+                }
+                "min_idle_secs" => {
+                    self.params.cyfs_stack_params.evict.min_idle_secs = TomlHelper::decode_to_int(v)?;
+                }
+                _ => {
+                    warn!("unknown non stack.evict field: {}", k.as_str());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_evict_dec_budget(&mut self, node: &toml::value::Table) -> BuckyResult<()> {
+        for (k, v) in node {
+            let budget = TomlHelper::decode_to_int(v)?;
+            if k.as_str() == "default" {
+                self.params.cyfs_stack_params.evict.dec_cache_budget = budget;
+                continue;
+            }
+
+            let dec_id = ObjectId::from_str(k.as_str()).map_err(|e| {
+                let msg = format!("invalid non stack.evict.dec_cache_budget dec_id: {}, {}", k, e);
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+            })?;
+
+            self.params
+                .cyfs_stack_params
+                .evict
+                .dec_cache_budget_list
+                .insert(dec_id, budget);
+        }
+
+        Ok(())
+    }
+
     fn load_meta(&mut self, node: &toml::value::Table) -> BuckyResult<()> {
         for (k, v) in node {
             match k.as_str() {
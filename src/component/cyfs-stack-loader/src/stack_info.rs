@@ -155,6 +155,7 @@ impl StackInfo {
             udp_sn_only: self.bdt_params.udp_sn_only,
             sn_mode: self.bdt_params.sn_mode,
             ping_interval: self.bdt_params.ping_interval,
+            chunk_compress: self.bdt_params.chunk_compress,
         };
 
         bdt_param
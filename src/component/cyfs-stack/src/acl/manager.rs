@@ -2,6 +2,7 @@ use super::config::AclConfig;
 use super::loader::AclFileLoader;
 use super::loader::AclLoader;
 use super::zone_cache::*;
+use crate::crypto_api::KeyChainManagerRef;
 use crate::resolver::DeviceCache;
 use crate::rmeta_api::GlobalStateMetaLocalService;
 use crate::zone::ZoneManagerRef;
@@ -76,6 +77,7 @@ pub struct AclManager {
     file_loader: AclFileLoader,
 
     local_zone_cache: LocalZoneCache,
+    key_chain: KeyChainManagerRef,
 
     config: OnceCell<AclConfig>,
 }
@@ -87,6 +89,7 @@ impl AclManager {
         noc: NamedObjectCacheRef,
         config_isolate: Option<String>,
         zone_manager: ZoneManagerRef,
+        key_chain: KeyChainManagerRef,
     ) -> Self {
         let local_zone_cache = LocalZoneCache::new(zone_manager.clone(), noc.clone());
 
@@ -98,6 +101,7 @@ impl AclManager {
             zone_manager,
             file_loader,
             local_zone_cache,
+            key_chain,
             config: OnceCell::new(),
         }
     }
@@ -152,9 +156,22 @@ impl AclManager {
     }
 
     pub async fn is_current_zone_device(&self, device_id: &DeviceId) -> BuckyResult<bool> {
-        self.local_zone_cache
+        let ret = self
+            .local_zone_cache
             .is_current_zone_device(device_id)
-            .await
+            .await?;
+        if !ret {
+            return Ok(false);
+        }
+
+        // 被owner吊销的设备不再视为同zone设备
+        let info = self.zone_manager.get_current_info().await?;
+        if self.key_chain.is_revoked(device_id, &info.owner_id).await {
+            warn!("device is revoked by zone owner: device={}, owner={}", device_id, info.owner_id);
+            return Ok(false);
+        }
+
+        Ok(true)
     }
 
     // 同协议栈检查
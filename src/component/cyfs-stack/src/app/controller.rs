@@ -322,6 +322,12 @@ impl AppController {
             AppManagerActionEnum::ModifyAcl(_action) => {
                 todo!();
             }
+            AppManagerActionEnum::InstallPlan(plan) => {
+                info!(
+                    "recv app install plan: dec={}, version={}, items={:?}",
+                    plan.dec_id, plan.version, plan.items
+                );
+            }
         }
 
         Ok(())
@@ -1,3 +1,4 @@
+use super::super::local::{ObjectInfo, ObjectVerifier};
 use crate::acl::*;
 use crate::crypto::*;
 use cyfs_base::*;
@@ -7,15 +8,52 @@ use std::sync::Arc;
 
 pub(crate) struct CryptoAclInputProcessor {
     acl: AclManagerRef,
+    verifier: Arc<ObjectVerifier>,
     next: CryptoInputProcessorRef,
 }
 
 impl CryptoAclInputProcessor {
-    pub fn new(acl: AclManagerRef, next: CryptoInputProcessorRef) -> CryptoInputProcessorRef {
-        let ret = Self { acl, next };
+    pub fn new(
+        acl: AclManagerRef,
+        verifier: Arc<ObjectVerifier>,
+        next: CryptoInputProcessorRef,
+    ) -> CryptoInputProcessorRef {
+        let ret = Self {
+            acl,
+            verifier,
+            next,
+        };
         Arc::new(Box::new(ret))
     }
 
+    // 门限签名的分片请求可以来自其它zone，但来源设备必须是该对象的签名设备
+    async fn check_threshold_part(&self, req: &CryptoSignObjectInputRequest) -> BuckyResult<()> {
+        let source_device = req.common.source.zone.device.as_ref().ok_or_else(|| {
+            let msg = format!(
+                "threshold sign part from other zone but source device missing! obj={}, source={}",
+                req.object.object_id, req.common.source
+            );
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::PermissionDenied, msg)
+        })?;
+
+        let object = ObjectInfo {
+            object_id: req.object.object_id.clone(),
+            object: req.object.clone_object(),
+        };
+        let policy = self.verifier.load_threshold_policy(&object).await?;
+        if !policy.is_holder(source_device) {
+            let msg = format!(
+                "threshold sign part from other zone but source is not holder! obj={}, owner={}, source={}",
+                req.object.object_id, policy.owner_id, source_device
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::PermissionDenied, msg));
+        }
+
+        Ok(())
+    }
+
     async fn check_access(
         &self,
         name: &str,
@@ -54,6 +92,14 @@ impl CryptoInputProcessor for CryptoAclInputProcessor {
         &self,
         req: CryptoSignObjectInputRequest,
     ) -> BuckyResult<CryptoSignObjectInputResponse> {
+        if req.flags & CRYPTO_REQUEST_FLAG_SIGN_THRESHOLD_PART != 0
+            && !req.common.source.is_current_zone()
+        {
+            // 本地设备是否为签名设备和签名方式的限制由router检查
+            self.check_threshold_part(&req).await?;
+            return self.next.sign_object(req).await;
+        }
+
         req.common.source.check_current_zone("crypto.sign_object")?;
 
         self.check_access("sign_object", &req.common.source, RequestOpType::Call).await?;
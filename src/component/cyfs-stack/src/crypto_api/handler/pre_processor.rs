@@ -29,18 +29,39 @@ impl CryptoHandlerPreProcessor {
         &self,
         request: CryptoSignObjectInputRequest,
     ) -> BuckyResult<CryptoSignObjectInputResponse> {
+        // 门限签名分片必须由匹配到的handler明确放行(返回Default)，没有handler匹配或者都pass时拒绝
+        let default_action =
+            if request.flags & CRYPTO_REQUEST_FLAG_SIGN_THRESHOLD_PART != 0 {
+                RouterHandlerAction::Reject
+            } else {
+                RouterHandlerAction::Default
+            };
+
         let mut param = RouterHandlerSignObjectRequest {
             request,
             response: None,
         };
 
-        if let Some(handler) = self.handlers.try_sign_object() {
-            if !handler.is_empty() {
+        match self.handlers.try_sign_object() {
+            Some(handler) if !handler.is_empty() => {
                 let mut handler = NONHandlerCaller::new(handler.emitter());
-                if let Some(resp) = handler.call("sign_object", &mut param).await? {
+                if let Some(resp) = handler
+                    .call_with_default("sign_object", &mut param, default_action)
+                    .await?
+                {
                     return resp;
                 }
             }
+            _ => {
+                if default_action == RouterHandlerAction::Reject {
+                    let msg = format!(
+                        "threshold sign part but no pre-crypto sign handler to approve! obj={}, source={}",
+                        param.request.object.object_id, param.request.common.source
+                    );
+                    warn!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::Reject, msg));
+                }
+            }
         }
 
         self.next.sign_object(param.request).await
@@ -0,0 +1,501 @@
+use crate::meta::*;
+use crate::zone::ZoneManagerRef;
+use cyfs_base::*;
+use cyfs_bdt::StackGuard;
+use cyfs_core::*;
+use cyfs_lib::*;
+
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// 轮换链和吊销列表的缓存时间
+const KEY_CHAIN_CACHE_TIMEOUT_IN_MICROS: u64 = 10 * 60 * 1000 * 1000;
+
+// 定期把zone owner的吊销列表同步给bdt协议栈
+const REVOKED_SYNC_INTERVAL_IN_SECS: u64 = 10 * 60;
+
+struct CacheItem<T> {
+    value: T,
+    last_update: u64,
+}
+
+impl<T: Clone> CacheItem<T> {
+    fn new(value: T) -> Self {
+        Self {
+            value,
+            last_update: bucky_time_now(),
+        }
+    }
+
+    fn get(&self) -> Option<T> {
+        if bucky_time_now() < self.last_update + KEY_CHAIN_CACHE_TIMEOUT_IN_MICROS {
+            Some(self.value.clone())
+        } else {
+            None
+        }
+    }
+}
+
+/*
+密钥轮换和设备吊销
+1. KeyRotation的id只和(subject, seq)相关，从seq=1开始依次在noc和meta上查找，组成轮换链
+   同一个(subject, seq)只接受第一条记录：noc和meta上的记录内容不一致，或者和之前接受的记录不一致时拒绝，轮换链不会分叉
+2. 每条轮换记录需要由上一个密钥签名，device的轮换记录也可以由owner的当前密钥签名
+3. DeviceRevocationList每个owner一个，由owner签名
+4. 签名里的sign_time由签名方控制，不能作为吊销的判断依据；只发生过轮换时按sign_time选择密钥，见ObjectVerifier的SignKeySelector
+5. 本地zone owner的吊销列表会同步给本地bdt协议栈，拒绝和被吊销设备建立连接；sn通过SnService::update_revoked获取吊销列表
+*/
+pub(crate) struct KeyChainManager {
+    noc: OnceCell<NamedObjectCacheRef>,
+    meta_cache: Box<dyn MetaCache>,
+
+    chains: Mutex<HashMap<ObjectId, CacheItem<KeyRotationChain>>>,
+
+    // 已经接受的轮换记录，key是轮换记录的id，同一个(subject, seq)之后出现的不同记录都会被拒绝
+    accepted_rotations: Mutex<HashMap<ObjectId, KeyRotation>>,
+
+    revocations: Mutex<HashMap<ObjectId, CacheItem<Option<DeviceRevocationList>>>>,
+}
+
+pub(crate) type KeyChainManagerRef = Arc<KeyChainManager>;
+
+impl KeyChainManager {
+    pub fn new(meta_cache: Box<dyn MetaCache>) -> Self {
+        Self {
+            noc: OnceCell::new(),
+            meta_cache,
+            chains: Mutex::new(HashMap::new()),
+            accepted_rotations: Mutex::new(HashMap::new()),
+            revocations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn bind_noc(&self, noc: NamedObjectCacheRef) {
+        if let Err(_) = self.noc.set(noc) {
+            unreachable!();
+        }
+    }
+
+    fn noc(&self) -> &NamedObjectCacheRef {
+        self.noc.get().unwrap()
+    }
+
+    // 对象的密钥轮换链，只有单公钥的对象才支持轮换
+    pub async fn get_chain(
+        &self,
+        subject_id: &ObjectId,
+        subject: &AnyNamedObject,
+    ) -> BuckyResult<Option<KeyRotationChain>> {
+        let origin = match subject.public_key() {
+            Some(PublicKeyRef::Single(pk)) => pk.clone(),
+            _ => return Ok(None),
+        };
+
+        if let Some(item) = self.chains.lock().unwrap().get(subject_id) {
+            if let Some(chain) = item.get() {
+                return Ok(Some(chain));
+            }
+        }
+
+        // device的轮换记录也可以由owner签名，owner自身的轮换链只接受自己签名
+        let mut owner_key = None;
+        if subject_id.obj_type_code() == ObjectTypeCode::Device {
+            if let Some(owner_id) = subject.owner() {
+                match self.search_object(owner_id).await {
+                    Ok(Some(owner)) => {
+                        if let Some(PublicKeyRef::Single(pk)) = owner.public_key() {
+                            let chain = self.load_chain(owner_id, pk.clone(), None).await?;
+                            owner_key = Some(chain.current().clone());
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!(
+                            "search device's owner for key rotation failed! device={}, owner={}, {}",
+                            subject_id, owner_id, e
+                        );
+                    }
+                }
+            }
+        }
+
+        let chain = self
+            .load_chain(subject_id, origin, owner_key.as_ref())
+            .await?;
+        self.chains
+            .lock()
+            .unwrap()
+            .insert(subject_id.to_owned(), CacheItem::new(chain.clone()));
+
+        Ok(Some(chain))
+    }
+
+    async fn load_chain(
+        &self,
+        subject_id: &ObjectId,
+        origin: PublicKey,
+        owner_key: Option<&PublicKey>,
+    ) -> BuckyResult<KeyRotationChain> {
+        let mut chain = KeyRotationChain::new(subject_id.to_owned(), origin);
+        loop {
+            let id = KeyRotation::rotation_id(subject_id, chain.next_seq());
+            let rotation = match self
+                .load_rotation(subject_id, chain.next_seq(), &id)
+                .await?
+            {
+                Some(rotation) => rotation,
+                None => break,
+            };
+
+            if let Err(e) = chain.check_next(&rotation) {
+                warn!(
+                    "invalid key rotation, will ignore the rest! subject={}, seq={}, {}",
+                    subject_id,
+                    rotation.seq(),
+                    e
+                );
+                break;
+            }
+
+            if !Self::verify_rotation(&chain, &rotation, owner_key).await {
+                warn!(
+                    "verify key rotation sign failed, will ignore the rest! subject={}, seq={}",
+                    subject_id,
+                    rotation.seq()
+                );
+                break;
+            }
+
+            info!(
+                "load key rotation: subject={}, seq={}, effective_time={}",
+                subject_id,
+                rotation.seq(),
+                rotation.effective_time()
+            );
+            self.accepted_rotations
+                .lock()
+                .unwrap()
+                .entry(id)
+                .or_insert_with(|| rotation.clone());
+            chain.append(rotation)?;
+        }
+
+        Ok(chain)
+    }
+
+    // 加载(subject, seq)的轮换记录，已经接受过的记录优先；没有接受过时noc和meta上的记录必须一致
+    async fn load_rotation(
+        &self,
+        subject_id: &ObjectId,
+        seq: u32,
+        id: &ObjectId,
+    ) -> BuckyResult<Option<KeyRotation>> {
+        let accepted = self.accepted_rotations.lock().unwrap().get(id).cloned();
+
+        let mut list = vec![];
+        if let Some(object_raw) = self.get_object_from_noc(id).await? {
+            list.push(KeyRotation::clone_from_slice(&object_raw)?);
+        }
+        if let Some(object_raw) = self.get_object_from_meta(id).await {
+            list.push(KeyRotation::clone_from_slice(&object_raw)?);
+        }
+
+        if let Some(accepted) = accepted {
+            for rotation in list.iter() {
+                if !Self::is_same_rotation(&accepted, rotation) {
+                    warn!(
+                        "got conflict key rotation with accepted one, will ignore! subject={}, seq={}",
+                        subject_id, seq
+                    );
+                }
+            }
+            return Ok(Some(accepted));
+        }
+
+        let mut ret: Option<KeyRotation> = None;
+        for rotation in list {
+            match &ret {
+                Some(prev) => {
+                    if !Self::is_same_rotation(prev, &rotation) {
+                        error!(
+                            "got conflict key rotations for same seq, will ignore the rest! subject={}, seq={}",
+                            subject_id, seq
+                        );
+                        return Ok(None);
+                    }
+                }
+                None => ret = Some(rotation),
+            }
+        }
+
+        Ok(ret)
+    }
+
+    // 签名不同但是内容相同的记录不算冲突
+    fn is_same_rotation(left: &KeyRotation, right: &KeyRotation) -> bool {
+        left.old_key() == right.old_key()
+            && left.new_key() == right.new_key()
+            && left.effective_time() == right.effective_time()
+    }
+
+    async fn verify_rotation(
+        chain: &KeyRotationChain,
+        rotation: &KeyRotation,
+        owner_key: Option<&PublicKey>,
+    ) -> bool {
+        let signs = match rotation.signs().body_signs() {
+            Some(signs) => signs,
+            None => return false,
+        };
+
+        let mut keys = vec![chain.current()];
+        if let Some(owner_key) = owner_key {
+            keys.push(owner_key);
+        }
+
+        for key in keys {
+            let verifier = RsaCPUObjectVerifier::new(key.clone());
+            for sign in signs {
+                if let Ok(true) = verify_object_body_sign(&verifier, rotation, sign).await {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    // owner的设备吊销列表，签名校验不通过的视为没有
+    // 本地noc和meta上的列表取body更新时间较新的一个；查询失败或者查到的更旧时保留之前的列表，吊销不能因此失效
+    pub async fn get_revocation_list(
+        &self,
+        owner_id: &ObjectId,
+    ) -> BuckyResult<Option<DeviceRevocationList>> {
+        let prev = match self.revocations.lock().unwrap().get(owner_id) {
+            Some(item) => match item.get() {
+                Some(list) => return Ok(list),
+                None => item.value.clone(),
+            },
+            None => None,
+        };
+
+        let id = DeviceRevocationList::list_id(owner_id);
+        let local = match self.get_object_from_noc(&id).await? {
+            Some(object_raw) => self.decode_revocation_list(owner_id, &object_raw).await?,
+            None => None,
+        };
+        let remote = match self.get_object_from_meta(&id).await {
+            Some(object_raw) => self.decode_revocation_list(owner_id, &object_raw).await?,
+            None => None,
+        };
+
+        let mut list = local.clone();
+        for item in vec![remote, prev] {
+            if Self::list_update_time(&item) > Self::list_update_time(&list) {
+                list = item;
+            }
+        }
+
+        if Self::list_update_time(&list) > Self::list_update_time(&local) {
+            if let Err(e) = self.save_to_noc(list.as_ref().unwrap()).await {
+                warn!(
+                    "save device revocation list to noc failed! owner={}, {}",
+                    owner_id, e
+                );
+            }
+        }
+
+        self.revocations
+            .lock()
+            .unwrap()
+            .insert(owner_id.to_owned(), CacheItem::new(list.clone()));
+
+        Ok(list)
+    }
+
+    fn list_update_time(list: &Option<DeviceRevocationList>) -> u64 {
+        list.as_ref()
+            .and_then(|list| list.body().as_ref().map(|body| body.update_time()))
+            .unwrap_or(0)
+    }
+
+    async fn decode_revocation_list(
+        &self,
+        owner_id: &ObjectId,
+        object_raw: &[u8],
+    ) -> BuckyResult<Option<DeviceRevocationList>> {
+        let list = DeviceRevocationList::clone_from_slice(object_raw)?;
+        if self.verify_revocation_list(owner_id, &list).await? {
+            Ok(Some(list))
+        } else {
+            warn!(
+                "verify device revocation list sign failed! owner={}",
+                owner_id
+            );
+            Ok(None)
+        }
+    }
+
+    async fn verify_revocation_list(
+        &self,
+        owner_id: &ObjectId,
+        list: &DeviceRevocationList,
+    ) -> BuckyResult<bool> {
+        let owner = match self.search_object(owner_id).await? {
+            Some(owner) => owner,
+            None => {
+                warn!(
+                    "device revocation list's owner not found! owner={}",
+                    owner_id
+                );
+                return Ok(false);
+            }
+        };
+
+        let key = match self.get_chain(owner_id, &owner).await? {
+            Some(chain) => chain.current().clone(),
+            None => return Ok(false),
+        };
+
+        let signs = match list.signs().body_signs() {
+            Some(signs) => signs,
+            None => return Ok(false),
+        };
+
+        let verifier = RsaCPUObjectVerifier::new(key);
+        for sign in signs {
+            if verify_object_body_sign(&verifier, list, sign).await? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    // 设备是否在owner的吊销列表里
+    pub async fn is_revoked(&self, device_id: &DeviceId, owner_id: &ObjectId) -> bool {
+        match self.get_revocation_list(owner_id).await {
+            Ok(Some(list)) => list.revoke_time(device_id).is_some(),
+            Ok(None) => false,
+            Err(e) => {
+                warn!(
+                    "get device revocation list failed! owner={}, {}",
+                    owner_id, e
+                );
+                false
+            }
+        }
+    }
+
+    pub fn start_revoked_monitor(
+        self: &Arc<Self>,
+        zone_manager: ZoneManagerRef,
+        bdt_stack: StackGuard,
+    ) {
+        let this = self.clone();
+        async_std::task::spawn(async move {
+            loop {
+                this.sync_revoked(&zone_manager, &bdt_stack).await;
+                async_std::task::sleep(Duration::from_secs(REVOKED_SYNC_INTERVAL_IN_SECS)).await;
+            }
+        });
+    }
+
+    async fn sync_revoked(&self, zone_manager: &ZoneManagerRef, bdt_stack: &StackGuard) {
+        let info = match zone_manager.get_current_info().await {
+            Ok(info) => info,
+            Err(e) => {
+                warn!("get current zone info for revoked sync failed! {}", e);
+                return;
+            }
+        };
+
+        let list = match self.get_revocation_list(&info.owner_id).await {
+            Ok(Some(list)) => list
+                .list()
+                .iter()
+                .map(|item| item.device_id.clone())
+                .collect(),
+            Ok(None) => vec![],
+            Err(e) => {
+                warn!(
+                    "get device revocation list for revoked sync failed! owner={}, {}",
+                    info.owner_id, e
+                );
+                return;
+            }
+        };
+
+        bdt_stack.keystore().update_revoked(&info.owner_id, list);
+    }
+
+    async fn search_object(&self, object_id: &ObjectId) -> BuckyResult<Option<AnyNamedObject>> {
+        match self.search_object_raw(object_id).await? {
+            Some(object_raw) => {
+                let (obj, _) = AnyNamedObject::raw_decode(&object_raw)?;
+                let real_id = obj.object_id();
+                if real_id != *object_id {
+                    let msg = format!("object id not match: except={}, got={}", object_id, real_id);
+                    error!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+                }
+
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // 先从本地noc查找，再从meta查找
+    async fn search_object_raw(&self, object_id: &ObjectId) -> BuckyResult<Option<Vec<u8>>> {
+        if let Some(object_raw) = self.get_object_from_noc(object_id).await? {
+            return Ok(Some(object_raw));
+        }
+
+        Ok(self.get_object_from_meta(object_id).await)
+    }
+
+    async fn get_object_from_noc(&self, object_id: &ObjectId) -> BuckyResult<Option<Vec<u8>>> {
+        let req = NamedObjectCacheGetObjectRequest {
+            object_id: object_id.clone(),
+            source: RequestSourceInfo::new_local_system(),
+            last_access_rpath: None,
+            flags: 0,
+        };
+
+        Ok(self
+            .noc()
+            .get_object(&req)
+            .await?
+            .map(|obj| obj.object.object_raw))
+    }
+
+    // meta出错视为不存在，避免影响正常的签名校验
+    async fn get_object_from_meta(&self, object_id: &ObjectId) -> Option<Vec<u8>> {
+        match self.meta_cache.get_object(object_id).await {
+            Ok(Some(data)) => Some(data.object_raw),
+            Ok(None) => None,
+            Err(e) => {
+                warn!("search object from meta failed! obj={}, {}", object_id, e);
+                None
+            }
+        }
+    }
+
+    async fn save_to_noc(&self, list: &DeviceRevocationList) -> BuckyResult<()> {
+        let object = NONObjectInfo::new(list.desc().calculate_id(), list.to_vec()?, None);
+        let req = NamedObjectCachePutObjectRequest {
+            source: RequestSourceInfo::new_local_system(),
+            object,
+            storage_category: NamedObjectStorageCategory::Storage,
+            context: None,
+            last_access_rpath: None,
+            access_string: Some(AccessString::full_except_write().value()),
+        };
+
+        self.noc().put_object(&req).await?;
+        Ok(())
+    }
+}
@@ -2,6 +2,10 @@ mod obj_crypto;
 mod obj_signer;
 mod obj_verifier;
 mod codec;
+mod key_chain;
+mod threshold;
 
+pub(crate) use key_chain::*;
 pub(crate) use obj_crypto::*;
-pub(crate) use obj_verifier::*;
\ No newline at end of file
+pub(crate) use obj_verifier::*;
+pub(crate) use threshold::*;
\ No newline at end of file
@@ -1,5 +1,8 @@
+use super::key_chain::*;
+use super::threshold::*;
 use crate::meta::*;
 use cyfs_base::*;
+use cyfs_core::*;
 use cyfs_lib::*;
 
 use once_cell::sync::OnceCell;
@@ -22,24 +25,103 @@ pub struct VerifyObjectInnerRequest {
     pub sign_object: VerifyObjectType,
 }
 
+// 选择校验用的公钥
+// 有可信时间(owner会签的签名时间)时，按可信时间选择当时生效的密钥，可信时间在吊销之后的签名无效
+// 没有可信时间时，被吊销的设备签名全部无效；只发生过轮换的，按签名里的sign_time选择当时生效的密钥，
+// 旧密钥只接受sign_time早于下一次轮换effective_time的签名，轮换前签名的对象在轮换后仍然有效
+struct SignKeySelector {
+    // (生效时间, verifier)，按生效时间递增
+    verifiers: Vec<(u64, Box<dyn Verifier>)>,
+    revoke_time: Option<u64>,
+    trusted_time: Option<u64>,
+}
+
+impl SignKeySelector {
+    fn new(pk: &PublicKey) -> Self {
+        Self {
+            verifiers: vec![(0, ObjectVerifier::new_verifier(pk))],
+            revoke_time: None,
+            trusted_time: None,
+        }
+    }
+
+    fn with_chain(chain: &KeyRotationChain, revoke_time: Option<u64>) -> Self {
+        let mut verifiers = vec![(0, ObjectVerifier::new_verifier(chain.origin()))];
+        for rotation in chain.list() {
+            verifiers.push((
+                rotation.effective_time(),
+                ObjectVerifier::new_verifier(rotation.new_key()),
+            ));
+        }
+
+        Self {
+            verifiers,
+            revoke_time,
+            trusted_time: None,
+        }
+    }
+
+    // 被吊销或者发生过轮换时，才需要可信时间来选择密钥，没有可信时间的轮换按签名时间选择
+    fn need_trusted_time(&self) -> bool {
+        self.revoke_time.is_some() || self.verifiers.len() > 1
+    }
+
+    fn select(&self, sign_time: u64) -> Option<&Box<dyn Verifier>> {
+        let time = match self.trusted_time {
+            Some(trusted_time) => {
+                if let Some(revoke_time) = self.revoke_time {
+                    if trusted_time >= revoke_time {
+                        return None;
+                    }
+                }
+                trusted_time
+            }
+            None => {
+                if self.revoke_time.is_some() {
+                    return None;
+                }
+                sign_time
+            }
+        };
+
+        self.verifiers
+            .iter()
+            .rev()
+            .find(|(effective_time, _)| *effective_time <= time)
+            .map(|(_, verifier)| verifier)
+    }
+
+    fn public_key(&self) -> &PublicKey {
+        self.verifiers.last().unwrap().1.public_key()
+    }
+}
+
 pub struct ObjectVerifier {
     noc: OnceCell<NamedObjectCacheRef>,
 
     local_device_id: DeviceId,
 
     meta_cache: Box<dyn MetaCache>,
+
+    key_chain: KeyChainManagerRef,
 }
 
 impl ObjectVerifier {
-    pub fn new(local_device_id: DeviceId, meta_cache: Box<dyn MetaCache>) -> Self {
+    pub(crate) fn new(
+        local_device_id: DeviceId,
+        meta_cache: Box<dyn MetaCache>,
+        key_chain: KeyChainManagerRef,
+    ) -> Self {
         Self {
             noc: OnceCell::new(),
             meta_cache,
             local_device_id,
+            key_chain,
         }
     }
 
     pub(crate) fn bind_noc(&self, noc: NamedObjectCacheRef) {
+        self.key_chain.bind_noc(noc.clone());
         if let Err(_) = self.noc.set(noc) {
             unreachable!();
         }
@@ -135,7 +217,235 @@ impl ObjectVerifier {
                 error!("{}", msg);
                 Err(BuckyError::new(BuckyErrorCode::NotSupport, msg))
             }
+            VerifyObjectType::Threshold => {
+                let (_, verify_result) = self
+                    .verify_threshold(&req.object, &req.sign_type)
+                    .await?;
+                Ok(verify_result)
+            }
+        }
+    }
+
+    // 对象的门限签名策略，主体为owner，没有owner的People/Group为其自身
+    pub(crate) async fn load_threshold_policy(
+        &self,
+        object: &ObjectInfo,
+    ) -> BuckyResult<ThresholdSignPolicy> {
+        match object.object.owner() {
+            Some(owner_id) => {
+                let owner = self.search_object(owner_id).await.map_err(|e| {
+                    error!(
+                        "search threshold sign object's owner failed: obj={}, owner={}, {}",
+                        object.object_id, owner_id, e
+                    );
+                    e
+                })?;
+                self.load_subject_threshold_policy(owner_id, &owner).await
+            }
+            None => {
+                self.load_subject_threshold_policy(&object.object_id, &object.object)
+                    .await
+            }
+        }
+    }
+
+    async fn load_subject_threshold_policy(
+        &self,
+        subject_id: &ObjectId,
+        subject: &AnyNamedObject,
+    ) -> BuckyResult<ThresholdSignPolicy> {
+        match subject {
+            AnyNamedObject::Standard(StandardObject::Group(group)) => {
+                let admin_devices = self.load_group_admin_devices(subject_id, group).await;
+                ThresholdSignPolicy::load_group(subject_id, group.admins().len(), admin_devices)
+            }
+            _ => ThresholdSignPolicy::load(subject_id, subject),
+        }
+    }
+
+    // 每个admin取第一个ood作为签名设备，加载失败的admin不参与签名
+    async fn load_group_admin_devices(
+        &self,
+        group_id: &ObjectId,
+        group: &Group,
+    ) -> Vec<(DeviceId, PublicKey)> {
+        let mut admin_ids: Vec<&ObjectId> = group.admins().keys().collect();
+        admin_ids.sort();
+
+        let mut ret = vec![];
+        for admin_id in admin_ids {
+            let admin = match self.search_object(admin_id).await {
+                Ok(admin) => admin,
+                Err(e) => {
+                    warn!(
+                        "search group admin for threshold sign failed, will ignore! group={}, admin={}, {}",
+                        group_id, admin_id, e
+                    );
+                    continue;
+                }
+            };
+
+            let device_id = match admin.ood_list() {
+                Ok(list) if !list.is_empty() => list[0].clone(),
+                _ => {
+                    warn!(
+                        "group admin has no ood for threshold sign, will ignore! group={}, admin={}",
+                        group_id, admin_id
+                    );
+                    continue;
+                }
+            };
+
+            let device = match self.search_object(device_id.object_id()).await {
+                Ok(device) => device,
+                Err(e) => {
+                    warn!(
+                        "search group admin's ood for threshold sign failed, will ignore! group={}, admin={}, ood={}, {}",
+                        group_id, admin_id, device_id, e
+                    );
+                    continue;
+                }
+            };
+
+            match device.public_key() {
+                Some(PublicKeyRef::Single(pk)) => ret.push((device_id, pk.clone())),
+                _ => {
+                    warn!(
+                        "group admin's ood has no single public key, will ignore! group={}, admin={}, ood={}",
+                        group_id, admin_id, device_id
+                    );
+                }
+            }
+        }
+
+        ret
+    }
+
+    // 校验签名设备的有效签名数是否达到门限，desc和body分别计数
+    pub(crate) async fn verify_threshold(
+        &self,
+        req: &ObjectInfo,
+        sign_type: &VerifySignType,
+    ) -> BuckyResult<(ThresholdSignPolicy, VerifyObjectResult)> {
+        let policy = self.load_threshold_policy(req).await?;
+
+        // 每个MN公钥最多对应一个签名设备，不在MN公钥集合里的设备签名不计数
+        let mut used_keys: Vec<PublicKey> = Vec::new();
+        let mut holders = Vec::new();
+        for device_id in &policy.holders {
+            match self.load_holder_keys(&policy, device_id, req).await {
+                Ok((pk, keys)) => {
+                    if used_keys.contains(&pk) {
+                        warn!(
+                            "threshold sign holder's key already used by other holder, will ignore! obj={}, holder={}",
+                            req.object_id, device_id
+                        );
+                        continue;
+                    }
+                    used_keys.push(pk);
+                    holders.push((device_id.object_id().to_owned(), keys));
+                }
+                Err(e) => {
+                    warn!(
+                        "load threshold sign holder's key failed, will ignore! obj={}, holder={}, {}",
+                        req.object_id, device_id, e
+                    );
+                }
+            }
+        }
+
+        let mut verify_result = VerifyObjectResult::default();
+        let mut valid = true;
+        if sign_type.desc() {
+            let pass_count = self
+                .count_threshold_signs(&holders, req, &VerifySignType::Desc, &mut verify_result)
+                .await;
+            if pass_count < policy.threshold {
+                warn!(
+                    "verify object desc threshold signs but not enough! obj={}, owner={}, n={}, m={}, pass={}",
+                    req.object_id,
+                    policy.owner_id,
+                    policy.holders.len(),
+                    policy.threshold,
+                    pass_count,
+                );
+                valid = false;
+            }
+        }
+
+        if sign_type.body() {
+            let pass_count = self
+                .count_threshold_signs(&holders, req, &VerifySignType::Body, &mut verify_result)
+                .await;
+            if pass_count < policy.threshold {
+                warn!(
+                    "verify object body threshold signs but not enough! obj={}, owner={}, n={}, m={}, pass={}",
+                    req.object_id,
+                    policy.owner_id,
+                    policy.holders.len(),
+                    policy.threshold,
+                    pass_count,
+                );
+                valid = false;
+            }
+        }
+
+        verify_result.valid = valid;
+        Ok((policy, verify_result))
+    }
+
+    async fn load_holder_keys(
+        &self,
+        policy: &ThresholdSignPolicy,
+        device_id: &DeviceId,
+        req: &ObjectInfo,
+    ) -> BuckyResult<(PublicKey, SignKeySelector)> {
+        let device = self.search_object(device_id.object_id()).await?;
+        let sign_object = ObjectInfo {
+            object_id: device_id.object_id().to_owned(),
+            object: Arc::new(device),
+        };
+
+        match sign_object.object.public_key() {
+            Some(PublicKeyRef::Single(pk)) => {
+                if !policy.is_holder_key(pk) {
+                    let msg = format!(
+                        "holder device's key is not in owner's mn public key! device={}, owner={}",
+                        device_id, policy.owner_id
+                    );
+                    error!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::PermissionDenied, msg));
+                }
+
+                let keys = self.load_sign_keys(&sign_object, pk, req).await?;
+                Ok((pk.to_owned(), keys))
+            }
+            _ => {
+                let msg = format!("holder device has no single public key: {}", device_id);
+                error!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::NotSupport, msg))
+            }
+        }
+    }
+
+    async fn count_threshold_signs(
+        &self,
+        holders: &Vec<(ObjectId, SignKeySelector)>,
+        req: &ObjectInfo,
+        verify_type: &VerifySignType,
+        verify_result: &mut VerifyObjectResult,
+    ) -> usize {
+        let mut pass_count = 0;
+        for (holder_id, keys) in holders {
+            if let Ok(true) = self
+                .verify_single_key(holder_id, keys, req, verify_type, verify_result)
+                .await
+            {
+                pass_count += 1;
+            }
         }
+
+        pass_count
     }
 
     // 显式的使用object来校验签名是否有效
@@ -164,10 +474,10 @@ impl ObjectVerifier {
 
         let valid = match pk {
             PublicKeyRef::Single(pk) => {
-                let verifier = self.new_verifier(pk);
+                let keys = self.load_sign_keys(&sign_object, pk, req).await?;
                 self.verify_single_key(
                     &sign_object.object_id,
-                    &verifier,
+                    &keys,
                     &req,
                     &sign_type,
                     &mut verify_result,
@@ -191,11 +501,121 @@ impl ObjectVerifier {
         Ok(verify_result)
     }
 
-    fn new_verifier(&self, pk: &PublicKey) -> Box<dyn Verifier> {
+    fn new_verifier(pk: &PublicKey) -> Box<dyn Verifier> {
         let verifier = RsaCPUObjectVerifier::new(pk.clone());
         Box::new(verifier) as Box<dyn Verifier>
     }
 
+    // 加载签名对象的密钥轮换链，device在owner吊销列表里的吊销时间，以及被校验对象上的可信时间
+    async fn load_sign_keys(
+        &self,
+        sign_object: &ObjectInfo,
+        pk: &PublicKey,
+        req: &ObjectInfo,
+    ) -> BuckyResult<SignKeySelector> {
+        let mut revoke_time = None;
+        if sign_object.object_id.obj_type_code() == ObjectTypeCode::Device {
+            if let Some(owner_id) = sign_object.object.owner() {
+                if let Some(list) = self.key_chain.get_revocation_list(owner_id).await? {
+                    let device_id = DeviceId::try_from(&sign_object.object_id)?;
+                    revoke_time = list.revoke_time(&device_id);
+                    if let Some(revoke_time) = &revoke_time {
+                        warn!(
+                            "sign object is revoked device: device={}, revoke_time={}",
+                            device_id, revoke_time
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut keys = match self
+            .key_chain
+            .get_chain(&sign_object.object_id, &sign_object.object)
+            .await?
+        {
+            Some(chain) => SignKeySelector::with_chain(&chain, revoke_time),
+            None => {
+                let mut keys = SignKeySelector::new(pk);
+                keys.revoke_time = revoke_time;
+                keys
+            }
+        };
+
+        if keys.need_trusted_time() {
+            keys.trusted_time = self.load_trusted_time(sign_object, req).await;
+        }
+
+        Ok(keys)
+    }
+
+    // device签名的可信时间取owner会签里最早的签名时间，owner的签名时间不受device控制
+    // owner必须是单公钥，用owner当前的密钥校验
+    async fn load_trusted_time(&self, sign_object: &ObjectInfo, req: &ObjectInfo) -> Option<u64> {
+        if sign_object.object_id.obj_type_code() != ObjectTypeCode::Device {
+            return None;
+        }
+
+        let owner_id = sign_object.object.owner().as_ref()?;
+        let owner = match self.search_object(owner_id).await {
+            Ok(owner) => owner,
+            Err(e) => {
+                warn!(
+                    "search sign object's owner for trusted time failed! sign_obj={}, owner={}, {}",
+                    sign_object.object_id, owner_id, e
+                );
+                return None;
+            }
+        };
+
+        let owner_key = match self.key_chain.get_chain(owner_id, &owner).await {
+            Ok(Some(chain)) => chain.current().clone(),
+            Ok(None) => return None,
+            Err(e) => {
+                warn!(
+                    "load owner's key chain for trusted time failed! owner={}, {}",
+                    owner_id, e
+                );
+                return None;
+            }
+        };
+
+        let signs = req.object.signs()?;
+        let verifier = Self::new_verifier(&owner_key);
+        let mut trusted_time: Option<u64> = None;
+        if let Some(signs) = signs.desc_signs() {
+            for sign in signs {
+                if let Ok(true) =
+                    AnyNamedObjectVerifyHelper::verify_desc_sign(&verifier, &req.object, sign).await
+                {
+                    trusted_time = Some(trusted_time.map_or(sign.sign_time(), |t| {
+                        std::cmp::min(t, sign.sign_time())
+                    }));
+                }
+            }
+        }
+        if let Some(signs) = signs.body_signs() {
+            for sign in signs {
+                if let Ok(true) =
+                    AnyNamedObjectVerifyHelper::verify_body_sign(&verifier, &req.object, sign).await
+                {
+                    trusted_time = Some(trusted_time.map_or(sign.sign_time(), |t| {
+                        std::cmp::min(t, sign.sign_time())
+                    }));
+                }
+            }
+        }
+
+        if let Some(trusted_time) = &trusted_time {
+            info!(
+                "got trusted time from owner's sign: obj={}, sign_obj={}, owner={}, time={}",
+                req.object_id, sign_object.object_id, owner_id, trusted_time
+            );
+        }
+
+        trusted_time
+    }
+
     async fn verify_mn_key(
         &self,
         sign_object_id: &ObjectId,
@@ -207,8 +627,7 @@ impl ObjectVerifier {
     ) -> BuckyResult<bool> {
         let mut verifiers = Vec::new();
         for pk in pk_list {
-            let verifier = self.new_verifier(pk);
-            verifiers.push(verifier);
+            verifiers.push(SignKeySelector::new(pk));
         }
 
         if verify_type.desc() {
@@ -285,7 +704,7 @@ impl ObjectVerifier {
     async fn verify_single_key(
         &self,
         sign_object_id: &ObjectId,
-        verifier: &Box<dyn Verifier>,
+        keys: &SignKeySelector,
         req: &ObjectInfo,
         verify_type: &VerifySignType,
         verify_result: &mut VerifyObjectResult,
@@ -306,6 +725,17 @@ impl ObjectVerifier {
 
             let mut ret = false;
             for (index, sign) in signs.iter().enumerate() {
+                let verifier = match keys.select(sign.sign_time()) {
+                    Some(verifier) => verifier,
+                    None => {
+                        warn!(
+                            "desc sign object is revoked and no trusted time before revoke! obj={}, sign_obj={}, index={}, sign_time={}",
+                            req.object_id, sign_object_id, index, sign.sign_time()
+                        );
+                        continue;
+                    }
+                };
+
                 match AnyNamedObjectVerifyHelper::verify_desc_sign(verifier, &req.object, &sign)
                     .await
                 {
@@ -336,7 +766,7 @@ impl ObjectVerifier {
                 let msg = format!(
                     "verify object desc signs but not match! obj={}, pk={:?}",
                     req.object_id,
-                    verifier.public_key()
+                    keys.public_key()
                 );
                 warn!("{}", msg);
 
@@ -354,6 +784,17 @@ impl ObjectVerifier {
 
             let mut ret = false;
             for (index, sign) in signs.iter().enumerate() {
+                let verifier = match keys.select(sign.sign_time()) {
+                    Some(verifier) => verifier,
+                    None => {
+                        warn!(
+                            "body sign object is revoked and no trusted time before revoke! obj={}, sign_obj={}, index={}, sign_time={}",
+                            req.object_id, sign_object_id, index, sign.sign_time()
+                        );
+                        continue;
+                    }
+                };
+
                 match AnyNamedObjectVerifyHelper::verify_body_sign(verifier, &req.object, &sign)
                     .await
                 {
@@ -384,7 +825,7 @@ impl ObjectVerifier {
                 let msg = format!(
                     "verify object body signs but not match! obj={}, pk={:?}",
                     req.object_id,
-                    verifier.public_key()
+                    keys.public_key()
                 );
                 error!("{}", msg);
 
@@ -458,3 +899,61 @@ impl ObjectVerifier {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[async_std::test]
+    async fn test_select_key_after_rotation() {
+        let secret1 = PrivateKey::generate_rsa(1024).unwrap();
+        let secret2 = PrivateKey::generate_rsa(1024).unwrap();
+
+        let mut people = People::new(None, Vec::new(), secret1.public(), None, None, None).build();
+        let people_id = people.desc().calculate_id();
+
+        // people用当前密钥签名
+        let signer = RsaCPUObjectSigner::new(secret1.public(), secret1.clone());
+        sign_and_set_named_object_desc(&signer, &mut people, &SignatureSource::RefIndex(0))
+            .await
+            .unwrap();
+        let object = AnyNamedObject::clone_from_slice(&people.to_vec().unwrap()).unwrap();
+        let sign = object.signs().unwrap().desc_signs().unwrap()[0].clone();
+
+        // 签名之后轮换到新的密钥
+        let effective_time = sign.sign_time() + 1;
+        let mut chain = KeyRotationChain::new(people_id.clone(), secret1.public());
+        chain
+            .append(KeyRotation::create(
+                people_id,
+                1,
+                secret1.public(),
+                secret2.public(),
+                effective_time,
+            ))
+            .unwrap();
+
+        // 没有可信时间，按签名时间选择轮换前的密钥，校验仍然通过
+        let keys = SignKeySelector::with_chain(&chain, None);
+        let verifier = keys.select(sign.sign_time()).unwrap();
+        assert_eq!(verifier.public_key(), &secret1.public());
+        assert!(
+            AnyNamedObjectVerifyHelper::verify_desc_sign(verifier, &object, &sign)
+                .await
+                .unwrap()
+        );
+
+        // 轮换生效之后的签名时间只接受新密钥
+        let verifier = keys.select(effective_time).unwrap();
+        assert_eq!(verifier.public_key(), &secret2.public());
+        assert!(
+            !AnyNamedObjectVerifyHelper::verify_desc_sign(verifier, &object, &sign)
+                .await
+                .unwrap()
+        );
+
+        // 被吊销并且没有可信时间，全部拒绝
+        let keys = SignKeySelector::with_chain(&chain, Some(effective_time));
+        assert!(keys.select(sign.sign_time()).is_none());
+    }
+}
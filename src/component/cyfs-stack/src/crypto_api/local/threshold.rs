@@ -0,0 +1,178 @@
+use cyfs_base::*;
+
+/*
+M-of-N门限签名
+1. 签名主体为对象的owner(People/Group)；没有owner的People/Group本身就是主体，用于owner自身的更新
+2. People主体必须使用MN公钥，M和N个签名公钥都取自主体的MN公钥；签名设备为主体ood_list里公钥在MN公钥集合内的设备
+3. Group主体没有公钥，每个admin(People)的第一个ood作为一个签名设备，N为admin数量，M为admin的多数
+4. M至少为2，并且不能超过N，单个设备无法完成签名
+5. 发起方协议栈逐个向签名设备发送SIGN_THRESHOLD_PART请求，每个设备审批后返回带自己签名的对象
+6. 签名设备上必须有pre-crypto链的sign_object handler明确同意，没有handler或者handler没有同意时返回Pending
+*/
+#[derive(Clone, Debug)]
+pub(crate) struct ThresholdSignPolicy {
+    pub owner_id: ObjectId,
+    pub holders: Vec<DeviceId>,
+    pub keys: Vec<PublicKey>,
+    pub threshold: usize,
+}
+
+impl ThresholdSignPolicy {
+    pub fn new(
+        owner_id: ObjectId,
+        holders: Vec<DeviceId>,
+        threshold: u8,
+        keys: Vec<PublicKey>,
+    ) -> BuckyResult<Self> {
+        let threshold = threshold as usize;
+
+        // 重复的公钥只算一个签名方
+        let mut distinct_keys: Vec<PublicKey> = Vec::with_capacity(keys.len());
+        for key in keys {
+            if !distinct_keys.contains(&key) {
+                distinct_keys.push(key);
+            }
+        }
+
+        if threshold < 2 || threshold > distinct_keys.len() {
+            let msg = format!(
+                "invalid threshold sign policy, single holder can sign or m exceeds n! owner={}, n={}, m={}",
+                owner_id,
+                distinct_keys.len(),
+                threshold
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        Ok(Self {
+            owner_id,
+            holders,
+            keys: distinct_keys,
+            threshold,
+        })
+    }
+
+    // People主体的策略，Group主体需要加载admin的设备，见load_group
+    pub fn load(owner_id: &ObjectId, owner: &AnyNamedObject) -> BuckyResult<Self> {
+        match owner_id.obj_type_code() {
+            ObjectTypeCode::People => {}
+            code @ _ => {
+                let msg = format!(
+                    "threshold sign only support people owner here! owner={}, type={:?}",
+                    owner_id, code
+                );
+                error!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::NotSupport, msg));
+            }
+        }
+
+        let (threshold, keys) = match owner.public_key() {
+            Some(PublicKeyRef::MN((m, keys))) => (*m, keys.clone()),
+            _ => {
+                let msg = format!(
+                    "threshold sign owner has no mn public key! owner={}",
+                    owner_id
+                );
+                error!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::NotSupport, msg));
+            }
+        };
+
+        let holders = owner.ood_list()?.clone();
+        if holders.is_empty() {
+            let msg = format!(
+                "threshold sign owner's ood list is empty! owner={}",
+                owner_id
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+        }
+
+        Self::new(owner_id.to_owned(), holders, threshold, keys)
+    }
+
+    // Group主体的策略，admin_devices为每个admin选出的签名设备和设备公钥
+    // 门限按全部admin的多数计算，部分admin的设备加载失败不会降低门限
+    pub fn load_group(
+        group_id: &ObjectId,
+        admin_count: usize,
+        admin_devices: Vec<(DeviceId, PublicKey)>,
+    ) -> BuckyResult<Self> {
+        if admin_devices.is_empty() {
+            let msg = format!(
+                "threshold sign group has no admin device! group={}",
+                group_id
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+        }
+
+        let threshold = Self::group_threshold(admin_count);
+        let (holders, keys) = admin_devices.into_iter().unzip();
+        Self::new(group_id.to_owned(), holders, threshold, keys)
+    }
+
+    fn group_threshold(admin_count: usize) -> u8 {
+        std::cmp::min(admin_count / 2 + 1, u8::MAX as usize) as u8
+    }
+
+    pub fn is_holder(&self, device_id: &DeviceId) -> bool {
+        self.holders.iter().any(|v| v == device_id)
+    }
+
+    pub fn is_holder_key(&self, key: &PublicKey) -> bool {
+        self.keys.iter().any(|v| v == key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_threshold() {
+        let owner_id = ObjectId::default();
+        let device_id = DeviceId::from_str("5aSixgPJLRApy31v15U8mM7cVSndc8kjbECXSfP9o6Ef").unwrap();
+        let keys: Vec<PublicKey> = (0..3)
+            .map(|_| PrivateKey::generate_rsa(1024).unwrap().public())
+            .collect();
+
+        let policy =
+            ThresholdSignPolicy::new(owner_id.clone(), vec![device_id.clone()], 2, keys.clone())
+                .unwrap();
+        assert_eq!(policy.threshold, 2);
+        assert!(policy.is_holder(&device_id));
+        assert!(policy.is_holder_key(&keys[1]));
+
+        // 单个签名方即可完成签名的策略需要拒绝
+        assert!(ThresholdSignPolicy::new(owner_id.clone(), vec![], 1, keys.clone()).is_err());
+        assert!(ThresholdSignPolicy::new(owner_id.clone(), vec![], 0, keys.clone()).is_err());
+
+        // m超过n
+        assert!(ThresholdSignPolicy::new(owner_id.clone(), vec![], 4, keys.clone()).is_err());
+
+        // 重复的公钥不能凑数
+        let dup = vec![keys[0].clone(), keys[0].clone(), keys[0].clone()];
+        assert!(ThresholdSignPolicy::new(owner_id.clone(), vec![], 2, dup).is_err());
+
+        // group的门限为admin的多数，只有一个admin时单个设备可以签名，需要拒绝
+        let admin_devices: Vec<(DeviceId, PublicKey)> = keys
+            .iter()
+            .map(|key| (device_id.clone(), key.clone()))
+            .collect();
+        let policy = ThresholdSignPolicy::load_group(&owner_id, 3, admin_devices.clone()).unwrap();
+        assert_eq!(policy.threshold, 2);
+        assert_eq!(policy.holders.len(), 3);
+        assert!(
+            ThresholdSignPolicy::load_group(&owner_id, 1, admin_devices[..1].to_vec()).is_err()
+        );
+        assert!(ThresholdSignPolicy::load_group(&owner_id, 0, vec![]).is_err());
+
+        // 5个admin只加载到2个设备，门限仍然是3，无法满足
+        assert!(
+            ThresholdSignPolicy::load_group(&owner_id, 5, admin_devices[..2].to_vec()).is_err()
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use super::super::acl::*;
 use super::super::handler::*;
-use super::super::local::ObjectCrypto;
+use super::super::local::{ObjectCrypto, ObjectInfo, ObjectVerifier};
 use crate::acl::AclManagerRef;
 use crate::crypto::*;
 use crate::forward::ForwardProcessorManager;
@@ -10,6 +10,7 @@ use crate::zone::ZoneManagerRef;
 use cyfs_base::*;
 use cyfs_lib::*;
 
+use std::collections::HashSet;
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -25,6 +26,9 @@ pub struct CryptoRouter {
     fail_handler: ObjectFailHandler,
 
     router_handlers: RouterHandlersManager,
+
+    // 门限签名需要加载签名策略和校验已收集的签名
+    verifier: Arc<ObjectVerifier>,
 }
 
 impl CryptoRouter {
@@ -35,6 +39,7 @@ impl CryptoRouter {
         forward: ForwardProcessorManager,
         fail_handler: ObjectFailHandler,
         router_handlers: RouterHandlersManager,
+        verifier: Arc<ObjectVerifier>,
     ) -> CryptoInputProcessorRef {
         let ret = Self {
             acl,
@@ -43,6 +48,7 @@ impl CryptoRouter {
             forward,
             fail_handler,
             router_handlers,
+            verifier,
         };
 
         Arc::new(Box::new(ret))
@@ -79,6 +85,8 @@ impl CryptoRouter {
         fail_handler: ObjectFailHandler,
         router_handlers: RouterHandlersManager,
     ) -> CryptoInputProcessorRef {
+        let verifier = object_crypto.verifier().clone();
+
         // 本地的crypto需要增加handler
         let processor = Self::new_local_with_handler(object_crypto, &router_handlers);
 
@@ -89,9 +97,10 @@ impl CryptoRouter {
             forward,
             fail_handler,
             router_handlers,
+            verifier.clone(),
         );
 
-        let acl_router = CryptoAclInputProcessor::new(acl, raw_router.clone());
+        let acl_router = CryptoAclInputProcessor::new(acl, verifier, raw_router.clone());
 
         acl_router
     }
@@ -148,14 +157,245 @@ impl CryptoRouter {
         &self,
         target: Option<&ObjectId>,
     ) -> BuckyResult<CryptoInputProcessorRef> {
-        if let Some(device_id) = self.get_target(target).await? {
-            debug!("crypto target resolved: {:?} -> {}", target, device_id);
+        let device_id = self.get_target(target).await?;
+        debug!("crypto target resolved: {:?} -> {:?}", target, device_id);
+
+        self.select_processor(device_id).await
+    }
+
+    async fn select_processor(
+        &self,
+        device_id: Option<DeviceId>,
+    ) -> BuckyResult<CryptoInputProcessorRef> {
+        if let Some(device_id) = device_id {
             let processor = self.get_forward(device_id).await?;
             Ok(processor)
         } else {
             Ok(self.processor.clone())
         }
     }
+
+    // 门限签名：逐个向尚未签名的签名设备发送分片请求，收集到足够的签名后返回Signed，否则返回Pending和已收集到签名的对象
+    async fn threshold_sign(
+        &self,
+        req: CryptoSignObjectInputRequest,
+    ) -> BuckyResult<CryptoSignObjectInputResponse> {
+        let desc = req.flags
+            & (CRYPTO_REQUEST_FLAG_SIGN_SET_DESC | CRYPTO_REQUEST_FLAG_SIGN_PUSH_DESC)
+            != 0;
+        let body = req.flags
+            & (CRYPTO_REQUEST_FLAG_SIGN_SET_BODY | CRYPTO_REQUEST_FLAG_SIGN_PUSH_BODY)
+            != 0;
+        let sign_type = match (desc, body) {
+            (true, true) => VerifySignType::Both,
+            (true, false) => VerifySignType::Desc,
+            (false, true) => VerifySignType::Body,
+            (false, false) => {
+                let msg = format!(
+                    "threshold sign but no desc or body flags! obj={}, flags={}",
+                    req.object.object_id, req.flags
+                );
+                error!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+            }
+        };
+
+        let mut object = req.object.clone();
+        let (policy, verify_result) = self
+            .verifier
+            .verify_threshold(&Self::object_info(&object), &sign_type)
+            .await?;
+        if verify_result.valid {
+            info!(
+                "threshold sign already satisfied: obj={}, owner={}",
+                object.object_id, policy.owner_id
+            );
+            return Ok(CryptoSignObjectInputResponse {
+                result: SignObjectResult::Signed,
+                object: Some(object),
+            });
+        }
+
+        let desc_signed: HashSet<ObjectId> = verify_result
+            .desc_signs
+            .iter()
+            .map(|v| v.sign_object_id.clone())
+            .collect();
+        let body_signed: HashSet<ObjectId> = verify_result
+            .body_signs
+            .iter()
+            .map(|v| v.sign_object_id.clone())
+            .collect();
+
+        let mut signed_count = 0;
+        let mut parts = vec![];
+        for holder in &policy.holders {
+            let mut flags = 0;
+            if desc && !desc_signed.contains(holder.object_id()) {
+                flags |= CRYPTO_REQUEST_FLAG_SIGN_PUSH_DESC;
+            }
+            if body && !body_signed.contains(holder.object_id()) {
+                flags |= CRYPTO_REQUEST_FLAG_SIGN_PUSH_BODY;
+            }
+
+            if flags == 0 {
+                signed_count += 1;
+            } else {
+                parts.push((holder, flags));
+            }
+        }
+
+        info!(
+            "will collect threshold signs: obj={}, owner={}, n={}, m={}, signed={}",
+            object.object_id,
+            policy.owner_id,
+            policy.holders.len(),
+            policy.threshold,
+            signed_count
+        );
+
+        for (holder, flags) in parts {
+            if signed_count >= policy.threshold {
+                break;
+            }
+
+            let part_req = CryptoSignObjectInputRequest {
+                common: CryptoInputRequestCommon {
+                    req_path: req.common.req_path.clone(),
+                    source: req.common.source.clone(),
+                    target: Some(holder.object_id().to_owned()),
+                    flags: req.common.flags,
+                },
+                object: object.clone(),
+                flags: CRYPTO_REQUEST_FLAG_SIGN_THRESHOLD_PART
+                    | CRYPTO_REQUEST_FLAG_SIGN_BY_DEVICE
+                    | flags,
+            };
+
+            match self.sign_threshold_part(holder, part_req).await {
+                Ok(Some(signed)) => {
+                    object = signed;
+                    signed_count += 1;
+                }
+                Ok(None) => {
+                    info!(
+                        "threshold sign part is pending: obj={}, holder={}",
+                        object.object_id, holder
+                    );
+                }
+                Err(e) => {
+                    warn!(
+                        "threshold sign part failed: obj={}, holder={}, {}",
+                        object.object_id, holder, e
+                    );
+                }
+            }
+        }
+
+        // 以最终对象上的签名为准重新校验
+        let (_, verify_result) = self
+            .verifier
+            .verify_threshold(&Self::object_info(&object), &sign_type)
+            .await?;
+        let result = if verify_result.valid {
+            info!(
+                "threshold sign success: obj={}, owner={}",
+                object.object_id, policy.owner_id
+            );
+            SignObjectResult::Signed
+        } else {
+            warn!(
+                "threshold sign not enough signs, will pending: obj={}, owner={}, m={}",
+                object.object_id, policy.owner_id, policy.threshold
+            );
+            SignObjectResult::Pending
+        };
+
+        Ok(CryptoSignObjectInputResponse {
+            result,
+            object: Some(object),
+        })
+    }
+
+    async fn sign_threshold_part(
+        &self,
+        holder: &DeviceId,
+        req: CryptoSignObjectInputRequest,
+    ) -> BuckyResult<Option<NONObjectInfo>> {
+        let object_id = req.object.object_id.clone();
+        let target = self.get_target(req.common.target.as_ref()).await?;
+        let processor = self.select_processor(target).await?;
+        let resp = processor.sign_object(req).await?;
+
+        match resp.result {
+            SignObjectResult::Signed => {
+                let mut signed = resp.object.ok_or_else(|| {
+                    let msg = format!(
+                        "threshold sign part but object missing! obj={}, holder={}",
+                        object_id, holder
+                    );
+                    error!("{}", msg);
+                    BuckyError::new(BuckyErrorCode::InvalidData, msg)
+                })?;
+
+                signed.decode_and_verify()?;
+                if signed.object_id != object_id {
+                    let msg = format!(
+                        "threshold sign part but object unmatch! obj={}, holder={}, got={}",
+                        object_id, holder, signed.object_id
+                    );
+                    error!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+                }
+
+                Ok(Some(signed))
+            }
+            SignObjectResult::Pending => Ok(None),
+        }
+    }
+
+    // 签名设备处理门限签名分片请求：本地设备需要是签名设备，并且只能以device身份追加签名
+    async fn check_threshold_part(
+        &self,
+        req: &mut CryptoSignObjectInputRequest,
+    ) -> BuckyResult<()> {
+        let policy = self
+            .verifier
+            .load_threshold_policy(&Self::object_info(&req.object))
+            .await?;
+
+        let local_device_id = self.zone_manager.get_current_device_id();
+        if !policy.is_holder(local_device_id) {
+            let msg = format!(
+                "threshold sign part but current device is not holder! obj={}, owner={}, device={}",
+                req.object.object_id, policy.owner_id, local_device_id
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::PermissionDenied, msg));
+        }
+
+        let flags =
+            req.flags & (CRYPTO_REQUEST_FLAG_SIGN_PUSH_DESC | CRYPTO_REQUEST_FLAG_SIGN_PUSH_BODY);
+        if flags == 0 {
+            let msg = format!(
+                "threshold sign part but no push flags! obj={}, flags={}",
+                req.object.object_id, req.flags
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        req.flags =
+            CRYPTO_REQUEST_FLAG_SIGN_THRESHOLD_PART | CRYPTO_REQUEST_FLAG_SIGN_BY_DEVICE | flags;
+        Ok(())
+    }
+
+    fn object_info(object: &NONObjectInfo) -> ObjectInfo {
+        ObjectInfo {
+            object_id: object.object_id.clone(),
+            object: object.clone_object(),
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -170,9 +410,21 @@ impl CryptoInputProcessor for CryptoRouter {
 
     async fn sign_object(
         &self,
-        req: CryptoSignObjectInputRequest,
+        mut req: CryptoSignObjectInputRequest,
     ) -> BuckyResult<CryptoSignObjectInputResponse> {
-        let processor = self.get_processor(req.common.target.as_ref()).await?;
+        let target = self.get_target(req.common.target.as_ref()).await?;
+        if target.is_none() {
+            if req.flags & CRYPTO_REQUEST_FLAG_SIGN_BY_THRESHOLD != 0 {
+                return self.threshold_sign(req).await;
+            }
+
+            if req.flags & CRYPTO_REQUEST_FLAG_SIGN_THRESHOLD_PART != 0 {
+                // 审批由本地pre-crypto链上匹配到的sign_object handler完成，见CryptoHandlerPreProcessor
+                self.check_threshold_part(&mut req).await?;
+            }
+        }
+
+        let processor = self.select_processor(target).await?;
         processor.sign_object(req).await
     }
 
@@ -82,6 +82,7 @@ impl CryptoRequestHandler {
             RequestorHelper::decode_header(&req.request, cyfs_base::CYFS_VERIFY_TYPE)?;
         let sign_object = match verify_type.as_str() {
             "owner" => VerifyObjectType::Owner,
+            "threshold" => VerifyObjectType::Threshold,
             "object" => {
                 let object_id: ObjectId =
                     RequestorHelper::decode_header(&req.request, cyfs_base::CYFS_SIGN_OBJ_ID)?;
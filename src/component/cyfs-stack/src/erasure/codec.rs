@@ -0,0 +1,219 @@
+use cyfs_base::*;
+use cyfs_raptorq::{
+    DecodeStatus, Encoder, EncodingPacket, ObjectTransmissionInformation, SourceBlockDecoder,
+};
+
+// raptorq symbol size is u16, keep some room for the payload id
+const MAX_SYMBOL_SIZE: u64 = 1024 * 60;
+
+// raptorq's max source symbols in one source block
+const MAX_SOURCE_SYMBOLS: u64 = 56403;
+
+// min symbols carried by each fragment
+const MIN_SYMBOLS_PER_FRAGMENT: u32 = 4;
+
+// raptorq is not a MDS code, so the source symbols are a little less than the symbols carried by data_fragments,
+// then any data_fragments fragments will be decoded with a failure probability less than 1e-6
+const SYMBOL_OVERHEAD: u32 = 2;
+
+// The layout of one chunk's fragments, all fragments have the same length
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct ErasureCodecParams {
+    pub data_fragments: u16,
+    pub parity_fragments: u16,
+    pub symbols_per_fragment: u32,
+    pub config: ObjectTransmissionInformation,
+}
+
+impl ErasureCodecParams {
+    pub fn total_fragments(&self) -> u16 {
+        self.data_fragments + self.parity_fragments
+    }
+
+    pub fn packet_size(&self) -> usize {
+        // payload_id + symbol
+        4 + self.config.symbol_size() as usize
+    }
+
+    pub fn fragment_size(&self) -> usize {
+        self.packet_size() * self.symbols_per_fragment as usize
+    }
+
+    pub fn config_to_hex(&self) -> String {
+        hex::encode(self.config.serialize())
+    }
+
+    pub fn config_from_hex(value: &str) -> BuckyResult<ObjectTransmissionInformation> {
+        let buf = hex::decode(value).map_err(|e| {
+            let msg = format!("invalid erasure codec config: {}, {}", value, e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+        })?;
+
+        if buf.len() != 12 {
+            let msg = format!("invalid erasure codec config length: {}", value);
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidFormat, msg));
+        }
+
+        let mut config = [0u8; 12];
+        config.copy_from_slice(&buf);
+        Ok(ObjectTransmissionInformation::deserialize(&config))
+    }
+}
+
+// Split data into data_fragments + parity_fragments fragments with raptorq, and any data_fragments of them can rebuild the data
+pub(crate) struct ErasureCodec;
+
+impl ErasureCodec {
+    pub fn params(
+        len: u64,
+        data_fragments: u16,
+        parity_fragments: u16,
+    ) -> BuckyResult<ErasureCodecParams> {
+        if len == 0 || data_fragments == 0 {
+            let msg = format!(
+                "invalid erasure codec params! len={}, data_fragments={}",
+                len, data_fragments
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        let k = data_fragments as u64;
+        let min_symbols = (len + MAX_SYMBOL_SIZE - 1) / MAX_SYMBOL_SIZE + SYMBOL_OVERHEAD as u64;
+        let symbols_per_fragment =
+            std::cmp::max(MIN_SYMBOLS_PER_FRAGMENT as u64, (min_symbols + k - 1) / k) as u32;
+
+        let source_symbols = k * symbols_per_fragment as u64 - SYMBOL_OVERHEAD as u64;
+        if source_symbols > MAX_SOURCE_SYMBOLS {
+            let msg = format!(
+                "data is too large for erasure codec! len={}, data_fragments={}",
+                len, data_fragments
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::OutOfLimit, msg));
+        }
+
+        let symbol_size = (len + source_symbols - 1) / source_symbols;
+        assert!(symbol_size <= MAX_SYMBOL_SIZE);
+
+        let config = ObjectTransmissionInformation::new(len, symbol_size as u16, 1, 1, 1);
+
+        Ok(ErasureCodecParams {
+            data_fragments,
+            parity_fragments,
+            symbols_per_fragment,
+            config,
+        })
+    }
+
+    fn source_symbols(params: &ErasureCodecParams) -> u32 {
+        let len = params.config.transfer_length();
+        let symbol_size = params.config.symbol_size() as u64;
+        ((len + symbol_size - 1) / symbol_size) as u32
+    }
+
+    pub fn encode(params: &ErasureCodecParams, data: &[u8]) -> BuckyResult<Vec<Vec<u8>>> {
+        if data.len() as u64 != params.config.transfer_length() {
+            let msg = format!(
+                "erasure encode data length unmatch! len={}, expect={}",
+                data.len(),
+                params.config.transfer_length()
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+        }
+
+        let total = params.total_fragments() as u32 * params.symbols_per_fragment;
+        let repair = total - Self::source_symbols(params);
+
+        let encoder = Encoder::new(data, params.config);
+        let packets = encoder.get_encoded_packets(repair);
+        assert_eq!(packets.len(), total as usize);
+
+        let fragments = packets
+            .chunks(params.symbols_per_fragment as usize)
+            .map(|list| {
+                let mut fragment = Vec::with_capacity(params.fragment_size());
+                for packet in list {
+                    fragment.extend_from_slice(&packet.serialize());
+                }
+                fragment
+            })
+            .collect();
+
+        Ok(fragments)
+    }
+
+    // fragments are the (index, fragment) pairs, the decode will fail if not enough fragments
+    pub fn decode(
+        params: &ErasureCodecParams,
+        fragments: &[(u16, Vec<u8>)],
+    ) -> BuckyResult<Vec<u8>> {
+        let source_symbols = Self::source_symbols(params);
+        let block_length = source_symbols as u64 * params.config.symbol_size() as u64;
+        let mut decoder = SourceBlockDecoder::new2(0, &params.config, block_length);
+
+        for (index, fragment) in fragments {
+            if fragment.len() != params.fragment_size() {
+                warn!(
+                    "erasure fragment length unmatch, now will ignore! index={}, len={}, expect={}",
+                    index,
+                    fragment.len(),
+                    params.fragment_size()
+                );
+                continue;
+            }
+
+            let packets = fragment
+                .chunks(params.packet_size())
+                .map(|buf| EncodingPacket::deserialize(buf.to_vec()));
+            if let (DecodeStatus::Done, Some(mut data)) = decoder.decode(packets) {
+                data.truncate(params.config.transfer_length() as usize);
+                return Ok(data);
+            }
+        }
+
+        let msg = format!(
+            "erasure decode failed, not enough fragments! got={}, data_fragments={}",
+            fragments.len(),
+            params.data_fragments
+        );
+        error!("{}", msg);
+        Err(BuckyError::new(BuckyErrorCode::InvalidData, msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codec() {
+        let data: Vec<u8> = (0..1024 * 1024 + 123).map(|i| (i % 251) as u8).collect();
+
+        let params = ErasureCodec::params(data.len() as u64, 4, 2).unwrap();
+        let fragments = ErasureCodec::encode(&params, &data).unwrap();
+        assert_eq!(fragments.len(), 6);
+        for fragment in &fragments {
+            assert_eq!(fragment.len(), params.fragment_size());
+        }
+
+        // lost two fragments
+        let list: Vec<(u16, Vec<u8>)> = fragments
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 0 && *i != 3)
+            .map(|(i, v)| (i as u16, v))
+            .collect();
+        let ret = ErasureCodec::decode(&params, &list).unwrap();
+        assert_eq!(ret, data);
+
+        let config = ErasureCodecParams::config_from_hex(&params.config_to_hex()).unwrap();
+        assert_eq!(config, params.config);
+
+        // not enough fragments
+        assert!(ErasureCodec::decode(&params, &list[..2]).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use super::codec::ErasureCodecParams;
+use cyfs_base::*;
+use cyfs_lib::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ErasureFragmentInfo {
+    pub index: u16,
+    pub fragment_id: ChunkId,
+    pub device_id: DeviceId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ErasureChunkInfo {
+    pub data_fragments: u16,
+    pub parity_fragments: u16,
+    pub symbols_per_fragment: u32,
+
+    // raptorq's transmission information in hex
+    pub config: String,
+
+    pub fragments: Vec<ErasureFragmentInfo>,
+}
+
+impl ErasureChunkInfo {
+    pub fn new(params: &ErasureCodecParams, fragments: Vec<ErasureFragmentInfo>) -> Self {
+        Self {
+            data_fragments: params.data_fragments,
+            parity_fragments: params.parity_fragments,
+            symbols_per_fragment: params.symbols_per_fragment,
+            config: params.config_to_hex(),
+            fragments,
+        }
+    }
+
+    pub fn params(&self) -> BuckyResult<ErasureCodecParams> {
+        let config = ErasureCodecParams::config_from_hex(&self.config)?;
+
+        Ok(ErasureCodecParams {
+            data_fragments: self.data_fragments,
+            parity_fragments: self.parity_fragments,
+            symbols_per_fragment: self.symbols_per_fragment,
+            config,
+        })
+    }
+}
+
+// The index is saved in system dec's root state, so it will be synced to the standby oods, and its replica
+// is put to the noc of all zone devices for restore
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct ErasureIndex {
+    // file_id -> chunk list of the file
+    pub files: HashMap<ObjectId, Vec<ChunkId>>,
+
+    // chunks may be shared by multi files
+    pub chunks: HashMap<ChunkId, ErasureChunkInfo>,
+}
+
+declare_collection_codec_for_serde!(ErasureIndex);
+
+impl ErasureIndex {
+    pub fn is_chunk_in_use(&self, chunk_id: &ChunkId) -> bool {
+        self.files.values().any(|list| list.contains(chunk_id))
+    }
+
+    // Remove the file and return the chunks which are not used by other files
+    pub fn remove_file(&mut self, file_id: &ObjectId) -> Vec<ChunkId> {
+        let list = match self.files.remove(file_id) {
+            Some(list) => list,
+            None => return vec![],
+        };
+
+        let mut removed = vec![];
+        for chunk_id in list {
+            if !self.is_chunk_in_use(&chunk_id) && self.chunks.remove(&chunk_id).is_some() {
+                removed.push(chunk_id);
+            }
+        }
+
+        removed
+    }
+}
+
+pub(crate) type ErasureIndexCollection = NOCCollectionRWSync<ErasureIndex>;
@@ -0,0 +1,862 @@
+use super::codec::*;
+use super::index::*;
+use super::store::ErasureFragmentStore;
+use crate::forward::ForwardProcessorManager;
+use crate::ndn_api::LocalDataManager;
+use crate::stack::CyfsStackErasureParams;
+use crate::zone::ZoneManagerRef;
+use cyfs_base::*;
+use cyfs_core::{Storage, StorageObj, ZoneObj};
+use cyfs_lib::*;
+
+use once_cell::sync::OnceCell;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// The replica of the index is a Storage object with fixed desc, so its object_id is well known on every device
+const ERASURE_INDEX_REPLICA_ID: &str = "cyfs-erasure-index-replica";
+
+// Any device holds at most parity_fragments fragments of a chunk, so the chunk can be reconstructed after losing any one device
+fn is_durable(device_count: usize, data_fragments: u16, parity_fragments: u16) -> bool {
+    device_count * parity_fragments as usize >= data_fragments as usize + parity_fragments as usize
+}
+
+// Select the chunks overlapped with the ranges of the data, the chunks are in the order of the data
+fn select_chunks(chunks: &[ChunkId], ranges: &[Range<u64>]) -> Vec<ChunkId> {
+    let mut ret = vec![];
+    let mut offset = 0;
+    for chunk_id in chunks {
+        let chunk = offset..offset + chunk_id.len() as u64;
+        offset = chunk.end;
+
+        if ranges
+            .iter()
+            .any(|range| range.start < chunk.end && chunk.start < range.end)
+        {
+            ret.push(chunk_id.to_owned());
+        }
+    }
+
+    ret
+}
+
+/*
+The files selected by user are set in system dec's root state:
+/.cyfs/erasure/files/{any key} -> file_id
+The active ood splits every chunk of the files into data_fragments + parity_fragments fragments, and puts them on the different
+devices of current zone, then the fragments layout is saved to /.cyfs/erasure/index
+The index is also replicated to the noc of every zone device, so it can be restored if the ood's root state is lost
+*/
+struct ErasureChunkManagerInner {
+    params: CyfsStackErasureParams,
+    device_id: DeviceId,
+
+    zone_manager: ZoneManagerRef,
+    root_state: GlobalStateOutputProcessorRef,
+    noc: NamedObjectCacheRef,
+
+    store: ErasureFragmentStore,
+    index: OnceCell<ErasureIndexCollection>,
+
+    // the index changed but not replicated to all the devices yet
+    replica_pending: AtomicBool,
+
+    // whether the replicas on the devices are checked when the local index is empty
+    replica_checked: AtomicBool,
+}
+
+#[derive(Clone)]
+pub(crate) struct ErasureChunkManager(Arc<ErasureChunkManagerInner>);
+
+impl ErasureChunkManager {
+    pub fn new(
+        params: CyfsStackErasureParams,
+        zone_manager: ZoneManagerRef,
+        root_state: GlobalStateOutputProcessorRef,
+        noc: NamedObjectCacheRef,
+        local: LocalDataManager,
+        forward: ForwardProcessorManager,
+    ) -> Self {
+        let device_id = zone_manager.get_current_device_id().to_owned();
+        let store = ErasureFragmentStore::new(device_id.clone(), local, forward);
+
+        let inner = ErasureChunkManagerInner {
+            params,
+            device_id,
+            zone_manager,
+            root_state,
+            noc,
+            store,
+            index: OnceCell::new(),
+            replica_pending: AtomicBool::new(false),
+            replica_checked: AtomicBool::new(false),
+        };
+
+        Self(Arc::new(inner))
+    }
+
+    pub async fn init(&self) -> BuckyResult<()> {
+        let index = ErasureIndexCollection::new_global_state(
+            self.0.root_state.clone(),
+            Some(cyfs_core::get_system_dec_app().to_owned()),
+            CYFS_ERASURE_INDEX_PATH.to_owned(),
+            None,
+            "cyfs-erasure-index",
+            self.0.noc.clone(),
+        );
+
+        if let Err(e) = index.load().await {
+            error!("load erasure index from global state failed! {}", e);
+            return Err(e);
+        }
+
+        {
+            let coll = index.coll().read().unwrap();
+            info!(
+                "load erasure index success! files={}, chunks={}",
+                coll.files.len(),
+                coll.chunks.len()
+            );
+        }
+
+        if let Err(_) = self.0.index.set(index) {
+            unreachable!();
+        }
+
+        Ok(())
+    }
+
+    fn index(&self) -> &ErasureIndexCollection {
+        self.0.index.get().unwrap()
+    }
+
+    pub fn start(&self) {
+        let this = self.clone();
+        async_std::task::spawn(async move {
+            let interval = Duration::from_secs(this.0.params.check_interval_secs);
+            loop {
+                // wait for the stack and the zone devices online
+                async_std::task::sleep(interval).await;
+                this.check_once().await;
+            }
+        });
+    }
+
+    async fn check_once(&self) {
+        let info = match self.0.zone_manager.get_current_info().await {
+            Ok(info) => info,
+            Err(e) => {
+                error!("get current zone info for erasure check failed! {}", e);
+                return;
+            }
+        };
+
+        // only active ood will update the index, others just reload it which synced from active ood
+        if !info.zone_role.is_active_ood() {
+            let _ = self.index().load().await;
+            return;
+        }
+
+        let devices = match self.zone_devices().await {
+            Ok(list) => list,
+            Err(e) => {
+                error!("get zone devices for erasure check failed! {}", e);
+                return;
+            }
+        };
+
+        if !is_durable(
+            devices.len(),
+            self.0.params.data_fragments,
+            self.0.params.parity_fragments,
+        ) {
+            warn!(
+                "not enough devices in current zone, the erasure fragments will not be durable! devices={}, data_fragments={}, parity_fragments={}",
+                devices.len(),
+                self.0.params.data_fragments,
+                self.0.params.parity_fragments
+            );
+        }
+
+        if !self.0.replica_checked.load(Ordering::SeqCst) {
+            self.restore_index(&devices).await;
+        }
+
+        if let Err(e) = self.sync_files(&devices).await {
+            error!("sync erasure files failed! {}", e);
+        }
+
+        self.check_fragments(&devices).await;
+
+        if self.index().is_dirty() {
+            self.0.replica_pending.store(true, Ordering::SeqCst);
+        }
+
+        if let Err(e) = self.index().save().await {
+            error!("save erasure index to global state failed! {}", e);
+            return;
+        }
+
+        if self.0.replica_pending.load(Ordering::SeqCst) {
+            if self.replicate_index(&devices).await {
+                self.0.replica_pending.store(false, Ordering::SeqCst);
+            }
+        }
+    }
+
+    fn index_replica(&self) -> BuckyResult<NONObjectInfo> {
+        let buf = self.index().coll().read().unwrap().encode()?;
+
+        let mut object = Storage::create(ERASURE_INDEX_REPLICA_ID, buf);
+        object
+            .body_mut()
+            .as_mut()
+            .unwrap()
+            .increase_update_time(bucky_time_now());
+
+        let object_id = object.desc().object_id();
+        Ok(NONObjectInfo::new(object_id, object.to_vec()?, None))
+    }
+
+    // Put the index replica to all the devices, return true if all succeeded
+    async fn replicate_index(&self, devices: &[DeviceId]) -> bool {
+        let object = match self.index_replica() {
+            Ok(object) => object,
+            Err(e) => {
+                error!("encode erasure index replica failed! {}", e);
+                return false;
+            }
+        };
+
+        let mut all = true;
+        for device_id in devices.iter().filter(|v| **v != self.0.device_id) {
+            if let Err(e) = self.0.store.put_object(device_id, object.clone()).await {
+                warn!(
+                    "replicate erasure index to device failed! device={}, {}",
+                    device_id, e
+                );
+                all = false;
+            }
+        }
+
+        info!(
+            "replicate erasure index to devices complete! devices={}, all={}",
+            devices.len(),
+            all
+        );
+        all
+    }
+
+    // If the index in root state is lost, restore the newest replica from the devices
+    async fn restore_index(&self, devices: &[DeviceId]) {
+        {
+            let coll = self.index().coll().read().unwrap();
+            if !coll.files.is_empty() || !coll.chunks.is_empty() {
+                self.0.replica_checked.store(true, Ordering::SeqCst);
+                return;
+            }
+        }
+
+        let object_id = Storage::create(ERASURE_INDEX_REPLICA_ID, vec![])
+            .desc()
+            .object_id();
+
+        let mut answered = false;
+        let mut newest: Option<Storage> = None;
+        for device_id in devices.iter().filter(|v| **v != self.0.device_id) {
+            let object = match self.0.store.get_object(device_id, &object_id).await {
+                Ok(Some(object)) => object,
+                Ok(None) => {
+                    answered = true;
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            answered = true;
+
+            let replica = match Storage::clone_from_slice(&object.object_raw) {
+                Ok(replica) => replica,
+                Err(e) => {
+                    error!("decode erasure index replica failed! device={}, {}", device_id, e);
+                    continue;
+                }
+            };
+
+            let update_time = replica.body().as_ref().map(|v| v.update_time()).unwrap_or(0);
+            let newest_time = newest
+                .as_ref()
+                .and_then(|v| v.body().as_ref().map(|v| v.update_time()))
+                .unwrap_or(0);
+            if newest.is_none() || update_time > newest_time {
+                newest = Some(replica);
+            }
+        }
+
+        if answered {
+            self.0.replica_checked.store(true, Ordering::SeqCst);
+        }
+
+        let replica = match newest {
+            Some(replica) => replica,
+            None => return,
+        };
+
+        let index = match ErasureIndex::decode(replica.value()) {
+            Ok(index) => index,
+            Err(e) => {
+                error!("decode erasure index from replica failed! {}", e);
+                return;
+            }
+        };
+
+        warn!(
+            "erasure index in root state is empty, restored from replica! files={}, chunks={}",
+            index.files.len(),
+            index.chunks.len()
+        );
+
+        *self.index().coll().write().unwrap() = index;
+        self.index().set_dirty(true);
+    }
+
+    // oods first, then the other known devices
+    async fn zone_devices(&self) -> BuckyResult<Vec<DeviceId>> {
+        let zone = self.0.zone_manager.get_current_zone().await?;
+
+        let mut list = zone.ood_list().clone();
+        for device_id in zone.known_device_list() {
+            if !list.contains(device_id) {
+                list.push(device_id.to_owned());
+            }
+        }
+
+        Ok(list)
+    }
+
+    async fn load_selected_files(&self) -> BuckyResult<HashSet<ObjectId>> {
+        let stub = GlobalStateStub::new(
+            self.0.root_state.clone(),
+            None,
+            Some(cyfs_core::get_system_dec_app().to_owned()),
+        );
+
+        let op_env = stub.create_path_op_env().await?;
+        let ret = op_env.list(CYFS_ERASURE_FILES_PATH).await;
+        let _ = op_env.abort().await;
+
+        let list = match ret {
+            Ok(list) => list,
+            Err(e) if e.code() == BuckyErrorCode::NotFound => vec![],
+            Err(e) => {
+                error!("list erasure files from global state failed! {}", e);
+                return Err(e);
+            }
+        };
+
+        let mut files = HashSet::new();
+        for item in list {
+            let id = match item {
+                ObjectMapContentItem::Map((_, id)) => id,
+                ObjectMapContentItem::Set(id) => id,
+                _ => continue,
+            };
+
+            if id.obj_type_code() == ObjectTypeCode::File {
+                files.insert(id);
+            } else {
+                warn!("erasure only support file object, now will ignore: {}", id);
+            }
+        }
+
+        Ok(files)
+    }
+
+    async fn sync_files(&self, devices: &[DeviceId]) -> BuckyResult<()> {
+        let selected = self.load_selected_files().await?;
+
+        let current: Vec<ObjectId> = {
+            let index = self.index().coll().read().unwrap();
+            index.files.keys().cloned().collect()
+        };
+
+        for file_id in current.iter().filter(|id| !selected.contains(id)) {
+            let removed = self.index().coll().write().unwrap().remove_file(file_id);
+            self.index().set_dirty(true);
+
+            info!(
+                "erasure file removed from selection: file={}, released chunks={}",
+                file_id,
+                removed.len()
+            );
+        }
+
+        for file_id in selected.iter() {
+            if current.contains(file_id) {
+                continue;
+            }
+
+            if let Err(e) = self.distribute_file(file_id, devices).await {
+                error!("distribute erasure file failed! file={}, {}", file_id, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn load_file(&self, file_id: &ObjectId) -> BuckyResult<File> {
+        let req = NamedObjectCacheGetObjectRequest {
+            source: RequestSourceInfo::new_local_system(),
+            object_id: file_id.clone(),
+            last_access_rpath: None,
+            flags: 0,
+        };
+
+        match self.0.noc.get_object(&req).await? {
+            Some(data) => File::clone_from_slice(&data.object.object_raw),
+            None => {
+                let msg = format!("erasure file not found in noc! file={}", file_id);
+                warn!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::NotFound, msg))
+            }
+        }
+    }
+
+    async fn distribute_file(&self, file_id: &ObjectId, devices: &[DeviceId]) -> BuckyResult<()> {
+        let file = self.load_file(file_id).await?;
+        let chunk_list = match file.body().as_ref() {
+            Some(body) => match body.content().inner_chunk_list() {
+                Some(list) => list.clone(),
+                None => {
+                    let msg = format!("erasure file's chunk list not in body! file={}", file_id);
+                    error!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::UnSupport, msg));
+                }
+            },
+            None => {
+                let msg = format!("erasure file has no body! file={}", file_id);
+                error!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::InvalidData, msg));
+            }
+        };
+
+        for chunk_id in &chunk_list {
+            if self
+                .index()
+                .coll()
+                .read()
+                .unwrap()
+                .chunks
+                .contains_key(chunk_id)
+            {
+                continue;
+            }
+
+            let info = self.distribute_chunk(chunk_id, devices).await?;
+            self.index()
+                .coll()
+                .write()
+                .unwrap()
+                .chunks
+                .insert(chunk_id.to_owned(), info);
+            self.index().set_dirty(true);
+        }
+
+        self.index()
+            .coll()
+            .write()
+            .unwrap()
+            .files
+            .insert(file_id.to_owned(), chunk_list);
+        self.index().set_dirty(true);
+
+        info!("distribute erasure file success! file={}", file_id);
+        Ok(())
+    }
+
+    async fn distribute_chunk(
+        &self,
+        chunk_id: &ChunkId,
+        devices: &[DeviceId],
+    ) -> BuckyResult<ErasureChunkInfo> {
+        let data = self.0.store.get_local_chunk(chunk_id).await.map_err(|e| {
+            error!(
+                "erasure chunk not exists on local! chunk={}, {}",
+                chunk_id, e
+            );
+            e
+        })?;
+
+        let params = ErasureCodec::params(
+            data.len() as u64,
+            self.0.params.data_fragments,
+            self.0.params.parity_fragments,
+        )?;
+        if !is_durable(devices.len(), params.data_fragments, params.parity_fragments) {
+            let msg = format!(
+                "not enough devices for durable erasure fragments! chunk={}, devices={}, data_fragments={}, parity_fragments={}",
+                chunk_id,
+                devices.len(),
+                params.data_fragments,
+                params.parity_fragments
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::ErrorState, msg));
+        }
+
+        let fragments = ErasureCodec::encode(&params, &data)?;
+
+        let mut list: Vec<ErasureFragmentInfo> = Vec::with_capacity(fragments.len());
+        for (index, fragment) in fragments.into_iter().enumerate() {
+            let fragment_id = ChunkId::calculate(&fragment).await?;
+            let device_id = self
+                .place_fragment(&fragment_id, fragment, devices, &list, &params)
+                .await?;
+
+            list.push(ErasureFragmentInfo {
+                index: index as u16,
+                fragment_id,
+                device_id,
+            });
+        }
+
+        info!(
+            "distribute erasure chunk success! chunk={}, fragments={:?}",
+            chunk_id,
+            list.iter()
+                .map(|item| item.device_id.to_string())
+                .collect::<Vec<String>>()
+        );
+
+        Ok(ErasureChunkInfo::new(&params, list))
+    }
+
+    // Put the fragment to the device which holds the fewest fragments of the same chunk, and the device which
+    // already holds parity_fragments fragments is never selected, otherwise losing it will lose the chunk
+    async fn place_fragment(
+        &self,
+        fragment_id: &ChunkId,
+        fragment: Vec<u8>,
+        devices: &[DeviceId],
+        placed: &[ErasureFragmentInfo],
+        params: &ErasureCodecParams,
+    ) -> BuckyResult<DeviceId> {
+        let mut candidates: Vec<(usize, &DeviceId)> = devices
+            .iter()
+            .map(|device_id| {
+                let count = placed
+                    .iter()
+                    .filter(|item| item.device_id == *device_id)
+                    .count();
+                (count, device_id)
+            })
+            .collect();
+        candidates.retain(|(count, _)| *count < params.parity_fragments as usize);
+        candidates.sort_by_key(|(count, _)| *count);
+
+        for (_, device_id) in candidates {
+            if self
+                .0
+                .store
+                .put_fragment(device_id, fragment_id, fragment.clone())
+                .await
+                .is_ok()
+            {
+                return Ok(device_id.to_owned());
+            }
+        }
+
+        let msg = format!(
+            "put erasure fragment failed, no device can hold it durably! fragment={}, devices={}, placed={}",
+            fragment_id,
+            devices.len(),
+            placed.len()
+        );
+        error!("{}", msg);
+        Err(BuckyError::new(BuckyErrorCode::ErrorState, msg))
+    }
+
+    async fn check_fragments(&self, devices: &[DeviceId]) {
+        let chunks: Vec<(ChunkId, ErasureChunkInfo)> = {
+            let index = self.index().coll().read().unwrap();
+            index
+                .chunks
+                .iter()
+                .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                .collect()
+        };
+
+        for (chunk_id, info) in chunks {
+            let mut lost = vec![];
+            for fragment in &info.fragments {
+                if !devices.contains(&fragment.device_id)
+                    || !self
+                        .0
+                        .store
+                        .probe_fragment(&fragment.device_id, &fragment.fragment_id)
+                        .await
+                {
+                    lost.push(fragment.index);
+                }
+            }
+
+            if lost.is_empty() {
+                continue;
+            }
+
+            warn!(
+                "erasure fragments lost, now will rebuild! chunk={}, lost={:?}",
+                chunk_id, lost
+            );
+
+            match self
+                .rebuild_fragments(&chunk_id, &info, &lost, devices)
+                .await
+            {
+                Ok(info) => {
+                    self.index()
+                        .coll()
+                        .write()
+                        .unwrap()
+                        .chunks
+                        .insert(chunk_id, info);
+                    self.index().set_dirty(true);
+                }
+                Err(e) => {
+                    error!(
+                        "rebuild erasure fragments failed! chunk={}, {}",
+                        chunk_id, e
+                    );
+                }
+            }
+        }
+    }
+
+    async fn rebuild_fragments(
+        &self,
+        chunk_id: &ChunkId,
+        info: &ErasureChunkInfo,
+        lost: &[u16],
+        devices: &[DeviceId],
+    ) -> BuckyResult<ErasureChunkInfo> {
+        let params = info.params()?;
+        let data = match self.0.store.get_local_chunk(chunk_id).await {
+            Ok(data) => data,
+            Err(_) => self.reconstruct_chunk(chunk_id, info).await?,
+        };
+
+        // the encoding is determinate, so the rebuilt fragments are the same as the lost ones
+        let fragments = ErasureCodec::encode(&params, &data)?;
+
+        let lost_devices: Vec<&DeviceId> = info
+            .fragments
+            .iter()
+            .filter(|item| lost.contains(&item.index))
+            .map(|item| &item.device_id)
+            .collect();
+        let alive: Vec<DeviceId> = devices
+            .iter()
+            .filter(|device_id| !lost_devices.contains(device_id))
+            .cloned()
+            .collect();
+        let candidates = if alive.is_empty() {
+            devices
+        } else {
+            &alive[..]
+        };
+
+        let mut placed: Vec<ErasureFragmentInfo> = info
+            .fragments
+            .iter()
+            .filter(|item| !lost.contains(&item.index))
+            .cloned()
+            .collect();
+
+        for index in lost {
+            let fragment = fragments[*index as usize].clone();
+            let fragment_id = ChunkId::calculate(&fragment).await?;
+            let prev = info
+                .fragments
+                .iter()
+                .find(|item| item.index == *index)
+                .unwrap();
+            if fragment_id != prev.fragment_id {
+                let msg = format!(
+                    "rebuild erasure fragment but unmatch! chunk={}, index={}, expect={}, got={}",
+                    chunk_id, index, prev.fragment_id, fragment_id
+                );
+                error!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+            }
+
+            let device_id = self
+                .place_fragment(&fragment_id, fragment, candidates, &placed, &params)
+                .await?;
+
+            info!(
+                "rebuild erasure fragment success! chunk={}, index={}, {} -> {}",
+                chunk_id, index, prev.device_id, device_id
+            );
+
+            placed.push(ErasureFragmentInfo {
+                index: *index,
+                fragment_id,
+                device_id,
+            });
+        }
+
+        placed.sort_by_key(|item| item.index);
+        Ok(ErasureChunkInfo::new(&params, placed))
+    }
+
+    async fn reconstruct_chunk(
+        &self,
+        chunk_id: &ChunkId,
+        info: &ErasureChunkInfo,
+    ) -> BuckyResult<Vec<u8>> {
+        let params = info.params()?;
+
+        let mut list = Vec::with_capacity(params.data_fragments as usize);
+        for fragment in &info.fragments {
+            match self
+                .0
+                .store
+                .get_fragment(&fragment.device_id, &fragment.fragment_id)
+                .await
+            {
+                Ok(data) => list.push((fragment.index, data)),
+                Err(_) => continue,
+            }
+
+            if list.len() < params.data_fragments as usize {
+                continue;
+            }
+
+            if let Ok(data) = ErasureCodec::decode(&params, &list) {
+                let id = ChunkId::calculate(&data).await?;
+                if id != *chunk_id {
+                    let msg = format!(
+                        "reconstruct erasure chunk but unmatch! chunk={}, got={}",
+                        chunk_id, id
+                    );
+                    error!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+                }
+
+                info!(
+                    "reconstruct erasure chunk success! chunk={}, fragments={}",
+                    chunk_id,
+                    list.len()
+                );
+                return Ok(data);
+            }
+        }
+
+        let msg = format!(
+            "reconstruct erasure chunk failed, not enough fragments! chunk={}, got={}, data_fragments={}",
+            chunk_id,
+            list.len(),
+            params.data_fragments
+        );
+        error!("{}", msg);
+        Err(BuckyError::new(BuckyErrorCode::NotFound, msg))
+    }
+
+    // Called before get_data, the missing chunks will be reconstructed from the fragments and saved to local
+    pub async fn prepare_data(&self, req: &NDNGetDataInputRequest) {
+        if let Some(target) = &req.common.target {
+            if target != self.0.device_id.object_id() {
+                return;
+            }
+        }
+
+        let index = match self.0.index.get() {
+            Some(index) => index,
+            None => return,
+        };
+
+        let chunks = match req.object_id.obj_type_code() {
+            ObjectTypeCode::Chunk => vec![ChunkId::try_from(&req.object_id).unwrap()],
+            ObjectTypeCode::File => match index.coll().read().unwrap().files.get(&req.object_id) {
+                Some(list) => list.clone(),
+                None => return,
+            },
+            _ => return,
+        };
+
+        // only the chunks in the request range are reconstructed for the ranged read of file
+        let chunks = match &req.range {
+            Some(range) if req.object_id.obj_type_code() == ObjectTypeCode::File => {
+                let len = chunks.iter().map(|v| v.len() as u64).sum();
+                match range.convert_to_response(len) {
+                    Some(NDNDataResponseRange::Range((ranges, _))) => {
+                        select_chunks(&chunks, &ranges)
+                    }
+                    Some(_) => return,
+                    None => chunks,
+                }
+            }
+            _ => chunks,
+        };
+
+        for chunk_id in chunks {
+            let info = match index.coll().read().unwrap().chunks.get(&chunk_id) {
+                Some(info) => info.clone(),
+                None => continue,
+            };
+
+            if self.0.store.local().exist_chunk(&chunk_id).await {
+                continue;
+            }
+
+            let ret = match self.reconstruct_chunk(&chunk_id, &info).await {
+                Ok(data) => self.0.store.put_local_chunk(&chunk_id, data).await,
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = ret {
+                error!(
+                    "reconstruct erasure chunk on read failed! object={}, chunk={}, {}",
+                    req.object_id, chunk_id, e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_durable() {
+        // 4+2 needs 3 devices at least, each holds 2 fragments
+        assert!(!is_durable(1, 4, 2));
+        assert!(!is_durable(2, 4, 2));
+        assert!(is_durable(3, 4, 2));
+        assert!(is_durable(6, 4, 2));
+
+        // without parity fragments, losing any device will lose the chunk
+        assert!(!is_durable(10, 4, 0));
+    }
+
+    #[test]
+    fn test_select_chunks() {
+        let chunks: Vec<ChunkId> = (0..4)
+            .map(|i| ChunkId::calculate_sync(&vec![i as u8; 100]).unwrap())
+            .collect();
+
+        assert_eq!(select_chunks(&chunks, &[0..400]), chunks);
+        assert_eq!(select_chunks(&chunks, &[0..1]), chunks[..1].to_vec());
+        assert_eq!(select_chunks(&chunks, &[99..101]), chunks[..2].to_vec());
+        assert_eq!(select_chunks(&chunks, &[100..200]), chunks[1..2].to_vec());
+        assert_eq!(
+            select_chunks(&chunks, &[50..60, 350..400]),
+            vec![chunks[0].clone(), chunks[3].clone()]
+        );
+        assert!(select_chunks(&chunks, &[400..500]).is_empty());
+    }
+}
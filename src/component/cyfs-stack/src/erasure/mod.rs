@@ -0,0 +1,6 @@
+mod codec;
+mod index;
+mod manager;
+mod store;
+
+pub(crate) use manager::*;
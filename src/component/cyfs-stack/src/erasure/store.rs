@@ -0,0 +1,207 @@
+use crate::forward::ForwardProcessorManager;
+use crate::ndn_api::LocalDataManager;
+use cyfs_base::*;
+use cyfs_chunk_cache::MemChunk;
+use cyfs_lib::*;
+
+use futures::AsyncReadExt;
+
+// Read and write the fragments on the devices of current zone, fragments are stored as normal chunks
+pub(crate) struct ErasureFragmentStore {
+    device_id: DeviceId,
+    local: LocalDataManager,
+    forward: ForwardProcessorManager,
+}
+
+impl ErasureFragmentStore {
+    pub fn new(
+        device_id: DeviceId,
+        local: LocalDataManager,
+        forward: ForwardProcessorManager,
+    ) -> Self {
+        Self {
+            device_id,
+            local,
+            forward,
+        }
+    }
+
+    pub fn local(&self) -> &LocalDataManager {
+        &self.local
+    }
+
+    async fn get_remote(&self, device_id: &DeviceId) -> BuckyResult<NDNOutputProcessorRef> {
+        let requestor = self.forward.get(device_id).await?;
+        Ok(NDNRequestor::new(None, requestor, None).into_processor())
+    }
+
+    async fn read_all(
+        id: &ChunkId,
+        mut reader: Box<dyn async_std::io::Read + Unpin + Send + Sync + 'static>,
+    ) -> BuckyResult<Vec<u8>> {
+        let mut buf = Vec::with_capacity(id.len());
+        reader.read_to_end(&mut buf).await.map_err(|e| {
+            let msg = format!("read chunk data failed! chunk={}, {}", id, e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
+
+        Ok(buf)
+    }
+
+    pub async fn get_local_chunk(&self, chunk_id: &ChunkId) -> BuckyResult<Vec<u8>> {
+        let source = RequestSourceInfo::new_local_system();
+        let (reader, _, _) = self.local.get_chunk(&source, chunk_id, None, None).await?;
+        Self::read_all(chunk_id, reader).await
+    }
+
+    pub async fn put_local_chunk(&self, chunk_id: &ChunkId, data: Vec<u8>) -> BuckyResult<()> {
+        if self.local.exist_chunk(chunk_id).await {
+            return Ok(());
+        }
+
+        self.local
+            .put_chunk(chunk_id, Box::new(MemChunk::from(data)), vec![])
+            .await
+    }
+
+    pub async fn put_fragment(
+        &self,
+        device_id: &DeviceId,
+        fragment_id: &ChunkId,
+        data: Vec<u8>,
+    ) -> BuckyResult<()> {
+        if *device_id == self.device_id {
+            return self.put_local_chunk(fragment_id, data).await;
+        }
+
+        let processor = self.get_remote(device_id).await?;
+        let mut req = NDNPutDataOutputRequest::new_with_buffer(
+            NDNAPILevel::NDC,
+            fragment_id.object_id(),
+            data,
+        );
+        req.common.dec_id = Some(cyfs_core::get_system_dec_app().to_owned());
+
+        processor.put_data(req).await.map_err(|e| {
+            error!(
+                "put erasure fragment to device failed! fragment={}, device={}, {}",
+                fragment_id, device_id, e
+            );
+            e
+        })?;
+
+        Ok(())
+    }
+
+    // Get the fragment and verify its content
+    pub async fn get_fragment(
+        &self,
+        device_id: &DeviceId,
+        fragment_id: &ChunkId,
+    ) -> BuckyResult<Vec<u8>> {
+        let data = if *device_id == self.device_id {
+            self.get_local_chunk(fragment_id).await?
+        } else {
+            let processor = self.get_remote(device_id).await?;
+            let mut req = NDNGetDataOutputRequest::new_ndc(fragment_id.object_id(), None);
+            req.common.dec_id = Some(cyfs_core::get_system_dec_app().to_owned());
+
+            let resp = processor.get_data(req).await.map_err(|e| {
+                warn!(
+                    "get erasure fragment from device failed! fragment={}, device={}, {}",
+                    fragment_id, device_id, e
+                );
+                e
+            })?;
+            Self::read_all(fragment_id, resp.data).await?
+        };
+
+        let id = ChunkId::calculate(&data).await?;
+        if id != *fragment_id {
+            let msg = format!(
+                "erasure fragment content unmatch! fragment={}, device={}, got={}",
+                fragment_id, device_id, id
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+        }
+
+        Ok(data)
+    }
+
+    // Put the object to the noc of the remote device
+    pub async fn put_object(&self, device_id: &DeviceId, object: NONObjectInfo) -> BuckyResult<()> {
+        let requestor = self.forward.get(device_id).await?;
+        let processor = NONRequestor::new(None, requestor).into_processor();
+
+        let object_id = object.object_id.clone();
+        let mut req = NONPutObjectOutputRequest::new_noc(object.object_id, object.object_raw);
+        req.common.dec_id = Some(cyfs_core::get_system_dec_app().to_owned());
+
+        processor.put_object(req).await.map_err(|e| {
+            error!(
+                "put object to device failed! object={}, device={}, {}",
+                object_id, device_id, e
+            );
+            e
+        })?;
+
+        Ok(())
+    }
+
+    // Get the object from the noc of the remote device, None if not exists
+    pub async fn get_object(
+        &self,
+        device_id: &DeviceId,
+        object_id: &ObjectId,
+    ) -> BuckyResult<Option<NONObjectInfo>> {
+        let requestor = self.forward.get(device_id).await?;
+        let processor = NONRequestor::new(None, requestor).into_processor();
+
+        let mut req = NONGetObjectOutputRequest::new_noc(object_id.to_owned(), None);
+        req.common.dec_id = Some(cyfs_core::get_system_dec_app().to_owned());
+
+        match processor.get_object(req).await {
+            Ok(resp) => Ok(Some(resp.object)),
+            Err(e) if e.code() == BuckyErrorCode::NotFound => Ok(None),
+            Err(e) => {
+                warn!(
+                    "get object from device failed! object={}, device={}, {}",
+                    object_id, device_id, e
+                );
+                Err(e)
+            }
+        }
+    }
+
+    // Check if the fragment still exists on the device, only read one byte for remote device
+    pub async fn probe_fragment(&self, device_id: &DeviceId, fragment_id: &ChunkId) -> bool {
+        if *device_id == self.device_id {
+            return self.local.exist_chunk(fragment_id).await;
+        }
+
+        let processor = match self.get_remote(device_id).await {
+            Ok(processor) => processor,
+            Err(_) => return false,
+        };
+
+        let mut req = NDNGetDataOutputRequest::new_ndc(fragment_id.object_id(), None);
+        req.common.dec_id = Some(cyfs_core::get_system_dec_app().to_owned());
+        req.range = Some(NDNDataRequestRange::new_range(vec![0..1]));
+
+        match processor.get_data(req).await {
+            Ok(mut resp) => {
+                let mut buf = vec![];
+                resp.data.read_to_end(&mut buf).await.is_ok() && buf.len() == 1
+            }
+            Err(e) => {
+                warn!(
+                    "probe erasure fragment failed! fragment={}, device={}, {}",
+                    fragment_id, device_id, e
+                );
+                false
+            }
+        }
+    }
+}
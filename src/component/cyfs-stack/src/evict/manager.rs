@@ -0,0 +1,451 @@
+use super::protect::EvictProtectedSet;
+use crate::root_state_api::GlobalStateManager;
+use crate::stack::{CyfsStackEvictParams, CyfsStackEvictPolicy};
+use cyfs_base::*;
+use cyfs_bdt_ext::NamedDataComponentsRef;
+use cyfs_lib::*;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/*
+按配额淘汰noc里面的缓存对象和chunk cache里面的chunk
+1. 存储类别为Storage的对象视为固定(pinned)对象，永远不会淘汰，超出预算只告警
+2. 存储类别为Cache的对象按dec预算和全局预算淘汰，先处理超出预算的dec
+3. chunk cache超出预算后按LRU淘汰，ndc没有记录chunk的访问次数，LFU策略对chunk不生效
+对象的淘汰顺序由策略决定: LRU按last_access_time，LFU按access_count再按last_access_time
+从任一global-state(root-state和local-cache)当前root可达的对象和chunk都不会被淘汰；
+遍历之后新加入的引用可能来不及保护，所以最近min_idle_secs内访问过的数据也不参与淘汰
+*/
+
+const EVICT_PAGE_SIZE: usize = 256;
+
+struct EvictManagerInner {
+    params: CyfsStackEvictParams,
+
+    noc: NamedObjectCacheRef,
+    named_data_components: NamedDataComponentsRef,
+    global_state_manager: GlobalStateManager,
+}
+
+// 一轮检查后各部分需要释放的空间
+struct EvictPlan {
+    // (dec_id, size)
+    dec_list: Vec<(ObjectId, u64)>,
+
+    // total size of the cached objects and chunks
+    noc_cache_size: u64,
+    chunk_cache_size: u64,
+}
+
+impl EvictPlan {
+    fn is_empty(&self, params: &CyfsStackEvictParams) -> bool {
+        self.dec_list.is_empty()
+            && !Self::is_over(self.noc_cache_size, params.noc_cache_budget)
+            && !Self::is_over(self.chunk_cache_size, params.chunk_cache_budget)
+    }
+
+    fn is_over(size: u64, budget: u64) -> bool {
+        budget > 0 && size > budget
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct EvictManager(Arc<EvictManagerInner>);
+
+impl EvictManager {
+    pub fn new(
+        params: CyfsStackEvictParams,
+        noc: NamedObjectCacheRef,
+        named_data_components: NamedDataComponentsRef,
+        global_state_manager: GlobalStateManager,
+    ) -> Self {
+        let inner = EvictManagerInner {
+            params,
+            noc,
+            named_data_components,
+            global_state_manager,
+        };
+
+        Self(Arc::new(inner))
+    }
+
+    pub fn start(&self) {
+        let this = self.clone();
+        async_std::task::spawn(async move {
+            // wait for the stack and the global states ready
+            async_std::task::sleep(Duration::from_secs(60)).await;
+
+            let interval = Duration::from_secs(this.0.params.interval_secs);
+            loop {
+                this.evict_once().await;
+                async_std::task::sleep(interval).await;
+            }
+        });
+    }
+
+    async fn evict_once(&self) {
+        let mut plan = match self.check_usage().await {
+            Ok(plan) => plan,
+            Err(e) => {
+                error!("check usage for evict failed! {}", e);
+                return;
+            }
+        };
+
+        if plan.is_empty(&self.0.params) {
+            debug!(
+                "evict check complete, all within budget: noc_cache={}, chunk_cache={}",
+                plan.noc_cache_size, plan.chunk_cache_size
+            );
+            return;
+        }
+
+        // 每轮重新遍历，无法确定引用关系时放弃本轮，宁可超出预算也不能误删
+        let protected = match EvictProtectedSet::load(
+            &self.0.global_state_manager,
+            &self.0.noc,
+            &self.0.named_data_components,
+        )
+        .await
+        {
+            Ok(set) => set,
+            Err(e) => {
+                error!(
+                    "load protected set failed, now will skip evict round! {}",
+                    e
+                );
+                return;
+            }
+        };
+
+        let last_access_before =
+            bucky_time_now().saturating_sub(self.0.params.min_idle_secs * 1000 * 1000);
+
+        let mut objects_freed = 0;
+        for (dec_id, need) in &plan.dec_list {
+            let freed = self
+                .evict_objects(Some(dec_id), *need, &protected, last_access_before)
+                .await;
+            if freed < *need {
+                warn!(
+                    "evict dec's cached objects but still over budget! dec={}, need={}, freed={}",
+                    dec_id, need, freed
+                );
+            }
+            objects_freed += freed;
+        }
+
+        plan.noc_cache_size = plan.noc_cache_size.saturating_sub(objects_freed);
+        if EvictPlan::is_over(plan.noc_cache_size, self.0.params.noc_cache_budget) {
+            let need = plan.noc_cache_size - self.0.params.noc_cache_budget;
+            let freed = self
+                .evict_objects(None, need, &protected, last_access_before)
+                .await;
+            if freed < need {
+                warn!(
+                    "evict cached objects but still over budget! need={}, freed={}",
+                    need, freed
+                );
+            }
+            objects_freed += freed;
+        }
+
+        let mut chunks_freed = 0;
+        if EvictPlan::is_over(plan.chunk_cache_size, self.0.params.chunk_cache_budget) {
+            let need = plan.chunk_cache_size - self.0.params.chunk_cache_budget;
+            chunks_freed = self
+                .evict_chunks(need, &protected, last_access_before)
+                .await;
+            if chunks_freed < need {
+                warn!(
+                    "evict cached chunks but still over budget! need={}, freed={}",
+                    need, chunks_freed
+                );
+            }
+        }
+
+        info!(
+            "evict round complete: policy={:?}, objects_freed={}, chunks_freed={}",
+            self.0.params.policy, objects_freed, chunks_freed
+        );
+    }
+
+    async fn check_usage(&self) -> BuckyResult<EvictPlan> {
+        let stat = self.0.noc.stat().await?;
+
+        let mut noc_cache_size = 0;
+        let mut noc_storage_size = 0;
+        let mut dec_list = vec![];
+        for item in &stat.usage {
+            match item.storage_category {
+                NamedObjectStorageCategory::Storage => {
+                    noc_storage_size += item.size;
+                }
+                NamedObjectStorageCategory::Cache => {
+                    noc_cache_size += item.size;
+
+                    let budget = self.0.params.get_dec_cache_budget(&item.dec_id);
+                    if EvictPlan::is_over(item.size, budget) {
+                        dec_list.push((item.dec_id.clone(), item.size - budget));
+                    }
+                }
+            }
+        }
+
+        if EvictPlan::is_over(noc_storage_size, self.0.params.noc_storage_budget) {
+            warn!(
+                "noc pinned objects over budget, but will not be evicted! size={}, budget={}",
+                noc_storage_size, self.0.params.noc_storage_budget
+            );
+        }
+
+        let chunk_cache_size = self
+            .0
+            .named_data_components
+            .chunk_manager
+            .used_size()
+            .await?;
+
+        Ok(EvictPlan {
+            dec_list,
+            noc_cache_size,
+            chunk_cache_size,
+        })
+    }
+
+    // 删除后同一页的内容会前移，所以有删除时重新读取当前页，否则读取下一页
+    async fn evict_objects(
+        &self,
+        dec_id: Option<&ObjectId>,
+        need: u64,
+        protected: &EvictProtectedSet,
+        last_access_before: u64,
+    ) -> u64 {
+        let order = match self.0.params.policy {
+            CyfsStackEvictPolicy::Lru => NamedObjectCacheSelectObjectOrder::LastAccessTime,
+            CyfsStackEvictPolicy::Lfu => NamedObjectCacheSelectObjectOrder::AccessCount,
+        };
+
+        let mut freed = 0;
+        let mut page_index = 0;
+        while freed < need {
+            let req = NamedObjectCacheSelectObjectRequest {
+                filter: NamedObjectCacheSelectObjectFilter {
+                    storage_category: Some(NamedObjectStorageCategory::Cache),
+                    create_dec_id: dec_id.cloned(),
+                    last_access_before: Some(last_access_before),
+                    ..Default::default()
+                },
+                opt: NamedObjectCacheSelectObjectOption {
+                    page_size: EVICT_PAGE_SIZE,
+                    page_index,
+                    order,
+                },
+            };
+
+            let list = match self.0.noc.select_object(&req).await {
+                Ok(resp) => resp.list,
+                Err(e) => {
+                    error!(
+                        "select objects from noc for evict failed! dec={:?}, page={}, {}",
+                        dec_id, page_index, e
+                    );
+                    break;
+                }
+            };
+
+            let count = list.len();
+            let mut deleted = 0;
+            for item in list {
+                if freed >= need {
+                    break;
+                }
+                if protected.objects.contains(&item.object_id) {
+                    continue;
+                }
+
+                if let Some(size) = self.delete_object(&item.object_id).await {
+                    freed += size;
+                    deleted += 1;
+                }
+            }
+
+            if count < EVICT_PAGE_SIZE {
+                break;
+            }
+            if deleted == 0 {
+                page_index += 1;
+            }
+        }
+
+        freed
+    }
+
+    async fn delete_object(&self, object_id: &ObjectId) -> Option<u64> {
+        let req = NamedObjectCacheDeleteObjectRequest {
+            source: RequestSourceInfo::new_local_system(),
+            object_id: object_id.to_owned(),
+            flags: CYFS_NOC_FLAG_DELETE_WITH_QUERY,
+        };
+
+        match self.0.noc.delete_object(&req).await {
+            Ok(resp) if resp.deleted_count > 0 => {
+                let size = resp
+                    .object
+                    .map(|object| object.object_raw.len() as u64)
+                    .unwrap_or(0);
+                debug!("evict object from noc: obj={}, size={}", object_id, size);
+                Some(size)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                error!("evict object from noc failed! obj={}, {}", object_id, e);
+                None
+            }
+        }
+    }
+
+    // chunk只支持LRU，不受CyfsStackEvictPolicy影响
+    async fn evict_chunks(
+        &self,
+        need: u64,
+        protected: &EvictProtectedSet,
+        last_access_before: u64,
+    ) -> u64 {
+        let mut freed = 0;
+        let mut page_index = 0;
+        while freed < need {
+            let req = SelectChunkRequest {
+                filter: SelectChunkFilter {
+                    state: Some(ChunkState::Ready),
+                    last_access_before: Some(last_access_before),
+                },
+                opt: SelectChunkOption {
+                    page_size: EVICT_PAGE_SIZE,
+                    page_index,
+                    order: SelectChunkOrder::LastAccessTime,
+                },
+            };
+
+            let list = match self.0.named_data_components.ndc.select_chunk(&req).await {
+                Ok(resp) => resp.list,
+                Err(e) => {
+                    error!(
+                        "select chunks from ndc for evict failed! page={}, {}",
+                        page_index, e
+                    );
+                    break;
+                }
+            };
+
+            let count = list.len();
+            let mut deleted = 0;
+            for item in list {
+                if freed >= need {
+                    break;
+                }
+                if protected.chunks.contains(&item.chunk_id) {
+                    continue;
+                }
+
+                if self.delete_chunk(&item.chunk_id).await {
+                    freed += item.chunk_id.len() as u64;
+                    deleted += 1;
+                }
+            }
+
+            if count < EVICT_PAGE_SIZE {
+                break;
+            }
+            if deleted == 0 {
+                page_index += 1;
+            }
+        }
+
+        freed
+    }
+
+    // 被固定对象引用的chunk也不能淘汰
+    async fn is_chunk_pinned(&self, chunk_id: &ChunkId) -> bool {
+        let req = GetChunkRefObjectsRequest {
+            chunk_id: chunk_id.to_owned(),
+            relation: None,
+        };
+
+        let list = match self
+            .0
+            .named_data_components
+            .ndc
+            .get_chunk_ref_objects(&req)
+            .await
+        {
+            Ok(list) => list,
+            Err(e) => {
+                error!("get chunk ref objects failed! chunk={}, {}", chunk_id, e);
+                return true;
+            }
+        };
+
+        for item in list {
+            let mut req = NamedObjectCacheGetObjectRequest {
+                source: RequestSourceInfo::new_local_system(),
+                object_id: item.object_id.clone(),
+                last_access_rpath: None,
+                flags: 0,
+            };
+            req.set_no_update_last_access();
+
+            match self.0.noc.get_object_raw(&req).await {
+                Ok(Some(data)) => {
+                    if data.meta.storage_category == NamedObjectStorageCategory::Storage {
+                        return true;
+                    }
+                }
+                Ok(None) => {}
+                Err(_) => return true,
+            }
+        }
+
+        false
+    }
+
+    async fn delete_chunk(&self, chunk_id: &ChunkId) -> bool {
+        let chunk_manager = &self.0.named_data_components.chunk_manager;
+
+        // ready的chunk也可能只存在于本地文件中(tracker)，只淘汰chunk cache里面的
+        if !chunk_manager.exist(chunk_id).await {
+            return false;
+        }
+
+        if self.is_chunk_pinned(chunk_id).await {
+            return false;
+        }
+
+        if let Err(e) = chunk_manager.delete_chunk(chunk_id).await {
+            error!(
+                "evict chunk from chunk cache failed! chunk={}, {}",
+                chunk_id, e
+            );
+            return false;
+        }
+
+        let req = UpdateChunkStateRequest {
+            chunk_id: chunk_id.to_owned(),
+            current_state: Some(ChunkState::Ready),
+            state: ChunkState::NotFound,
+        };
+        if let Err(e) = self
+            .0
+            .named_data_components
+            .ndc
+            .update_chunk_state(&req)
+            .await
+        {
+            error!("evict update chunk state failed! chunk={}, {}", chunk_id, e);
+        }
+
+        debug!("evict chunk from chunk cache: chunk={}", chunk_id);
+        true
+    }
+}
@@ -0,0 +1,4 @@
+mod manager;
+mod protect;
+
+pub(crate) use manager::*;
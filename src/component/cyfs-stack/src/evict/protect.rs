@@ -0,0 +1,145 @@
+use crate::root_state_api::GlobalStateManager;
+use cyfs_base::*;
+use cyfs_bdt_ext::NamedDataComponentsRef;
+use cyfs_lib::*;
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+// 从所有global-state的当前root可达的对象和chunk，淘汰时必须跳过
+pub(crate) struct EvictProtectedSet {
+    pub objects: HashSet<ObjectId>,
+    pub chunks: HashSet<ChunkId>,
+}
+
+#[derive(Clone)]
+struct EvictProtectedCollector {
+    set: Arc<Mutex<EvictProtectedSet>>,
+
+    // 遍历出错时无法确定引用关系，本轮放弃淘汰
+    error_count: Arc<Mutex<u32>>,
+}
+
+#[async_trait::async_trait]
+impl ObjectTraverserHandler for EvictProtectedCollector {
+    async fn filter_path(&self, _path: &str) -> ObjectTraverseFilterResult {
+        ObjectTraverseFilterResult::Keep(None)
+    }
+
+    async fn filter_object(
+        &self,
+        _object: &NONObjectInfo,
+        _meta: Option<&NamedObjectMetaData>,
+    ) -> ObjectTraverseFilterResult {
+        ObjectTraverseFilterResult::Keep(None)
+    }
+
+    async fn on_error(&self, id: &ObjectId, e: BuckyError) -> BuckyResult<()> {
+        warn!(
+            "evict traverse global state object error! obj={}, {}",
+            id, e
+        );
+        *self.error_count.lock().unwrap() += 1;
+        Ok(())
+    }
+
+    async fn on_missing(&self, _id: &ObjectId) -> BuckyResult<()> {
+        Ok(())
+    }
+
+    async fn on_object(
+        &self,
+        object: &NONObjectInfo,
+        _meta: &Option<NamedObjectMetaData>,
+    ) -> BuckyResult<()> {
+        self.set
+            .lock()
+            .unwrap()
+            .objects
+            .insert(object.object_id.clone());
+        Ok(())
+    }
+
+    async fn on_chunk(&self, chunk_id: &ChunkId) -> BuckyResult<()> {
+        self.set.lock().unwrap().chunks.insert(chunk_id.to_owned());
+        Ok(())
+    }
+}
+
+impl EvictProtectedSet {
+    // 遍历root-state和local-cache下所有isolate的当前root
+    pub async fn load(
+        global_state_manager: &GlobalStateManager,
+        noc: &NamedObjectCacheRef,
+        named_data_components: &NamedDataComponentsRef,
+    ) -> BuckyResult<Self> {
+        let collector = EvictProtectedCollector {
+            set: Arc::new(Mutex::new(Self {
+                objects: HashSet::new(),
+                chunks: HashSet::new(),
+            })),
+            error_count: Arc::new(Mutex::new(0)),
+        };
+
+        let chunk_reader = Arc::new(named_data_components.new_chunk_reader());
+        let loader = ObjectTraverserLocalLoader::new(noc.clone(), chunk_reader).into_reader();
+        let handler: ObjectTraverserHandlerRef = Arc::new(Box::new(collector.clone()));
+
+        for category in [
+            GlobalStateCategory::RootState,
+            GlobalStateCategory::LocalCache,
+        ] {
+            for info in global_state_manager.get_isolate_list(category).await {
+                let state = global_state_manager
+                    .load_global_state(category, &info.isolate_id, info.owner.clone(), false)
+                    .await?;
+                let state = match state {
+                    Some(state) => state,
+                    None => continue,
+                };
+
+                let (root, revision) = state.get_current_root();
+                debug!(
+                    "will traverse global state for evict: category={}, isolate={}, root={}, revision={}",
+                    category, info.isolate_id, root, revision
+                );
+
+                let traverser = ObjectTraverser::new(loader.clone(), handler.clone());
+                traverser.run(root).await.map_err(|e| {
+                    let msg = format!(
+                        "traverse global state for evict failed! category={}, isolate={}, root={}, {}",
+                        category, info.isolate_id, root, e
+                    );
+                    error!("{}", msg);
+                    BuckyError::new(e.code(), msg)
+                })?;
+            }
+        }
+
+        let error_count = *collector.error_count.lock().unwrap();
+        if error_count > 0 {
+            let msg = format!(
+                "traverse global state for evict but got errors! count={}",
+                error_count
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Failed, msg));
+        }
+
+        let set = std::mem::replace(
+            &mut *collector.set.lock().unwrap(),
+            Self {
+                objects: HashSet::new(),
+                chunks: HashSet::new(),
+            },
+        );
+
+        info!(
+            "load evict protected set complete: objects={}, chunks={}",
+            set.objects.len(),
+            set.chunks.len()
+        );
+
+        Ok(set)
+    }
+}
@@ -7,6 +7,9 @@ pub enum FrontRequestGetMode {
     Default,
     Object,
     Data,
+
+    // list the dir or object_map as an index page, the default for browsers which accept html
+    Index,
 }
 
 impl FrontRequestGetMode {
@@ -15,6 +18,7 @@ impl FrontRequestGetMode {
             Self::Default => "default",
             Self::Object => "object",
             Self::Data => "data",
+            Self::Index => "index",
         }
     }
 }
@@ -33,6 +37,7 @@ impl FromStr for FrontRequestGetMode {
             "default" => Self::Default,
             "object" => Self::Object,
             "data" => Self::Data,
+            "index" => Self::Index,
 
             _ => {
                 // as default action in access get action
@@ -80,3 +85,19 @@ impl FromStr for FrontRequestObjectFormat {
         Ok(ret)
     }
 }
+
+// The index page format for dir and object_map, negotiated by the format query and Accept header
+#[derive(Clone, Debug, Copy, Eq, PartialEq)]
+pub enum FrontIndexFormat {
+    Html,
+    Json,
+}
+
+impl FrontIndexFormat {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Self::Html => "html",
+            Self::Json => "json",
+        }
+    }
+}
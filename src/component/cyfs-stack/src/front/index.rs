@@ -0,0 +1,654 @@
+use super::def::FrontIndexFormat;
+use crate::ndn::NDNInputProcessorRef;
+use crate::non::NONInputProcessorRef;
+use cyfs_base::*;
+use cyfs_lib::*;
+
+use async_std::io::ReadExt;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct FrontIndexEntry {
+    pub name: String,
+    pub is_dir: bool,
+
+    // sub dir inside a dir object has no object_id
+    pub object_id: Option<ObjectId>,
+    pub size: Option<u64>,
+}
+
+impl FrontIndexEntry {
+    fn type_str(&self) -> String {
+        match &self.object_id {
+            Some(id) => format!("{:?}", id.obj_type_code()),
+            None => "Dir".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FrontIndexPage {
+    // the dir or object_map being listed
+    pub object_id: ObjectId,
+    pub inner_path: Option<String>,
+
+    pub entries: Vec<FrontIndexEntry>,
+}
+
+impl FrontIndexPage {
+    pub fn new(object_id: ObjectId, inner_path: Option<String>, mut entries: Vec<FrontIndexEntry>) -> Self {
+        // dirs first, then sort by name
+        entries.sort_by(|left, right| {
+            right
+                .is_dir
+                .cmp(&left.is_dir)
+                .then_with(|| left.name.cmp(&right.name))
+        });
+
+        Self {
+            object_id,
+            inner_path,
+            entries,
+        }
+    }
+
+    // same dir or object_map always has the same content, so the etag is derived from the object_id
+    pub fn etag(&self, format: FrontIndexFormat) -> String {
+        let path = self.inner_path.as_deref().unwrap_or("").trim_matches('/');
+        if path.is_empty() {
+            format!("\"{}.{}\"", self.object_id, format.as_str())
+        } else {
+            let hash = hash_data(path.as_bytes());
+            format!(
+                "\"{}.{}.{}\"",
+                self.object_id,
+                &hash.to_hex_string()[..16],
+                format.as_str()
+            )
+        }
+    }
+
+    pub fn encode(&self, format: FrontIndexFormat, base_path: &str, query: Option<&str>) -> String {
+        match format {
+            FrontIndexFormat::Html => self.encode_html(base_path, query),
+            FrontIndexFormat::Json => self.encode_json(base_path, query),
+        }
+    }
+
+    fn entry_href(base_path: &str, name: &str, query: Option<&str>) -> String {
+        let name: String =
+            percent_encoding::utf8_percent_encode(name, percent_encoding::NON_ALPHANUMERIC)
+                .collect();
+        let href = format!("{}/{}", base_path.trim_end_matches('/'), name);
+        match query {
+            Some(query) => format!("{}?{}", href, query),
+            None => href,
+        }
+    }
+
+    // the base_path maybe with or without the trailing '/', so a relative "../" can't be used
+    fn parent_href(base_path: &str, query: Option<&str>) -> String {
+        let path = base_path.trim_end_matches('/');
+        let href = match path.rfind('/') {
+            Some(pos) => &path[..pos + 1],
+            None => "/",
+        };
+        match query {
+            Some(query) => format!("{}?{}", href, query),
+            None => href.to_owned(),
+        }
+    }
+
+    fn object_href(object_id: &ObjectId) -> String {
+        format!("/o/{}", object_id)
+    }
+
+    pub fn encode_json(&self, base_path: &str, query: Option<&str>) -> String {
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            let mut item = Map::new();
+            item.insert("name".to_owned(), Value::String(entry.name.clone()));
+            item.insert("type".to_owned(), Value::String(entry.type_str()));
+            item.insert("is_dir".to_owned(), Value::Bool(entry.is_dir));
+            item.insert(
+                "href".to_owned(),
+                Value::String(Self::entry_href(base_path, &entry.name, query)),
+            );
+            if let Some(id) = &entry.object_id {
+                item.insert("object_id".to_owned(), Value::String(id.to_string()));
+                item.insert("object_href".to_owned(), Value::String(Self::object_href(id)));
+            }
+            if let Some(size) = entry.size {
+                item.insert("size".to_owned(), Value::Number(size.into()));
+            }
+
+            entries.push(Value::Object(item));
+        }
+
+        let mut page = Map::new();
+        page.insert("object_id".to_owned(), Value::String(self.object_id.to_string()));
+        if let Some(inner_path) = &self.inner_path {
+            page.insert("inner_path".to_owned(), Value::String(inner_path.clone()));
+        }
+        page.insert("entries".to_owned(), Value::Array(entries));
+
+        Value::Object(page).to_string()
+    }
+
+    pub fn encode_html(&self, base_path: &str, query: Option<&str>) -> String {
+        let title = format!("Index of {}", html_escape(base_path));
+
+        let mut html = String::with_capacity(1024 + self.entries.len() * 256);
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str(&format!("<title>{}</title>\n", title));
+        html.push_str("</head>\n<body>\n");
+        html.push_str(&format!("<h1>{}</h1>\n", title));
+        html.push_str(&format!(
+            "<p><a href=\"{}\">{}</a></p>\n",
+            Self::object_href(&self.object_id),
+            self.object_id
+        ));
+        html.push_str("<table>\n<tr><th>Name</th><th>Type</th><th>Size</th><th>Object</th></tr>\n");
+
+        if self.inner_path.as_deref().unwrap_or("").trim_matches('/').len() > 0 {
+            html.push_str(&format!(
+                "<tr><td><a href=\"{}\">../</a></td><td></td><td></td><td></td></tr>\n",
+                html_escape(&Self::parent_href(base_path, query))
+            ));
+        }
+
+        for entry in &self.entries {
+            let href = Self::entry_href(base_path, &entry.name, query);
+            let name = if entry.is_dir {
+                format!("{}/", html_escape(&entry.name))
+            } else {
+                html_escape(&entry.name)
+            };
+            let size = match entry.size {
+                Some(size) => size.to_string(),
+                None => "-".to_owned(),
+            };
+            let object = match &entry.object_id {
+                Some(id) => format!("<a href=\"{}\">{}</a>", Self::object_href(id), id),
+                None => "".to_owned(),
+            };
+
+            html.push_str(&format!(
+                "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                html_escape(&href),
+                name,
+                entry.type_str(),
+                size,
+                object,
+            ));
+        }
+
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+}
+
+fn html_escape(value: &str) -> String {
+    let mut ret = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            '\'' => ret.push_str("&#39;"),
+            _ => ret.push(c),
+        }
+    }
+
+    ret
+}
+
+// The source and target of the front request, all objects and chunks used to build the index are
+// loaded with them through the non/ndn router, so the acl and the remote target are same as the
+// request itself
+#[derive(Clone, Debug)]
+pub(crate) struct FrontIndexContext {
+    pub source: RequestSourceInfo,
+    pub target: Option<ObjectId>,
+    pub flags: u32,
+}
+
+impl FrontIndexContext {
+    async fn get_object(
+        &self,
+        non: &NONInputProcessorRef,
+        object_id: &ObjectId,
+    ) -> BuckyResult<NONGetObjectInputResponse> {
+        let req = NONGetObjectInputRequest {
+            common: NONInputRequestCommon {
+                req_path: None,
+                source: self.source.clone(),
+                level: NONAPILevel::Router,
+                target: self.target.clone(),
+                flags: self.flags,
+            },
+            object_id: object_id.to_owned(),
+            inner_path: None,
+        };
+
+        non.get_object(req).await
+    }
+
+    async fn get_chunk(
+        &self,
+        ndn: &NDNInputProcessorRef,
+        dir_id: &ObjectId,
+        chunk_id: &ChunkId,
+    ) -> BuckyResult<Vec<u8>> {
+        let req = NDNGetDataInputRequest {
+            common: NDNInputRequestCommon {
+                req_path: None,
+                source: self.source.clone(),
+                level: NDNAPILevel::Router,
+                referer_object: vec![NDNDataRefererObject {
+                    target: self.target.clone(),
+                    object_id: dir_id.to_owned(),
+                    inner_path: None,
+                }],
+                target: self.target.clone(),
+                flags: self.flags,
+                user_data: None,
+            },
+            object_id: chunk_id.object_id(),
+            data_type: NDNDataType::Mem,
+            range: None,
+            inner_path: None,
+            context: None,
+            group: None,
+        };
+
+        let mut resp = ndn.get_data(req).await.map_err(|e| {
+            error!(
+                "load dir chunk for index failed! dir={}, chunk={}, {}",
+                dir_id, chunk_id, e
+            );
+            e
+        })?;
+
+        let mut buf = Vec::with_capacity(chunk_id.len());
+        resp.data.read_to_end(&mut buf).await.map_err(|e| {
+            let msg = format!(
+                "read dir chunk for index failed! dir={}, chunk={}, {}",
+                dir_id, chunk_id, e
+            );
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
+
+        if buf.len() != chunk_id.len() {
+            let msg = format!(
+                "read dir chunk for index but len unmatch! dir={}, chunk={}, read={}",
+                dir_id,
+                chunk_id,
+                buf.len()
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+        }
+
+        Ok(buf)
+    }
+}
+
+// Read only object_map cache for listing, the object_maps are loaded with the request context
+struct FrontIndexObjectMapCache {
+    non: NONInputProcessorRef,
+    ctx: FrontIndexContext,
+}
+
+#[async_trait::async_trait]
+impl ObjectMapNOCCache for FrontIndexObjectMapCache {
+    async fn exists(&self, dec: Option<ObjectId>, object_id: &ObjectId) -> BuckyResult<bool> {
+        Ok(self.get_object_map_ex(dec, object_id).await?.is_some())
+    }
+
+    async fn get_object_map_ex(
+        &self,
+        _dec: Option<ObjectId>,
+        object_id: &ObjectId,
+    ) -> BuckyResult<Option<ObjectMapCacheItem>> {
+        let resp = match self.ctx.get_object(&self.non, object_id).await {
+            Ok(resp) => resp,
+            Err(e) if e.code() == BuckyErrorCode::NotFound => return Ok(None),
+            Err(e) => {
+                error!("load object_map for index failed! id={}, {}", object_id, e);
+                return Err(e);
+            }
+        };
+
+        let (object, _) = ObjectMap::raw_decode(&resp.object.object_raw).map_err(|e| {
+            error!("decode object_map for index failed! id={}, {}", object_id, e);
+            e
+        })?;
+        object.direct_set_object_id_on_init(object_id);
+
+        Ok(Some(ObjectMapCacheItem {
+            object,
+            access: AccessString::default(),
+        }))
+    }
+
+    async fn put_object_map(
+        &self,
+        _dec: Option<ObjectId>,
+        object_id: ObjectId,
+        _object: ObjectMap,
+        _access: Option<AccessString>,
+    ) -> BuckyResult<()> {
+        let msg = format!("object_map cache for index is read only! id={}", object_id);
+        error!("{}", msg);
+        Err(BuckyError::new(BuckyErrorCode::NotSupport, msg))
+    }
+}
+
+pub(crate) struct FrontIndexLoader {
+    non: NONInputProcessorRef,
+    ndn: NDNInputProcessorRef,
+}
+
+impl FrontIndexLoader {
+    pub fn new(non: NONInputProcessorRef, ndn: NDNInputProcessorRef) -> Self {
+        Self { non, ndn }
+    }
+
+    async fn load_dir_lists(
+        &self,
+        ctx: &FrontIndexContext,
+        dir_id: &ObjectId,
+        dir: &Dir,
+    ) -> BuckyResult<(NDNObjectList, Option<DirBodyContentObjectList>)> {
+        let body = match dir.body() {
+            Some(body) => match body.content() {
+                DirBodyContent::ObjList(list) => Some(list.to_owned()),
+                DirBodyContent::Chunk(chunk_id) => {
+                    let buf = ctx.get_chunk(&self.ndn, dir_id, chunk_id).await?;
+                    let (list, _) = DirBodyContentObjectList::raw_decode(&buf)?;
+                    Some(list)
+                }
+            },
+            None => None,
+        };
+
+        let list = match dir.desc().content().obj_list() {
+            NDNObjectInfo::ObjList(list) => list.to_owned(),
+            NDNObjectInfo::Chunk(chunk_id) => {
+                // the desc chunk maybe packed in the body
+                let buf = match body.as_ref().and_then(|body| body.get(chunk_id.as_object_id())) {
+                    Some(buf) => buf.to_owned(),
+                    None => ctx.get_chunk(&self.ndn, dir_id, chunk_id).await?,
+                };
+                let (list, _) = NDNObjectList::raw_decode(&buf)?;
+                list
+            }
+        };
+
+        Ok((list, body))
+    }
+
+    // list the dir's direct children under the prefix, return None if the prefix not exists
+    pub async fn load_dir(
+        &self,
+        ctx: &FrontIndexContext,
+        dir_id: &ObjectId,
+        dir: &Dir,
+        inner_path: Option<&str>,
+    ) -> BuckyResult<Option<FrontIndexPage>> {
+        let (desc, body) = self.load_dir_lists(ctx, dir_id, dir).await?;
+
+        let prefix = inner_path.unwrap_or("").trim_matches('/');
+        let mut sub_dirs = HashSet::new();
+        let mut entries = vec![];
+        let mut found = prefix.is_empty();
+        for (path, info) in desc.object_map() {
+            let path = path.trim_start_matches('/');
+            let rel = if prefix.is_empty() {
+                path
+            } else {
+                match path.strip_prefix(prefix).and_then(|v| v.strip_prefix('/')) {
+                    Some(rel) => rel,
+                    None => continue,
+                }
+            };
+            found = true;
+
+            if let Some(pos) = rel.find('/') {
+                let name = &rel[..pos];
+                if sub_dirs.insert(name.to_owned()) {
+                    entries.push(FrontIndexEntry {
+                        name: name.to_owned(),
+                        is_dir: true,
+                        object_id: None,
+                        size: None,
+                    });
+                }
+                continue;
+            }
+
+            let entry = match info.node() {
+                InnerNode::ObjId(id) => FrontIndexEntry {
+                    name: rel.to_owned(),
+                    is_dir: Self::is_dir_type(id),
+                    object_id: Some(id.to_owned()),
+                    size: self.object_size(ctx, id, body.as_ref()).await,
+                },
+                InnerNode::Chunk(chunk_id) => FrontIndexEntry {
+                    name: rel.to_owned(),
+                    is_dir: false,
+                    object_id: Some(chunk_id.object_id()),
+                    size: Some(chunk_id.len() as u64),
+                },
+                InnerNode::IndexInParentChunk(_, len) => FrontIndexEntry {
+                    name: rel.to_owned(),
+                    is_dir: false,
+                    object_id: None,
+                    size: Some(*len as u64),
+                },
+            };
+            entries.push(entry);
+        }
+
+        if !found {
+            return Ok(None);
+        }
+
+        let inner_path = inner_path.map(|v| v.to_owned());
+        Ok(Some(FrontIndexPage::new(dir_id.to_owned(), inner_path, entries)))
+    }
+
+    pub async fn load_object_map(
+        &self,
+        ctx: &FrontIndexContext,
+        object_map_id: &ObjectId,
+        inner_path: Option<&str>,
+    ) -> BuckyResult<FrontIndexPage> {
+        let noc_cache: ObjectMapNOCCacheRef = Arc::new(Box::new(FrontIndexObjectMapCache {
+            non: self.non.clone(),
+            ctx: ctx.to_owned(),
+        }));
+        let root_cache = ObjectMapRootMemoryCache::new_ref(None, noc_cache, 60, 64);
+        let op_env_cache = ObjectMapOpEnvMemoryCache::new_ref(root_cache);
+
+        let path = ObjectMapPath::new(object_map_id.clone(), op_env_cache, false);
+        let list = path.list("/").await.map_err(|e| {
+            error!("list object_map for index failed! object_map={}, {}", object_map_id, e);
+            e
+        })?;
+
+        let inner_path = inner_path.map(|v| v.to_owned());
+        Ok(self
+            .load_list(ctx, object_map_id.to_owned(), inner_path, list.list)
+            .await)
+    }
+
+    pub async fn load_list(
+        &self,
+        ctx: &FrontIndexContext,
+        object_id: ObjectId,
+        inner_path: Option<String>,
+        list: Vec<ObjectMapContentItem>,
+    ) -> FrontIndexPage {
+        let mut entries = Vec::with_capacity(list.len());
+        for item in list {
+            let (name, id) = match item {
+                ObjectMapContentItem::Map((key, id)) => (key, id),
+                ObjectMapContentItem::Set(id) => (id.to_string(), id),
+                _ => {
+                    debug!("diff item will be ignored in index: {}", item);
+                    continue;
+                }
+            };
+
+            entries.push(FrontIndexEntry {
+                name,
+                is_dir: Self::is_dir_type(&id),
+                size: self.object_size(ctx, &id, None).await,
+                object_id: Some(id),
+            });
+        }
+
+        FrontIndexPage::new(object_id, inner_path, entries)
+    }
+
+    fn is_dir_type(object_id: &ObjectId) -> bool {
+        match object_id.obj_type_code() {
+            ObjectTypeCode::Dir | ObjectTypeCode::ObjectMap => true,
+            _ => false,
+        }
+    }
+
+    // the size is optional for the index page, so the errors are ignored
+    async fn object_size(
+        &self,
+        ctx: &FrontIndexContext,
+        object_id: &ObjectId,
+        body: Option<&DirBodyContentObjectList>,
+    ) -> Option<u64> {
+        match object_id.obj_type_code() {
+            ObjectTypeCode::Chunk => {
+                return ChunkId::try_from(object_id).ok().map(|v| v.len() as u64);
+            }
+            ObjectTypeCode::File => {}
+            _ => return None,
+        }
+
+        if let Some(buf) = body.and_then(|body| body.get(object_id)) {
+            return File::clone_from_slice(buf).ok().map(|file| file.len());
+        }
+
+        match ctx.get_object(&self.non, object_id).await {
+            Ok(resp) => File::clone_from_slice(&resp.object.object_raw)
+                .ok()
+                .map(|file| file.len()),
+            Err(e) => {
+                debug!("load file for index failed! file={}, {}", object_id, e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_page(inner_path: Option<&str>) -> FrontIndexPage {
+        let object_id = ObjectIdDataBuilder::new().data("index").build().unwrap();
+        let chunk_id = ChunkId::calculate_sync("a".as_bytes()).unwrap();
+
+        let entries = vec![
+            FrontIndexEntry {
+                name: "b.txt".to_owned(),
+                is_dir: false,
+                object_id: Some(chunk_id.object_id()),
+                size: Some(1),
+            },
+            FrontIndexEntry {
+                name: "sub dir".to_owned(),
+                is_dir: true,
+                object_id: None,
+                size: None,
+            },
+            FrontIndexEntry {
+                name: "a<b>".to_owned(),
+                is_dir: false,
+                object_id: None,
+                size: None,
+            },
+        ];
+
+        FrontIndexPage::new(object_id, inner_path.map(|v| v.to_owned()), entries)
+    }
+
+    #[test]
+    fn test_sort_and_etag() {
+        let page = test_page(None);
+        let names: Vec<&str> = page.entries.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["sub dir", "a<b>", "b.txt"]);
+
+        let html = page.etag(FrontIndexFormat::Html);
+        let json = page.etag(FrontIndexFormat::Json);
+        assert_ne!(html, json);
+        assert_eq!(html, format!("\"{}.html\"", page.object_id));
+
+        // different inner path of the same dir must not share the etag
+        let sub = test_page(Some("/sub dir/")).etag(FrontIndexFormat::Html);
+        assert_ne!(sub, html);
+        assert_eq!(sub, test_page(Some("sub dir")).etag(FrontIndexFormat::Html));
+    }
+
+    #[test]
+    fn test_href() {
+        assert_eq!(FrontIndexPage::entry_href("/o/x/", "a b", None), "/o/x/a%20b");
+        assert_eq!(FrontIndexPage::entry_href("/o/x", "a", Some("dec_id=y")), "/o/x/a?dec_id=y");
+
+        // same parent with or without the trailing '/'
+        assert_eq!(FrontIndexPage::parent_href("/o/x/a", None), "/o/x/");
+        assert_eq!(FrontIndexPage::parent_href("/o/x/a/", None), "/o/x/");
+        assert_eq!(FrontIndexPage::parent_href("/a", Some("mode=index")), "/?mode=index");
+    }
+
+    #[test]
+    fn test_encode_html() {
+        let html = test_page(Some("sub")).encode_html("/o/x/sub", Some("mode=index"));
+        assert!(html.contains("<a href=\"/o/x/?mode=index\">../</a>"));
+        assert!(html.contains("href=\"/o/x/sub/sub%20dir?mode=index\">sub dir/</a>"));
+        assert!(html.contains(">a&lt;b&gt;</a>"));
+        assert!(!html.contains("<b>"));
+
+        // no parent link for the root
+        let html = test_page(None).encode_html("/o/x", None);
+        assert!(!html.contains("../"));
+    }
+
+    #[test]
+    fn test_encode_json() {
+        let page = test_page(Some("sub"));
+        let json: Value = serde_json::from_str(&page.encode_json("/o/x/sub/", None)).unwrap();
+
+        assert_eq!(json["object_id"], page.object_id.to_string());
+        assert_eq!(json["inner_path"], "sub");
+
+        let entries = json["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0]["name"], "sub dir");
+        assert_eq!(entries[0]["is_dir"], true);
+        assert_eq!(entries[0]["href"], "/o/x/sub/sub%20dir");
+        assert!(entries[0].get("object_id").is_none());
+
+        assert_eq!(entries[2]["name"], "b.txt");
+        assert_eq!(entries[2]["type"], "Chunk");
+        assert_eq!(entries[2]["size"], 1);
+        assert_eq!(
+            entries[2]["object_href"],
+            format!("/o/{}", page.entries[2].object_id.as_ref().unwrap())
+        );
+    }
+}
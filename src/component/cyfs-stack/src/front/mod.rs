@@ -1,4 +1,5 @@
 mod def;
+mod index;
 mod listener;
 mod protocol;
 mod request;
@@ -7,6 +8,7 @@ mod http_request;
 
 pub use def::*;
 pub use request::*;
+pub(crate) use index::*;
 pub(crate) use listener::*;
 pub(crate) use protocol::*;
 pub(crate) use service::*;
@@ -1,5 +1,6 @@
 use super::def::*;
 use super::http_request::FrontInputHttpRequest;
+use super::index::FrontIndexPage;
 use super::listener::FrontRequestType;
 use super::request::*;
 use super::service::*;
@@ -130,6 +131,61 @@ pub(crate) fn parse_front_host_with_anonymous_dec_id(
     Some((ft, cyfs_core::get_anonymous_dec_app().to_owned()))
 }
 
+// query params that only affect current response, will not be kept in the index page links
+const INDEX_LINK_IGNORE_QUERYS: &[&str] = &[
+    "mode",
+    "format",
+    "range",
+    "action",
+    "page_index",
+    "page_size",
+];
+
+// Request info used to encode the response
+struct FrontResponseContext {
+    format: FrontRequestObjectFormat,
+    index_format: FrontIndexFormat,
+
+    // used to generate the entry links of index page
+    base_path: String,
+    query: Option<String>,
+
+    if_none_match: Option<String>,
+}
+
+impl FrontResponseContext {
+    fn new(req: &http_types::Request, format: FrontRequestObjectFormat) -> Self {
+        let url = req.url();
+
+        let mut serializer = http_types::url::form_urlencoded::Serializer::new(String::new());
+        let mut count = 0;
+        for (k, v) in url.query_pairs() {
+            if INDEX_LINK_IGNORE_QUERYS.contains(&k.as_ref()) {
+                continue;
+            }
+            serializer.append_pair(&k, &v);
+            count += 1;
+        }
+        let query = if count > 0 {
+            Some(serializer.finish())
+        } else {
+            None
+        };
+
+        let if_none_match = req
+            .header(http_types::headers::IF_NONE_MATCH)
+            .map(|v| v.last().as_str().to_owned());
+
+        Self {
+            format,
+            index_format: FrontProtocolHandler::index_format_from_request(req, format),
+            base_path: url.path().to_owned(),
+            query,
+            if_none_match,
+        }
+    }
+}
+
 pub(crate) struct FrontProtocolHandler {
     name_resolver: NameResolver,
     zone_manager: ZoneManagerRef,
@@ -271,6 +327,27 @@ impl FrontProtocolHandler {
         }
     }
 
+    // format=json always use json index, otherwise browsers which accept html will get the html index
+    fn index_format_from_request(
+        req: &http_types::Request,
+        format: FrontRequestObjectFormat,
+    ) -> FrontIndexFormat {
+        if format == FrontRequestObjectFormat::Json {
+            return FrontIndexFormat::Json;
+        }
+
+        match req.header(http_types::headers::ACCEPT) {
+            Some(values) => {
+                if values.iter().any(|v| v.as_str().contains("text/html")) {
+                    FrontIndexFormat::Html
+                } else {
+                    FrontIndexFormat::Json
+                }
+            }
+            None => FrontIndexFormat::Json,
+        }
+    }
+
     fn range_from_request(req: &http_types::Request) -> BuckyResult<Option<NDNDataRequestRange>> {
         // first extract dec_id from headers
         let s: Option<String> = match RequestorHelper::decode_optional_header(req, "Range")? {
@@ -419,33 +496,34 @@ impl FrontProtocolHandler {
         req: FrontInputHttpRequest<State>,
     ) -> BuckyResult<tide::Response> {
         let format = Self::object_format_from_request(req.request.url())?;
+        let ctx = FrontResponseContext::new(req.request.as_ref(), format);
 
         match req_type {
             FrontRequestType::O => {
                 let route_param = Self::extract_route_param(&req.request)?;
-                let resp = self.process_o_request(req, route_param, format).await?;
+                let resp = self.process_o_request(req, route_param, &ctx).await?;
 
-                let http_resp = self.encode_o_response(resp, format).await;
-                Ok(http_resp)
+                let http_resp = self.encode_o_response(resp, &ctx).await;
+                Ok(Self::check_not_modified(http_resp, &ctx))
             }
             FrontRequestType::R | FrontRequestType::L => {
                 let route_param = Self::extract_route_param(&req.request)?;
-                let resp = self.process_r_request(req_type, req, route_param).await?;
+                let resp = self.process_r_request(req_type, req, route_param, &ctx).await?;
 
-                let http_resp = self.encode_r_response(resp, format).await;
-                Ok(http_resp)
+                let http_resp = self.encode_r_response(resp, &ctx).await;
+                Ok(Self::check_not_modified(http_resp, &ctx))
             }
             FrontRequestType::A => {
                 let route_param = Self::extract_route_param(&req.request)?;
                 let is_cyfs_browser = Self::is_cyfs_browser(&req.request.as_ref());
-                let resp = self.process_a_request(req, route_param, format).await?;
+                let resp = self.process_a_request(req, route_param, &ctx).await?;
 
-                let http_resp = self.encode_a_response(resp, format, is_cyfs_browser).await;
-                Ok(http_resp)
+                let http_resp = self.encode_a_response(resp, &ctx, is_cyfs_browser).await;
+                Ok(Self::check_not_modified(http_resp, &ctx))
             }
             FrontRequestType::Any => {
                 let route_param = Self::extract_option_route_param(&req.request)?;
-                self.process_any_request(req, route_param, format).await
+                self.process_any_request(req, route_param, &ctx).await
             }
         }
     }
@@ -454,7 +532,7 @@ impl FrontProtocolHandler {
         &self,
         mut req: FrontInputHttpRequest<State>,
         route_param: Option<String>,
-        format: FrontRequestObjectFormat,
+        ctx: &FrontResponseContext,
     ) -> BuckyResult<tide::Response> {
         let name = req.request.param("name").map_err(|e| {
             let msg = format!(
@@ -513,36 +591,35 @@ impl FrontProtocolHandler {
             };
         }
 
-        match req_type {
+        let http_resp = match req_type {
             FrontRequestType::O => {
-                let resp = self.process_o_request(req, req_route_param, format).await?;
-                let http_resp = self.encode_o_response(resp, format).await;
-                Ok(http_resp)
+                let resp = self.process_o_request(req, req_route_param, ctx).await?;
+                self.encode_o_response(resp, ctx).await
             }
             FrontRequestType::A => {
                 let is_cyfs_browser = Self::is_cyfs_browser(&req.request.as_ref());
-                let resp = self.process_a_request(req, req_route_param, format).await?;
-                let http_resp = self.encode_a_response(resp, format, is_cyfs_browser).await;
-                Ok(http_resp)
+                let resp = self.process_a_request(req, req_route_param, ctx).await?;
+                self.encode_a_response(resp, ctx, is_cyfs_browser).await
             }
             FrontRequestType::R | FrontRequestType::L => {
                 let resp = self
-                    .process_r_request(req_type, req, req_route_param)
+                    .process_r_request(req_type, req, req_route_param, ctx)
                     .await?;
-                let http_resp = self.encode_r_response(resp, format).await;
-                Ok(http_resp)
+                self.encode_r_response(resp, ctx).await
             }
             FrontRequestType::Any => {
                 unreachable!()
             }
-        }
+        };
+
+        Ok(Self::check_not_modified(http_resp, ctx))
     }
 
     async fn process_o_request<State>(
         &self,
         req: FrontInputHttpRequest<State>,
         route_param: String,
-        format: FrontRequestObjectFormat,
+        ctx: &FrontResponseContext,
     ) -> BuckyResult<FrontOResponse> {
         let segs = Self::parse_url_segs(&route_param)?;
         let url = req.request.url();
@@ -603,7 +680,8 @@ impl FrontProtocolHandler {
                     range,
 
                     mode,
-                    format,
+                    format: ctx.format,
+                    index_format: ctx.index_format,
 
                     referer_objects,
                     context,
@@ -638,7 +716,8 @@ impl FrontProtocolHandler {
                     range,
 
                     mode,
-                    format,
+                    format: ctx.format,
+                    index_format: ctx.index_format,
 
                     referer_objects,
                     context,
@@ -700,6 +779,7 @@ impl FrontProtocolHandler {
         req_type: FrontRequestType,
         req: FrontInputHttpRequest<State>,
         route_param: String,
+        ctx: &FrontResponseContext,
     ) -> BuckyResult<FrontRResponse> {
         /*
         [/target]/{dec_id}/{inner_path}
@@ -897,6 +977,7 @@ impl FrontProtocolHandler {
             page_size,
 
             mode,
+            index_format: ctx.index_format,
             context,
             group,
 
@@ -916,7 +997,7 @@ impl FrontProtocolHandler {
         &self,
         req: FrontInputHttpRequest<State>,
         route_param: String,
-        format: FrontRequestObjectFormat,
+        ctx: &FrontResponseContext,
     ) -> BuckyResult<FrontAResponse> {
         let segs = Self::parse_url_segs(&route_param)?;
         let url = req.request.url();
@@ -998,7 +1079,8 @@ impl FrontProtocolHandler {
             goal,
 
             mode,
-            format,
+            format: ctx.format,
+            index_format: ctx.index_format,
 
             origin_url: url.to_owned(),
 
@@ -1017,10 +1099,15 @@ impl FrontProtocolHandler {
     async fn encode_o_response(
         &self,
         resp: FrontOResponse,
-        format: FrontRequestObjectFormat,
+        ctx: &FrontResponseContext,
     ) -> tide::Response {
+        if let Some(index) = resp.index {
+            return Self::encode_index_response(&index, ctx);
+        }
+
         match resp.data {
             Some(data_resp) => {
+                let etag = Self::data_etag(&data_resp.object_id);
                 let mut http_resp = NDNRequestHandler::encode_get_data_response(data_resp);
 
                 if let Some(object_resp) = resp.object {
@@ -1030,11 +1117,18 @@ impl FrontProtocolHandler {
                     );
                 }
 
+                Self::set_etag(&mut http_resp, etag);
                 http_resp
             }
             None => {
                 let object_resp = resp.object.unwrap();
-                NONRequestHandler::encode_get_object_response(object_resp, format)
+                let object_id = object_resp.object.object_id.clone();
+                let etag = Self::object_etag(&object_id, ctx.format);
+                let mut http_resp = NONRequestHandler::encode_get_object_response(object_resp, ctx.format);
+
+                Self::set_etag(&mut http_resp, etag);
+                Self::set_index_vary(&mut http_resp, &object_id);
+                http_resp
             }
         }
     }
@@ -1042,9 +1136,12 @@ impl FrontProtocolHandler {
     async fn encode_r_response(
         &self,
         resp: FrontRResponse,
-        format: FrontRequestObjectFormat,
+        ctx: &FrontResponseContext,
     ) -> tide::Response {
-        let mut http_resp = if let Some(data_resp) = resp.data {
+        let mut http_resp = if let Some(index) = resp.index {
+            Self::encode_index_response(&index, ctx)
+        } else if let Some(data_resp) = resp.data {
+            let etag = Self::data_etag(&data_resp.object_id);
             let mut http_resp = NDNRequestHandler::encode_get_data_response(data_resp);
 
             if let Some(object_resp) = resp.object {
@@ -1054,9 +1151,16 @@ impl FrontProtocolHandler {
                 );
             }
 
+            Self::set_etag(&mut http_resp, etag);
             http_resp
         } else if let Some(object_resp) = resp.object {
-            NONRequestHandler::encode_get_object_response(object_resp, format)
+            let object_id = object_resp.object.object_id.clone();
+            let etag = Self::object_etag(&object_id, ctx.format);
+            let mut http_resp = NONRequestHandler::encode_get_object_response(object_resp, ctx.format);
+
+            Self::set_etag(&mut http_resp, etag);
+            Self::set_index_vary(&mut http_resp, &object_id);
+            http_resp
         } else if let Some(list_resp) = resp.list {
             let mut http_resp = RequestorHelper::new_response(http_types::StatusCode::Ok);
             http_resp.set_body(list_resp.encode_string());
@@ -1075,11 +1179,11 @@ impl FrontProtocolHandler {
     async fn encode_a_response(
         &self,
         resp: FrontAResponse,
-        format: FrontRequestObjectFormat,
+        ctx: &FrontResponseContext,
         is_cyfs_browser: bool,
     ) -> tide::Response {
         match resp {
-            FrontAResponse::Response(o_resp) => self.encode_o_response(o_resp, format).await,
+            FrontAResponse::Response(o_resp) => self.encode_o_response(o_resp, ctx).await,
             FrontAResponse::Redirect(mut url) => {
                 if is_cyfs_browser {
                     url = format!("cyfs:/{}", url);
@@ -1088,6 +1192,86 @@ impl FrontProtocolHandler {
             }
         }
     }
+
+    // the raw dir and object_map share the url with the index page which browsers get by default
+    fn set_index_vary(http_resp: &mut tide::Response, object_id: &ObjectId) {
+        match object_id.obj_type_code() {
+            ObjectTypeCode::Dir | ObjectTypeCode::ObjectMap => {
+                http_resp.insert_header(http_types::headers::VARY, "Accept");
+            }
+            _ => {}
+        }
+    }
+
+    fn encode_index_response(index: &FrontIndexPage, ctx: &FrontResponseContext) -> tide::Response {
+        let body = index.encode(ctx.index_format, &ctx.base_path, ctx.query.as_deref());
+
+        let mut http_resp = RequestorHelper::new_response(http_types::StatusCode::Ok);
+        http_resp.set_body(body);
+        match ctx.index_format {
+            FrontIndexFormat::Html => http_resp.set_content_type(tide::http::mime::HTML),
+            FrontIndexFormat::Json => http_resp.set_content_type(tide::http::mime::JSON),
+        }
+
+        // index format is negotiated by the Accept header
+        http_resp.insert_header(http_types::headers::VARY, "Accept");
+
+        let mut http_resp: tide::Response = http_resp.into();
+        Self::set_etag(&mut http_resp, index.etag(ctx.index_format));
+
+        http_resp
+    }
+
+    // file and chunk are immutable, so the object_id is a strong etag
+    fn data_etag(object_id: &ObjectId) -> String {
+        format!("\"{}\"", object_id)
+    }
+
+    fn object_etag(object_id: &ObjectId, format: FrontRequestObjectFormat) -> String {
+        match format {
+            FrontRequestObjectFormat::Json => format!("\"{}.json\"", object_id),
+            _ => format!("\"{}\"", object_id),
+        }
+    }
+
+    fn set_etag(http_resp: &mut tide::Response, etag: String) {
+        if http_resp.status().is_success() {
+            http_resp.insert_header(http_types::headers::ETAG, etag);
+        }
+    }
+
+    fn is_etag_matched(if_none_match: &str, etag: &str) -> bool {
+        if_none_match.split(',').any(|v| {
+            let v = v.trim();
+            v == "*" || v.trim_start_matches("W/") == etag.trim_start_matches("W/")
+        })
+    }
+
+    fn check_not_modified(http_resp: tide::Response, ctx: &FrontResponseContext) -> tide::Response {
+        let if_none_match = match &ctx.if_none_match {
+            Some(v) => v,
+            None => return http_resp,
+        };
+
+        let etag = match http_resp.header(http_types::headers::ETAG) {
+            Some(v) => v.last().as_str().to_owned(),
+            None => return http_resp,
+        };
+
+        if !Self::is_etag_matched(if_none_match, &etag) {
+            return http_resp;
+        }
+
+        debug!("front request etag matched, not modified! etag={}", etag);
+
+        let mut resp = RequestorHelper::new_response(http_types::StatusCode::NotModified);
+        resp.insert_header(http_types::headers::ETAG, etag);
+        if let Some(vary) = http_resp.header(http_types::headers::VARY) {
+            resp.insert_header(http_types::headers::VARY, vary.last().as_str());
+        }
+
+        resp.into()
+    }
 }
 
 #[cfg(test)]
@@ -1104,4 +1288,77 @@ mod tests {
         let value: String = RequestorHelper::value_from_querys_with_utf8_decoding("req_path", &url).unwrap().unwrap();
         assert_eq!(value, "/a/b?token=xxx&id=xxx");
     }
+
+    fn new_request(url: &str, accept: Option<&str>) -> http_types::Request {
+        let url = http_types::Url::parse(url).unwrap();
+        let mut req = http_types::Request::new(http_types::Method::Get, url);
+        if let Some(accept) = accept {
+            req.insert_header(http_types::headers::ACCEPT, accept);
+        }
+        req
+    }
+
+    #[test]
+    fn test_index_format() {
+        let req = new_request("http://127.0.0.1/o/x?mode=index", Some("text/html,*/*"));
+        assert_eq!(
+            FrontProtocolHandler::index_format_from_request(&req, FrontRequestObjectFormat::Default),
+            FrontIndexFormat::Html
+        );
+        assert_eq!(
+            FrontProtocolHandler::index_format_from_request(&req, FrontRequestObjectFormat::Json),
+            FrontIndexFormat::Json
+        );
+
+        let req = new_request("http://127.0.0.1/o/x?mode=index", None);
+        assert_eq!(
+            FrontProtocolHandler::index_format_from_request(&req, FrontRequestObjectFormat::Default),
+            FrontIndexFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_response_context() {
+        let req = new_request(
+            "http://127.0.0.1/o/x/a?mode=index&format=json&dec_id=y&range=0-1",
+            None,
+        );
+        let ctx = FrontResponseContext::new(&req, FrontRequestObjectFormat::Json);
+        assert_eq!(ctx.base_path, "/o/x/a");
+        assert_eq!(ctx.query.as_deref(), Some("dec_id=y"));
+        assert_eq!(ctx.index_format, FrontIndexFormat::Json);
+        assert!(ctx.if_none_match.is_none());
+
+        let mut req = new_request("http://127.0.0.1/o/x?mode=index", None);
+        req.insert_header(http_types::headers::IF_NONE_MATCH, "\"x.json\"");
+        let ctx = FrontResponseContext::new(&req, FrontRequestObjectFormat::Json);
+        assert!(ctx.query.is_none());
+
+        let mut http_resp: tide::Response =
+            RequestorHelper::new_response(http_types::StatusCode::Ok).into();
+        FrontProtocolHandler::set_etag(&mut http_resp, "\"x.json\"".to_owned());
+        let http_resp = FrontProtocolHandler::check_not_modified(http_resp, &ctx);
+        assert_eq!(http_resp.status(), http_types::StatusCode::NotModified);
+
+        let mut http_resp: tide::Response =
+            RequestorHelper::new_response(http_types::StatusCode::Ok).into();
+        FrontProtocolHandler::set_etag(&mut http_resp, "\"x\"".to_owned());
+        let http_resp = FrontProtocolHandler::check_not_modified(http_resp, &ctx);
+        assert_eq!(http_resp.status(), http_types::StatusCode::Ok);
+    }
+
+    #[test]
+    fn test_etag_matched() {
+        let etag = "\"95RvaS5anntyAoRUBi48vQoivWzX95M8xm4rkB93DdSt.json\"";
+        assert!(FrontProtocolHandler::is_etag_matched(etag, etag));
+        assert!(FrontProtocolHandler::is_etag_matched("*", etag));
+        assert!(FrontProtocolHandler::is_etag_matched(
+            "\"xxx\", W/\"95RvaS5anntyAoRUBi48vQoivWzX95M8xm4rkB93DdSt.json\"",
+            etag
+        ));
+        assert!(!FrontProtocolHandler::is_etag_matched(
+            "\"95RvaS5anntyAoRUBi48vQoivWzX95M8xm4rkB93DdSt\"",
+            etag
+        ));
+    }
 }
\ No newline at end of file
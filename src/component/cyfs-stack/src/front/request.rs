@@ -1,4 +1,5 @@
 use super::def::*;
+use super::index::FrontIndexPage;
 use cyfs_base::*;
 use cyfs_lib::*;
 
@@ -20,6 +21,7 @@ pub struct FrontORequest {
 
     pub mode: FrontRequestGetMode,
     pub format: FrontRequestObjectFormat,
+    pub index_format: FrontIndexFormat,
 
     pub flags: u32,
 }
@@ -27,6 +29,9 @@ pub struct FrontORequest {
 pub struct FrontOResponse {
     pub object: Option<NONGetObjectInputResponse>,
     pub data: Option<NDNGetDataInputResponse>,
+
+    // for dir and object_map in index mode
+    pub index: Option<FrontIndexPage>,
 }
 
 #[derive(Clone, Debug)]
@@ -51,6 +56,7 @@ pub struct FrontRRequest {
     pub group: Option<String>,
 
     pub mode: FrontRequestGetMode,
+    pub index_format: FrontIndexFormat,
     pub flags: u32,
 }
 
@@ -63,6 +69,9 @@ pub struct FrontRResponse {
 
     // for list action
     pub list: Option<Vec<ObjectMapContentItem>>,
+
+    // for dir and object_map in index mode
+    pub index: Option<FrontIndexPage>,
 }
 
 pub struct FrontNDNRequest {
@@ -207,6 +216,7 @@ pub struct FrontARequest {
 
     pub mode: FrontRequestGetMode,
     pub format: FrontRequestObjectFormat,
+    pub index_format: FrontIndexFormat,
 
     pub origin_url: http_types::Url,
 
@@ -1,4 +1,5 @@
 use super::def::*;
+use super::index::*;
 use super::request::*;
 use crate::app::AppInstallStatus;
 use crate::app::AppService;
@@ -25,6 +26,8 @@ pub(crate) struct FrontService {
     app: AppService,
 
     ood_resolver: OodResolver,
+
+    index_loader: FrontIndexLoader,
 }
 
 impl FrontService {
@@ -36,6 +39,8 @@ impl FrontService {
         app: AppService,
         ood_resolver: OodResolver,
     ) -> Self {
+        let index_loader = FrontIndexLoader::new(non.clone(), ndn.clone());
+
         Self {
             non,
             ndn,
@@ -43,6 +48,7 @@ impl FrontService {
             local_cache,
             app,
             ood_resolver,
+            index_loader,
         }
     }
 
@@ -52,7 +58,7 @@ impl FrontService {
         let resp = match req.object_id.obj_type_code() {
             ObjectTypeCode::Chunk => {
                 // verify the mode
-                let mode = Self::select_mode(&req.mode, &req.object_id)?;
+                let mode = Self::select_mode(&req.mode, &req.object_id, false)?;
                 assert_eq!(mode, FrontRequestGetMode::Data);
 
                 let ndn_req = FrontNDNRequest::new_o_chunk(req);
@@ -61,18 +67,39 @@ impl FrontService {
                 FrontOResponse {
                     object: None,
                     data: Some(resp),
+                    index: None,
                 }
             }
             _ => {
-                let non_resp = self.process_get_object(req.clone()).await?;
+                let non_resp = match self.process_get_object(req.clone()).await {
+                    Ok(resp) => resp,
+                    Err(e) => {
+                        // sub dir inside a dir object has no object, try list it as index
+                        if e.code() == BuckyErrorCode::InnerPathNotFound
+                            && req.object_id.obj_type_code() == ObjectTypeCode::Dir
+                            && Self::o_prefer_index(&req)
+                        {
+                            if let Some(resp) = self.process_dir_sub_index(&req).await? {
+                                return Ok(resp);
+                            }
+                        }
+
+                        return Err(e);
+                    }
+                };
 
                 // decide the mode
-                let mode = Self::select_mode(&req.mode, &non_resp.object.object_id)?;
+                let mode = Self::select_mode(
+                    &req.mode,
+                    &non_resp.object.object_id,
+                    Self::o_prefer_index(&req),
+                )?;
 
                 match mode {
                     FrontRequestGetMode::Object => FrontOResponse {
                         object: Some(non_resp),
                         data: None,
+                        index: None,
                     },
                     FrontRequestGetMode::Data => {
                         let ndn_req = FrontNDNRequest::new_o_file(req, non_resp.object.clone());
@@ -81,6 +108,19 @@ impl FrontService {
                         FrontOResponse {
                             object: Some(non_resp),
                             data: Some(ndn_resp),
+                            index: None,
+                        }
+                    }
+                    FrontRequestGetMode::Index => {
+                        let ctx = self.o_index_context(&req).await;
+                        let index = self
+                            .load_object_index(&ctx, &non_resp.object, req.inner_path.clone())
+                            .await?;
+
+                        FrontOResponse {
+                            object: Some(non_resp),
+                            data: None,
+                            index: Some(index),
                         }
                     }
                     _ => unreachable!(),
@@ -91,11 +131,62 @@ impl FrontService {
         Ok(resp)
     }
 
-    async fn process_get_object(
+    async fn process_dir_sub_index(&self, req: &FrontORequest) -> BuckyResult<Option<FrontOResponse>> {
+        let mut dir_req = req.clone();
+        dir_req.inner_path = None;
+
+        let non_resp = self.process_get_object(dir_req).await?;
+        let dir = non_resp.object.object.as_ref().unwrap().as_dir();
+        let ctx = self.o_index_context(req).await;
+        let ret = self
+            .index_loader
+            .load_dir(&ctx, &req.object_id, dir, req.inner_path.as_deref())
+            .await?;
+
+        Ok(ret.map(|index| FrontOResponse {
+            object: None,
+            data: None,
+            index: Some(index),
+        }))
+    }
+
+    // the index is loaded with the same source and target as the object itself
+    async fn o_index_context(&self, req: &FrontORequest) -> FrontIndexContext {
+        FrontIndexContext {
+            source: req.source.clone(),
+            target: self.select_o_target(req).await,
+            flags: req.flags,
+        }
+    }
+
+    async fn load_object_index(
         &self,
-        req: FrontORequest,
-    ) -> BuckyResult<NONGetObjectInputResponse> {
-        let target = if req.target.len() > 0 {
+        ctx: &FrontIndexContext,
+        object: &NONObjectInfo,
+        inner_path: Option<String>,
+    ) -> BuckyResult<FrontIndexPage> {
+        match object.object_id.obj_type_code() {
+            ObjectTypeCode::Dir => {
+                let dir = object.object.as_ref().unwrap().as_dir();
+                let mut index = self
+                    .index_loader
+                    .load_dir(ctx, &object.object_id, dir, None)
+                    .await?
+                    .unwrap();
+                index.inner_path = inner_path;
+                Ok(index)
+            }
+            ObjectTypeCode::ObjectMap => {
+                self.index_loader
+                    .load_object_map(ctx, &object.object_id, inner_path.as_deref())
+                    .await
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    async fn select_o_target(&self, req: &FrontORequest) -> Option<ObjectId> {
+        if req.target.len() > 0 {
             Some(req.target[0])
         } else {
             if let Ok(list) = self.resolve_target_from_object_id(&req.object_id).await {
@@ -107,7 +198,14 @@ impl FrontService {
             } else {
                 None
             }
-        };
+        }
+    }
+
+    async fn process_get_object(
+        &self,
+        req: FrontORequest,
+    ) -> BuckyResult<NONGetObjectInputResponse> {
+        let target = self.select_o_target(&req).await;
 
         let common = NONInputRequestCommon {
             req_path: req.req_path,
@@ -290,9 +388,22 @@ impl FrontService {
         }
     }
 
+    // browsers which accept html and ask no explicit format get the index page by default
+    fn o_prefer_index(req: &FrontORequest) -> bool {
+        match req.mode {
+            FrontRequestGetMode::Index => true,
+            FrontRequestGetMode::Default => {
+                req.format == FrontRequestObjectFormat::Default
+                    && req.index_format == FrontIndexFormat::Html
+            }
+            _ => false,
+        }
+    }
+
     fn select_mode(
         mode: &FrontRequestGetMode,
         object_id: &ObjectId,
+        prefer_index: bool,
     ) -> BuckyResult<FrontRequestGetMode> {
         let mode = match mode {
             FrontRequestGetMode::Object => {
@@ -317,9 +428,25 @@ impl FrontService {
 
                 FrontRequestGetMode::Data
             }
+            FrontRequestGetMode::Index => {
+                if !Self::is_index_mode_valid(object_id) {
+                    let msg = format!(
+                        "object not support index mode! object={}, type={:?}",
+                        object_id,
+                        object_id.obj_type_code(),
+                    );
+                    error!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::NotSupport, msg));
+                }
+
+                FrontRequestGetMode::Index
+            }
             FrontRequestGetMode::Default => {
+                // dir and object_map are listed as index page for browsers, mode=object for the raw object
                 if Self::is_data_mode_valid(object_id) {
                     FrontRequestGetMode::Data
+                } else if prefer_index && Self::is_index_mode_valid(object_id) {
+                    FrontRequestGetMode::Index
                 } else {
                     FrontRequestGetMode::Object
                 }
@@ -336,6 +463,13 @@ impl FrontService {
         }
     }
 
+    fn is_index_mode_valid(object_id: &ObjectId) -> bool {
+        match object_id.obj_type_code() {
+            ObjectTypeCode::Dir | ObjectTypeCode::ObjectMap => true,
+            _ => false,
+        }
+    }
+
     pub async fn process_r_request(&self, req: FrontRRequest) -> BuckyResult<FrontRResponse> {
         info!("will process r request: {:?}", req);
 
@@ -346,8 +480,11 @@ impl FrontService {
                 match state_resp.object.object.object_id.obj_type_code() {
                     ObjectTypeCode::Chunk => {
                         // verify the mode
-                        let mode =
-                            Self::select_mode(&req.mode, &state_resp.object.object.object_id)?;
+                        let mode = Self::select_mode(
+                            &req.mode,
+                            &state_resp.object.object.object_id,
+                            false,
+                        )?;
                         assert_eq!(mode, FrontRequestGetMode::Data);
 
                         let ndn_req =
@@ -360,12 +497,16 @@ impl FrontService {
                             revision: state_resp.revision,
                             data: Some(resp),
                             list: None,
+                            index: None,
                         }
                     }
                     _ => {
                         // decide the mode
-                        let mode =
-                            Self::select_mode(&req.mode, &state_resp.object.object.object_id)?;
+                        let mode = Self::select_mode(
+                            &req.mode,
+                            &state_resp.object.object.object_id,
+                            req.index_format == FrontIndexFormat::Html,
+                        )?;
 
                         match mode {
                             FrontRequestGetMode::Object => FrontRResponse {
@@ -374,6 +515,7 @@ impl FrontService {
                                 revision: state_resp.revision,
                                 data: None,
                                 list: None,
+                                index: None,
                             },
                             FrontRequestGetMode::Data => {
                                 let ndn_req = FrontNDNRequest::new_r_resp(
@@ -388,6 +530,19 @@ impl FrontService {
                                     revision: state_resp.revision,
                                     data: Some(ndn_resp),
                                     list: None,
+                                    index: None,
+                                }
+                            }
+                            FrontRequestGetMode::Index => {
+                                let index = self.load_state_index(req, &state_resp).await?;
+
+                                FrontRResponse {
+                                    object: Some(state_resp.object),
+                                    root: state_resp.root,
+                                    revision: state_resp.revision,
+                                    data: None,
+                                    list: None,
+                                    index: Some(index),
                                 }
                             }
                             _ => unreachable!(),
@@ -401,12 +556,44 @@ impl FrontService {
                 revision: state_resp.revision,
                 data: None,
                 list: Some(state_resp.list),
+                index: None,
             },
         };
 
         Ok(resp)
     }
 
+    async fn load_state_index(
+        &self,
+        req: FrontRRequest,
+        state_resp: &RootStateAccessorGetObjectByPathInputResponse,
+    ) -> BuckyResult<FrontIndexPage> {
+        let object = &state_resp.object.object;
+        let ctx = FrontIndexContext {
+            source: req.source.clone(),
+            target: req.target.clone(),
+            flags: req.flags,
+        };
+
+        match object.object_id.obj_type_code() {
+            ObjectTypeCode::ObjectMap => {
+                // list with the accessor, so the access and paging are same as the list action
+                let inner_path = req.inner_path.clone();
+                let mut list_req = req;
+                list_req.action = GlobalStateAccessorAction::List;
+
+                match self.process_global_state_request(list_req).await? {
+                    GlobalStateResponse::List(list_resp) => Ok(self
+                        .index_loader
+                        .load_list(&ctx, object.object_id.clone(), inner_path, list_resp.list)
+                        .await),
+                    GlobalStateResponse::Object(_) => unreachable!(),
+                }
+            }
+            _ => self.load_object_index(&ctx, object, req.inner_path).await,
+        }
+    }
+
     async fn process_global_state_request(
         &self,
         req: FrontRRequest,
@@ -476,6 +663,7 @@ impl FrontService {
 
                             mode: req.mode,
                             format: req.format,
+                            index_format: req.index_format,
 
                             referer_objects: req.referer_objects,
                             context: req.context,
@@ -518,6 +706,7 @@ impl FrontService {
 
                             mode: req.mode,
                             format: req.format,
+                            index_format: req.index_format,
 
                             referer_objects: req.referer_objects,
                             context: req.context,
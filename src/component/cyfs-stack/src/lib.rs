@@ -26,6 +26,10 @@ mod events;
 mod root_state;
 mod root_state_api;
 mod config;
+mod erasure;
+mod scrub;
+mod evict;
+mod sealed;
 mod front;
 mod rmeta_api;
 mod rmeta;
@@ -11,5 +11,6 @@ mod service;
 
 pub(crate) use bdt::*;
 pub(crate) use common::*;
+pub(crate) use data::*;
 pub(crate) use forward::*;
 pub(crate) use service::*;
\ No newline at end of file
@@ -3,15 +3,18 @@ use super::super::ndn::*;
 use super::super::router::*;
 use crate::NamedDataComponents;
 use crate::acl::AclManagerRef;
+use crate::erasure::ErasureChunkManager;
 use crate::forward::ForwardProcessorManager;
 use crate::meta::ObjectFailHandler;
 use crate::ndn::*;
 use crate::non::*;
 use crate::router_handler::RouterHandlersManager;
+use crate::sealed::ObjectUnsealerRef;
 use crate::zone::ZoneManagerRef;
 use cyfs_base::*;
 use cyfs_lib::*;
 
+use once_cell::sync::OnceCell;
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -19,6 +22,13 @@ pub struct NDNService {
     ndc: NDNInputProcessorRef,
     ndn: NDNInputProcessorRef,
     router: NDNInputProcessorRef,
+
+    // reconstruct the erasure coded chunks on read
+    erasure: Arc<OnceCell<ErasureChunkManager>>,
+
+    // 以SealedObject为目标的get_data，需要通过non router获取加密对象
+    unsealer: ObjectUnsealerRef,
+    non_router: NONInputProcessorRef,
 }
 
 impl NDNService {
@@ -34,6 +44,7 @@ impl NDNService {
 
         forward: ForwardProcessorManager,
         fail_handler: ObjectFailHandler,
+        unsealer: ObjectUnsealerRef,
     ) -> Self {
         let named_data_components = Arc::new(named_data_components.clone());
 
@@ -55,7 +66,7 @@ impl NDNService {
         let router = NDNRouter::new_acl(
             acl,
             &named_data_components,
-            non_router,
+            non_router.clone(),
             zone_manager,
             router_handlers,
             forward,
@@ -66,6 +77,15 @@ impl NDNService {
             ndc: ndc_processor,
             ndn: ndn_processor,
             router,
+            erasure: Arc::new(OnceCell::new()),
+            unsealer,
+            non_router,
+        }
+    }
+
+    pub(crate) fn bind_erasure(&self, erasure: ErasureChunkManager) {
+        if let Err(_) = self.erasure.set(erasure) {
+            unreachable!();
         }
     }
 
@@ -107,8 +127,15 @@ impl NDNService {
                 return Err(BuckyError::new(BuckyErrorCode::NotSupport, msg));
             }
         }
+
+        if let Some(erasure) = self.erasure.get() {
+            erasure.prepare_data(&req).await;
+        }
+
         let processor = self.get_api(&req.common.level);
-        processor.get_data(req).await
+        self.unsealer
+            .get_data(&self.non_router, processor, req)
+            .await
     }
 
     async fn delete_data(
@@ -53,7 +53,17 @@ where
         name: &str,
         param: &mut RouterHandlerRequest<REQ, RESP>,
     ) -> BuckyResult<Option<BuckyResult<RESP>>> {
-        let default_action = RouterHandlerAction::Default;
+        self.call_with_default(name, param, RouterHandlerAction::Default)
+            .await
+    }
+
+    // 没有handler匹配或者所有handler都pass时，使用default_action作为结果
+    pub async fn call_with_default(
+        &mut self,
+        name: &str,
+        param: &mut RouterHandlerRequest<REQ, RESP>,
+        default_action: RouterHandlerAction,
+    ) -> BuckyResult<Option<BuckyResult<RESP>>> {
 
         let req_path = if let Some(req_path) = param.request.req_path() {
             let mut req_path = RequestGlobalStatePath::from_str(&req_path)?;
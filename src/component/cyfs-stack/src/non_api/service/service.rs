@@ -6,6 +6,7 @@ use crate::forward::ForwardProcessorManager;
 use crate::meta::{MetaCacheRef, ObjectFailHandler};
 use crate::ndn_api::*;
 use crate::router_handler::RouterHandlersManager;
+use crate::sealed::ObjectUnsealerRef;
 use crate::zone::ZoneManagerRef;
 use crate::NamedDataComponents;
 use crate::{acl::*, non::*};
@@ -21,6 +22,9 @@ pub struct NONService {
     rmeta_noc_processor: NONInputProcessorRef,
     non: NONInputProcessorRef,
     router: NONInputProcessorRef,
+
+    // 为zone内的请求透明解密SealedObject
+    unsealer: ObjectUnsealerRef,
 }
 
 impl NONService {
@@ -34,6 +38,7 @@ impl NONService {
         router_handlers: RouterHandlersManager,
         meta_cache: MetaCacheRef,
         fail_handler: ObjectFailHandler,
+        unsealer: ObjectUnsealerRef,
     ) -> (NONService, NDNService) {
         // raw service with inner_path service support
         let raw_noc_processor = NOCLevelInputProcessor::new_with_inner_path_service(
@@ -88,6 +93,7 @@ impl NONService {
             rmeta_noc_processor,
             non: non_processor.clone(),
             router: router.clone(),
+            unsealer: unsealer.clone(),
         };
 
         // 同时初始化ndn
@@ -99,6 +105,7 @@ impl NONService {
             router,
             forward_manager,
             fail_handler,
+            unsealer,
         );
 
         (non_service, ndn_service)
@@ -141,7 +148,12 @@ impl NONService {
         req: NONGetObjectInputRequest,
     ) -> BuckyResult<NONGetObjectInputResponse> {
         let processor = self.get_api(&req.common.level);
-        processor.get_object(req).await
+
+        let source = req.common.source.clone();
+        let flags = req.common.flags;
+        let resp = processor.get_object(req).await?;
+
+        self.unsealer.unseal_object(&source, flags, resp)
     }
 
     async fn post_object(
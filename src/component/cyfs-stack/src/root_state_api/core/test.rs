@@ -199,6 +199,7 @@ async fn create_global_state_manager() -> GlobalStateManager {
         udp_sn_only: None,
         sn_mode: SNMode::Normal,
         ping_interval: None,
+        chunk_compress: None,
     };
     let config = StackGlobalConfig::new(params, bdt_params);
 
@@ -0,0 +1,778 @@
+use super::quarantine::ScrubQuarantine;
+use super::throttle::ScrubThrottle;
+use crate::crypto_api::{ObjectInfo, ObjectVerifier, VerifyObjectInnerRequest};
+use crate::forward::ForwardProcessorManager;
+use crate::meta::MetaCache;
+use crate::ndn_api::LocalDataManager;
+use crate::stack::CyfsStackScrubParams;
+use crate::zone::ZoneManagerRef;
+use cyfs_base::*;
+use cyfs_bdt_ext::NamedDataComponentsRef;
+use cyfs_chunk_cache::{ChunkType, MemChunk};
+use cyfs_core::ZoneObj;
+use cyfs_lib::*;
+
+use futures::AsyncReadExt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/*
+后台完整性校验，防止消费级磁盘上的静默数据损坏
+每一轮按限速遍历noc里面的对象和chunk cache里面的chunk:
+1. 对象: 校验blob内容和object_id是否匹配，以及owner的desc签名
+2. chunk: 重新计算hash并和chunk_id比较
+损坏的数据先移入隔离目录，然后尝试从zone内其它设备或者meta链重新获取
+结果通过util.get_scrub_status查询
+*/
+
+const SCRUB_PAGE_SIZE: usize = 256;
+
+// 只保留最近的问题记录
+const SCRUB_MAX_ISSUES: usize = 256;
+
+struct ScrubManagerInner {
+    params: CyfsStackScrubParams,
+    device_id: DeviceId,
+
+    noc: NamedObjectCacheRef,
+    named_data_components: NamedDataComponentsRef,
+    local: LocalDataManager,
+    verifier: Arc<ObjectVerifier>,
+    meta_cache: Box<dyn MetaCache>,
+
+    zone_manager: ZoneManagerRef,
+    forward: ForwardProcessorManager,
+
+    quarantine: ScrubQuarantine,
+    status: Mutex<ScrubStatus>,
+}
+
+#[derive(Clone)]
+pub(crate) struct ScrubManager(Arc<ScrubManagerInner>);
+
+impl ScrubManager {
+    pub fn new(
+        params: CyfsStackScrubParams,
+        isolate: &str,
+        noc: NamedObjectCacheRef,
+        named_data_components: NamedDataComponentsRef,
+        verifier: Arc<ObjectVerifier>,
+        meta_cache: Box<dyn MetaCache>,
+        zone_manager: ZoneManagerRef,
+        forward: ForwardProcessorManager,
+    ) -> Self {
+        let device_id = zone_manager.get_current_device_id().to_owned();
+        let local = LocalDataManager::new(named_data_components.clone());
+
+        let mut status = ScrubStatus::default();
+        status.enable = true;
+
+        let inner = ScrubManagerInner {
+            params,
+            device_id,
+            noc,
+            named_data_components,
+            local,
+            verifier,
+            meta_cache,
+            zone_manager,
+            forward,
+            quarantine: ScrubQuarantine::new(isolate),
+            status: Mutex::new(status),
+        };
+
+        Self(Arc::new(inner))
+    }
+
+    pub fn status(&self) -> ScrubStatus {
+        self.0.status.lock().unwrap().clone()
+    }
+
+    pub fn start(&self) {
+        let this = self.clone();
+        async_std::task::spawn(async move {
+            // wait for the stack and the zone devices online
+            async_std::task::sleep(Duration::from_secs(60 * 5)).await;
+
+            let interval = Duration::from_secs(this.0.params.interval_secs);
+            loop {
+                this.scrub_once().await;
+                async_std::task::sleep(interval).await;
+            }
+        });
+    }
+
+    async fn scrub_once(&self) {
+        let round = {
+            let mut status = self.0.status.lock().unwrap();
+            status.running = true;
+            status.round += 1;
+            status.round_start_time = bucky_time_now();
+            status.objects_checked = 0;
+            status.chunks_checked = 0;
+            status.bytes_checked = 0;
+            status.round
+        };
+
+        info!("scrub round begin: round={}", round);
+
+        let devices = if self.0.params.repair {
+            match self.zone_devices().await {
+                Ok(list) => list,
+                Err(e) => {
+                    error!("get zone devices for scrub failed! {}", e);
+                    vec![]
+                }
+            }
+        } else {
+            vec![]
+        };
+
+        let mut throttle = ScrubThrottle::new(&self.0.params);
+        self.scrub_objects(&devices, &mut throttle).await;
+        self.scrub_chunks(&devices, &mut throttle).await;
+
+        let status = {
+            let mut status = self.0.status.lock().unwrap();
+            status.running = false;
+            status.last_round_complete_time = bucky_time_now();
+            status.clone()
+        };
+
+        info!(
+            "scrub round complete: round={}, objects={}, chunks={}, bytes={}, corrupted={}, repaired={}",
+            round,
+            status.objects_checked,
+            status.chunks_checked,
+            status.bytes_checked,
+            status.corrupted,
+            status.repaired
+        );
+    }
+
+    // oods first, then the other known devices, exclude current device
+    async fn zone_devices(&self) -> BuckyResult<Vec<DeviceId>> {
+        let zone = self.0.zone_manager.get_current_zone().await?;
+
+        let mut list: Vec<DeviceId> = vec![];
+        for device_id in zone
+            .ood_list()
+            .iter()
+            .chain(zone.known_device_list().iter())
+        {
+            if *device_id != self.0.device_id && !list.contains(device_id) {
+                list.push(device_id.to_owned());
+            }
+        }
+
+        Ok(list)
+    }
+
+    fn on_checked(&self, is_object: bool, bytes: u64) {
+        let mut status = self.0.status.lock().unwrap();
+        if is_object {
+            status.objects_checked += 1;
+        } else {
+            status.chunks_checked += 1;
+        }
+        status.bytes_checked += bytes;
+    }
+
+    fn on_issue(&self, issue: ScrubIssue) {
+        let mut status = self.0.status.lock().unwrap();
+        match issue.kind {
+            ScrubIssueKind::ObjectSignInvalid | ScrubIssueKind::ChunkReadFailed => {}
+            _ => status.corrupted += 1,
+        }
+        if issue.quarantined {
+            status.quarantined += 1;
+        }
+        if issue.repaired_from.is_some() {
+            status.repaired += 1;
+        }
+
+        if status.issues.len() >= SCRUB_MAX_ISSUES {
+            status.issues.remove(0);
+        }
+        status.issues.push(issue);
+    }
+
+    fn new_issue(id: ObjectId, kind: ScrubIssueKind, msg: String) -> ScrubIssue {
+        ScrubIssue {
+            id,
+            kind,
+            msg,
+            quarantined: false,
+            repaired_from: None,
+            time: bucky_time_now(),
+        }
+    }
+
+    // 分页遍历过程中删除和修复会影响后续页的内容，漏掉的条目在下一轮检查
+    async fn scrub_objects(&self, devices: &[DeviceId], throttle: &mut ScrubThrottle) {
+        let mut page_index = 0;
+        loop {
+            let req = NamedObjectCacheSelectObjectRequest {
+                filter: NamedObjectCacheSelectObjectFilter::default(),
+                opt: NamedObjectCacheSelectObjectOption {
+                    page_size: SCRUB_PAGE_SIZE,
+                    page_index,
+                    ..Default::default()
+                },
+            };
+
+            let list = match self.0.noc.select_object(&req).await {
+                Ok(resp) => resp.list,
+                Err(e) => {
+                    error!(
+                        "select objects from noc for scrub failed! page={}, {}",
+                        page_index, e
+                    );
+                    break;
+                }
+            };
+
+            let count = list.len();
+            for item in list {
+                let bytes = self.check_object(&item.object_id, devices).await;
+                self.on_checked(true, bytes);
+                throttle.on_item(bytes).await;
+            }
+
+            if count < SCRUB_PAGE_SIZE {
+                break;
+            }
+            page_index += 1;
+        }
+    }
+
+    async fn check_object(&self, object_id: &ObjectId, devices: &[DeviceId]) -> u64 {
+        let mut req = NamedObjectCacheGetObjectRequest {
+            source: RequestSourceInfo::new_local_system(),
+            object_id: object_id.to_owned(),
+            last_access_rpath: None,
+            flags: 0,
+        };
+        req.set_no_update_last_access();
+
+        let data = match self.0.noc.get_object_raw(&req).await {
+            Ok(Some(data)) => data,
+            Ok(None) => {
+                // removed during the scrub
+                return 0;
+            }
+            Err(e) => {
+                // blob读取或者解码失败，单独加载meta，按原来的权限隔离并修复
+                let msg = format!("load object from noc failed! {}", e);
+                error!("scrub {}, obj={}", msg, object_id);
+                let mut issue =
+                    Self::new_issue(object_id.to_owned(), ScrubIssueKind::ObjectCorrupted, msg);
+
+                let bytes = match self.load_object_meta(object_id).await {
+                    Some(meta) => {
+                        let raw = self.load_object_blob(object_id).await;
+                        let bytes = raw.as_ref().map(|v| v.len() as u64).unwrap_or(0);
+                        self.replace_corrupted_object(object_id, devices, &meta, raw, &mut issue)
+                            .await;
+                        bytes
+                    }
+                    None => 0,
+                };
+                self.on_issue(issue);
+
+                return bytes;
+            }
+        };
+
+        let meta = data.meta;
+        let object = match data.object {
+            Some(object) => object,
+            None => {
+                let msg = "object blob missing but meta exists".to_owned();
+                warn!("scrub {}, obj={}", msg, object_id);
+                let mut issue =
+                    Self::new_issue(object_id.to_owned(), ScrubIssueKind::ObjectMissing, msg);
+
+                // 没有可用的副本时保留meta
+                if let Some((object, from)) = self.fetch_object(object_id, devices, false).await {
+                    if self.restore_object(object, &meta).await.is_ok() {
+                        issue.repaired_from = Some(from);
+                    }
+                }
+                self.on_issue(issue);
+
+                return 0;
+            }
+        };
+
+        let bytes = object.object_raw.len() as u64;
+        if object.object_id != *object_id {
+            let msg = format!("object blob content unmatch! got={}", object.object_id);
+            error!("scrub {}, obj={}", msg, object_id);
+            let mut issue =
+                Self::new_issue(object_id.to_owned(), ScrubIssueKind::ObjectCorrupted, msg);
+
+            self.replace_corrupted_object(
+                object_id,
+                devices,
+                &meta,
+                Some(object.object_raw),
+                &mut issue,
+            )
+            .await;
+            self.on_issue(issue);
+
+            return bytes;
+        }
+
+        // 签名失效也可能是密钥吊销等正常情况，只尝试用有效副本替换，不做隔离
+        if let Some(false) = self.verify_object_sign(&object).await {
+            let msg = "object has no valid owner desc sign".to_owned();
+            warn!("scrub {}, obj={}", msg, object_id);
+            let mut issue =
+                Self::new_issue(object_id.to_owned(), ScrubIssueKind::ObjectSignInvalid, msg);
+
+            if let Some((object, from)) = self.fetch_object(object_id, devices, true).await {
+                if self.delete_object(object_id).await.is_ok() {
+                    if self.restore_object(object, &meta).await.is_ok() {
+                        issue.repaired_from = Some(from);
+                    }
+                }
+            }
+            self.on_issue(issue);
+        }
+
+        bytes
+    }
+
+    // 隔离损坏的blob，然后从其它设备拉取有效副本按原meta恢复
+    async fn replace_corrupted_object(
+        &self,
+        object_id: &ObjectId,
+        devices: &[DeviceId],
+        meta: &NamedObjectMetaData,
+        raw: Option<Vec<u8>>,
+        issue: &mut ScrubIssue,
+    ) {
+        if let Some(raw) = raw {
+            issue.quarantined = self.0.quarantine.save_object(object_id, &raw).await.is_ok();
+        }
+
+        // 损坏的blob不能继续提供给调用方，无论是否修复成功都要删除
+        let fetched = self.fetch_object(object_id, devices, false).await;
+        if self.delete_object(object_id).await.is_ok() {
+            if let Some((object, from)) = fetched {
+                if self.restore_object(object, meta).await.is_ok() {
+                    issue.repaired_from = Some(from);
+                }
+            }
+        }
+    }
+
+    async fn load_object_meta(&self, object_id: &ObjectId) -> Option<NamedObjectMetaData> {
+        let mut req = NamedObjectCacheGetObjectRequest {
+            source: RequestSourceInfo::new_local_system(),
+            object_id: object_id.to_owned(),
+            last_access_rpath: None,
+            flags: 0,
+        };
+        req.set_no_update_last_access();
+        req.set_meta_only();
+
+        match self.0.noc.get_object_raw(&req).await {
+            Ok(Some(data)) => Some(data.meta),
+            Ok(None) => None,
+            Err(e) => {
+                error!("scrub load object meta failed! obj={}, {}", object_id, e);
+                None
+            }
+        }
+    }
+
+    // 读取未解码的blob内容用于隔离，读取失败时不影响后续修复
+    async fn load_object_blob(&self, object_id: &ObjectId) -> Option<Vec<u8>> {
+        match self.0.noc.get_object_blob(object_id).await {
+            Ok(ret) => ret,
+            Err(e) => {
+                warn!("scrub load object blob failed! obj={}, {}", object_id, e);
+                None
+            }
+        }
+    }
+
+    // 只校验带有owner和desc签名的对象，None表示无需校验或者校验过程出错
+    async fn verify_object_sign(&self, object: &NONObjectInfo) -> Option<bool> {
+        let obj = object.object.as_ref()?;
+        if obj.owner().is_none() {
+            return None;
+        }
+
+        match obj.signs() {
+            Some(signs) if !signs.is_desc_signs_empty() => {}
+            _ => return None,
+        }
+
+        let req = VerifyObjectInnerRequest {
+            sign_type: VerifySignType::Desc,
+            object: ObjectInfo {
+                object_id: object.object_id.clone(),
+                object: obj.clone(),
+            },
+            sign_object: VerifyObjectType::Owner,
+        };
+
+        match self.0.verifier.verify_object_inner(req).await {
+            Ok(result) => Some(result.valid),
+            Err(e) => {
+                warn!(
+                    "scrub verify object sign failed! obj={}, {}",
+                    object.object_id, e
+                );
+                None
+            }
+        }
+    }
+
+    // 先从zone内其它设备获取，再尝试meta链
+    async fn fetch_object(
+        &self,
+        object_id: &ObjectId,
+        devices: &[DeviceId],
+        require_sign: bool,
+    ) -> Option<(NONObjectInfo, String)> {
+        if !self.0.params.repair {
+            return None;
+        }
+
+        for device_id in devices {
+            let object = match self.get_remote_object(device_id, object_id).await {
+                Ok(object) => object,
+                Err(_) => continue,
+            };
+
+            if self
+                .check_fetched_object(object_id, &object, require_sign)
+                .await
+            {
+                return Some((object, device_id.to_string()));
+            }
+        }
+
+        match self.0.meta_cache.get_object(object_id).await {
+            Ok(Some(data)) => match NONObjectInfo::new_from_object_raw(data.object_raw) {
+                Ok(object) => {
+                    if self
+                        .check_fetched_object(object_id, &object, require_sign)
+                        .await
+                    {
+                        return Some((object, "meta".to_owned()));
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        "scrub decode object from meta failed! obj={}, {}",
+                        object_id, e
+                    );
+                }
+            },
+            Ok(None) => {}
+            Err(e) => {
+                warn!(
+                    "scrub get object from meta failed! obj={}, {}",
+                    object_id, e
+                );
+            }
+        }
+
+        warn!(
+            "scrub object repair failed, no valid copy found! obj={}",
+            object_id
+        );
+        None
+    }
+
+    async fn check_fetched_object(
+        &self,
+        object_id: &ObjectId,
+        object: &NONObjectInfo,
+        require_sign: bool,
+    ) -> bool {
+        if object.object_id != *object_id || object.verify().is_err() {
+            warn!(
+                "scrub fetched object but unmatch! obj={}, got={}",
+                object_id, object.object_id
+            );
+            return false;
+        }
+
+        if require_sign {
+            return self.verify_object_sign(object).await == Some(true);
+        }
+
+        true
+    }
+
+    async fn get_remote_object(
+        &self,
+        device_id: &DeviceId,
+        object_id: &ObjectId,
+    ) -> BuckyResult<NONObjectInfo> {
+        let requestor = self.0.forward.get(device_id).await?;
+        let processor = NONRequestor::new(None, requestor).into_processor();
+
+        let mut req = NONGetObjectOutputRequest::new_noc(object_id.to_owned(), None);
+        req.common.dec_id = Some(cyfs_core::get_system_dec_app().to_owned());
+
+        let resp = processor.get_object(req).await.map_err(|e| {
+            warn!(
+                "scrub get object from device failed! obj={}, device={}, {}",
+                object_id, device_id, e
+            );
+            e
+        })?;
+
+        Ok(resp.object)
+    }
+
+    async fn delete_object(&self, object_id: &ObjectId) -> BuckyResult<()> {
+        let req = NamedObjectCacheDeleteObjectRequest {
+            source: RequestSourceInfo::new_local_system(),
+            object_id: object_id.to_owned(),
+            flags: 0,
+        };
+
+        self.0.noc.delete_object(&req).await.map_err(|e| {
+            error!(
+                "scrub delete object from noc failed! obj={}, {}",
+                object_id, e
+            );
+            e
+        })?;
+
+        Ok(())
+    }
+
+    // 按原来的dec、存储类别和权限重新保存
+    async fn restore_object(
+        &self,
+        object: NONObjectInfo,
+        meta: &NamedObjectMetaData,
+    ) -> BuckyResult<()> {
+        let object_id = object.object_id.clone();
+        let req = NamedObjectCachePutObjectRequest {
+            source: RequestSourceInfo::new_local_dec_or_system(Some(meta.create_dec_id.clone())),
+            object,
+            storage_category: meta.storage_category,
+            context: meta.context.clone(),
+            last_access_rpath: meta.last_access_rpath.clone(),
+            access_string: Some(meta.access_string),
+        };
+
+        self.0.noc.put_object(&req).await.map_err(|e| {
+            error!(
+                "scrub restore object to noc failed! obj={}, {}",
+                object_id, e
+            );
+            e
+        })?;
+
+        info!("scrub restore object success! obj={}", object_id);
+        Ok(())
+    }
+
+    async fn scrub_chunks(&self, devices: &[DeviceId], throttle: &mut ScrubThrottle) {
+        let mut page_index = 0;
+        loop {
+            let req = SelectChunkRequest {
+                filter: SelectChunkFilter {
+                    state: Some(ChunkState::Ready),
+                    last_access_before: None,
+                },
+                opt: SelectChunkOption {
+                    page_size: SCRUB_PAGE_SIZE,
+                    page_index,
+                    ..Default::default()
+                },
+            };
+
+            let list = match self.0.named_data_components.ndc.select_chunk(&req).await {
+                Ok(resp) => resp.list,
+                Err(e) => {
+                    error!(
+                        "select chunks from ndc for scrub failed! page={}, {}",
+                        page_index, e
+                    );
+                    break;
+                }
+            };
+
+            let count = list.len();
+            for item in list {
+                let bytes = self.check_chunk(&item.chunk_id, devices).await;
+                if bytes > 0 {
+                    self.on_checked(false, bytes);
+                    throttle.on_item(bytes).await;
+                }
+            }
+
+            if count < SCRUB_PAGE_SIZE {
+                break;
+            }
+            page_index += 1;
+        }
+    }
+
+    async fn check_chunk(&self, chunk_id: &ChunkId, devices: &[DeviceId]) -> u64 {
+        let chunk_manager = &self.0.named_data_components.chunk_manager;
+
+        // ready的chunk也可能只存在于本地文件中(tracker)，这里只检查chunk cache里面的
+        if !chunk_manager.exist(chunk_id).await {
+            return 0;
+        }
+
+        let data = match chunk_manager.get_chunk(chunk_id, ChunkType::MemChunk).await {
+            Ok(chunk) => chunk.into_vec(),
+            Err(e) if e.code() == BuckyErrorCode::NotFound => {
+                // removed during the scrub
+                return 0;
+            }
+            Err(e) => {
+                // 读取失败可能是临时的io错误，不能确认数据损坏，只记录问题，下一轮再检查
+                let msg = format!("read chunk from chunk cache failed! {}", e);
+                error!("scrub {}, chunk={}", msg, chunk_id);
+                let issue =
+                    Self::new_issue(chunk_id.object_id(), ScrubIssueKind::ChunkReadFailed, msg);
+                self.on_issue(issue);
+                return 0;
+            }
+        };
+
+        // 只有确认hash不匹配才删除
+        let msg = match ChunkId::calculate(&data).await {
+            Ok(id) if id == *chunk_id => return data.len() as u64,
+            Ok(id) => format!("chunk content unmatch! got={}", id),
+            Err(e) => {
+                let msg = format!("calculate chunk id failed! {}", e);
+                error!("scrub {}, chunk={}", msg, chunk_id);
+                let issue =
+                    Self::new_issue(chunk_id.object_id(), ScrubIssueKind::ChunkReadFailed, msg);
+                self.on_issue(issue);
+                return 0;
+            }
+        };
+
+        error!("scrub {}, chunk={}", msg, chunk_id);
+        let bytes = chunk_id.len() as u64;
+        let mut issue = Self::new_issue(chunk_id.object_id(), ScrubIssueKind::ChunkCorrupted, msg);
+        issue.quarantined = self.0.quarantine.save_chunk(chunk_id, &data).await.is_ok();
+
+        if let Err(e) = chunk_manager.delete_chunk(chunk_id).await {
+            error!("scrub delete chunk failed! chunk={}, {}", chunk_id, e);
+            self.on_issue(issue);
+            return bytes;
+        }
+
+        let req = UpdateChunkStateRequest {
+            chunk_id: chunk_id.to_owned(),
+            current_state: Some(ChunkState::Ready),
+            state: ChunkState::NotFound,
+        };
+        if let Err(e) = self
+            .0
+            .named_data_components
+            .ndc
+            .update_chunk_state(&req)
+            .await
+        {
+            error!("scrub update chunk state failed! chunk={}, {}", chunk_id, e);
+        }
+
+        if let Some(from) = self.repair_chunk(chunk_id, devices).await {
+            issue.repaired_from = Some(from);
+        }
+        self.on_issue(issue);
+
+        bytes
+    }
+
+    async fn repair_chunk(&self, chunk_id: &ChunkId, devices: &[DeviceId]) -> Option<String> {
+        if !self.0.params.repair {
+            return None;
+        }
+
+        for device_id in devices {
+            let data = match self.get_remote_chunk(device_id, chunk_id).await {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+
+            match ChunkId::calculate(&data).await {
+                Ok(id) if id == *chunk_id => {}
+                _ => {
+                    warn!(
+                        "scrub fetched chunk but content unmatch! chunk={}, device={}",
+                        chunk_id, device_id
+                    );
+                    continue;
+                }
+            }
+
+            match self
+                .0
+                .local
+                .put_chunk(chunk_id, Box::new(MemChunk::from(data)), vec![])
+                .await
+            {
+                Ok(_) => {
+                    info!(
+                        "scrub repair chunk success! chunk={}, device={}",
+                        chunk_id, device_id
+                    );
+                    return Some(device_id.to_string());
+                }
+                Err(e) => {
+                    error!("scrub put repaired chunk failed! chunk={}, {}", chunk_id, e);
+                    return None;
+                }
+            }
+        }
+
+        warn!(
+            "scrub chunk repair failed, no valid copy found! chunk={}",
+            chunk_id
+        );
+        None
+    }
+
+    async fn get_remote_chunk(
+        &self,
+        device_id: &DeviceId,
+        chunk_id: &ChunkId,
+    ) -> BuckyResult<Vec<u8>> {
+        let requestor = self.0.forward.get(device_id).await?;
+        let processor = NDNRequestor::new(None, requestor, None).into_processor();
+
+        let mut req = NDNGetDataOutputRequest::new_ndc(chunk_id.object_id(), None);
+        req.common.dec_id = Some(cyfs_core::get_system_dec_app().to_owned());
+
+        let mut resp = processor.get_data(req).await.map_err(|e| {
+            warn!(
+                "scrub get chunk from device failed! chunk={}, device={}, {}",
+                chunk_id, device_id, e
+            );
+            e
+        })?;
+
+        let mut buf = Vec::with_capacity(chunk_id.len());
+        resp.data.read_to_end(&mut buf).await.map_err(|e| {
+            let msg = format!(
+                "scrub read chunk from device failed! chunk={}, device={}, {}",
+                chunk_id, device_id, e
+            );
+            warn!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
+
+        Ok(buf)
+    }
+}
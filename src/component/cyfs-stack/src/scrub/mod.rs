@@ -0,0 +1,5 @@
+mod manager;
+mod quarantine;
+mod throttle;
+
+pub(crate) use manager::*;
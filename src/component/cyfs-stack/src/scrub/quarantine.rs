@@ -0,0 +1,66 @@
+use cyfs_base::*;
+
+use std::path::PathBuf;
+
+// 损坏的数据在删除前先移入隔离目录，便于事后排查，不会再被协议栈读取
+// {cyfs_root}/data/{isolate}/scrub/quarantine/{objects|chunks}/{id}.{time}
+pub(crate) struct ScrubQuarantine {
+    root: PathBuf,
+}
+
+impl ScrubQuarantine {
+    pub fn new(isolate: &str) -> Self {
+        let mut root = cyfs_util::get_cyfs_root_path();
+        root.push("data");
+        if isolate.len() > 0 {
+            root.push(isolate);
+        }
+        root.push("scrub");
+        root.push("quarantine");
+
+        Self { root }
+    }
+
+    pub async fn save_object(&self, object_id: &ObjectId, data: &[u8]) -> BuckyResult<PathBuf> {
+        self.save("objects", &object_id.to_string(), data).await
+    }
+
+    pub async fn save_chunk(&self, chunk_id: &ChunkId, data: &[u8]) -> BuckyResult<PathBuf> {
+        self.save("chunks", &chunk_id.to_string(), data).await
+    }
+
+    async fn save(&self, category: &str, id: &str, data: &[u8]) -> BuckyResult<PathBuf> {
+        let dir = self.root.join(category);
+        if !dir.is_dir() {
+            async_std::fs::create_dir_all(&dir).await.map_err(|e| {
+                let msg = format!(
+                    "create scrub quarantine dir failed! dir={}, {}",
+                    dir.display(),
+                    e
+                );
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::IoError, msg)
+            })?;
+        }
+
+        // 同一个对象可能多次损坏，加上时间避免覆盖
+        let file = dir.join(format!("{}.{}", id, bucky_time_now()));
+        async_std::fs::write(&file, data).await.map_err(|e| {
+            let msg = format!(
+                "write scrub quarantine file failed! file={}, {}",
+                file.display(),
+                e
+            );
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
+
+        warn!(
+            "corrupted data moved to quarantine: id={}, file={}",
+            id,
+            file.display()
+        );
+
+        Ok(file)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::stack::CyfsStackScrubParams;
+
+use std::time::{Duration, Instant};
+
+// 按字节数和条目数限速，两者取较慢的一个；0表示不限制
+pub(crate) struct ScrubThrottle {
+    max_bytes_per_sec: u64,
+    max_items_per_sec: u32,
+
+    start: Instant,
+    bytes: u64,
+    items: u64,
+}
+
+impl ScrubThrottle {
+    pub fn new(params: &CyfsStackScrubParams) -> Self {
+        Self {
+            max_bytes_per_sec: params.max_bytes_per_sec,
+            max_items_per_sec: params.max_items_per_sec,
+            start: Instant::now(),
+            bytes: 0,
+            items: 0,
+        }
+    }
+
+    pub async fn on_item(&mut self, bytes: u64) {
+        self.bytes += bytes;
+        self.items += 1;
+
+        if let Some(delay) = self.delay(self.start.elapsed()) {
+            async_std::task::sleep(delay).await;
+        }
+    }
+
+    fn delay(&self, elapsed: Duration) -> Option<Duration> {
+        let mut expect = 0f64;
+        if self.max_bytes_per_sec > 0 {
+            expect = self.bytes as f64 / self.max_bytes_per_sec as f64;
+        }
+        if self.max_items_per_sec > 0 {
+            expect = expect.max(self.items as f64 / self.max_items_per_sec as f64);
+        }
+
+        let expect = Duration::from_secs_f64(expect);
+        if expect > elapsed {
+            Some(expect - elapsed)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let mut params = CyfsStackScrubParams::default();
+        params.max_bytes_per_sec = 1000;
+        params.max_items_per_sec = 10;
+
+        let mut throttle = ScrubThrottle::new(&params);
+        throttle.bytes = 2000;
+        throttle.items = 5;
+        assert_eq!(
+            throttle.delay(Duration::from_secs(1)),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(throttle.delay(Duration::from_secs(3)), None);
+
+        throttle.bytes = 100;
+        throttle.items = 20;
+        assert_eq!(
+            throttle.delay(Duration::from_secs(1)),
+            Some(Duration::from_secs(1))
+        );
+
+        params.max_bytes_per_sec = 0;
+        params.max_items_per_sec = 0;
+        let mut throttle = ScrubThrottle::new(&params);
+        throttle.bytes = 1024 * 1024;
+        throttle.items = 1024;
+        assert_eq!(throttle.delay(Duration::from_secs(0)), None);
+    }
+}
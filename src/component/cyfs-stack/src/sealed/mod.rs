@@ -0,0 +1,4 @@
+mod reader;
+mod unsealer;
+
+pub(crate) use unsealer::*;
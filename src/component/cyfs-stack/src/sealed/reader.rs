@@ -0,0 +1,181 @@
+use cyfs_base::*;
+use cyfs_core::*;
+
+use async_std::io::Read;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+// 按密文chunk逐个解密加密文件的数据流，只输出请求的明文范围(需要升序且不重叠)
+pub(crate) struct SealedFileReader {
+    inner: Box<dyn Read + Unpin + Send + Sync + 'static>,
+    key: AesKey,
+
+    // 剩余的密文chunk长度
+    chunks: VecDeque<usize>,
+
+    // 剩余需要输出的明文范围
+    ranges: VecDeque<Range<u64>>,
+
+    sealed: Vec<u8>,
+    sealed_len: usize,
+
+    plain: Vec<u8>,
+    plain_pos: usize,
+
+    // 已解密的明文长度
+    offset: u64,
+}
+
+impl SealedFileReader {
+    pub fn new(
+        inner: Box<dyn Read + Unpin + Send + Sync + 'static>,
+        key: AesKey,
+        chunks: &Vec<ChunkId>,
+        ranges: Vec<Range<u64>>,
+    ) -> Self {
+        Self {
+            inner,
+            key,
+            chunks: chunks.iter().map(|v| v.len()).collect(),
+            ranges: ranges.into(),
+            sealed: vec![],
+            sealed_len: 0,
+            plain: vec![],
+            plain_pos: 0,
+            offset: 0,
+        }
+    }
+
+    fn unseal_chunk(&mut self, chunk_len: usize) -> std::io::Result<()> {
+        let data = unseal_data(&self.key, &self.sealed[..chunk_len]).map_err(|e| {
+            let msg = format!("unseal file chunk failed! offset={}, {}", self.offset, e);
+            error!("{}", msg);
+            std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+        })?;
+
+        let start = self.offset;
+        let end = start + data.len() as u64;
+        self.offset = end;
+
+        self.plain.clear();
+        self.plain_pos = 0;
+        while let Some(range) = self.ranges.front() {
+            if range.start >= end {
+                break;
+            }
+
+            let begin = std::cmp::max(range.start, start);
+            let stop = std::cmp::min(range.end, end);
+            if begin < stop {
+                self.plain
+                    .extend_from_slice(&data[(begin - start) as usize..(stop - start) as usize]);
+            }
+
+            if range.end <= end {
+                self.ranges.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for SealedFileReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            if this.plain_pos < this.plain.len() {
+                let len = std::cmp::min(buf.len(), this.plain.len() - this.plain_pos);
+                buf[..len].copy_from_slice(&this.plain[this.plain_pos..this.plain_pos + len]);
+                this.plain_pos += len;
+                return Poll::Ready(Ok(len));
+            }
+
+            if this.ranges.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+
+            let chunk_len = match this.chunks.front() {
+                Some(len) => *len,
+                None => return Poll::Ready(Ok(0)),
+            };
+
+            if this.sealed.len() < chunk_len {
+                this.sealed.resize(chunk_len, 0);
+            }
+
+            while this.sealed_len < chunk_len {
+                match Pin::new(&mut this.inner)
+                    .poll_read(cx, &mut this.sealed[this.sealed_len..chunk_len])
+                {
+                    Poll::Ready(Ok(0)) => {
+                        let msg = format!(
+                            "sealed file data ended unexpectedly! offset={}, chunk_len={}, got={}",
+                            this.offset, chunk_len, this.sealed_len
+                        );
+                        error!("{}", msg);
+                        return Poll::Ready(Err(std::io::Error::new(
+                            std::io::ErrorKind::UnexpectedEof,
+                            msg,
+                        )));
+                    }
+                    Poll::Ready(Ok(len)) => this.sealed_len += len,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            this.chunks.pop_front();
+            this.sealed_len = 0;
+            this.unseal_chunk(chunk_len)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_std::io::ReadExt;
+
+    #[async_std::test]
+    async fn test_reader() {
+        let key = AesKey::random();
+        let plain: Vec<u8> = (0..100u32).map(|v| v as u8).collect();
+
+        let mut sealed = vec![];
+        let mut chunks = vec![];
+        for part in plain.chunks(32) {
+            let data = seal_data(&key, part).unwrap();
+            chunks.push(ChunkId::calculate_sync(&data).unwrap());
+            sealed.extend_from_slice(&data);
+        }
+
+        let mut reader = SealedFileReader::new(
+            Box::new(async_std::io::Cursor::new(sealed.clone())),
+            key.clone(),
+            &chunks,
+            vec![0..100],
+        );
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, plain);
+
+        let mut reader = SealedFileReader::new(
+            Box::new(async_std::io::Cursor::new(sealed)),
+            key,
+            &chunks,
+            vec![10..20, 30..70],
+        );
+        let mut buf = vec![];
+        reader.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, [&plain[10..20], &plain[30..70]].concat());
+    }
+}
@@ -0,0 +1,267 @@
+use super::reader::SealedFileReader;
+use crate::ndn::*;
+use crate::non::*;
+use cyfs_base::*;
+use cyfs_bdt::StackGuard;
+use cyfs_bdt_ext::zero_bytes_reader;
+use cyfs_core::*;
+use cyfs_lib::*;
+
+use std::sync::Arc;
+
+struct SealedFileInfo {
+    key: AesKey,
+    file_id: ObjectId,
+    file: File,
+    data_len: u64,
+}
+
+/*
+加密对象的透明解密
+1. 只处理当前zone内、没有指定CYFS_REQUEST_FLAG_KEEP_SEALED的请求，其余请求包括zone外的转发都原样返回密文
+2. 当前设备需要在接收者列表里，使用bdt协议栈的设备私钥解出内容密钥
+3. get_object返回内部对象；get_data以SealedObject为目标，拉取内部的密文File并按chunk解密
+*/
+pub(crate) struct ObjectUnsealer {
+    device_id: ObjectId,
+    bdt_stack: StackGuard,
+}
+
+pub(crate) type ObjectUnsealerRef = Arc<ObjectUnsealer>;
+
+impl ObjectUnsealer {
+    pub fn new(bdt_stack: StackGuard) -> Self {
+        Self {
+            device_id: bdt_stack.local_device_id().object_id().to_owned(),
+            bdt_stack,
+        }
+    }
+
+    fn need_unseal(source: &RequestSourceInfo, flags: u32) -> bool {
+        flags & CYFS_REQUEST_FLAG_KEEP_SEALED == 0 && source.is_current_zone()
+    }
+
+    fn decode_sealed(object: &NONObjectInfo) -> Option<SealedObject> {
+        if object.object_id.obj_type_code() != ObjectTypeCode::Custom {
+            return None;
+        }
+
+        let obj_type = match &object.object {
+            Some(obj) => obj.obj_type(),
+            None => match AnyNamedObject::raw_decode(&object.object_raw) {
+                Ok((obj, _)) => obj.obj_type(),
+                Err(_) => return None,
+            },
+        };
+
+        if obj_type != CoreObjectType::SealedObject as u16 {
+            return None;
+        }
+
+        match SealedObject::clone_from_slice(&object.object_raw) {
+            Ok(sealed) => Some(sealed),
+            Err(e) => {
+                warn!(
+                    "decode sealed object failed! obj={}, {}",
+                    object.object_id, e
+                );
+                None
+            }
+        }
+    }
+
+    fn open(&self, sealed: &SealedObject) -> BuckyResult<(AesKey, NONObjectInfo)> {
+        let key = sealed.open_key(&self.device_id, self.bdt_stack.keystore().private_key())?;
+        let object_raw = sealed.open(&key)?;
+        let object = NONObjectInfo::new_from_object_raw(object_raw)?;
+
+        Ok((key, object))
+    }
+
+    pub fn unseal_object(
+        &self,
+        source: &RequestSourceInfo,
+        flags: u32,
+        resp: NONGetObjectInputResponse,
+    ) -> BuckyResult<NONGetObjectInputResponse> {
+        if !Self::need_unseal(source, flags) {
+            return Ok(resp);
+        }
+
+        let sealed = match Self::decode_sealed(&resp.object) {
+            Some(sealed) => sealed,
+            None => return Ok(resp),
+        };
+
+        // 不是接收者的话原样返回，由调用方自行处理
+        if !sealed.is_recipient(&self.device_id) {
+            return Ok(resp);
+        }
+
+        let (_, object) = self.open(&sealed).map_err(|e| {
+            error!(
+                "unseal object failed! obj={}, device={}, {}",
+                resp.object.object_id, self.device_id, e
+            );
+            e
+        })?;
+
+        info!(
+            "unseal object success: sealed={}, object={}, source={}",
+            resp.object.object_id, object.object_id, source
+        );
+
+        Ok(NONGetObjectInputResponse { object, ..resp })
+    }
+
+    pub async fn get_data(
+        &self,
+        non_router: &NONInputProcessorRef,
+        next: &NDNInputProcessorRef,
+        req: NDNGetDataInputRequest,
+    ) -> BuckyResult<NDNGetDataInputResponse> {
+        let info = match self.load_sealed_file(non_router, &req).await? {
+            Some(info) => info,
+            None => return next.get_data(req).await,
+        };
+
+        let chunks = info
+            .file
+            .body()
+            .as_ref()
+            .and_then(|v| v.content().inner_chunk_list())
+            .ok_or_else(|| {
+                let msg = format!(
+                    "sealed file's chunk list not support! sealed={}, file={}",
+                    req.object_id, info.file_id
+                );
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::NotSupport, msg)
+            })?
+            .clone();
+
+        // 明文范围，密文chunk需要完整解密，所以总是拉取整个密文文件
+        let mut ranges = vec![0..info.data_len];
+        let mut resp_range = None;
+        if let Some(range) = &req.range {
+            resp_range = range.convert_to_response(info.data_len);
+            match &resp_range {
+                Some(NDNDataResponseRange::Range((list, _))) => {
+                    for i in 1..list.len() {
+                        if list[i].start < list[i - 1].end {
+                            let msg = format!(
+                                "get sealed data only support ascending ranges! sealed={}, range={:?}",
+                                req.object_id, list
+                            );
+                            error!("{}", msg);
+                            return Err(BuckyError::new(BuckyErrorCode::NotSupport, msg));
+                        }
+                    }
+                    ranges = list.clone();
+                }
+                Some(_) => ranges = vec![],
+                None => {}
+            }
+        }
+
+        let length = ranges.iter().fold(0, |acc, v| acc + (v.end - v.start));
+        let sealed_id = req.object_id.clone();
+
+        let (data, owner_id, attr, group) = if ranges.is_empty() {
+            (
+                zero_bytes_reader(),
+                info.file.desc().owner().to_owned(),
+                None,
+                None,
+            )
+        } else {
+            let mut file_req = req;
+            file_req.object_id = info.file_id.clone();
+            file_req.range = None;
+
+            let resp = next.get_data(file_req).await?;
+            let reader = SealedFileReader::new(resp.data, info.key, &chunks, ranges);
+            let data: Box<dyn async_std::io::Read + Unpin + Send + Sync + 'static> =
+                Box::new(reader);
+            (data, resp.owner_id, resp.attr, resp.group)
+        };
+
+        info!(
+            "get sealed data: sealed={}, file={}, data_len={}, length={}",
+            sealed_id, info.file_id, info.data_len, length
+        );
+
+        Ok(NDNGetDataInputResponse {
+            object_id: sealed_id,
+            owner_id,
+            attr,
+            range: resp_range,
+            group,
+            length,
+            data,
+        })
+    }
+
+    async fn load_sealed_file(
+        &self,
+        non_router: &NONInputProcessorRef,
+        req: &NDNGetDataInputRequest,
+    ) -> BuckyResult<Option<SealedFileInfo>> {
+        if !Self::need_unseal(&req.common.source, req.common.flags)
+            || req.object_id.obj_type_code() != ObjectTypeCode::Custom
+            || req.inner_path.is_some()
+        {
+            return Ok(None);
+        }
+
+        let non_req = NONGetObjectInputRequest {
+            common: NONInputRequestCommon {
+                req_path: req.common.req_path.clone(),
+                source: req.common.source.clone(),
+                level: NONAPILevel::Router,
+                target: req.common.target.clone(),
+                flags: CYFS_REQUEST_FLAG_KEEP_SEALED,
+            },
+            object_id: req.object_id.clone(),
+            inner_path: None,
+        };
+
+        let resp = non_router.get_object(non_req).await?;
+        let sealed = match Self::decode_sealed(&resp.object) {
+            Some(sealed) => sealed,
+            None => return Ok(None),
+        };
+
+        let data_len = sealed.data_len().ok_or_else(|| {
+            let msg = format!("sealed object has no sealed data! obj={}", req.object_id);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::NotSupport, msg)
+        })?;
+
+        let (key, object) = self.open(&sealed).map_err(|e| {
+            error!(
+                "unseal file failed! obj={}, device={}, {}",
+                req.object_id, self.device_id, e
+            );
+            e
+        })?;
+
+        if object.object_id.obj_type_code() != ObjectTypeCode::File {
+            let msg = format!(
+                "sealed object's inner object is not file! obj={}, inner={}",
+                req.object_id, object.object_id
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotSupport, msg));
+        }
+
+        let file = File::clone_from_slice(&object.object_raw)?;
+
+        Ok(Some(SealedFileInfo {
+            key,
+            file_id: object.object_id,
+            file,
+            data_len,
+        }))
+    }
+}
@@ -6,7 +6,8 @@ use crate::admin::AdminManager;
 use crate::app::{AppController, AppService};
 use crate::config::*;
 use crate::crypto::CryptoOutputTransformer;
-use crate::crypto_api::{CryptoService, ObjectCrypto, ObjectVerifier};
+use crate::crypto_api::{CryptoService, KeyChainManager, ObjectCrypto, ObjectVerifier};
+use crate::erasure::ErasureChunkManager;
 use crate::events::RouterEventsManager;
 use crate::forward::ForwardProcessorManager;
 use crate::front::FrontService;
@@ -17,10 +18,12 @@ use crate::interface::{
 use crate::meta::*;
 use crate::name::NameResolver;
 use crate::ndn::NDNOutputTransformer;
-use crate::ndn_api::{BdtNDNEventHandler, NDNService};
+use crate::ndn_api::{BdtNDNEventHandler, LocalDataManager, NDNService};
 use crate::non::NONOutputTransformer;
 use crate::non_api::NONService;
 use crate::resolver::{CompoundObjectSearcher, DeviceInfoManager, OodResolver};
+use crate::scrub::ScrubManager;
+use crate::evict::EvictManager;
 use crate::rmeta::GlobalStateMetaOutputTransformer;
 use crate::rmeta_api::{GlobalStateMetaLocalService, GlobalStateMetaService};
 use crate::root_state::{GlobalStateAccessorOutputTransformer, GlobalStateOutputTransformer};
@@ -28,6 +31,7 @@ use crate::root_state_api::{
     GlobalStateLocalService, GlobalStateManager, GlobalStateService, GlobalStateValidatorManager,
 };
 use crate::router_handler::RouterHandlersManager;
+use crate::sealed::ObjectUnsealer;
 use crate::trans::TransOutputTransformer;
 use crate::trans_api::{create_trans_store, TransService};
 use crate::util::UtilOutputTransformer;
@@ -186,9 +190,11 @@ impl CyfsStackImpl {
         );
 
         // init signs verifier
+        let key_chain = Arc::new(KeyChainManager::new(raw_meta_cache.clone_meta()));
         let verifier = ObjectVerifier::new(
             bdt_param.device.desc().device_id().to_owned(),
             raw_meta_cache.clone_meta(),
+            key_chain.clone(),
         );
         let verifier = Arc::new(verifier);
         verifier.bind_noc(noc.clone());
@@ -205,6 +211,7 @@ impl CyfsStackImpl {
             isolate,
             noc.clone(),
             device_manager.clone_cache(),
+            bdt_param.chunk_compress.unwrap_or(false),
         )
         .await?;
 
@@ -259,6 +266,7 @@ impl CyfsStackImpl {
             noc.clone(),
             param.config.isolate.clone(),
             zone_manager.clone(),
+            key_chain.clone(),
         ));
 
         // handlers
@@ -304,6 +312,9 @@ impl CyfsStackImpl {
 
         named_data_components.bind_bdt_stack(bdt_stack.clone());
 
+        // 同步zone owner的设备吊销列表到bdt，拒绝和被吊销的设备建立连接
+        key_chain.start_revoked_monitor(zone_manager.clone(), bdt_stack.clone());
+
         // enable the zone search ablity for obj_searcher
         obj_searcher.init_zone_searcher(zone_manager.clone(), noc.clone(), bdt_stack.clone());
 
@@ -358,6 +369,7 @@ impl CyfsStackImpl {
             router_handlers.clone(),
             raw_meta_cache.clone(),
             fail_handler.clone(),
+            Arc::new(ObjectUnsealer::new(bdt_stack.clone())),
         );
 
         bdt_event.bind_non_processor(non_service.rmeta_noc_processor().clone());
@@ -380,6 +392,61 @@ impl CyfsStackImpl {
         let crypto_service = Arc::new(crypto_service);
         let util_service = Arc::new(util_service);
 
+        // erasure coded chunks, only works on ood
+        if param.erasure.enable {
+            let info = zone_manager.get_current_info().await?;
+            if info.zone_role.is_ood_device() {
+                let erasure = ErasureChunkManager::new(
+                    param.erasure.clone(),
+                    zone_manager.clone(),
+                    GlobalStateOutputTransformer::new(
+                        local_root_state.clone_global_state_processor(),
+                        RequestSourceInfo::new_local_system(),
+                    ),
+                    noc.clone(),
+                    LocalDataManager::new(Arc::new(named_data_components.clone())),
+                    forward_manager.clone(),
+                );
+                erasure.init().await?;
+
+                ndn_service.bind_erasure(erasure.clone());
+                erasure.start();
+            } else {
+                warn!(
+                    "erasure is enabled but current device is not ood! role={}",
+                    info.zone_role
+                );
+            }
+        }
+
+        // integrity scrub of local noc objects and chunks
+        if param.scrub.enable {
+            let scrub = ScrubManager::new(
+                param.scrub.clone(),
+                isolate,
+                noc.clone(),
+                Arc::new(named_data_components.clone()),
+                crypto_service.local_service().verifier().clone(),
+                raw_meta_cache.clone_meta(),
+                zone_manager.clone(),
+                forward_manager.clone(),
+            );
+
+            util_service.local_service().bind_scrub(scrub.clone());
+            scrub.start();
+        }
+
+        // quota-aware eviction of cached noc objects and chunks
+        if param.evict.enable {
+            let evict = EvictManager::new(
+                param.evict.clone(),
+                noc.clone(),
+                Arc::new(named_data_components.clone()),
+                global_state_manager.clone(),
+            );
+            evict.start();
+        }
+
         // load root-state service
         let root_state = Self::load_root_state_service(
             local_root_state,
@@ -1,7 +1,10 @@
+use cyfs_base::*;
 use cyfs_lib::*;
 use cyfs_meta_lib::MetaMinerTarget;
 
 use async_std::net::SocketAddr;
+use std::collections::HashMap;
+use std::str::FromStr;
 
 #[derive(Debug, Clone)]
 pub struct CyfsStackConfigParams {
@@ -69,6 +72,138 @@ impl Default for CyfsStackNOCParams {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct CyfsStackErasureParams {
+    // if enable the erasure coded chunk distribution on ood
+    pub enable: bool,
+
+    // fragments count of each chunk, any data_fragments of them can rebuild the chunk
+    pub data_fragments: u16,
+    pub parity_fragments: u16,
+
+    // interval of checking the selected files and the fragments
+    pub check_interval_secs: u64,
+}
+
+impl Default for CyfsStackErasureParams {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            data_fragments: 4,
+            parity_fragments: 2,
+            check_interval_secs: 60 * 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CyfsStackScrubParams {
+    // if enable the background integrity scrub of noc objects and local chunks
+    pub enable: bool,
+
+    // interval between two scrub rounds
+    pub interval_secs: u64,
+
+    // throttle of the scrub, limit the disk io and cpu usage
+    pub max_bytes_per_sec: u64,
+    pub max_items_per_sec: u32,
+
+    // try to refetch the corrupted data from other zone devices or meta chain
+    pub repair: bool,
+}
+
+impl Default for CyfsStackScrubParams {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            interval_secs: 60 * 60 * 24,
+            max_bytes_per_sec: 1024 * 1024 * 4,
+            max_items_per_sec: 50,
+            repair: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CyfsStackEvictPolicy {
+    // least recently used
+    Lru,
+
+    // least frequently used
+    Lfu,
+}
+
+impl FromStr for CyfsStackEvictPolicy {
+    type Err = BuckyError;
+
+    fn from_str(s: &str) -> BuckyResult<Self> {
+        match s {
+            "lru" => Ok(Self::Lru),
+            "lfu" => Ok(Self::Lfu),
+            _ => {
+                let msg = format!("unknown CyfsStackEvictPolicy value: {}", s);
+                error!("{}", msg);
+
+                Err(BuckyError::new(BuckyErrorCode::InvalidData, msg))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CyfsStackEvictParams {
+    // if enable the quota-aware eviction of cached noc objects and chunks
+    pub enable: bool,
+
+    // interval between two eviction checks
+    pub interval_secs: u64,
+
+    // eviction order of the cached noc objects, the chunks are always evicted by lru
+    pub policy: CyfsStackEvictPolicy,
+
+    // disk budget of the objects in noc with storage category cache, 0 means unlimited
+    pub noc_cache_budget: u64,
+
+    // disk budget of the objects in noc with storage category storage, 0 means unlimited
+    // the pinned objects will never be evicted, only warn if over budget
+    pub noc_storage_budget: u64,
+
+    // disk budget of the cached objects of each dec, 0 means unlimited
+    pub dec_cache_budget: u64,
+    pub dec_cache_budget_list: HashMap<ObjectId, u64>,
+
+    // disk budget of the chunk cache, 0 means unlimited
+    pub chunk_cache_budget: u64,
+
+    // the objects and chunks accessed within min_idle_secs will not be evicted
+    pub min_idle_secs: u64,
+}
+
+impl CyfsStackEvictParams {
+    pub fn get_dec_cache_budget(&self, dec_id: &ObjectId) -> u64 {
+        match self.dec_cache_budget_list.get(dec_id) {
+            Some(v) => *v,
+            None => self.dec_cache_budget,
+        }
+    }
+}
+
+impl Default for CyfsStackEvictParams {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            interval_secs: 60 * 10,
+            policy: CyfsStackEvictPolicy::Lru,
+            noc_cache_budget: 1024 * 1024 * 1024,
+            noc_storage_budget: 0,
+            dec_cache_budget: 0,
+            dec_cache_budget_list: HashMap::new(),
+            chunk_cache_budget: 1024 * 1024 * 1024 * 10,
+            min_idle_secs: 60 * 60,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CyfsStackInterfaceParams {
     // bdt协议栈监听的vport列表
@@ -128,6 +263,15 @@ pub struct CyfsStackParams {
 
     // front module config
     pub front: CyfsStackFrontParams,
+
+    // erasure module config
+    pub erasure: CyfsStackErasureParams,
+
+    // scrub module config
+    pub scrub: CyfsStackScrubParams,
+
+    // evict module config
+    pub evict: CyfsStackEvictParams,
 }
 
 impl CyfsStackParams {
@@ -139,6 +283,9 @@ impl CyfsStackParams {
             interface: CyfsStackInterfaceParams::new_empty(),
             meta: CyfsStackMetaParams::default(),
             front: CyfsStackFrontParams::default(),
+            erasure: CyfsStackErasureParams::default(),
+            scrub: CyfsStackScrubParams::default(),
+            evict: CyfsStackEvictParams::default(),
         }
     }
 
@@ -149,6 +296,9 @@ impl CyfsStackParams {
             interface: CyfsStackInterfaceParams::default(),
             meta: CyfsStackMetaParams::default(),
             front: CyfsStackFrontParams::default(),
+            erasure: CyfsStackErasureParams::default(),
+            scrub: CyfsStackScrubParams::default(),
+            evict: CyfsStackEvictParams::default(),
         }
     }
 }
@@ -264,6 +264,11 @@ impl SyncPingClient {
                 let _ = self.role_manager.notify_owner_changed().await;
             }
 
+            // active/standby模式下，主ood可能已经被standby接任了
+            if failed_count >= 5 {
+                self.role_manager.on_ood_ping_failed().await;
+            }
+
             let abort_registration;
 
             {
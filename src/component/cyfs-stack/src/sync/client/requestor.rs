@@ -268,6 +268,24 @@ impl SyncClientRequestor {
         }
     }
 
+    // 按id从目标设备获取对象，不关心同步序号
+    pub async fn get_objects(&self, list: Vec<ObjectId>) -> BuckyResult<Vec<NONObjectInfo>> {
+        let req = SyncObjectsRequest {
+            begin_seq: 0,
+            end_seq: 0,
+            list,
+        };
+
+        let resp = self.sync_objects(req).await?;
+        let list = resp
+            .objects
+            .into_iter()
+            .filter_map(|item| item.object)
+            .collect();
+
+        Ok(list)
+    }
+
     fn encode_chunks_request(&self, req: &SyncChunksRequest) -> Request {
         let url = self.service_url.join("chunks").unwrap();
 
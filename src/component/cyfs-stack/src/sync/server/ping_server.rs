@@ -24,6 +24,9 @@ struct DevicePingState {
 struct SyncPingServerState {
     timeout: u64,
     device_list: HashMap<DeviceId, DevicePingState>,
+
+    // standby ood最后一次ping的时间，作为主ood的租约，超时也不移除，只在设备不再是standby时清除
+    standby_leases: HashMap<DeviceId, u64>,
 }
 
 impl SyncPingServerState {
@@ -32,6 +35,7 @@ impl SyncPingServerState {
         Self {
             timeout,
             device_list: HashMap::new(),
+            standby_leases: HashMap::new(),
         }
     }
 
@@ -113,6 +117,28 @@ impl SyncPingServer {
         info_list
     }
 
+    // 各个standby ood最后一次ping的时间，不在standby_list里的租约会被清除
+    pub fn standby_leases(&self, standby_list: &[DeviceId]) -> HashMap<DeviceId, u64> {
+        let mut state = self.state.lock().unwrap();
+        state.standby_leases.retain(|device_id, _| {
+            let ret = standby_list.contains(device_id);
+            if !ret {
+                info!("remove stale standby lease: device={}", device_id);
+            }
+            ret
+        });
+
+        state.standby_leases.clone()
+    }
+
+    pub fn renew_standby_lease(&self, device_id: &DeviceId) {
+        self.state
+            .lock()
+            .unwrap()
+            .standby_leases
+            .insert(device_id.to_owned(), bucky_time_now());
+    }
+
     pub async fn ping(&self, ping_req: &SyncPingRequest) -> BuckyResult<SyncPingResponse> {
         let _device_state = match ping_req.state {
             DeviceSyncState::Online | DeviceSyncState::OnlineAccept => {
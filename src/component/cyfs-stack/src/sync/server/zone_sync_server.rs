@@ -11,11 +11,13 @@ use cyfs_bdt::{DeviceCache, StackGuard};
 use cyfs_core::ZoneId;
 use cyfs_lib::*;
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub(crate) struct ZoneSyncServer {
     ping_server: SyncPingServer,
     zone_state: Arc<ZoneStateManager>,
+    zone_manager: ZoneManagerRef,
 
     noc: NamedObjectCacheRef,
 
@@ -39,8 +41,12 @@ impl ZoneSyncServer {
         ood_sync_vport: u16,
         device_manager: Box<dyn DeviceCache>,
     ) -> Self {
-        let zone_state =
-            ZoneStateManager::new(zone_id, root_state.clone(), zone_manager, noc.clone());
+        let zone_state = ZoneStateManager::new(
+            zone_id,
+            root_state.clone(),
+            zone_manager.clone(),
+            noc.clone(),
+        );
         let zone_state = Arc::new(zone_state);
 
         let ping_server = SyncPingServer::new(zone_state.clone(), role_manager);
@@ -53,6 +59,7 @@ impl ZoneSyncServer {
         Self {
             ping_server,
             zone_state,
+            zone_manager,
 
             noc,
             state_sync_server,
@@ -126,7 +133,21 @@ impl ZoneSyncServer {
 
         self.zone_state.verify_source(&source).await?;
 
-        self.ping_server.ping(&ping_req).await
+        let resp = self.ping_server.ping(&ping_req).await?;
+
+        // 只有standby自己的ping才能续租，是否standby以当前zone为准，不信任ping里面上报的zone_role
+        if ping_req.state != DeviceSyncState::Offline && ping_req.device_id == source {
+            let standby_list = self.zone_manager.get_current_standby_ood_list().await?;
+            if standby_list.contains(&source) {
+                self.ping_server.renew_standby_lease(&source);
+            }
+        }
+
+        Ok(resp)
+    }
+
+    pub fn standby_leases(&self, standby_list: &[DeviceId]) -> HashMap<DeviceId, u64> {
+        self.ping_server.standby_leases(standby_list)
     }
 
     pub async fn sync_diff(
@@ -114,7 +114,9 @@ impl TransInputProcessor for TransInputTransformer {
             local_path: req.local_path,
             chunk_size: req.chunk_size, 
             chunk_method: req.chunk_method, 
+            chunk_cdc: req.chunk_cdc,
             file_id: req.file_id,
+            base_file_id: req.base_file_id,
             dirs: req.dirs,
             access: req.access,
         };
@@ -123,6 +125,7 @@ impl TransInputProcessor for TransInputTransformer {
 
         Ok(TransPublishFileInputResponse {
             file_id: out_resp.file_id,
+            diff_id: out_resp.diff_id,
         })
     }
 
@@ -233,7 +236,9 @@ impl TransOutputProcessor for TransOutputTransformer {
             local_path: req.local_path,
             chunk_size: req.chunk_size,
             chunk_method: req.chunk_method, 
+            chunk_cdc: req.chunk_cdc,
             file_id: req.file_id,
+            base_file_id: req.base_file_id,
             dirs: req.dirs,
             access: req.access,
         };
@@ -242,6 +247,7 @@ impl TransOutputProcessor for TransOutputTransformer {
 
         Ok(TransPublishFileOutputResponse {
             file_id: in_resp.file_id,
+            diff_id: in_resp.diff_id,
         })
     }
 
@@ -1,8 +1,10 @@
 use cyfs_base::*;
 use cyfs_lib::*;
+use cyfs_util::{CdcChunkParams, CdcFileSplitter};
 
 use async_std::prelude::*;
-use std::collections::{HashMap, VecDeque};
+use cyfs_chunk_cache::{ChunkManagerRef, MemChunk};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -13,6 +15,7 @@ pub struct AddFileRequest {
 pub(in crate::trans_api) struct FileRecorder {
     ndc: Box<dyn NamedDataCache>,
     tracker: Box<dyn TrackerCache>,
+    chunk_manager: ChunkManagerRef,
     noc: NamedObjectCacheRef,
     dec_id: ObjectId,
 }
@@ -22,6 +25,7 @@ impl Clone for FileRecorder {
         Self {
             ndc: self.ndc.clone(),
             tracker: self.tracker.clone(),
+            chunk_manager: self.chunk_manager.clone(),
             noc: self.noc.clone(),
             dec_id: self.dec_id.clone(),
         }
@@ -32,12 +36,14 @@ impl FileRecorder {
     pub fn new(
         ndc: Box<dyn NamedDataCache>,
         tracker: Box<dyn TrackerCache>,
+        chunk_manager: ChunkManagerRef,
         noc: NamedObjectCacheRef,
         dec_id: ObjectId,
     ) -> Self {
         Self {
             ndc,
             tracker,
+            chunk_manager,
             noc,
             dec_id,
         }
@@ -49,17 +55,41 @@ impl FileRecorder {
         source: &Path,
         chunk_size: u32, 
         chunk_method: TransPublishChunkMethod, 
+        chunk_cdc: Option<&CdcChunkParams>,
         dirs: Option<Vec<FileDirRef>>,
         access: Option<AccessString>,
     ) -> BuckyResult<FileId> {
-        let file = Self::generate_file(owner, source, chunk_size).await?;
+        let file = Self::generate_file(owner, source, chunk_size, chunk_cdc).await?;
 
         self.record_file(source, &file, dirs, chunk_method, access).await?;
 
         Ok(file.desc().file_id())
     }
 
-    async fn generate_file(owner: &ObjectId, source: &Path, chunk_size: u32) -> BuckyResult<File> {
+    async fn generate_file(
+        owner: &ObjectId,
+        source: &Path,
+        chunk_size: u32,
+        chunk_cdc: Option<&CdcChunkParams>,
+    ) -> BuckyResult<File> {
+        // 按内容切分chunk，忽略chunk_size
+        if let Some(cdc) = chunk_cdc {
+            cdc.check()?;
+
+            info!(
+                "will gen file with cdc: owner={}, path={}, cdc={:?}",
+                owner,
+                source.display(),
+                cdc
+            );
+
+            let (hash, len, chunk_list) = Self::create_cdc_chunk_list(source, cdc).await?;
+            let file = cyfs_base::File::new(owner.to_owned(), len, hash, chunk_list)
+                .no_create_time()
+                .build();
+            return Ok(file);
+        }
+
         // chunk_size不能太小
         if chunk_size < 1024 {
             let msg = format!("chunk size should >= 1024");
@@ -181,6 +211,50 @@ impl FileRecorder {
         Ok((file_hash, file_len as u64, ChunkList::ChunkInList(list)))
     }
 
+    async fn create_cdc_chunk_list(
+        source: &Path,
+        params: &CdcChunkParams,
+    ) -> BuckyResult<(HashValue, u64, ChunkList)> {
+        let mut file = async_std::fs::File::open(source).await.map_err(|e| {
+            let msg = format!(
+                "open file for calc chunk list error! file={}, {}",
+                source.display(),
+                e
+            );
+            error!("{}", msg);
+
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
+
+        use sha2::Digest;
+        let mut file_sha256 = sha2::Sha256::new();
+        let mut file_len = 0;
+        let mut list = Vec::new();
+
+        let mut splitter = CdcFileSplitter::new(params, &mut file)?;
+        while let Some(data) = splitter.next().await? {
+            let len = data.len();
+            let hash = cyfs_base::hash_data(data);
+            let chunk_id = ChunkId::new(&hash, len as u32);
+
+            debug!(
+                "got file cdc chunk: id={}, len={}, file={}, ",
+                chunk_id,
+                len,
+                source.display()
+            );
+            list.push(chunk_id);
+            file_sha256.input(data);
+            file_len += len as u64;
+        }
+
+        Ok((
+            file_sha256.result().into(),
+            file_len,
+            ChunkList::ChunkInList(list),
+        ))
+    }
+
     async fn record_file(
         &self,
         source: &Path,
@@ -261,6 +335,11 @@ impl FileRecorder {
         cur_pos: u64, 
         method: TransPublishChunkMethod
     ) -> BuckyResult<u64> {
+        // Copy模式需要先把数据写入chunk cache，然后才能在ndc里面标记为Ready
+        if let TransPublishChunkMethod::Copy = method {
+            self.copy_chunk_in_file(source, file_id, chunk_id, cur_pos).await?;
+        }
+
         // 先添加到chunk索引
         let ref_obj = ChunkObjectRef {
             object_id: file_id.object_id().to_owned(),
@@ -326,31 +405,76 @@ impl FileRecorder {
                     Ok(new_pos)
                 }
             },
-            TransPublishChunkMethod::Copy => unimplemented!(), 
-            TransPublishChunkMethod::None => Ok(new_pos)
+            TransPublishChunkMethod::Copy | TransPublishChunkMethod::None => Ok(new_pos)
         }
     }
 
-    pub async fn record_file_chunk_list(&self, source: &Path, file: &File, method: TransPublishChunkMethod) -> BuckyResult<()> {
-        let file_id = file.desc().file_id();
+    async fn copy_chunk_in_file(
+        &self,
+        source: &Path,
+        file_id: &FileId,
+        chunk_id: &ChunkId,
+        cur_pos: u64,
+    ) -> BuckyResult<()> {
+        if self.chunk_manager.exist(chunk_id).await {
+            return Ok(());
+        }
+
+        let mut file = async_std::fs::File::open(source).await.map_err(|e| {
+            let msg = format!("open file for copy chunk error! path={}, {}", source.display(), e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
 
+        let mut buf = vec![0u8; chunk_id.len()];
+        let ret = match file.seek(std::io::SeekFrom::Start(cur_pos)).await {
+            Ok(_) => file.read_exact(&mut buf).await,
+            Err(e) => Err(e),
+        };
+        ret.map_err(|e| {
+            let msg = format!(
+                "read chunk from file error! path={}, file={}, chunk={}, pos={}, {}",
+                source.display(),
+                file_id,
+                chunk_id,
+                cur_pos,
+                e
+            );
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
+
+        // 文件在构建之后可能已经被修改
+        let actual_id = ChunkId::calculate(&buf).await?;
+        if actual_id != *chunk_id {
+            let msg = format!(
+                "copy chunk but file content changed! path={}, file={}, chunk={}, got={}",
+                source.display(),
+                file_id,
+                chunk_id,
+                actual_id
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidData, msg));
+        }
+
+        self.chunk_manager
+            .put_chunk(chunk_id, Box::new(MemChunk::from(buf)))
+            .await
+            .map_err(|e| {
+                error!(
+                    "copy file chunk to chunk cache error! file={}, chunk={}, {}",
+                    file_id, chunk_id, e
+                );
+                e
+            })
+    }
+
+    fn get_chunk_list(file: &File) -> BuckyResult<Option<&Vec<ChunkId>>> {
         let chunk_list = if let Some(body) = file.body() {
             match body.content().chunk_list() {
-                ChunkList::ChunkInList(chunk_list) => {
-                    if !chunk_list.is_empty() {
-                        chunk_list
-                    } else {
-                        return Ok(());
-                    }
-                }
-                ChunkList::ChunkInBundle(bundle) => {
-                    let chunk_list = bundle.chunk_list();
-                    if !chunk_list.is_empty() {
-                        chunk_list
-                    } else {
-                        return Ok(());
-                    }
-                }
+                ChunkList::ChunkInList(chunk_list) => chunk_list,
+                ChunkList::ChunkInBundle(bundle) => bundle.chunk_list(),
                 ChunkList::ChunkInFile(_) => {
                     let msg = format!("ChunkInFile format not support!");
                     error!("{}", msg);
@@ -358,7 +482,22 @@ impl FileRecorder {
                 }
             }
         } else {
-            return Ok(());
+            return Ok(None);
+        };
+
+        if chunk_list.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(chunk_list))
+        }
+    }
+
+    pub async fn record_file_chunk_list(&self, source: &Path, file: &File, method: TransPublishChunkMethod) -> BuckyResult<()> {
+        let file_id = file.desc().file_id();
+
+        let chunk_list = match Self::get_chunk_list(file)? {
+            Some(chunk_list) => chunk_list,
+            None => return Ok(()),
         };
 
         let mut cur_pos = 0;
@@ -368,12 +507,177 @@ impl FileRecorder {
         Ok(())
     }
 
+    // 基于旧版本登记chunk，和旧版本相同并且仍然可用的chunk只添加引用关系，不再重新登记，返回复用的chunk数
+    pub async fn record_file_chunk_list_with_base(
+        &self,
+        source: &Path,
+        file: &File,
+        base_file: &File,
+        method: TransPublishChunkMethod,
+    ) -> BuckyResult<usize> {
+        let file_id = file.desc().file_id();
+
+        let chunk_list = match Self::get_chunk_list(file)? {
+            Some(chunk_list) => chunk_list,
+            None => return Ok(0),
+        };
+
+        let base_chunks: HashSet<&ChunkId> = match Self::get_chunk_list(base_file)? {
+            Some(chunk_list) => chunk_list.iter().collect(),
+            None => HashSet::new(),
+        };
+
+        let mut cur_pos = 0;
+        let mut reused = 0;
+        for chunk_id in chunk_list {
+            if base_chunks.contains(chunk_id)
+                && self.is_chunk_reusable(source, chunk_id, cur_pos, method).await?
+            {
+                self.ref_chunk_in_file(&file_id, chunk_id).await?;
+                cur_pos += chunk_id.len() as u64;
+                reused += 1;
+            } else {
+                cur_pos = self.track_chunk_in_file(source, &file_id, chunk_id, cur_pos, method).await?;
+            }
+        }
+
+        info!(
+            "record file chunk list with base success! file={}, base={}, chunks={}, reused={}",
+            file_id,
+            base_file.desc().file_id(),
+            chunk_list.len(),
+            reused
+        );
+        Ok(reused)
+    }
+
+    async fn is_chunk_reusable(
+        &self,
+        source: &Path,
+        chunk_id: &ChunkId,
+        cur_pos: u64,
+        method: TransPublishChunkMethod,
+    ) -> BuckyResult<bool> {
+        let req = GetChunkRequest {
+            chunk_id: chunk_id.to_owned(),
+            flags: 0,
+        };
+        match self.ndc.get_chunk(&req).await? {
+            Some(data) if data.state == ChunkState::Ready => {}
+            _ => return Ok(false),
+        }
+
+        match method {
+            TransPublishChunkMethod::Track => {}
+            TransPublishChunkMethod::Copy => return Ok(self.chunk_manager.exist(chunk_id).await),
+            TransPublishChunkMethod::None => return Ok(true),
+        }
+
+        let req = GetTrackerPositionRequest {
+            id: chunk_id.to_string(),
+            direction: Some(TrackerDirection::Store),
+        };
+        let list = self.tracker.get_position(&req).await?;
+
+        // 同一路径的文件可能已经被新版本覆盖，只有偏移相同的记录仍然有效
+        let path = source.to_str().unwrap();
+        let ret = list.iter().any(|item| match &item.pos {
+            TrackerPostion::FileRange(range) => {
+                range.path != path
+                    || (range.range_begin == cur_pos
+                        && range.range_end == cur_pos + chunk_id.len() as u64)
+            }
+            TrackerPostion::File(file_path) => file_path != path,
+            TrackerPostion::ChunkManager => true,
+            _ => false,
+        });
+
+        Ok(ret)
+    }
+
+    async fn ref_chunk_in_file(&self, file_id: &FileId, chunk_id: &ChunkId) -> BuckyResult<()> {
+        let req = UpdateChunkRefsRequest {
+            chunk_id: chunk_id.to_owned(),
+            add_list: vec![ChunkObjectRef {
+                object_id: file_id.object_id().to_owned(),
+                relation: ChunkObjectRelation::FileBody,
+            }],
+            remove_list: vec![],
+        };
+
+        self.ndc.update_chunk_ref_objects(&req).await.map_err(|e| {
+            error!(
+                "add file ref to chunk error! file={}, chunk={}, {}",
+                file_id, chunk_id, e
+            );
+            e
+        })
+    }
+
+    // 新版本相对旧版本的差异，diff_list为旧版本中不存在的chunk
+    pub fn build_diff(owner: &ObjectId, base_file: &File, file: &File) -> BuckyResult<Diff> {
+        let base_chunks: HashSet<&ChunkId> = match Self::get_chunk_list(base_file)? {
+            Some(chunk_list) => chunk_list.iter().collect(),
+            None => HashSet::new(),
+        };
+
+        let mut diff_list = vec![];
+        if let Some(chunk_list) = Self::get_chunk_list(file)? {
+            let mut added = HashSet::new();
+            for chunk_id in chunk_list {
+                if !base_chunks.contains(chunk_id) && added.insert(chunk_id) {
+                    diff_list.push(chunk_id.to_owned());
+                }
+            }
+        }
+
+        let diff = Diff::new_with_base(base_file.desc().file_id(), file.desc().file_id(), diff_list)
+            .owner(owner.to_owned())
+            .no_create_time()
+            .build();
+        Ok(diff)
+    }
+
+    pub async fn add_diff_to_noc(&self, diff: &Diff) -> BuckyResult<()> {
+        let diff_id = diff.desc().diff_id();
+        let object_raw = diff.to_vec()?;
+        let object = Arc::new(AnyNamedObject::Standard(StandardObject::Diff(diff.clone())));
+        let object = NONObjectInfo::new(diff_id.object_id().to_owned(), object_raw, Some(object));
+
+        let req = NamedObjectCachePutObjectRequest {
+            source: RequestSourceInfo::new_local_dec(Some(self.dec_id.clone())),
+            object,
+            storage_category: NamedObjectStorageCategory::Storage,
+            context: None,
+            last_access_rpath: None,
+            access_string: None,
+        };
+
+        match self.noc.put_object(&req).await {
+            Ok(resp) => {
+                info!(
+                    "insert diff object to noc success: diff={}, file={}, diff_chunks={}, {:?}",
+                    diff_id,
+                    diff.desc().content().file_id(),
+                    diff.desc().content().diff_list().len(),
+                    resp.result
+                );
+                Ok(())
+            }
+            Err(e) => {
+                error!("insert diff object to noc failed: {} {}", diff_id, e);
+                Err(e)
+            }
+        }
+    }
+
     pub async fn add_dir(
         &self,
         owner: &ObjectId,
         source: &Path,
         chunk_size: u32,
         chunk_method: TransPublishChunkMethod, 
+        chunk_cdc: Option<&CdcChunkParams>,
         access: Option<AccessString>,
     ) -> BuckyResult<(DirId, Vec<(FileId, PathBuf)>)> {
         let mut scaner = DirScaner::new(source);
@@ -386,7 +690,7 @@ impl FileRecorder {
         let mut file_list = Vec::new();
         let mut file_id_list = Vec::new();
         for file_path in file_path_list {
-            let file = Self::generate_file(owner, &file_path, chunk_size).await?;
+            let file = Self::generate_file(owner, &file_path, chunk_size, chunk_cdc).await?;
             let file_id = file.desc().file_id();
             let buf = file.to_vec().map_err(|e| {
                 let msg = format!(
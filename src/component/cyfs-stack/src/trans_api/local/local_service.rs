@@ -75,6 +75,7 @@ impl LocalTransService {
             task_manager.clone(),
             named_data_components.ndc.clone(),
             named_data_components.tracker.clone(),
+            named_data_components.chunk_manager.clone(),
             noc.clone(),
             bdt_stack.local_device_id().clone(),
         );
@@ -155,6 +156,7 @@ impl LocalTransService {
                 let file_recorder = FileRecorder::new(
                     self.named_data_components.ndc.clone(),
                     self.named_data_components.tracker.clone(),
+                    self.named_data_components.chunk_manager.clone(),
                     self.noc.clone(),
                     req.common.source.dec.clone(),
                 );
@@ -171,6 +173,7 @@ impl LocalTransService {
             }
             Ok(TransPublishFileInputResponse {
                 file_id: req.file_id.clone().unwrap(),
+                diff_id: None,
             })
         } else if req.local_path.is_file() {
             self.add_file_impl(req).await
@@ -213,7 +216,32 @@ impl LocalTransService {
         } else {
             None
         };
-        let file_id = self
+
+        // 基于旧版本发布，旧版本必须已经存在于noc
+        let base_file = if let Some(base_file_id) = &req.base_file_id {
+            let resp = self
+                .noc
+                .get_object(&NamedObjectCacheGetObjectRequest {
+                    source: req.common.source.clone(),
+                    object_id: base_file_id.clone(),
+                    last_access_rpath: None,
+                    flags: 0,
+                })
+                .await?;
+
+            match resp {
+                Some(resp) => Some(File::clone_from_slice(resp.object.object_raw.as_slice())?),
+                None => {
+                    let msg = format!("publish file but base file not found! base={}", base_file_id);
+                    error!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+                }
+            }
+        } else {
+            None
+        };
+
+        let (file_id, diff_id) = self
             .publish_manager
             .publish_local_file(
                 req.common.source.zone.device.unwrap(),
@@ -221,16 +249,19 @@ impl LocalTransService {
                 req.local_path.to_string_lossy().to_string(),
                 req.owner.clone(),
                 file,
+                base_file,
                 req.chunk_size, 
                 req.chunk_method, 
+                req.chunk_cdc,
                 req.access,
             )
             .await?;
 
         let resp = TransPublishFileInputResponse {
             file_id: file_id.object_id().to_owned(),
+            diff_id: diff_id.map(|id| id.object_id().to_owned()),
         };
-        info!("trans add file success! file={}", resp.file_id);
+        info!("trans add file success! file={}, diff={:?}", resp.file_id, resp.diff_id);
 
         Ok(resp)
     }
@@ -241,6 +272,12 @@ impl LocalTransService {
     ) -> BuckyResult<TransPublishFileInputResponse> {
         info!("trans recv add dir request: {:?}", req);
 
+        if req.base_file_id.is_some() {
+            let msg = format!("publish dir with base file not support! path={}", req.local_path.display());
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotSupport, msg));
+        }
+
         let dir_id = self
             .publish_manager
             .publish_local_dir(
@@ -251,11 +288,15 @@ impl LocalTransService {
                 req.file_id,
                 req.chunk_size, 
                 req.chunk_method, 
+                req.chunk_cdc,
                 req.access,
             )
             .await?;
 
-        let resp = TransPublishFileInputResponse { file_id: dir_id };
+        let resp = TransPublishFileInputResponse {
+            file_id: dir_id,
+            diff_id: None,
+        };
         info!("trans add dir success! file={}", resp.file_id);
 
         Ok(resp)
@@ -1,10 +1,12 @@
 use crate::trans_api::local::FileRecorder;
 use crate::util_api::{BuildDirParams, BuildDirTaskStatus, BuildFileParams, BuildFileTaskStatus};
 use cyfs_base::*;
+use cyfs_chunk_cache::ChunkManagerRef;
 use cyfs_debug::Mutex;
 use cyfs_lib::*;
 use cyfs_task_manager::*;
 use cyfs_util::cache::{NamedDataCache, TrackerCache};
+use cyfs_util::CdcChunkParams;
 use sha2::Digest;
 use std::path::Path;
 use std::sync::Arc;
@@ -18,6 +20,7 @@ pub struct PublishLocalFile {
     file: File,
     chunk_size: u32, 
     chunk_method: TransPublishChunkMethod, 
+    base_file: Option<File>,
 }
 
 impl ProtobufTransform<super::trans_proto::PublishLocalFile> for PublishLocalFile {
@@ -30,7 +33,11 @@ impl ProtobufTransform<super::trans_proto::PublishLocalFile> for PublishLocalFil
             dec_id: ObjectId::clone_from_slice(value.dec_id.as_slice())?,
             file: File::clone_from_slice(value.file.as_slice())?,
             chunk_size: value.chunk_size, 
-            chunk_method: value.chunk_method.map(|v| TransPublishChunkMethod::try_from(v as u8)).unwrap_or(Ok(TransPublishChunkMethod::default()))?
+            chunk_method: value.chunk_method.map(|v| TransPublishChunkMethod::try_from(v as u8)).unwrap_or(Ok(TransPublishChunkMethod::default()))?,
+            base_file: match value.base_file {
+                Some(v) => Some(File::clone_from_slice(v.as_slice())?),
+                None => None,
+            },
         })
     }
 }
@@ -43,7 +50,11 @@ impl ProtobufTransform<&PublishLocalFile> for super::trans_proto::PublishLocalFi
             dec_id: value.dec_id.as_slice().to_vec(),
             file: value.file.to_vec()?,
             chunk_size: value.chunk_size, 
-            chunk_method: Some(Into::<u8>::into(value.chunk_method) as i32)
+            chunk_method: Some(Into::<u8>::into(value.chunk_method) as i32),
+            base_file: match &value.base_file {
+                Some(v) => Some(v.to_vec()?),
+                None => None,
+            },
         })
     }
 }
@@ -61,6 +72,7 @@ struct PublishLocalFileTask {
     task_id: TaskId,
     ndc: Box<dyn NamedDataCache>,
     tracker: Box<dyn TrackerCache>,
+    chunk_manager: ChunkManagerRef,
     noc: NamedObjectCacheRef,
     dec_id: ObjectId,
     local_path: String,
@@ -68,6 +80,7 @@ struct PublishLocalFileTask {
     file: File,
     chunk_size: u32,
     chunk_method: TransPublishChunkMethod, 
+    base_file: Option<File>,
     task_state: Mutex<PublishLocalFileTaskStatus>,
 }
 
@@ -78,8 +91,10 @@ impl PublishLocalFileTask {
         file: File,
         chunk_size: u32, 
         chunk_method: TransPublishChunkMethod, 
+        base_file: Option<File>,
         ndc: Box<dyn NamedDataCache>,
         tracker: Box<dyn TrackerCache>,
+        chunk_manager: ChunkManagerRef,
         noc: NamedObjectCacheRef,
         dec_id: ObjectId,
     ) -> Self {
@@ -93,6 +108,7 @@ impl PublishLocalFileTask {
             task_id,
             ndc,
             tracker,
+            chunk_manager,
             noc,
             dec_id,
             local_path,
@@ -100,6 +116,7 @@ impl PublishLocalFileTask {
             file,
             chunk_size, 
             chunk_method, 
+            base_file,
             task_state: Mutex::new(PublishLocalFileTaskStatus::Stopped),
         }
     }
@@ -134,26 +151,49 @@ impl Runnable for PublishLocalFileTask {
         let file_recorder = FileRecorder::new(
             self.ndc.clone(),
             self.tracker.clone(),
+            self.chunk_manager.clone(),
             self.noc.clone(),
             self.dec_id.clone(),
         );
 
+        let local_path = Path::new(self.local_path.as_str());
+        let ret = match &self.base_file {
+            Some(base_file) => file_recorder
+                .record_file_chunk_list_with_base(local_path, &self.file, base_file, self.chunk_method)
+                .await
+                .map(|_| ()),
+            None => {
+                file_recorder
+                    .record_file_chunk_list(local_path, &self.file, self.chunk_method)
+                    .await
+            }
+        };
+        ret.map_err(|e| {
+            let mut state = self.task_state.lock().unwrap();
+            *state = PublishLocalFileTaskStatus::Failed(e.clone());
+            e
+        })?;
         file_recorder
-            .record_file_chunk_list(Path::new(self.local_path.as_str()), &self.file, self.chunk_method)
+            .add_file_to_ndc(&self.file, None)
             .await
             .map_err(|e| {
                 let mut state = self.task_state.lock().unwrap();
                 *state = PublishLocalFileTaskStatus::Failed(e.clone());
                 e
             })?;
-        file_recorder
-            .add_file_to_ndc(&self.file, None)
-            .await
-            .map_err(|e| {
+
+        // 记录新旧版本之间的差异，持有旧版本的一方只需要获取差异部分的chunk
+        if let Some(base_file) = &self.base_file {
+            let ret = match FileRecorder::build_diff(&self.owner, base_file, &self.file) {
+                Ok(diff) => file_recorder.add_diff_to_noc(&diff).await,
+                Err(e) => Err(e),
+            };
+            ret.map_err(|e| {
                 let mut state = self.task_state.lock().unwrap();
                 *state = PublishLocalFileTaskStatus::Failed(e.clone());
                 e
             })?;
+        }
 
         let mut state = self.task_state.lock().unwrap();
         *state = PublishLocalFileTaskStatus::Finished;
@@ -170,6 +210,7 @@ impl Runnable for PublishLocalFileTask {
 struct PublishLocalFileTaskFactory {
     ndc: Box<dyn NamedDataCache>,
     tracker: Box<dyn TrackerCache>,
+    chunk_manager: ChunkManagerRef,
     noc: NamedObjectCacheRef,
 }
 
@@ -177,9 +218,10 @@ impl PublishLocalFileTaskFactory {
     pub(crate) fn new(
         ndc: Box<dyn NamedDataCache>,
         tracker: Box<dyn TrackerCache>,
+        chunk_manager: ChunkManagerRef,
         noc: NamedObjectCacheRef,
     ) -> Self {
-        Self { ndc, tracker, noc }
+        Self { ndc, tracker, chunk_manager, noc }
     }
 }
 
@@ -198,8 +240,10 @@ impl TaskFactory for PublishLocalFileTaskFactory {
             params.file,
             params.chunk_size, 
             params.chunk_method, 
+            params.base_file,
             self.ndc.clone(),
             self.tracker.clone(),
+            self.chunk_manager.clone(),
             self.noc.clone(),
             params.dec_id, 
         );
@@ -220,8 +264,10 @@ impl TaskFactory for PublishLocalFileTaskFactory {
             params.file,
             params.chunk_size, 
             params.chunk_method, 
+            params.base_file,
             self.ndc.clone(),
             self.tracker.clone(),
+            self.chunk_manager.clone(),
             self.noc.clone(),
             params.dec_id,
         );
@@ -268,6 +314,7 @@ struct PublishLocalDirTask {
     task_id: TaskId,
     ndc: Box<dyn NamedDataCache>,
     tracker: Box<dyn TrackerCache>,
+    chunk_manager: ChunkManagerRef,
     noc: NamedObjectCacheRef, 
     dec_id: ObjectId,
     local_path: String,
@@ -282,6 +329,7 @@ impl PublishLocalDirTask {
         root_id: ObjectId,
         ndc: Box<dyn NamedDataCache>,
         tracker: Box<dyn TrackerCache>,
+        chunk_manager: ChunkManagerRef,
         noc: NamedObjectCacheRef,
         dec_id: ObjectId, 
         chunk_method: TransPublishChunkMethod, 
@@ -296,6 +344,7 @@ impl PublishLocalDirTask {
             task_id,
             ndc,
             tracker,
+            chunk_manager,
             noc,
             dec_id,
             local_path,
@@ -342,6 +391,7 @@ impl PublishLocalDirTask {
                                 let file_recorder = FileRecorder::new(
                                     self.ndc.clone(),
                                     self.tracker.clone(),
+                                    self.chunk_manager.clone(),
                                     self.noc.clone(),
                                     self.dec_id.clone(),
                                 );
@@ -419,6 +469,7 @@ impl Runnable for PublishLocalDirTask {
 struct PublishLocalDirTaskFactory {
     ndc: Box<dyn NamedDataCache>,
     tracker: Box<dyn TrackerCache>,
+    chunk_manager: ChunkManagerRef,
     noc: NamedObjectCacheRef,
 }
 
@@ -426,9 +477,10 @@ impl PublishLocalDirTaskFactory {
     pub(crate) fn new(
         ndc: Box<dyn NamedDataCache>,
         tracker: Box<dyn TrackerCache>,
+        chunk_manager: ChunkManagerRef,
         noc: NamedObjectCacheRef,
     ) -> Self {
-        Self { ndc, tracker, noc }
+        Self { ndc, tracker, chunk_manager, noc }
     }
 }
 
@@ -446,6 +498,7 @@ impl TaskFactory for PublishLocalDirTaskFactory {
             params.root_id,
             self.ndc.clone(),
             self.tracker.clone(),
+            self.chunk_manager.clone(),
             self.noc.clone(),
             params.dec_id, 
             params.chunk_method
@@ -466,6 +519,7 @@ impl TaskFactory for PublishLocalDirTaskFactory {
             params.root_id,
             self.ndc.clone(),
             self.tracker.clone(),
+            self.chunk_manager.clone(),
             self.noc.clone(),
             params.dec_id, 
             params.chunk_method
@@ -484,6 +538,7 @@ impl PublishManager {
         task_manager: Arc<TaskManager>,
         ndc: Box<dyn NamedDataCache>,
         tracker: Box<dyn TrackerCache>,
+        chunk_manager: ChunkManagerRef,
         noc: NamedObjectCacheRef,
         device_id: DeviceId,
     ) -> Self {
@@ -491,11 +546,12 @@ impl PublishManager {
             .register_task_factory(PublishLocalDirTaskFactory::new(
                 ndc.clone(),
                 tracker.clone(),
+                chunk_manager.clone(),
                 noc.clone(),
             ))
             .unwrap();
         task_manager
-            .register_task_factory(PublishLocalFileTaskFactory::new(ndc, tracker, noc))
+            .register_task_factory(PublishLocalFileTaskFactory::new(ndc, tracker, chunk_manager, noc))
             .unwrap();
 
         let tmp_task_manager = task_manager.clone();
@@ -530,10 +586,12 @@ impl PublishManager {
         local_path: String,
         owner: ObjectId,
         file: Option<File>,
+        base_file: Option<File>,
         chunk_size: u32, 
         chunk_method: TransPublishChunkMethod, 
+        chunk_cdc: Option<CdcChunkParams>,
         access: Option<AccessString>,
-    ) -> BuckyResult<FileId> {
+    ) -> BuckyResult<(FileId, Option<DiffId>)> {
         let file = if file.is_none() {
             let params = BuildFileParams {
                 local_path: local_path.clone(),
@@ -541,6 +599,7 @@ impl PublishManager {
                 dec_id: dec_id.clone(),
                 chunk_size, 
                 chunk_method, 
+                chunk_cdc,
                 access: access.map(|v| v.value()),
             };
             let task_id = self
@@ -576,13 +635,18 @@ impl PublishManager {
         };
 
         let file_id = file.desc().file_id();
+        let diff_id = match &base_file {
+            Some(base_file) => Some(FileRecorder::build_diff(&owner, base_file, &file)?.desc().diff_id()),
+            None => None,
+        };
         let params = PublishLocalFile {
             local_path: local_path.clone(),
             owner,
             dec_id: dec_id.clone(),
             file,
             chunk_size, 
-            chunk_method
+            chunk_method,
+            base_file,
         };
 
         let task_id = self
@@ -604,10 +668,10 @@ impl PublishManager {
         match state {
             PublishLocalFileTaskStatus::Finished => {
                 info!(
-                    "publish local file success! path={}, chunk_size={}, file={}",
-                    local_path, chunk_size, file_id
+                    "publish local file success! path={}, chunk_size={}, file={}, diff={:?}",
+                    local_path, chunk_size, file_id, diff_id
                 );
-                Ok(file_id)
+                Ok((file_id, diff_id))
             }
             PublishLocalFileTaskStatus::Failed(err) => {
                 let msg = format!(
@@ -632,6 +696,7 @@ impl PublishManager {
         dir: Option<ObjectId>,
         chunk_size: u32, 
         chunk_method: TransPublishChunkMethod, 
+        chunk_cdc: Option<CdcChunkParams>,
         access: Option<AccessString>,
     ) -> BuckyResult<ObjectId> {
         let root_id = if dir.is_none() {
@@ -641,6 +706,7 @@ impl PublishManager {
                 dec_id: dec_id.clone(),
                 chunk_size, 
                 chunk_method, 
+                chunk_cdc,
                 access: access.map(|v| v.value()),
                 device_id: self.device_id.object_id().clone(),
             };
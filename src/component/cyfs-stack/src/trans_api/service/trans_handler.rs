@@ -231,7 +231,9 @@ impl TransRequestHandler {
             local_path: JsonCodecHelper::decode_string_field(&body, "local_path")?,
             chunk_size: JsonCodecHelper::decode_int_field(&body, "chunk_size")?, 
             chunk_method: JsonCodecHelper::decode_option_string_field(&body, "chunk_method")?.unwrap_or(TransPublishChunkMethod::Track),
+            chunk_cdc: JsonCodecHelper::decode_option_field(&body, "chunk_cdc")?,
             file_id: JsonCodecHelper::decode_option_string_field(&body, "file_id")?,
+            base_file_id: JsonCodecHelper::decode_option_string_field(&body, "base_file_id")?,
             dirs: JsonCodecHelper::decode_option_array_field(&body, "dirs")?,
             access,
         };
@@ -24,6 +24,9 @@ pub trait UtilInputProcessor: Sync + Send + 'static {
     async fn get_noc_info(&self, req: UtilGetNOCInfoInputRequest)
         -> BuckyResult<UtilGetNOCInfoInputResponse>;
 
+    async fn get_scrub_status(&self, req: UtilGetScrubStatusInputRequest)
+        -> BuckyResult<UtilGetScrubStatusInputResponse>;
+
     async fn get_network_access_info(&self, req: UtilGetNetworkAccessInfoInputRequest)
         -> BuckyResult<UtilGetNetworkAccessInfoInputResponse>;
 
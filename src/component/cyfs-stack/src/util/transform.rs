@@ -122,6 +122,23 @@ impl UtilInputTransformer {
         Ok(resp)
     }
 
+    async fn get_scrub_status(
+        &self,
+        req: UtilGetScrubStatusInputRequest,
+    ) -> BuckyResult<UtilGetScrubStatusInputResponse> {
+        let out_req = UtilGetScrubStatusOutputRequest {
+            common: Self::convert_common(req.common),
+        };
+
+        let out_resp = self.processor.get_scrub_status(out_req).await?;
+
+        let resp = UtilGetScrubStatusInputResponse {
+            status: out_resp.status,
+        };
+
+        Ok(resp)
+    }
+
     async fn get_network_access_info(
         &self,
         req: UtilGetNetworkAccessInfoInputRequest,
@@ -214,6 +231,7 @@ impl UtilInputTransformer {
             owner: req.owner,
             chunk_size: req.chunk_size,
             chunk_method: req.chunk_method,
+            chunk_cdc: req.chunk_cdc,
             access: req.access,
         };
 
@@ -273,6 +291,13 @@ impl UtilInputProcessor for UtilInputTransformer {
         Self::get_noc_info(&self, req).await
     }
 
+    async fn get_scrub_status(
+        &self,
+        req: UtilGetScrubStatusInputRequest,
+    ) -> BuckyResult<UtilGetScrubStatusInputResponse> {
+        Self::get_scrub_status(&self, req).await
+    }
+
     async fn get_network_access_info(
         &self,
         req: UtilGetNetworkAccessInfoInputRequest,
@@ -425,6 +450,19 @@ impl UtilOutputProcessor for UtilOutputTransformer {
         Ok(resp)
     }
 
+    async fn get_scrub_status(
+        &self,
+        req: UtilGetScrubStatusOutputRequest,
+    ) -> BuckyResult<UtilGetScrubStatusOutputResponse> {
+        let in_req = UtilGetScrubStatusInputRequest {
+            common: self.convert_common(req.common),
+        };
+
+        let resp = self.processor.get_scrub_status(in_req).await?;
+
+        Ok(resp)
+    }
+
     async fn get_network_access_info(
         &self,
         req: UtilGetNetworkAccessInfoOutputRequest,
@@ -501,6 +539,7 @@ impl UtilOutputProcessor for UtilOutputTransformer {
             owner: req.owner,
             chunk_size: req.chunk_size,
             chunk_method: req.chunk_method,
+            chunk_cdc: req.chunk_cdc,
             access: req.access,
         };
 
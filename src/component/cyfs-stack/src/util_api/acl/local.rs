@@ -84,6 +84,15 @@ impl UtilInputProcessor for UtilAclInnerInputProcessor {
         self.next.get_noc_info(req).await
     }
 
+    async fn get_scrub_status(
+        &self,
+        req: UtilGetScrubStatusInputRequest,
+    ) -> BuckyResult<UtilGetScrubStatusInputResponse> {
+        self.check_local_zone_permit("util.get_scrub_status", &req.common.source)?;
+
+        self.next.get_scrub_status(req).await
+    }
+
     async fn get_network_access_info(
         &self,
         req: UtilGetNetworkAccessInfoInputRequest,
@@ -20,6 +20,7 @@ pub struct BuildDirParams {
     pub dec_id: ObjectId,
     pub chunk_size: u32, 
     pub chunk_method: TransPublishChunkMethod, 
+    pub chunk_cdc: Option<CdcChunkParams>,
     pub device_id: ObjectId,
     pub access: Option<u32>,
 }
@@ -35,6 +36,7 @@ impl ProtobufTransform<super::util_proto::BuildDirParams> for BuildDirParams {
             dec_id: ObjectId::clone_from_slice(value.dec_id.as_slice())?,
             chunk_size: value.chunk_size, 
             chunk_method: value.chunk_method.map(|v| TransPublishChunkMethod::try_from(v as u8)).unwrap_or(Ok(TransPublishChunkMethod::default()))?, 
+            chunk_cdc: value.chunk_cdc.map(|v| CdcChunkParams::new(v.min_size, v.avg_size, v.max_size)),
             device_id: ObjectId::clone_from_slice(value.device_id.as_slice())?,
             access: value.access
         })
@@ -49,6 +51,11 @@ impl ProtobufTransform<&BuildDirParams> for super::util_proto::BuildDirParams {
             dec_id: value.dec_id.as_slice().to_vec(),
             chunk_size: value.chunk_size, 
             chunk_method: Some(Into::<u8>::into(value.chunk_method) as i32), 
+            chunk_cdc: value.chunk_cdc.map(|v| super::util_proto::CdcChunkParams {
+                min_size: v.min_size,
+                avg_size: v.avg_size,
+                max_size: v.max_size,
+            }),
             device_id: value.device_id.as_slice().to_vec(),
             access: value.access
         })
@@ -82,6 +89,7 @@ impl TaskFactory for BuildDirTaskFactory {
             params.dec_id,
             params.chunk_size, 
             params.chunk_method, 
+            params.chunk_cdc,
             params.access,
             self.task_manager.clone(),
             DeviceId::try_from(params.device_id)?,
@@ -105,6 +113,7 @@ impl TaskFactory for BuildDirTaskFactory {
             params.dec_id,
             params.chunk_size, 
             params.chunk_method, 
+            params.chunk_cdc,
             params.access,
             self.task_manager.clone(),
             DeviceId::try_from(params.device_id)?,
@@ -424,6 +433,7 @@ pub struct BuildDirTask {
     dec_id: ObjectId,
     chunk_size: u32, 
     chunk_method: TransPublishChunkMethod, 
+    chunk_cdc: Option<CdcChunkParams>,
     device_id: DeviceId,
     access: Option<u32>,
     noc: NamedObjectCacheRef,
@@ -439,6 +449,7 @@ impl BuildDirTask {
         dec_id: ObjectId,
         chunk_size: u32, 
         chunk_method: TransPublishChunkMethod, 
+        chunk_cdc: Option<CdcChunkParams>,
         access: Option<u32>,
         task_manager: Weak<TaskManager>,
         device_id: DeviceId,
@@ -449,6 +460,11 @@ impl BuildDirTask {
         sha2.input(owner.as_slice());
         sha2.input(dec_id.as_slice());
         sha2.input(chunk_size.to_be_bytes());
+        if let Some(cdc) = &chunk_cdc {
+            sha2.input(cdc.min_size.to_be_bytes());
+            sha2.input(cdc.avg_size.to_be_bytes());
+            sha2.input(cdc.max_size.to_be_bytes());
+        }
         sha2.input(BUILD_DIR_TASK.into().to_be_bytes());
         let task_id: TaskId = sha2.result().into();
         Self {
@@ -459,6 +475,7 @@ impl BuildDirTask {
             dec_id,
             chunk_size, 
             chunk_method, 
+            chunk_cdc,
             access,
             device_id,
             noc,
@@ -474,6 +491,7 @@ impl BuildDirTask {
         dec_id: ObjectId,
         chunk_size: u32, 
         chunk_method: TransPublishChunkMethod, 
+        chunk_cdc: Option<CdcChunkParams>,
         access: Option<u32>,
         task_manager: Weak<TaskManager>,
         device_id: DeviceId,
@@ -485,6 +503,11 @@ impl BuildDirTask {
         sha2.input(owner.as_slice());
         sha2.input(dec_id.as_slice());
         sha2.input(chunk_size.to_be_bytes());
+        if let Some(cdc) = &chunk_cdc {
+            sha2.input(cdc.min_size.to_be_bytes());
+            sha2.input(cdc.avg_size.to_be_bytes());
+            sha2.input(cdc.max_size.to_be_bytes());
+        }
         sha2.input(BUILD_DIR_TASK.into().to_be_bytes());
         let task_id: TaskId = sha2.result().into();
 
@@ -496,6 +519,7 @@ impl BuildDirTask {
             dec_id,
             chunk_size, 
             chunk_method, 
+            chunk_cdc,
             access,
             device_id,
             noc,
@@ -552,6 +576,7 @@ impl BuildDirTask {
                 dec_id: self.dec_id.clone(),
                 chunk_size: self.chunk_size, 
                 chunk_method: self.chunk_method, 
+                chunk_cdc: self.chunk_cdc.clone(),
                 access: self.access.clone(),
             };
 
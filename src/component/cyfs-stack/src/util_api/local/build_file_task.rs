@@ -13,6 +13,7 @@ pub struct BuildFileParams {
     pub dec_id: ObjectId,
     pub chunk_size: u32, 
     pub chunk_method: TransPublishChunkMethod, 
+    pub chunk_cdc: Option<CdcChunkParams>,
     pub access: Option<u32>,
 }
 
@@ -26,6 +27,7 @@ impl ProtobufTransform<super::util_proto::BuildFileParams> for BuildFileParams {
             dec_id: ObjectId::clone_from_slice(value.dec_id.as_slice())?,
             chunk_size: value.chunk_size, 
             chunk_method: value.chunk_method.map(|v| TransPublishChunkMethod::try_from(v as u8)).unwrap_or(Ok(TransPublishChunkMethod::default()))?, 
+            chunk_cdc: value.chunk_cdc.map(|v| CdcChunkParams::new(v.min_size, v.avg_size, v.max_size)),
             access: value.access
         })
     }
@@ -39,6 +41,11 @@ impl ProtobufTransform<&BuildFileParams> for super::util_proto::BuildFileParams
             dec_id: value.dec_id.as_slice().to_vec(),
             chunk_size: value.chunk_size, 
             chunk_method: Some(Into::<u8>::into(value.chunk_method) as i32), 
+            chunk_cdc: value.chunk_cdc.map(|v| super::util_proto::CdcChunkParams {
+                min_size: v.min_size,
+                avg_size: v.avg_size,
+                max_size: v.max_size,
+            }),
             access: value.access
         })
     }
@@ -70,6 +77,7 @@ impl TaskFactory for BuildFileTaskFactory {
             params.dec_id,
             params.chunk_size,
             params.chunk_method, 
+            params.chunk_cdc,
             params.access,
             self.noc.clone(),
             self.ndc.clone(),
@@ -95,6 +103,7 @@ impl TaskFactory for BuildFileTaskFactory {
             params.dec_id,
             params.chunk_size, 
             params.chunk_method, 
+            params.chunk_cdc,
             params.access,
             task_state,
             self.noc.clone(),
@@ -225,6 +234,7 @@ pub struct BuildFileTask {
     dec_id: ObjectId,
     chunk_size: u32, 
     chunk_method: TransPublishChunkMethod, 
+    chunk_cdc: Option<CdcChunkParams>,
     access: Option<u32>,
     noc: NamedObjectCacheRef,
     ndc: Box<dyn NamedDataCache>,
@@ -237,6 +247,7 @@ impl BuildFileTask {
         dec_id: ObjectId,
         chunk_size: u32, 
         chunk_method: TransPublishChunkMethod, 
+        chunk_cdc: Option<CdcChunkParams>,
         access: Option<u32>,
         noc: NamedObjectCacheRef,
         ndc: Box<dyn NamedDataCache>,
@@ -246,6 +257,11 @@ impl BuildFileTask {
         sha2.input(owner.as_slice());
         sha2.input(dec_id.as_slice());
         sha2.input(chunk_size.to_be_bytes());
+        if let Some(cdc) = &chunk_cdc {
+            sha2.input(cdc.min_size.to_be_bytes());
+            sha2.input(cdc.avg_size.to_be_bytes());
+            sha2.input(cdc.max_size.to_be_bytes());
+        }
         sha2.input(BUILD_FILE_TASK.into().to_be_bytes());
         let task_id: TaskId = sha2.result().into();
 
@@ -261,6 +277,7 @@ impl BuildFileTask {
             dec_id,
             chunk_size, 
             chunk_method, 
+            chunk_cdc,
             access,
             noc,
             ndc,
@@ -273,6 +290,7 @@ impl BuildFileTask {
         dec_id: ObjectId,
         chunk_size: u32, 
         chunk_method: TransPublishChunkMethod, 
+        chunk_cdc: Option<CdcChunkParams>,
         access: Option<u32>,
         task_state: FileTaskState,
         noc: NamedObjectCacheRef,
@@ -283,6 +301,11 @@ impl BuildFileTask {
         sha2.input(owner.as_slice());
         sha2.input(dec_id.as_slice());
         sha2.input(chunk_size.to_be_bytes());
+        if let Some(cdc) = &chunk_cdc {
+            sha2.input(cdc.min_size.to_be_bytes());
+            sha2.input(cdc.avg_size.to_be_bytes());
+            sha2.input(cdc.max_size.to_be_bytes());
+        }
         sha2.input(BUILD_FILE_TASK.into().to_be_bytes());
         let task_id: TaskId = sha2.result().into();
 
@@ -295,20 +318,34 @@ impl BuildFileTask {
             dec_id,
             chunk_size, 
             chunk_method, 
+            chunk_cdc,
             access,
             noc,
             ndc,
         }
     }
 
+    fn is_same_chunk_list(left: &File, right: &File) -> bool {
+        match (left.body().as_ref(), right.body().as_ref()) {
+            (Some(left), Some(right)) => {
+                let left = left.content().inner_chunk_list();
+                left.is_some() && left == right.content().inner_chunk_list()
+            }
+            _ => false,
+        }
+    }
+
     async fn run_inner(&self) -> BuckyResult<File> {
         self.task_state.get_state_mut().await.status = BuildFileTaskStatus::Running;
-        let builder = FileObjectBuilder::<TaskState>::new(
+        let mut builder = FileObjectBuilder::<TaskState>::new(
             self.local_path.clone(),
             self.owner.clone(),
             self.chunk_size,
             None,
         );
+        if let Some(cdc) = &self.chunk_cdc {
+            builder = builder.cdc(cdc.clone());
+        }
         let file = match builder.build().await {
             Ok(file) => file,
             Err(e) => {
@@ -332,9 +369,18 @@ impl BuildFileTask {
                     flags: 0,
                 })
                 .await?;
-            if let Some(file) = file {
-                if let Ok(file) = File::clone_from_slice(file.object.object_raw.as_slice()) {
-                    return Ok(file);
+            if let Some(exists) = file {
+                if let Ok(exists) = File::clone_from_slice(exists.object.object_raw.as_slice()) {
+                    // 同样hash的文件可能是用另一种切分方式(固定大小或者cdc)生成的，chunk列表一致才能复用
+                    if Self::is_same_chunk_list(&exists, &file) {
+                        return Ok(exists);
+                    }
+
+                    info!(
+                        "file with same hash exists but chunk list unmatch, will not reuse! path={}, exists={}",
+                        self.local_path,
+                        exists.desc().file_id()
+                    );
                 }
             }
         }
@@ -651,6 +697,7 @@ mod build_file_task_test {
                 dec_id: dec_id.to_owned(),
                 access: None,
                 chunk_method: TransPublishChunkMethod::default(),
+                chunk_cdc: None,
             };
             let task = task_manager
                 .create_task(
@@ -692,6 +739,7 @@ mod build_file_task_test {
                 dec_id: dec_id.to_owned(),
                 access: None,
                 chunk_method: TransPublishChunkMethod::default(),
+                chunk_cdc: None,
             };
             let task = task_manager
                 .create_task(
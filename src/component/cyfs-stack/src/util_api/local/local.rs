@@ -2,6 +2,7 @@ use super::bdt_access_info::BdtNetworkAccessInfoManager;
 use super::dir_helper::*;
 use crate::config::StackGlobalConfig;
 use crate::resolver::OodResolver;
+use crate::scrub::ScrubManager;
 use crate::sync::DeviceSyncClient;
 use crate::util::*;
 use crate::zone::*;
@@ -29,6 +30,8 @@ pub(crate) struct UtilLocalService {
 
     sync_client: Arc<OnceCell<Arc<DeviceSyncClient>>>,
 
+    scrub: Arc<OnceCell<ScrubManager>>,
+
     access_info_manager: BdtNetworkAccessInfoManager,
 
     task_manager: Arc<TaskManager>,
@@ -44,6 +47,7 @@ impl Clone for UtilLocalService {
             zone_manager: self.zone_manager.clone(),
             ood_resolver: self.ood_resolver.clone(),
             sync_client: self.sync_client.clone(),
+            scrub: self.scrub.clone(),
             access_info_manager: self.access_info_manager.clone(),
             task_manager: self.task_manager.clone(),
             config: self.config.clone(),
@@ -79,6 +83,7 @@ impl UtilLocalService {
             zone_manager,
             ood_resolver,
             sync_client: Arc::new(OnceCell::new()),
+            scrub: Arc::new(OnceCell::new()),
             access_info_manager,
             task_manager,
             config,
@@ -95,6 +100,12 @@ impl UtilLocalService {
         }
     }
 
+    pub(crate) fn bind_scrub(&self, scrub: ScrubManager) {
+        if let Err(_) = self.scrub.set(scrub) {
+            unreachable!();
+        }
+    }
+
     async fn get_device(
         &self,
         _req: UtilGetDeviceInputRequest,
@@ -268,6 +279,19 @@ impl UtilLocalService {
         Ok(UtilGetNOCInfoInputResponse { stat })
     }
 
+    pub async fn get_scrub_status(
+        &self,
+        _req: UtilGetScrubStatusInputRequest,
+    ) -> BuckyResult<UtilGetScrubStatusInputResponse> {
+        // 未开启scrub服务时返回默认状态
+        let status = match self.scrub.get() {
+            Some(scrub) => scrub.status(),
+            None => ScrubStatus::default(),
+        };
+
+        Ok(UtilGetScrubStatusInputResponse { status })
+    }
+
     pub async fn get_network_access_info(
         &self,
         _req: UtilGetNetworkAccessInfoInputRequest,
@@ -319,6 +343,7 @@ impl UtilLocalService {
                 dec_id: req.common.source.dec.clone(),
                 chunk_size: req.chunk_size,
                 chunk_method: req.chunk_method,
+                chunk_cdc: req.chunk_cdc,
                 access: req.access.map(|v| v.value()),
             };
             let task_id = self
@@ -355,6 +380,7 @@ impl UtilLocalService {
                 dec_id: req.common.source.dec.clone(),
                 chunk_size: req.chunk_size,
                 chunk_method: req.chunk_method,
+                chunk_cdc: req.chunk_cdc,
                 access: req.access.map(|v| v.value()),
                 device_id: self.bdt_stack.local_device_id().object_id().clone(),
             };
@@ -446,6 +472,13 @@ impl UtilInputProcessor for UtilLocalService {
         Self::get_noc_info(&self, req).await
     }
 
+    async fn get_scrub_status(
+        &self,
+        req: UtilGetScrubStatusInputRequest,
+    ) -> BuckyResult<UtilGetScrubStatusInputResponse> {
+        Self::get_scrub_status(&self, req).await
+    }
+
     async fn get_network_access_info(
         &self,
         req: UtilGetNetworkAccessInfoInputRequest,
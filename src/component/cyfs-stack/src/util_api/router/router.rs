@@ -129,6 +129,14 @@ impl UtilRouter {
         processor.get_noc_info(req).await
     }
 
+    async fn get_scrub_status(
+        &self,
+        req: UtilGetScrubStatusInputRequest,
+    ) -> BuckyResult<UtilGetScrubStatusInputResponse> {
+        let processor = self.get_processor(req.common.target.as_ref()).await?;
+        processor.get_scrub_status(req).await
+    }
+
     async fn get_network_access_info(
         &self,
         req: UtilGetNetworkAccessInfoInputRequest,
@@ -215,6 +223,13 @@ impl UtilInputProcessor for UtilRouter {
         Self::get_noc_info(&self, req).await
     }
 
+    async fn get_scrub_status(
+        &self,
+        req: UtilGetScrubStatusInputRequest,
+    ) -> BuckyResult<UtilGetScrubStatusInputResponse> {
+        Self::get_scrub_status(&self, req).await
+    }
+
     async fn get_network_access_info(
         &self,
         req: UtilGetNetworkAccessInfoInputRequest,
@@ -329,6 +329,38 @@ impl UtilRequestHandler {
         self.processor.get_noc_info(req).await
     }
 
+    // get_scrub_status
+    fn encode_get_scrub_status_response(resp: UtilGetScrubStatusInputResponse) -> Response {
+        let mut http_resp = RequestorHelper::new_response(StatusCode::Ok);
+
+        http_resp.set_content_type(::tide::http::mime::JSON);
+        http_resp.set_body(serde_json::to_string(&resp).unwrap());
+
+        http_resp.into()
+    }
+
+    pub async fn process_get_scrub_status_request<State>(
+        &self,
+        req: NONInputHttpRequest<State>,
+    ) -> Response {
+        let ret = self.on_get_scrub_status_request(req).await;
+        match ret {
+            Ok(resp) => Self::encode_get_scrub_status_response(resp),
+            Err(e) => RequestorHelper::trans_error(e),
+        }
+    }
+
+    async fn on_get_scrub_status_request<State>(
+        &self,
+        req: NONInputHttpRequest<State>,
+    ) -> BuckyResult<UtilGetScrubStatusInputResponse> {
+        let common = Self::decode_common_headers(&req)?;
+
+        let req = UtilGetScrubStatusInputRequest { common };
+
+        self.processor.get_scrub_status(req).await
+    }
+
     // get_network_access_info
     fn encode_get_network_access_info_response(
         resp: UtilGetNetworkAccessInfoInputResponse,
@@ -434,6 +466,7 @@ impl UtilRequestHandler {
             owner: out_req.owner,
             chunk_size: out_req.chunk_size,
             chunk_method: out_req.chunk_method,
+            chunk_cdc: out_req.chunk_cdc,
             access: out_req.access,
         };
         self.processor.build_file_object(in_req).await
@@ -15,6 +15,7 @@ enum UtilRequestType {
     GetSystemInfo,
     UpdateSystemInfo,
     GetNOCInfo,
+    GetScrubStatus,
     GetNetworkAccessInfo,
     GetVersionInfo,
     BuildFile,
@@ -68,6 +69,9 @@ impl UtilRequestHandlerEndpoint {
             }
 
             UtilRequestType::GetNOCInfo => self.handler.process_get_noc_info_request(req).await,
+            UtilRequestType::GetScrubStatus => {
+                self.handler.process_get_scrub_status_request(req).await
+            }
             UtilRequestType::GetNetworkAccessInfo => {
                 self.handler
                     .process_get_network_access_info_request(req)
@@ -235,6 +239,26 @@ impl UtilRequestHandlerEndpoint {
             handler.clone(),
         ));
 
+        // scrub_status
+        server.at("/util/scrub_status").get(Self::new(
+            zone_manager.clone(),
+            protocol.to_owned(),
+            UtilRequestType::GetScrubStatus,
+            handler.clone(),
+        ));
+        server.at("/util/scrub_status/").get(Self::new(
+            zone_manager.clone(),
+            protocol.to_owned(),
+            UtilRequestType::GetScrubStatus,
+            handler.clone(),
+        ));
+        server.at("/util/scrub_status/*must").get(Self::new(
+            zone_manager.clone(),
+            protocol.to_owned(),
+            UtilRequestType::GetScrubStatus,
+            handler.clone(),
+        ));
+
         // network_access_info
         server.at("/util/network_access_info").get(Self::new(
             zone_manager.clone(),
@@ -4,6 +4,7 @@ mod role_manager;
 mod zone_container;
 mod friends;
 mod target_zone;
+mod ood_failover;
 
 pub(crate) use zone_manager::*;
 pub(crate) use target_zone::*;
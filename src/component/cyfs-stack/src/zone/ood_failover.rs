@@ -0,0 +1,535 @@
+use super::zone_manager::*;
+use crate::sync::SyncClientRequestor;
+use cyfs_base::*;
+use cyfs_bdt::{DeviceCache, StackGuard};
+use cyfs_core::*;
+use cyfs_lib::*;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// 向其它ood查询epoch的超时时间
+const PROBE_TIMEOUT_IN_SECS: u64 = 30;
+
+// 两次查询epoch的最小间隔
+const PROBE_MIN_INTERVAL_IN_MICROS: u64 = 60 * 1000 * 1000;
+
+/*
+active/standby模式下的ood故障切换
+1. epoch基于owner对象的某个版本，每个epoch对应唯一的主ood: ood_list[epoch % ood_list.len()]，epoch 0就是owner指定的ood_list[0]
+2. owner批准: epoch对象必须带有owner的签名(MN的owner需要m个key签名)。owner事先为每台standby签好下一个轮到它的epoch，put到ood上；
+   standby自己不能生成可用的epoch，没有owner批准的epoch不会接任
+3. quorum: 主ood失联后，各ood对当前轮到的epoch投票(用设备key签名同一个epoch对象)，接任的standby收集其它ood的签名，
+   票数达到quorum(去掉失联的主ood后ood_list的多数)才算有效，其它设备校验epoch时同样要求owner签名和quorum
+4. fencing: 主ood持有standby的ping作为租约(还没有ping过的standby从成为主ood时算起)，超过FENCE_TIMEOUT没有ping的standby数量达到quorum时，
+   主ood自己把root_state切到只读；FENCE_TIMEOUT小于standby接任的超时，所以新主ood接任时旧主ood已经停止写入
+5. 其它设备(包括恢复后的旧主ood)向ood_list里的其它ood查询更大的epoch，校验通过后切换到新的主ood
+6. owner更新后，之前的epoch全部失效，以owner的ood_list为准
+*/
+pub(crate) struct OODEpochHelper;
+
+impl OODEpochHelper {
+    pub fn active_ood_of<T>(ood_list: &Vec<T>, epoch: u64) -> &T {
+        &ood_list[(epoch % ood_list.len() as u64) as usize]
+    }
+
+    // 当前epoch之后，第一个轮到device_id的epoch
+    pub fn next_epoch<T: PartialEq>(ood_list: &Vec<T>, current: u64, device_id: &T) -> Option<u64> {
+        let index = ood_list.iter().position(|id| id == device_id)? as u64;
+        let len = ood_list.len() as u64;
+        let next = current - current % len + index;
+        if next > current {
+            Some(next)
+        } else {
+            Some(next + len)
+        }
+    }
+
+    pub fn epoch_id(owner: &ObjectId, owner_update_time: u64, ood_list: &Vec<DeviceId>, epoch: u64) -> ObjectId {
+        let active_ood = Self::active_ood_of(ood_list, epoch).to_owned();
+        ZoneOODEpoch::create(owner.to_owned(), owner_update_time, epoch, active_ood)
+            .desc()
+            .calculate_id()
+    }
+
+    // 一个epoch生效需要的票数：失联的主ood不能投票，取剩下的ood的多数
+    pub fn quorum(ood_count: usize) -> usize {
+        (ood_count.max(1) - 1) / 2 + 1
+    }
+
+    // standby里租约过期的数量达到quorum时，它们可能已经选出了新的主ood，主ood需要停止写入
+    // 还没有ping过的standby(主ood重启或者分区发生在第一次ping之前)，租约从成为主ood的时间算起
+    pub fn should_fence(
+        last_pings: &[Option<u64>],
+        active_since: u64,
+        now: u64,
+        timeout: u64,
+        ood_count: usize,
+    ) -> bool {
+        let expired = last_pings
+            .iter()
+            .filter(|last_ping| now >= last_ping.unwrap_or(active_since) + timeout)
+            .count();
+        expired >= Self::quorum(ood_count)
+    }
+
+    async fn is_signed_by(epoch: &ZoneOODEpoch, pk: &PublicKey) -> BuckyResult<bool> {
+        let verifier = RsaCPUObjectVerifier::new(pk.clone());
+        if let Some(signs) = epoch.signs().desc_signs() {
+            for sign in signs {
+                if verify_object_desc_sign(&verifier, epoch, sign).await? {
+                    return Ok(true);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    // 校验owner的批准，MN的owner需要至少m个key的签名
+    pub async fn verify_owner_approval(epoch: &ZoneOODEpoch, owner: &AnyNamedObject) -> BuckyResult<()> {
+        let (threshold, keys) = match owner.public_key() {
+            Some(PublicKeyRef::Single(pk)) => (1, vec![pk.to_owned()]),
+            Some(PublicKeyRef::MN((m, list))) => (*m as usize, list.to_owned()),
+            None => {
+                let msg = format!("zone owner has no public key! owner={}", epoch.owner());
+                error!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::NotSupport, msg));
+            }
+        };
+
+        let mut count = 0;
+        for pk in &keys {
+            if Self::is_signed_by(epoch, pk).await? {
+                count += 1;
+            }
+        }
+
+        if count == 0 || count < threshold {
+            let msg = format!(
+                "ood epoch not approved by owner! epoch={}, owner={}, signs={}, threshold={}",
+                epoch.epoch(),
+                epoch.owner(),
+                count,
+                threshold
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidSignature, msg));
+        }
+
+        Ok(())
+    }
+
+    // ood_list里对epoch签名(投票)的ood
+    pub async fn voters(
+        epoch: &ZoneOODEpoch,
+        ood_list: &Vec<DeviceId>,
+        device_manager: &dyn DeviceCache,
+    ) -> Vec<DeviceId> {
+        let mut ret = vec![];
+        for ood in ood_list {
+            let device = match device_manager.search(ood).await {
+                Ok(device) => device,
+                Err(e) => {
+                    warn!("search ood device for epoch vote failed! ood={}, {}", ood, e);
+                    continue;
+                }
+            };
+
+            if let Ok(true) = Self::is_signed_by(epoch, device.desc().public_key()).await {
+                ret.push(ood.to_owned());
+            }
+        }
+
+        ret
+    }
+
+    // 完整校验一个epoch: owner版本、主ood、owner批准、quorum，并且接任的主ood自己必须投票
+    pub async fn verify(
+        epoch: &ZoneOODEpoch,
+        owner_id: &ObjectId,
+        owner: &AnyNamedObject,
+        zone: &Zone,
+        device_manager: &dyn DeviceCache,
+    ) -> BuckyResult<()> {
+        if epoch.owner() != owner_id || epoch.owner_update_time() != owner.get_update_time() {
+            let msg = format!(
+                "ood epoch's owner unmatch: epoch={}, owner={}, update_time={}, current owner={}, update_time={}",
+                epoch.epoch(),
+                epoch.owner(),
+                epoch.owner_update_time(),
+                owner_id,
+                owner.get_update_time()
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+        }
+
+        let active_ood = epoch.active_ood()?;
+        if *Self::active_ood_of(zone.ood_list(), epoch.epoch()) != active_ood {
+            let msg = format!(
+                "ood epoch's active ood unmatch: epoch={}, active ood={}",
+                epoch.epoch(),
+                active_ood
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+        }
+
+        Self::verify_owner_approval(epoch, owner).await?;
+
+        let voters = Self::voters(epoch, zone.ood_list(), device_manager).await;
+        let quorum = Self::quorum(zone.ood_list().len());
+        if !voters.contains(&active_ood) || voters.len() < quorum {
+            let msg = format!(
+                "ood epoch has not enough votes! epoch={}, active ood={}, voters={:?}, quorum={}",
+                epoch.epoch(),
+                active_ood,
+                voters,
+                quorum
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidSignature, msg));
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) struct OODFailover {
+    device_id: DeviceId,
+    zone_manager: ZoneManagerRef,
+    noc: NamedObjectCacheRef,
+    bdt_stack: StackGuard,
+    device_manager: Box<dyn DeviceCache>,
+
+    last_probe: AtomicU64,
+}
+
+impl OODFailover {
+    pub fn new(
+        device_id: DeviceId,
+        zone_manager: ZoneManagerRef,
+        noc: NamedObjectCacheRef,
+        bdt_stack: StackGuard,
+        device_manager: Box<dyn DeviceCache>,
+    ) -> Self {
+        Self {
+            device_id,
+            zone_manager,
+            noc,
+            bdt_stack,
+            device_manager,
+            last_probe: AtomicU64::new(0),
+        }
+    }
+
+    pub async fn current_epoch(&self) -> BuckyResult<(CurrentZoneInfoRef, Zone, u64)> {
+        let info = self.zone_manager.get_current_info().await?;
+        let zone = self.zone_manager.get_current_zone().await?;
+        let epoch = self
+            .zone_manager
+            .get_ood_epoch()
+            .filter(|epoch| epoch.owner_update_time() == info.owner.get_update_time())
+            .map(|epoch| epoch.epoch())
+            .unwrap_or(0);
+
+        Ok((info, zone, epoch))
+    }
+
+    // 排在自己前面还有几台standby，用来错开各个standby接任的时间
+    pub async fn standby_rank(&self) -> BuckyResult<u64> {
+        let (_, zone, current) = self.current_epoch().await?;
+        let next = OODEpochHelper::next_epoch(zone.ood_list(), current, &self.device_id).ok_or_else(|| {
+            let msg = format!("current device not in zone's ood list! device={}", self.device_id);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::NotFound, msg)
+        })?;
+
+        Ok(next - current - 1)
+    }
+
+    pub async fn verify_epoch(&self, epoch: &ZoneOODEpoch) -> BuckyResult<()> {
+        let (info, zone, _) = self.current_epoch().await?;
+        if info.ood_work_mode != OODWorkMode::ActiveStandby {
+            let msg = format!(
+                "zone's ood_work_mode is not active-standby! mode={}",
+                info.ood_work_mode
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::UnSupport, msg));
+        }
+
+        OODEpochHelper::verify(epoch, &info.owner_id, &info.owner, &zone, self.device_manager.as_ref()).await
+    }
+
+    // 查找owner批准的epoch，先查本地noc，再向其它ood查询
+    async fn load_approved_epoch(&self, info: &CurrentZoneInfo, zone: &Zone, epoch: u64) -> BuckyResult<ZoneOODEpoch> {
+        let id = OODEpochHelper::epoch_id(&info.owner_id, info.owner.get_update_time(), zone.ood_list(), epoch);
+
+        let req = NamedObjectCacheGetObjectRequest {
+            source: RequestSourceInfo::new_local_system(),
+            object_id: id.clone(),
+            last_access_rpath: None,
+            flags: 0,
+        };
+        if let Ok(Some(data)) = self.noc.get_object(&req).await {
+            let ret = ZoneOODEpoch::clone_from_slice(&data.object.object_raw)?;
+            if OODEpochHelper::verify_owner_approval(&ret, &info.owner).await.is_ok() {
+                return Ok(ret);
+            }
+        }
+
+        for ood in zone.ood_list() {
+            if *ood == self.device_id {
+                continue;
+            }
+            if let Ok(Some(ret)) = self.fetch_epoch(ood, &id).await {
+                if OODEpochHelper::verify_owner_approval(&ret, &info.owner).await.is_ok() {
+                    return Ok(ret);
+                }
+            }
+        }
+
+        let msg = format!(
+            "owner approved ood epoch not found! epoch={}, id={}, active ood={}",
+            epoch,
+            id,
+            OODEpochHelper::active_ood_of(zone.ood_list(), epoch)
+        );
+        warn!("{}", msg);
+        Err(BuckyError::new(BuckyErrorCode::PermissionDenied, msg))
+    }
+
+    // 用当前设备签名投票，并保存到noc，其它ood通过sync接口获取时noc会合并签名
+    async fn sign_vote(&self, epoch: &mut ZoneOODEpoch) -> BuckyResult<()> {
+        let signer = RsaCPUObjectSigner::new(
+            self.bdt_stack.keystore().public_key().clone(),
+            self.bdt_stack.keystore().private_key().clone(),
+        );
+        let sign_source = SignatureSource::Object(ObjectLink {
+            obj_id: self.device_id.object_id().to_owned(),
+            obj_owner: None,
+        });
+        sign_and_push_named_object_desc(&signer, epoch, &sign_source).await?;
+
+        self.save_epoch(epoch).await
+    }
+
+    async fn save_epoch(&self, epoch: &ZoneOODEpoch) -> BuckyResult<()> {
+        let id = epoch.desc().calculate_id();
+        let object = NONObjectInfo::new(id, epoch.to_vec()?, None);
+        let req = NamedObjectCachePutObjectRequest {
+            source: RequestSourceInfo::new_local_system(),
+            object,
+            storage_category: NamedObjectStorageCategory::Storage,
+            context: None,
+            last_access_rpath: None,
+            access_string: Some(AccessString::full_except_write().value()),
+        };
+        self.noc.put_object(&req).await?;
+        Ok(())
+    }
+
+    // 主ood失联时，为当前轮到的standby投票；每个epoch只有一台接任的ood，所以不会给两台ood投同一个epoch
+    pub async fn vote(&self, epoch: u64) -> BuckyResult<()> {
+        let (info, zone, current) = self.current_epoch().await?;
+        if epoch <= current {
+            return Ok(());
+        }
+
+        let mut approved = self.load_approved_epoch(&info, &zone, epoch).await?;
+        let voters = OODEpochHelper::voters(&approved, zone.ood_list(), self.device_manager.as_ref()).await;
+        if voters.contains(&self.device_id) {
+            return Ok(());
+        }
+
+        info!(
+            "vote for ood epoch: epoch={}, active ood={}",
+            epoch,
+            OODEpochHelper::active_ood_of(zone.ood_list(), epoch)
+        );
+        self.sign_vote(&mut approved).await
+    }
+
+    // 接任: 找到owner批准的下一个轮到自己的epoch，投票并收集其它ood的票，达到quorum后返回
+    pub async fn promote(&self) -> BuckyResult<ZoneOODEpoch> {
+        let (info, zone, current) = self.current_epoch().await?;
+        let next = OODEpochHelper::next_epoch(zone.ood_list(), current, &self.device_id).ok_or_else(|| {
+            let msg = format!("current device not in zone's ood list! device={}", self.device_id);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::NotFound, msg)
+        })?;
+
+        let mut epoch = self.load_approved_epoch(&info, &zone, next).await?;
+        self.sign_vote(&mut epoch).await?;
+
+        // 收集其它ood的投票
+        let id = epoch.desc().calculate_id();
+        for ood in zone.ood_list() {
+            if *ood == self.device_id {
+                continue;
+            }
+
+            let remote = match self.fetch_epoch(ood, &id).await {
+                Ok(Some(remote)) => remote,
+                Ok(None) => continue,
+                Err(e) => {
+                    debug!("fetch ood epoch vote failed! ood={}, epoch={}, {}", ood, next, e);
+                    continue;
+                }
+            };
+
+            if let Some(signs) = remote.signs().desc_signs() {
+                for sign in signs.clone() {
+                    epoch.signs_mut().push_desc_sign(sign);
+                }
+            }
+        }
+
+        let voters = OODEpochHelper::voters(&epoch, zone.ood_list(), self.device_manager.as_ref()).await;
+        let quorum = OODEpochHelper::quorum(zone.ood_list().len());
+        if voters.len() < quorum {
+            let msg = format!(
+                "promote but not enough votes! epoch={}, voters={:?}, quorum={}",
+                next, voters, quorum
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::ErrorState, msg));
+        }
+
+        self.save_epoch(&epoch).await?;
+
+        warn!(
+            "will promote current device to active ood! device={}, epoch {} -> {}, prev active ood={}, voters={:?}",
+            self.device_id, current, next, info.zone_device_ood_id, voters
+        );
+
+        Ok(epoch)
+    }
+
+    // 向ood_list里的其它ood查询比当前更大的epoch，返回校验通过的最大的一个
+    pub async fn probe(&self, force: bool) -> BuckyResult<Option<ZoneOODEpoch>> {
+        let now = bucky_time_now();
+        if !force {
+            let last = self.last_probe.load(Ordering::SeqCst);
+            if now < last + PROBE_MIN_INTERVAL_IN_MICROS {
+                return Ok(None);
+            }
+        }
+        self.last_probe.store(now, Ordering::SeqCst);
+
+        let (info, zone, current) = self.current_epoch().await?;
+        let ood_list = zone.ood_list();
+        let owner_update_time = info.owner.get_update_time();
+
+        let mut found: Option<ZoneOODEpoch> = None;
+        for epoch in current + 1..=current + ood_list.len() as u64 {
+            let active_ood = OODEpochHelper::active_ood_of(ood_list, epoch);
+            if *active_ood == self.device_id {
+                continue;
+            }
+
+            let id = OODEpochHelper::epoch_id(&info.owner_id, owner_update_time, ood_list, epoch);
+            let ret = match self.fetch_epoch(active_ood, &id).await {
+                Ok(Some(ret)) => ret,
+                Ok(None) => continue,
+                Err(e) => {
+                    debug!(
+                        "probe ood epoch failed! ood={}, epoch={}, {}",
+                        active_ood, epoch, e
+                    );
+                    continue;
+                }
+            };
+
+            if let Err(e) = self.verify_epoch(&ret).await {
+                warn!("probe got invalid ood epoch! ood={}, epoch={}, {}", active_ood, epoch, e);
+                continue;
+            }
+
+            info!("probe got newer ood epoch: ood={}, epoch={}", active_ood, epoch);
+            found = Some(ret);
+        }
+
+        Ok(found)
+    }
+
+    async fn fetch_epoch(&self, ood: &DeviceId, id: &ObjectId) -> BuckyResult<Option<ZoneOODEpoch>> {
+        let device = self.device_manager.search(ood).await?;
+        let bdt_requestor = BdtHttpRequestor::new(
+            self.bdt_stack.clone(),
+            device,
+            cyfs_base::NON_STACK_SYNC_BDT_VPORT,
+        );
+        let requestor = SyncClientRequestor::new(Box::new(bdt_requestor));
+
+        let list = async_std::future::timeout(
+            Duration::from_secs(PROBE_TIMEOUT_IN_SECS),
+            requestor.get_objects(vec![id.to_owned()]),
+        )
+        .await
+        .map_err(|_| {
+            let msg = format!("get ood epoch timeout! ood={}, id={}", ood, id);
+            BuckyError::new(BuckyErrorCode::Timeout, msg)
+        })??;
+
+        for object in list {
+            if object.object_id == *id {
+                let epoch = ZoneOODEpoch::clone_from_slice(&object.object_raw)?;
+                return Ok(Some(epoch));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OODEpochHelper;
+
+    #[test]
+    fn test_next_epoch() {
+        let list = vec![0, 1, 2];
+
+        assert_eq!(OODEpochHelper::active_ood_of(&list, 0), &0);
+        assert_eq!(OODEpochHelper::active_ood_of(&list, 4), &1);
+
+        assert_eq!(OODEpochHelper::next_epoch(&list, 0, &1), Some(1));
+        assert_eq!(OODEpochHelper::next_epoch(&list, 0, &2), Some(2));
+        assert_eq!(OODEpochHelper::next_epoch(&list, 1, &0), Some(3));
+        assert_eq!(OODEpochHelper::next_epoch(&list, 4, &1), Some(7));
+        assert_eq!(OODEpochHelper::next_epoch(&list, 4, &2), Some(5));
+        assert_eq!(OODEpochHelper::next_epoch(&list, 4, &3), None);
+    }
+
+    #[test]
+    fn test_quorum_and_fence() {
+        assert_eq!(OODEpochHelper::quorum(1), 1);
+        assert_eq!(OODEpochHelper::quorum(2), 1);
+        assert_eq!(OODEpochHelper::quorum(3), 2);
+        assert_eq!(OODEpochHelper::quorum(4), 2);
+        assert_eq!(OODEpochHelper::quorum(5), 3);
+
+        // 3台ood，两台standby都过期才需要停止写入
+        assert!(!OODEpochHelper::should_fence(&[], 0, 100, 10, 3));
+        assert!(!OODEpochHelper::should_fence(&[Some(95), Some(80)], 0, 100, 10, 3));
+        assert!(OODEpochHelper::should_fence(&[Some(90), Some(80)], 0, 100, 10, 3));
+
+        // 2台ood，唯一的standby过期就停止写入
+        assert!(!OODEpochHelper::should_fence(&[Some(95)], 0, 100, 10, 2));
+        assert!(OODEpochHelper::should_fence(&[Some(90)], 0, 100, 10, 2));
+    }
+
+    #[test]
+    fn test_fence_without_ping() {
+        // 主ood重启后standby一直没有ping，租约从重启时间算起，超时后必须停止写入
+        assert!(!OODEpochHelper::should_fence(&[None, None], 95, 100, 10, 3));
+        assert!(OODEpochHelper::should_fence(&[None, None], 90, 100, 10, 3));
+
+        // 一台standby还在ping，另一台从来没有ping过，不够quorum
+        assert!(!OODEpochHelper::should_fence(&[Some(95), None], 50, 100, 10, 3));
+        assert!(OODEpochHelper::should_fence(&[Some(80), None], 50, 100, 10, 3));
+    }
+}
@@ -1,3 +1,4 @@
+use super::ood_failover::{OODEpochHelper, OODFailover};
 use super::zone_manager::*;
 use crate::acl::AclManagerRef;
 use crate::config::StackGlobalConfig;
@@ -14,10 +15,24 @@ use cyfs_lib::*;
 use cyfs_util::*;
 
 use once_cell::sync::OnceCell;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 const ROLE_MANAGER_HANDLER_ID: &str = "system_role_manager_controller";
 
+// active/standby模式下检查主ood状态的间隔
+const FAILOVER_CHECK_INTERVAL_IN_SECS: u64 = 30;
+
+// 主ood失联超过这个时间，排在第一位的standby接任，后面的standby依次顺延
+const FAILOVER_TIMEOUT_IN_MICROS: u64 = 3 * 60 * 1000 * 1000;
+
+// 接任前要求连续ping失败的最少次数
+const FAILOVER_MIN_PING_FAILED_COUNT: u64 = 3;
+
+// 主ood超过这个时间没有收到standby的ping，认为租约过期；必须小于FAILOVER_TIMEOUT_IN_MICROS
+const FENCE_TIMEOUT_IN_MICROS: u64 = 2 * 60 * 1000 * 1000;
+
 struct OnPeopleUpdateWatcher {
     owner: ZoneRoleManager,
 }
@@ -65,6 +80,12 @@ pub struct ZoneRoleManager {
     sync_client: Arc<OnceCell<Arc<DeviceSyncClient>>>,
     sync_interface: Arc<OnceCell<SyncListenerManager>>,
 
+    // active/standby模式下的故障切换
+    failover: Arc<OnceCell<OODFailover>>,
+
+    // 成为主ood的时间(进程启动或者接任)，没有ping过的standby租约从这个时间算起
+    active_since: Arc<AtomicU64>,
+
     // events
     event_manager: RouterEventsManager,
 }
@@ -92,6 +113,8 @@ impl ZoneRoleManager {
             sync_server: Arc::new(OnceCell::new()),
             sync_client: Arc::new(OnceCell::new()),
             sync_interface: Arc::new(OnceCell::new()),
+            failover: Arc::new(OnceCell::new()),
+            active_since: Arc::new(AtomicU64::new(bucky_time_now())),
         }
     }
 
@@ -313,6 +336,16 @@ impl ZoneRoleManager {
                     }
                 }
                 ZoneRole::ActiveOOD | ZoneRole::ReservedOOD | ZoneRole::StandbyOOD => {
+                    if current_info.zone_device_ood_id != new_info.zone_device_ood_id {
+                        if let Some(client) = self.sync_client.get() {
+                            info!(
+                                "zone ood device id changed! now will notify sync client {} -> {}",
+                                current_info.zone_device_ood_id, new_info.zone_device_ood_id
+                            );
+                            let _ = client.notify_zone_ood_chanegd().await;
+                        }
+                    }
+
                     match self.sync_server.get() {
                         Some(server) => {
                             let zone_state = server.zone_state_manager().get_zone_state().await;
@@ -516,9 +549,242 @@ impl ZoneRoleManager {
 
         self.start_sync_interface(bdt_stack).await?;
 
+        if current_zone_info.ood_work_mode == OODWorkMode::ActiveStandby {
+            self.start_failover(bdt_stack, device_manager, current_zone_info.zone_role);
+        }
+
+        Ok(())
+    }
+
+    fn start_failover(
+        &self,
+        bdt_stack: &StackGuard,
+        device_manager: &Box<dyn DeviceCache>,
+        zone_role: ZoneRole,
+    ) {
+        let failover = OODFailover::new(
+            self.device_id.clone(),
+            self.zone_manager.clone(),
+            self.noc.clone(),
+            bdt_stack.clone(),
+            device_manager.clone_cache(),
+        );
+
+        if let Err(_) = self.failover.set(failover) {
+            unreachable!();
+        }
+
+        // 普通设备只在ping主ood失败时才去查询，ood需要持续检查
+        match zone_role {
+            ZoneRole::ActiveOOD | ZoneRole::StandbyOOD => {
+                let this = self.clone();
+                async_std::task::spawn(async move {
+                    this.run_failover_monitor().await;
+                });
+            }
+            _ => {}
+        }
+    }
+
+    async fn run_failover_monitor(&self) {
+        use async_std::prelude::*;
+
+        info!("will start ood failover monitor! device={}", self.device_id);
+
+        let mut interval =
+            async_std::stream::interval(Duration::from_secs(FAILOVER_CHECK_INTERVAL_IN_SECS));
+        while let Some(_) = interval.next().await {
+            // 先看其它ood是不是已经接任了，旧的主ood恢复后也是通过这里降级
+            let _ = self.probe_ood_epoch(true).await;
+
+            match self.zone_manager.get_current_info().await {
+                Ok(info) if info.zone_role == ZoneRole::StandbyOOD => {
+                    if let Err(e) = self.check_active_ood().await {
+                        error!("check active ood failed! {}", e);
+                    }
+                }
+                Ok(info) if info.zone_role == ZoneRole::ActiveOOD => {
+                    if let Err(e) = self.check_fence().await {
+                        error!("check active ood fence failed! {}", e);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // 同步客户端ping主ood连续失败时调用，尝试从其它ood获取新的主ood
+    pub(crate) async fn on_ood_ping_failed(&self) {
+        if self.failover.get().is_none() {
+            return;
+        }
+
+        let _ = self.probe_ood_epoch(false).await;
+    }
+
+    async fn probe_ood_epoch(&self, force: bool) -> BuckyResult<bool> {
+        let failover = self.failover.get().unwrap();
+
+        let mut changed = false;
+        let mut force = force;
+        loop {
+            let epoch = match failover.probe(force).await {
+                Ok(Some(epoch)) => epoch,
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("probe ood epoch failed! {}", e);
+                    return Err(e);
+                }
+            };
+
+            if !self.apply_ood_epoch(epoch).await? {
+                break;
+            }
+
+            // 可能还有更新的epoch，继续查找
+            changed = true;
+            force = true;
+        }
+
+        Ok(changed)
+    }
+
+    // 主ood检查standby的租约，多数standby失联时可能已经有新的主ood接任，root_state切到只读
+    async fn check_fence(&self) -> BuckyResult<()> {
+        let server = match self.sync_server.get() {
+            Some(server) => server,
+            None => return Ok(()),
+        };
+
+        // 只统计当前仍是standby的ood的租约，主ood和已经移出zone的设备不计入
+        let zone = self.zone_manager.get_current_zone().await?;
+        let standby_list = self.zone_manager.get_current_standby_ood_list().await?;
+        let leases = server.standby_leases(&standby_list);
+        let last_pings: Vec<Option<u64>> = standby_list
+            .iter()
+            .map(|device_id| leases.get(device_id).cloned())
+            .collect();
+        let fenced = OODEpochHelper::should_fence(
+            &last_pings,
+            self.active_since.load(Ordering::SeqCst),
+            bucky_time_now(),
+            FENCE_TIMEOUT_IN_MICROS,
+            zone.ood_list().len(),
+        );
+
+        let access_mode = if fenced {
+            GlobalStateAccessMode::Read
+        } else {
+            GlobalStateAccessMode::Write
+        };
+        if self.config.get_access_mode(GlobalStateCategory::RootState) != access_mode {
+            warn!(
+                "active ood fence changed by standby leases: fenced={}, leases={:?}",
+                fenced, last_pings
+            );
+        }
+
+        self.config
+            .change_access_mode(GlobalStateCategory::RootState, access_mode);
+
         Ok(())
     }
 
+    // standby检查主ood是否失联，失联超时后按顺序接任，没轮到自己时给轮到的standby投票
+    async fn check_active_ood(&self) -> BuckyResult<()> {
+        let client = match self.sync_client.get() {
+            Some(client) => client,
+            None => return Ok(()),
+        };
+
+        let status = client.get_ood_status(false).await?;
+        if status.first_success_ping == 0 {
+            // 从来没有连上过主ood，有可能是自己的网络有问题，不能接任
+            return Ok(());
+        }
+
+        let now = bucky_time_now();
+        if status.cont_fail_count < FAILOVER_MIN_PING_FAILED_COUNT
+            || now < status.last_success_ping + FAILOVER_TIMEOUT_IN_MICROS
+        {
+            return Ok(());
+        }
+
+        let failover = self.failover.get().unwrap();
+        let (_, zone, current) = failover.current_epoch().await?;
+        let rank = failover.standby_rank().await?;
+
+        // 按失联的时长算出当前轮到第几台standby
+        let turn = (now - status.last_success_ping) / FAILOVER_TIMEOUT_IN_MICROS - 1;
+        if turn + 1 >= zone.ood_list().len() as u64 {
+            return Ok(());
+        }
+
+        if turn != rank {
+            if let Err(e) = failover.vote(current + 1 + turn).await {
+                warn!("vote for ood epoch failed! epoch={}, {}", current + 1 + turn, e);
+            }
+            return Ok(());
+        }
+
+        // 接任前再确认一次
+        let status = client.get_ood_status(true).await?;
+        if status.cont_fail_count < FAILOVER_MIN_PING_FAILED_COUNT {
+            info!("active ood recovered, cancel failover! ood={}", status.ood_device_id);
+            return Ok(());
+        }
+
+        // 本地root_state落后于zone的版本时接任会丢失已经提交的状态，等同步完成后再接任，
+        // 超时后会轮到下一台standby
+        if status.device_root_state_revision < status.zone_root_state_revision {
+            warn!(
+                "active ood lost but local root state not synced yet, defer promotion! local revision={}, zone revision={}",
+                status.device_root_state_revision, status.zone_root_state_revision
+            );
+            return Ok(());
+        }
+
+        warn!(
+            "active ood lost for {}s, now will promote current device! ood={}, rank={}",
+            (now - status.last_success_ping) / 1000 / 1000,
+            status.ood_device_id,
+            rank
+        );
+
+        let epoch = failover.promote().await?;
+        self.apply_ood_epoch(epoch).await?;
+
+        Ok(())
+    }
+
+    async fn apply_ood_epoch(&self, epoch: ZoneOODEpoch) -> BuckyResult<bool> {
+        let failover = self.failover.get().unwrap();
+        failover.verify_epoch(&epoch).await?;
+
+        let current_info = self.zone_manager.get_current_info().await?;
+        if !self.zone_manager.update_ood_epoch(epoch).await? {
+            return Ok(false);
+        }
+
+        let new_info = self.zone_manager.get_current_info().await?;
+        info!(
+            "zone info changed by ood epoch: current={}, latest={}",
+            current_info, new_info
+        );
+
+        // 接任后立即允许写root_state，降级的旧主ood立即变为只读
+        if current_info.zone_role != new_info.zone_role {
+            if new_info.zone_role == ZoneRole::ActiveOOD {
+                self.active_since.store(bucky_time_now(), Ordering::SeqCst);
+            }
+            self.init_root_state_access_mode().await?;
+        }
+
+        self.on_zone_changed(current_info, new_info).await;
+
+        Ok(true)
+    }
+
     async fn register_router_handler(
         &self,
         router_handlers: &RouterHandlerManagerProcessorRef,
@@ -1,15 +1,16 @@
+use super::ood_failover::OODEpochHelper;
 use super::target_zone::TargetZoneManager;
 use super::zone_container::ZoneContainer;
 use super::{failed_cache::ZoneFailedCache, friends::FriendsManager};
 use crate::meta::*;
 use crate::resolver::DeviceCache;
 use cyfs_base::*;
-use cyfs_core::{Zone, ZoneId, ZoneObj};
+use cyfs_core::{Zone, ZoneId, ZoneOODEpoch, ZoneOODEpochObj, ZoneObj};
 use cyfs_lib::*;
 use cyfs_util::*;
 
 use once_cell::sync::OnceCell;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use async_std::sync::Mutex as AsyncMutex;
 
 // zone发生改变
@@ -79,6 +80,9 @@ pub struct ZoneManager {
         ReenterCallManager<ObjectId, BuckyResult<(ObjectId, OODWorkMode, Vec<DeviceId>)>>,
 
     target_zone_manager: Arc<OnceCell<TargetZoneManager>>,
+
+    // active/standby模式下当前zone最新的ood epoch
+    ood_epoch: Arc<RwLock<Option<ZoneOODEpoch>>>,
 }
 
 pub type ZoneManagerRef = Arc<ZoneManager>;
@@ -112,6 +116,7 @@ impl ZoneManager {
             search_zone_ood_by_owner_reenter_call_manager: ReenterCallManager::new(),
             friends_manager: FriendsManager::new(root_state),
             target_zone_manager: Arc::new(OnceCell::new()),
+            ood_epoch: Arc::new(RwLock::new(None)),
         };
 
         let ret = Arc::new(ret);
@@ -203,9 +208,20 @@ impl ZoneManager {
             zone = self.get_zone(&self.device_id, None).await?;
         }
 
-        let zone_device_ood_id = zone.ood().to_owned();
+        let mut zone_device_ood_id = zone.ood().to_owned();
 
         let ood_work_mode = zone.ood_work_mode().to_owned();
+        if ood_work_mode == OODWorkMode::ActiveStandby {
+            if let Some(active_ood) = self.load_ood_epoch(&zone, &owner_id, &owner).await {
+                if active_ood != zone_device_ood_id {
+                    info!(
+                        "zone's active ood changed by epoch: {} -> {}",
+                        zone_device_ood_id, active_ood
+                    );
+                    zone_device_ood_id = active_ood;
+                }
+            }
+        }
         let zone_role = if self.device_id == zone_device_ood_id {
             ZoneRole::ActiveOOD
         } else if zone.is_ood(&self.device_id) {
@@ -233,6 +249,126 @@ impl ZoneManager {
         Ok(info)
     }
 
+    pub fn get_ood_epoch(&self) -> Option<ZoneOODEpoch> {
+        self.ood_epoch.read().unwrap().clone()
+    }
+
+    // 更新当前zone的ood epoch，调用前需要校验过epoch的签名
+    pub(crate) async fn update_ood_epoch(&self, epoch: ZoneOODEpoch) -> BuckyResult<bool> {
+        let id = epoch.desc().calculate_id();
+        {
+            let mut slot = self.ood_epoch.write().unwrap();
+            if let Some(current) = &*slot {
+                if current.owner_update_time() == epoch.owner_update_time()
+                    && current.epoch() >= epoch.epoch()
+                {
+                    return Ok(false);
+                }
+            }
+            *slot = Some(epoch.clone());
+        }
+
+        // 保存到noc，重启后从noc加载
+        let object = NONObjectInfo::new(id.clone(), epoch.to_vec()?, None);
+        let req = NamedObjectCachePutObjectRequest {
+            source: RequestSourceInfo::new_local_system(),
+            object,
+            storage_category: NamedObjectStorageCategory::Storage,
+            context: None,
+            last_access_rpath: None,
+            access_string: Some(AccessString::full_except_write().value()),
+        };
+        if let Err(e) = self.noc.put_object(&req).await {
+            error!("save ood epoch to noc failed! epoch={}, {}", id, e);
+        }
+
+        info!(
+            "ood epoch updated: epoch={}, active ood={:?}, id={}",
+            epoch.epoch(),
+            epoch.active_ood(),
+            id
+        );
+
+        // 清除当前zone信息，下次获取时按新的epoch重新计算
+        *self.current_info.lock().await = None;
+
+        Ok(true)
+    }
+
+    // 从noc加载最新的epoch，返回对应的主ood；epoch对象的id可以直接算出来，向后查找直到连续一轮都不存在或者校验不通过
+    async fn load_ood_epoch(
+        &self,
+        zone: &Zone,
+        owner_id: &ObjectId,
+        owner: &AnyNamedObject,
+    ) -> Option<DeviceId> {
+        let ood_list = zone.ood_list();
+        let owner_update_time = owner.get_update_time();
+        let mut current = match self.get_ood_epoch() {
+            Some(epoch) if epoch.owner_update_time() == owner_update_time => Some(epoch),
+            _ => None,
+        };
+
+        let mut epoch = current.as_ref().map(|v| v.epoch()).unwrap_or(0) + 1;
+        let mut missed = 0;
+        while missed < ood_list.len() {
+            let id = OODEpochHelper::epoch_id(zone.owner(), owner_update_time, ood_list, epoch);
+            let req = NamedObjectCacheGetObjectRequest {
+                source: RequestSourceInfo::new_local_system(),
+                object_id: id,
+                last_access_rpath: None,
+                flags: 0,
+            };
+
+            match self.noc.get_object(&req).await {
+                Ok(Some(data)) => match ZoneOODEpoch::clone_from_slice(&data.object.object_raw) {
+                    Ok(v) => {
+                        // 只有owner批准并且达到quorum的epoch才有效，未投票完成的epoch也会保存在noc里
+                        match OODEpochHelper::verify(
+                            &v,
+                            owner_id,
+                            owner,
+                            zone,
+                            self.device_manager.as_ref().as_ref(),
+                        )
+                        .await
+                        {
+                            Ok(()) => {
+                                current = Some(v);
+                                missed = 0;
+                            }
+                            Err(e) => {
+                                debug!("ood epoch in noc not valid! epoch={}, {}", epoch, e);
+                                missed += 1;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        error!("decode ood epoch from noc failed! epoch={}, {}", epoch, e);
+                        missed += 1;
+                    }
+                },
+                _ => {
+                    missed += 1;
+                }
+            }
+
+            epoch += 1;
+        }
+
+        let current = current?;
+        let active_ood = current.active_ood().ok()?;
+        if !zone.is_ood(&active_ood) {
+            warn!("ood epoch's active ood not in zone's ood list! ood={}", active_ood);
+            return None;
+        }
+
+        info!("load ood epoch: epoch={}, active ood={}", current.epoch(), active_ood);
+        *self.ood_epoch.write().unwrap() = Some(current);
+
+        Some(active_ood)
+    }
+
     pub fn get_current_device_id(&self) -> &DeviceId {
         &self.device_id
     }
@@ -245,6 +381,24 @@ impl ZoneManager {
         self.get_zone_id(&self.device_id, None).await
     }
 
+    // active/standby模式下当前zone的standby ood列表，以zone和当前epoch为准，不包括主ood
+    pub async fn get_current_standby_ood_list(&self) -> BuckyResult<Vec<DeviceId>> {
+        let info = self.get_current_info().await?;
+        if info.ood_work_mode != OODWorkMode::ActiveStandby {
+            return Ok(vec![]);
+        }
+
+        let zone = self.get_current_zone().await?;
+        let list = zone
+            .ood_list()
+            .iter()
+            .filter(|id| **id != info.zone_device_ood_id)
+            .cloned()
+            .collect();
+
+        Ok(list)
+    }
+
     pub fn query(&self, zone_id: &ZoneId) -> Option<Zone> {
         if let Some(zone) = self.zones.query(zone_id) {
             return Some(zone);
@@ -3,6 +3,7 @@ mod db_helper;
 mod object_locker;
 mod task;
 mod task_manager;
+mod task_schedule;
 mod task_store;
 
 pub(crate) use condvar_helper::*;
@@ -10,6 +11,7 @@ pub use task_manager::*;
 pub(crate) use db_helper::*;
 pub(crate) use object_locker::*;
 pub use task::*;
+pub use task_schedule::*;
 pub use task_store::*;
 
 #[macro_use]
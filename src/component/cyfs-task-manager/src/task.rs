@@ -270,11 +270,15 @@ impl<R: 'static + Runnable> Task for RunnableTask<R> {
         let runnable = self.runnable.clone();
         let task_id = self.runnable.get_task_id();
 
+        // 返回前就切到Running，调用方(比如任务调度)紧接着等待任务结束时不会读到上一次运行的状态
         {
-            let tmp_data = self.data.lock().unwrap();
+            let mut tmp_data = self.data.lock().unwrap();
             if tmp_data.task_status == TaskStatus::Running {
                 return Ok(());
             }
+            tmp_data.task_status = TaskStatus::Running;
+            tmp_data.error = None;
+            self.runnable.status_change(tmp_data.task_status);
         }
 
         let (ft, handle) = futures::future::abortable(async move {
@@ -294,11 +298,6 @@ impl<R: 'static + Runnable> Task for RunnableTask<R> {
         };
         let runnable_handle = async_std::task::spawn(async move {
             let _: BuckyResult<()> = async move {
-                {
-                    let mut tmp_data = data.lock().unwrap();
-                    tmp_data.task_status = TaskStatus::Running;
-                    runnable.status_change(tmp_data.task_status);
-                }
                 if task_store.is_some() {
                     task_store.as_ref().unwrap().save_task_status(&task_id, TaskStatus::Running).await?;
                 }
@@ -472,4 +471,15 @@ mod test_task {
             assert_eq!(task.get_task_status().await, TaskStatus::Stopped);
         });
     }
+
+    #[test]
+    fn test_runnable_running_on_start() {
+        async_std::task::block_on(async {
+            let task = RunnableTask::new(TestRunnable {});
+            task.start_task().await.unwrap();
+            // start_task返回时就应该是Running，不依赖运行协程被调度
+            assert_eq!(task.get_task_status().await, TaskStatus::Running);
+            task.stop_task().await.unwrap();
+        });
+    }
 }
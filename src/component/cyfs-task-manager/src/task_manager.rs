@@ -1,8 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use cyfs_base::*;
-use crate::{Locker, Task, TaskCategory, TaskFactory, TaskId, TaskManagerStore, TaskStatus, TaskStore, TaskType};
+use crate::{Locker, Task, TaskCategory, TaskFactory, TaskId, TaskManagerStore, TaskStatus, TaskStore, TaskType, TaskSchedule, TaskRetryPolicy, TaskScheduleInfo, TaskScheduleState};
 
 struct TaskInfo {
     pub task: Arc<Box<dyn Task>>,
@@ -54,6 +55,15 @@ pub struct TaskManager {
     task_manager_store: Arc<dyn TaskManagerStore>,
     task_store: Arc<dyn TaskStore>,
     task_map: async_std::sync::Mutex<HashMap<TaskId, TaskInfo>>,
+
+    // 调度中的任务，不能在持有schedule_map的时候再去锁task_map
+    schedule_map: async_std::sync::Mutex<HashMap<TaskId, TaskScheduleInfo>>,
+    schedule_complete_list: Arc<Mutex<Vec<(TaskId, TaskStatus)>>>,
+    schedule_seq: AtomicU64,
+
+    // 调度启动任务时的并发限制，由使用方在启动时设置，不持久化
+    category_limits: Mutex<HashMap<TaskCategory, u32>>,
+    dec_limits: Mutex<HashMap<ObjectId, u32>>,
 }
 
 impl TaskManager {
@@ -62,9 +72,16 @@ impl TaskManager {
             task_factory_map: Mutex::new(Default::default()),
             task_store,
             task_manager_store,
-            task_map: async_std::sync::Mutex::new(Default::default())
+            task_map: async_std::sync::Mutex::new(Default::default()),
+            schedule_map: async_std::sync::Mutex::new(Default::default()),
+            schedule_complete_list: Arc::new(Mutex::new(Vec::new())),
+            schedule_seq: AtomicU64::new(0),
+            category_limits: Mutex::new(Default::default()),
+            dec_limits: Mutex::new(Default::default()),
         });
 
+        task_manager.load_schedules().await?;
+
         // task_manager.task_manager_store.clear_can_delete_task().await?;
         let tmp_task_manager = Arc::downgrade(&task_manager);
         async_std::task::spawn(async move {
@@ -82,6 +99,23 @@ impl TaskManager {
                 }
             }
         });
+
+        let tmp_task_manager = Arc::downgrade(&task_manager);
+        async_std::task::spawn(async move {
+            loop {
+                match tmp_task_manager.upgrade() {
+                    Some(task_manager) => {
+                        if let Err(e) = task_manager.check_schedule().await {
+                            log::error!("task manager check schedule err {}", e);
+                        }
+                    },
+                    None => {
+                        break;
+                    }
+                }
+                async_std::task::sleep(Duration::from_secs(1)).await;
+            }
+        });
         Ok(task_manager)
     }
 
@@ -394,6 +428,7 @@ impl TaskManager {
                 if dec_list.len() == 0 {
                     self.task_manager_store.delete_task(task_id).await?;
                     task_map.remove(task_id);
+                    self.schedule_map.lock().await.remove(task_id);
                 }
             }
             Some(info) => {
@@ -407,6 +442,7 @@ impl TaskManager {
                         self.task_manager_store.delete_task(task_id).await?;
                     }
                     task_map.remove(task_id);
+                    self.schedule_map.lock().await.remove(task_id);
                 }
             }
         }
@@ -430,6 +466,7 @@ impl TaskManager {
                 task_map.remove(task_id);
             }
         }
+        self.schedule_map.lock().await.remove(task_id);
 
         Ok(())
     }
@@ -442,6 +479,359 @@ impl TaskManager {
         self.task_manager_store.get_tasks_by_category(category).await
     }
 
+    // 为已经创建的任务设置调度，depends里的任务需要已经在调度中，再次调用会覆盖之前的调度
+    pub async fn schedule_task(&self, task_id: &TaskId, schedule: TaskSchedule, retry: Option<TaskRetryPolicy>, depends: Vec<TaskId>) -> BuckyResult<()> {
+        log::info!("schedule_task {} schedule {:?} retry {:?} depends {:?}", task_id, schedule, retry, depends);
+        let _locker = Locker::get_locker(format!("task_manager_{}", task_id)).await;
+
+        // 调度状态需要持久化，只支持已经持久化的任务
+        self.task_manager_store.get_task(task_id).await.map_err(|e| {
+            let msg = format!("schedule task but task not found in store! task={}, {}", task_id, e);
+            log::error!("{}", msg);
+            BuckyError::new(e.code(), msg)
+        })?;
+
+        let mut info = TaskScheduleInfo::new(schedule, retry, depends)?;
+        info.seq = self.schedule_seq.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let mut schedule_map = self.schedule_map.lock().await;
+        for depend_id in info.depends.iter() {
+            if !schedule_map.contains_key(depend_id) {
+                let msg = format!("schedule task but depend task not scheduled! task={}, depend={}", task_id, depend_id);
+                log::error!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+            }
+        }
+
+        if Self::find_depend(&schedule_map, &info.depends, |id, _| id == task_id) {
+            let msg = format!("schedule task but depends has cycle! task={}, depends={:?}", task_id, info.depends);
+            log::error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        self.task_manager_store.add_task_schedule(task_id, &info).await?;
+        schedule_map.insert(task_id.clone(), info);
+
+        Ok(())
+    }
+
+    pub async fn unschedule_task(&self, task_id: &TaskId) -> BuckyResult<()> {
+        log::info!("unschedule_task {}", task_id);
+        let mut schedule_map = self.schedule_map.lock().await;
+        if schedule_map.remove(task_id).is_some() {
+            self.task_manager_store.delete_task_schedule(task_id).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_task_schedule(&self, task_id: &TaskId) -> BuckyResult<TaskScheduleInfo> {
+        let schedule_map = self.schedule_map.lock().await;
+        schedule_map.get(task_id).cloned().ok_or_else(|| {
+            let msg = format!("task schedule not found! task={}", task_id);
+            log::warn!("{}", msg);
+            BuckyError::new(BuckyErrorCode::NotFound, msg)
+        })
+    }
+
+    // 调度启动的任务在同一category下同时运行的数量限制，直接调用start_task不受限制
+    pub fn set_category_limit(&self, category: TaskCategory, limit: u32) {
+        log::info!("set task category limit: category={}, limit={}", category, limit);
+        self.category_limits.lock().unwrap().insert(category, limit);
+    }
+
+    pub fn set_dec_limit(&self, dec_id: ObjectId, limit: u32) {
+        log::info!("set task dec limit: dec={}, limit={}", dec_id, limit);
+        self.dec_limits.lock().unwrap().insert(dec_id, limit);
+    }
+
+    async fn load_schedules(&self) -> BuckyResult<()> {
+        let list = self.task_manager_store.get_task_schedules().await?;
+        let mut schedule_map = self.schedule_map.lock().await;
+        for (task_id, mut info) in list {
+            // 上次退出时还在运行的任务，重新启动并且不再要求依赖任务有新的成功
+            if info.state == TaskScheduleState::Running {
+                info.state = TaskScheduleState::Waiting;
+                info.next_time = 0;
+                info.last_run_time = 0;
+            }
+            schedule_map.insert(task_id, info);
+        }
+        log::info!("load task schedules: count={}", schedule_map.len());
+
+        Ok(())
+    }
+
+    async fn check_schedule(&self) -> BuckyResult<()> {
+        let complete_list: Vec<(TaskId, TaskStatus)> = {
+            let mut list = self.schedule_complete_list.lock().unwrap();
+            list.drain(..).collect()
+        };
+
+        // 在副本上计算，避免持有schedule_map的时候去锁task_map
+        let mut schedule_map = self.schedule_map.lock().await.clone();
+        if schedule_map.is_empty() {
+            return Ok(());
+        }
+
+        let now = bucky_time_now();
+        let mut changed = HashSet::new();
+
+        for (task_id, status) in complete_list {
+            let depends_recurring = Self::is_depends_recurring(&schedule_map, &task_id);
+            if let Some(info) = schedule_map.get_mut(&task_id) {
+                if info.state != TaskScheduleState::Running {
+                    continue;
+                }
+
+                if status == TaskStatus::Finished {
+                    log::info!("scheduled task succeeded: task={}", task_id);
+                    info.on_success(now, depends_recurring);
+                } else if status == TaskStatus::Paused || status == TaskStatus::Stopped {
+                    // 用户主动暂停或者停止，按取消处理，不进入失败重试
+                    log::info!("scheduled task canceled: task={}, status={:?}", task_id, status);
+                    info.on_canceled(now, depends_recurring);
+                } else {
+                    log::warn!("scheduled task failed: task={}, status={:?}, retry_count={}", task_id, status, info.retry_count);
+                    info.on_failed(now, depends_recurring);
+                }
+                changed.insert(task_id);
+            }
+        }
+
+        let mut ready_list = Vec::new();
+        let mut failed_list = Vec::new();
+        for (task_id, info) in schedule_map.iter() {
+            if info.state != TaskScheduleState::Waiting && info.state != TaskScheduleState::Queued {
+                continue;
+            }
+            if info.next_time > now {
+                continue;
+            }
+
+            match Self::check_depends(&schedule_map, task_id, info) {
+                Some(true) => ready_list.push((info.next_time, task_id.clone())),
+                Some(false) => {}
+                None => failed_list.push(task_id.clone()),
+            }
+        }
+
+        for task_id in failed_list {
+            schedule_map.get_mut(&task_id).unwrap().state = TaskScheduleState::Failed;
+            changed.insert(task_id);
+        }
+
+        if !ready_list.is_empty() {
+            // 先到期的先启动
+            ready_list.sort();
+
+            let (mut category_count, mut dec_count) = self.get_running_count(&schedule_map).await;
+            let category_limits = self.category_limits.lock().unwrap().clone();
+            let dec_limits = self.dec_limits.lock().unwrap().clone();
+
+            for (_, task_id) in ready_list {
+                let depends_recurring = Self::is_depends_recurring(&schedule_map, &task_id);
+                let (category, dec_list) = match self.get_task_limit_info(&task_id).await {
+                    Ok(ret) => ret,
+                    Err(e) => {
+                        log::error!("get scheduled task info failed! task={}, {}", task_id, e);
+                        schedule_map.get_mut(&task_id).unwrap().state = TaskScheduleState::Failed;
+                        changed.insert(task_id);
+                        continue;
+                    }
+                };
+
+                let category_full = match category_limits.get(&category) {
+                    Some(limit) => *category_count.get(&category).unwrap_or(&0) >= *limit,
+                    None => false,
+                };
+                let dec_full = dec_list.iter().any(|dec_id| match dec_limits.get(dec_id) {
+                    Some(limit) => *dec_count.get(dec_id).unwrap_or(&0) >= *limit,
+                    None => false,
+                });
+
+                let info = schedule_map.get_mut(&task_id).unwrap();
+                if category_full || dec_full {
+                    if info.state != TaskScheduleState::Queued {
+                        log::info!("scheduled task queued for limit: task={}, category={}", task_id, category);
+                        info.state = TaskScheduleState::Queued;
+                        changed.insert(task_id);
+                    }
+                    continue;
+                }
+
+                match self.start_schedule_task(&task_id).await {
+                    Ok(()) => {
+                        log::info!("start scheduled task: task={}, category={}, retry_count={}", task_id, category, info.retry_count);
+                        info.state = TaskScheduleState::Running;
+                        info.last_run_time = now;
+
+                        *category_count.entry(category).or_insert(0) += 1;
+                        for dec_id in dec_list {
+                            *dec_count.entry(dec_id).or_insert(0) += 1;
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("start scheduled task failed! task={}, {}", task_id, e);
+                        info.on_failed(now, depends_recurring);
+                    }
+                }
+                changed.insert(task_id);
+            }
+        }
+
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        let mut current_map = self.schedule_map.lock().await;
+        for task_id in changed {
+            // 计算过程中被取消调度或者重新调度的任务忽略，避免覆盖新的调度信息
+            if let Some(current) = current_map.get_mut(&task_id) {
+                let info = schedule_map.remove(&task_id).unwrap();
+                if current.seq != info.seq {
+                    log::info!("task rescheduled while checking, ignore: task={}", task_id);
+                    continue;
+                }
+                if let Err(e) = self.task_manager_store.update_task_schedule(&task_id, &info).await {
+                    log::error!("save task schedule failed! task={}, {}", task_id, e);
+                }
+                *current = info;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn start_schedule_task(&self, task_id: &TaskId) -> BuckyResult<()> {
+        self.start_task(task_id).await?;
+
+        let task = {
+            let task_map = self.task_map.lock().await;
+            match task_map.get(task_id) {
+                Some(task_info) => task_info.task.clone(),
+                None => {
+                    let msg = format!("scheduled task not found after start! task={}", task_id);
+                    log::error!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+                }
+            }
+        };
+
+        // 等待本次运行结束，由下一次check_schedule处理结果
+        let complete_list = self.schedule_complete_list.clone();
+        let task_id = task_id.clone();
+        async_std::task::spawn(async move {
+            task.check_and_waiting_stop().await;
+            let status = task.get_task_status().await;
+            complete_list.lock().unwrap().push((task_id, status));
+        });
+
+        Ok(())
+    }
+
+    async fn get_running_count(&self, schedule_map: &HashMap<TaskId, TaskScheduleInfo>) -> (HashMap<TaskCategory, u32>, HashMap<ObjectId, u32>) {
+        let mut category_count = HashMap::new();
+        let mut dec_count = HashMap::new();
+
+        let task_map = self.task_map.lock().await;
+        for (task_id, task_info) in task_map.iter() {
+            let running = match schedule_map.get(task_id) {
+                Some(info) if info.state == TaskScheduleState::Running => true,
+                _ => task_info.task.get_task_status().await == TaskStatus::Running,
+            };
+            if !running {
+                continue;
+            }
+
+            *category_count.entry(task_info.task.get_task_category()).or_insert(0) += 1;
+            let dec_set: HashSet<&ObjectId> = task_info.dec_list.iter().map(|v| v.dec_id()).collect();
+            for dec_id in dec_set {
+                *dec_count.entry(dec_id.clone()).or_insert(0) += 1;
+            }
+        }
+
+        (category_count, dec_count)
+    }
+
+    async fn get_task_limit_info(&self, task_id: &TaskId) -> BuckyResult<(TaskCategory, Vec<ObjectId>)> {
+        let ret = {
+            let task_map = self.task_map.lock().await;
+            match task_map.get(task_id) {
+                Some(task_info) => Some((task_info.task.get_task_category(), task_info.dec_list.clone())),
+                None => None,
+            }
+        };
+
+        let (category, dec_list) = match ret {
+            Some(ret) => ret,
+            None => {
+                let (category, _task_type, _task_status, _task_param, _task_data) = self.task_manager_store.get_task(task_id).await?;
+                (category, self.task_manager_store.get_dec_list(task_id).await?)
+            }
+        };
+
+        let mut dec_id_list: Vec<ObjectId> = Vec::new();
+        for dec in dec_list.iter() {
+            if !dec_id_list.contains(dec.dec_id()) {
+                dec_id_list.push(dec.dec_id().clone());
+            }
+        }
+
+        Ok((category, dec_id_list))
+    }
+
+    // 依赖都在本任务上次启动之后成功过返回Some(true)，依赖不存在或者最终失败返回None
+    fn check_depends(schedule_map: &HashMap<TaskId, TaskScheduleInfo>, task_id: &TaskId, info: &TaskScheduleInfo) -> Option<bool> {
+        let mut ready = true;
+        for depend_id in info.depends.iter() {
+            match schedule_map.get(depend_id) {
+                Some(depend) => {
+                    if depend.state == TaskScheduleState::Failed || depend.state == TaskScheduleState::Canceled {
+                        log::warn!("scheduled task's depend failed or canceled! task={}, depend={}, state={:?}", task_id, depend_id, depend.state);
+                        return None;
+                    }
+                    if depend.last_success_time <= info.last_run_time {
+                        ready = false;
+                    }
+                }
+                None => {
+                    log::warn!("scheduled task's depend not found! task={}, depend={}", task_id, depend_id);
+                    return None;
+                }
+            }
+        }
+
+        Some(ready)
+    }
+
+    // 直接或者间接依赖了重复任务
+    fn is_depends_recurring(schedule_map: &HashMap<TaskId, TaskScheduleInfo>, task_id: &TaskId) -> bool {
+        match schedule_map.get(task_id) {
+            Some(info) => Self::find_depend(schedule_map, &info.depends, |_, depend| depend.schedule.is_recurring()),
+            None => false,
+        }
+    }
+
+    fn find_depend(schedule_map: &HashMap<TaskId, TaskScheduleInfo>, depends: &[TaskId], pred: impl Fn(&TaskId, &TaskScheduleInfo) -> bool) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = depends.to_vec();
+        while let Some(depend_id) = stack.pop() {
+            if !visited.insert(depend_id.clone()) {
+                continue;
+            }
+
+            if let Some(depend) = schedule_map.get(&depend_id) {
+                if pred(&depend_id, depend) {
+                    return true;
+                }
+                stack.extend(depend.depends.iter().cloned());
+            }
+        }
+
+        false
+    }
+
     fn add_dec(dec_list: &mut Vec<DecInfo>, new_dec: ObjectId, source: DeviceId) -> bool {
         let mut find = false;
         for dec in dec_list.iter_mut() {
@@ -492,3 +882,379 @@ pub mod test_task_manager {
         Ok(task_manager)
     }
 }
+
+#[cfg(test)]
+mod test_task_schedule {
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use cyfs_base::*;
+    use crate::*;
+
+    const TEST_TASK_TYPE: TaskType = TaskType(60001);
+
+    #[derive(Clone, RawEncode, RawDecode)]
+    struct TestTaskParam {
+        name: String,
+        category: u16,
+        run_ms: u32,
+        // 前fail_times次运行返回失败
+        fail_times: u32,
+    }
+
+    #[derive(Default)]
+    struct TestRecord {
+        // 每次运行的(name, 开始时间, 结束时间)，结束时间为0表示还在运行
+        runs: Vec<(String, u64, u64)>,
+        fails: HashMap<String, u32>,
+        running: HashMap<u16, u32>,
+        max_running: HashMap<u16, u32>,
+    }
+
+    impl TestRecord {
+        fn runs_of(&self, name: &str) -> Vec<(u64, u64)> {
+            self.runs.iter().filter(|(n, _, _)| n == name).map(|(_, start, end)| (*start, *end)).collect()
+        }
+    }
+
+    struct TestRunnable {
+        task_id: TaskId,
+        param: TestTaskParam,
+        record: Arc<Mutex<TestRecord>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Runnable for TestRunnable {
+        fn get_task_id(&self) -> TaskId {
+            self.task_id
+        }
+
+        fn get_task_type(&self) -> TaskType {
+            TEST_TASK_TYPE
+        }
+
+        fn get_task_category(&self) -> TaskCategory {
+            TaskCategory(self.param.category)
+        }
+
+        async fn set_task_store(&mut self, _task_store: Arc<dyn TaskStore>) {
+        }
+
+        async fn run(&self) -> BuckyResult<()> {
+            let index = {
+                let mut record = self.record.lock().unwrap();
+                record.runs.push((self.param.name.clone(), bucky_time_now(), 0));
+                let running = record.running.entry(self.param.category).or_insert(0);
+                *running += 1;
+                let running = *running;
+                let max_running = record.max_running.entry(self.param.category).or_insert(0);
+                *max_running = std::cmp::max(*max_running, running);
+                record.runs.len() - 1
+            };
+
+            async_std::task::sleep(Duration::from_millis(self.param.run_ms as u64)).await;
+
+            let mut record = self.record.lock().unwrap();
+            record.runs[index].2 = bucky_time_now();
+            *record.running.get_mut(&self.param.category).unwrap() -= 1;
+            let fails = record.fails.entry(self.param.name.clone()).or_insert(0);
+            if *fails < self.param.fail_times {
+                *fails += 1;
+                return Err(BuckyError::new(BuckyErrorCode::Failed, format!("test task {} failed", self.param.name)));
+            }
+            Ok(())
+        }
+
+        async fn get_task_detail_status(&self) -> BuckyResult<Vec<u8>> {
+            Ok(vec![])
+        }
+    }
+
+    struct TestTaskFactory {
+        record: Arc<Mutex<TestRecord>>,
+    }
+
+    #[async_trait::async_trait]
+    impl TaskFactory for TestTaskFactory {
+        fn get_task_type(&self) -> TaskType {
+            TEST_TASK_TYPE
+        }
+
+        async fn create(&self, params: &[u8]) -> BuckyResult<Box<dyn Task>> {
+            let param = TestTaskParam::clone_from_slice(params)?;
+            let task_id = TaskId::from(hash_data(params).as_slice());
+            Ok(Box::new(RunnableTask::new(TestRunnable {
+                task_id,
+                param,
+                record: self.record.clone(),
+            })))
+        }
+
+        async fn restore(&self, _task_status: TaskStatus, params: &[u8], _data: &[u8]) -> BuckyResult<Box<dyn Task>> {
+            self.create(params).await
+        }
+    }
+
+    struct TestContext {
+        task_manager: Arc<TaskManager>,
+        record: Arc<Mutex<TestRecord>>,
+        source: DeviceId,
+    }
+
+    impl TestContext {
+        async fn open(db_path: &std::path::Path) -> Self {
+            let store = Arc::new(SQLiteTaskStore::new(db_path).await.unwrap());
+            store.init().await.unwrap();
+            let task_manager = TaskManager::new(store.clone(), store).await.unwrap();
+
+            let record = Arc::new(Mutex::new(TestRecord::default()));
+            task_manager.register_task_factory(TestTaskFactory { record: record.clone() }).unwrap();
+
+            Self {
+                task_manager,
+                record,
+                source: DeviceId::from_str("5aSixgPXvhR4puWzFCHqvUXrjFWjxbq4y3thJVgZg6ty").unwrap(),
+            }
+        }
+
+        async fn new(name: &str) -> Self {
+            Self::open(&Self::db_path(name)).await
+        }
+
+        // 每个用例使用单独的数据库文件，:memory:在连接池的每个连接上都是不同的库
+        fn db_path(name: &str) -> std::path::PathBuf {
+            let path = std::env::temp_dir().join(format!("test-task-schedule-{}-{}.db", name, std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            path
+        }
+
+        async fn create(&self, dec: &str, name: &str, category: u16, run_ms: u32, fail_times: u32) -> TaskId {
+            let dec_id = ObjectIdDataBuilder::new().data(dec).build().unwrap();
+            let param = TestTaskParam {
+                name: name.to_owned(),
+                category,
+                run_ms,
+                fail_times,
+            };
+            self.task_manager.create_task(dec_id, self.source.clone(), TEST_TASK_TYPE, param).await.unwrap()
+        }
+
+        async fn state(&self, task_id: &TaskId) -> TaskScheduleState {
+            self.task_manager.get_task_schedule(task_id).await.unwrap().state
+        }
+
+        async fn wait_state(&self, task_id: &TaskId, state: TaskScheduleState) -> TaskScheduleInfo {
+            for _ in 0..300 {
+                let info = self.task_manager.get_task_schedule(task_id).await.unwrap();
+                if info.state == state {
+                    return info;
+                }
+                async_std::task::sleep(Duration::from_millis(100)).await;
+            }
+            panic!("wait task {} schedule state {:?} timeout", task_id, state);
+        }
+
+        fn runs_of(&self, name: &str) -> Vec<(u64, u64)> {
+            self.record.lock().unwrap().runs_of(name)
+        }
+    }
+
+    #[async_std::test]
+    async fn test_schedule_fire() {
+        let ctx = TestContext::new("fire").await;
+
+        let now_task = ctx.create("dec", "now", 1, 10, 0).await;
+        let delay_task = ctx.create("dec", "delay", 1, 10, 0).await;
+        let start_time = bucky_time_now() + 2 * 1000 * 1000;
+        ctx.task_manager.schedule_task(&now_task, TaskSchedule::Immediate, None, vec![]).await.unwrap();
+        ctx.task_manager.schedule_task(&delay_task, TaskSchedule::Delay(start_time), None, vec![]).await.unwrap();
+
+        ctx.wait_state(&now_task, TaskScheduleState::Succeeded).await;
+        assert_eq!(ctx.runs_of("now").len(), 1);
+
+        // 延迟任务到时间之后才启动，只运行一次
+        ctx.wait_state(&delay_task, TaskScheduleState::Succeeded).await;
+        let runs = ctx.runs_of("delay");
+        assert_eq!(runs.len(), 1);
+        assert!(runs[0].0 >= start_time);
+
+        // 重复任务成功之后等待下一次启动
+        let interval_task = ctx.create("dec", "interval", 1, 10, 0).await;
+        let schedule = TaskSchedule::Interval { start_time: bucky_time_now(), interval: 1 };
+        ctx.task_manager.schedule_task(&interval_task, schedule, None, vec![]).await.unwrap();
+        for _ in 0..100 {
+            if ctx.runs_of("interval").len() >= 2 {
+                break;
+            }
+            async_std::task::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(ctx.runs_of("interval").len() >= 2);
+        assert_ne!(ctx.state(&interval_task).await, TaskScheduleState::Succeeded);
+    }
+
+    #[async_std::test]
+    async fn test_schedule_category_limit() {
+        let ctx = TestContext::new("category").await;
+        ctx.task_manager.set_category_limit(TaskCategory(7), 1);
+
+        let mut list = vec![];
+        for i in 0..3 {
+            let task_id = ctx.create("dec", &format!("task{}", i), 7, 1500, 0).await;
+            ctx.task_manager.schedule_task(&task_id, TaskSchedule::Immediate, None, vec![]).await.unwrap();
+            list.push(task_id);
+        }
+
+        // 同一次调度里只有一个启动，其它的排队
+        let mut states = vec![];
+        for _ in 0..100 {
+            states.clear();
+            for task_id in list.iter() {
+                states.push(ctx.state(task_id).await);
+            }
+            if states.contains(&TaskScheduleState::Running) {
+                break;
+            }
+            async_std::task::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(states.iter().filter(|s| **s == TaskScheduleState::Running).count(), 1);
+        assert_eq!(states.iter().filter(|s| **s == TaskScheduleState::Queued).count(), 2);
+
+        for task_id in list.iter() {
+            ctx.wait_state(task_id, TaskScheduleState::Succeeded).await;
+        }
+        let record = ctx.record.lock().unwrap();
+        assert_eq!(record.runs.len(), 3);
+        assert_eq!(record.max_running[&7], 1);
+    }
+
+    #[async_std::test]
+    async fn test_schedule_dec_limit() {
+        let ctx = TestContext::new("dec").await;
+        let dec_a = ObjectIdDataBuilder::new().data("dec-a").build().unwrap();
+        ctx.task_manager.set_dec_limit(dec_a, 1);
+
+        // dec-a的两个任务在不同的category，只受dec的限制
+        let a1 = ctx.create("dec-a", "a1", 1, 1500, 0).await;
+        let a2 = ctx.create("dec-a", "a2", 2, 1500, 0).await;
+        let b1 = ctx.create("dec-b", "b1", 1, 1500, 0).await;
+        for task_id in [&a1, &a2, &b1] {
+            ctx.task_manager.schedule_task(task_id, TaskSchedule::Immediate, None, vec![]).await.unwrap();
+        }
+
+        ctx.wait_state(&b1, TaskScheduleState::Running).await;
+        let states = [ctx.state(&a1).await, ctx.state(&a2).await];
+        assert!(states.contains(&TaskScheduleState::Running));
+        assert!(states.contains(&TaskScheduleState::Queued));
+
+        ctx.wait_state(&a1, TaskScheduleState::Succeeded).await;
+        ctx.wait_state(&a2, TaskScheduleState::Succeeded).await;
+        let a1_run = ctx.runs_of("a1")[0];
+        let a2_run = ctx.runs_of("a2")[0];
+        assert!(a1_run.0 >= a2_run.1 || a2_run.0 >= a1_run.1);
+    }
+
+    #[async_std::test]
+    async fn test_schedule_depends() {
+        let ctx = TestContext::new("depends").await;
+
+        let parent = ctx.create("dec", "parent", 1, 1000, 0).await;
+        let child = ctx.create("dec", "child", 1, 10, 0).await;
+        ctx.task_manager.schedule_task(&parent, TaskSchedule::Immediate, None, vec![]).await.unwrap();
+        ctx.task_manager.schedule_task(&child, TaskSchedule::Immediate, None, vec![parent.clone()]).await.unwrap();
+
+        // 依赖运行期间子任务一直等待
+        ctx.wait_state(&parent, TaskScheduleState::Running).await;
+        assert_eq!(ctx.state(&child).await, TaskScheduleState::Waiting);
+        assert!(ctx.runs_of("child").is_empty());
+
+        ctx.wait_state(&child, TaskScheduleState::Succeeded).await;
+        let parent_run = ctx.runs_of("parent")[0];
+        let child_run = ctx.runs_of("child")[0];
+        assert!(child_run.0 >= parent_run.1);
+
+        // 依赖最终失败的话子任务也失败，不会启动
+        let parent = ctx.create("dec", "failed-parent", 1, 10, 1).await;
+        let child = ctx.create("dec", "failed-child", 1, 10, 0).await;
+        ctx.task_manager.schedule_task(&parent, TaskSchedule::Immediate, None, vec![]).await.unwrap();
+        ctx.task_manager.schedule_task(&child, TaskSchedule::Immediate, None, vec![parent.clone()]).await.unwrap();
+
+        ctx.wait_state(&parent, TaskScheduleState::Failed).await;
+        ctx.wait_state(&child, TaskScheduleState::Failed).await;
+        assert!(ctx.runs_of("failed-child").is_empty());
+
+        // 依赖还没有调度的任务不能调度
+        let orphan = ctx.create("dec", "orphan", 1, 10, 0).await;
+        let unscheduled = ctx.create("dec", "unscheduled", 1, 10, 0).await;
+        let ret = ctx.task_manager.schedule_task(&orphan, TaskSchedule::Immediate, None, vec![unscheduled]).await;
+        assert_eq!(ret.unwrap_err().code(), BuckyErrorCode::NotFound);
+    }
+
+    #[async_std::test]
+    async fn test_schedule_retry() {
+        let ctx = TestContext::new("retry").await;
+
+        // 失败两次，第三次成功，每次重试前至少等待1秒
+        let task_id = ctx.create("dec", "retry", 1, 10, 2).await;
+        let retry = TaskRetryPolicy::new(3, 1, 1);
+        ctx.task_manager.schedule_task(&task_id, TaskSchedule::Immediate, Some(retry), vec![]).await.unwrap();
+
+        ctx.wait_state(&task_id, TaskScheduleState::Succeeded).await;
+        let runs = ctx.runs_of("retry");
+        assert_eq!(runs.len(), 3);
+        for i in 1..runs.len() {
+            assert!(runs[i].0 >= runs[i - 1].1 + 1000 * 1000);
+        }
+        assert_eq!(ctx.task_manager.get_task_schedule(&task_id).await.unwrap().retry_count, 0);
+
+        // 重试次数用完后失败
+        let task_id = ctx.create("dec", "exhausted", 1, 10, 5).await;
+        let retry = TaskRetryPolicy::new(1, 1, 1);
+        ctx.task_manager.schedule_task(&task_id, TaskSchedule::Immediate, Some(retry), vec![]).await.unwrap();
+
+        ctx.wait_state(&task_id, TaskScheduleState::Failed).await;
+        assert_eq!(ctx.runs_of("exhausted").len(), 2);
+    }
+
+    #[async_std::test]
+    async fn test_schedule_restore() {
+        let db_path = TestContext::db_path("restore");
+
+        let (running, delayed, delay_time) = {
+            let ctx = TestContext::open(&db_path).await;
+
+            let running = ctx.create("dec", "running", 1, 60 * 1000, 0).await;
+            let delayed = ctx.create("dec", "delayed", 1, 10, 0).await;
+            let delay_time = bucky_time_now() + 3600 * 1000 * 1000;
+            let retry = TaskRetryPolicy::new(2, 1, 1);
+            ctx.task_manager.schedule_task(&running, TaskSchedule::Immediate, Some(retry.clone()), vec![]).await.unwrap();
+            ctx.task_manager.schedule_task(&delayed, TaskSchedule::Delay(delay_time), Some(retry), vec![running.clone()]).await.unwrap();
+
+            ctx.wait_state(&running, TaskScheduleState::Running).await;
+            (running, delayed, delay_time)
+        };
+
+        // 等待上一个task manager的调度协程退出
+        async_std::task::sleep(Duration::from_secs(2)).await;
+
+        // 重启后调度信息从TaskStore恢复，退出时还在运行的任务重新启动
+        let ctx = TestContext::open(&db_path).await;
+        let info = ctx.task_manager.get_task_schedule(&delayed).await.unwrap();
+        assert_eq!(info.state, TaskScheduleState::Waiting);
+        assert_eq!(info.next_time, delay_time);
+        assert_eq!(info.depends, vec![running.clone()]);
+        assert_eq!(info.retry.unwrap().max_retry, 2);
+
+        ctx.wait_state(&running, TaskScheduleState::Running).await;
+        for _ in 0..100 {
+            if !ctx.runs_of("running").is_empty() {
+                break;
+            }
+            async_std::task::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(ctx.runs_of("running").len(), 1);
+
+        ctx.task_manager.unschedule_task(&delayed).await.unwrap();
+        ctx.task_manager.unschedule_task(&running).await.unwrap();
+    }
+}
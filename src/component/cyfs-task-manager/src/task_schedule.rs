@@ -0,0 +1,434 @@
+use cyfs_base::*;
+use crate::TaskId;
+
+/*
+任务调度
+1. schedule决定任务的启动时间：立即、延迟到指定时间、固定间隔重复、cron表达式重复
+2. depends里的任务都在本任务上次启动之后成功过，本任务才会启动；依赖任务最终失败的话本任务也失败
+3. 同一个category、同一个dec同时运行的任务数受限，超出的任务排队等待
+4. 任务失败按照retry策略指数退避重试，重试次数用完后如果是重复任务则等待下一次调度
+*/
+#[derive(Clone, Debug, RawEncode, RawDecode)]
+pub enum TaskSchedule {
+    Immediate,
+
+    // 延迟到指定的bucky_time启动
+    Delay(u64),
+
+    // 从start_time(bucky_time)开始，每隔interval秒启动一次
+    Interval { start_time: u64, interval: u32 },
+
+    // "分 时 日 月 周"格式的cron表达式，使用UTC时间
+    Cron(String),
+}
+
+impl TaskSchedule {
+    pub fn check(&self) -> BuckyResult<()> {
+        match self {
+            Self::Interval { interval, .. } => {
+                if *interval == 0 {
+                    let msg = format!("task schedule interval should not be zero!");
+                    log::error!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+                }
+                Ok(())
+            }
+            Self::Cron(expr) => {
+                CronSchedule::parse(expr)?;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_recurring(&self) -> bool {
+        match self {
+            Self::Interval { .. } | Self::Cron(_) => true,
+            _ => false,
+        }
+    }
+
+    // 首次启动的时间
+    pub fn first_time(&self, now: u64) -> BuckyResult<u64> {
+        match self {
+            Self::Immediate => Ok(now),
+            Self::Delay(time) => Ok(*time),
+            Self::Interval { start_time, .. } => Ok(*start_time),
+            Self::Cron(expr) => {
+                let cron = CronSchedule::parse(expr)?;
+                cron.next_time(now).ok_or_else(|| {
+                    let msg = format!("task schedule cron never fires! cron={}", expr);
+                    log::error!("{}", msg);
+                    BuckyError::new(BuckyErrorCode::InvalidParam, msg)
+                })
+            }
+        }
+    }
+
+    // 重复任务now之后的下一次启动时间，非重复任务返回None
+    pub fn next_time(&self, now: u64) -> Option<u64> {
+        match self {
+            Self::Interval { start_time, interval } => {
+                let interval = *interval as u64 * 1000 * 1000;
+                if now < *start_time {
+                    Some(*start_time)
+                } else {
+                    Some(start_time + ((now - start_time) / interval + 1) * interval)
+                }
+            }
+            Self::Cron(expr) => match CronSchedule::parse(expr) {
+                Ok(cron) => cron.next_time(now),
+                Err(_) => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, RawEncode, RawDecode)]
+pub struct TaskRetryPolicy {
+    pub max_retry: u32,
+
+    // 首次重试的延迟(秒)，之后每次翻倍，不超过max_delay
+    pub delay: u32,
+    pub max_delay: u32,
+}
+
+impl TaskRetryPolicy {
+    pub fn new(max_retry: u32, delay: u32, max_delay: u32) -> Self {
+        Self {
+            max_retry,
+            delay,
+            max_delay,
+        }
+    }
+
+    // 第retry_count次重试前的等待时间，单位微秒
+    pub fn retry_delay(&self, retry_count: u32) -> u64 {
+        let delay = (self.delay as u64) << std::cmp::min(retry_count.saturating_sub(1), 20);
+        std::cmp::min(delay, self.max_delay as u64) * 1000 * 1000
+    }
+}
+
+#[derive(Clone, Debug, RawEncode, RawDecode)]
+pub(crate) struct TaskScheduleConfig {
+    pub schedule: TaskSchedule,
+    pub retry: Option<TaskRetryPolicy>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TaskScheduleState {
+    // 等待启动时间或者依赖任务
+    Waiting,
+    // 已经可以启动，由于并发限制在排队
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    // 运行中被用户暂停或者停止
+    Canceled,
+}
+
+impl TaskScheduleState {
+    pub fn into(self) -> i32 {
+        match self {
+            Self::Waiting => 0,
+            Self::Queued => 1,
+            Self::Running => 2,
+            Self::Succeeded => 3,
+            Self::Failed => 4,
+            Self::Canceled => 5,
+        }
+    }
+
+    pub fn try_from(value: i32) -> BuckyResult<Self> {
+        match value {
+            0 => Ok(Self::Waiting),
+            1 => Ok(Self::Queued),
+            2 => Ok(Self::Running),
+            3 => Ok(Self::Succeeded),
+            4 => Ok(Self::Failed),
+            5 => Ok(Self::Canceled),
+            _ => {
+                let msg = format!("unsupport task schedule state {}", value);
+                log::error!("{}", msg.as_str());
+                Err(BuckyError::new(BuckyErrorCode::NotSupport, msg))
+            }
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        *self == Self::Succeeded || *self == Self::Failed || *self == Self::Canceled
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TaskScheduleInfo {
+    pub schedule: TaskSchedule,
+    pub retry: Option<TaskRetryPolicy>,
+    pub depends: Vec<TaskId>,
+
+    pub state: TaskScheduleState,
+    pub next_time: u64,
+    pub retry_count: u32,
+    pub last_run_time: u64,
+    pub last_success_time: u64,
+
+    // 每次调用schedule_task时递增，用于检查调度计算过程中任务是否被重新调度，不持久化
+    pub(crate) seq: u64,
+}
+
+impl TaskScheduleInfo {
+    pub fn new(schedule: TaskSchedule, retry: Option<TaskRetryPolicy>, depends: Vec<TaskId>) -> BuckyResult<Self> {
+        schedule.check()?;
+        let next_time = schedule.first_time(bucky_time_now())?;
+
+        Ok(Self {
+            schedule,
+            retry,
+            depends,
+            state: TaskScheduleState::Waiting,
+            next_time,
+            retry_count: 0,
+            last_run_time: 0,
+            last_success_time: 0,
+            seq: 0,
+        })
+    }
+
+    pub(crate) fn config(&self) -> TaskScheduleConfig {
+        TaskScheduleConfig {
+            schedule: self.schedule.clone(),
+            retry: self.retry.clone(),
+        }
+    }
+
+    // 本次运行成功，重复任务计算下一次启动时间
+    pub(crate) fn on_success(&mut self, now: u64, depends_recurring: bool) {
+        self.last_success_time = now;
+        self.retry_count = 0;
+        if !self.wait_next(now, depends_recurring) {
+            self.state = TaskScheduleState::Succeeded;
+        }
+    }
+
+    // 本次运行失败，还有重试次数的话退避后重试
+    pub(crate) fn on_failed(&mut self, now: u64, depends_recurring: bool) {
+        if let Some(retry) = &self.retry {
+            if self.retry_count < retry.max_retry {
+                self.retry_count += 1;
+                self.next_time = now + retry.retry_delay(self.retry_count);
+                self.state = TaskScheduleState::Waiting;
+                return;
+            }
+        }
+
+        self.retry_count = 0;
+        if !self.wait_next(now, depends_recurring) {
+            self.state = TaskScheduleState::Failed;
+        }
+    }
+
+    // 本次运行被用户暂停或者停止，不算失败也不重试，重复任务等待下一次启动
+    pub(crate) fn on_canceled(&mut self, now: u64, depends_recurring: bool) {
+        self.retry_count = 0;
+        if !self.wait_next(now, depends_recurring) {
+            self.state = TaskScheduleState::Canceled;
+        }
+    }
+
+    fn wait_next(&mut self, now: u64, depends_recurring: bool) -> bool {
+        if let Some(next_time) = self.schedule.next_time(now) {
+            self.next_time = next_time;
+        } else if depends_recurring {
+            // 依赖的是重复任务，等待依赖下一次成功后再次启动
+            self.next_time = now;
+        } else {
+            return false;
+        }
+
+        self.state = TaskScheduleState::Waiting;
+        true
+    }
+}
+
+// cron表达式，五个字段依次为：分(0-59) 时(0-23) 日(1-31) 月(1-12) 周(0-6，0为周日)
+// 每个字段支持 * a a-b */n a-b/n 以及逗号分隔的列表
+// 日和周都不是*的时候，两者满足其一即可，和标准cron一致
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> BuckyResult<Self> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            let msg = format!("invalid cron expression, should have 5 fields: {}", expr);
+            log::error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidFormat, msg));
+        }
+
+        Ok(Self {
+            minutes: Self::parse_field(expr, fields[0], 0, 59)?,
+            hours: Self::parse_field(expr, fields[1], 0, 23)?,
+            days: Self::parse_field(expr, fields[2], 1, 31)?,
+            months: Self::parse_field(expr, fields[3], 1, 12)?,
+            weekdays: Self::parse_field(expr, fields[4], 0, 6)?,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    fn parse_field(expr: &str, field: &str, min: u32, max: u32) -> BuckyResult<u64> {
+        let invalid = || {
+            let msg = format!("invalid cron field: expr={}, field={}", expr, field);
+            log::error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+        };
+
+        let mut mask = 0u64;
+        for item in field.split(',') {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+                None => (item, 1),
+            };
+            if step == 0 {
+                return Err(invalid());
+            }
+
+            let (begin, end) = if range == "*" {
+                (min, max)
+            } else if let Some((begin, end)) = range.split_once('-') {
+                (
+                    begin.parse::<u32>().map_err(|_| invalid())?,
+                    end.parse::<u32>().map_err(|_| invalid())?,
+                )
+            } else {
+                let value = range.parse::<u32>().map_err(|_| invalid())?;
+                (value, value)
+            };
+            if begin < min || end > max || begin > end {
+                return Err(invalid());
+            }
+
+            let mut value = begin;
+            while value <= end {
+                mask |= 1 << value;
+                value += step;
+            }
+        }
+
+        Ok(mask)
+    }
+
+    fn match_day(&self, month: u32, day: u32, weekday: u32) -> bool {
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+
+        let day_match = self.days & (1 << day) != 0;
+        let weekday_match = self.weekdays & (1 << weekday) != 0;
+        if !self.any_day && !self.any_weekday {
+            day_match || weekday_match
+        } else {
+            day_match && weekday_match
+        }
+    }
+
+    // time之后(不包括time所在的分钟)第一个满足的时间，最多向后查找五年
+    pub fn next_time(&self, time: u64) -> Option<u64> {
+        let start = bucky_time_to_unix_time(time) / 1000 / 1000 / 60 + 1;
+        let start_day = start / 1440;
+        for day in start_day..start_day + 366 * 5 {
+            let (_, month, mday) = Self::civil_from_days(day as i64);
+            // 1970-01-01是周四
+            let weekday = ((day + 4) % 7) as u32;
+            if !self.match_day(month, mday, weekday) {
+                continue;
+            }
+
+            let begin = if day == start_day { start % 1440 } else { 0 };
+            for minute in begin..1440 {
+                if self.hours & (1 << (minute / 60)) != 0 && self.minutes & (1 << (minute % 60)) != 0 {
+                    let unix_time = (day * 1440 + minute) * 60 * 1000 * 1000;
+                    return Some(unix_time_to_bucky_time(unix_time));
+                }
+            }
+        }
+
+        None
+    }
+
+    // 从1970-01-01开始的天数转换为(年, 月, 日)
+    fn civil_from_days(days: i64) -> (i64, u32, u32) {
+        let z = days + 719468;
+        let era = if z >= 0 { z } else { z - 146096 } / 146097;
+        let doe = (z - era * 146097) as u64;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe as i64 + era * 400;
+
+        (if month <= 2 { year + 1 } else { year }, month, day)
+    }
+}
+
+#[cfg(test)]
+mod test_schedule {
+    use super::*;
+
+    fn bucky_time(secs: u64) -> u64 {
+        unix_time_to_bucky_time(secs * 1000 * 1000)
+    }
+
+    #[test]
+    fn test_cron() {
+        assert!(CronSchedule::parse("* * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+
+        // 2023-01-01 00:00:00 UTC，周日
+        let base = 1672531200;
+
+        let cron = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(cron.next_time(bucky_time(base)), Some(bucky_time(base + 15 * 60)));
+        assert_eq!(cron.next_time(bucky_time(base + 16 * 60)), Some(bucky_time(base + 30 * 60)));
+
+        let cron = CronSchedule::parse("30 2 * * *").unwrap();
+        assert_eq!(cron.next_time(bucky_time(base)), Some(bucky_time(base + 2 * 3600 + 30 * 60)));
+
+        // 每周一 03:00
+        let cron = CronSchedule::parse("0 3 * * 1").unwrap();
+        assert_eq!(cron.next_time(bucky_time(base)), Some(bucky_time(base + 86400 + 3 * 3600)));
+
+        // 每月1号 00:00
+        let cron = CronSchedule::parse("0 0 1 * *").unwrap();
+        assert_eq!(cron.next_time(bucky_time(base)), Some(bucky_time(base + 31 * 86400)));
+
+        let cron = CronSchedule::parse("0 0 30 2 *").unwrap();
+        assert_eq!(cron.next_time(bucky_time(base)), None);
+    }
+
+    #[test]
+    fn test_schedule() {
+        let schedule = TaskSchedule::Interval { start_time: bucky_time(100), interval: 10 };
+        assert_eq!(schedule.next_time(bucky_time(50)), Some(bucky_time(100)));
+        assert_eq!(schedule.next_time(bucky_time(100)), Some(bucky_time(110)));
+        assert_eq!(schedule.next_time(bucky_time(125)), Some(bucky_time(130)));
+        assert_eq!(TaskSchedule::Delay(bucky_time(100)).next_time(bucky_time(50)), None);
+
+        let retry = TaskRetryPolicy::new(5, 2, 10);
+        assert_eq!(retry.retry_delay(1), 2 * 1000 * 1000);
+        assert_eq!(retry.retry_delay(2), 4 * 1000 * 1000);
+        assert_eq!(retry.retry_delay(4), 10 * 1000 * 1000);
+    }
+}
@@ -1,7 +1,7 @@
 use std::path::{Path};
 use std::str::FromStr;
 use cyfs_base::*;
-use crate::{DecInfo, sql_query, SqlConnection, SqlPool, TaskCategory, TaskId, TaskStatus, TaskType, SqlRow, RawSqlPool};
+use crate::{DecInfo, sql_query, SqlConnection, SqlPool, TaskCategory, TaskId, TaskStatus, TaskType, SqlRow, RawSqlPool, TaskScheduleInfo, TaskScheduleConfig, TaskScheduleState};
 
 #[async_trait::async_trait]
 pub trait TaskStore: Send + Sync {
@@ -26,6 +26,10 @@ pub trait TaskManagerStore: Send + Sync {
     async fn add_dec_info(&self, task_id: &TaskId, category: TaskCategory, task_status: TaskStatus, dec_info: &DecInfo) -> BuckyResult<()>;
     async fn delete_dec_info(&self, task_id: &TaskId, dec_id: &ObjectId, source: &DeviceId) -> BuckyResult<()>;
     async fn delete_task(&self, task_id: &TaskId) -> BuckyResult<()>;
+    async fn add_task_schedule(&self, task_id: &TaskId, info: &TaskScheduleInfo) -> BuckyResult<()>;
+    async fn update_task_schedule(&self, task_id: &TaskId, info: &TaskScheduleInfo) -> BuckyResult<()>;
+    async fn get_task_schedules(&self) -> BuckyResult<Vec<(TaskId, TaskScheduleInfo)>>;
+    async fn delete_task_schedule(&self, task_id: &TaskId) -> BuckyResult<()>;
 }
 
 pub struct SQLiteTaskStore {
@@ -91,6 +95,29 @@ impl SQLiteTaskStore {
         let sql = r#"create index if not exists task_index on dec_tasks (task_id)"#;
         conn.execute_sql(sql_query(sql)).await?;
 
+        let sql = r#"create table if not exists "task_schedules" (
+            "task_id" char(45) primary key not null,
+            "schedule" BLOB not null,
+            "schedule_state" INTEGER,
+            "next_time" INTEGER,
+            "retry_count" INTEGER,
+            "last_run_time" INTEGER,
+            "last_success_time" INTEGER,
+            "created_at" TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            "updated_at" TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )"#;
+        conn.execute_sql(sql_query(sql)).await?;
+
+        let sql = r#"create table if not exists "task_depends" (
+            "task_id" char(45) not null,
+            "depend_id" char(45) not null,
+            "created_at" TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )"#;
+        conn.execute_sql(sql_query(sql)).await?;
+
+        let sql = r#"create index if not exists depend_index on task_depends (task_id)"#;
+        conn.execute_sql(sql_query(sql)).await?;
+
         Ok(())
     }
 }
@@ -334,8 +361,107 @@ impl TaskManagerStore for SQLiteTaskStore
         let sql = r#"delete from dec_tasks where task_id = ?1"#;
         conn.execute_sql(sql_query(sql).bind(task_id.to_string())).await?;
 
+        let sql = r#"delete from task_schedules where task_id = ?1"#;
+        conn.execute_sql(sql_query(sql).bind(task_id.to_string())).await?;
+
+        let sql = r#"delete from task_depends where task_id = ?1"#;
+        conn.execute_sql(sql_query(sql).bind(task_id.to_string())).await?;
+
+        conn.commit_transaction().await?;
+
+        Ok(())
+    }
+
+    async fn add_task_schedule(&self, task_id: &TaskId, info: &TaskScheduleInfo) -> BuckyResult<()> {
+        info!("will add task schedule: id={}, schedule={:?}, retry={:?}, depends={:?}", task_id, info.schedule, info.retry, info.depends);
+
+        let mut conn = self.pool.get_conn().await?;
+        conn.begin_transaction().await?;
+
+        let sql = r#"delete from task_schedules where task_id = ?1"#;
+        conn.execute_sql(sql_query(sql).bind(task_id.to_string())).await?;
+
+        let sql = r#"delete from task_depends where task_id = ?1"#;
+        conn.execute_sql(sql_query(sql).bind(task_id.to_string())).await?;
+
+        let sql = r#"insert into task_schedules (task_id, schedule, schedule_state, next_time, retry_count, last_run_time, last_success_time) values (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#;
+        conn.execute_sql(sql_query(sql)
+            .bind(task_id.to_string())
+            .bind(info.config().to_vec()?)
+            .bind(info.state.into())
+            .bind(info.next_time as i64)
+            .bind(info.retry_count as i64)
+            .bind(info.last_run_time as i64)
+            .bind(info.last_success_time as i64)).await?;
+
+        for depend_id in info.depends.iter() {
+            let sql = r#"insert into task_depends (task_id, depend_id) values (?1, ?2)"#;
+            conn.execute_sql(sql_query(sql)
+                .bind(task_id.to_string())
+                .bind(depend_id.to_string())).await?;
+        }
+
         conn.commit_transaction().await?;
+        Ok(())
+    }
+
+    async fn update_task_schedule(&self, task_id: &TaskId, info: &TaskScheduleInfo) -> BuckyResult<()> {
+        let mut conn = self.pool.get_conn().await?;
+        let sql = r#"update task_schedules set schedule_state = ?1, next_time = ?2, retry_count = ?3, last_run_time = ?4, last_success_time = ?5, updated_at = CURRENT_TIMESTAMP where task_id = ?6"#;
+        conn.execute_sql(sql_query(sql)
+            .bind(info.state.into())
+            .bind(info.next_time as i64)
+            .bind(info.retry_count as i64)
+            .bind(info.last_run_time as i64)
+            .bind(info.last_success_time as i64)
+            .bind(task_id.to_string())).await?;
+        Ok(())
+    }
+
+    async fn get_task_schedules(&self) -> BuckyResult<Vec<(TaskId, TaskScheduleInfo)>> {
+        let mut conn = self.pool.get_conn().await?;
+        let sql = r#"select * from task_schedules"#;
+        let rows = conn.query_all(sql_query(sql)).await?;
+        let mut list = Vec::new();
+        for row in rows.iter() {
+            let task_id = TaskId::from_str(row.get("task_id"))?;
+            let config = TaskScheduleConfig::clone_from_slice(row.get("schedule"))?;
+
+            let sql = r#"select depend_id from task_depends where task_id = ?1"#;
+            let depend_rows = conn.query_all(sql_query(sql).bind(task_id.to_string())).await?;
+            let mut depends = Vec::new();
+            for depend_row in depend_rows.iter() {
+                depends.push(TaskId::from_str(depend_row.get("depend_id"))?);
+            }
+
+            list.push((task_id, TaskScheduleInfo {
+                schedule: config.schedule,
+                retry: config.retry,
+                depends,
+                state: TaskScheduleState::try_from(row.get("schedule_state"))?,
+                next_time: row.get::<i64, _>("next_time") as u64,
+                retry_count: row.get::<i64, _>("retry_count") as u32,
+                last_run_time: row.get::<i64, _>("last_run_time") as u64,
+                last_success_time: row.get::<i64, _>("last_success_time") as u64,
+                seq: 0,
+            }));
+        }
+        Ok(list)
+    }
 
+    async fn delete_task_schedule(&self, task_id: &TaskId) -> BuckyResult<()> {
+        info!("will delete task schedule! id={}", task_id);
+
+        let mut conn = self.pool.get_conn().await?;
+        conn.begin_transaction().await?;
+
+        let sql = r#"delete from task_schedules where task_id = ?1"#;
+        conn.execute_sql(sql_query(sql).bind(task_id.to_string())).await?;
+
+        let sql = r#"delete from task_depends where task_id = ?1"#;
+        conn.execute_sql(sql_query(sql).bind(task_id.to_string())).await?;
+
+        conn.commit_transaction().await?;
         Ok(())
     }
 }
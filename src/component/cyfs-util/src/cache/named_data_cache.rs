@@ -224,6 +224,24 @@ pub struct GetChunkRefObjectsResponse {
 #[derive(Clone, Debug)]
 pub struct SelectChunkFilter {
     pub state: Option<ChunkState>,
+
+    // only select the chunks which last_access_time < last_access_before
+    pub last_access_before: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SelectChunkOrder {
+    // insert_time desc
+    InsertTime,
+
+    // last_access_time asc, the least recently used first
+    LastAccessTime,
+}
+
+impl Default for SelectChunkOrder {
+    fn default() -> Self {
+        Self::InsertTime
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -233,6 +251,8 @@ pub struct SelectChunkOption {
 
     // The page number currently read, starting from 0
     pub page_index: usize,
+
+    pub order: SelectChunkOrder,
 }
 
 impl Default for SelectChunkOption {
@@ -240,6 +260,7 @@ impl Default for SelectChunkOption {
         Self {
             page_size: 256,
             page_index: 0,
+            order: SelectChunkOrder::default(),
         }
     }
 }
@@ -0,0 +1,317 @@
+use cyfs_base::*;
+
+use futures::{AsyncRead, AsyncReadExt};
+use serde_json::{Map, Value};
+
+/*
+基于内容的分块(FastCDC)
+1. 使用gear hash滚动计算，hash的高位满足掩码时切分，切分点只和附近的内容相关，文件中间插入数据只影响附近的chunk
+2. 小于avg_size时使用更严格的掩码，大于avg_size后使用更宽松的掩码(normalized chunking)，使chunk长度集中在avg_size附近
+3. chunk长度在[min_size, max_size]之间，最后一个chunk可能小于min_size
+*/
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CdcChunkParams {
+    pub min_size: u32,
+    pub avg_size: u32,
+    pub max_size: u32,
+}
+
+// 切分点需要足够的字节来积累gear hash
+const CDC_MIN_CHUNK_SIZE: u32 = 64;
+const CDC_MAX_CHUNK_SIZE: u32 = 1024 * 1024 * 64;
+
+impl CdcChunkParams {
+    pub fn new(min_size: u32, avg_size: u32, max_size: u32) -> Self {
+        Self {
+            min_size,
+            avg_size,
+            max_size,
+        }
+    }
+
+    // 以avg_size为中心，min_size=avg/4，max_size=avg*4
+    pub fn with_avg_size(avg_size: u32) -> Self {
+        Self::new(avg_size / 4, avg_size, avg_size.saturating_mul(4))
+    }
+
+    pub fn check(&self) -> BuckyResult<()> {
+        if self.min_size < CDC_MIN_CHUNK_SIZE
+            || self.min_size > self.avg_size
+            || self.avg_size > self.max_size
+            || self.max_size > CDC_MAX_CHUNK_SIZE
+        {
+            let msg = format!(
+                "invalid cdc chunk params, should be {} <= min <= avg <= max <= {}: {:?}",
+                CDC_MIN_CHUNK_SIZE, CDC_MAX_CHUNK_SIZE, self
+            );
+            log::error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        Ok(())
+    }
+}
+
+impl JsonCodec<CdcChunkParams> for CdcChunkParams {
+    fn encode_json(&self) -> Map<String, Value> {
+        let mut obj = Map::new();
+        JsonCodecHelper::encode_number_field(&mut obj, "min_size", self.min_size);
+        JsonCodecHelper::encode_number_field(&mut obj, "avg_size", self.avg_size);
+        JsonCodecHelper::encode_number_field(&mut obj, "max_size", self.max_size);
+
+        obj
+    }
+
+    fn decode_json(obj: &Map<String, Value>) -> BuckyResult<Self> {
+        Ok(Self {
+            min_size: JsonCodecHelper::decode_int_field(obj, "min_size")?,
+            avg_size: JsonCodecHelper::decode_int_field(obj, "avg_size")?,
+            max_size: JsonCodecHelper::decode_int_field(obj, "max_size")?,
+        })
+    }
+}
+
+// gear表使用splitmix64固定种子生成，切分结果需要在不同版本之间保持一致，不能修改
+const fn gen_gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut seed = 0x4359_4653_4344_4331u64;
+    let mut i = 0;
+    while i < 256 {
+        seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+
+    table
+}
+
+const GEAR: [u64; 256] = gen_gear_table();
+
+pub struct CdcChunker {
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+    mask_s: u64,
+    mask_l: u64,
+}
+
+impl CdcChunker {
+    pub fn new(params: &CdcChunkParams) -> BuckyResult<Self> {
+        params.check()?;
+
+        let bits = 32 - params.avg_size.leading_zeros() - 1;
+        Ok(Self {
+            min_size: params.min_size as usize,
+            avg_size: params.avg_size as usize,
+            max_size: params.max_size as usize,
+            mask_s: Self::mask(bits + 2),
+            mask_l: Self::mask(bits.saturating_sub(2)),
+        })
+    }
+
+    // 使用hash的高位，高位受最近64个字节的影响
+    fn mask(bits: u32) -> u64 {
+        let bits = std::cmp::min(std::cmp::max(bits, 1), 63);
+        ((1u64 << bits) - 1) << (64 - bits)
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    // 返回第一个chunk的长度；data不足max_size时调用方需要保证已经到达文件末尾
+    pub fn cut(&self, data: &[u8]) -> usize {
+        let len = data.len();
+        if len <= self.min_size {
+            return len;
+        }
+
+        let end = std::cmp::min(len, self.max_size);
+        let normal = std::cmp::min(end, self.avg_size);
+
+        let mut hash = 0u64;
+        let mut i = self.min_size;
+        while i < normal {
+            hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+            if hash & self.mask_s == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+
+        while i < end {
+            hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+            if hash & self.mask_l == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+
+        end
+    }
+}
+
+// 从文件中依次切分出cdc chunk，构造File对象和发布文件都使用这里的切分，保证两边的切分点一致
+pub struct CdcFileSplitter<R: AsyncRead + Unpin> {
+    chunker: CdcChunker,
+    reader: R,
+    buf: Vec<u8>,
+    data_len: usize,
+    consumed: usize,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> CdcFileSplitter<R> {
+    pub fn new(params: &CdcChunkParams, reader: R) -> BuckyResult<Self> {
+        let chunker = CdcChunker::new(params)?;
+        let buf = vec![0u8; chunker.max_size()];
+        Ok(Self {
+            chunker,
+            reader,
+            buf,
+            data_len: 0,
+            consumed: 0,
+            eof: false,
+        })
+    }
+
+    // 返回下一个chunk的内容，到达文件末尾返回None
+    pub async fn next(&mut self) -> BuckyResult<Option<&[u8]>> {
+        if self.consumed > 0 {
+            self.buf.copy_within(self.consumed..self.data_len, 0);
+            self.data_len -= self.consumed;
+            self.consumed = 0;
+        }
+
+        // 缓冲区需要填满max_size(或者到达文件末尾)，切分点才和读取的长度无关
+        while !self.eof && self.data_len < self.buf.len() {
+            let len = self
+                .reader
+                .read(&mut self.buf[self.data_len..])
+                .await
+                .map_err(|e| {
+                    let msg = format!("read file for cdc chunk failed! {}", e);
+                    error!("{}", msg);
+                    BuckyError::new(BuckyErrorCode::IoError, msg)
+                })?;
+            if len == 0 {
+                self.eof = true;
+            } else {
+                self.data_len += len;
+            }
+        }
+
+        if self.data_len == 0 {
+            return Ok(None);
+        }
+
+        let len = self.chunker.cut(&self.buf[..self.data_len]);
+        self.consumed = len;
+        Ok(Some(&self.buf[..len]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn split(chunker: &CdcChunker, data: &[u8]) -> Vec<usize> {
+        let mut list = vec![];
+        let mut pos = 0;
+        while pos < data.len() {
+            let end = std::cmp::min(data.len(), pos + chunker.max_size());
+            let len = chunker.cut(&data[pos..end]);
+            list.push(len);
+            pos += len;
+        }
+
+        list
+    }
+
+    #[test]
+    fn test_cdc() {
+        assert!(CdcChunkParams::new(16, 1024, 4096).check().is_err());
+        assert!(CdcChunkParams::new(2048, 1024, 4096).check().is_err());
+
+        let params = CdcChunkParams::with_avg_size(1024 * 8);
+        let chunker = CdcChunker::new(&params).unwrap();
+
+        let mut seed = 1u64;
+        let data: Vec<u8> = (0..1024 * 1024)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (seed >> 56) as u8
+            })
+            .collect();
+
+        let list = split(&chunker, &data);
+        assert_eq!(list.iter().sum::<usize>(), data.len());
+        for len in &list[..list.len() - 1] {
+            assert!(*len >= params.min_size as usize && *len <= params.max_size as usize);
+        }
+
+        // 头部插入数据后，后面的切分点保持不变
+        let mut data2 = vec![7u8; 100];
+        data2.extend_from_slice(&data);
+        let list2 = split(&chunker, &data2);
+
+        let tail: Vec<usize> = list.iter().rev().take(list.len() / 2).cloned().collect();
+        let tail2: Vec<usize> = list2.iter().rev().take(list.len() / 2).cloned().collect();
+        assert_eq!(tail, tail2);
+    }
+
+    // 每次最多读取step个字节
+    struct StepReader {
+        data: Vec<u8>,
+        pos: usize,
+        step: usize,
+    }
+
+    impl AsyncRead for StepReader {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &mut [u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            let len = std::cmp::min(
+                std::cmp::min(buf.len(), self.step),
+                self.data.len() - self.pos,
+            );
+            buf[..len].copy_from_slice(&self.data[self.pos..self.pos + len]);
+            self.pos += len;
+            std::task::Poll::Ready(Ok(len))
+        }
+    }
+
+    #[test]
+    fn test_cdc_file_splitter() {
+        let params = CdcChunkParams::with_avg_size(1024 * 8);
+        let chunker = CdcChunker::new(&params).unwrap();
+
+        let data: Vec<u8> = (0..1024 * 256u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+        let expect = split(&chunker, &data);
+
+        // 切分点和每次读取的长度无关
+        for step in [1000, 4096, 1024 * 1024] {
+            let reader = StepReader {
+                data: data.clone(),
+                pos: 0,
+                step,
+            };
+            let mut splitter = CdcFileSplitter::new(&params, reader).unwrap();
+            let list = async_std::task::block_on(async move {
+                let mut list = vec![];
+                while let Some(chunk) = splitter.next().await.unwrap() {
+                    list.push(chunk.len());
+                }
+                list
+            });
+            assert_eq!(list, expect);
+        }
+    }
+}
@@ -61,6 +61,9 @@ pub struct FileObjectBuilder<T: FileObjectBuilderState> {
     owner: ObjectId,
     chunk_size: u32,
     state: Option<FileObjectBuilderStateWrapper<T>>,
+
+    // 设置后按内容切分chunk，忽略chunk_size
+    cdc: Option<CdcChunkParams>,
 }
 
 impl<T: FileObjectBuilderState> FileObjectBuilder<T> {
@@ -75,9 +78,15 @@ impl<T: FileObjectBuilderState> FileObjectBuilder<T> {
             owner,
             chunk_size,
             state,
+            cdc: None,
         }
     }
 
+    pub fn cdc(mut self, params: CdcChunkParams) -> Self {
+        self.cdc = Some(params);
+        self
+    }
+
     async fn get_file_time(path: &Path) -> BuckyResult<(u64, u64, u64)> {
         let metadata = async_std::fs::metadata(path).await?;
         let modify_time = metadata.modified()?;
@@ -100,7 +109,9 @@ impl<T: FileObjectBuilderState> FileObjectBuilder<T> {
             return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
         }
 
-        if self.chunk_size % 64 != 0 {
+        if let Some(cdc) = &self.cdc {
+            cdc.check()?;
+        } else if self.chunk_size % 64 != 0 {
             let msg = format!("chunk size {} mod 64 is not zero", self.chunk_size);
             log::error!("{}", msg.as_str());
             return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
@@ -142,6 +153,13 @@ impl<T: FileObjectBuilderState> FileObjectBuilder<T> {
             cyfs_sha2::Sha256::from((hash_state.0, &hash_state.1))
         };
 
+        if let Some(cdc) = &self.cdc {
+            let (file_len, file_hash) = self
+                .calc_cdc_chunk_list(cdc, &mut file, pos, file_sha256, &mut list)
+                .await?;
+            return self.build_file(file_len, file_hash, list).await;
+        }
+
         let mut file_len = pos as usize;
         let mut file_hash = None;
         let mut buf = Vec::with_capacity(self.chunk_size as usize);
@@ -199,11 +217,53 @@ impl<T: FileObjectBuilderState> FileObjectBuilder<T> {
             None => file_sha256.result().into(),
         };
 
+        self.build_file(file_len as u64, file_hash, list).await
+    }
+
+    async fn calc_cdc_chunk_list(
+        &self,
+        params: &CdcChunkParams,
+        file: &mut async_std::fs::File,
+        pos: u64,
+        mut file_sha256: cyfs_sha2::Sha256,
+        list: &mut Vec<ChunkId>,
+    ) -> BuckyResult<(u64, HashValue)> {
+        let mut splitter = CdcFileSplitter::new(params, file)?;
+        let mut file_len = pos;
+
+        while let Some(data) = splitter.next().await? {
+            let len = data.len();
+            let hash = hash_data(data);
+            let chunk_id = ChunkId::new(&hash, len as u32);
+
+            debug!(
+                "got file cdc chunk: id={}, len={}, file={}, ",
+                chunk_id,
+                len,
+                self.local_path.as_str()
+            );
+            list.push(chunk_id.clone());
+            file_sha256.input(data);
+            file_len += len as u64;
+
+            if self.state.is_some() {
+                self.state
+                    .as_ref()
+                    .unwrap()
+                    .update(file_len, file_sha256.get_state(), chunk_id)
+                    .await?;
+            }
+        }
+
+        Ok((file_len, file_sha256.result().into()))
+    }
+
+    async fn build_file(&self, file_len: u64, file_hash: HashValue, list: Vec<ChunkId>) -> BuckyResult<File> {
         log::info!("file_hash {}", file_hash.to_string());
         let (create_time, _, _) = Self::get_file_time(Path::new(self.local_path.as_str())).await?;
         let file = File::new(
             self.owner.clone(),
-            file_len as u64,
+            file_len,
             file_hash,
             ChunkList::ChunkInList(list),
         )
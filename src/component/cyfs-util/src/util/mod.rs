@@ -16,6 +16,7 @@ mod dir_loader;
 mod sn_dir;
 mod local_device_manager;
 mod db_helper;
+mod cdc_chunker;
 
 pub use bdt_util::*;
 pub use condvar_helper::*;
@@ -34,4 +35,5 @@ pub use read_seek::*;
 pub use dir_loader::*;
 pub use sn_dir::*;
 pub use local_device_manager::*;
-pub use db_helper::*;
\ No newline at end of file
+pub use db_helper::*;
+pub use cdc_chunker::*;
\ No newline at end of file
@@ -6,6 +6,7 @@ mod helper;
 mod server;
 mod db_sql;
 mod nft_storage;
+mod log_storage;
 
 pub use block_monitor::*;
 pub use spv_chain_storage::*;
@@ -14,3 +15,4 @@ pub use server::*;
 pub use db_helper::*;
 pub use helper::*;
 pub use nft_storage::*;
+pub use log_storage::*;
@@ -0,0 +1,226 @@
+use sqlx::Row;
+use cyfs_base::*;
+use cyfs_base_meta::*;
+use crate::{DBExecutor, MetaConnection, SPVTxStorage};
+
+// evm的LOG指令最多带4个topic
+const MAX_LOG_TOPICS: usize = 4;
+
+// 单次查询最多返回的日志条数
+const MAX_LOGS_PAGE_LENGTH: i64 = 100;
+
+#[async_trait::async_trait]
+pub trait ContractLogStorage {
+    async fn init_log_storage(&self) -> BuckyResult<()>;
+    async fn add_contract_logs(&self, conn: &mut MetaConnection, tx_hash: &str, block_number: i64, tx_index: i64, create_time: u64, receipt: &Receipt) -> BuckyResult<()>;
+    async fn remove_contract_logs(&self, conn: &mut MetaConnection, from_block: i64) -> BuckyResult<()>;
+    async fn get_contract_logs(&self, filter: &GetContractLogsRequest) -> BuckyResult<Vec<SPVContractLog>>;
+}
+
+#[async_trait::async_trait]
+impl ContractLogStorage for SPVTxStorage {
+    async fn init_log_storage(&self) -> BuckyResult<()> {
+        let mut conn = self.get_conn().await?;
+        // 只会索引升级后同步的block，之前的日志需要删除spv_db重新同步
+        let sql = r#"create table if not exists contract_log (
+            "id" INTEGER PRIMARY KEY autoincrement,
+            "address" char(45) NOT NULL,
+            "topic0" char(64),
+            "topic1" char(64),
+            "topic2" char(64),
+            "topic3" char(64),
+            "data" BLOB NOT NULL,
+            "tx_hash" char(45) NOT NULL,
+            "number" INTEGER NOT NULL,
+            "tx_index" INTEGER NOT NULL,
+            "log_index" INTEGER NOT NULL,
+            "create_time" INTEGER NOT NULL
+        )"#;
+        conn.execute_sql(sqlx::query(sql)).await?;
+
+        let sql = r#"create index if not exists contract_log_address_i on contract_log(address, number)"#;
+        conn.execute_sql(sqlx::query(sql)).await?;
+        let sql = r#"create index if not exists contract_log_topic0_i on contract_log(topic0, number)"#;
+        conn.execute_sql(sqlx::query(sql)).await?;
+
+        Ok(())
+    }
+
+    async fn add_contract_logs(&self, conn: &mut MetaConnection, tx_hash: &str, block_number: i64, tx_index: i64, create_time: u64, receipt: &Receipt) -> BuckyResult<()> {
+        let sql = r#"insert into contract_log (address, topic0, topic1, topic2, topic3, data, tx_hash, number, tx_index, log_index, create_time)
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"#;
+
+        let mut log_index = 0;
+        for log in receipt.logs.iter() {
+            if let TxLog::ContractLog(log) = log {
+                let mut topics: Vec<Option<String>> = log.topics.iter().take(MAX_LOG_TOPICS).map(|t| Some(hex::encode(t.as_slice()))).collect();
+                topics.resize(MAX_LOG_TOPICS, None);
+
+                conn.execute_sql(sqlx::query(sql)
+                    .bind(log.address.to_string())
+                    .bind(topics[0].clone())
+                    .bind(topics[1].clone())
+                    .bind(topics[2].clone())
+                    .bind(topics[3].clone())
+                    .bind(log.data.clone())
+                    .bind(tx_hash)
+                    .bind(block_number)
+                    .bind(tx_index)
+                    .bind(log_index)
+                    .bind(create_time as i64)).await?;
+                log_index += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    // 同高度及更高的日志属于被替换掉的分叉块，添加新块前删除
+    async fn remove_contract_logs(&self, conn: &mut MetaConnection, from_block: i64) -> BuckyResult<()> {
+        let sql = "delete from contract_log where number >= ?1";
+        conn.execute_sql(sqlx::query(sql).bind(from_block)).await?;
+        Ok(())
+    }
+
+    async fn get_contract_logs(&self, filter: &GetContractLogsRequest) -> BuckyResult<Vec<SPVContractLog>> {
+        if filter.topics.len() > MAX_LOG_TOPICS {
+            return Err(crate::meta_err!(ERROR_PARAM_ERROR));
+        }
+
+        let mut sql = "select * from contract_log where number >= ?1 and number <= ?2 and id > ?3".to_owned();
+        let mut index = 4;
+        if filter.address.is_some() {
+            sql += format!(" and address = ?{}", index).as_str();
+            index += 1;
+        }
+        for (i, topic) in filter.topics.iter().enumerate() {
+            if topic.is_some() {
+                sql += format!(" and topic{} = ?{}", i, index).as_str();
+                index += 1;
+            }
+        }
+        sql += format!(" order by id limit ?{} offset ?{}", index, index + 1).as_str();
+
+        let mut query = sqlx::query(sql.as_str())
+            .bind(filter.from_block.unwrap_or(0))
+            .bind(filter.to_block.unwrap_or(i64::MAX))
+            .bind(filter.after_id.unwrap_or(0));
+        if let Some(address) = &filter.address {
+            query = query.bind(address.as_str());
+        }
+        for topic in filter.topics.iter() {
+            if let Some(topic) = topic {
+                query = query.bind(topic.trim_start_matches("0x").to_lowercase());
+            }
+        }
+        let length = std::cmp::min(std::cmp::max(filter.length, 0), MAX_LOGS_PAGE_LENGTH);
+        query = query.bind(length).bind(std::cmp::max(filter.offset, 0));
+
+        let rows = self.get_conn().await?.query_all(query).await?;
+        let mut list = Vec::new();
+        for row in rows {
+            let mut topics = vec![];
+            for i in 0..MAX_LOG_TOPICS {
+                let topic: Option<String> = row.get(format!("topic{}", i).as_str());
+                if let Some(topic) = topic {
+                    topics.push(topic);
+                }
+            }
+            let data: Vec<u8> = row.get("data");
+            let create_time: i64 = row.get("create_time");
+            list.push(SPVContractLog {
+                id: row.get("id"),
+                address: row.get("address"),
+                topics,
+                data: hex::encode(data),
+                tx_hash: row.get("tx_hash"),
+                height: row.get("number"),
+                tx_index: row.get("tx_index"),
+                log_index: row.get("log_index"),
+                create_time: bucky_time_to_js_time(create_time as u64),
+            });
+        }
+
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod log_storage_tests {
+    use cyfs_base::*;
+    use cyfs_base_meta::*;
+    use crate::{ContractLogStorage, SPVTxStorage};
+    use crate::db_helper::{map_sql_err, MetaConnectionOptions};
+    use sqlx::ConnectOptions;
+    use std::str::FromStr;
+
+    fn new_log(address: &ObjectId, topic: u8) -> TxLog {
+        let mut log = ContractLog {
+            address: address.clone(),
+            topics: vec![Default::default()],
+            data: vec![topic],
+        };
+        log.topics[0].copy_from_slice(&[topic; 32]);
+        TxLog::ContractLog(log)
+    }
+
+    #[test]
+    fn test_contract_logs() {
+        async_std::task::block_on(async {
+            let mut temp_dir = std::env::temp_dir();
+            temp_dir.push("spv_log_storage_test");
+            if temp_dir.exists() {
+                std::fs::remove_dir_all(temp_dir.clone()).unwrap();
+            }
+            std::fs::create_dir(temp_dir.clone()).unwrap();
+
+            let options = MetaConnectionOptions::new().filename(temp_dir.join("spv_db")).create_if_missing(true);
+            let conn = options.connect().await.map_err(map_sql_err).unwrap();
+            let storage = SPVTxStorage::new(conn);
+            storage.init_log_storage().await.unwrap();
+
+            let contract1 = ObjectId::from_str("5r4MYfF7qVAbn1gdNy9JaNQUW5DfFM8yD3pnwFWY8nn4").unwrap();
+            let contract2 = ObjectId::from_str("5r4MYfF7qVAbn1gdNy9JaNQUW5DfFM8yD3pnwFWY8nn5").unwrap();
+            let mut receipt = Receipt::new(0, 0);
+            receipt.logs = vec![new_log(&contract1, 1), new_log(&contract2, 2), new_log(&contract1, 2)];
+            {
+                let mut conn = storage.get_conn().await.unwrap();
+                storage.add_contract_logs(&mut conn, "tx1", 10, 0, 0, &receipt).await.unwrap();
+            }
+
+            let mut filter = GetContractLogsRequest {
+                address: Some(contract1.to_string()),
+                topics: vec![],
+                from_block: None,
+                to_block: None,
+                after_id: None,
+                offset: 0,
+                length: 10,
+            };
+            let list = storage.get_contract_logs(&filter).await.unwrap();
+            assert_eq!(list.len(), 2);
+            assert_eq!(list[1].log_index, 2);
+
+            filter.topics = vec![Some(hex::encode([2u8; 32]))];
+            let list = storage.get_contract_logs(&filter).await.unwrap();
+            assert_eq!(list.len(), 1);
+            assert_eq!(list[0].data, "02");
+
+            filter.after_id = Some(list[0].id);
+            assert!(storage.get_contract_logs(&filter).await.unwrap().is_empty());
+
+            filter.after_id = None;
+            filter.from_block = Some(11);
+            assert!(storage.get_contract_logs(&filter).await.unwrap().is_empty());
+
+            // 高度10的块被替换后，旧的日志被删除
+            filter.from_block = None;
+            filter.topics = vec![];
+            {
+                let mut conn = storage.get_conn().await.unwrap();
+                storage.remove_contract_logs(&mut conn, 10).await.unwrap();
+            }
+            assert!(storage.get_contract_logs(&filter).await.unwrap().is_empty());
+        });
+    }
+}
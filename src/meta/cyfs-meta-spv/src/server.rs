@@ -677,6 +677,54 @@ impl SPVHttpServer {
             }
         });
 
+        let tmp_storage = storage.clone();
+        app.at("/contract_logs").post(move |mut req: Request<()>| {
+            let storage = tmp_storage.clone();
+            async move {
+                let req_param: GetContractLogsRequest = req.body_json().await?;
+                let tx_storage = storage.create_tx_storage().await?;
+                let result = match tx_storage.get_contract_logs(&req_param).await {
+                    Ok(ret) => {
+                        RequestResult::from(ret)
+                    }
+                    Err(e) => {
+                        info!("get contract logs error.{}", e);
+                        RequestResult::from_err(e)
+                    }
+                };
+
+                let body_str = serde_json::to_string(&result).unwrap();
+                let mut resp = Response::new(tide::http::StatusCode::Ok);
+                resp.set_content_type("application/json");
+                resp.set_body(body_str);
+                Ok(resp)
+            }
+        });
+
+        // 长轮询，有新的匹配日志或者超时后返回
+        let tmp_storage = storage.clone();
+        app.at("/contract_logs/wait").post(move |mut req: Request<()>| {
+            let storage = tmp_storage.clone();
+            async move {
+                let req_param: WaitContractLogsRequest = req.body_json().await?;
+                let result = match storage.wait_contract_logs(&req_param.filter, std::time::Duration::from_secs(req_param.timeout_secs)).await {
+                    Ok(ret) => {
+                        RequestResult::from(ret)
+                    }
+                    Err(e) => {
+                        info!("wait contract logs error.{}", e);
+                        RequestResult::from_err(e)
+                    }
+                };
+
+                let body_str = serde_json::to_string(&result).unwrap();
+                let mut resp = Response::new(tide::http::StatusCode::Ok);
+                resp.set_content_type("application/json");
+                resp.set_body(body_str);
+                Ok(resp)
+            }
+        });
+
         Self {
             spv_storage: storage,
             app,
@@ -13,6 +13,9 @@ use async_trait::async_trait;
 use sqlx::sqlite::SqliteJournalMode;
 use cyfs_base_meta::*;
 use crate::NFTStorage;
+use crate::ContractLogStorage;
+use async_std::channel::Sender;
+use std::time::Instant;
 
 pub type SPVChainStorageRef = Arc<SPVChainStorage>;
 pub type SPVChainStorageWeakRef = Weak<SPVChainStorage>;
@@ -34,9 +37,17 @@ impl<F, Fut> BlockEventEndpoint for F
     }
 }
 
+// 长轮询订阅合约日志的最长等待时间
+const MAX_WAIT_LOGS_TIMEOUT: Duration = Duration::from_secs(60);
+
+// 同时等待的长轮询请求上限，超过后直接返回查询结果
+const MAX_BLOCK_WAITERS: usize = 1024;
+
 pub struct SPVChainStorage {
     db_path: PathBuf,
     listener: Mutex<Option<Arc<dyn BlockEventEndpoint>>>,
+    // 等待新block的长轮询请求，每次有新block提交后唤醒
+    block_waiters: Mutex<Vec<Sender<()>>>,
 }
 
 impl SPVChainStorage {
@@ -44,10 +55,12 @@ impl SPVChainStorage {
         let chain_storage = Arc::new(Self {
             db_path: dir.join("spv_db"),
             listener: Mutex::new(None),
+            block_waiters: Mutex::new(Vec::new()),
         });
         let tx_storage = chain_storage.create_tx_storage().await?;
         tx_storage.init().await?;
         tx_storage.init_nft_storage().await?;
+        tx_storage.init_log_storage().await?;
         Ok(chain_storage)
     }
 
@@ -60,10 +73,12 @@ impl SPVChainStorage {
         let storage = Self {
             db_path: dir.join("spv_db"),
             listener: Mutex::new(None),
+            block_waiters: Mutex::new(Vec::new()),
         };
 
         let tx_storage = storage.create_tx_storage().await?;
         tx_storage.init().await?;
+        tx_storage.init_log_storage().await?;
 
         if block.is_some() {
             tx_storage.add_block(block.as_ref().unwrap()).await?;
@@ -110,10 +125,49 @@ impl SPVChainStorage {
         } else {
             tx_storage.commit().await?;
             log::info!("add_mined_block commit");
+            self.notify_block_waiters();
             Ok(())
         }
     }
 
+    fn notify_block_waiters(&self) {
+        let waiters: Vec<Sender<()>> = self.block_waiters.lock().unwrap().drain(..).collect();
+        for waiter in waiters {
+            let _ = waiter.try_send(());
+        }
+    }
+
+    // 没有匹配的日志时等待新block，直到有匹配的日志或者超时
+    pub async fn wait_contract_logs(&self, filter: &GetContractLogsRequest, timeout: Duration) -> BuckyResult<Vec<SPVContractLog>> {
+        let deadline = Instant::now() + std::cmp::min(timeout, MAX_WAIT_LOGS_TIMEOUT);
+        loop {
+            // 先注册再查询，避免漏掉查询和等待之间提交的block
+            let (sender, receiver) = async_std::channel::bounded(1);
+            let registered = {
+                let mut waiters = self.block_waiters.lock().unwrap();
+                // 超时返回的请求已经丢弃了receiver，注册时清理掉，不用等到下一个block
+                waiters.retain(|waiter| !waiter.is_closed());
+                if waiters.len() < MAX_BLOCK_WAITERS {
+                    waiters.push(sender);
+                    true
+                } else {
+                    false
+                }
+            };
+
+            let list = self.create_tx_storage().await?.get_contract_logs(filter).await?;
+            let now = Instant::now();
+            if !list.is_empty() || now >= deadline || !registered {
+                if !registered {
+                    log::warn!("too many contract log waiters, return without waiting");
+                }
+                return Ok(list);
+            }
+
+            let _ = async_std::future::timeout(deadline - now, receiver.recv()).await;
+        }
+    }
+
     pub fn set_block_listener(&self, listener: impl BlockEventEndpoint) {
         let mut listener_lock = self.listener.lock().unwrap();
         *listener_lock = Some(Arc::new(listener));
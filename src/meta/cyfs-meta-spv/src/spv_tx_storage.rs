@@ -11,6 +11,7 @@ use log::*;
 use sqlx::query::Query;
 use sqlx::sqlite::SqliteArguments;
 use crate::NFTStorage;
+use crate::ContractLogStorage;
 
 pub struct SPVTxStorage {
     conn: Mutex<MetaConnection>,
@@ -453,6 +454,7 @@ impl SPVTxStorage {
         self.config_set("latest_height", block.desc().number().to_string().as_str()).await?;
 
         let mut conn = self.get_conn().await?;
+        self.remove_contract_logs(&mut conn, block.header().number()).await?;
         let transactions: &Vec<MetaTx> = block.transactions();
         let receipts: Vec<Receipt> = block.receipts();
         let event_records = block.event_records();
@@ -835,6 +837,9 @@ impl SPVTxStorage {
                     _ => {}
                 }
             }
+            if receipt.logs.len() > 0 {
+                self.add_contract_logs(&mut conn, tx_hash.as_str(), block.header().number(), i as i64, block.header().create_time(), receipt).await?;
+            }
             i += 1;
         }
         for event in event_records.iter() {
@@ -7,11 +7,22 @@ use crate::executor::*;
 use crate::executor::context::Config;
 use std::str::FromStr;
 use crate::chain::{BlockHeaderStorage, BlockStorage, TxStorage, to_meta_data};
-use crate::get_meta_err_code;
+use crate::{get_meta_err_code, NFTAuction};
 use crate::stat::Stat;
+use crate::executor::context::UnionWithdrawManager;
+use crate::executor::tx_executor::TxExecutor;
+use crate::events::event_manager::EventManager;
+use crate::rent::rent_manager::RentManager;
+use crate::name_auction::auction::Auction;
+use std::convert::TryFrom;
+use primitive_types::H160;
+use log::*;
 
 const CURRENT_STORAGE_VER: u16 = 1;
 
+// 预执行过程中最多消耗的手续费，避免调用方用很大的max_fee让节点执行很久；只限制执行，报告的手续费仍按交易的max_fee计算
+const SIMULATE_MAX_FEE: u32 = 10_000_000;
+
 pub type ChainStorageRef = Arc<ChainStorage>;
 pub type ChainStorageWeakRef = Weak<ChainStorage>;
 
@@ -20,6 +31,9 @@ pub struct ChainStorage {
     header_storage: BlockHeaderStorage,
     tx_storage: TxStorage,
     state_storage: StorageRef,
+
+    // 预执行使用的状态快照和对应的tip，tip变化后重新生成，预执行不会占用state_storage的锁
+    simulate_snapshot: async_std::sync::Mutex<Option<(BlockHash, StorageRef)>>,
 }
 
 impl ChainStorage {
@@ -59,12 +73,41 @@ impl ChainStorage {
         let state_hash = state_storage.state_hash().await.unwrap();
         log::info!("load state_hash:{} db:{}", state_hash.to_string(), state_storage.path().to_str().unwrap());
 
-        Ok(Arc::new(Self {
+        let storage = Arc::new(Self {
             block_storage,
             header_storage,
             tx_storage,
             state_storage,
-        }))
+            simulate_snapshot: async_std::sync::Mutex::new(None),
+        });
+        storage.sync_eth_address_index().await?;
+
+        Ok(storage)
+    }
+
+    // 老的链数据没有以太坊地址反查表，启动时补齐
+    async fn sync_eth_address_index(&self) -> BuckyResult<()> {
+        let tip_header = match self.header_storage.load_tip_header().await {
+            Ok(header) => header,
+            Err(_) => return Ok(()),
+        };
+
+        let height = self.tx_storage.eth_index_height().await?;
+        if height >= tip_header.number() {
+            return Ok(());
+        }
+
+        log::info!("sync eth address index from {} to {}", height + 1, tip_header.number());
+        for number in height + 1..tip_header.number() + 1 {
+            let block = self.get_block_by_number(number).await?;
+            self.tx_storage.index_eth_address(&block).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_id_by_eth_address(&self, address: &H160) -> BuckyResult<Option<ObjectId>> {
+        self.tx_storage.get_id_by_eth_address(address).await
     }
 
     pub async fn reset(dir: PathBuf, block: Option<Block>, state_storage: StorageRef) -> BuckyResult<ChainStorageRef> {
@@ -82,6 +125,7 @@ impl ChainStorage {
             block_storage,
             tx_storage,
             state_storage,
+            simulate_snapshot: async_std::sync::Mutex::new(None),
         }))
     }
 
@@ -303,6 +347,14 @@ impl ChainStorage {
         })
     }
 
+    // 返回交易所在的block，交易在block中的序号，交易和receipt
+    pub async fn get_tx_with_block(&self, tx_hash: &TxHash) -> BuckyResult<(BlockDesc, i64, MetaTx, Receipt)> {
+        let (number, index) = self.tx_storage.get_tx_seq(tx_hash).await?;
+        let header = self.header_storage.load_header_by_number(number).await?;
+        let (tx, receipt) = self.block_storage.get_tx_from_block(&header.hash(), index).await?;
+        Ok((header, index, tx, receipt))
+    }
+
     pub async fn get_tx_full_info(&self, tx_hash: &TxHash) -> BuckyResult<TxFullInfo> {
         let (number, index) = self.tx_storage.get_tx_seq(tx_hash).await?;
         let header = self.header_storage.load_header_by_number(number).await?;
@@ -400,6 +452,95 @@ impl ChainStorage {
         self.header_storage.recovery(height)?;
         self.state_storage.recovery(height).await
     }
+
+    // 在tip+1的块上预执行交易，用于交易预执行和手续费估算
+    // 交易在tip状态的快照上执行并回滚，不会修改链上状态，也不会阻塞出块
+    // 余额检查和报告的手续费和实际执行一致；执行消耗超过SIMULATE_MAX_FEE时按out of gas返回
+    pub async fn simulate(&self, tx: &MetaTx, stat: Option<Stat>) -> BuckyResult<SimulateTxResult> {
+        let tip = self.header_storage.load_tip_header().await?;
+        let header = BlockDesc::new(BlockDescContent::new(tip.coinbase().clone(), Some(&tip))).build();
+
+        // 同一时间只有一个预执行使用快照
+        let mut snapshot = self.simulate_snapshot.lock().await;
+        let storage = match snapshot.as_ref() {
+            Some((hash, storage)) if *hash == tip.hash() => storage.clone(),
+            _ => {
+                let storage = self.create_simulate_snapshot(&tip).await?;
+                if let Some((_, old)) = snapshot.replace((tip.hash(), storage.clone())) {
+                    if let Err(e) = old.remove() {
+                        warn!("remove old simulate snapshot failed, err {}", e);
+                    }
+                }
+                storage
+            }
+        };
+
+        let ref_state = storage.create_state(false).await;
+        ref_state.being_transaction().await?;
+        let ret = Self::simulate_in_transaction(&header, &ref_state, tx, stat).await;
+        if let Err(err) = ref_state.rollback().await {
+            error!("rollback simulate transaction failed, err {}", err);
+
+            // 快照里可能残留了修改，下次重新生成
+            if let Some((_, old)) = snapshot.take() {
+                let _ = old.remove();
+            }
+            return Err(err);
+        }
+
+        ret
+    }
+
+    async fn create_simulate_snapshot(&self, tip: &BlockDesc) -> BuckyResult<StorageRef> {
+        let path = PathBuf::from(format!("{}_simulate_{}", self.state_storage.path().display(), tip.number()));
+        let storage = self.state_storage.snapshot(path.as_path()).await?;
+        info!("create simulate snapshot at {}, tip {}", path.display(), tip.number());
+
+        Ok(storage)
+    }
+
+    async fn simulate_in_transaction(header: &BlockDesc, ref_state: &StateRef, tx: &MetaTx, stat: Option<Stat>) -> BuckyResult<SimulateTxResult> {
+        let config = Config::new(ref_state)?;
+        let event_manager = EventManager::new(ref_state, &config);
+        let rent_manager = RentManager::new(ref_state, &config, &event_manager);
+        let auction = Auction::new(ref_state, &config, &rent_manager, &event_manager);
+        let union_withdraw_manager = UnionWithdrawManager::new(ref_state, &config, &event_manager);
+        let nft_auction = NFTAuction::new(ref_state, &config, &event_manager);
+        let tx_executor = TxExecutor::new(
+            ref_state,
+            &config,
+            &rent_manager,
+            &auction,
+            &event_manager,
+            &union_withdraw_manager,
+            &nft_auction,
+            "".to_owned(),
+            None,
+            ObjectId::default(),
+            true, stat);
+
+        // 和出块时一样，新账户先写入账户信息，签名不做校验，方便钱包在签名前估算手续费
+        if !tx.desc().content().caller.is_miner() {
+            let account_info_ret = ref_state.get_account_info(&tx.desc().content().caller.id()?).await;
+            if let Err(err) = &account_info_ret {
+                if let ERROR_NOT_FOUND = get_meta_err_code(&err)? {
+                    ref_state.add_account_info(&AccountInfo::try_from(tx.desc().content().caller.clone())?).await?;
+                } else {
+                    return Err(account_info_ret.err().unwrap());
+                }
+            }
+        }
+
+        let (receipt, fee_consumed) = tx_executor.execute_and_meter(header, tx, None, Some(SIMULATE_MAX_FEE)).await?;
+        let fee = receipt.fee_used as i64 * tx.desc().content().gas_price as i64;
+
+        Ok(SimulateTxResult {
+            receipt,
+            fee_consumed,
+            fee,
+            block_number: header.number(),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -513,4 +654,29 @@ pub mod chain_storage_tests {
             assert_eq!(ret.as_ref().unwrap().height, 1);
         });
     }
+
+    #[test]
+    fn test_simulate() {
+        async_std::task::block_on(async {
+            let storage = create_test_chain_storage("test_simulate").await;
+            let people1 = create_people();
+            let people2 = create_people();
+            let people1_id = people1.calculate_id();
+
+            let tip = storage.block_header(ViewBlockEnum::Tip).await.unwrap();
+            let state_hash = storage.state_storage().state_hash().await.unwrap();
+
+            // 同一笔交易重复预执行，nonce和新账户都被回滚，结果一致
+            let tx = create_test_tx(&people1, 1, &people2, 10);
+            let ret1 = storage.simulate(&tx, None).await.unwrap();
+            let ret2 = storage.simulate(&tx, None).await.unwrap();
+            assert_eq!(ret1.block_number, tip.number() + 1);
+            assert_eq!(ret1.receipt.result, ret2.receipt.result);
+
+            assert_eq!(storage.state_storage().state_hash().await.unwrap(), state_hash);
+            let state = storage.state_storage().create_state(true).await;
+            assert!(state.get_account_info(&people1_id).await.is_err());
+            assert_eq!(state.get_nonce(&people1_id).await.unwrap(), 0);
+        });
+    }
 }
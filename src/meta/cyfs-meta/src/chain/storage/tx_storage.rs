@@ -6,6 +6,9 @@ use sqlx::{ConnectOptions, Row, SqlitePool};
 use log::LevelFilter;
 use std::time::Duration;
 use sqlx::sqlite::SqliteJournalMode;
+use primitive_types::H160;
+use std::str::FromStr;
+use crate::eth_rpc::{eth_address_of, eth_key_addresses, eth_related_ids};
 
 pub struct TxStorage {
     db_path: PathBuf,
@@ -35,8 +38,16 @@ impl TxStorage {
             \"hash\" CHAR(64) PRIMARY KEY NOT NULL UNIQUE,
             \"number\" INTEGER NOT NULL,\
             \"_index\" INTEGER NOT NULL)";
+        static INIT_ETH_ADDRESS_TBL_SQL: &str = "CREATE TABLE IF NOT EXISTS \"eth_address\"(
+            \"address\" CHAR(40) PRIMARY KEY NOT NULL UNIQUE,
+            \"id\" CHAR(64) NOT NULL)";
+        static INIT_ETH_INDEX_TBL_SQL: &str = "CREATE TABLE IF NOT EXISTS \"eth_index\"(
+            \"id\" INTEGER PRIMARY KEY NOT NULL UNIQUE,
+            \"height\" INTEGER NOT NULL)";
         let mut conn = self.get_conn(false).await?;
         conn.execute_sql(sqlx::query(INIT_TX_TBL_SQL)).await?;
+        conn.execute_sql(sqlx::query(INIT_ETH_ADDRESS_TBL_SQL)).await?;
+        conn.execute_sql(sqlx::query(INIT_ETH_INDEX_TBL_SQL)).await?;
         Ok(())
     }
 
@@ -51,9 +62,51 @@ impl TxStorage {
                 .bind(index)).await?;
             index += 1;
         }
+        self.index_eth_address(block).await
+    }
+
+    // 以太坊地址到ObjectId的反查表，不属于state，不影响state_hash
+    pub async fn index_eth_address(&self, block: &Block) -> BuckyResult<()> {
+        static INSERT_ADDRESS_SQL: &str = "INSERT OR IGNORE INTO eth_address (address, id) VALUES (?1, ?2)";
+        static UPDATE_HEIGHT_SQL: &str = "INSERT OR REPLACE INTO eth_index (id, height) VALUES (0, ?1)";
+        let mut conn = self.get_conn(false).await?;
+        let mut addresses: Vec<(H160, ObjectId)> = eth_related_ids(block)
+            .into_iter()
+            .map(|id| (eth_address_of(&id), id))
+            .collect();
+        addresses.append(&mut eth_key_addresses(block));
+        for (address, id) in addresses {
+            conn.execute_sql(sqlx::query(INSERT_ADDRESS_SQL)
+                .bind(hex::encode(address.as_bytes()))
+                .bind(id.to_string())).await?;
+        }
+        conn.execute_sql(sqlx::query(UPDATE_HEIGHT_SQL).bind(block.header().number())).await?;
         Ok(())
     }
 
+    pub async fn eth_index_height(&self) -> BuckyResult<i64> {
+        static QUERY_HEIGHT_SQL: &str = "SELECT height FROM eth_index WHERE id=0";
+        let mut conn = self.get_conn(true).await?;
+        let rows = conn.query_all(sqlx::query(QUERY_HEIGHT_SQL)).await?;
+        if rows.is_empty() {
+            Ok(-1)
+        } else {
+            Ok(rows[0].get("height"))
+        }
+    }
+
+    pub async fn get_id_by_eth_address(&self, address: &H160) -> BuckyResult<Option<ObjectId>> {
+        static QUERY_ADDRESS_SQL: &str = "SELECT id FROM eth_address WHERE address=?1";
+        let mut conn = self.get_conn(true).await?;
+        let rows = conn.query_all(sqlx::query(QUERY_ADDRESS_SQL).bind(hex::encode(address.as_bytes()))).await?;
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let id: String = rows[0].get("id");
+        Ok(Some(ObjectId::from_str(id.as_str())?))
+    }
+
     pub async fn get_tx_seq(&self, tx_hash: &TxHash) -> BuckyResult<(i64, i64)> {
         static QUERY_TX_SQL: &str = "SELECT number, _index FROM tx WHERE hash=?1";
         let mut conn = self.get_conn(true).await?;
@@ -0,0 +1,79 @@
+use cyfs_base::*;
+use cyfs_base_meta::*;
+use primitive_types::H160;
+
+// evm里的地址是完整的ObjectId，solidity的address类型只会保留低20字节，这里和solidity保持一致
+pub fn eth_address_of(id: &ObjectId) -> H160 {
+    H160::from_slice(&id.as_slice()[12..])
+}
+
+// 收集block里所有可能会被以太坊地址查询到的ObjectId
+pub fn eth_related_ids(block: &Block) -> Vec<ObjectId> {
+    let mut ids = vec![];
+    for tx in block.transactions() {
+        if let Ok(id) = tx.desc().content().caller.id() {
+            ids.push(id);
+        }
+
+        for body in tx.desc().content().body.get_obj() {
+            match body {
+                MetaTxBody::TransBalance(tx) => {
+                    for (to, _) in &tx.to {
+                        ids.push(to.clone());
+                    }
+                }
+                MetaTxBody::CallContract(tx) => {
+                    ids.push(tx.address.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for receipt in block.receipts() {
+        if let Some(address) = &receipt.address {
+            ids.push(address.clone());
+        }
+        for log in &receipt.logs {
+            if let TxLog::ContractLog(log) = log {
+                ids.push(log.address.clone());
+            }
+        }
+    }
+
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+// 公钥为secp256k1的caller，以太坊工具链用公钥算出的地址标识它，eth_sendRawTransaction通过这个地址找到meta账户
+pub fn eth_key_addresses(block: &Block) -> Vec<(H160, ObjectId)> {
+    let mut ret = vec![];
+    for tx in block.transactions() {
+        let caller = &tx.desc().content().caller;
+        if let (Ok(id), Ok(public_key)) = (caller.id(), caller.get_public_key()) {
+            if let Some(address) = eth_address_of_public_key(public_key) {
+                ret.push((address, id));
+            }
+        }
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_eth_address() {
+        let id = ObjectId::from_str("5r4MYfF7qVAbn1gdNy9JaNQUW5DfFM8yD3pnwFWY8nn6").unwrap();
+        let address = eth_address_of(&id);
+        assert_eq!(address.as_bytes(), &id.as_slice()[12..]);
+
+        // 和evm里把ObjectId当作H256再截断成address的结果一致
+        let word: primitive_types::H256 = id.clone().into();
+        assert_eq!(&word.as_bytes()[12..], address.as_bytes());
+    }
+}
@@ -0,0 +1,5 @@
+mod address;
+mod rpc;
+
+pub use address::*;
+pub use rpc::*;
@@ -0,0 +1,659 @@
+use super::address::eth_address_of;
+use crate::chain::chain_storage::ChainStorageRef;
+use crate::meta_backend::MetaBackend;
+use crate::server::commit_signed_tx;
+use crate::chain::MinerRef;
+use crate::{get_meta_err_code, State};
+use cyfs_base::*;
+use cyfs_base_meta::*;
+use evm::executor::{MemoryStackState, StackExecutor, StackSubstateMetadata};
+use evm::ExitReason;
+use primitive_types::{H160, H256};
+use serde_json::{json, Map, Value};
+
+// 和CallContractTx执行时的gas limit保持一致，这部分gas不从手续费中扣除
+const CALL_GAS_LIMIT: u64 = 10000000;
+
+// 和/commit要求的最低gas_price保持一致
+const MIN_GAS_PRICE: u64 = 10;
+
+// eth_getLogs一次最多遍历的block数
+const MAX_LOG_BLOCK_RANGE: i64 = 1000;
+
+const ERROR_PARSE: i64 = -32700;
+const ERROR_INVALID_REQUEST: i64 = -32600;
+const ERROR_METHOD_NOT_FOUND: i64 = -32601;
+const ERROR_INVALID_PARAMS: i64 = -32602;
+const ERROR_SERVER: i64 = -32000;
+const ERROR_EXECUTION_REVERTED: i64 = 3;
+
+pub struct EthRpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl EthRpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(ERROR_INVALID_PARAMS, message)
+    }
+
+    fn to_value(&self) -> Value {
+        let mut error = json!({
+            "code": self.code,
+            "message": self.message,
+        });
+        if let Some(data) = &self.data {
+            error["data"] = data.clone();
+        }
+        error
+    }
+}
+
+impl From<BuckyError> for EthRpcError {
+    fn from(e: BuckyError) -> Self {
+        Self::new(ERROR_SERVER, e.to_string())
+    }
+}
+
+type EthRpcResult<T> = Result<T, EthRpcError>;
+
+fn to_quantity(value: u64) -> Value {
+    Value::String(format!("0x{:x}", value))
+}
+
+fn to_data(data: &[u8]) -> Value {
+    Value::String(format!("0x{}", hex::encode(data)))
+}
+
+fn to_hash(id: &ObjectId) -> Value {
+    to_data(id.as_slice())
+}
+
+fn to_address(id: &ObjectId) -> Value {
+    to_data(eth_address_of(id).as_bytes())
+}
+
+fn parse_hex(value: &Value) -> EthRpcResult<Vec<u8>> {
+    let s = value
+        .as_str()
+        .ok_or_else(|| EthRpcError::invalid_params(format!("expect hex string: {}", value)))?;
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    hex::decode(s).map_err(|e| EthRpcError::invalid_params(format!("invalid hex string: {}, {}", value, e)))
+}
+
+fn parse_quantity(value: &Value) -> EthRpcResult<u64> {
+    let s = value
+        .as_str()
+        .ok_or_else(|| EthRpcError::invalid_params(format!("expect quantity: {}", value)))?;
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    u64::from_str_radix(s, 16).map_err(|e| EthRpcError::invalid_params(format!("invalid quantity: {}, {}", value, e)))
+}
+
+fn parse_address(value: &Value) -> EthRpcResult<H160> {
+    let buf = parse_hex(value)?;
+    if buf.len() != 20 {
+        return Err(EthRpcError::invalid_params(format!("invalid address: {}", value)));
+    }
+    Ok(H160::from_slice(&buf))
+}
+
+fn parse_h256(value: &Value) -> EthRpcResult<H256> {
+    let buf = parse_hex(value)?;
+    if buf.len() != 32 {
+        return Err(EthRpcError::invalid_params(format!("invalid hash: {}", value)));
+    }
+    Ok(H256::from_slice(&buf))
+}
+
+fn parse_hash(value: &Value) -> EthRpcResult<ObjectId> {
+    Ok(ObjectId::from(parse_h256(value)?))
+}
+
+fn parse_block(value: Option<&Value>) -> EthRpcResult<ViewBlockEnum> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(ViewBlockEnum::Tip),
+    };
+
+    if let Some(hash) = value.get("blockHash") {
+        return Ok(ViewBlockEnum::Hash(parse_hash(hash)?));
+    }
+    if let Some(number) = value.get("blockNumber") {
+        return parse_block(Some(number));
+    }
+
+    match value.as_str() {
+        Some("latest") | Some("pending") | Some("safe") | Some("finalized") => Ok(ViewBlockEnum::Tip),
+        Some("earliest") => Ok(ViewBlockEnum::Number(0)),
+        Some(_) => Ok(ViewBlockEnum::Number(parse_quantity(value)? as i64)),
+        None if value.is_null() => Ok(ViewBlockEnum::Tip),
+        None => Err(EthRpcError::invalid_params(format!("invalid block: {}", value))),
+    }
+}
+
+fn param(params: &[Value], index: usize) -> EthRpcResult<&Value> {
+    params
+        .get(index)
+        .ok_or_else(|| EthRpcError::invalid_params(format!("missing param at {}", index)))
+}
+
+fn is_not_found(e: &BuckyError) -> bool {
+    match get_meta_err_code(e) {
+        Ok(code) => code == ERROR_NOT_FOUND,
+        Err(_) => e.code() == BuckyErrorCode::NotFound,
+    }
+}
+
+// topics过滤条件，每一位可以是null，单个topic或者多个topic之一
+fn match_topics(filter: &[Option<Vec<H256>>], topics: &[H256]) -> bool {
+    for (i, cond) in filter.iter().enumerate() {
+        if let Some(list) = cond {
+            match topics.get(i) {
+                Some(topic) if list.contains(topic) => {}
+                _ => return false,
+            }
+        }
+    }
+    true
+}
+
+struct LogFilter {
+    from: i64,
+    to: i64,
+    addresses: Option<Vec<H160>>,
+    topics: Vec<Option<Vec<H256>>>,
+}
+
+// 以太坊JSON-RPC兼容层，以太坊地址是ObjectId的低20字节，通过链上出现过的ObjectId反查
+// eth_sendRawTransaction从以太坊交易的secp256k1签名恢复发送方地址，通过地址反查meta账户后映射成CallContractTx，见EthRawTx
+// 已签名的MetaTx通过cyfs_sendRawTransaction提交
+// 链上只保存最新的状态，状态查询只接受指向tip的block参数
+pub struct EthRpc {
+    miner: MinerRef,
+}
+
+impl EthRpc {
+    pub fn new(miner: MinerRef) -> Self {
+        Self { miner }
+    }
+
+    fn chain_storage(&self) -> &ChainStorageRef {
+        self.miner.as_chain().get_chain_storage()
+    }
+
+    pub async fn process(&self, body: &str) -> String {
+        let resp = match serde_json::from_str::<Value>(body) {
+            Ok(Value::Array(list)) => {
+                if list.is_empty() {
+                    Self::error_response(Value::Null, EthRpcError::new(ERROR_INVALID_REQUEST, "empty batch"))
+                } else {
+                    let mut resp_list = vec![];
+                    for req in list {
+                        resp_list.push(self.process_one(req).await);
+                    }
+                    Value::Array(resp_list)
+                }
+            }
+            Ok(req) => self.process_one(req).await,
+            Err(e) => Self::error_response(Value::Null, EthRpcError::new(ERROR_PARSE, e.to_string())),
+        };
+
+        resp.to_string()
+    }
+
+    fn error_response(id: Value, e: EthRpcError) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": e.to_value(),
+        })
+    }
+
+    async fn process_one(&self, req: Value) -> Value {
+        let id = req.get("id").cloned().unwrap_or(Value::Null);
+        let method = match req.get("method").and_then(|v| v.as_str()) {
+            Some(method) => method.to_owned(),
+            None => {
+                return Self::error_response(id, EthRpcError::new(ERROR_INVALID_REQUEST, "method missing"));
+            }
+        };
+        let params = match req.get("params") {
+            Some(Value::Array(list)) => list.clone(),
+            Some(Value::Null) | None => vec![],
+            Some(_) => {
+                return Self::error_response(id, EthRpcError::invalid_params("params should be array"));
+            }
+        };
+
+        log::debug!("eth rpc request: method={}, params={:?}", method, params);
+        match self.dispatch(&method, &params).await {
+            Ok(result) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": result,
+            }),
+            Err(e) => {
+                log::info!("eth rpc {} failed: {}", method, e.message);
+                Self::error_response(id, e)
+            }
+        }
+    }
+
+    async fn dispatch(&self, method: &str, params: &[Value]) -> EthRpcResult<Value> {
+        match method {
+            "web3_clientVersion" => Ok(Value::String(format!("cyfs-meta/{}", env!("CARGO_PKG_VERSION")))),
+            "net_version" => Ok(Value::String(META_ETH_CHAIN_ID.to_string())),
+            "eth_chainId" => Ok(to_quantity(META_ETH_CHAIN_ID)),
+            "eth_gasPrice" => Ok(to_quantity(MIN_GAS_PRICE)),
+            "eth_estimateGas" => {
+                let (_, used_gas) = self.execute(params).await?;
+                Ok(to_quantity(used_gas))
+            }
+            "eth_blockNumber" => {
+                let header = self.chain_storage().block_header(ViewBlockEnum::Tip).await?;
+                Ok(to_quantity(header.number() as u64))
+            }
+            "eth_getBlockByNumber" => self.get_block(parse_block(Some(param(params, 0)?))?).await,
+            "eth_getBlockByHash" => self.get_block(ViewBlockEnum::Hash(parse_hash(param(params, 0)?)?)).await,
+            "eth_getBalance" => self.get_balance(params).await,
+            "eth_getTransactionCount" => self.get_transaction_count(params).await,
+            "eth_getCode" => self.get_code(params).await,
+            "eth_call" => self.call(params).await,
+            "eth_getLogs" => self.get_logs(params).await,
+            "eth_getTransactionReceipt" => self.get_transaction_receipt(params).await,
+            "eth_sendRawTransaction" => self.send_eth_raw_transaction(params).await,
+            "cyfs_sendRawTransaction" => self.send_raw_transaction(params).await,
+            _ => Err(EthRpcError::new(
+                ERROR_METHOD_NOT_FOUND,
+                format!("method not supported: {}", method),
+            )),
+        }
+    }
+
+    async fn resolve_address(&self, address: &H160) -> EthRpcResult<Option<ObjectId>> {
+        Ok(self.chain_storage().get_id_by_eth_address(address).await?)
+    }
+
+    // 状态查询的block参数必须指向tip，历史状态不保存，不能用最新状态冒充
+    async fn state_header(&self, block: Option<&Value>) -> EthRpcResult<BlockDesc> {
+        let tip = self.chain_storage().block_header(ViewBlockEnum::Tip).await?;
+        match parse_block(block)? {
+            ViewBlockEnum::Tip => Ok(tip),
+            block => {
+                let header = self.chain_storage().block_header(block).await?;
+                if header.hash() != tip.hash() {
+                    return Err(EthRpcError::new(
+                        ERROR_SERVER,
+                        format!("state of block {} is not available, only the latest state is kept", header.number()),
+                    ));
+                }
+                Ok(tip)
+            }
+        }
+    }
+
+    async fn get_block(&self, block: ViewBlockEnum) -> EthRpcResult<Value> {
+        let header = match self.chain_storage().block_header(block).await {
+            Ok(header) => header,
+            Err(e) if is_not_found(&e) => return Ok(Value::Null),
+            Err(e) => return Err(e.into()),
+        };
+        let block = self.chain_storage().get_block_by_number(header.number()).await?;
+        let transactions: Vec<Value> = block
+            .transactions()
+            .iter()
+            .map(|tx| to_hash(&tx.desc().calculate_id()))
+            .collect();
+
+        Ok(json!({
+            "number": to_quantity(header.number() as u64),
+            "hash": to_hash(&header.hash()),
+            "parentHash": to_hash(header.pre_block_hash()),
+            "miner": to_address(header.coinbase()),
+            "timestamp": to_quantity(bucky_time_to_js_time(header.create_time()) / 1000),
+            "gasLimit": to_quantity(CALL_GAS_LIMIT),
+            "gasUsed": to_quantity(0),
+            "baseFeePerGas": to_quantity(0),
+            "transactions": transactions,
+        }))
+    }
+
+    async fn get_balance(&self, params: &[Value]) -> EthRpcResult<Value> {
+        let address = parse_address(param(params, 0)?)?;
+        let id = match self.resolve_address(&address).await? {
+            Some(id) => id,
+            None => return Ok(to_quantity(0)),
+        };
+
+        self.state_header(params.get(1)).await?;
+        let state = self.chain_storage().state_storage().create_state(true).await;
+        let balance = state.get_balance(&id, &CoinTokenId::Coin(0)).await?;
+        Ok(to_quantity(balance as u64))
+    }
+
+    async fn get_transaction_count(&self, params: &[Value]) -> EthRpcResult<Value> {
+        let address = parse_address(param(params, 0)?)?;
+        let id = match self.resolve_address(&address).await? {
+            Some(id) => id,
+            None => return Ok(to_quantity(0)),
+        };
+
+        let nonce = if params.get(1).and_then(|v| v.as_str()) == Some("pending") {
+            self.miner.get_nonce(&id).await?
+        } else {
+            self.state_header(params.get(1)).await?;
+            let state = self.chain_storage().state_storage().create_state(true).await;
+            state.get_nonce(&id).await?
+        };
+        Ok(to_quantity(nonce as u64))
+    }
+
+    async fn get_code(&self, params: &[Value]) -> EthRpcResult<Value> {
+        let address = parse_address(param(params, 0)?)?;
+        let id = match self.resolve_address(&address).await? {
+            Some(id) => id,
+            None => return Ok(to_data(&[])),
+        };
+
+        self.state_header(params.get(1)).await?;
+        let state = self.chain_storage().state_storage().create_state(true).await;
+        match state.code(&id).await {
+            Ok(code) => Ok(to_data(&code)),
+            Err(e) if is_not_found(&e) => Ok(to_data(&[])),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn call(&self, params: &[Value]) -> EthRpcResult<Value> {
+        let (output, _) = self.execute(params).await?;
+        Ok(to_data(&output))
+    }
+
+    // 在最新状态上执行调用，没有to时按创建合约执行，返回输出和实际消耗的gas
+    async fn execute(&self, params: &[Value]) -> EthRpcResult<(Vec<u8>, u64)> {
+        let call = param(params, 0)?;
+        let address = match call.get("to") {
+            Some(to) if !to.is_null() => {
+                let to = parse_address(to)?;
+                let address = self.resolve_address(&to).await?.ok_or_else(|| {
+                    EthRpcError::new(ERROR_SERVER, format!("unknown contract address: {:?}", to))
+                })?;
+                Some(address)
+            }
+            _ => None,
+        };
+
+        let caller = match call.get("from") {
+            Some(from) if !from.is_null() => self.resolve_address(&parse_address(from)?).await?.unwrap_or_default(),
+            _ => ObjectId::default(),
+        };
+        let value = match call.get("value") {
+            Some(value) if !value.is_null() => parse_quantity(value)?,
+            _ => 0,
+        };
+        let data = match call.get("data").or_else(|| call.get("input")) {
+            Some(data) if !data.is_null() => parse_hex(data)?,
+            _ => vec![],
+        };
+
+        let header = self.state_header(params.get(1)).await?;
+        let state = self.chain_storage().state_storage().create_state(true).await;
+        let config = evm::Config::istanbul();
+        let backend = MetaBackend::new(&state, 0, &header, caller, Some(self.chain_storage()), config.clone());
+        let stack_state = MemoryStackState::new(StackSubstateMetadata::new(CALL_GAS_LIMIT, &config), &backend);
+        let mut executor = StackExecutor::new(stack_state, &config);
+        let (ret, output) = match address {
+            Some(address) => executor.transact_call(caller, address, value, data, CALL_GAS_LIMIT),
+            None => {
+                let (ret, _, output) = executor.transact_create(caller, value, data, CALL_GAS_LIMIT);
+                (ret, output)
+            }
+        };
+
+        match ret {
+            ExitReason::Succeed(_) => Ok((output, executor.used_gas())),
+            ExitReason::Revert(_) => {
+                let mut e = EthRpcError::new(ERROR_EXECUTION_REVERTED, "execution reverted");
+                e.data = Some(to_data(&output));
+                Err(e)
+            }
+            reason => Err(EthRpcError::new(ERROR_SERVER, format!("execution failed: {:?}", reason))),
+        }
+    }
+
+    async fn parse_log_filter(&self, filter: &Value) -> EthRpcResult<LogFilter> {
+        let tip = self.chain_storage().block_header(ViewBlockEnum::Tip).await?.number();
+        let number_of = |block: ViewBlockEnum| -> EthRpcResult<Option<i64>> {
+            match block {
+                ViewBlockEnum::Tip => Ok(None),
+                ViewBlockEnum::Number(n) => Ok(Some(n)),
+                ViewBlockEnum::Hash(_) => Err(EthRpcError::invalid_params("block hash not supported here")),
+            }
+        };
+
+        let (from, to) = if let Some(hash) = filter.get("blockHash") {
+            let header = self.chain_storage().block_header(ViewBlockEnum::Hash(parse_hash(hash)?)).await?;
+            (header.number(), header.number())
+        } else {
+            let from = number_of(parse_block(filter.get("fromBlock"))?)?.unwrap_or(tip);
+            let to = number_of(parse_block(filter.get("toBlock"))?)?.unwrap_or(tip);
+            (from, std::cmp::min(to, tip))
+        };
+
+        if to >= from && to - from >= MAX_LOG_BLOCK_RANGE {
+            return Err(EthRpcError::new(
+                ERROR_SERVER,
+                format!("block range should be less than {}", MAX_LOG_BLOCK_RANGE),
+            ));
+        }
+
+        let addresses = match filter.get("address") {
+            Some(Value::Array(list)) => Some(list.iter().map(parse_address).collect::<EthRpcResult<Vec<H160>>>()?),
+            Some(Value::Null) | None => None,
+            Some(address) => Some(vec![parse_address(address)?]),
+        };
+
+        let mut topics = vec![];
+        if let Some(Value::Array(list)) = filter.get("topics") {
+            for item in list {
+                let cond = match item {
+                    Value::Null => None,
+                    Value::Array(list) => Some(list.iter().map(parse_h256).collect::<EthRpcResult<Vec<H256>>>()?),
+                    topic => Some(vec![parse_h256(topic)?]),
+                };
+                topics.push(cond);
+            }
+        }
+
+        Ok(LogFilter {
+            from,
+            to,
+            addresses,
+            topics,
+        })
+    }
+
+    async fn get_logs(&self, params: &[Value]) -> EthRpcResult<Value> {
+        let filter = self.parse_log_filter(param(params, 0)?).await?;
+
+        let mut ret = vec![];
+        for number in filter.from..filter.to + 1 {
+            let block = self.chain_storage().get_block_by_number(number).await?;
+            let block_hash = block.header().hash();
+            let mut log_index = 0u64;
+            for (tx_index, (tx, receipt)) in block.transactions().iter().zip(block.receipts()).enumerate() {
+                let tx_hash = tx.desc().calculate_id();
+                for log in receipt.logs.iter() {
+                    let log = match log {
+                        TxLog::ContractLog(log) => log,
+                        _ => continue,
+                    };
+                    let index = log_index;
+                    log_index += 1;
+
+                    if let Some(addresses) = &filter.addresses {
+                        if !addresses.contains(&eth_address_of(&log.address)) {
+                            continue;
+                        }
+                    }
+                    let topics: Vec<H256> = log.topics.iter().map(|t| H256::from_slice(t.as_slice())).collect();
+                    if !match_topics(&filter.topics, &topics) {
+                        continue;
+                    }
+
+                    ret.push(json!({
+                        "address": to_address(&log.address),
+                        "topics": topics.iter().map(|t| to_data(t.as_bytes())).collect::<Vec<Value>>(),
+                        "data": to_data(&log.data),
+                        "blockNumber": to_quantity(number as u64),
+                        "blockHash": to_hash(&block_hash),
+                        "transactionHash": to_hash(&tx_hash),
+                        "transactionIndex": to_quantity(tx_index as u64),
+                        "logIndex": to_quantity(index),
+                        "removed": false,
+                    }));
+                }
+            }
+        }
+
+        Ok(Value::Array(ret))
+    }
+
+    async fn get_transaction_receipt(&self, params: &[Value]) -> EthRpcResult<Value> {
+        let tx_hash = parse_hash(param(params, 0)?)?;
+        let (header, index, tx, receipt) = match self.chain_storage().get_tx_with_block(&tx_hash).await {
+            Ok(ret) => ret,
+            Err(e) if is_not_found(&e) => return Ok(Value::Null),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut to = Value::Null;
+        for body in tx.desc().content().body.get_obj() {
+            if let MetaTxBody::CallContract(call) = body {
+                to = to_address(&call.address);
+                break;
+            }
+        }
+
+        // logIndex只在交易内部计数，需要block内的序号时请使用eth_getLogs
+        let mut logs = vec![];
+        for log in receipt.logs.iter() {
+            if let TxLog::ContractLog(log) = log {
+                let topics: Vec<Value> = log.topics.iter().map(|t| to_data(t.as_slice())).collect();
+                logs.push(json!({
+                    "address": to_address(&log.address),
+                    "topics": topics,
+                    "data": to_data(&log.data),
+                    "blockNumber": to_quantity(header.number() as u64),
+                    "blockHash": to_hash(&header.hash()),
+                    "transactionHash": to_hash(&tx_hash),
+                    "transactionIndex": to_quantity(index as u64),
+                    "logIndex": to_quantity(logs.len() as u64),
+                    "removed": false,
+                }));
+            }
+        }
+
+        let mut ret = Map::new();
+        ret.insert("transactionHash".to_owned(), to_hash(&tx_hash));
+        ret.insert("transactionIndex".to_owned(), to_quantity(index as u64));
+        ret.insert("blockHash".to_owned(), to_hash(&header.hash()));
+        ret.insert("blockNumber".to_owned(), to_quantity(header.number() as u64));
+        ret.insert("from".to_owned(), to_address(&tx.desc().content().caller.id()?));
+        ret.insert("to".to_owned(), to);
+        ret.insert("gasUsed".to_owned(), to_quantity(receipt.fee_used as u64));
+        ret.insert("cumulativeGasUsed".to_owned(), to_quantity(receipt.fee_used as u64));
+        ret.insert("effectiveGasPrice".to_owned(), to_quantity(tx.desc().content().gas_price as u64));
+        ret.insert(
+            "contractAddress".to_owned(),
+            receipt.address.as_ref().map(to_address).unwrap_or(Value::Null),
+        );
+        ret.insert("logs".to_owned(), Value::Array(logs));
+        ret.insert("logsBloom".to_owned(), to_data(&[0u8; 256]));
+        ret.insert("type".to_owned(), to_quantity(0));
+        ret.insert("status".to_owned(), to_quantity(if receipt.result == 0 { 1 } else { 0 }));
+
+        Ok(Value::Object(ret))
+    }
+
+    async fn send_raw_transaction(&self, params: &[Value]) -> EthRpcResult<Value> {
+        let buf = parse_hex(param(params, 0)?)?;
+        let tx = MetaTx::clone_from_slice(&buf).map_err(|e| {
+            EthRpcError::invalid_params(format!("raw transaction should be signed MetaTx: {}", e))
+        })?;
+
+        for body in tx.desc().content().body.get_obj() {
+            match body {
+                MetaTxBody::CallContract(_) | MetaTxBody::CreateContract(_) | MetaTxBody::CreateContract2(_) => {}
+                _ => {
+                    return Err(EthRpcError::invalid_params(
+                        "only contract transactions are accepted by cyfs_sendRawTransaction",
+                    ));
+                }
+            }
+        }
+
+        self.commit_tx(tx).await
+    }
+
+    // 以太坊签名交易映射成CallContractTx提交，发送方需要是公钥为secp256k1并且在链上出现过的meta账户
+    // 返回的交易hash是映射出的MetaTx的id
+    async fn send_eth_raw_transaction(&self, params: &[Value]) -> EthRpcResult<Value> {
+        let raw = parse_hex(param(params, 0)?)?;
+        let eth_tx = EthRawTx::decode(&raw).map_err(|e| EthRpcError::invalid_params(e.to_string()))?;
+        let sender = eth_tx.sender().map_err(|e| EthRpcError::invalid_params(e.to_string()))?;
+        let caller = self.resolve_address(&sender).await?.ok_or_else(|| {
+            EthRpcError::new(ERROR_SERVER, format!("unknown sender, the account should be on chain first: {:?}", sender))
+        })?;
+
+        let to = eth_tx.to.ok_or_else(|| {
+            EthRpcError::invalid_params("contract creation is not supported by eth_sendRawTransaction")
+        })?;
+        let address = self.resolve_address(&to).await?.ok_or_else(|| {
+            EthRpcError::new(ERROR_SERVER, format!("unknown contract address: {:?}", to))
+        })?;
+
+        let tx = eth_tx
+            .to_meta_tx(&raw, caller, address)
+            .map_err(|e| EthRpcError::invalid_params(e.to_string()))?;
+        self.commit_tx(tx).await
+    }
+
+    async fn commit_tx(&self, tx: MetaTx) -> EthRpcResult<Value> {
+        let tx_hash = tx.desc().calculate_id();
+        match commit_signed_tx(&self.miner, tx).await? {
+            Ok(_) => Ok(to_hash(&tx_hash)),
+            Err(code) => Err(EthRpcError::new(ERROR_SERVER, format!("commit tx failed: meta error {}", code))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_quantity(&json!("0x1a")).unwrap(), 26);
+        assert!(parse_address(&json!("0x1234")).is_err());
+        assert!(parse_h256(&json!("0x1234")).is_err());
+        assert!(parse_h256(&json!(format!("0x{}", "00".repeat(32)))).is_ok());
+        assert!(matches!(parse_block(Some(&json!("latest"))).unwrap(), ViewBlockEnum::Tip));
+        assert!(matches!(parse_block(Some(&json!("0x10"))).unwrap(), ViewBlockEnum::Number(16)));
+
+        let topic = H256::from_low_u64_be(1);
+        let other = H256::from_low_u64_be(2);
+        assert!(match_topics(&[None, Some(vec![topic, other])], &[other, topic]));
+        assert!(!match_topics(&[Some(vec![topic])], &[other]));
+        assert!(!match_topics(&[None, Some(vec![topic])], &[topic]));
+    }
+}
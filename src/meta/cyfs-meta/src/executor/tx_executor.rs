@@ -100,6 +100,13 @@ impl TxExecutor {
     }
 
     pub async fn execute(&self, owner_block: &BlockDesc, tx: &MetaTx, chain_storage: Option<&ChainStorageRef>) -> BuckyResult<Receipt> {
+        let (receipt, _) = self.execute_and_meter(owner_block, tx, chain_storage, None).await?;
+        Ok(receipt)
+    }
+
+    // 执行交易，同时返回扣除全部max_fee之前实际消耗的fee，用于交易预执行和手续费估算
+    // exec_fee_limit只限制执行过程中可以消耗的fee，余额检查和最终扣除的手续费仍然按交易的max_fee计算
+    pub async fn execute_and_meter(&self, owner_block: &BlockDesc, tx: &MetaTx, chain_storage: Option<&ChainStorageRef>, exec_fee_limit: Option<u32>) -> BuckyResult<(Receipt, u32)> {
         let mut caller = context::Account::from_caller(&tx.desc().content().caller, &self.ref_state.to_rc()?)?;
         let caller_id = caller.id().clone();
 
@@ -119,7 +126,7 @@ impl TxExecutor {
                 error!("execute tx failed for invalid nonce expected {:?} but {:?} thread {:?}",
                        nonce + 1, tx.desc().content().nonce, std::thread::current().id());
 
-                return Ok(Receipt::new(1,0));
+                return Ok((Receipt::new(1,0), 0));
                 // return Err(crate::meta_err!(ERROR_INVALID));
             }
         }
@@ -161,7 +168,7 @@ impl TxExecutor {
         if let Err(e) = self.ref_state.to_rc()?.dec_balance(&CoinTokenId::Coin(tx.desc().content().gas_coin_id), caller.id(), total_fee).await {
             return match e.code() {
                 BuckyErrorCode::MetaError(meta_code) => {
-                    Ok(Receipt::new(meta_code as u32, 0))
+                    Ok((Receipt::new(meta_code as u32, 0), 0))
                 },
                 _ => {
                     Err(e)
@@ -176,7 +183,11 @@ impl TxExecutor {
                                               &self.event_manager.to_rc()?,
                                               self.is_verify_block);
 
-        let mut fee_counter = context::FeeCounter::new(tx.desc().content().max_fee);
+        let exec_max_fee = match exec_fee_limit {
+            Some(limit) => std::cmp::min(limit, tx.desc().content().max_fee),
+            None => tx.desc().content().max_fee,
+        };
+        let mut fee_counter = context::FeeCounter::new(exec_max_fee);
 
         self.ref_state.to_rc()?.being_transaction().await?;
         //TODO: cost some fee for tx's storage
@@ -403,7 +414,11 @@ impl TxExecutor {
             self.ref_state.to_rc()?.commit().await?;
         }
 
+        let fee_consumed = fee_counter.fee_used();
+
         // TODO: 暂时扣除所有手续费
+        // 执行时的fee可能受exec_fee_limit限制，扣费按交易的max_fee重新计算
+        let mut fee_counter = context::FeeCounter::new(tx.desc().content().max_fee);
         let _ = fee_counter.cost(tx.desc().content().max_fee);
 
         let fee_used = fee_counter.fee_used() as i64 * tx.desc().content().gas_price as i64;
@@ -419,7 +434,7 @@ impl TxExecutor {
         receipt.address = address;
         receipt.return_value = return_value;
         receipt.logs = logs;
-        Ok(receipt)
+        Ok((receipt, fee_consumed))
     }
 }
 
@@ -11,6 +11,7 @@ pub use network::*;
 pub use server::*;
 pub use state_storage::*;
 pub use nft_auction::*;
+pub use eth_rpc::*;
 
 mod tmp_manager;
 mod state_storage;
@@ -30,6 +31,7 @@ mod extension;
 mod server;
 mod meta_backend;
 mod nft_auction;
+mod eth_rpc;
 
 /*
 尝试列出所有的TX
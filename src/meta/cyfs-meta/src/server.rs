@@ -39,6 +39,50 @@ fn txhash_from_req_params(url: &Url) -> Result<TxHash, std::io::Error> {
     };
 }
 
+// 校验交易的手续费和签名，通过后放入交易池，/commit和cyfs_sendRawTransaction共用
+pub(crate) async fn commit_signed_tx(miner: &Arc<dyn Miner>, tx: MetaTx) -> BuckyResult<Result<TxId, u16>> {
+    let result;
+    if tx.desc().content().max_fee < 10 || tx.desc().content().gas_price < 10 {
+        result = Err(ERROR_NOT_ENOUGH_FEE);
+    } else {
+        let public_key_ret = {
+            let storage = miner.as_chain().get_chain_storage().state_storage();
+            let ref_state = storage.create_state(true).await;
+            let account_info_ret = ref_state.get_account_info(&tx.desc().content().caller.id()?).await;
+            if let Err(err) = &account_info_ret {
+                if let ERROR_NOT_FOUND = get_meta_err_code(&err)? {
+                    Ok(tx.desc().content().caller.get_public_key()?.clone())
+                } else {
+                    Err(account_info_ret.err().unwrap())
+                }
+            } else {
+                Ok(account_info_ret.unwrap().get_public_key()?.clone())
+            }
+        };
+
+        if public_key_ret.is_ok() {
+            let public_key = public_key_ret.unwrap();
+            if !tx.async_verify_signature(public_key).await? {
+                result = Err(ERROR_SIGNATURE_ERROR);
+            } else {
+                let tx_hash = tx.desc().calculate_id();
+                match miner.push_tx(tx).await {
+                    Err(e) => {
+                        result = Err(ERROR_BUCKY_ERR_START + e.code().into_u16());
+                    },
+                    Ok(_) => {
+                        result = Ok(TxId::try_from(tx_hash)?);
+                    }
+                }
+            }
+        } else {
+            result = Err(ERROR_PUBLIC_KEY_NOT_EXIST);
+        }
+    }
+
+    Ok(result)
+}
+
 impl MetaHttpServer {
     pub fn new(miner: Arc<dyn Miner>, server_port: u16) -> Self {
         let mut app = tide::new();
@@ -95,44 +139,7 @@ impl MetaHttpServer {
                        tx.desc().content().nonce, tx.desc().content().max_fee, tx.desc().content().gas_price);
                 }
 
-                let result;
-                if tx.desc().content().max_fee < 10 || tx.desc().content().gas_price < 10 {
-                    result = Err(ERROR_NOT_ENOUGH_FEE);
-                } else {
-                    let public_key_ret = {
-                        let storage = miner.as_chain().get_chain_storage().state_storage();
-                        let ref_state = storage.create_state(true).await;
-                        let account_info_ret = ref_state.get_account_info(&tx.desc().content().caller.id()?).await;
-                        if let Err(err) = &account_info_ret {
-                            if let ERROR_NOT_FOUND = get_meta_err_code(&err)? {
-                                Ok(tx.desc().content().caller.get_public_key()?.clone())
-                            } else {
-                                Err(account_info_ret.err().unwrap())
-                            }
-                        } else {
-                            Ok(account_info_ret.unwrap().get_public_key()?.clone())
-                        }
-                    };
-
-                    if public_key_ret.is_ok() {
-                        let public_key = public_key_ret.unwrap();
-                        if !tx.async_verify_signature(public_key).await? {
-                            result = Err(ERROR_SIGNATURE_ERROR);
-                        } else {
-                            let tx_hash = tx.desc().calculate_id();
-                            match miner.push_tx(tx).await {
-                                Err(e) => {
-                                    result = Err(ERROR_BUCKY_ERR_START + e.code().into_u16());
-                                },
-                                Ok(_) => {
-                                    result = Ok(TxId::try_from(tx_hash)?);
-                                }
-                            }
-                        }
-                    } else {
-                        result = Err(ERROR_PUBLIC_KEY_NOT_EXIST);
-                    }
-                }
+                let result = commit_signed_tx(&miner, tx).await?;
                 // API 调用记录日志
                 if !is_fake {
                     if let Some(stat) = miner.as_chain().get_stat() {
@@ -172,6 +179,51 @@ impl MetaHttpServer {
             }
         });
 
+        let tmp_miner = miner.clone();
+        app.at("/simulate").post(move |mut req: Request<()>| {
+            let miner = tmp_miner.clone();
+            async move {
+                let tx_body = req.body_bytes().await?;
+                let tx = MetaTx::clone_from_slice(tx_body.as_slice())?;
+                debug!("simulate tx {} caller {} nonce {} max_fee {} gas_price {}", tx.desc().calculate_id().to_string(),
+                       tx.desc().content().caller.id()?.to_string(),
+                       tx.desc().content().nonce, tx.desc().content().max_fee, tx.desc().content().gas_price);
+
+                let stat = miner.as_chain().get_stat();
+                let result = miner.as_chain().get_chain_storage().simulate(&tx, stat.clone()).await.or_else(|e| {
+                    info!("simulate tx error.{}", e);
+                    if let BuckyErrorCode::MetaError(code) = e.code() {
+                        Err(code)
+                    } else {
+                        Err(ERROR_EXCEPTION)
+                    }
+                });
+                // API 调用记录日志
+                if let Some(stat) = stat {
+                    stat.api_call("simulate", *result.as_ref().err().unwrap_or(&0))
+                }
+
+                let body_str = result.to_hex()?;
+                let mut resp = Response::new(tide::http::StatusCode::Ok);
+                resp.set_body(body_str);
+                Ok(resp)
+            }
+        });
+
+        // 以太坊JSON-RPC兼容接口，方便使用solidity的标准工具链
+        let eth_rpc = Arc::new(EthRpc::new(miner.clone()));
+        app.at("/eth_rpc").post(move |mut req: Request<()>| {
+            let eth_rpc = eth_rpc.clone();
+            async move {
+                let body = req.body_string().await?;
+                let body_str = eth_rpc.process(body.as_str()).await;
+                let mut resp = Response::new(tide::http::StatusCode::Ok);
+                resp.set_content_type("application/json");
+                resp.set_body(body_str);
+                Ok(resp)
+            }
+        });
+
         let tmp_miner = miner.clone();
         app.at("/status").get(move |_req: Request<()>| {
             let miner = tmp_miner.clone();
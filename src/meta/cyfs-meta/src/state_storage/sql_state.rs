@@ -1988,6 +1988,86 @@ impl Storage for SqlStorage {
     async fn get_locker(&self) -> MutexGuard<'_, ()> {
         self.locker.lock().await
     }
+
+    // 使用sqlite的在线备份接口，备份过程中源库有修改时sqlite会自动重新开始，不需要持有storage的锁
+    async fn snapshot(&self, path: &Path) -> BuckyResult<StorageRef> {
+        let src = self.path.clone();
+        let dest = path.to_path_buf();
+        async_std::task::spawn_blocking(move || sqlite_backup(&src, &dest)).await?;
+
+        Ok(new_sql_storage(path))
+    }
+}
+
+// 每一步备份的页数，两步之间让出源库的读锁，避免阻塞出块
+const SQLITE_BACKUP_STEP_PAGES: i32 = 256;
+
+struct SqliteHandle(*mut libsqlite3_sys::sqlite3);
+
+impl SqliteHandle {
+    fn open(path: &Path, flags: i32) -> BuckyResult<Self> {
+        let c_path = std::ffi::CString::new(path.to_str().unwrap()).map_err(|err| {
+            error!("invalid sqlite path {}, err {}", path.display(), err);
+            meta_err!(ERROR_PARAM_ERROR)})?;
+
+        let mut db = std::ptr::null_mut();
+        let rc = unsafe { libsqlite3_sys::sqlite3_open_v2(c_path.as_ptr(), &mut db, flags, std::ptr::null()) };
+        let handle = Self(db);
+        if rc != libsqlite3_sys::SQLITE_OK {
+            error!("open sqlite {} fail, err {}", path.display(), handle.errmsg());
+            return Err(meta_err!(ERROR_NOT_FOUND));
+        }
+        unsafe { libsqlite3_sys::sqlite3_busy_timeout(db, 5000) };
+
+        Ok(handle)
+    }
+
+    fn errmsg(&self) -> String {
+        if self.0.is_null() {
+            return "out of memory".to_owned();
+        }
+        unsafe { std::ffi::CStr::from_ptr(libsqlite3_sys::sqlite3_errmsg(self.0)) }.to_string_lossy().to_string()
+    }
+}
+
+impl Drop for SqliteHandle {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { libsqlite3_sys::sqlite3_close(self.0) };
+        }
+    }
+}
+
+fn sqlite_backup(src: &Path, dest: &Path) -> BuckyResult<()> {
+    use libsqlite3_sys::*;
+
+    let src_db = SqliteHandle::open(src, SQLITE_OPEN_READONLY)?;
+    let dest_db = SqliteHandle::open(dest, SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE)?;
+
+    let main = std::ffi::CString::new("main").unwrap();
+    let backup = unsafe { sqlite3_backup_init(dest_db.0, main.as_ptr(), src_db.0, main.as_ptr()) };
+    if backup.is_null() {
+        error!("snapshot {} to {} fail, err {}", src.display(), dest.display(), dest_db.errmsg());
+        return Err(meta_err!(ERROR_EXCEPTION));
+    }
+
+    let mut rc;
+    loop {
+        rc = unsafe { sqlite3_backup_step(backup, SQLITE_BACKUP_STEP_PAGES) };
+        match rc {
+            SQLITE_DONE => break,
+            SQLITE_OK | SQLITE_BUSY | SQLITE_LOCKED => std::thread::sleep(Duration::from_millis(1)),
+            _ => break,
+        }
+    }
+
+    let finish_rc = unsafe { sqlite3_backup_finish(backup) };
+    if rc != SQLITE_DONE || finish_rc != SQLITE_OK {
+        error!("snapshot {} to {} fail, step {}, finish {}, err {}", src.display(), dest.display(), rc, finish_rc, dest_db.errmsg());
+        return Err(meta_err!(ERROR_EXCEPTION));
+    }
+
+    Ok(())
 }
 
 pub fn new_sql_storage(path: &Path) -> StorageRef {
@@ -71,6 +71,12 @@ pub trait Storage: std::marker::Send + Sync {
 
     async fn create_state(&self, read_only: bool) -> StateRef;
 
+    // 把当前状态复制到path，返回新的storage，修改快照不会影响当前storage
+    async fn snapshot(&self, path: &Path) -> BuckyResult<StorageRef> {
+        error!("storage {} not support snapshot to {}", self.path().display(), path.display());
+        Err(crate::meta_err!(ERROR_EXCEPTION))
+    }
+
     async fn get_locker(&self) -> MutexGuard<'_, ()>;
     // async fn run_in_transaction<Fn>(&self, func: Fn) where Fn: FnOnce(StateRef) -> dyn Future<Output=BuckyResult<()>>;
 }
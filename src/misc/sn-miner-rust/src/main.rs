@@ -1,11 +1,17 @@
+use std::collections::HashMap;
 use std::net::{Ipv4Addr, Ipv6Addr};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
 use cyfs_base::*;
 use cyfs_bdt::{sn::service::*, ReceiptWithSignature, SnServiceReceipt};
 
 const APP_NAME: &str = "sn-miner";
 
+// 重新加载吊销列表文件的间隔
+const REVOKED_RELOAD_INTERVAL_IN_SECS: u64 = 10 * 60;
+
 struct SnServiceContractServerImpl {}
 
 impl SnServiceContractServerImpl {
@@ -37,7 +43,9 @@ async fn main() {
     let matches = clap::App::new(APP_NAME).version(cyfs_base::get_version())
         .arg(clap::Arg::with_name("desc").short("d").long("desc").takes_value(true)
             .default_value(default_desc_path.to_str().unwrap())
-            .help("sn desc/sec files, exclude extension")).get_matches();
+            .help("sn desc/sec files, exclude extension"))
+        .arg(clap::Arg::with_name("revoked").long("revoked").takes_value(true)
+            .help("revoked devices file, one \"owner_id device_id\" per line")).get_matches();
 
     match load_device_info(Path::new(matches.value_of("desc").unwrap())) {
         Ok((device, private_key)) => {
@@ -62,6 +70,10 @@ async fn main() {
                 Box::new(SnServiceContractServerImpl::new()),
             );
 
+            if let Some(path) = matches.value_of("revoked") {
+                start_revoked_monitor(service.clone(), PathBuf::from(path));
+            }
+
             let _ = service.start().await;
         }
         Err(e) => {
@@ -73,6 +85,65 @@ async fn main() {
     println!("exit.");
 }
 
+// 定期从文件加载owner吊销的设备，sn拒绝为这些设备提供ping和call服务
+fn start_revoked_monitor(service: SnService, path: PathBuf) {
+    async_std::task::spawn(async move {
+        let mut owners: Vec<ObjectId> = vec![];
+        loop {
+            match load_revoked(&path) {
+                Ok(revoked) => {
+                    // 文件里已经没有的owner，清除之前的吊销列表
+                    for owner_id in owners.iter() {
+                        if !revoked.contains_key(owner_id) {
+                            service.update_revoked(owner_id, vec![]);
+                        }
+                    }
+
+                    owners = revoked.keys().cloned().collect();
+                    for (owner_id, list) in revoked {
+                        service.update_revoked(&owner_id, list);
+                    }
+                }
+                Err(e) => {
+                    // 加载失败时保留之前的列表，吊销不能因此失效
+                    log::error!("load revoked devices failed! path={}, {}", path.display(), e);
+                }
+            }
+
+            async_std::task::sleep(Duration::from_secs(REVOKED_RELOAD_INTERVAL_IN_SECS)).await;
+        }
+    });
+}
+
+fn load_revoked(path: &Path) -> BuckyResult<HashMap<ObjectId, Vec<DeviceId>>> {
+    let content = std::fs::read_to_string(path)?;
+    let mut revoked: HashMap<ObjectId, Vec<DeviceId>> = HashMap::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let (owner_id, device_id) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(owner_id), Some(device_id), None) => (owner_id, device_id),
+            _ => {
+                let msg = format!("invalid revoked device line: {}", line);
+                log::error!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::InvalidFormat, msg));
+            }
+        };
+
+        revoked
+            .entry(ObjectId::from_str(owner_id)?)
+            .or_insert_with(Vec::new)
+            .push(DeviceId::from_str(device_id)?);
+    }
+
+    log::info!("load revoked devices: path={}, owners={}", path.display(), revoked.len());
+    Ok(revoked)
+}
+
 fn load_device_info(folder_path: &Path) -> BuckyResult<(Device, PrivateKey)> {
     let (mut device, _) = Device::decode_from_file(folder_path.with_extension("desc").as_path(), &mut vec![])?;
     let (private_key, _) = PrivateKey::decode_from_file(folder_path.with_extension("sec").as_path(), &mut vec![])?;
@@ -9,9 +9,28 @@ use serde::{Deserialize, Serialize};
 
 /*
 [config]
-sandbox = "default"    // default\no\docker
+sandbox = "default"    // default\no\docker\native
 repo_mode = "local"     // named_data/local
 
+[config.native]
+cgroup = "cyfs"         // cgroup v2 sub dir
+seccomp = true
+pids_max = 512
+net_rate = 10240        // kbit/s
+
+[config.supervise]
+restart = "on-failure"  // always\on-failure\never
+max_retries = 3         // 0: unlimited
+backoff_base_secs = 10
+backoff_max_secs = 600
+reset_after_secs = 600
+
+[config.supervise.liveness]
+kind = "process"        // process\http\router
+interval_secs = 60
+timeout_secs = 10
+failure_threshold = 3
+
 [app]
 include = []
 exclude = []
@@ -20,6 +39,12 @@ source = "all"              // all\system\user
 [app.sandbox]
 id1 = "no"
 id2 = "docker"
+
+[app.supervise.id1]
+restart = "always"
+[app.supervise.id1.readiness]
+kind = "http"
+url = "http://127.0.0.1:8080/health"
 */
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -36,14 +61,20 @@ pub struct ManagerConfig {
     #[serde(default)]
     pub sandbox: SandBoxMode,
     #[serde(default)]
-    pub repo_mode: RepoMode
+    pub repo_mode: RepoMode,
+    #[serde(default)]
+    pub native: NativeSandboxConfig,
+    #[serde(default)]
+    pub supervise: SuperviseConfig,
 }
 
 impl Default for ManagerConfig {
     fn default() -> Self {
         Self {
             sandbox: SandBoxMode::default(),
-            repo_mode: RepoMode::default()
+            repo_mode: RepoMode::default(),
+            native: NativeSandboxConfig::default(),
+            supervise: SuperviseConfig::default(),
         }
     }
 }
@@ -59,6 +90,9 @@ pub struct AppConfig {
 
     #[serde(default)]
     pub sandbox: HashMap<DecAppId, SandBoxMode>,
+
+    #[serde(default)]
+    pub supervise: HashMap<DecAppId, SuperviseConfig>,
 }
 
 impl AppConfig {
@@ -78,6 +112,10 @@ impl AppConfig {
         }
         false
     }
+
+    pub fn use_native(&self) -> bool {
+        self.sandbox.values().any(|mode| *mode == SandBoxMode::Native)
+    }
 }
 
 impl Default for AppConfig {
@@ -87,6 +125,7 @@ impl Default for AppConfig {
             exclude: vec![],
             source: AppSource::All,
             sandbox: HashMap::new(),
+            supervise: HashMap::new(),
         }
     }
 }
@@ -128,7 +167,23 @@ impl AppManagerConfig {
     }
 
     pub fn app_use_docker(&self, id: &DecAppId) -> bool {
-        self.app.sandbox.get(id).map(|s|*s == SandBoxMode::Docker).unwrap_or(self.config.sandbox == SandBoxMode::Docker)
+        self.app_sandbox(id) == SandBoxMode::Docker
+    }
+
+    pub fn use_native(&self) -> bool {
+        self.app.use_native() || self.config.sandbox == SandBoxMode::Native
+    }
+
+    pub fn app_use_native(&self, id: &DecAppId) -> bool {
+        self.app_sandbox(id) == SandBoxMode::Native
+    }
+
+    pub fn app_sandbox(&self, id: &DecAppId) -> SandBoxMode {
+        self.app.sandbox.get(id).cloned().unwrap_or(self.config.sandbox.clone())
+    }
+
+    pub fn app_supervise(&self, id: &DecAppId) -> &SuperviseConfig {
+        self.app.supervise.get(id).unwrap_or(&self.config.supervise)
     }
 }
 
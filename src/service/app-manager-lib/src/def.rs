@@ -13,7 +13,10 @@ pub enum SandBoxMode {
     No,
 
     // use docker as sandbox
-    Docker
+    Docker,
+
+    // use linux namespace/cgroup v2/seccomp directly, for devices without docker
+    Native,
 }
 
 #[derive(Clone, Serialize, PartialEq)]
@@ -63,6 +66,9 @@ impl Display for SandBoxMode {
             SandBoxMode::Docker => {
                 f.write_str("docker")
             }
+            SandBoxMode::Native => {
+                f.write_str("native")
+            }
         }
     }
 }
@@ -74,6 +80,7 @@ impl FromStr for SandBoxMode {
         match s {
             "no" => Ok(Self::No),
             "docker" => Ok(Self::Docker),
+            "native" => Ok(Self::Native),
             "default" => Ok(Self::default()),
             v @ _ => {
                 let msg = format!("unknown app manager sandbox mode type: {}", v);
@@ -104,6 +111,222 @@ impl Default for SandBoxMode {
     }
 }
 
+// native sandbox的配置，只在sandbox = "native"时生效
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct NativeSandboxConfig {
+    // cgroup v2下给app使用的子目录，相对/sys/fs/cgroup
+    #[serde(default = "NativeSandboxConfig::default_cgroup")]
+    pub cgroup: String,
+
+    // 是否启用seccomp过滤危险的系统调用
+    #[serde(default = "NativeSandboxConfig::default_seccomp")]
+    pub seccomp: bool,
+
+    // 每个app的最大进程数
+    #[serde(default)]
+    pub pids_max: Option<u32>,
+
+    // 每个app的出口带宽限制，单位 kbit/s
+    #[serde(default)]
+    pub net_rate: Option<u32>,
+
+    // app按分配到的slot使用uid/gid: uid_base + slot
+    #[serde(default = "NativeSandboxConfig::default_uid_base")]
+    pub uid_base: u32,
+}
+
+impl NativeSandboxConfig {
+    fn default_cgroup() -> String {
+        "cyfs".to_owned()
+    }
+
+    fn default_seccomp() -> bool {
+        true
+    }
+
+    fn default_uid_base() -> u32 {
+        30000
+    }
+}
+
+impl Default for NativeSandboxConfig {
+    fn default() -> Self {
+        Self {
+            cgroup: Self::default_cgroup(),
+            seccomp: Self::default_seccomp(),
+            pids_max: None,
+            net_rate: None,
+            uid_base: Self::default_uid_base(),
+        }
+    }
+}
+
+// app退出后的重启策略
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    // 一直重启，不受max_retries限制
+    Always,
+    // 进程退出或者存活探针失败时重启，连续失败超过max_retries后不再重启
+    OnFailure,
+    // 不自动重启
+    Never,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self::OnFailure
+    }
+}
+
+impl Display for RestartPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RestartPolicy::Always => f.write_str("always"),
+            RestartPolicy::OnFailure => f.write_str("on-failure"),
+            RestartPolicy::Never => f.write_str("never"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeKind {
+    // 只检查进程/容器是否存在
+    Process,
+    // GET url, 返回2xx算成功
+    Http,
+    // 向app的router handler post一个ping对象，返回成功算通过
+    Router,
+}
+
+impl Default for ProbeKind {
+    fn default() -> Self {
+        Self::Process
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ProbeConfig {
+    #[serde(default)]
+    pub kind: ProbeKind,
+
+    // kind = "http"时使用，如 http://127.0.0.1:8080/health
+    #[serde(default)]
+    pub url: Option<String>,
+
+    // kind = "router"时使用，app注册post_object handler的req_path
+    #[serde(default)]
+    pub req_path: Option<String>,
+
+    #[serde(default = "ProbeConfig::default_interval_secs")]
+    pub interval_secs: u64,
+
+    #[serde(default = "ProbeConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+
+    // 连续失败多少次才认为失败
+    #[serde(default = "ProbeConfig::default_failure_threshold")]
+    pub failure_threshold: u32,
+}
+
+impl ProbeConfig {
+    fn default_interval_secs() -> u64 {
+        60
+    }
+
+    fn default_timeout_secs() -> u64 {
+        10
+    }
+
+    fn default_failure_threshold() -> u32 {
+        3
+    }
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            kind: ProbeKind::default(),
+            url: None,
+            req_path: None,
+            interval_secs: Self::default_interval_secs(),
+            timeout_secs: Self::default_timeout_secs(),
+            failure_threshold: Self::default_failure_threshold(),
+        }
+    }
+}
+
+// app的守护配置，[config.supervise]是全局默认值，[app.supervise.<id>]可以单独覆盖
+// 只守护状态为Running的app，用户主动停止的app不会被拉起
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SuperviseConfig {
+    #[serde(default)]
+    pub restart: RestartPolicy,
+
+    // 连续重启的最大次数，超过后app进入RunException，0表示不限制
+    #[serde(default = "SuperviseConfig::default_max_retries")]
+    pub max_retries: u32,
+
+    // 重启间隔按base * 2^n增长，最大不超过backoff_max_secs
+    #[serde(default = "SuperviseConfig::default_backoff_base_secs")]
+    pub backoff_base_secs: u64,
+
+    #[serde(default = "SuperviseConfig::default_backoff_max_secs")]
+    pub backoff_max_secs: u64,
+
+    // 稳定运行超过这个时间后，重置连续失败计数
+    #[serde(default = "SuperviseConfig::default_reset_after_secs")]
+    pub reset_after_secs: u64,
+
+    #[serde(default)]
+    pub liveness: Option<ProbeConfig>,
+
+    #[serde(default)]
+    pub readiness: Option<ProbeConfig>,
+}
+
+impl SuperviseConfig {
+    fn default_max_retries() -> u32 {
+        3
+    }
+
+    fn default_backoff_base_secs() -> u64 {
+        10
+    }
+
+    fn default_backoff_max_secs() -> u64 {
+        10 * 60
+    }
+
+    fn default_reset_after_secs() -> u64 {
+        10 * 60
+    }
+
+    // 第n次(从1开始)重启前需要等待的时间
+    pub fn backoff_secs(&self, retry: u32) -> u64 {
+        if retry == 0 {
+            return 0;
+        }
+        let shift = std::cmp::min(retry - 1, 16);
+        std::cmp::min(self.backoff_base_secs.saturating_mul(1 << shift), self.backoff_max_secs)
+    }
+}
+
+impl Default for SuperviseConfig {
+    fn default() -> Self {
+        Self {
+            restart: RestartPolicy::default(),
+            max_retries: Self::default_max_retries(),
+            backoff_base_secs: Self::default_backoff_base_secs(),
+            backoff_max_secs: Self::default_backoff_max_secs(),
+            reset_after_secs: Self::default_reset_after_secs(),
+            liveness: None,
+            readiness: None,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum AppSource {
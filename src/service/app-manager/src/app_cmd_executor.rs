@@ -1,7 +1,9 @@
 use crate::app_controller::{AppActionResult, AppController};
+use crate::app_dep_resolver::AppDepResolver;
 use crate::app_install_detail::AppInstallDetail;
 use crate::docker_api::*;
 use crate::docker_network_manager::{DockerNetworkManager, CYFS_BRIDGE_NAME};
+use crate::native_sandbox::NativeSandbox;
 use crate::non_helper::*;
 use cyfs_base::*;
 use cyfs_core::*;
@@ -54,6 +56,14 @@ impl AppCmdExecutor {
             }
         }
 
+        if self.config.use_native() {
+            info!("app manager init native sandbox");
+            if let Err(e) = NativeSandbox::init(&self.config.config.native) {
+                error!("init native sandbox failed, err: {}", e);
+                return Err(e);
+            }
+        }
+
         Ok(())
     }
 
@@ -191,6 +201,12 @@ impl AppCmdExecutor {
             } else {
                 Some(quota.mem)
             },
+            disk_quota: if quota.disk_space == 0 {
+                None
+            } else {
+                Some(quota.disk_space)
+            },
+            net_rate: self.config.config.native.net_rate,
             ip: None,
             network: None,
         };
@@ -332,7 +348,11 @@ impl AppCmdExecutor {
             }
             Err(e) => {
                 sub_err = e;
-                AppLocalStatusCode::InstallFailed
+                let mut code = None;
+                if sub_err == SubErrorCode::MigrationFailed {
+                    code = self.rollback_install(status.clone(), app_id, &ver).await;
+                }
+                code.unwrap_or(AppLocalStatusCode::InstallFailed)
             }
         };
 
@@ -367,11 +387,23 @@ impl AppCmdExecutor {
                 SubErrorCode::AppNotFound
             })?;
 
+        //依赖的命令排在本app前面串行执行，执行到这里时依赖应该已经装好并且启动了，失败的话不再继续安装
+        let status_list = self.status_list.read().unwrap().clone();
+        AppDepResolver::new(self.non_helper.clone(), &status_list)
+            .check_deps_ready(app_id, version)
+            .await
+            .map_err(|e| {
+                warn!("check deps failed, app:{}, ver:{}, err: {}", app_id, version, e);
+                SubErrorCode::DependencyNotReady
+            })?;
+
         let (no_service, web_dir) = self
             .app_controller
             .install_app(app_id, version, &dec_app)
             .await?;
 
+        self.migrate_internal(status.clone(), app_id, version).await?;
+
         // 获取权限配置并且设置到local status
         let permissions = self
             .app_controller
@@ -411,6 +443,108 @@ impl AppCmdExecutor {
         Ok(target_status_code)
     }
 
+    //迁移失败并且数据已经回滚到快照时，把程序也装回之前的版本，让旧版本继续在旧数据上运行
+    //数据回滚失败时快照会保留到下次安装，这里不动旧版本
+    async fn rollback_install(
+        &self,
+        status: Arc<Mutex<AppLocalStatus>>,
+        app_id: &DecAppId,
+        version: &str,
+    ) -> Option<AppLocalStatusCode> {
+        let migration_status = status.lock().unwrap().migration_status();
+        if migration_status != AppMigrationStatus::RolledBack {
+            warn!("app {} data not rolled back, status {}, skip rollback install", app_id, migration_status);
+            return None;
+        }
+
+        //安装成功才会更新install version，所以这里还是之前的版本
+        let prev = AppInstallDetail::new(app_id).get_install_version()?.to_owned();
+        if prev == version {
+            return None;
+        }
+
+        info!("app {} migrate data failed, rollback install from {} to {}", app_id, version, prev);
+        let dec_app = match self.non_helper.get_dec_app(app_id.object_id(), None).await {
+            Ok(dec_app) => dec_app,
+            Err(e) => {
+                error!("get dec app failed when rollback install, app:{}, err: {}", app_id, e);
+                return None;
+            }
+        };
+
+        match self.app_controller.install_app(app_id, &prev, &dec_app).await {
+            Ok((no_service, web_dir)) => {
+                {
+                    let mut status = status.lock().unwrap();
+                    status.set_version(&prev);
+                    status.set_web_dir(web_dir);
+                }
+                info!("app {} rollback install to {} success", app_id, prev);
+                if no_service {
+                    Some(AppLocalStatusCode::NoService)
+                } else {
+                    Some(AppLocalStatusCode::Stop)
+                }
+            }
+            Err(e) => {
+                error!("app {} rollback install to {} failed, err: {}", app_id, prev, e);
+                None
+            }
+        }
+    }
+
+    //根据安装前记录的数据版本，判断是否需要迁移数据
+    async fn migrate_internal(
+        &self,
+        status: Arc<Mutex<AppLocalStatus>>,
+        app_id: &DecAppId,
+        version: &str,
+    ) -> AppActionResult<()> {
+        let mut install_detail = AppInstallDetail::new(app_id);
+        let target = self.app_controller.get_app_data_version(app_id);
+        //没有记录数据版本但是装过旧版本的，认为数据版本是0
+        let from = install_detail
+            .get_data_version()
+            .or(install_detail.get_install_version().map(|_| 0));
+
+        let from = match from {
+            Some(from) if from != target => from,
+            _ => {
+                info!("app {} not need migrate data, data version {:?}, target {}", app_id, from, target);
+                let _ = install_detail.set_data_version(target);
+                status.lock().unwrap().set_data_version(target);
+                return Ok(());
+            }
+        };
+
+        info!("app {} will migrate data from {} to {}", app_id, from, target);
+        let status_clone = {
+            let mut status = status.lock().unwrap();
+            status.set_data_version(from);
+            status.set_migration_status(AppMigrationStatus::Migrating);
+            status.clone()
+        };
+        let _ = self.non_helper.put_local_status(&status_clone).await;
+
+        let migration_status = self.app_controller.migrate_app_data(app_id, version, from).await;
+        {
+            let mut status = status.lock().unwrap();
+            status.set_migration_status(migration_status);
+            if migration_status == AppMigrationStatus::Success {
+                status.set_data_version(target);
+            }
+        }
+
+        if migration_status == AppMigrationStatus::Success {
+            //migrate_app_data期间install detail已经被修改过(migrate snapshot)，这里要重新加载，不能用之前的实例保存
+            let _ = AppInstallDetail::new(app_id).set_data_version(target);
+            Ok(())
+        } else {
+            warn!("app {} migrate data failed, status {}", app_id, migration_status);
+            Err(SubErrorCode::MigrationFailed)
+        }
+    }
+
     pub(crate) async fn execute_uninstall(
         &self,
         status: Arc<Mutex<AppLocalStatus>>,
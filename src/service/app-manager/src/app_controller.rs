@@ -1,10 +1,14 @@
 use crate::app_acl_util::*;
+use crate::app_install_detail::AppInstallDetail;
+use crate::app_migration::AppDataSnapshot;
 use crate::dapp::DApp;
 use crate::docker_api::*;
+use crate::native_sandbox::NativeSandbox;
+use crate::wasm_runtime::WasmRuntime;
 use crate::package::AppPackage;
 use cyfs_base::*;
 use cyfs_client::{NamedCacheClient, NamedCacheClientConfig};
-use cyfs_core::{DecApp, DecAppId, DecAppObj, SubErrorCode};
+use cyfs_core::{AppMigrationStatus, DecApp, DecAppId, DecAppObj, SubErrorCode};
 use cyfs_lib::*;
 use cyfs_util::*;
 use log::*;
@@ -30,6 +34,8 @@ pub struct AppController {
     shared_stack: OnceCell<SharedCyfsStack>,
     owner: ObjectId,
     docker_api: DockerApi,
+    native_sandbox: NativeSandbox,
+    wasm_runtime: OnceCell<WasmRuntime>,
     named_cache_client: OnceCell<NamedCacheClient>,
     sn_hash: RwLock<HashValue>,
     config: AppManagerConfig,
@@ -57,6 +63,8 @@ impl AppController {
             named_cache_client: OnceCell::new(),
             sn_hash: RwLock::new(HashValue::default()),
             docker_api: DockerApi::new(),
+            native_sandbox: NativeSandbox::new(config.config.native.clone()),
+            wasm_runtime: OnceCell::new(),
             config,
             dapp_instance: RwLock::new(HashMap::new())
         }
@@ -126,6 +134,17 @@ impl AppController {
         });
     }
 
+    // wasm运行时在第一个wasm app启动时才创建
+    fn get_wasm_runtime(&self) -> BuckyResult<&WasmRuntime> {
+        self.wasm_runtime.get_or_try_init(|| WasmRuntime::new())
+    }
+
+    fn is_wasm_app(&self, app_id: &DecAppId) -> bool {
+        DApp::load_from_app_id(&app_id.to_string())
+            .map(|dapp| dapp.get_wasm_info().is_some())
+            .unwrap_or(false)
+    }
+
     //返回isNoService，还有webDir
     pub async fn install_app(
         &self,
@@ -147,10 +166,13 @@ impl AppController {
         })?;
         // stop prev install pid
         let use_docker = self.config.app_use_docker(app_id);
-        info!("app {} use docker install: {}", app_id, use_docker);
+        let use_native = self.config.app_use_native(app_id);
+        info!("app {} use docker install: {}, use native: {}", app_id, use_docker, use_native);
         if use_docker {
             let container_name = format!("decapp-{}-install", app_id.to_string().to_lowercase());
             let _ = stop_docker(&container_name);
+        } else if use_native {
+            let _ = self.native_sandbox.stop_install(&app_id.to_string());
         } else {
             let install_pid_path = get_install_pid_file_path(app_id);
             let work_dir = get_app_dir(&app_id.to_string());
@@ -183,7 +205,9 @@ impl AppController {
 
             //run docker install -> build image
 
-            if use_docker {
+            if dapp.get_wasm_info().is_some() {
+                info!("app {} is wasm app, skip install cmds", app_id);
+            } else if use_docker {
                 info!("run docker install!");
                 let id = app_id.to_string();
                 let install_cmds = dapp.get_install_cmd();
@@ -194,6 +218,17 @@ impl AppController {
                         error!("docker install failed. app:{} failed, {}", app_id, e);
                         SubErrorCode::DockerFailed
                     })?;
+            } else if use_native {
+                info!("run native sandbox install!");
+                let id = app_id.to_string();
+                let install_cmds = dapp.get_install_cmd();
+                self.native_sandbox
+                    .install(&id, version, install_cmds)
+                    .await
+                    .map_err(|e| {
+                        error!("native sandbox install failed. app:{} failed, {}", app_id, e);
+                        SubErrorCode::CommondFailed
+                    })?;
             } else {
                 let install_pid_path = get_install_pid_file_path(app_id);
                 let ret = dapp.install(Some(&install_pid_path));
@@ -207,6 +242,103 @@ impl AppController {
         Ok((no_service, web_dir_id))
     }
 
+    //新安装的版本声明的数据版本，没有service的app为0
+    pub fn get_app_data_version(&self, app_id: &DecAppId) -> u32 {
+        DApp::load_from_app_id(&app_id.to_string())
+            .map(|dapp| dapp.get_data_version())
+            .unwrap_or(0)
+    }
+
+    //升级后把app数据从from迁移到新版本声明的数据版本，失败时回滚到迁移前的快照
+    pub async fn migrate_app_data(&self, app_id: &DecAppId, version: &str, from: u32) -> AppMigrationStatus {
+        let snapshot = AppDataSnapshot::new(self.shared_stack.get().unwrap(), app_id);
+        let mut install_detail = AppInstallDetail::new(app_id);
+
+        //上次迁移中途退出，先回滚到上次的快照
+        if let Some(dec_root) = install_detail.get_migrate_snapshot() {
+            warn!("app {} has unfinished migration, restore to snapshot {} first", app_id, dec_root);
+            if let Err(e) = snapshot.restore(&dec_root).await {
+                error!("restore app {} unfinished migration err {}", app_id, e);
+                return AppMigrationStatus::Failed;
+            }
+            snapshot.discard();
+            let _ = install_detail.set_migrate_snapshot(None);
+        }
+
+        let dapp = match DApp::load_from_app_id(&app_id.to_string()) {
+            Ok(dapp) => dapp,
+            Err(e) => {
+                error!("get dapp instance failed when migrate. app:{}, err:{}", app_id, e);
+                return AppMigrationStatus::Failed;
+            }
+        };
+        if dapp.get_wasm_info().is_some() {
+            error!("wasm app {} not support migration cmds", app_id);
+            return AppMigrationStatus::Failed;
+        }
+        let steps = match dapp.get_migrations(from) {
+            Ok(steps) => steps,
+            Err(_) => return AppMigrationStatus::Failed,
+        };
+
+        let dec_root = match snapshot.take().await {
+            Ok(dec_root) => dec_root,
+            Err(e) => {
+                error!("take app {} data snapshot err {}, cancel migration", app_id, e);
+                return AppMigrationStatus::Failed;
+            }
+        };
+        if let Err(e) = install_detail.set_migrate_snapshot(Some(&dec_root)) {
+            error!("save app {} migrate snapshot err {}, cancel migration", app_id, e);
+            snapshot.discard();
+            return AppMigrationStatus::Failed;
+        }
+
+        let mut ret = Ok(());
+        for step in &steps {
+            info!("app {} migrate data from {} to {}, cmd {}", app_id, step.from, step.to, step.cmd);
+            ret = self.run_migration(app_id, version, &dapp, step.cmd.clone()).await;
+            if ret.is_err() {
+                break;
+            }
+        }
+
+        let status = match ret {
+            Ok(()) => {
+                info!("app {} migrate data from {} to {} success", app_id, from, dapp.get_data_version());
+                AppMigrationStatus::Success
+            }
+            Err(e) => {
+                error!("app {} migrate data from {} err {}, rollback", app_id, from, e);
+                match snapshot.restore(&dec_root).await {
+                    Ok(()) => AppMigrationStatus::RolledBack,
+                    Err(e) => {
+                        //回滚失败时保留快照，下次安装时继续回滚
+                        error!("rollback app {} data to snapshot {} err {}", app_id, dec_root, e);
+                        return AppMigrationStatus::Failed;
+                    }
+                }
+            }
+        };
+
+        snapshot.discard();
+        let _ = install_detail.set_migrate_snapshot(None);
+        status
+    }
+
+    //迁移命令和install命令一样，在app对应的沙箱里执行
+    async fn run_migration(&self, app_id: &DecAppId, version: &str, dapp: &DApp, cmd: String) -> BuckyResult<()> {
+        let id = app_id.to_string();
+        if self.config.app_use_docker(app_id) {
+            self.docker_api.install(&id, version, vec![cmd]).await
+        } else if self.config.app_use_native(app_id) {
+            self.native_sandbox.install(&id, version, vec![cmd]).await
+        } else {
+            let install_pid_path = get_install_pid_file_path(app_id);
+            dapp.migrate(&vec![cmd], Some(&install_pid_path)).map(|_| ())
+        }
+    }
+
     pub async fn uninstall_app(&self, app_id: &DecAppId, ver: &str) -> AppActionResult<()> {
         let _ = self.stop_app(app_id).await;
         info!("try to uninstall after stop. appid:{}", app_id);
@@ -252,6 +384,12 @@ impl AppController {
                 );
                 SubErrorCode::DockerFailed
             });
+        } else if self.config.app_use_native(app_id) {
+            info!("native sandbox instance try to uninstall app:{}", app_id);
+            let _ = self.native_sandbox.uninstall(&app_id.to_string()).await.map_err(|e| {
+                warn!("release native sandbox failed, app:{}, err:{}", app_id, e);
+                SubErrorCode::CommondFailed
+            });
         }
         Ok(())
     }
@@ -260,6 +398,25 @@ impl AppController {
         info!("try to start app:{}", app_id);
         let id = app_id.to_string();
 
+        if self.is_wasm_app(app_id) {
+            let dapp = DApp::load_from_app_id(&id).map_err(|e| {
+                warn!("load app failed, appId: {}, err:{}", id, e);
+                SubErrorCode::LoadFailed
+            })?;
+            let runtime = self.get_wasm_runtime().map_err(|e| {
+                warn!("create wasm runtime failed, appId: {}, {}", app_id, e);
+                SubErrorCode::CommondFailed
+            })?;
+            runtime
+                .start(app_id, dapp.get_wasm_info().unwrap(), config)
+                .await
+                .map_err(|e| {
+                    warn!("wasm runtime start failed, appId: {}, {}", app_id, e);
+                    SubErrorCode::CommondFailed
+                })?;
+            return Ok(());
+        }
+
         let use_docker = self.config.app_use_docker(app_id);
         info!("app {} use docker start: {}", app_id, use_docker);
         if use_docker {
@@ -276,6 +433,20 @@ impl AppController {
                     warn!("docker start failed, appId: {}, {}", app_id, e);
                     SubErrorCode::DockerFailed
                 })?;
+        } else if self.config.app_use_native(app_id) {
+            let dapp = DApp::load_from_app_id(&id).map_err(|e| {
+                warn!("load app failed, appId: {}, err:{}", id, e);
+                SubErrorCode::LoadFailed
+            })?;
+            let cmd = dapp.get_start_cmd();
+            info!("native sandbox service cmd: {}", &cmd);
+            self.native_sandbox
+                .start(&id, config, cmd)
+                .await
+                .map_err(|e| {
+                    warn!("native sandbox start failed, appId: {}, {}", app_id, e);
+                    SubErrorCode::CommondFailed
+                })?;
         } else {
             // 应用在主机直接运行
             info!("run app simple:{}", app_id);
@@ -296,6 +467,17 @@ impl AppController {
 
     pub async fn stop_app(&self, app_id: &DecAppId) -> AppActionResult<()> {
         let id = app_id.to_string();
+        if let Some(runtime) = self.wasm_runtime.get() {
+            if runtime.is_running(app_id) {
+                let _ = runtime.stop(app_id).await;
+                return Ok(());
+            }
+        }
+        if self.is_wasm_app(app_id) {
+            info!("wasm app {} not running", app_id);
+            return Ok(());
+        }
+
         let use_docker = self.config.app_use_docker(app_id);
         info!("app {} use docker stop: {}", app_id, use_docker);
         if use_docker {
@@ -308,6 +490,12 @@ impl AppController {
                     return Err(SubErrorCode::DockerFailed);
                 }
             }
+        } else if self.config.app_use_native(app_id) {
+            self.native_sandbox.stop(&id).map_err(|e| {
+                warn!("stop native sandbox app failed, app:{}, err:{}", app_id, e);
+                SubErrorCode::CommondFailed
+            })?;
+            info!("stop native sandbox app success!, app:{}", id);
         } else {
             let mut app_list = self.dapp_instance.write().unwrap();
             if let Some(dapp) = app_list.remove(app_id) {
@@ -335,11 +523,16 @@ impl AppController {
 
     pub async fn is_app_running(&self, app_id: &DecAppId) -> BuckyResult<bool> {
         let id = app_id.to_string();
+        if self.is_wasm_app(app_id) {
+            return Ok(self.wasm_runtime.get().map(|runtime| runtime.is_running(app_id)).unwrap_or(false));
+        }
 
         let use_docker = self.config.app_use_docker(app_id);
         info!("app {} use docker status: {}", app_id, use_docker);
         if use_docker {
             self.docker_api.is_running(&id)
+        } else if self.config.app_use_native(app_id) {
+            self.native_sandbox.is_running(&id)
         } else {
             if let Some(dapp) = self.dapp_instance.read().unwrap().get(app_id) {
                 dapp.status()
@@ -350,6 +543,26 @@ impl AppController {
         }
     }
 
+    // app自己退出后的退出码，用来区分正常退出和崩溃；wasm app和无法获取时返回None
+    pub fn get_exit_code(&self, app_id: &DecAppId) -> Option<i32> {
+        if self.is_wasm_app(app_id) {
+            return None;
+        }
+
+        let id = app_id.to_string();
+        if self.config.app_use_docker(app_id) {
+            self.docker_api.exit_code(&id)
+        } else if self.config.app_use_native(app_id) {
+            self.native_sandbox.exit_code(&id)
+        } else {
+            self.dapp_instance
+                .read()
+                .unwrap()
+                .get(app_id)
+                .and_then(|dapp| dapp.exit_code())
+        }
+    }
+
     pub async fn get_app_permission(
         &self,
         app_id: &DecAppId,
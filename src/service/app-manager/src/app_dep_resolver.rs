@@ -0,0 +1,632 @@
+use crate::non_helper::NonHelper;
+use cyfs_base::*;
+use cyfs_core::*;
+use log::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanAction {
+    // 没有安装，需要安装
+    Install,
+    // 已安装的版本不满足要求，需要升级
+    Upgrade,
+    // 已安装的版本满足要求，但是没有运行
+    Start,
+    // 已安装并且正在运行
+    Keep,
+}
+
+impl fmt::Display for PlanAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanAction::Install => write!(f, "install"),
+            PlanAction::Upgrade => write!(f, "upgrade"),
+            PlanAction::Start => write!(f, "start"),
+            PlanAction::Keep => write!(f, "keep"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PlanStep {
+    pub app_id: DecAppId,
+    pub version: String,
+    pub action: PlanAction,
+    pub required_by: DecAppId,
+}
+
+struct InstalledApp {
+    version: String,
+    status: AppLocalStatusCode,
+}
+
+// 从DecApp里取出的版本列表和每个版本的依赖，解析过程只依赖这些数据
+#[derive(Default)]
+struct AppCatalog {
+    versions: Vec<String>,
+    deps: HashMap<String, Vec<(DecAppId, String)>>,
+}
+
+impl AppCatalog {
+    fn load(dec_app: &DecApp, installed_version: Option<&str>) -> BuckyResult<Self> {
+        let versions: Vec<String> = dec_app.source().keys().cloned().collect();
+        let mut deps = HashMap::new();
+        for version in versions.iter().map(|v| v.as_str()).chain(installed_version) {
+            if let Some(list) = dec_app.find_deps(version) {
+                let mut ret = vec![];
+                for (dec_id, req) in list {
+                    ret.push((DecAppId::try_from(dec_id)?, req.to_owned()));
+                }
+                deps.insert(version.to_owned(), ret);
+            }
+        }
+
+        Ok(Self { versions, deps })
+    }
+
+    fn deps(&self, version: &str) -> &[(DecAppId, String)] {
+        self.deps.get(version).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+}
+
+enum Resolved {
+    Plan(Vec<PlanStep>),
+    // 缺少这个app的DecApp，加载后重新解析
+    Missing(DecAppId),
+}
+
+// 选中的版本变化后依赖图也会变，多轮之后还不稳定就认为无法解析
+const MAX_RESOLVE_ROUNDS: usize = 32;
+
+/*
+根据DecApp对象里每个版本声明的依赖，计算安装一个app之前要处理的依赖
+1. 依赖已经安装并且版本满足所有要求的，保持不变，没运行的话启动它
+2. 依赖没有安装或者版本不满足的，从DecApp里找同时满足所有要求的最高版本安装/升级
+3. 要求来自依赖图里的所有app，以及不在本次plan里的其它已安装app
+4. 某个依赖换了版本，它自己的依赖也会变，所以按选中的版本反复展开依赖图，直到所有要求都满足
+返回的plan按依赖顺序排列，被依赖的在前面
+*/
+pub struct AppDepResolver {
+    non_helper: Arc<NonHelper>,
+    installed: HashMap<DecAppId, InstalledApp>,
+    catalog: HashMap<DecAppId, AppCatalog>,
+}
+
+impl AppDepResolver {
+    pub fn new(
+        non_helper: Arc<NonHelper>,
+        status_list: &HashMap<DecAppId, Arc<Mutex<AppLocalStatus>>>,
+    ) -> Self {
+        Self {
+            non_helper,
+            installed: Self::load_installed(status_list),
+            catalog: HashMap::new(),
+        }
+    }
+
+    fn load_installed(
+        status_list: &HashMap<DecAppId, Arc<Mutex<AppLocalStatus>>>,
+    ) -> HashMap<DecAppId, InstalledApp> {
+        let mut installed = HashMap::new();
+        for (app_id, status) in status_list {
+            let status = status.lock().unwrap();
+            let status_code = status.status();
+            if !Self::is_installed(status_code) {
+                continue;
+            }
+            if let Some(version) = status.version() {
+                installed.insert(
+                    app_id.clone(),
+                    InstalledApp {
+                        version: version.to_owned(),
+                        status: status_code,
+                    },
+                );
+            }
+        }
+
+        installed
+    }
+
+    fn is_installed(status_code: AppLocalStatusCode) -> bool {
+        match status_code {
+            AppLocalStatusCode::Init
+            | AppLocalStatusCode::Installing
+            | AppLocalStatusCode::InstallFailed
+            | AppLocalStatusCode::Uninstalling
+            | AppLocalStatusCode::Uninstalled
+            | AppLocalStatusCode::ErrStatus => false,
+            _ => true,
+        }
+    }
+
+    pub fn version_matches(version: &str, req_semver: &str) -> BuckyResult<bool> {
+        let req = semver::VersionReq::parse(req_semver).map_err(|e| {
+            let msg = format!("invalid semver req {}, {}", req_semver, e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+        })?;
+        let ver = semver::Version::parse(&SemVerHelper::fix_semver(version)).map_err(|e| {
+            let msg = format!("invalid semver {}, {}", version, e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+        })?;
+
+        Ok(req.matches(&ver))
+    }
+
+    async fn load_catalog(&mut self, app_id: &DecAppId) -> BuckyResult<()> {
+        if self.catalog.contains_key(app_id) {
+            return Ok(());
+        }
+
+        let dec_app = self
+            .non_helper
+            .get_dec_app(app_id.object_id(), None)
+            .await
+            .map_err(|e| {
+                warn!("get dec app failed when resolve deps, app:{}, err:{}", app_id, e);
+                e
+            })?;
+        let installed_version = self.installed.get(app_id).map(|app| app.version.as_str());
+        let catalog = AppCatalog::load(&dec_app, installed_version)?;
+        self.catalog.insert(app_id.clone(), catalog);
+        Ok(())
+    }
+
+    // 已安装app的依赖也是要求的来源，加载失败的跳过
+    async fn load_installed_catalog(&mut self) {
+        let installed: Vec<DecAppId> = self.installed.keys().cloned().collect();
+        for id in installed {
+            if let Err(e) = self.load_catalog(&id).await {
+                warn!("get deps of installed app {} failed, skip it. {}", id, e);
+            }
+        }
+    }
+
+    // 已安装的app里，有哪些依赖了app_id, 返回(依赖者, semver要求)
+    pub async fn find_dependents(&mut self, app_id: &DecAppId) -> BuckyResult<Vec<(DecAppId, String)>> {
+        self.load_installed_catalog().await;
+        Ok(Self::dependents_of(app_id, &HashSet::new(), &self.installed, &self.catalog))
+    }
+
+    fn dependents_of(
+        app_id: &DecAppId,
+        exclude: &HashSet<DecAppId>,
+        installed: &HashMap<DecAppId, InstalledApp>,
+        catalog: &HashMap<DecAppId, AppCatalog>,
+    ) -> Vec<(DecAppId, String)> {
+        let mut ret = vec![];
+        for (id, app) in installed {
+            if id == app_id || exclude.contains(id) {
+                continue;
+            }
+            if let Some(c) = catalog.get(id) {
+                for (dep_id, req) in c.deps(&app.version) {
+                    if dep_id == app_id {
+                        ret.push((id.clone(), req.clone()));
+                    }
+                }
+            }
+        }
+
+        ret
+    }
+
+    pub async fn resolve(&mut self, app_id: &DecAppId, version: &str) -> BuckyResult<Vec<PlanStep>> {
+        info!("resolve install plan for app:{}, ver:{}", app_id, version);
+        self.load_installed_catalog().await;
+
+        loop {
+            match Self::resolve_plan(app_id, version, &self.installed, &self.catalog)? {
+                Resolved::Plan(plan) => {
+                    for step in &plan {
+                        info!(
+                            "resolve dep for app:{}, dep:{}, ver:{}, action:{}, required by:{}",
+                            app_id, step.app_id, step.version, step.action, step.required_by
+                        );
+                    }
+                    return Ok(plan);
+                }
+                Resolved::Missing(id) => self.load_catalog(&id).await?,
+            }
+        }
+    }
+
+    fn resolve_plan(
+        app_id: &DecAppId,
+        version: &str,
+        installed: &HashMap<DecAppId, InstalledApp>,
+        catalog: &HashMap<DecAppId, AppCatalog>,
+    ) -> BuckyResult<Resolved> {
+        let mut selected: HashMap<DecAppId, (String, PlanAction)> = HashMap::new();
+        for _ in 0..MAX_RESOLVE_ROUNDS {
+            // 按当前选中的版本展开依赖图，收集每个app受到的所有要求
+            let mut edges: HashMap<DecAppId, Vec<DecAppId>> = HashMap::new();
+            let mut reqs: HashMap<DecAppId, Vec<(DecAppId, String)>> = HashMap::new();
+            let mut visited = HashSet::new();
+            let mut queue = VecDeque::new();
+            visited.insert(app_id.clone());
+            queue.push_back((app_id.clone(), version.to_owned()));
+            while let Some((cur_id, cur_ver)) = queue.pop_front() {
+                let deps = match catalog.get(&cur_id) {
+                    Some(c) => c.deps(&cur_ver),
+                    None => return Ok(Resolved::Missing(cur_id)),
+                };
+                for (dep_id, req) in deps {
+                    edges.entry(cur_id.clone()).or_default().push(dep_id.clone());
+                    reqs.entry(dep_id.clone()).or_default().push((cur_id.clone(), req.clone()));
+                    if !visited.insert(dep_id.clone()) {
+                        continue;
+                    }
+
+                    if !selected.contains_key(dep_id) {
+                        match Self::select_version(dep_id, &[(cur_id.clone(), req.clone())], installed, catalog)? {
+                            Some(v) => {
+                                selected.insert(dep_id.clone(), v);
+                            }
+                            None => return Ok(Resolved::Missing(dep_id.clone())),
+                        }
+                    }
+                    queue.push_back((dep_id.clone(), selected[dep_id].0.clone()));
+                }
+            }
+
+            // 不在依赖图里的已安装app不会被重新安装，它们对图里app的要求也要满足
+            for id in &visited {
+                let dependents = Self::dependents_of(id, &visited, installed, catalog);
+                if dependents.len() > 0 {
+                    reqs.entry(id.clone()).or_default().extend(dependents);
+                }
+            }
+
+            // app本身的版本是指定的，不满足就是冲突
+            if let Some(list) = reqs.get(app_id) {
+                for (by, req) in list {
+                    if !Self::version_matches(version, req)? {
+                        let msg = format!(
+                            "app {} ver {} conflicts with app {} which requires {}",
+                            app_id, version, by, req
+                        );
+                        warn!("{}", msg);
+                        return Err(BuckyError::new(BuckyErrorCode::Conflict, msg));
+                    }
+                }
+            }
+
+            let mut changed = false;
+            for (dep_id, list) in &reqs {
+                if dep_id == app_id {
+                    continue;
+                }
+                let (cur_ver, _) = &selected[dep_id];
+                let mut matched = true;
+                for (_, req) in list {
+                    if !Self::version_matches(cur_ver, req)? {
+                        matched = false;
+                        break;
+                    }
+                }
+                if matched {
+                    continue;
+                }
+
+                match Self::select_version(dep_id, list, installed, catalog)? {
+                    Some(v) => {
+                        selected.insert(dep_id.clone(), v);
+                        changed = true;
+                    }
+                    None => return Ok(Resolved::Missing(dep_id.clone())),
+                }
+            }
+            if changed {
+                continue;
+            }
+
+            // 按依赖关系排序，被依赖的在前面，根app本身不在plan里
+            let mut order = vec![];
+            let mut visiting = HashSet::new();
+            let mut sorted = HashSet::new();
+            Self::sort(app_id, &edges, &mut visiting, &mut sorted, &mut order)?;
+
+            let plan = order
+                .into_iter()
+                .filter(|id| id != app_id)
+                .map(|id| {
+                    let (version, action) = selected.remove(&id).unwrap();
+                    let required_by = reqs[&id][0].0.clone();
+                    PlanStep {
+                        app_id: id,
+                        version,
+                        action,
+                        required_by,
+                    }
+                })
+                .collect();
+            return Ok(Resolved::Plan(plan));
+        }
+
+        let msg = format!(
+            "cannot resolve dependencies of app {} ver {} in {} rounds",
+            app_id, version, MAX_RESOLVE_ROUNDS
+        );
+        warn!("{}", msg);
+        Err(BuckyError::new(BuckyErrorCode::Conflict, msg))
+    }
+
+    // 选择同时满足所有要求的版本：已安装的版本满足就不动，否则选DecApp里满足的最高版本; 返回None表示需要先加载DecApp
+    fn select_version(
+        app_id: &DecAppId,
+        reqs: &[(DecAppId, String)],
+        installed: &HashMap<DecAppId, InstalledApp>,
+        catalog: &HashMap<DecAppId, AppCatalog>,
+    ) -> BuckyResult<Option<(String, PlanAction)>> {
+        let req = reqs
+            .iter()
+            .map(|(_, req)| req.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ");
+
+        let installed = installed.get(app_id);
+        if let Some(app) = installed {
+            if Self::version_matches(&app.version, &req)? {
+                let action = if app.status == AppLocalStatusCode::Running {
+                    PlanAction::Keep
+                } else {
+                    PlanAction::Start
+                };
+                return Ok(Some((app.version.clone(), action)));
+            }
+        }
+
+        let catalog = match catalog.get(app_id) {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+
+        let mut best: Option<(&str, semver::Version)> = None;
+        for version in &catalog.versions {
+            let ver = match semver::Version::parse(&SemVerHelper::fix_semver(version)) {
+                Ok(ver) => ver,
+                Err(e) => {
+                    warn!("invalid semver {} of app {}, skip it. {}", version, app_id, e);
+                    continue;
+                }
+            };
+            // 和find_version一样，不选预发布版本
+            if !ver.pre.is_empty() || !Self::version_matches(version, &req)? {
+                continue;
+            }
+            if best.as_ref().map(|(_, b)| ver > *b).unwrap_or(true) {
+                best = Some((version.as_str(), ver));
+            }
+        }
+
+        match best {
+            Some((version, _)) => {
+                let action = if installed.is_some() {
+                    PlanAction::Upgrade
+                } else {
+                    PlanAction::Install
+                };
+                Ok(Some((version.to_owned(), action)))
+            }
+            None => {
+                let list: Vec<String> = reqs.iter().map(|(by, req)| format!("{}({})", by, req)).collect();
+                let msg = format!(
+                    "dependency conflict: no version of app {} matches all requirements: {}",
+                    app_id,
+                    list.join(", ")
+                );
+                warn!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::Conflict, msg))
+            }
+        }
+    }
+
+    // 执行app本身的安装前确认plan里的依赖都已经装好并且在运行，依赖的命令失败时不再安装app本身
+    pub async fn check_deps_ready(&mut self, app_id: &DecAppId, version: &str) -> BuckyResult<()> {
+        self.load_catalog(app_id).await?;
+        let deps = self.catalog[app_id].deps(version).to_vec();
+        for (dep_id, req) in deps {
+            let ready = match self.installed.get(&dep_id) {
+                Some(app) => {
+                    (app.status == AppLocalStatusCode::Running || app.status == AppLocalStatusCode::NoService)
+                        && Self::version_matches(&app.version, &req)?
+                }
+                None => false,
+            };
+            if !ready {
+                let current = self
+                    .installed
+                    .get(&dep_id)
+                    .map(|app| format!("{} {}", app.version, app.status));
+                let msg = format!(
+                    "dependency {} of app {} not ready, requires {}, current {:?}",
+                    dep_id, app_id, req, current
+                );
+                warn!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::ErrorState, msg));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sort(
+        app_id: &DecAppId,
+        edges: &HashMap<DecAppId, Vec<DecAppId>>,
+        visiting: &mut HashSet<DecAppId>,
+        visited: &mut HashSet<DecAppId>,
+        order: &mut Vec<DecAppId>,
+    ) -> BuckyResult<()> {
+        if visited.contains(app_id) {
+            return Ok(());
+        }
+        if !visiting.insert(app_id.clone()) {
+            let msg = format!("circular dependency found at app {}", app_id);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Conflict, msg));
+        }
+
+        if let Some(deps) = edges.get(app_id) {
+            for dep in deps {
+                Self::sort(dep, edges, visiting, visited, order)?;
+            }
+        }
+
+        visiting.remove(app_id);
+        visited.insert(app_id.clone());
+        order.push(app_id.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn app(name: &str) -> DecAppId {
+        DecAppId::try_from(&DecApp::generate_id(ObjectId::default(), name)).unwrap()
+    }
+
+    struct Repo {
+        installed: HashMap<DecAppId, InstalledApp>,
+        catalog: HashMap<DecAppId, AppCatalog>,
+    }
+
+    impl Repo {
+        fn new() -> Self {
+            Self {
+                installed: HashMap::new(),
+                catalog: HashMap::new(),
+            }
+        }
+
+        fn publish(&mut self, name: &str, version: &str, deps: &[(&str, &str)]) -> &mut Self {
+            let catalog = self.catalog.entry(app(name)).or_default();
+            catalog.versions.push(version.to_owned());
+            catalog.deps.insert(
+                version.to_owned(),
+                deps.iter().map(|(dep, req)| (app(dep), req.to_string())).collect(),
+            );
+            self
+        }
+
+        fn install(&mut self, name: &str, version: &str, status: AppLocalStatusCode) -> &mut Self {
+            self.installed.insert(
+                app(name),
+                InstalledApp {
+                    version: version.to_owned(),
+                    status,
+                },
+            );
+            self
+        }
+
+        fn resolve(&self, name: &str, version: &str) -> BuckyResult<Vec<(DecAppId, String, PlanAction)>> {
+            match AppDepResolver::resolve_plan(&app(name), version, &self.installed, &self.catalog)? {
+                Resolved::Plan(plan) => Ok(plan
+                    .into_iter()
+                    .map(|step| (step.app_id, step.version, step.action))
+                    .collect()),
+                Resolved::Missing(id) => panic!("missing {}", id),
+            }
+        }
+    }
+
+    #[test]
+    fn test_version_matches() {
+        assert!(AppDepResolver::version_matches("1.0.3.12", "^1.0").unwrap());
+        assert!(AppDepResolver::version_matches("1.2.0.5", ">=1.1, <2").unwrap());
+        assert!(!AppDepResolver::version_matches("2.0.0.1", "~1.0").unwrap());
+        assert!(AppDepResolver::version_matches("1.0.0", "*").unwrap());
+    }
+
+    #[test]
+    fn test_resolve_order() {
+        let mut repo = Repo::new();
+        repo.publish("root", "1.0.0", &[("a", "^1"), ("b", "^1")])
+            .publish("a", "1.0.0", &[("c", "^1")])
+            .publish("b", "1.0.0", &[("c", "^1")])
+            .publish("c", "1.0.0", &[])
+            .publish("c", "1.1.0", &[])
+            .install("b", "1.0.0", AppLocalStatusCode::Stop);
+
+        let plan = repo.resolve("root", "1.0.0").unwrap();
+        assert_eq!(plan.len(), 3);
+        let pos = |name: &str| plan.iter().position(|(id, _, _)| *id == app(name)).unwrap();
+        assert!(pos("c") < pos("a") && pos("c") < pos("b"));
+        assert_eq!(plan[pos("c")], (app("c"), "1.1.0".to_owned(), PlanAction::Install));
+        assert_eq!(plan[pos("b")], (app("b"), "1.0.0".to_owned(), PlanAction::Start));
+    }
+
+    #[test]
+    fn test_resolve_intersection() {
+        // a要^1，b要<1.2，先看到a时选1.3，之后要换成同时满足两者的1.1而不是报冲突
+        let mut repo = Repo::new();
+        repo.publish("root", "1.0.0", &[("a", "^1"), ("b", "^1")])
+            .publish("a", "1.0.0", &[("c", "^1")])
+            .publish("b", "1.0.0", &[("c", "<1.2")])
+            .publish("c", "1.1.0", &[])
+            .publish("c", "1.3.0", &[]);
+        let plan = repo.resolve("root", "1.0.0").unwrap();
+        assert!(plan.contains(&(app("c"), "1.1.0".to_owned(), PlanAction::Install)));
+
+        // 已安装的版本不满足新的要求，但是已安装的其它app还依赖它，要选两边都满足的版本升级
+        let mut repo = Repo::new();
+        repo.publish("root", "1.0.0", &[("c", ">=1.1")])
+            .publish("other", "1.0.0", &[("c", "<1.3")])
+            .publish("c", "1.0.0", &[])
+            .publish("c", "1.2.0", &[])
+            .publish("c", "1.3.0", &[])
+            .install("other", "1.0.0", AppLocalStatusCode::Running)
+            .install("c", "1.0.0", AppLocalStatusCode::Running);
+        let plan = repo.resolve("root", "1.0.0").unwrap();
+        assert_eq!(plan, vec![(app("c"), "1.2.0".to_owned(), PlanAction::Upgrade)]);
+
+        // 真正的冲突
+        let mut repo = Repo::new();
+        repo.publish("root", "1.0.0", &[("a", "^1"), ("c", "^2")])
+            .publish("a", "1.0.0", &[("c", "^1")])
+            .publish("c", "1.0.0", &[])
+            .publish("c", "2.0.0", &[]);
+        assert_eq!(
+            repo.resolve("root", "1.0.0").unwrap_err().code(),
+            BuckyErrorCode::Conflict
+        );
+    }
+
+    #[test]
+    fn test_resolve_version_changes_deps() {
+        // c升级到2.0后多了对d的依赖，要继续展开
+        let mut repo = Repo::new();
+        repo.publish("root", "1.0.0", &[("a", "^1"), ("c", ">=1")])
+            .publish("a", "1.0.0", &[("c", ">=2")])
+            .publish("c", "1.0.0", &[])
+            .publish("c", "2.0.0", &[("d", "^1")])
+            .publish("d", "1.0.0", &[])
+            .install("c", "1.0.0", AppLocalStatusCode::Running);
+        let plan = repo.resolve("root", "1.0.0").unwrap();
+        let pos = |name: &str| plan.iter().position(|(id, _, _)| *id == app(name)).unwrap();
+        assert_eq!(plan.len(), 3);
+        assert_eq!(plan[pos("c")], (app("c"), "2.0.0".to_owned(), PlanAction::Upgrade));
+        assert!(pos("d") < pos("c") && pos("c") < pos("a"));
+    }
+
+    #[test]
+    fn test_sort_cycle() {
+        let mut edges = HashMap::new();
+        edges.insert(app("a"), vec![app("b")]);
+        edges.insert(app("b"), vec![app("c")]);
+        let mut order = vec![];
+        AppDepResolver::sort(&app("a"), &edges, &mut HashSet::new(), &mut HashSet::new(), &mut order).unwrap();
+        assert_eq!(order, vec![app("c"), app("b"), app("a")]);
+
+        edges.insert(app("c"), vec![app("a")]);
+        let ret = AppDepResolver::sort(&app("a"), &edges, &mut HashSet::new(), &mut HashSet::new(), &mut vec![]);
+        assert_eq!(ret.unwrap_err().code(), BuckyErrorCode::Conflict);
+    }
+}
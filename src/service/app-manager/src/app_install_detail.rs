@@ -3,16 +3,21 @@ use cyfs_core::*;
 use log::*;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::str::FromStr;
 use toml;
 
 /*
 [info]
 install_version = "1.0.3"
+data_version = 2
+migrate_snapshot = "95RvaS5..."     // 迁移中的dec root快照，迁移完成后清除
 */
 
 #[derive(Deserialize, Serialize)]
 struct InfoNode {
     install_version: Option<String>,
+    data_version: Option<u32>,
+    migrate_snapshot: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -31,6 +36,8 @@ impl AppInstallDetail {
             detail: Detail {
                 info: InfoNode {
                     install_version: None,
+                    data_version: None,
+                    migrate_snapshot: None,
                 },
             },
             app_id: app_id.to_string(),
@@ -67,6 +74,37 @@ impl AppInstallDetail {
         self.save()
     }
 
+    // 卸载不会清除数据版本，和数据目录一样保留到下次安装
+    pub fn get_data_version(&self) -> Option<u32> {
+        self.detail.info.data_version
+    }
+
+    pub fn set_data_version(&mut self, data_version: u32) -> BuckyResult<()> {
+        if self.detail.info.data_version == Some(data_version) {
+            return Ok(());
+        }
+
+        self.detail.info.data_version = Some(data_version);
+        info!("save data version, {}, {}", self.app_id, data_version);
+
+        self.save()
+    }
+
+    pub fn get_migrate_snapshot(&self) -> Option<ObjectId> {
+        self.detail
+            .info
+            .migrate_snapshot
+            .as_ref()
+            .and_then(|id| ObjectId::from_str(id).ok())
+    }
+
+    pub fn set_migrate_snapshot(&mut self, snapshot: Option<&ObjectId>) -> BuckyResult<()> {
+        self.detail.info.migrate_snapshot = snapshot.map(|id| id.to_string());
+        info!("save migrate snapshot, {}, {:?}", self.app_id, snapshot);
+
+        self.save()
+    }
+
     fn save(&self) -> BuckyResult<()> {
         let content = toml::to_string(&self.detail).map_err(|e| {
             let msg = format!("format app install detail failed! err={}", e);
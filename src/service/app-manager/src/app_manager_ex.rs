@@ -1,6 +1,8 @@
 use crate::app_cmd_executor::AppCmdExecutor;
 use crate::app_controller::AppController;
+use crate::app_dep_resolver::{AppDepResolver, PlanAction, PlanStep};
 use crate::app_install_detail::AppInstallDetail;
+use crate::app_supervisor::{AppSupervisor, SuperviseAction};
 use crate::event_handler::EventListener;
 use crate::non_helper::*;
 use async_std::channel::{Receiver, Sender};
@@ -24,6 +26,8 @@ const STATUS_LASTED_TIME_LIMIT_IN_MICROS: u64 = 15 * 60 * 1000 * 1000;
 
 //1分钟检查一次状态
 const CHECK_STATUS_INTERVAL_IN_SECS: u64 = 1 * 60; //2 * 60 * 1000 * 1000;
+//守护检查的节拍，每个app实际的检查间隔由探针配置和重启退避决定
+const SUPERVISE_TICK_IN_SECS: u64 = 5;
                                                    //每6小时检查一次app的新版本
 const CHECK_APP_UPDATE_INTERVAL_IN_SECS: u64 = 6 * 60 * 60;
 //get sys app list every 30 mins
@@ -52,6 +56,7 @@ pub struct AppManager {
     non_helper: Arc<NonHelper>,
     config: AppManagerConfig,
     start_couter: Arc<RwLock<HashMap<DecAppId, u8>>>,
+    supervisor: AppSupervisor,
 }
 
 impl AppManager {
@@ -75,9 +80,10 @@ impl AppManager {
             sender,
             receiver,
             cmd_executor: None,
-            non_helper: Arc::new(NonHelper::new(owner, shared_stack)),
+            non_helper: Arc::new(NonHelper::new(owner, shared_stack.clone())),
             config,
             start_couter: Arc::new(RwLock::new(HashMap::new())),
+            supervisor: AppSupervisor::new(shared_stack),
         }
     }
 
@@ -160,15 +166,20 @@ impl AppManager {
             }
         });
 
-        // 起一个1分钟的timer，检查App的状态
+        // 起一个timer，检查App的状态，每1分钟唤醒一次执行器
         let manager_checker = manager.clone();
         async_std::task::spawn(async move {
             manager_checker.check_app_status_on_startup().await;
+            let mut last_wakeup = bucky_time_now();
             let mut interval =
-                async_std::stream::interval(Duration::from_secs(CHECK_STATUS_INTERVAL_IN_SECS));
+                async_std::stream::interval(Duration::from_secs(SUPERVISE_TICK_IN_SECS));
             while let Some(_) = interval.next().await {
-                if let Err(e) = manager_checker.sender.send(false).await {
-                    error!("active executor failed! err:{}", e);
+                let now = bucky_time_now();
+                if now >= last_wakeup + CHECK_STATUS_INTERVAL_IN_SECS * 1000 * 1000 {
+                    last_wakeup = now;
+                    if let Err(e) = manager_checker.sender.send(false).await {
+                        error!("active executor failed! err:{}", e);
+                    }
                 }
                 manager_checker.check_app_status().await;
             }
@@ -463,13 +474,18 @@ impl AppManager {
 
     /* 根据local_status检查app状态
     已经入错误状态的app不用管。等下一个命令纠正它。
-    已经入Running状态的app要确保它正在运行。
-    除Running状态的其他非中间状态，不用管。
+    已经入Running状态的app要确保它正在运行，并按守护配置执行探针和重启。
+    守护重启失败进入StartFailed的app，按退避时间继续重启。
+    除此之外的其他非中间状态，不用管。
     */
     async fn check_app_status(&self) {
         let status_list = self.status_list.read().unwrap().clone();
         for (app_id, status) in status_list {
             let status_code = status.lock().unwrap().status();
+            let supervise = self.config.app_supervise(&app_id);
+            if !self.supervisor.is_due(&app_id, supervise) {
+                continue;
+            }
             debug!(
                 "###[STATUS CHECK] app:{}, status should be: {}",
                 app_id, status_code
@@ -480,6 +496,9 @@ impl AppManager {
                 self.check_running_app(&app_id, status.clone()).await;
                 continue;
             }
+            if status_code == AppLocalStatusCode::StartFailed && self.supervisor.is_restarting(&app_id) {
+                self.on_app_failure(&app_id, status.clone(), AppHealthStatus::Unknown, None).await;
+            }
         }
     }
 
@@ -588,47 +607,19 @@ impl AppManager {
         {
             Ok(is_running) => {
                 if is_running {
-                    let mut writer = self.start_couter.write().unwrap();
-                    let running_counter = writer.entry(app_id.clone()).or_insert(0);
-                    if *running_counter > 0 {
-                        info!("reset app {} restart counter {}", app_id, *running_counter);
-                        *running_counter = 0;
+                    //进程还在，再根据探针确认是否健康，存活探针失败和进程退出一样处理
+                    let supervise = self.config.app_supervise(app_id);
+                    let health = self.supervisor.check_health(app_id, supervise).await;
+                    if health == AppHealthStatus::Unhealthy {
+                        info!("[RUNNING CHECK] app liveness probe failed, app:{}", app_id);
+                        self.on_app_failure(app_id, status, health, None).await;
+                    } else {
+                        self.update_app_health(app_id, status, health).await;
                     }
-                    return;
                 } else {
-                    let mut try_start = false;
-                    let mut status_clone = None;
-                    {
-                        let mut status = status.lock().unwrap();
-                        let cur_status_code = status.status();
-                        if cur_status_code != AppLocalStatusCode::Running {
-                            //判断状态是否还是Running，如果不是就不改变状态了
-                            debug!(
-                            "[RUNNING CHECK] after check app running, but current status is not running, skip. app:{}, status: {}",
-                            app_id, cur_status_code
-                        );
-                            return;
-                        }
-                        //status is running, but not actually
-                        let mut writer = self.start_couter.write().unwrap();
-                        let running_counter = writer.entry(app_id.clone()).or_insert(0);
-                        if *running_counter > START_RETRY_LIMIT {
-                            let target_status_code = AppLocalStatusCode::RunException;
-                            info!("[RUNNING CHECK] app failed count is out of limit! app:{}, change app status from [{}] to [{}]", 
-                            app_id, cur_status_code, target_status_code);
-                            status.set_status(target_status_code);
-                            status_clone = Some(status.clone());
-                        } else {
-                            *running_counter = *running_counter + 1;
-                            info!("[RUNNING CHECK] app status is running, but not actually. will restart it, app:{}, retry count:{}", app_id, *running_counter);
-                            try_start = true;
-                        }
-                    }
-                    if try_start {
-                        let _ = self.restart_app(app_id, status.clone()).await;
-                    } else if let Some(new_status) = status_clone {
-                        let _ = self.non_helper.put_local_status(&new_status).await;
-                    }
+                    let exit_code = self.app_controller.get_exit_code(app_id);
+                    info!("[RUNNING CHECK] app status is running, but not actually. app:{}, exit code:{:?}", app_id, exit_code);
+                    self.on_app_failure(app_id, status, AppHealthStatus::Unknown, exit_code).await;
                 }
             }
             Err(e) => {
@@ -645,6 +636,96 @@ impl AppManager {
         }
     }
 
+    async fn update_app_health(
+        &self,
+        app_id: &DecAppId,
+        status: Arc<Mutex<AppLocalStatus>>,
+        health: AppHealthStatus,
+    ) {
+        let status_clone;
+        {
+            let mut status = status.lock().unwrap();
+            if status.status() != AppLocalStatusCode::Running || status.health() == health {
+                return;
+            }
+            info!(
+                "[RUNNING CHECK] app health changed from [{}] to [{}], app:{}",
+                status.health(),
+                health,
+                app_id
+            );
+            status.set_health(health);
+            status_clone = status.clone();
+        }
+        let _ = self.non_helper.put_local_status(&status_clone).await;
+    }
+
+    //app没有运行或者不健康，根据守护配置和退出码决定重启、等待退避、进入Stop还是进入RunException
+    async fn on_app_failure(
+        &self,
+        app_id: &DecAppId,
+        status: Arc<Mutex<AppLocalStatus>>,
+        health: AppHealthStatus,
+        exit_code: Option<i32>,
+    ) {
+        let supervise = self.config.app_supervise(app_id);
+        let mut try_start = false;
+        let status_clone;
+        {
+            let mut status = status.lock().unwrap();
+            let cur_status_code = status.status();
+            if cur_status_code != AppLocalStatusCode::Running
+                && cur_status_code != AppLocalStatusCode::StartFailed
+            {
+                //判断状态是否还是Running，如果不是就不改变状态了
+                debug!(
+                    "[RUNNING CHECK] after check app running, but current status is not running, skip. app:{}, status: {}",
+                    app_id, cur_status_code
+                );
+                return;
+            }
+
+            match self.supervisor.on_failure(app_id, supervise, exit_code) {
+                SuperviseAction::Wait => {
+                    debug!("[RUNNING CHECK] app is in restart backoff, app:{}", app_id);
+                    if status.health() == health {
+                        return;
+                    }
+                }
+                SuperviseAction::Restart(retry) => {
+                    info!(
+                        "[RUNNING CHECK] will restart app:{}, policy:{}, retry count:{}, total restart:{}",
+                        app_id,
+                        supervise.restart,
+                        retry,
+                        status.restart_count() + 1
+                    );
+                    status.set_restart_count(status.restart_count() + 1);
+                    try_start = true;
+                }
+                SuperviseAction::GiveUp => {
+                    let target_status_code = AppLocalStatusCode::RunException;
+                    info!("[RUNNING CHECK] app will not be restarted, policy:{}, max retries:{}, app:{}, change app status from [{}] to [{}]",
+                        supervise.restart, supervise.max_retries, app_id, cur_status_code, target_status_code);
+                    status.set_status(target_status_code);
+                }
+                SuperviseAction::Exited => {
+                    let target_status_code = AppLocalStatusCode::Stop;
+                    info!("[RUNNING CHECK] app exited normally, policy:{}, app:{}, change app status from [{}] to [{}]",
+                        supervise.restart, app_id, cur_status_code, target_status_code);
+                    status.set_status(target_status_code);
+                }
+            }
+            status.set_health(health);
+            status_clone = status.clone();
+        }
+
+        let _ = self.non_helper.put_local_status(&status_clone).await;
+        if try_start {
+            let _ = self.restart_app(app_id, status).await;
+        }
+    }
+
     //这一组函数的意义是响应cmd事件，判断是否可以执行cmd，如果可以执行，改变local_status并且将cmd加入队列
     async fn on_add_cmd(&self, app_id: &DecAppId) -> BuckyResult<()> {
         info!("recv add cmd, app:{}", app_id);
@@ -769,6 +850,142 @@ impl AppManager {
             }
         }
 
+        //用户的操作会打断守护逻辑的重启退避
+        if from_user {
+            self.supervisor.reset(app_id);
+        }
+
+        //安装前先解析依赖，有冲突就拒绝安装；卸载时如果还有已安装的app依赖它，拒绝卸载
+        match cmd_code {
+            CmdCode::Install(install) => {
+                let status_list = self.status_list.read().unwrap().clone();
+                let mut resolver = AppDepResolver::new(self.non_helper.clone(), &status_list);
+                let plan = resolver.resolve(app_id, &install.ver).await?;
+                if plan.len() > 0 {
+                    //先确认app本身可以执行这个命令，以免依赖处理了app本身却装不了
+                    let status_code = status.lock().unwrap().status();
+                    let cmd_group = self.get_cmd_group(&cmd);
+                    if !cmd_group
+                        .iter()
+                        .any(|(cmd, _)| AppCmdExecutor::is_valid_pre_status(cmd.cmd(), status_code))
+                    {
+                        let err_msg = format!(
+                            "cannot do cmd: {}, current status is {}",
+                            cmd.output(),
+                            status_code
+                        );
+                        warn!("{}", err_msg);
+                        return Err(BuckyError::from((BuckyErrorCode::ErrorState, err_msg)));
+                    }
+
+                    self.apply_install_plan(app_id, &install.ver, plan).await?;
+                }
+            }
+            CmdCode::Uninstall => {
+                let status_list = self.status_list.read().unwrap().clone();
+                let mut resolver = AppDepResolver::new(self.non_helper.clone(), &status_list);
+                let dependents = resolver.find_dependents(app_id).await?;
+                if dependents.len() > 0 {
+                    let dependents: Vec<String> = dependents
+                        .iter()
+                        .map(|(id, req)| format!("{}({})", id, req))
+                        .collect();
+                    let err_msg = format!(
+                        "cannot uninstall app {}, required by installed apps: {}",
+                        app_id,
+                        dependents.join(", ")
+                    );
+                    warn!("{}", err_msg);
+                    return Err(BuckyError::from((BuckyErrorCode::Conflict, err_msg)));
+                }
+            }
+            _ => {}
+        }
+
+        self.accept_cmd(app_id, status, cmd, from_user).await
+    }
+
+    //安装依赖时，按plan的顺序把依赖的命令加入队列，排在app本身的命令前面。plan会通过AppManagerAction上报给协议栈
+    //命令是串行执行的，app本身安装前会检查依赖是否都已经装好并运行(check_deps_ready)，依赖失败时app安装失败，sub error为DependencyNotReady
+    async fn apply_install_plan(
+        &self,
+        app_id: &DecAppId,
+        version: &str,
+        plan: Vec<PlanStep>,
+    ) -> BuckyResult<()> {
+        for step in &plan {
+            if step.action != PlanAction::Keep && self.config.app.exclude.contains(&step.app_id) {
+                let err_msg = format!(
+                    "dependency {} of app {} in exclude list",
+                    step.app_id, app_id
+                );
+                warn!("{}", err_msg);
+                return Err(BuckyError::new(BuckyErrorCode::PermissionDenied, err_msg));
+            }
+        }
+
+        let items = plan
+            .iter()
+            .map(|step| AppInstallPlanItem {
+                dec_id: step.app_id.to_string(),
+                version: step.version.clone(),
+                action: step.action.to_string(),
+                required_by: step.required_by.to_string(),
+            })
+            .collect();
+        let action = AppManagerAction::create_install_plan(
+            self.owner.clone(),
+            AppInstallPlan {
+                dec_id: app_id.to_string(),
+                version: version.to_owned(),
+                items,
+            },
+        );
+        if let Err(e) = self.non_helper.post_object_without_resp(&action).await {
+            warn!("report install plan to stack failed, app:{}, err:{}", app_id, e);
+        }
+
+        for step in plan {
+            let cmd = match step.action {
+                PlanAction::Keep => continue,
+                PlanAction::Start => AppCmd::start(self.owner.clone(), step.app_id.clone()),
+                PlanAction::Install | PlanAction::Upgrade => {
+                    if step.action == PlanAction::Install {
+                        if let Err(e) = self.on_add_cmd(&step.app_id).await {
+                            if e.code() != BuckyErrorCode::AlreadyExists {
+                                return Err(e);
+                            }
+                        }
+                    }
+                    AppCmd::install(self.owner.clone(), step.app_id.clone(), &step.version, true)
+                }
+            };
+
+            info!(
+                "apply install plan, app:{}, dep:{}, ver:{}, action:{}",
+                app_id, step.app_id, step.version, step.action
+            );
+            let status = self.status_list.read().unwrap().get(&step.app_id).cloned();
+            let status = status.ok_or_else(|| {
+                let err_msg = format!("status of dependency {} not found", step.app_id);
+                error!("{}", err_msg);
+                BuckyError::new(BuckyErrorCode::NotFound, err_msg)
+            })?;
+            self.accept_cmd(&step.app_id, status, cmd, false).await?;
+        }
+
+        Ok(())
+    }
+
+    //判断当前状态下是否可以执行cmd，可以的话改变local_status并且将cmd加入队列
+    async fn accept_cmd(
+        &self,
+        app_id: &DecAppId,
+        status: Arc<Mutex<AppLocalStatus>>,
+        cmd: AppCmd,
+        from_user: bool,
+    ) -> BuckyResult<()> {
+        let cmd_code = cmd.cmd();
         let mut cmd_group = self.get_cmd_group(&cmd);
 
         let status_clone;
@@ -810,9 +1027,15 @@ impl AppManager {
                 cmd_code, status_code, next_status_code, app_id, cmd_group_code
             );
             status.set_status(next_status_code);
+            status.set_health(AppHealthStatus::Unknown);
             if let CmdCode::Install(install) = cmd_code {
                 status.set_version(&install.ver);
             }
+            if from_user {
+                if let CmdCode::Install(_) | CmdCode::Start = cmd_code {
+                    status.set_restart_count(0);
+                }
+            }
             status_clone = status.clone();
         }
 
@@ -0,0 +1,357 @@
+use cyfs_base::*;
+use cyfs_core::DecAppId;
+use cyfs_lib::*;
+use cyfs_util::{get_app_data_dir, get_cyfs_root_path};
+use fs_extra::dir;
+use log::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/*
+升级迁移前对app数据做快照，迁移失败时回滚
+1. global-state: 记录dec root，回滚时逐层对比，把dec root下的内容恢复成快照里的内容
+2. 本地数据: 把{cyfs_root}/data/app/{id}整个复制到{cyfs_root}/data/app-migration/{id}
+快照的dec root id会记到install detail里，迁移过程中app-manager退出的话，下次安装前先回滚
+*/
+pub struct AppDataSnapshot {
+    app_id: DecAppId,
+    stub: GlobalStateStub,
+}
+
+impl AppDataSnapshot {
+    pub fn new(stack: &SharedCyfsStack, app_id: &DecAppId) -> Self {
+        Self {
+            app_id: app_id.clone(),
+            stub: stack.root_state_stub(None, Some(app_id.object_id().clone())),
+        }
+    }
+
+    fn backup_dir(&self) -> PathBuf {
+        get_cyfs_root_path()
+            .join("data")
+            .join("app-migration")
+            .join(self.app_id.to_string())
+    }
+
+    // 返回快照时的dec root
+    pub async fn take(&self) -> BuckyResult<ObjectId> {
+        let info = self.stub.get_dec_root().await.map_err(|e| {
+            error!("get app {} dec root for snapshot err {}", self.app_id, e);
+            e
+        })?;
+
+        let data_dir = get_app_data_dir(&self.app_id.to_string());
+        let backup_dir = self.backup_dir();
+        replace_dir_content(&data_dir, &backup_dir)?;
+
+        info!(
+            "take app {} data snapshot, dec root {}, data backup {}",
+            self.app_id,
+            info.dec_root,
+            backup_dir.display()
+        );
+        Ok(info.dec_root)
+    }
+
+    pub async fn restore(&self, dec_root: &ObjectId) -> BuckyResult<()> {
+        info!("restore app {} data to snapshot {}", self.app_id, dec_root);
+        self.restore_dec_root(dec_root).await?;
+
+        let backup_dir = self.backup_dir();
+        if backup_dir.is_dir() {
+            replace_dir_content(&backup_dir, &get_app_data_dir(&self.app_id.to_string()))?;
+        } else {
+            warn!(
+                "app {} data backup {} not exists, skip restore local data",
+                self.app_id,
+                backup_dir.display()
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn discard(&self) {
+        let backup_dir = self.backup_dir();
+        if backup_dir.exists() {
+            if let Err(e) = std::fs::remove_dir_all(&backup_dir) {
+                warn!("remove app {} data backup {} err {}", self.app_id, backup_dir.display(), e);
+            }
+        }
+    }
+
+    // dec root不能直接替换，这里从根开始逐层对比当前内容和快照内容:
+    // 当前多出的删除，快照里有而当前没有或者不同的写回，两边都是子object map的继续往下对比
+    async fn restore_dec_root(&self, dec_root: &ObjectId) -> BuckyResult<()> {
+        let current = self.stub.get_dec_root().await?;
+        if current.dec_root == *dec_root {
+            info!("app {} dec root not changed, skip restore", self.app_id);
+            return Ok(());
+        }
+
+        let op_env = self.stub.create_path_op_env().await?;
+        let ret = self.restore_path(&op_env, dec_root).await;
+
+        match ret {
+            Ok(()) => {
+                let info = op_env.commit().await?;
+                info!("restore app {} dec root from {} to {}", self.app_id, current.dec_root, info.dec_root);
+                Ok(())
+            }
+            Err(e) => {
+                error!("restore app {} dec root to {} err {}", self.app_id, dec_root, e);
+                let _ = op_env.abort().await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn restore_path(&self, op_env: &PathOpEnvStub, dec_root: &ObjectId) -> BuckyResult<()> {
+        let mut pending = vec![("/".to_owned(), dec_root.clone())];
+        while let Some((path, snapshot_id)) = pending.pop() {
+            let single = self.stub.create_single_op_env().await?;
+            single.load(snapshot_id.clone()).await?;
+            let snapshot_items = single.list().await;
+            let _ = single.abort().await;
+            let snapshot_items = snapshot_items?;
+            let current_items = op_env.list(path.clone()).await?;
+
+            let ops = match diff_items(&current_items, &snapshot_items) {
+                Some(ops) => ops,
+                None => {
+                    // map和set类型不一致，整个子树替换成快照里的
+                    info!("app {} path {} content type changed, replace with snapshot {}", self.app_id, path, snapshot_id);
+                    op_env.set_with_path(path.clone(), &snapshot_id, None, true).await?;
+                    continue;
+                }
+            };
+
+            for op in ops {
+                match op {
+                    RestoreOp::Remove(key) => {
+                        op_env.remove_with_path(join_path(&path, &key), None).await?;
+                    }
+                    RestoreOp::Set(key, value) => {
+                        op_env.set_with_path(join_path(&path, &key), &value, None, true).await?;
+                    }
+                    RestoreOp::Descend(key, value) => {
+                        pending.push((join_path(&path, &key), value));
+                    }
+                    RestoreOp::RemoveItem(value) => {
+                        op_env.remove(path.clone(), &value).await?;
+                    }
+                    RestoreOp::InsertItem(value) => {
+                        op_env.insert(path.clone(), &value).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RestoreOp {
+    Remove(String),
+    Set(String, ObjectId),
+    Descend(String, ObjectId),
+    RemoveItem(ObjectId),
+    InsertItem(ObjectId),
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.ends_with('/') {
+        format!("{}{}", path, key)
+    } else {
+        format!("{}/{}", path, key)
+    }
+}
+
+// 对比同一层的当前内容和快照内容，得到恢复需要的操作; 两边一个是map一个是set时返回None
+fn diff_items(current: &[ObjectMapContentItem], snapshot: &[ObjectMapContentItem]) -> Option<Vec<RestoreOp>> {
+    let mut current_map = BTreeMap::new();
+    let mut current_set = BTreeSet::new();
+    for item in current {
+        match item {
+            ObjectMapContentItem::Map((key, value)) => {
+                current_map.insert(key.as_str(), value);
+            }
+            ObjectMapContentItem::Set(value) => {
+                current_set.insert(value);
+            }
+            // dec root下不会有diff类型的object map
+            _ => {}
+        }
+    }
+
+    let mut snapshot_map = BTreeMap::new();
+    let mut snapshot_set = BTreeSet::new();
+    for item in snapshot {
+        match item {
+            ObjectMapContentItem::Map((key, value)) => {
+                snapshot_map.insert(key.as_str(), value);
+            }
+            ObjectMapContentItem::Set(value) => {
+                snapshot_set.insert(value);
+            }
+            _ => {}
+        }
+    }
+
+    let is_map = |map: &BTreeMap<_, _>, set: &BTreeSet<_>| -> Option<bool> {
+        match (map.is_empty(), set.is_empty()) {
+            (true, true) => None,
+            (false, _) => Some(true),
+            (true, false) => Some(false),
+        }
+    };
+    if let (Some(a), Some(b)) = (is_map(&current_map, &current_set), is_map(&snapshot_map, &snapshot_set)) {
+        if a != b {
+            return None;
+        }
+    }
+
+    let mut ops = vec![];
+    for key in current_map.keys() {
+        if !snapshot_map.contains_key(key) {
+            ops.push(RestoreOp::Remove(key.to_string()));
+        }
+    }
+    for (key, value) in &snapshot_map {
+        match current_map.get(key) {
+            Some(current) if *current == *value => {}
+            Some(current)
+                if current.obj_type_code() == ObjectTypeCode::ObjectMap
+                    && value.obj_type_code() == ObjectTypeCode::ObjectMap =>
+            {
+                ops.push(RestoreOp::Descend(key.to_string(), (*value).clone()));
+            }
+            _ => ops.push(RestoreOp::Set(key.to_string(), (*value).clone())),
+        }
+    }
+
+    for value in current_set.difference(&snapshot_set) {
+        ops.push(RestoreOp::RemoveItem((*value).clone()));
+    }
+    for value in snapshot_set.difference(&current_set) {
+        ops.push(RestoreOp::InsertItem((*value).clone()));
+    }
+
+    Some(ops)
+}
+
+// 清空target，再把source里的内容复制过去; target可能是挂载点，所以不删除target本身
+fn replace_dir_content(source: &Path, target: &Path) -> BuckyResult<()> {
+    if target.is_dir() {
+        for entry in std::fs::read_dir(target)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                std::fs::remove_dir_all(&path)?;
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+        }
+    } else {
+        std::fs::create_dir_all(target)?;
+    }
+
+    if !source.is_dir() {
+        return Ok(());
+    }
+
+    let mut options = dir::CopyOptions::new();
+    options.copy_inside = true;
+    options.content_only = true;
+    dir::copy(source, target, &options).map_err(|e| {
+        let msg = format!("copy dir {} to {} err {}", source.display(), target.display(), e);
+        error!("{}", msg);
+        BuckyError::new(BuckyErrorCode::IoError, msg)
+    })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_id(data: &str) -> ObjectId {
+        ObjectIdDataBuilder::new().data(data).build().unwrap()
+    }
+
+    fn map_id(owner: &str) -> ObjectId {
+        ObjectMap::new(ObjectMapSimpleContentType::Map, Some(data_id(owner)), None)
+            .no_create_time()
+            .build()
+            .flush_id()
+    }
+
+    fn map_item(key: &str, value: &ObjectId) -> ObjectMapContentItem {
+        ObjectMapContentItem::Map((key.to_owned(), value.clone()))
+    }
+
+    #[test]
+    fn test_diff_items() {
+        let (a, b, c) = (data_id("a"), data_id("b"), data_id("c"));
+        let (m1, m2) = (map_id("m1"), map_id("m2"));
+
+        let current = vec![map_item("same", &a), map_item("added", &b), map_item("changed", &c), map_item("sub", &m1)];
+        let snapshot = vec![map_item("same", &a), map_item("changed", &a), map_item("sub", &m2), map_item("removed", &m1)];
+        let ops = diff_items(&current, &snapshot).unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                RestoreOp::Remove("added".to_owned()),
+                RestoreOp::Set("changed".to_owned(), a.clone()),
+                RestoreOp::Set("removed".to_owned(), m1.clone()),
+                RestoreOp::Descend("sub".to_owned(), m2.clone()),
+            ]
+        );
+
+        let current = vec![ObjectMapContentItem::Set(a.clone()), ObjectMapContentItem::Set(b.clone())];
+        let snapshot = vec![ObjectMapContentItem::Set(b.clone()), ObjectMapContentItem::Set(c.clone())];
+        let ops = diff_items(&current, &snapshot).unwrap();
+        assert_eq!(ops, vec![RestoreOp::RemoveItem(a.clone()), RestoreOp::InsertItem(c.clone())]);
+
+        // 当前为空时全部写回，类型不一致时整体替换
+        assert_eq!(diff_items(&[], &snapshot).unwrap().len(), 2);
+        assert!(diff_items(&[map_item("k", &a)], &snapshot).is_none());
+    }
+
+    #[test]
+    fn test_join_path() {
+        assert_eq!(join_path("/", "a"), "/a");
+        assert_eq!(join_path("/a", "b"), "/a/b");
+    }
+
+    #[test]
+    fn test_replace_dir_content() {
+        let root = std::env::temp_dir().join(format!("app_migration_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let (data, backup) = (root.join("data"), root.join("backup"));
+        std::fs::create_dir_all(data.join("sub")).unwrap();
+        std::fs::write(data.join("a.txt"), "v1").unwrap();
+        std::fs::write(data.join("sub").join("b.txt"), "v1").unwrap();
+
+        // 快照
+        replace_dir_content(&data, &backup).unwrap();
+
+        // 迁移修改了数据
+        std::fs::write(data.join("a.txt"), "v2").unwrap();
+        std::fs::remove_dir_all(data.join("sub")).unwrap();
+        std::fs::write(data.join("new.txt"), "v2").unwrap();
+
+        // 回滚
+        replace_dir_content(&backup, &data).unwrap();
+        assert_eq!(std::fs::read_to_string(data.join("a.txt")).unwrap(), "v1");
+        assert_eq!(std::fs::read_to_string(data.join("sub").join("b.txt")).unwrap(), "v1");
+        assert!(!data.join("new.txt").exists());
+
+        // 没有备份时清空目标
+        replace_dir_content(&root.join("none"), &data).unwrap();
+        assert_eq!(std::fs::read_dir(&data).unwrap().count(), 0);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,577 @@
+use app_manager_lib::{ProbeConfig, ProbeKind, RestartPolicy, SuperviseConfig};
+use cyfs_base::*;
+use cyfs_core::{AppHealthStatus, DecAppId, Text, TextObj};
+use cyfs_lib::*;
+use log::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// 没有配置存活探针时，检查进程是否存在的间隔
+const PROCESS_CHECK_INTERVAL_IN_SECS: u64 = 60;
+// router探针默认使用的req_path
+const DEFAULT_PROBE_REQ_PATH: &str = "/health";
+
+#[derive(Debug, PartialEq)]
+pub enum SuperviseAction {
+    // 还在退避时间内，等下一次检查
+    Wait,
+    // 重启app，参数是连续重启的次数
+    Restart(u32),
+    // 按策略不再重启
+    GiveUp,
+    // app正常退出，on-failure/never策略下不重启
+    Exited,
+}
+
+#[derive(Default)]
+struct ProbeState {
+    failed: u32,
+    last_check: u64,
+}
+
+impl ProbeState {
+    fn is_due(&self, probe: &ProbeConfig, now: u64) -> bool {
+        now >= self.last_check + probe.interval_secs * 1000 * 1000
+    }
+}
+
+struct SuperviseState {
+    // 连续重启次数，稳定运行reset_after_secs后清零
+    failures: u32,
+    last_restart: u64,
+    last_check: u64,
+    running_since: Option<u64>,
+    health: AppHealthStatus,
+    liveness: ProbeState,
+    readiness: ProbeState,
+}
+
+impl Default for SuperviseState {
+    fn default() -> Self {
+        Self {
+            failures: 0,
+            last_restart: 0,
+            last_check: 0,
+            running_since: None,
+            health: AppHealthStatus::Unknown,
+            liveness: ProbeState::default(),
+            readiness: ProbeState::default(),
+        }
+    }
+}
+
+// 守护状态机，时间由调用方传入，不依赖协议栈
+struct SuperviseStates {
+    states: Mutex<HashMap<DecAppId, SuperviseState>>,
+}
+
+impl SuperviseStates {
+    fn new() -> Self {
+        Self {
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn reset(&self, app_id: &DecAppId) {
+        self.states.lock().unwrap().remove(app_id);
+    }
+
+    fn is_restarting(&self, app_id: &DecAppId) -> bool {
+        self.states
+            .lock()
+            .unwrap()
+            .get(app_id)
+            .map(|state| state.failures > 0)
+            .unwrap_or(false)
+    }
+
+    fn is_due(&self, app_id: &DecAppId, config: &SuperviseConfig, now: u64) -> bool {
+        let states = self.states.lock().unwrap();
+        let state = match states.get(app_id) {
+            Some(state) => state,
+            None => return true,
+        };
+
+        if state.running_since.is_none() && state.failures > 0 {
+            return now >= state.last_restart + config.backoff_secs(state.failures) * 1000 * 1000;
+        }
+
+        let mut interval = PROCESS_CHECK_INTERVAL_IN_SECS;
+        for probe in config.liveness.iter().chain(config.readiness.iter()) {
+            interval = std::cmp::min(interval, probe.interval_secs);
+        }
+        now >= state.last_check + interval * 1000 * 1000
+    }
+
+    // 开始一次检查，返回到期需要执行的存活和就绪探针
+    fn begin_check<'a>(
+        &self,
+        app_id: &DecAppId,
+        config: &'a SuperviseConfig,
+        now: u64,
+    ) -> (Option<&'a ProbeConfig>, Option<&'a ProbeConfig>) {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(app_id.clone()).or_default();
+        state.last_check = now;
+        let since = *state.running_since.get_or_insert(now);
+        if state.failures > 0 && now >= since + config.reset_after_secs * 1000 * 1000 {
+            info!(
+                "app {} running stable, reset restart failures {}",
+                app_id, state.failures
+            );
+            state.failures = 0;
+        }
+
+        (
+            config
+                .liveness
+                .as_ref()
+                .filter(|probe| state.liveness.is_due(probe, now)),
+            config
+                .readiness
+                .as_ref()
+                .filter(|probe| state.readiness.is_due(probe, now)),
+        )
+    }
+
+    // 记录探针结果，None表示这一轮没有执行对应的探针
+    fn end_check(
+        &self,
+        app_id: &DecAppId,
+        config: &SuperviseConfig,
+        now: u64,
+        liveness: Option<bool>,
+        readiness: Option<bool>,
+    ) -> AppHealthStatus {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(app_id.clone()).or_default();
+        if let Some(ok) = liveness {
+            Self::update_probe(&mut state.liveness, ok, now);
+        }
+        if let Some(ok) = readiness {
+            Self::update_probe(&mut state.readiness, ok, now);
+        }
+
+        let threshold = |probe: &Option<ProbeConfig>| {
+            probe
+                .as_ref()
+                .map(|p| p.failure_threshold.max(1))
+                .unwrap_or(1)
+        };
+        state.health =
+            if config.liveness.is_some() && state.liveness.failed >= threshold(&config.liveness) {
+                AppHealthStatus::Unhealthy
+            } else if config.readiness.is_some()
+                && (state.readiness.last_check == 0
+                    || state.readiness.failed >= threshold(&config.readiness))
+            {
+                AppHealthStatus::NotReady
+            } else {
+                AppHealthStatus::Ready
+            };
+
+        state.health
+    }
+
+    // 退出码为0算正常退出，拿不到退出码(存活探针失败、app-manager重启过等)按失败处理
+    fn is_clean_exit(policy: RestartPolicy, exit_code: Option<i32>) -> bool {
+        policy != RestartPolicy::Always && exit_code == Some(0)
+    }
+
+    fn on_failure(
+        &self,
+        app_id: &DecAppId,
+        config: &SuperviseConfig,
+        exit_code: Option<i32>,
+        now: u64,
+    ) -> SuperviseAction {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(app_id.clone()).or_default();
+        state.running_since = None;
+        state.health = AppHealthStatus::Unknown;
+
+        if Self::is_clean_exit(config.restart, exit_code) {
+            info!(
+                "app {} exited normally, policy {}, will not restart",
+                app_id, config.restart
+            );
+            state.failures = 0;
+            return SuperviseAction::Exited;
+        }
+
+        match config.restart {
+            RestartPolicy::Never => SuperviseAction::GiveUp,
+            policy => {
+                if policy == RestartPolicy::OnFailure
+                    && config.max_retries > 0
+                    && state.failures >= config.max_retries
+                {
+                    return SuperviseAction::GiveUp;
+                }
+
+                if now < state.last_restart + config.backoff_secs(state.failures) * 1000 * 1000 {
+                    return SuperviseAction::Wait;
+                }
+
+                state.failures += 1;
+                state.last_restart = now;
+                state.liveness = ProbeState::default();
+                state.readiness = ProbeState::default();
+                SuperviseAction::Restart(state.failures)
+            }
+        }
+    }
+
+    fn update_probe(state: &mut ProbeState, ok: bool, now: u64) {
+        state.last_check = now;
+        if ok {
+            state.failed = 0;
+        } else {
+            state.failed += 1;
+        }
+    }
+}
+
+/*
+app的守护：进程检查、存活/就绪探针和重启退避
+状态只保存在内存里，app-manager重启后从头计数；对外展示的重启次数和健康状态保存在AppLocalStatus里
+*/
+pub struct AppSupervisor {
+    stack: SharedCyfsStack,
+    states: SuperviseStates,
+}
+
+impl AppSupervisor {
+    pub fn new(stack: SharedCyfsStack) -> Self {
+        Self {
+            stack,
+            states: SuperviseStates::new(),
+        }
+    }
+
+    // 用户发起的命令会重置守护状态
+    pub fn reset(&self, app_id: &DecAppId) {
+        self.states.reset(app_id)
+    }
+
+    // 是否正在由守护逻辑重启中，用来判断StartFailed是不是重启引起的
+    pub fn is_restarting(&self, app_id: &DecAppId) -> bool {
+        self.states.is_restarting(app_id)
+    }
+
+    // 这一轮是否需要检查app，按探针间隔和重启退避时间决定
+    pub fn is_due(&self, app_id: &DecAppId, config: &SuperviseConfig) -> bool {
+        self.states.is_due(app_id, config, bucky_time_now())
+    }
+
+    // 进程在运行时调用，执行到期的探针，返回当前的健康状态
+    pub async fn check_health(
+        &self,
+        app_id: &DecAppId,
+        config: &SuperviseConfig,
+    ) -> AppHealthStatus {
+        let now = bucky_time_now();
+        let (check_liveness, check_readiness) = self.states.begin_check(app_id, config, now);
+
+        let liveness = match check_liveness {
+            Some(probe) => Some(self.probe(app_id, probe).await),
+            None => None,
+        };
+        let readiness = match check_readiness {
+            Some(probe) => Some(self.probe(app_id, probe).await),
+            None => None,
+        };
+
+        self.states
+            .end_check(app_id, config, now, liveness, readiness)
+    }
+
+    // app不在运行或者存活探针失败时调用，根据重启策略、退出码和退避时间决定下一步
+    pub fn on_failure(
+        &self,
+        app_id: &DecAppId,
+        config: &SuperviseConfig,
+        exit_code: Option<i32>,
+    ) -> SuperviseAction {
+        self.states
+            .on_failure(app_id, config, exit_code, bucky_time_now())
+    }
+
+    async fn probe(&self, app_id: &DecAppId, probe: &ProbeConfig) -> bool {
+        let timeout = Duration::from_secs(probe.timeout_secs);
+        let ret = match probe.kind {
+            // 调用前已经确认过进程存在
+            ProbeKind::Process => return true,
+            ProbeKind::Http => async_std::future::timeout(timeout, self.probe_http(probe)).await,
+            ProbeKind::Router => {
+                async_std::future::timeout(timeout, self.probe_router(app_id, probe)).await
+            }
+        };
+
+        match ret {
+            Ok(Ok(())) => true,
+            Ok(Err(e)) => {
+                warn!("app {} {:?} probe failed, {}", app_id, probe.kind, e);
+                false
+            }
+            Err(_) => {
+                warn!(
+                    "app {} {:?} probe timeout after {}s",
+                    app_id, probe.kind, probe.timeout_secs
+                );
+                false
+            }
+        }
+    }
+
+    async fn probe_http(&self, probe: &ProbeConfig) -> BuckyResult<()> {
+        let url = probe.url.as_ref().ok_or_else(|| {
+            let msg = "http probe not set url".to_owned();
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidParam, msg)
+        })?;
+
+        let resp = surf::get(url).await.map_err(|e| {
+            let msg = format!("http probe {} err {}", url, e);
+            BuckyError::new(BuckyErrorCode::ConnectFailed, msg)
+        })?;
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            let msg = format!("http probe {} return status {}", url, resp.status());
+            Err(BuckyError::new(BuckyErrorCode::Failed, msg))
+        }
+    }
+
+    // 向app注册的post_object handler发送一个Text对象，app返回成功即可
+    async fn probe_router(&self, app_id: &DecAppId, probe: &ProbeConfig) -> BuckyResult<()> {
+        let ping = Text::create("app_manager_probe", "ping", bucky_time_now().to_string());
+        let mut req = NONPostObjectOutputRequest::new_router(
+            None,
+            ping.desc().calculate_id(),
+            ping.to_vec()?,
+        );
+        let req_path = probe.req_path.as_deref().unwrap_or(DEFAULT_PROBE_REQ_PATH);
+        req.common.req_path = Some(
+            RequestGlobalStatePath::new(Some(app_id.object_id().clone()), Some(req_path))
+                .format_string(),
+        );
+
+        match self.stack.non_service().post_object(req).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.code() == BuckyErrorCode::Ok => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SuperviseAction, SuperviseStates};
+    use app_manager_lib::{ProbeConfig, ProbeKind, RestartPolicy, SuperviseConfig};
+    use cyfs_base::ObjectId;
+    use cyfs_core::{AppHealthStatus, DecApp, DecAppId, DecAppObj};
+
+    const SECS: u64 = 1000 * 1000;
+
+    fn app_id() -> DecAppId {
+        DecAppId::try_from(DecApp::generate_id(ObjectId::default(), "supervise-test")).unwrap()
+    }
+
+    #[test]
+    fn test_clean_exit() {
+        assert!(SuperviseStates::is_clean_exit(
+            RestartPolicy::OnFailure,
+            Some(0)
+        ));
+        assert!(SuperviseStates::is_clean_exit(
+            RestartPolicy::Never,
+            Some(0)
+        ));
+        assert!(!SuperviseStates::is_clean_exit(
+            RestartPolicy::Always,
+            Some(0)
+        ));
+        assert!(!SuperviseStates::is_clean_exit(
+            RestartPolicy::OnFailure,
+            Some(1)
+        ));
+        assert!(!SuperviseStates::is_clean_exit(
+            RestartPolicy::OnFailure,
+            Some(137)
+        ));
+        assert!(!SuperviseStates::is_clean_exit(
+            RestartPolicy::OnFailure,
+            None
+        ));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let config = SuperviseConfig::default();
+        assert_eq!(config.backoff_secs(0), 0);
+        assert_eq!(config.backoff_secs(1), 10);
+        assert_eq!(config.backoff_secs(3), 40);
+        assert_eq!(config.backoff_secs(100), 600);
+    }
+
+    #[test]
+    fn test_restart_limit() {
+        let states = SuperviseStates::new();
+        let app_id = app_id();
+        let mut config = SuperviseConfig::default();
+        config.restart = RestartPolicy::OnFailure;
+        config.max_retries = 3;
+
+        let mut now = 1000 * SECS;
+        assert_eq!(
+            states.on_failure(&app_id, &config, Some(1), now),
+            SuperviseAction::Restart(1)
+        );
+        assert!(states.is_restarting(&app_id));
+
+        // 退避时间内不重启
+        now += SECS;
+        assert_eq!(
+            states.on_failure(&app_id, &config, Some(1), now),
+            SuperviseAction::Wait
+        );
+        assert!(!states.is_due(&app_id, &config, now));
+
+        now += config.backoff_secs(1) * SECS;
+        assert!(states.is_due(&app_id, &config, now));
+        assert_eq!(
+            states.on_failure(&app_id, &config, Some(1), now),
+            SuperviseAction::Restart(2)
+        );
+
+        now += config.backoff_secs(2) * SECS;
+        assert_eq!(
+            states.on_failure(&app_id, &config, None, now),
+            SuperviseAction::Restart(3)
+        );
+
+        // 达到最大重启次数后放弃，不再等待退避
+        now += config.backoff_secs(3) * SECS;
+        assert_eq!(
+            states.on_failure(&app_id, &config, Some(1), now),
+            SuperviseAction::GiveUp
+        );
+        assert_eq!(
+            states.on_failure(&app_id, &config, Some(1), now + 3600 * SECS),
+            SuperviseAction::GiveUp
+        );
+
+        // 用户命令重置后重新计数
+        states.reset(&app_id);
+        assert!(!states.is_restarting(&app_id));
+        assert_eq!(
+            states.on_failure(&app_id, &config, Some(1), now),
+            SuperviseAction::Restart(1)
+        );
+
+        // 正常退出不重启
+        assert_eq!(
+            states.on_failure(&app_id, &config, Some(0), now),
+            SuperviseAction::Exited
+        );
+        assert!(!states.is_restarting(&app_id));
+    }
+
+    #[test]
+    fn test_restart_reset_after_stable() {
+        let states = SuperviseStates::new();
+        let app_id = app_id();
+        let mut config = SuperviseConfig::default();
+        config.restart = RestartPolicy::Always;
+        config.max_retries = 1;
+
+        let mut now = 1000 * SECS;
+        for i in 1..=3 {
+            now += config.backoff_secs(i) * SECS;
+            assert_eq!(
+                states.on_failure(&app_id, &config, Some(0), now),
+                SuperviseAction::Restart(i)
+            );
+        }
+
+        // 稳定运行reset_after_secs后清零连续失败次数
+        states.begin_check(&app_id, &config, now);
+        now += config.reset_after_secs * SECS;
+        states.begin_check(&app_id, &config, now);
+        assert!(!states.is_restarting(&app_id));
+        assert_eq!(
+            states.on_failure(&app_id, &config, Some(1), now),
+            SuperviseAction::Restart(1)
+        );
+    }
+
+    #[test]
+    fn test_health_failure_restart() {
+        let states = SuperviseStates::new();
+        let app_id = app_id();
+        let mut config = SuperviseConfig::default();
+        config.restart = RestartPolicy::OnFailure;
+        config.liveness = Some(ProbeConfig {
+            kind: ProbeKind::Http,
+            interval_secs: 10,
+            failure_threshold: 2,
+            ..Default::default()
+        });
+
+        let mut now = 1000 * SECS;
+        let (liveness, readiness) = states.begin_check(&app_id, &config, now);
+        assert!(liveness.is_some());
+        assert!(readiness.is_none());
+        let health = states.end_check(&app_id, &config, now, Some(true), None);
+        assert_eq!(health, AppHealthStatus::Ready);
+
+        // 探针间隔内不会重复执行
+        let (liveness, _) = states.begin_check(&app_id, &config, now + SECS);
+        assert!(liveness.is_none());
+
+        // 连续失败未达到阈值时仍然健康
+        now += 10 * SECS;
+        let (liveness, _) = states.begin_check(&app_id, &config, now);
+        assert!(liveness.is_some());
+        let health = states.end_check(&app_id, &config, now, Some(false), None);
+        assert_eq!(health, AppHealthStatus::Ready);
+
+        now += 10 * SECS;
+        let (liveness, _) = states.begin_check(&app_id, &config, now);
+        assert!(liveness.is_some());
+        let health = states.end_check(&app_id, &config, now, Some(false), None);
+        assert_eq!(health, AppHealthStatus::Unhealthy);
+
+        // 存活探针失败拿不到退出码，按失败重启，并且重置探针状态
+        assert_eq!(
+            states.on_failure(&app_id, &config, None, now),
+            SuperviseAction::Restart(1)
+        );
+        let (liveness, _) = states.begin_check(&app_id, &config, now + SECS);
+        assert!(liveness.is_some());
+        let health = states.end_check(&app_id, &config, now + SECS, Some(false), None);
+        assert_eq!(health, AppHealthStatus::Ready);
+    }
+
+    #[test]
+    fn test_readiness() {
+        let states = SuperviseStates::new();
+        let app_id = app_id();
+        let mut config = SuperviseConfig::default();
+        config.readiness = Some(ProbeConfig {
+            kind: ProbeKind::Http,
+            failure_threshold: 1,
+            ..Default::default()
+        });
+
+        let now = 1000 * SECS;
+        let health = states.end_check(&app_id, &config, now, None, None);
+        assert_eq!(health, AppHealthStatus::NotReady);
+        let health = states.end_check(&app_id, &config, now, None, Some(true));
+        assert_eq!(health, AppHealthStatus::Ready);
+        let health = states.end_check(&app_id, &config, now, None, Some(false));
+        assert_eq!(health, AppHealthStatus::NotReady);
+    }
+}
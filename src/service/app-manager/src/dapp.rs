@@ -1,467 +1,675 @@
-use cyfs_base::{BuckyError, BuckyErrorCode, BuckyResult};
-use cyfs_util::{get_app_dir};
-use log::*;
-use serde::Deserialize;
-use serde_json::Value;
-use std::fs::File;
-use std::io::Read;
-use std::path::{Path, PathBuf};
-use std::process::{Child, Command, ExitStatus};
-use std::sync::Mutex;
-use std::time::Duration;
-use wait_timeout::ChildExt;
-use crate::process_util::{run, try_stop_process_by_pid};
-
-const STATUS_CMD_TIME_OUT_IN_SECS: u64 = 15;
-const STOP_CMD_TIME_OUT_IN_SECS: u64 = 60;
-const START_CMD_TIME_OUT_IN_SECS: u64 = 5 * 60;
-pub(crate) const INSTALL_CMD_TIME_OUT_IN_SECS: u64 = 15 * 60;
-
-#[derive(Deserialize, Clone)]
-pub struct DAppInfo {
-    id: String,
-    version: String,
-    start: String,
-    status: String,
-    stop: String,
-    install: Vec<String>,
-    executable: Vec<String>,
-}
-
-pub struct DApp {
-    dec_id: String,
-    info: DAppInfo,
-    work_dir: PathBuf,
-    process: Mutex<Option<Child>>,
-}
-
-fn get_str(value: &Value, key: &str) -> BuckyResult<String> {
-    Ok(value
-        .get(key)
-        .ok_or(BuckyError::from(BuckyErrorCode::InvalidFormat))?
-        .as_str()
-        .ok_or(BuckyError::from(BuckyErrorCode::InvalidFormat))?
-        .to_owned())
-}
-
-impl Drop for DApp {
-    fn drop(&mut self) {
-        if let Some(child) = self.process.lock().unwrap().as_mut() {
-            let id = child.id();
-            warn!("dapp {} dropped when child process start! pid {}", &self.dec_id, id);
-            if let Err(e) = child.kill() {
-                error!("kill child process {} err {}", id, e);
-            };
-            if let Err(e) = child.wait() {
-                error!("wait child process {} err {}", id, e);
-            };
-        }
-    }
-}
-
-impl DApp {
-    pub fn load_from_app_id(app_id: &str) -> BuckyResult<DApp> {
-        let dapp = DApp::load_from(&get_app_dir(&app_id.to_string()))?;
-        Ok(dapp)
-    }
-
-    // load_from
-    // 获取dapp的配置信息
-    pub fn load_from(path: &PathBuf) -> BuckyResult<DApp> {
-        let package_file = path.join("package.cfg");
-        if !package_file.exists() {
-            error!("package file {} not exist!", package_file.display());
-            return Err(BuckyError::from(BuckyErrorCode::NotFound));
-        }
-
-        // 通过上一级目录拿到 decid
-        let dec_id = {
-            let parent = package_file.parent();
-            let dec_id = parent.unwrap().file_name().unwrap();
-            let dec_id = dec_id.to_str().unwrap().to_string();
-            dec_id
-        };
-
-        // open package.cfg 文件
-        // 如果json解析失败，就把文件的整个内容，log出来
-        let package = File::open(package_file.clone())?;
-        let app_info_root = serde_json::from_reader(package);
-        if let Err(e) = app_info_root {
-            let mut package = File::open(package_file)?;
-            let mut content = String::new();
-            package.read_to_string(&mut content).map_err(|e| {
-                error!("read package.cfg error: {}", e);
-                BuckyError::new(
-                    BuckyErrorCode::InternalError,
-                    format!("read file error: {}", e),
-                )
-            })?;
-            error!(
-                "load dapp package.cfg json error, json content: {}",
-                content
-            );
-            return Err(BuckyError::new(
-                BuckyErrorCode::JsonError,
-                format!("json error: {}", e),
-            ));
-        }
-        let app_info_root = app_info_root.unwrap();
-        info!(
-            "load dapp package.cfg success, json content: {:?}",
-            app_info_root
-        );
-
-        // 解析packge.cfg完成，提取关键字段
-        let app_info = DApp::parse_info(app_info_root)?;
-        if app_info.start.is_empty() {
-            let msg = format!("app {} has no start script!", &dec_id);
-            warn!("{}", &msg);
-        }
-        if app_info.status.is_empty() {
-            let msg = format!("app {} has no status script!", &dec_id);
-            warn!("{}", &msg);
-        }
-        if app_info.stop.is_empty() {
-            let msg = format!("app {} has no stop script!", &dec_id);
-            warn!("{}", &msg);
-        }
-        Ok(DApp {
-            dec_id,
-            info: app_info,
-            work_dir: path.clone(),
-            process: Mutex::new(None),
-        })
-    }
-
-    fn parse_info(root: Value) -> BuckyResult<DAppInfo> {
-        let id = get_str(&root, "id")?;
-
-        let version = get_str(&root, "version")?;
-
-        let start = get_str(&root, "start")?;
-
-        let status = get_str(&root, "status")?;
-
-        let stop = get_str(&root, "stop")?;
-
-        let install = match root
-            .get("install")
-            .ok_or(BuckyError::from(BuckyErrorCode::InvalidFormat))?
-        {
-            Value::String(str) => Ok(vec![str.to_owned()]),
-            Value::Array(array) => {
-                let mut install = vec![];
-                for value in array {
-                    if value.is_string() {
-                        install.push(value.as_str().unwrap().to_owned())
-                    }
-                }
-                Ok(install)
-            }
-            _ => Err(BuckyError::from(BuckyErrorCode::InvalidFormat)),
-        }?;
-        let mut executable = vec![];
-        if let Some(value) = root.get("executable") {
-            match value {
-                Value::String(str) => executable.push(str.to_owned()),
-                Value::Array(array) => {
-                    for value in array {
-                        if value.is_string() {
-                            executable.push(value.as_str().unwrap().to_owned())
-                        }
-                    }
-                }
-                _ => {}
-            }
-        }
-
-        Ok(DAppInfo {
-            id,
-            version,
-            start,
-            status,
-            stop,
-            install,
-            executable,
-        })
-    }
-
-    pub fn get_start_cmd(&self) -> String {
-        self.info.start.clone()
-    }
-
-    pub fn get_executable_binary(&self) -> BuckyResult<Vec<String>> {
-        Ok(self.info.executable.clone())
-    }
-
-    fn get_pid_file_path(&self) -> PathBuf {
-        cyfs_util::get_cyfs_root_path()
-            .join("run")
-            .join(format!("app_manager_app_{}", self.dec_id))
-    }
-
-    pub fn start(&self) -> BuckyResult<bool> {
-        if !self.status()? {
-            let child = run(&self.info.start, &self.work_dir, true, None, Some(self.get_pid_file_path().as_path()))?;
-            *self.process.lock().unwrap() = Some(child);
-            info!(
-                "start app:{} {} success!",
-                self.dec_id, self.info.id
-            );
-
-            return Ok(true);
-        }
-        Ok(false)
-    }
-
-    //time_out == 0 wait forever
-    fn run_cmd(
-        &self,
-        cmd: &str,
-        detach: bool,
-        stdout: Option<File>,
-        time_out: u64,
-        record_pid: Option<&Path>
-    ) -> BuckyResult<i32> {
-        let mut process = run(cmd, &self.work_dir, detach, stdout, record_pid)?;
-
-        let app_id = self.info.id.as_str();
-
-        let wait_exit_status = |status: ExitStatus| match status.code() {
-            None => {
-                error!("get process code failed, app:{}, cmd:{}", app_id, cmd);
-                Err(BuckyError::from(BuckyErrorCode::ExecuteError))
-            }
-            Some(code) => {
-                info!(
-                    "get process code success, app:{}, cmd:{}, code:{}",
-                    app_id, cmd, code
-                );
-                Ok(code)
-            }
-        };
-
-        if time_out == 0 {
-            let exit_status = process.wait().map_err(|e| {
-                error!(
-                    "wait process failed, app:{}, cmd:{}, err:{}",
-                    app_id, cmd, e
-                );
-                e
-            })?;
-            return wait_exit_status(exit_status);
-        }
-
-        let exit_status = process
-            .wait_timeout(Duration::from_secs(time_out))
-            .map_err(|e| {
-                warn!(
-                    "wait timeout process failed, app:{}, cmd:{}, err:{}",
-                    app_id, cmd, e
-                );
-                e
-            })?;
-
-        match exit_status {
-            None => {
-                error!(
-                    "process not exit after timeout, app:{}, cmd:{}",
-                    app_id, cmd
-                );
-
-                #[cfg(windows)]
-                {
-                    let pid = process.id();
-                    let _ = Command::new("taskkill").args(["/F", "/T", "/PID", &pid.to_string()])
-                        .status();
-                }
-
-                let _ = process.kill();
-                let _ = process.wait();
-
-                Err(BuckyError::from(BuckyErrorCode::ExecuteError))
-            }
-            Some(status) => wait_exit_status(status),
-        }
-    }
-
-    fn status_by_cmd(&self) -> BuckyResult<bool> {
-        // 通过命令行判定app运行状态
-        let exit_code = self.run_cmd(
-            &self.info.status,
-            false,
-            None,
-            STATUS_CMD_TIME_OUT_IN_SECS,
-            None,
-        )?;
-        Ok(exit_code != 0)
-    }
-
-    pub fn status(&self) -> BuckyResult<bool> {
-        let mut proc = self.process.lock().unwrap();
-        if proc.is_none() {
-            info!("process obj not exist, check by cmd");
-            self.status_by_cmd()
-        } else {
-            // app是这个进程起的，通过Child对象来判断状态，也能阻止僵尸进程
-            info!("process obj exist, try wait");
-            match proc.as_mut().unwrap().try_wait() {
-                Ok(Some(status)) => {
-                    info!("app exited, name={}, status={}", self.info.id, status);
-
-                    let mut process = proc.take().unwrap();
-                    match process.wait() {
-                        Ok(_) => {
-                            info!("wait app process complete! name={}", self.info.id);
-                        }
-                        Err(e) => {
-                            info!("wait app process error! name={}, err={}", self.info.id, e);
-                        }
-                    }
-
-                    Ok(false)
-                }
-                Ok(None) => {
-                    info!("app running, name={}", self.info.id);
-                    Ok(true)
-                }
-                Err(e) => {
-                    error!("update app state error, name={}, err={}", self.info.id, e);
-
-                    self.status_by_cmd()
-                }
-            }
-        }
-    }
-
-    pub fn stop(&self) -> BuckyResult<bool> {
-        match self.status() {
-            Err(e) => {
-                warn!("check app status failed, app:{}, err:{}", &self.info.id, e);
-                let _ = self._force_stop();
-            }
-            Ok(is_running) => {
-                if is_running {
-                    let process = self.process.lock().unwrap().take();
-                    if process.is_some() {
-                        let mut process = process.unwrap();
-                        info!("stop app through child process");
-                        match process.kill() {
-                            Ok(_) => {
-                                info!("kill app success, name={}", &self.info.id);
-                            }
-                            Err(err) => {
-                                if err.kind() == std::io::ErrorKind::InvalidInput {
-                                    info!("kill app but not exists! name={}", &self.info.id);
-                                } else {
-                                    error!("kill app got err, name={}, err={}", &self.info.id, err);
-                                }
-                            }
-                        }
-
-                        // 需要通过wait来释放进程的一些资源
-                        match process.wait() {
-                            Ok(status) => {
-                                info!(
-                                    "app exit! service={}, status={}",
-                                    &self.info.id,
-                                    status.code().unwrap_or_default()
-                                );
-                            }
-                            Err(e) => {
-                                error!("app exit error! service={}, err={}", &self.info.id, e);
-                            }
-                        }
-                        return Ok(true);
-                    } else {
-                        info!("stop app through cmd");
-
-                        match self.run_cmd(
-                            &self.info.stop,
-                            false,
-                            None,
-                            STOP_CMD_TIME_OUT_IN_SECS,
-                            None,
-                        ) {
-                            Ok(code) => {
-                                if code != 0 {
-                                    let _ = self._force_stop();
-                                }
-                            }
-                            Err(e) => {
-                                error!("kill app by cmd failed, err:{}", e);
-                                let _ = self._force_stop();
-                            }
-                        }
-                    }
-                } else {
-                    let _ = self._force_stop();
-                }
-            }
-        }
-
-        Ok(false)
-    }
-
-    // _force_stop
-    // system kill app by pid
-    // appmanager 通过start记录的pid去兜底删除应用
-    fn _force_stop(&self) -> BuckyResult<()> {
-        try_stop_process_by_pid(self.get_pid_file_path().as_path(), Some(self.work_dir.as_path()))
-    }
-
-    // 这里做DecApp被安装后，执行前，根据配置文件需要做的预配置
-    pub fn prepare(&self) -> BuckyResult<()> {
-        // 非windows下，设置executable对应的文件为可执行
-        #[cfg(not(windows))]
-        {
-            for path in &self.info.executable {
-                let cmd = format!("chmod +x \"{}\"", path);
-                // 就算执行不成功，也可以让开发者打包的时候就设置好，这里不成功不算错
-                let _ = self.run_cmd(
-                    &cmd,
-                    false,
-                    None,
-                    0,
-                    None
-                );
-            }
-        }
-        Ok(())
-    }
-
-    pub fn get_install_cmd(&self) -> Vec<String> {
-        self.info.install.clone()
-    }
-
-    pub fn install(&self, pid_path: Option<&Path>) -> BuckyResult<bool> {
-        let mut cmd_index = 0;
-        for cmd in &self.info.install {
-            let log_file = self.work_dir.join(format!("install_{}.log", cmd_index));
-
-            match self.run_cmd(
-                cmd,
-                false,
-                File::create(log_file).ok(),
-                INSTALL_CMD_TIME_OUT_IN_SECS,
-                pid_path
-            ) {
-                Err(e) => {
-                    error!("run app:{} install cmd {} err {}", &self.info.id, cmd, e);
-                    return Err(e);
-                }
-                Ok(code) => {
-                    if code != 0 {
-                        error!(
-                            "run app:{} install cmd {} exit code: {}",
-                            &self.info.id, cmd, code
-                        );
-                        return Err(BuckyError::from(BuckyErrorCode::ExecuteError));
-                    }
-                    cmd_index += 1;
-                }
-            };
-        }
-        Ok(true)
-    }
-}
+use cyfs_base::{BuckyError, BuckyErrorCode, BuckyResult};
+use cyfs_util::{get_app_dir};
+use log::*;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::Mutex;
+use std::time::Duration;
+use wait_timeout::ChildExt;
+use crate::process_util::{exit_status_code, run, try_stop_process_by_pid};
+
+const STATUS_CMD_TIME_OUT_IN_SECS: u64 = 15;
+const STOP_CMD_TIME_OUT_IN_SECS: u64 = 60;
+const START_CMD_TIME_OUT_IN_SECS: u64 = 5 * 60;
+pub(crate) const INSTALL_CMD_TIME_OUT_IN_SECS: u64 = 15 * 60;
+
+// wasm app可以使用的宿主接口
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WasmPermission {
+    #[serde(rename = "non.get")]
+    NONGet,
+    #[serde(rename = "non.put")]
+    NONPut,
+    #[serde(rename = "root_state")]
+    RootState,
+    #[serde(rename = "ndn.read")]
+    NDNRead,
+    #[serde(rename = "router.handler")]
+    RouterHandler,
+}
+
+// package.cfg里的wasm配置，例如：
+// "wasm": {"module": "service.wasm", "permissions": ["non.get", "router.handler"]}
+#[derive(Deserialize, Clone, Debug)]
+pub struct DAppWasmInfo {
+    pub module: String,
+    #[serde(default)]
+    pub permissions: HashSet<WasmPermission>,
+}
+
+// package.cfg里声明的数据迁移步骤，升级时按from -> to依次执行，例如：
+// "data_version": 2,
+// "migrations": [{"from": 0, "to": 1, "cmd": "node migrate_v1.js"}, {"from": 1, "to": 2, "cmd": "./service --migrate 2"}]
+#[derive(Deserialize, Clone, Debug)]
+pub struct DAppMigration {
+    pub from: u32,
+    pub to: u32,
+    pub cmd: String,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct DAppInfo {
+    id: String,
+    version: String,
+    start: String,
+    status: String,
+    stop: String,
+    install: Vec<String>,
+    executable: Vec<String>,
+    wasm: Option<DAppWasmInfo>,
+    data_version: u32,
+    migrations: Vec<DAppMigration>,
+}
+
+pub struct DApp {
+    dec_id: String,
+    info: DAppInfo,
+    work_dir: PathBuf,
+    process: Mutex<Option<Child>>,
+    // 本进程启动的app退出后记录退出码
+    exit_code: Mutex<Option<i32>>,
+}
+
+fn get_str(value: &Value, key: &str) -> BuckyResult<String> {
+    Ok(value
+        .get(key)
+        .ok_or(BuckyError::from(BuckyErrorCode::InvalidFormat))?
+        .as_str()
+        .ok_or(BuckyError::from(BuckyErrorCode::InvalidFormat))?
+        .to_owned())
+}
+
+impl Drop for DApp {
+    fn drop(&mut self) {
+        if let Some(child) = self.process.lock().unwrap().as_mut() {
+            let id = child.id();
+            warn!("dapp {} dropped when child process start! pid {}", &self.dec_id, id);
+            if let Err(e) = child.kill() {
+                error!("kill child process {} err {}", id, e);
+            };
+            if let Err(e) = child.wait() {
+                error!("wait child process {} err {}", id, e);
+            };
+        }
+    }
+}
+
+impl DApp {
+    pub fn load_from_app_id(app_id: &str) -> BuckyResult<DApp> {
+        let dapp = DApp::load_from(&get_app_dir(&app_id.to_string()))?;
+        Ok(dapp)
+    }
+
+    // load_from
+    // 获取dapp的配置信息
+    pub fn load_from(path: &PathBuf) -> BuckyResult<DApp> {
+        let package_file = path.join("package.cfg");
+        if !package_file.exists() {
+            error!("package file {} not exist!", package_file.display());
+            return Err(BuckyError::from(BuckyErrorCode::NotFound));
+        }
+
+        // 通过上一级目录拿到 decid
+        let dec_id = {
+            let parent = package_file.parent();
+            let dec_id = parent.unwrap().file_name().unwrap();
+            let dec_id = dec_id.to_str().unwrap().to_string();
+            dec_id
+        };
+
+        // open package.cfg 文件
+        // 如果json解析失败，就把文件的整个内容，log出来
+        let package = File::open(package_file.clone())?;
+        let app_info_root = serde_json::from_reader(package);
+        if let Err(e) = app_info_root {
+            let mut package = File::open(package_file)?;
+            let mut content = String::new();
+            package.read_to_string(&mut content).map_err(|e| {
+                error!("read package.cfg error: {}", e);
+                BuckyError::new(
+                    BuckyErrorCode::InternalError,
+                    format!("read file error: {}", e),
+                )
+            })?;
+            error!(
+                "load dapp package.cfg json error, json content: {}",
+                content
+            );
+            return Err(BuckyError::new(
+                BuckyErrorCode::JsonError,
+                format!("json error: {}", e),
+            ));
+        }
+        let app_info_root = app_info_root.unwrap();
+        info!(
+            "load dapp package.cfg success, json content: {:?}",
+            app_info_root
+        );
+
+        // 解析packge.cfg完成，提取关键字段
+        let app_info = DApp::parse_info(app_info_root)?;
+        if app_info.start.is_empty() {
+            let msg = format!("app {} has no start script!", &dec_id);
+            warn!("{}", &msg);
+        }
+        if app_info.status.is_empty() {
+            let msg = format!("app {} has no status script!", &dec_id);
+            warn!("{}", &msg);
+        }
+        if app_info.stop.is_empty() {
+            let msg = format!("app {} has no stop script!", &dec_id);
+            warn!("{}", &msg);
+        }
+        Ok(DApp {
+            dec_id,
+            info: app_info,
+            work_dir: path.clone(),
+            process: Mutex::new(None),
+            exit_code: Mutex::new(None),
+        })
+    }
+
+    fn parse_info(root: Value) -> BuckyResult<DAppInfo> {
+        let id = get_str(&root, "id")?;
+
+        let version = get_str(&root, "version")?;
+
+        let start = get_str(&root, "start")?;
+
+        let status = get_str(&root, "status")?;
+
+        let stop = get_str(&root, "stop")?;
+
+        let install = match root
+            .get("install")
+            .ok_or(BuckyError::from(BuckyErrorCode::InvalidFormat))?
+        {
+            Value::String(str) => Ok(vec![str.to_owned()]),
+            Value::Array(array) => {
+                let mut install = vec![];
+                for value in array {
+                    if value.is_string() {
+                        install.push(value.as_str().unwrap().to_owned())
+                    }
+                }
+                Ok(install)
+            }
+            _ => Err(BuckyError::from(BuckyErrorCode::InvalidFormat)),
+        }?;
+        let mut executable = vec![];
+        if let Some(value) = root.get("executable") {
+            match value {
+                Value::String(str) => executable.push(str.to_owned()),
+                Value::Array(array) => {
+                    for value in array {
+                        if value.is_string() {
+                            executable.push(value.as_str().unwrap().to_owned())
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let wasm = match root.get("wasm") {
+            Some(value) => Some(serde_json::from_value::<DAppWasmInfo>(value.clone()).map_err(|e| {
+                let msg = format!("invalid wasm config {}, err {}", value, e);
+                error!("{}", &msg);
+                BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+            })?),
+            None => None,
+        };
+
+        let data_version = match root.get("data_version") {
+            Some(value) => value.as_u64().ok_or_else(|| {
+                let msg = format!("invalid data_version {}", value);
+                error!("{}", &msg);
+                BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+            })? as u32,
+            None => 0,
+        };
+
+        let migrations = match root.get("migrations") {
+            Some(value) => serde_json::from_value::<Vec<DAppMigration>>(value.clone()).map_err(|e| {
+                let msg = format!("invalid migrations config {}, err {}", value, e);
+                error!("{}", &msg);
+                BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+            })?,
+            None => vec![],
+        };
+
+        Ok(DAppInfo {
+            id,
+            version,
+            start,
+            status,
+            stop,
+            install,
+            executable,
+            wasm,
+            data_version,
+            migrations,
+        })
+    }
+
+    pub fn get_start_cmd(&self) -> String {
+        self.info.start.clone()
+    }
+
+    pub fn get_wasm_info(&self) -> Option<&DAppWasmInfo> {
+        self.info.wasm.as_ref()
+    }
+
+    pub fn get_data_version(&self) -> u32 {
+        self.info.data_version
+    }
+
+    // 找到从from升级到当前data_version需要依次执行的迁移步骤
+    pub fn get_migrations(&self, from: u32) -> BuckyResult<Vec<DAppMigration>> {
+        let to = self.info.data_version;
+        if from > to {
+            let msg = format!("app {} data version downgrade from {} to {} not support", self.dec_id, from, to);
+            error!("{}", &msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotSupport, msg));
+        }
+
+        // 按步数广度优先搜索最短的迁移路径，同一层里优先展开跨度大的步骤
+        let mut prev: HashMap<u32, &DAppMigration> = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+        while let Some(cur) = queue.pop_front() {
+            if cur == to {
+                break;
+            }
+
+            let mut next: Vec<&DAppMigration> = self
+                .info
+                .migrations
+                .iter()
+                .filter(|step| step.from == cur && step.to > cur && step.to <= to)
+                .collect();
+            next.sort_by(|a, b| b.to.cmp(&a.to));
+            for step in next {
+                if step.to != from && !prev.contains_key(&step.to) {
+                    prev.insert(step.to, step);
+                    queue.push_back(step.to);
+                }
+            }
+        }
+
+        let mut steps = vec![];
+        let mut cur = to;
+        while cur != from {
+            let step = prev.get(&cur).ok_or_else(|| {
+                let msg = format!("app {} has no migration path from data version {} to {}", self.dec_id, from, to);
+                error!("{}", &msg);
+                BuckyError::new(BuckyErrorCode::NotFound, msg)
+            })?;
+            steps.push((*step).clone());
+            cur = step.from;
+        }
+        steps.reverse();
+
+        Ok(steps)
+    }
+
+    pub fn get_executable_binary(&self) -> BuckyResult<Vec<String>> {
+        Ok(self.info.executable.clone())
+    }
+
+    fn get_pid_file_path(&self) -> PathBuf {
+        cyfs_util::get_cyfs_root_path()
+            .join("run")
+            .join(format!("app_manager_app_{}", self.dec_id))
+    }
+
+    pub fn start(&self) -> BuckyResult<bool> {
+        if !self.status()? {
+            let child = run(&self.info.start, &self.work_dir, true, None, Some(self.get_pid_file_path().as_path()))?;
+            *self.process.lock().unwrap() = Some(child);
+            *self.exit_code.lock().unwrap() = None;
+            info!(
+                "start app:{} {} success!",
+                self.dec_id, self.info.id
+            );
+
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    //time_out == 0 wait forever
+    fn run_cmd(
+        &self,
+        cmd: &str,
+        detach: bool,
+        stdout: Option<File>,
+        time_out: u64,
+        record_pid: Option<&Path>
+    ) -> BuckyResult<i32> {
+        let mut process = run(cmd, &self.work_dir, detach, stdout, record_pid)?;
+
+        let app_id = self.info.id.as_str();
+
+        let wait_exit_status = |status: ExitStatus| match status.code() {
+            None => {
+                error!("get process code failed, app:{}, cmd:{}", app_id, cmd);
+                Err(BuckyError::from(BuckyErrorCode::ExecuteError))
+            }
+            Some(code) => {
+                info!(
+                    "get process code success, app:{}, cmd:{}, code:{}",
+                    app_id, cmd, code
+                );
+                Ok(code)
+            }
+        };
+
+        if time_out == 0 {
+            let exit_status = process.wait().map_err(|e| {
+                error!(
+                    "wait process failed, app:{}, cmd:{}, err:{}",
+                    app_id, cmd, e
+                );
+                e
+            })?;
+            return wait_exit_status(exit_status);
+        }
+
+        let exit_status = process
+            .wait_timeout(Duration::from_secs(time_out))
+            .map_err(|e| {
+                warn!(
+                    "wait timeout process failed, app:{}, cmd:{}, err:{}",
+                    app_id, cmd, e
+                );
+                e
+            })?;
+
+        match exit_status {
+            None => {
+                error!(
+                    "process not exit after timeout, app:{}, cmd:{}",
+                    app_id, cmd
+                );
+
+                #[cfg(windows)]
+                {
+                    let pid = process.id();
+                    let _ = Command::new("taskkill").args(["/F", "/T", "/PID", &pid.to_string()])
+                        .status();
+                }
+
+                let _ = process.kill();
+                let _ = process.wait();
+
+                Err(BuckyError::from(BuckyErrorCode::ExecuteError))
+            }
+            Some(status) => wait_exit_status(status),
+        }
+    }
+
+    fn status_by_cmd(&self) -> BuckyResult<bool> {
+        // 通过命令行判定app运行状态
+        let exit_code = self.run_cmd(
+            &self.info.status,
+            false,
+            None,
+            STATUS_CMD_TIME_OUT_IN_SECS,
+            None,
+        )?;
+        Ok(exit_code != 0)
+    }
+
+    // 本进程启动的app退出后才有，其它情况返回None
+    pub fn exit_code(&self) -> Option<i32> {
+        *self.exit_code.lock().unwrap()
+    }
+
+    pub fn status(&self) -> BuckyResult<bool> {
+        let mut proc = self.process.lock().unwrap();
+        if proc.is_none() {
+            info!("process obj not exist, check by cmd");
+            self.status_by_cmd()
+        } else {
+            // app是这个进程起的，通过Child对象来判断状态，也能阻止僵尸进程
+            info!("process obj exist, try wait");
+            match proc.as_mut().unwrap().try_wait() {
+                Ok(Some(status)) => {
+                    info!("app exited, name={}, status={}", self.info.id, status);
+                    *self.exit_code.lock().unwrap() = Some(exit_status_code(&status));
+
+                    let mut process = proc.take().unwrap();
+                    match process.wait() {
+                        Ok(_) => {
+                            info!("wait app process complete! name={}", self.info.id);
+                        }
+                        Err(e) => {
+                            info!("wait app process error! name={}, err={}", self.info.id, e);
+                        }
+                    }
+
+                    Ok(false)
+                }
+                Ok(None) => {
+                    info!("app running, name={}", self.info.id);
+                    Ok(true)
+                }
+                Err(e) => {
+                    error!("update app state error, name={}, err={}", self.info.id, e);
+
+                    self.status_by_cmd()
+                }
+            }
+        }
+    }
+
+    pub fn stop(&self) -> BuckyResult<bool> {
+        match self.status() {
+            Err(e) => {
+                warn!("check app status failed, app:{}, err:{}", &self.info.id, e);
+                let _ = self._force_stop();
+            }
+            Ok(is_running) => {
+                if is_running {
+                    let process = self.process.lock().unwrap().take();
+                    if process.is_some() {
+                        let mut process = process.unwrap();
+                        info!("stop app through child process");
+                        match process.kill() {
+                            Ok(_) => {
+                                info!("kill app success, name={}", &self.info.id);
+                            }
+                            Err(err) => {
+                                if err.kind() == std::io::ErrorKind::InvalidInput {
+                                    info!("kill app but not exists! name={}", &self.info.id);
+                                } else {
+                                    error!("kill app got err, name={}, err={}", &self.info.id, err);
+                                }
+                            }
+                        }
+
+                        // 需要通过wait来释放进程的一些资源
+                        match process.wait() {
+                            Ok(status) => {
+                                info!(
+                                    "app exit! service={}, status={}",
+                                    &self.info.id,
+                                    status.code().unwrap_or_default()
+                                );
+                            }
+                            Err(e) => {
+                                error!("app exit error! service={}, err={}", &self.info.id, e);
+                            }
+                        }
+                        return Ok(true);
+                    } else {
+                        info!("stop app through cmd");
+
+                        match self.run_cmd(
+                            &self.info.stop,
+                            false,
+                            None,
+                            STOP_CMD_TIME_OUT_IN_SECS,
+                            None,
+                        ) {
+                            Ok(code) => {
+                                if code != 0 {
+                                    let _ = self._force_stop();
+                                }
+                            }
+                            Err(e) => {
+                                error!("kill app by cmd failed, err:{}", e);
+                                let _ = self._force_stop();
+                            }
+                        }
+                    }
+                } else {
+                    let _ = self._force_stop();
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    // _force_stop
+    // system kill app by pid
+    // appmanager 通过start记录的pid去兜底删除应用
+    fn _force_stop(&self) -> BuckyResult<()> {
+        try_stop_process_by_pid(self.get_pid_file_path().as_path(), Some(self.work_dir.as_path()))
+    }
+
+    // 这里做DecApp被安装后，执行前，根据配置文件需要做的预配置
+    pub fn prepare(&self) -> BuckyResult<()> {
+        // 非windows下，设置executable对应的文件为可执行
+        #[cfg(not(windows))]
+        {
+            for path in &self.info.executable {
+                let cmd = format!("chmod +x \"{}\"", path);
+                // 就算执行不成功，也可以让开发者打包的时候就设置好，这里不成功不算错
+                let _ = self.run_cmd(
+                    &cmd,
+                    false,
+                    None,
+                    0,
+                    None
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_install_cmd(&self) -> Vec<String> {
+        self.info.install.clone()
+    }
+
+    pub fn install(&self, pid_path: Option<&Path>) -> BuckyResult<bool> {
+        self.run_cmds(&self.info.install, "install", pid_path)
+    }
+
+    // 在宿主上执行迁移命令，和install命令的执行方式一样
+    pub fn migrate(&self, cmds: &Vec<String>, pid_path: Option<&Path>) -> BuckyResult<bool> {
+        self.run_cmds(cmds, "migrate", pid_path)
+    }
+
+    fn run_cmds(&self, cmds: &Vec<String>, name: &str, pid_path: Option<&Path>) -> BuckyResult<bool> {
+        let mut cmd_index = 0;
+        for cmd in cmds {
+            let log_file = self.work_dir.join(format!("{}_{}.log", name, cmd_index));
+
+            match self.run_cmd(
+                cmd,
+                false,
+                File::create(log_file).ok(),
+                INSTALL_CMD_TIME_OUT_IN_SECS,
+                pid_path
+            ) {
+                Err(e) => {
+                    error!("run app:{} {} cmd {} err {}", &self.info.id, name, cmd, e);
+                    return Err(e);
+                }
+                Ok(code) => {
+                    if code != 0 {
+                        error!(
+                            "run app:{} {} cmd {} exit code: {}",
+                            &self.info.id, name, cmd, code
+                        );
+                        return Err(BuckyError::from(BuckyErrorCode::ExecuteError));
+                    }
+                    cmd_index += 1;
+                }
+            };
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dapp_with_migrations(data_version: u32, migrations: &[(u32, u32)]) -> DApp {
+        let info = DAppInfo {
+            id: "test".to_owned(),
+            version: "1.0.0".to_owned(),
+            start: String::new(),
+            status: String::new(),
+            stop: String::new(),
+            install: vec![],
+            executable: vec![],
+            wasm: None,
+            data_version,
+            migrations: migrations
+                .iter()
+                .map(|(from, to)| DAppMigration {
+                    from: *from,
+                    to: *to,
+                    cmd: format!("migrate {} {}", from, to),
+                })
+                .collect(),
+        };
+        DApp {
+            dec_id: "test".to_owned(),
+            info,
+            work_dir: PathBuf::new(),
+            process: Mutex::new(None),
+            exit_code: Mutex::new(None),
+        }
+    }
+
+    fn steps(dapp: &DApp, from: u32) -> BuckyResult<Vec<(u32, u32)>> {
+        Ok(dapp.get_migrations(from)?.iter().map(|step| (step.from, step.to)).collect())
+    }
+
+    #[test]
+    fn test_get_migrations() {
+        let dapp = dapp_with_migrations(3, &[(0, 1), (1, 2), (2, 3), (1, 3)]);
+        assert_eq!(steps(&dapp, 3).unwrap(), vec![]);
+        // 能跳过的优先选跨度最大的一步
+        assert_eq!(steps(&dapp, 0).unwrap(), vec![(0, 1), (1, 3)]);
+        assert_eq!(steps(&dapp, 2).unwrap(), vec![(2, 3)]);
+        assert_eq!(steps(&dapp, 4).unwrap_err().code(), BuckyErrorCode::NotSupport);
+
+        // 缺少中间步骤
+        let dapp = dapp_with_migrations(3, &[(0, 1), (2, 3)]);
+        assert_eq!(steps(&dapp, 0).unwrap_err().code(), BuckyErrorCode::NotFound);
+
+        // 超过目标版本的步骤不会被选中
+        let dapp = dapp_with_migrations(2, &[(0, 3), (0, 2)]);
+        assert_eq!(steps(&dapp, 0).unwrap(), vec![(0, 2)]);
+
+        // 跨度最大的一步走不通时，要能找到其它能到达目标的路径
+        let dapp = dapp_with_migrations(3, &[(0, 2), (0, 1), (1, 3)]);
+        assert_eq!(steps(&dapp, 0).unwrap(), vec![(0, 1), (1, 3)]);
+
+        // 取步数最少的路径
+        let dapp = dapp_with_migrations(4, &[(0, 1), (1, 2), (2, 3), (3, 4), (0, 2), (2, 4)]);
+        assert_eq!(steps(&dapp, 0).unwrap(), vec![(0, 2), (2, 4)]);
+    }
+}
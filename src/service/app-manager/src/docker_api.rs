@@ -18,6 +18,10 @@ pub struct RunConfig {
     pub cpu_shares: Option<i64>,
     // 单位 MiB:  ->  *1048576   = xxx bytes
     pub memory: Option<i64>,
+    // 单位 MiB, 目前只有native sandbox支持
+    pub disk_quota: Option<i64>,
+    // 出口带宽 kbit/s, 目前只有native sandbox支持
+    pub net_rate: Option<u32>,
 
     pub network: Option<String>,
     pub ip: Option<String>,
@@ -29,6 +33,8 @@ impl Default for RunConfig {
             cpu_core: None,
             cpu_shares: Some(100),
             memory: Some(1024),
+            disk_quota: None,
+            net_rate: None,
             network: None,
             ip: None,
         }
@@ -99,6 +105,24 @@ fn is_docker_running(name: &str) -> BuckyResult<bool> {
     }
 }
 
+// 容器已经退出时返回退出码
+fn get_docker_exit_code(name: &str) -> Option<i32> {
+    let output = run_docker(vec!["container", "inspect", name, "--format", "{{.State.Status}} {{.State.ExitCode}}"])
+        .ok()?
+        .wait_with_output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout);
+    let mut fields = output.split_whitespace();
+    match (fields.next(), fields.next()) {
+        (Some("exited"), Some(code)) => code.parse().ok(),
+        _ => None,
+    }
+}
+
 pub(crate) fn stop_docker(name: &str) -> BuckyResult<()> {
     info!("try to stop container[{}]", name);
     if !(is_docker_running(name)?) {
@@ -393,7 +417,8 @@ impl DockerApi {
             "--log-driver".to_string(), "json-file".to_string(),
             "--log-opt".to_string(), "max-size=100m".to_string(),
             "--log-opt".to_string(), "max-file=3".to_string(),
-            "--rm".to_string(), "-d".to_string(), "--init".to_string()
+            // 不使用--rm, 容器退出后保留下来查询退出码，下次启动或者stop时再删除
+            "-d".to_string(), "--init".to_string()
         ];
 
         // 容器启动的host配置
@@ -457,13 +482,20 @@ impl DockerApi {
 
     pub fn stop(&self, id: &str) -> BuckyResult<()> {
         let container_name = format!("decapp-{}", id.to_lowercase());
-        stop_docker(&container_name)
+        stop_docker(&container_name)?;
+        let _ = run_docker(vec!["rm", "-f", &container_name])?.wait();
+        Ok(())
     }
 
     pub fn is_running(&self, id: &str) -> BuckyResult<bool> {
         let container_name = format!("decapp-{}", id.to_lowercase());
         is_docker_running(&container_name)
     }
+
+    pub fn exit_code(&self, id: &str) -> Option<i32> {
+        let container_name = format!("decapp-{}", id.to_lowercase());
+        get_docker_exit_code(&container_name)
+    }
 }
 
 #[cfg(test)]
@@ -17,11 +17,17 @@ use std::{str::FromStr, sync::Arc};
 mod app_acl_util;
 mod app_cmd_executor;
 mod app_controller;
+mod app_dep_resolver;
 mod app_install_detail;
 mod app_manager_ex;
+mod app_migration;
+mod app_supervisor;
 mod dapp;
 mod docker_api;
 mod docker_network_manager;
+mod native_sandbox;
+mod wasm_host_api;
+mod wasm_runtime;
 mod event_handler;
 mod non_helper;
 mod package;
@@ -92,7 +98,7 @@ async fn main_run() -> BuckyResult<()> {
 
     let app_config = AppManagerConfig::load();
 
-    info!("app manager use docker:{}", app_config.use_docker());
+    info!("app manager use docker:{}, use native sandbox:{}", app_config.use_docker(), app_config.use_native());
 
     let _ = set_process_cmd_funcs(Box::new(AppManagerProcessFuncs { config: app_config.clone() }));
 
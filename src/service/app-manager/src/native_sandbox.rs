@@ -0,0 +1,1507 @@
+use crate::dapp::INSTALL_CMD_TIME_OUT_IN_SECS;
+use crate::docker_api::RunConfig;
+use crate::process_util::exit_status_code;
+use app_manager_lib::NativeSandboxConfig;
+use cyfs_base::*;
+use cyfs_util::*;
+use itertools::Itertools;
+use log::*;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use wait_timeout::ChildExt;
+
+// 不依赖docker的沙箱：
+// 1. 每个app分配固定的slot, 以slot对应的uid/gid运行，不能操作cgroup和其它app的进程
+// 2. cgroup v2 限制 cpu/memory/pids，同时用cgroup跟踪app的所有进程
+// 3. user/pid/mount/net/ipc/uts namespace隔离，cyfs根目录只bind进app自己的目录，app目录在运行时只读
+// 4. seccomp禁止mount/module/reboot/io_uring等危险的系统调用，以及带namespace flag的clone
+// 5. 磁盘配额通过固定大小的loop镜像挂载到app的data目录
+// 6. 每个app一个network namespace, 通过veth连到host，和docker一样把127.0.0.1的协议栈端口DNAT出来
+// 7. 网络配额在app端的veth上用tc tbf限制出口带宽
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const CPU_PERIOD: i64 = 100000;
+const STOP_TIMEOUT_IN_SECS: u64 = 30;
+const KILL_TIMEOUT_IN_SECS: u64 = 5;
+const CGROUP_CONTROLLERS: [&str; 3] = ["cpu", "memory", "pids"];
+
+const NETNS_DIR: &str = "/var/run/netns";
+// app网络使用的网段，每个slot占一个/30, .1给host端, .2给app端
+const NET_SUBNET: &str = "10.254.0.0/16";
+const MAX_SLOTS: u32 = 16384;
+
+fn run_cmd<S: AsRef<OsStr>>(program: &str, args: Vec<S>) -> BuckyResult<String> {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let args_str = cmd.get_args().map(|s| s.to_string_lossy()).join(" ");
+    info!("will run cmd: {} {}", program, &args_str);
+
+    let output = cmd.output().map_err(|e| {
+        error!("run cmd: {} {} err {}", program, args_str, e);
+        e
+    })?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        let msg = format!(
+            "run cmd: {} {} failed, exit code {:?}, {}",
+            program,
+            args_str,
+            output.status.code(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        error!("{}", msg);
+        Err(BuckyError::new(BuckyErrorCode::Failed, msg))
+    }
+}
+
+fn write_cgroup_file(dir: &Path, name: &str, value: &str) -> BuckyResult<()> {
+    let path = dir.join(name);
+    std::fs::write(&path, value).map_err(|e| {
+        let msg = format!("write {} to {} err {}", value, path.display(), e);
+        error!("{}", msg);
+        BuckyError::new(BuckyErrorCode::IoError, msg)
+    })
+}
+
+fn read_cgroup_pids(dir: &Path) -> Vec<i32> {
+    match std::fs::read_to_string(dir.join("cgroup.procs")) {
+        Ok(content) => content
+            .lines()
+            .filter_map(|line| line.trim().parse::<i32>().ok())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+// 父cgroup打开controller之后，子cgroup才能设置对应的限制
+fn enable_controllers(dir: &Path) -> BuckyResult<()> {
+    let controllers = std::fs::read_to_string(dir.join("cgroup.controllers"))?;
+    let enabled = std::fs::read_to_string(dir.join("cgroup.subtree_control")).unwrap_or_default();
+    for name in CGROUP_CONTROLLERS {
+        if enabled.split_whitespace().any(|s| s == name) {
+            continue;
+        }
+        if !controllers.split_whitespace().any(|s| s == name) {
+            warn!(
+                "cgroup controller {} not available in {}",
+                name,
+                dir.display()
+            );
+            continue;
+        }
+        write_cgroup_file(dir, "cgroup.subtree_control", &format!("+{}", name))?;
+    }
+
+    Ok(())
+}
+
+// docker的cpu-shares默认1024, 按比例换算，对应cgroup v2的默认cpu.weight 100
+fn shares_to_weight(shares: i64) -> i64 {
+    (shares * 100 / 1024).max(1).min(10000)
+}
+
+fn slot_ip(slot: u32, host: u32) -> String {
+    let n = slot * 4 + host;
+    format!("10.254.{}.{}", n >> 8, n & 0xff)
+}
+
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+fn is_dir_empty(dir: &Path) -> bool {
+    std::fs::read_dir(dir)
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(true)
+}
+
+// 从/proc/mounts里查找挂载在dir上的设备
+fn find_mount_source(dir: &Path) -> Option<String> {
+    let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+    let target = dir.to_string_lossy();
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let source = fields.next()?;
+            let mount_point = fields.next()?;
+            Some((source, mount_point))
+        })
+        .filter(|(_, mount_point)| *mount_point == target)
+        .last()
+        .map(|(source, _)| source.to_owned())
+}
+
+// 每个app分配一个固定的slot, app的uid/gid和网络地址都由slot得到
+// app的目录属于这个uid, 所以分配结果需要持久化，重启后不能变化
+struct NativeSlots {
+    path: PathBuf,
+    slots: HashMap<String, u32>,
+}
+
+impl NativeSlots {
+    fn load(path: PathBuf) -> Self {
+        let slots = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                error!("parse native sandbox slots {} err {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self { path, slots }
+    }
+
+    fn get(&self, id: &str) -> Option<u32> {
+        self.slots.get(id).cloned()
+    }
+
+    // 已经分配过的直接返回，否则分配最小的空闲slot
+    fn alloc(&mut self, id: &str) -> BuckyResult<u32> {
+        if let Some(slot) = self.get(id) {
+            return Ok(slot);
+        }
+
+        let used: HashSet<u32> = self.slots.values().cloned().collect();
+        let slot = (0..MAX_SLOTS)
+            .find(|slot| !used.contains(slot))
+            .ok_or_else(|| {
+                let msg = format!("native sandbox slots exhausted, app {}", id);
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::OutOfLimit, msg)
+            })?;
+
+        self.slots.insert(id.to_owned(), slot);
+        if let Err(e) = self.save() {
+            self.slots.remove(id);
+            return Err(e);
+        }
+        info!("native sandbox alloc slot {} for app {}", slot, id);
+
+        Ok(slot)
+    }
+
+    fn release(&mut self, id: &str) {
+        if self.slots.remove(id).is_some() {
+            let _ = self.save();
+        }
+    }
+
+    fn save(&self) -> BuckyResult<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string(&self.slots).unwrap();
+        std::fs::write(&self.path, content).map_err(|e| {
+            let msg = format!(
+                "save native sandbox slots to {} err {}",
+                self.path.display(),
+                e
+            );
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })
+    }
+}
+
+// 传给sys::set_isolation的参数
+struct Isolation {
+    procs: PathBuf,
+    netns: Option<PathBuf>,
+    uid: u32,
+    gid: u32,
+    // cyfs根目录，在app的mount namespace里换成只包含binds的tmpfs
+    root: PathBuf,
+    // 用来准备tmpfs的空目录
+    staging: PathBuf,
+    // (源目录, 相对root的目标目录, 是否只读)
+    binds: Vec<(PathBuf, PathBuf, bool)>,
+    work_dir: PathBuf,
+    seccomp: bool,
+}
+
+pub struct NativeSandbox {
+    config: NativeSandboxConfig,
+    // 本进程启动的app进程，需要wait来回收
+    children: Mutex<HashMap<String, Child>>,
+    // 回收之后记录app的退出码
+    exit_codes: Mutex<HashMap<String, i32>>,
+    slots: Mutex<NativeSlots>,
+}
+
+impl NativeSandbox {
+    pub fn new(config: NativeSandboxConfig) -> Self {
+        let slots = NativeSlots::load(Self::get_slots_path());
+        Self {
+            config,
+            children: Mutex::new(HashMap::new()),
+            exit_codes: Mutex::new(HashMap::new()),
+            slots: Mutex::new(slots),
+        }
+    }
+
+    fn get_slots_path() -> PathBuf {
+        get_cyfs_root_path()
+            .join("data")
+            .join("app-manager")
+            .join("native_sandbox_slots.json")
+    }
+
+    fn get_app_tmp_dir(id: &str) -> PathBuf {
+        get_temp_path().join("app").join(id)
+    }
+
+    fn get_staging_dir(slot: u32) -> PathBuf {
+        get_temp_path()
+            .join("native-sandbox")
+            .join(slot.to_string())
+    }
+
+    fn slot_ids(&self, slot: u32) -> (u32, u32) {
+        let id = self.config.uid_base + slot;
+        (id, id)
+    }
+
+    // 检查cgroup v2是否可用，并在app的cgroup根目录上打开需要的controller
+    pub fn init(config: &NativeSandboxConfig) -> BuckyResult<()> {
+        if !cfg!(target_os = "linux") {
+            let msg = "native sandbox only support linux".to_owned();
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotSupport, msg));
+        }
+
+        let root = PathBuf::from(CGROUP_ROOT);
+        if !root.join("cgroup.controllers").is_file() {
+            let msg = format!("cgroup v2 not mounted at {}", CGROUP_ROOT);
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotSupport, msg));
+        }
+
+        let mut dir = root;
+        for name in Path::new(&config.cgroup).iter() {
+            enable_controllers(&dir)?;
+            dir = dir.join(name);
+            if !dir.exists() {
+                std::fs::create_dir(&dir)?;
+            }
+        }
+        enable_controllers(&dir)?;
+        info!("native sandbox cgroup root {} ready", dir.display());
+
+        Ok(())
+    }
+
+    fn cgroup_rel_path(&self, name: &str) -> String {
+        format!("{}/{}", self.config.cgroup.trim_matches('/'), name)
+    }
+
+    fn cgroup_dir(&self, name: &str) -> PathBuf {
+        PathBuf::from(CGROUP_ROOT).join(self.cgroup_rel_path(name))
+    }
+
+    fn app_cgroup_name(id: &str) -> String {
+        format!("decapp-{}", id.to_lowercase())
+    }
+
+    fn install_cgroup_name(id: &str) -> String {
+        format!("decapp-{}-install", id.to_lowercase())
+    }
+
+    fn get_disk_image_path(id: &str) -> PathBuf {
+        get_cyfs_root_path()
+            .join("data")
+            .join("app-image")
+            .join(format!("{}.img", id))
+    }
+
+    fn create_cgroup(&self, name: &str) -> BuckyResult<PathBuf> {
+        let dir = self.cgroup_dir(name);
+        if !dir.exists() {
+            std::fs::create_dir_all(&dir).map_err(|e| {
+                error!("create cgroup {} err {}", dir.display(), e);
+                e
+            })?;
+        }
+        if let Some(pids_max) = self.config.pids_max {
+            write_cgroup_file(&dir, "pids.max", &pids_max.to_string())?;
+        }
+        Ok(dir)
+    }
+
+    fn apply_limits(&self, dir: &Path, config: &RunConfig) -> BuckyResult<()> {
+        // 内存限制
+        let memory = config
+            .memory
+            .map(|m| (m * 1048576).to_string())
+            .unwrap_or("max".to_owned());
+        write_cgroup_file(dir, "memory.max", &memory)?;
+
+        // cpu绝对限制
+        let cpu_quota = config
+            .cpu_core
+            .map(|c| ((c * CPU_PERIOD as f64).round() as i64).to_string())
+            .unwrap_or("max".to_owned());
+        write_cgroup_file(dir, "cpu.max", &format!("{} {}", cpu_quota, CPU_PERIOD))?;
+
+        // cpu相对限制
+        if let Some(cpu_shares) = config.cpu_shares {
+            write_cgroup_file(dir, "cpu.weight", &shares_to_weight(cpu_shares).to_string())?;
+        }
+
+        Ok(())
+    }
+
+    // 给app的data目录挂载一个固定大小的ext4镜像，镜像文件是稀疏的，不会预先占满磁盘
+    fn prepare_disk_quota(&self, id: &str, quota: i64) -> BuckyResult<()> {
+        let data_dir = get_app_data_dir(id);
+        let image = Self::get_disk_image_path(id);
+        let size = quota as u64 * 1048576;
+
+        if let Some(source) = find_mount_source(&data_dir) {
+            let cur_size = std::fs::metadata(&image)?.len();
+            if cur_size < size {
+                info!("app {} disk quota grow from {} to {}", id, cur_size, size);
+                OpenOptions::new().write(true).open(&image)?.set_len(size)?;
+                run_cmd("losetup", vec!["-c", &source])?;
+                run_cmd("resize2fs", vec![source.as_str()])?;
+            } else if cur_size > size {
+                warn!(
+                    "app {} disk quota {} less than current image size {}, shrink not support",
+                    id, size, cur_size
+                );
+            }
+            return Ok(());
+        }
+
+        if !image.exists() {
+            std::fs::create_dir_all(image.parent().unwrap())?;
+            File::create(&image)?.set_len(size)?;
+            let image_str = image.to_string_lossy().to_string();
+            if let Err(e) = run_cmd("mkfs.ext4", vec!["-q", "-F", &image_str]) {
+                let _ = std::fs::remove_file(&image);
+                return Err(e);
+            }
+
+            // 已有的app数据搬到镜像里
+            if !is_dir_empty(&data_dir) {
+                self.move_data_to_image(id, &data_dir, &image)?;
+            }
+        } else {
+            let cur_size = std::fs::metadata(&image)?.len();
+            if cur_size < size {
+                info!("app {} disk quota grow from {} to {}", id, cur_size, size);
+                OpenOptions::new().write(true).open(&image)?.set_len(size)?;
+                let image_str = image.to_string_lossy().to_string();
+                run_cmd("e2fsck", vec!["-f", "-p", &image_str])?;
+                run_cmd("resize2fs", vec![image_str.as_str()])?;
+            }
+        }
+
+        run_cmd(
+            "mount",
+            vec![
+                OsStr::new("-o"),
+                OsStr::new("loop"),
+                image.as_os_str(),
+                data_dir.as_os_str(),
+            ],
+        )?;
+        info!(
+            "mount app {} disk image {} to {}",
+            id,
+            image.display(),
+            data_dir.display()
+        );
+
+        Ok(())
+    }
+
+    fn move_data_to_image(&self, id: &str, data_dir: &Path, image: &Path) -> BuckyResult<()> {
+        let tmp_dir = get_temp_path().join(format!("app-image-{}", id));
+        std::fs::create_dir_all(&tmp_dir)?;
+        run_cmd(
+            "mount",
+            vec![
+                OsStr::new("-o"),
+                OsStr::new("loop"),
+                image.as_os_str(),
+                tmp_dir.as_os_str(),
+            ],
+        )?;
+
+        let ret = run_cmd(
+            "cp",
+            vec![
+                OsStr::new("-a"),
+                data_dir.join(".").as_os_str(),
+                tmp_dir.as_os_str(),
+            ],
+        );
+        let _ = run_cmd("umount", vec![tmp_dir.as_os_str()]);
+        let _ = std::fs::remove_dir(&tmp_dir);
+        if let Err(e) = ret {
+            error!("copy app {} data to disk image err {}", id, e);
+            let _ = std::fs::remove_file(image);
+            return Err(e);
+        }
+
+        // 拷贝成功后再清理原目录，避免挂载后被覆盖的旧数据继续占用空间
+        for entry in std::fs::read_dir(data_dir)? {
+            let path = entry?.path();
+            let _ = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+        }
+        info!("move app {} data to disk image {}", id, image.display());
+
+        Ok(())
+    }
+
+    fn release_disk_quota(&self, id: &str) {
+        let data_dir = get_app_data_dir(id);
+        if find_mount_source(&data_dir).is_some() {
+            let _ = run_cmd("umount", vec![data_dir.as_os_str()]);
+        }
+    }
+
+    fn netns_name(slot: u32) -> String {
+        format!("cyfs-app-{}", slot)
+    }
+
+    // (host端, app端)
+    fn veth_names(slot: u32) -> (String, String) {
+        (format!("cyfsh{}", slot), format!("cyfsa{}", slot))
+    }
+
+    // host上给app的veth加的规则，按从上到下的顺序
+    fn host_net_rules(slot: u32) -> Vec<(&'static str, &'static str, Vec<String>)> {
+        let (host_dev, _) = Self::veth_names(slot);
+        let host_ip = slot_ip(slot, 1);
+        let app_ip = slot_ip(slot, 2);
+        let mut rules = vec![];
+
+        // app访问host端地址上的协议栈端口，转到本机的127.0.0.1
+        for port in [NON_STACK_HTTP_PORT, NON_STACK_WS_PORT] {
+            rules.push((
+                "nat",
+                "PREROUTING",
+                to_args(&[
+                    "-i",
+                    &host_dev,
+                    "-d",
+                    &host_ip,
+                    "-p",
+                    "tcp",
+                    "--dport",
+                    &port.to_string(),
+                    "-j",
+                    "DNAT",
+                    "--to-destination",
+                    &format!("127.0.0.1:{}", port),
+                ]),
+            ));
+        }
+        rules.push((
+            "nat",
+            "POSTROUTING",
+            to_args(&["-s", &app_ip, "!", "-d", NET_SUBNET, "-j", "MASQUERADE"]),
+        ));
+
+        // 本机只允许访问协议栈端口
+        let ports = format!("{},{}", NON_STACK_HTTP_PORT, NON_STACK_WS_PORT);
+        rules.push((
+            "filter",
+            "INPUT",
+            to_args(&[
+                "-i",
+                &host_dev,
+                "-d",
+                "127.0.0.1",
+                "-p",
+                "tcp",
+                "-m",
+                "multiport",
+                "--dports",
+                &ports,
+                "-j",
+                "ACCEPT",
+            ]),
+        ));
+        rules.push(("filter", "INPUT", to_args(&["-i", &host_dev, "-j", "DROP"])));
+
+        // 可以访问外网，但不能访问其它app
+        rules.push((
+            "filter",
+            "FORWARD",
+            to_args(&["-i", &host_dev, "-d", NET_SUBNET, "-j", "DROP"]),
+        ));
+        rules.push((
+            "filter",
+            "FORWARD",
+            to_args(&["-i", &host_dev, "-j", "ACCEPT"]),
+        ));
+        rules.push((
+            "filter",
+            "FORWARD",
+            to_args(&[
+                "-o",
+                &host_dev,
+                "-m",
+                "conntrack",
+                "--ctstate",
+                "RELATED,ESTABLISHED",
+                "-j",
+                "ACCEPT",
+            ]),
+        ));
+
+        rules
+    }
+
+    fn setup_net(&self, slot: u32, rate: Option<u32>) -> BuckyResult<()> {
+        self.release_net(slot);
+        let ret = self.do_setup_net(slot, rate);
+        if ret.is_err() {
+            self.release_net(slot);
+        }
+        ret
+    }
+
+    fn do_setup_net(&self, slot: u32, rate: Option<u32>) -> BuckyResult<()> {
+        let ns = Self::netns_name(slot);
+        let (host_dev, app_dev) = Self::veth_names(slot);
+        let host_ip = slot_ip(slot, 1);
+        let app_ip = slot_ip(slot, 2);
+        let ns_cmd = |args: &[&str]| {
+            let mut full = vec!["netns", "exec", ns.as_str()];
+            full.extend_from_slice(args);
+            run_cmd("ip", full)
+        };
+
+        run_cmd("ip", vec!["netns", "add", &ns])?;
+        run_cmd(
+            "ip",
+            vec![
+                "link", "add", &host_dev, "type", "veth", "peer", "name", &app_dev,
+            ],
+        )?;
+        run_cmd("ip", vec!["link", "set", &app_dev, "netns", &ns])?;
+        run_cmd(
+            "ip",
+            vec!["addr", "add", &format!("{}/30", host_ip), "dev", &host_dev],
+        )?;
+        run_cmd("ip", vec!["link", "set", &host_dev, "up"])?;
+        run_cmd("sysctl", vec!["-w", "net.ipv4.ip_forward=1"])?;
+        run_cmd(
+            "sysctl",
+            vec![
+                "-w",
+                &format!("net.ipv4.conf.{}.route_localnet=1", host_dev),
+            ],
+        )?;
+
+        ns_cmd(&["ip", "link", "set", "lo", "up"])?;
+        ns_cmd(&[
+            "ip",
+            "addr",
+            "add",
+            &format!("{}/30", app_ip),
+            "dev",
+            &app_dev,
+        ])?;
+        ns_cmd(&["ip", "link", "set", &app_dev, "up"])?;
+        ns_cmd(&["ip", "route", "add", "default", "via", &host_ip])?;
+        ns_cmd(&[
+            "sysctl",
+            "-w",
+            &format!("net.ipv4.conf.{}.route_localnet=1", app_dev),
+        ])?;
+
+        // 和docker的启动脚本一样，app访问127.0.0.1的协议栈端口时转到host端
+        for port in [NON_STACK_HTTP_PORT, NON_STACK_WS_PORT] {
+            ns_cmd(&[
+                "iptables",
+                "-t",
+                "nat",
+                "-A",
+                "OUTPUT",
+                "-d",
+                "127.0.0.1/32",
+                "-p",
+                "tcp",
+                "--dport",
+                &port.to_string(),
+                "-j",
+                "DNAT",
+                "--to-destination",
+                &format!("{}:{}", host_ip, port),
+            ])?;
+        }
+        ns_cmd(&[
+            "iptables",
+            "-t",
+            "nat",
+            "-A",
+            "POSTROUTING",
+            "-s",
+            "127.0.0.1",
+            "-p",
+            "tcp",
+            "-j",
+            "SNAT",
+            "--to-source",
+            &app_ip,
+        ])?;
+
+        // 倒序插入到链的最前面，保持规则之间的顺序
+        for (table, chain, rule) in Self::host_net_rules(slot).into_iter().rev() {
+            let check = [to_args(&["-t", table, "-C", chain]), rule.clone()].concat();
+            if run_cmd("iptables", check).is_err() {
+                run_cmd(
+                    "iptables",
+                    [to_args(&["-t", table, "-I", chain]), rule].concat(),
+                )?;
+            }
+        }
+
+        // 出口限速放在app端的veth上，app自己没有权限修改
+        if let Some(rate) = rate {
+            ns_cmd(&[
+                "tc",
+                "qdisc",
+                "replace",
+                "dev",
+                &app_dev,
+                "root",
+                "tbf",
+                "rate",
+                &format!("{}kbit", rate),
+                "burst",
+                "32kbit",
+                "latency",
+                "400ms",
+            ])?;
+        }
+        info!(
+            "native sandbox net ready, slot {}, host {}, app {}, rate {:?}",
+            slot, host_ip, app_ip, rate
+        );
+
+        Ok(())
+    }
+
+    // netns最先创建最后删除，不存在就说明没有需要清理的
+    fn release_net(&self, slot: u32) {
+        let ns = Self::netns_name(slot);
+        if !Path::new(NETNS_DIR).join(&ns).exists() {
+            return;
+        }
+
+        for (table, chain, rule) in Self::host_net_rules(slot) {
+            while run_cmd(
+                "iptables",
+                [to_args(&["-t", table, "-D", chain]), rule.clone()].concat(),
+            )
+            .is_ok()
+            {}
+        }
+        let (host_dev, _) = Self::veth_names(slot);
+        let _ = run_cmd("ip", vec!["link", "del", &host_dev]);
+        let _ = run_cmd("ip", vec!["netns", "del", &ns]);
+    }
+
+    // app在自己的uid下运行，需要写的目录都交给这个uid, install时app目录也要可写
+    fn prepare_app_dirs(&self, id: &str, slot: u32, install: bool) -> BuckyResult<()> {
+        let (uid, gid) = self.slot_ids(slot);
+        let owner = format!("{}:{}", uid, gid);
+        let mut dirs = vec![
+            get_app_data_dir(id),
+            get_app_log_dir(id),
+            Self::get_app_tmp_dir(id),
+        ];
+        if install {
+            dirs.push(get_app_dir(id));
+        }
+
+        for dir in dirs {
+            std::fs::create_dir_all(&dir)?;
+            if sys::owner(&dir) != Some((uid, gid)) {
+                run_cmd(
+                    "chown",
+                    vec![OsStr::new("-R"), OsStr::new(&owner), dir.as_os_str()],
+                )?;
+            }
+        }
+        std::fs::create_dir_all(Self::get_staging_dir(slot))?;
+
+        Ok(())
+    }
+
+    fn spawn(
+        &self,
+        id: &str,
+        slot: u32,
+        cgroup: &Path,
+        cmd: &str,
+        read_only: bool,
+        log_file: &Path,
+    ) -> BuckyResult<Child> {
+        let work_dir = get_app_dir(id);
+        let args: Vec<&str> = ProcessUtil::parse_cmd(cmd);
+        if args.len() == 0 {
+            error!("parse cmd {} failed, cmd empty?", cmd);
+            return Err(BuckyError::from(BuckyErrorCode::InvalidData));
+        }
+        // app目录里的程序优先，不能被host PATH里的同名程序替换掉
+        let program = work_dir.join(args[0]);
+        let program = if program.is_file() {
+            program
+        } else {
+            which::which(args[0]).unwrap_or(program)
+        };
+        info!(
+            "native sandbox run app {} cmd {}, program {}",
+            id,
+            cmd,
+            program.display()
+        );
+
+        let out = sys::open_log(log_file)?;
+        let err = out.try_clone()?;
+        let mut command = Command::new(program);
+        command
+            .args(&args[1..])
+            .current_dir(&work_dir)
+            .stdin(Stdio::null())
+            .stdout(out)
+            .stderr(err);
+
+        // 和docker一样只给app自己的app/data/log/tmp目录
+        let (uid, gid) = self.slot_ids(slot);
+        let isolation = Isolation {
+            procs: cgroup.join("cgroup.procs"),
+            netns: Some(Path::new(NETNS_DIR).join(Self::netns_name(slot))),
+            uid,
+            gid,
+            root: get_cyfs_root_path(),
+            staging: Self::get_staging_dir(slot),
+            binds: vec![
+                (work_dir.clone(), Path::new("app").join(id), read_only),
+                (
+                    get_app_data_dir(id),
+                    Path::new("data").join("app").join(id),
+                    false,
+                ),
+                (get_app_log_dir(id), Path::new("log").join("app"), false),
+                (Self::get_app_tmp_dir(id), PathBuf::from("tmp"), false),
+            ],
+            work_dir: work_dir.clone(),
+            seccomp: self.config.seccomp,
+        };
+        sys::set_isolation(&mut command, &isolation)?;
+
+        command.spawn().map_err(|e| {
+            let msg = format!(
+                "spawn app {} in native sandbox failed! cmd {}, err {}",
+                id, cmd, e
+            );
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::ExecuteError, msg)
+        })
+    }
+
+    // 先SIGTERM, 超时后再强制杀掉cgroup里的所有进程
+    fn kill_cgroup(&self, dir: &Path) -> BuckyResult<()> {
+        if !dir.exists() {
+            return Ok(());
+        }
+
+        for pid in read_cgroup_pids(dir) {
+            let _ = sys::kill(pid, false);
+        }
+        if self.wait_cgroup_empty(dir, STOP_TIMEOUT_IN_SECS) {
+            return Ok(());
+        }
+
+        warn!(
+            "cgroup {} not exit after {} secs, kill",
+            dir.display(),
+            STOP_TIMEOUT_IN_SECS
+        );
+        if dir.join("cgroup.kill").exists() {
+            write_cgroup_file(dir, "cgroup.kill", "1")?;
+        } else {
+            for pid in read_cgroup_pids(dir) {
+                let _ = sys::kill(pid, true);
+            }
+        }
+        if self.wait_cgroup_empty(dir, KILL_TIMEOUT_IN_SECS) {
+            Ok(())
+        } else {
+            let msg = format!("kill cgroup {} processes timeout", dir.display());
+            error!("{}", msg);
+            Err(BuckyError::new(BuckyErrorCode::Timeout, msg))
+        }
+    }
+
+    fn wait_cgroup_empty(&self, dir: &Path, secs: u64) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(secs) {
+            self.reap_children();
+            if read_cgroup_pids(dir).is_empty() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(500));
+        }
+        false
+    }
+
+    fn reap_children(&self) {
+        let mut exit_codes = self.exit_codes.lock().unwrap();
+        self.children
+            .lock()
+            .unwrap()
+            .retain(|id, child| match child.try_wait() {
+                Ok(Some(status)) => {
+                    info!("native sandbox app {} exited, status {}", id, status);
+                    exit_codes.insert(id.clone(), exit_status_code(&status));
+                    false
+                }
+                _ => true,
+            });
+    }
+
+    // app自己退出后的退出码，被stop或者不是本进程启动的返回None
+    pub fn exit_code(&self, id: &str) -> Option<i32> {
+        self.reap_children();
+        self.exit_codes.lock().unwrap().get(id).cloned()
+    }
+
+    fn remove_cgroup(&self, dir: &Path) {
+        if dir.exists() {
+            if let Err(e) = std::fs::remove_dir(dir) {
+                warn!("remove cgroup {} err {}", dir.display(), e);
+            }
+        }
+    }
+
+    pub fn stop_install(&self, id: &str) -> BuckyResult<()> {
+        let dir = self.cgroup_dir(&Self::install_cgroup_name(id));
+        self.kill_cgroup(&dir)?;
+        self.remove_cgroup(&dir);
+        Ok(())
+    }
+
+    pub async fn install(
+        &self,
+        id: &str,
+        _version: &str,
+        install_cmd: Vec<String>,
+    ) -> BuckyResult<()> {
+        self.stop_install(id)?;
+        let slot = self.slots.lock().unwrap().alloc(id)?;
+        self.prepare_app_dirs(id, slot, true)?;
+        self.setup_net(slot, None)?;
+        let ret = self.run_install_cmds(id, slot, &install_cmd);
+        self.release_net(slot);
+        ret
+    }
+
+    fn run_install_cmds(&self, id: &str, slot: u32, install_cmd: &[String]) -> BuckyResult<()> {
+        let work_dir = get_app_dir(id);
+        for (index, cmd) in install_cmd.iter().enumerate() {
+            info!("start app {} install cmd {} in native sandbox", id, cmd);
+            let cgroup = self.create_cgroup(&Self::install_cgroup_name(id))?;
+            // app install的时候service目录为可写
+            let log_file = work_dir.join(format!("install_{}.log", index));
+            let mut child = self.spawn(id, slot, &cgroup, cmd, false, &log_file)?;
+
+            let ret = child.wait_timeout(Duration::from_secs(INSTALL_CMD_TIME_OUT_IN_SECS))?;
+            let _ = self.kill_cgroup(&cgroup);
+            self.remove_cgroup(&cgroup);
+            match ret {
+                None => {
+                    let msg = format!(
+                        "app {} run install cmd {} not return after {} secs, kill",
+                        id, cmd, INSTALL_CMD_TIME_OUT_IN_SECS
+                    );
+                    error!("{}", &msg);
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(BuckyError::new(BuckyErrorCode::Timeout, msg));
+                }
+                Some(status) => {
+                    if status.success() {
+                        info!("app {} run install cmd {} success", id, cmd);
+                    } else {
+                        let msg = format!(
+                            "app {} run install cmd {}, exit code {:?}",
+                            id,
+                            cmd,
+                            status.code()
+                        );
+                        error!("{}", &msg);
+                        return Err(BuckyError::new(BuckyErrorCode::Failed, msg));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 卸载时只卸下数据镜像，和docker的volume一样保留用户数据
+    pub async fn uninstall(&self, id: &str) -> BuckyResult<()> {
+        let _ = self.stop(id);
+        self.release_disk_quota(id);
+        self.slots.lock().unwrap().release(id);
+        Ok(())
+    }
+
+    pub async fn start(&self, id: &str, config: RunConfig, command: String) -> BuckyResult<()> {
+        info!("native sandbox run dec app:{}, config {:?}", id, config);
+        if self.is_running(id)? {
+            info!("native sandbox app is alreay running {}", id);
+            return Ok(());
+        }
+
+        let slot = self.slots.lock().unwrap().alloc(id)?;
+        let cgroup = self.create_cgroup(&Self::app_cgroup_name(id))?;
+        self.apply_limits(&cgroup, &config)?;
+
+        if let Some(quota) = config.disk_quota {
+            self.prepare_disk_quota(id, quota)?;
+        }
+        self.prepare_app_dirs(id, slot, false)?;
+        self.setup_net(slot, config.net_rate)?;
+
+        // 运行时service目录为只读
+        let log_file = get_app_log_dir(id).join("native_sandbox.log");
+        let child = match self.spawn(id, slot, &cgroup, &command, true, &log_file) {
+            Ok(child) => child,
+            Err(e) => {
+                self.release_net(slot);
+                return Err(e);
+            }
+        };
+        info!(
+            "native sandbox start app {} success, pid {}",
+            id,
+            child.id()
+        );
+        self.exit_codes.lock().unwrap().remove(id);
+        self.children.lock().unwrap().insert(id.to_owned(), child);
+
+        Ok(())
+    }
+
+    pub fn stop(&self, id: &str) -> BuckyResult<()> {
+        info!("try to stop native sandbox app {}", id);
+        let dir = self.cgroup_dir(&Self::app_cgroup_name(id));
+        self.kill_cgroup(&dir)?;
+        self.children.lock().unwrap().remove(id);
+        self.exit_codes.lock().unwrap().remove(id);
+        let slot = self.slots.lock().unwrap().get(id);
+        if let Some(slot) = slot {
+            self.release_net(slot);
+        }
+        self.remove_cgroup(&dir);
+        Ok(())
+    }
+
+    pub fn is_running(&self, id: &str) -> BuckyResult<bool> {
+        self.reap_children();
+        let dir = self.cgroup_dir(&Self::app_cgroup_name(id));
+        Ok(!read_cgroup_pids(&dir).is_empty())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use super::Isolation;
+    use cyfs_base::*;
+    use log::*;
+    use std::ffi::CString;
+    use std::fs::{File, OpenOptions};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+    use std::os::unix::process::CommandExt;
+    use std::path::Path;
+    use std::process::Command;
+
+    const BPF_LD_W_ABS: u16 = 0x20;
+    const BPF_JMP_JEQ_K: u16 = 0x15;
+    const BPF_JMP_JGE_K: u16 = 0x35;
+    const BPF_JMP_JSET_K: u16 = 0x45;
+    const BPF_RET_K: u16 = 0x06;
+    const SECCOMP_RET_KILL_PROCESS: u32 = 0x80000000;
+    const SECCOMP_RET_ERRNO: u32 = 0x00050000;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff0000;
+    // struct seccomp_data { int nr; __u32 arch; ... }
+    const SECCOMP_DATA_NR: u32 = 0;
+    const SECCOMP_DATA_ARCH: u32 = 4;
+    // args[0]的低32位, x86_64和aarch64都是小端
+    const SECCOMP_DATA_ARG0_LO: u32 = 16;
+
+    // 和docker默认的seccomp profile一样，clone不允许带任何创建namespace的flag
+    // CLONE_NEWNS | CLONE_NEWCGROUP | CLONE_NEWUTS | CLONE_NEWIPC | CLONE_NEWUSER | CLONE_NEWPID | CLONE_NEWNET
+    const CLONE_NAMESPACE_FLAGS: u32 = 0x7e020000;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xc000003e);
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: Option<u32> = Some(0xc00000b7);
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    const AUDIT_ARCH: Option<u32> = None;
+
+    // app不应该调用的系统调用，返回EPERM
+    const DENY_SYSCALLS: [libc::c_long; 35] = [
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_fsopen,
+        libc::SYS_fsconfig,
+        libc::SYS_fsmount,
+        libc::SYS_fspick,
+        libc::SYS_move_mount,
+        libc::SYS_open_tree,
+        libc::SYS_mount_setattr,
+        libc::SYS_pivot_root,
+        libc::SYS_reboot,
+        libc::SYS_kexec_load,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_ptrace,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_acct,
+        libc::SYS_settimeofday,
+        libc::SYS_clock_settime,
+        libc::SYS_sethostname,
+        libc::SYS_setdomainname,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_userfaultfd,
+        libc::SYS_io_uring_setup,
+        libc::SYS_io_uring_enter,
+        libc::SYS_io_uring_register,
+    ];
+
+    fn stmt(code: u16, k: u32) -> libc::sock_filter {
+        libc::sock_filter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter { code, jt, jf, k }
+    }
+
+    fn seccomp_filter() -> Option<Vec<libc::sock_filter>> {
+        let arch = AUDIT_ARCH?;
+        let deny = SECCOMP_RET_ERRNO | (libc::EPERM as u32 & 0xffff);
+
+        let mut filter = vec![
+            // 其它架构的调用号不一样，直接杀掉
+            stmt(BPF_LD_W_ABS, SECCOMP_DATA_ARCH),
+            jump(BPF_JMP_JEQ_K, arch, 1, 0),
+            stmt(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+            stmt(BPF_LD_W_ABS, SECCOMP_DATA_NR),
+        ];
+        // x32 abi的调用号带0x40000000标记，会绕过下面的调用号判断
+        if cfg!(target_arch = "x86_64") {
+            filter.push(jump(BPF_JMP_JGE_K, 0x40000000, 0, 1));
+            filter.push(stmt(BPF_RET_K, deny));
+        }
+
+        // clone3的flags在用户态结构体里，bpf检查不了，和docker一样返回ENOSYS让libc回退到clone
+        filter.push(jump(BPF_JMP_JEQ_K, libc::SYS_clone3 as u32, 0, 1));
+        filter.push(stmt(
+            BPF_RET_K,
+            SECCOMP_RET_ERRNO | (libc::ENOSYS as u32 & 0xffff),
+        ));
+
+        // clone带namespace flag的拒绝，其它放行
+        filter.push(jump(BPF_JMP_JEQ_K, libc::SYS_clone as u32, 0, 4));
+        filter.push(stmt(BPF_LD_W_ABS, SECCOMP_DATA_ARG0_LO));
+        filter.push(jump(BPF_JMP_JSET_K, CLONE_NAMESPACE_FLAGS, 0, 1));
+        filter.push(stmt(BPF_RET_K, deny));
+        filter.push(stmt(BPF_RET_K, SECCOMP_RET_ALLOW));
+
+        for nr in DENY_SYSCALLS {
+            filter.push(jump(BPF_JMP_JEQ_K, nr as u32, 0, 1));
+            filter.push(stmt(BPF_RET_K, deny));
+        }
+        filter.push(stmt(BPF_RET_K, SECCOMP_RET_ALLOW));
+
+        Some(filter)
+    }
+
+    fn check(ret: libc::c_int) -> std::io::Result<()> {
+        if ret < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn to_cstring(path: &Path) -> BuckyResult<CString> {
+        CString::new(path.as_os_str().as_bytes()).map_err(|e| {
+            let msg = format!("invalid path {}, {}", path.display(), e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidParam, msg)
+        })
+    }
+
+    fn write_file(path: &CString, content: &[u8]) -> std::io::Result<()> {
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            check(fd)?;
+            let ret = libc::write(fd, content.as_ptr() as *const libc::c_void, content.len());
+            libc::close(fd);
+            if ret < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    fn bind(src: &CString, dst: &CString, read_only: bool) -> std::io::Result<()> {
+        unsafe {
+            check(libc::mount(
+                src.as_ptr(),
+                dst.as_ptr(),
+                std::ptr::null(),
+                libc::MS_BIND | libc::MS_REC,
+                std::ptr::null(),
+            ))?;
+            if read_only {
+                check(libc::mount(
+                    std::ptr::null(),
+                    dst.as_ptr(),
+                    std::ptr::null(),
+                    libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY,
+                    std::ptr::null(),
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn owner(path: &Path) -> Option<(u32, u32)> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some((meta.uid(), meta.gid()))
+    }
+
+    // 日志所在的目录属于app, 不能跟随app留下的符号链接
+    pub(super) fn open_log(path: &Path) -> BuckyResult<File> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(path)
+            .map_err(|e| {
+                let msg = format!("open log file {} err {}", path.display(), e);
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::IoError, msg)
+            })?;
+        Ok(file)
+    }
+
+    // 在fork之后、exec之前进入cgroup和namespace, 切换到app的uid, 最后加载seccomp
+    // pre_exec里只能做async-signal-safe的操作，所有参数都需要提前准备好
+    pub(super) fn set_isolation(command: &mut Command, isolation: &Isolation) -> BuckyResult<()> {
+        let procs = to_cstring(&isolation.procs)?;
+        let netns = match &isolation.netns {
+            Some(path) => Some(to_cstring(path)?),
+            None => None,
+        };
+        let cyfs_root = to_cstring(&isolation.root)?;
+        let staging = to_cstring(&isolation.staging)?;
+        let work_dir = to_cstring(&isolation.work_dir)?;
+        let cgroup_root = to_cstring(Path::new(super::CGROUP_ROOT))?;
+
+        // tmpfs里需要先建好bind的目标目录
+        let mut mkdirs = vec![];
+        let mut binds = vec![];
+        for (src, target, read_only) in &isolation.binds {
+            let mut dir = isolation.staging.clone();
+            for name in target.iter() {
+                dir.push(name);
+                let dir = to_cstring(&dir)?;
+                if !mkdirs.contains(&dir) {
+                    mkdirs.push(dir);
+                }
+            }
+            binds.push((
+                to_cstring(src)?,
+                to_cstring(&isolation.staging.join(target))?,
+                *read_only,
+            ));
+        }
+
+        let uid = isolation.uid;
+        let gid = isolation.gid;
+        let uid_map = format!("{} {} 1", uid, uid);
+        let gid_map = format!("{} {} 1", gid, gid);
+        let max_fd = unsafe { libc::sysconf(libc::_SC_OPEN_MAX) }
+            .max(1024)
+            .min(65536) as libc::c_int;
+
+        let filter = if isolation.seccomp {
+            let filter = seccomp_filter();
+            if filter.is_none() {
+                warn!("seccomp not support on this arch, skip");
+            }
+            filter
+        } else {
+            None
+        };
+        let root = CString::new("/").unwrap();
+        let proc_dir = CString::new("/proc").unwrap();
+        let proc_fs = CString::new("proc").unwrap();
+        let tmpfs = CString::new("tmpfs").unwrap();
+        let tmpfs_opts = CString::new("mode=755").unwrap();
+        let uid_map_path = CString::new("/proc/self/uid_map").unwrap();
+        let gid_map_path = CString::new("/proc/self/gid_map").unwrap();
+        let setgroups_path = CString::new("/proc/self/setgroups").unwrap();
+
+        unsafe {
+            command.pre_exec(move || {
+                // 加入cgroup, 写0表示当前进程
+                write_file(&procs, b"0")?;
+
+                // 脱离app-manager的会话，app-manager退出时不会被一起带走
+                libc::setsid();
+
+                // 进入为app准备好的network namespace
+                if let Some(netns) = &netns {
+                    let fd = libc::open(netns.as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC);
+                    check(fd)?;
+                    let ret = libc::setns(fd, libc::CLONE_NEWNET);
+                    libc::close(fd);
+                    check(ret)?;
+                }
+
+                check(libc::unshare(
+                    libc::CLONE_NEWNS
+                        | libc::CLONE_NEWIPC
+                        | libc::CLONE_NEWUTS
+                        | libc::CLONE_NEWPID,
+                ))?;
+
+                // 新的pid namespace只对子进程生效，再fork一次，由子进程作为namespace里的1号进程去exec
+                // 当前进程关掉继承的fd(包括std用来报告exec结果的管道)，等子进程退出后用同样的退出码退出
+                let pid = libc::fork();
+                check(pid)?;
+                if pid > 0 {
+                    for fd in 3..max_fd {
+                        libc::close(fd);
+                    }
+                    let mut status = 0;
+                    loop {
+                        let ret = libc::waitpid(pid, &mut status, 0);
+                        if ret == pid {
+                            break;
+                        }
+                        if ret < 0
+                            && std::io::Error::last_os_error().raw_os_error() != Some(libc::EINTR)
+                        {
+                            libc::_exit(1);
+                        }
+                    }
+                    let code = if libc::WIFEXITED(status) {
+                        libc::WEXITSTATUS(status)
+                    } else if libc::WIFSIGNALED(status) {
+                        128 + libc::WTERMSIG(status)
+                    } else {
+                        1
+                    };
+                    libc::_exit(code);
+                }
+
+                check(libc::mount(
+                    std::ptr::null(),
+                    root.as_ptr(),
+                    std::ptr::null(),
+                    libc::MS_REC | libc::MS_PRIVATE,
+                    std::ptr::null(),
+                ))?;
+                check(libc::mount(
+                    proc_fs.as_ptr(),
+                    proc_dir.as_ptr(),
+                    proc_fs.as_ptr(),
+                    libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+                    std::ptr::null(),
+                ))?;
+
+                // cyfs根目录换成tmpfs, 只bind进app自己的目录，etc下的密钥和其它app的数据都不可见
+                check(libc::mount(
+                    tmpfs.as_ptr(),
+                    staging.as_ptr(),
+                    tmpfs.as_ptr(),
+                    libc::MS_NOSUID | libc::MS_NODEV,
+                    tmpfs_opts.as_ptr() as *const libc::c_void,
+                ))?;
+                for dir in &mkdirs {
+                    check(libc::mkdir(dir.as_ptr(), 0o755))?;
+                }
+                for (src, dst, read_only) in &binds {
+                    bind(src, dst, *read_only)?;
+                }
+                check(libc::mount(
+                    staging.as_ptr(),
+                    cyfs_root.as_ptr(),
+                    std::ptr::null(),
+                    libc::MS_MOVE,
+                    std::ptr::null(),
+                ))?;
+
+                // cgroup文件系统只读，app不能把自己移到别的cgroup
+                bind(&cgroup_root, &cgroup_root, true)?;
+
+                // std在pre_exec之前已经chdir过，指向的是被覆盖掉的原目录，需要重新进入
+                check(libc::chdir(work_dir.as_ptr()))?;
+
+                // 切换到app自己的uid/gid, 之后不能再写cgroup, 也不能给其它uid的进程发信号
+                check(libc::setgroups(0, std::ptr::null()))?;
+                check(libc::setgid(gid))?;
+                check(libc::setuid(uid))?;
+
+                // 再进入新的user namespace, 只映射app自己的uid/gid
+                // 有的发行版禁止了非特权的user namespace, 这时app已经是普通用户，跳过即可
+                if libc::unshare(libc::CLONE_NEWUSER) == 0 {
+                    write_file(&uid_map_path, uid_map.as_bytes())?;
+                    write_file(&setgroups_path, b"deny")?;
+                    write_file(&gid_map_path, gid_map.as_bytes())?;
+                }
+
+                check(libc::prctl(
+                    libc::PR_SET_NO_NEW_PRIVS,
+                    1 as libc::c_ulong,
+                    0 as libc::c_ulong,
+                    0 as libc::c_ulong,
+                    0 as libc::c_ulong,
+                ))?;
+                if let Some(filter) = &filter {
+                    let prog = libc::sock_fprog {
+                        len: filter.len() as libc::c_ushort,
+                        filter: filter.as_ptr() as *mut libc::sock_filter,
+                    };
+                    check(libc::prctl(
+                        libc::PR_SET_SECCOMP,
+                        libc::SECCOMP_MODE_FILTER as libc::c_ulong,
+                        &prog as *const libc::sock_fprog,
+                    ))?;
+                }
+
+                Ok(())
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn kill(pid: i32, force: bool) -> BuckyResult<()> {
+        let sig = if force { libc::SIGKILL } else { libc::SIGTERM };
+        if unsafe { libc::kill(pid, sig) } < 0 {
+            let e = std::io::Error::last_os_error();
+            warn!("kill process {} with signal {} err {}", pid, sig, e);
+            return Err(BuckyError::from(e));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use super::Isolation;
+    use cyfs_base::*;
+    use std::fs::{File, OpenOptions};
+    use std::path::Path;
+    use std::process::Command;
+
+    pub(super) fn owner(_path: &Path) -> Option<(u32, u32)> {
+        None
+    }
+
+    pub(super) fn open_log(path: &Path) -> BuckyResult<File> {
+        Ok(OpenOptions::new().create(true).append(true).open(path)?)
+    }
+
+    pub(super) fn set_isolation(_command: &mut Command, _isolation: &Isolation) -> BuckyResult<()> {
+        Err(BuckyError::new(
+            BuckyErrorCode::NotSupport,
+            "native sandbox only support linux",
+        ))
+    }
+
+    pub(super) fn kill(_pid: i32, _force: bool) -> BuckyResult<()> {
+        Err(BuckyError::new(
+            BuckyErrorCode::NotSupport,
+            "native sandbox only support linux",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shares_to_weight() {
+        assert_eq!(shares_to_weight(1024), 100);
+        assert_eq!(shares_to_weight(512), 50);
+        assert_eq!(shares_to_weight(2), 1);
+        assert_eq!(shares_to_weight(262144), 10000);
+    }
+
+    #[test]
+    fn test_slot_ip() {
+        assert_eq!(slot_ip(0, 1), "10.254.0.1");
+        assert_eq!(slot_ip(0, 2), "10.254.0.2");
+        assert_eq!(slot_ip(64, 2), "10.254.1.2");
+        assert_eq!(slot_ip(MAX_SLOTS - 1, 2), "10.254.255.254");
+    }
+
+    #[test]
+    fn test_native_slots() {
+        let path = std::env::temp_dir()
+            .join(format!("native_sandbox_slots_{}", std::process::id()))
+            .join("slots.json");
+        let _ = std::fs::remove_file(&path);
+
+        let mut slots = NativeSlots::load(path.clone());
+        assert_eq!(slots.alloc("a").unwrap(), 0);
+        assert_eq!(slots.alloc("b").unwrap(), 1);
+        assert_eq!(slots.alloc("a").unwrap(), 0);
+
+        // 重新加载后分配结果不变，释放的slot可以复用
+        let mut slots = NativeSlots::load(path.clone());
+        assert_eq!(slots.get("b"), Some(1));
+        slots.release("a");
+        assert_eq!(slots.alloc("c").unwrap(), 0);
+        assert_eq!(NativeSlots::load(path.clone()).get("a"), None);
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
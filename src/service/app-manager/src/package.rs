@@ -23,6 +23,8 @@ use crate::dapp::DApp;
 
  */
 
+const WASM_SERVICE_INNER_PATH: &str = "service/wasm32.zip";
+
 pub struct AppPackage {
 }
 
@@ -133,9 +135,11 @@ impl AppPackage {
                     local_path: path.to_owned(),
                     chunk_size: 1024 * 1024 * 4,
                     file_id: None,
+                    base_file_id: None,
                     dirs: None,
                     access: None,
                     chunk_method: TransPublishChunkMethod::Track,
+                    chunk_cdc: None,
                 }).await?;
             info!("publish dir {}, object id {}", path.display(), &pub_resp.file_id);
             Some(pub_resp.file_id)
@@ -155,7 +159,13 @@ impl AppPackage {
         let target = system_config.target.clone();
         //拼app service的inner_path，当前为"service/{target}.zip"
         let service_inner_path = format!("service/{}.zip", &target);
-        Self::download_files(dir, owner, client, &service_inner_path, target_path).await.map(|size|size > 0)
+        if Self::download_files(dir, owner, client, &service_inner_path, target_path).await? > 0 {
+            return Ok(true);
+        }
+
+        // 没有当前平台的service时，尝试下载跨平台的wasm service
+        info!("app service for target {} not found, try {}", &target, WASM_SERVICE_INNER_PATH);
+        Self::download_files(dir, owner, client, WASM_SERVICE_INNER_PATH, target_path).await.map(|size|size > 0)
     }
 
     pub async fn download_web(dir: &ObjectId, owner: &ObjectId, client: &NamedCacheClient, target_path: &Path) -> BuckyResult<bool> {
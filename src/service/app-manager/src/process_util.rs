@@ -1,6 +1,6 @@
 use std::fs::File;
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command};
+use std::process::{Child, Command, ExitStatus};
 use std::str::FromStr;
 use sysinfo::{Pid, ProcessExt, ProcessRefreshKind, RefreshKind, SystemExt};
 use cyfs_base::{BuckyError, BuckyErrorCode, BuckyResult};
@@ -8,6 +8,23 @@ use log::*;
 use cyfs_core::DecAppId;
 use cyfs_util::ProcessUtil;
 
+// 进程的退出码，被信号杀掉的按shell的习惯记为128 + signal
+pub fn exit_status_code(status: &ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+
+    -1
+}
+
 pub fn run(cmd: &str, work_dir: &Path, detach: bool, stdout: Option<File>, record_pid: Option<&Path>) -> BuckyResult<Child> {
     let args: Vec<&str> = ProcessUtil::parse_cmd(cmd);
     if args.len() == 0 {
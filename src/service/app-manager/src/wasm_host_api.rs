@@ -0,0 +1,536 @@
+use crate::dapp::WasmPermission;
+use crate::wasm_runtime::{WasmHost, WasmInstance};
+use async_std::io::ReadExt;
+use async_trait::async_trait;
+use cyfs_base::*;
+use cyfs_core::DecAppId;
+use cyfs_lib::*;
+use cyfs_util::EventListenerAsyncRoutine;
+use log::*;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::str::FromStr;
+use std::sync::Weak;
+use wasmtime::*;
+
+// 宿主接口统一放在cyfs模块下
+// 入参通过(ptr, len)传递, json或者原始字节
+// 返回值i64: 负数为-BuckyErrorCode, 0表示没有数据, 其它为(ptr << 32 | len), 数据由guest的cyfs_alloc分配
+pub(crate) const WASM_HOST_MODULE: &str = "cyfs";
+pub(crate) const WASM_EXPORT_POST_OBJECT: &str = "cyfs_on_post_object";
+
+const WASM_EXPORT_MEMORY: &str = "memory";
+const WASM_EXPORT_ALLOC: &str = "cyfs_alloc";
+const WASM_MAX_DATA_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Deserialize)]
+struct WasmObjectRequest {
+    object_id: String,
+    inner_path: Option<String>,
+    target: Option<String>,
+}
+
+fn default_chain() -> String {
+    RouterHandlerChain::Handler.as_str().to_owned()
+}
+
+fn default_action() -> String {
+    RouterHandlerAction::Default.to_string()
+}
+
+#[derive(Deserialize)]
+struct WasmAddHandlerRequest {
+    id: String,
+    #[serde(default = "default_chain")]
+    chain: String,
+    #[serde(default)]
+    index: i32,
+    filter: Option<String>,
+    req_path: Option<String>,
+    #[serde(default = "default_action")]
+    default_action: String,
+}
+
+#[derive(Deserialize)]
+struct WasmRemoveHandlerRequest {
+    id: String,
+    #[serde(default = "default_chain")]
+    chain: String,
+}
+
+struct GuestMemory {
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+}
+
+fn invalid_guest(msg: String) -> BuckyError {
+    error!("{}", msg);
+    BuckyError::new(BuckyErrorCode::InvalidData, msg)
+}
+
+fn guest_memory_from_caller(caller: &mut Caller<'_, WasmHost>) -> BuckyResult<GuestMemory> {
+    let memory = caller
+        .get_export(WASM_EXPORT_MEMORY)
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| invalid_guest(format!("wasm app not export {}", WASM_EXPORT_MEMORY)))?;
+    let alloc = caller
+        .get_export(WASM_EXPORT_ALLOC)
+        .and_then(Extern::into_func)
+        .ok_or_else(|| invalid_guest(format!("wasm app not export {}", WASM_EXPORT_ALLOC)))?
+        .typed::<i32, i32>(&*caller)
+        .map_err(|e| invalid_guest(format!("wasm app {} type mismatch, {}", WASM_EXPORT_ALLOC, e)))?;
+
+    Ok(GuestMemory { memory, alloc })
+}
+
+fn guest_memory_from_instance(instance: &Instance, store: &mut Store<WasmHost>) -> BuckyResult<GuestMemory> {
+    let memory = instance
+        .get_memory(&mut *store, WASM_EXPORT_MEMORY)
+        .ok_or_else(|| invalid_guest(format!("wasm app not export {}", WASM_EXPORT_MEMORY)))?;
+    let alloc = instance
+        .get_typed_func::<i32, i32>(&mut *store, WASM_EXPORT_ALLOC)
+        .map_err(|e| invalid_guest(format!("wasm app not export {}, {}", WASM_EXPORT_ALLOC, e)))?;
+
+    Ok(GuestMemory { memory, alloc })
+}
+
+async fn write_bytes(
+    guest: &GuestMemory,
+    mut store: impl AsContextMut<Data = WasmHost>,
+    data: &[u8],
+) -> BuckyResult<(i32, i32)> {
+    if data.is_empty() {
+        return Ok((0, 0));
+    }
+
+    let ptr = guest
+        .alloc
+        .call_async(&mut store, data.len() as i32)
+        .await
+        .map_err(|e| invalid_guest(format!("wasm app alloc {} bytes trap, {}", data.len(), e)))?;
+    if ptr < 0 {
+        return Err(invalid_guest(format!("wasm app alloc return invalid ptr {}", ptr)));
+    }
+    guest
+        .memory
+        .write(&mut store, ptr as usize, data)
+        .map_err(|e| invalid_guest(format!("write wasm memory at {} err {}", ptr, e)))?;
+
+    Ok((ptr, data.len() as i32))
+}
+
+// 先检查范围再拷贝，不能按guest传入的长度直接分配内存
+fn read_bytes(memory: &Memory, store: impl AsContext, ptr: i32, len: i32) -> BuckyResult<Vec<u8>> {
+    if ptr < 0 || len < 0 || len as u64 > WASM_MAX_DATA_SIZE {
+        return Err(invalid_guest(format!("invalid wasm memory range {} {}", ptr, len)));
+    }
+
+    let start = ptr as usize;
+    let data = memory.data(&store);
+    let buf = data.get(start..start + len as usize).ok_or_else(|| {
+        invalid_guest(format!(
+            "read wasm memory {} {} out of bounds, memory size {}",
+            ptr,
+            len,
+            data.len()
+        ))
+    })?;
+    Ok(buf.to_vec())
+}
+
+fn pack_ret(ptr: i32, len: i32) -> i64 {
+    ((ptr as u32 as i64) << 32) | (len as u32 as i64)
+}
+
+fn to_ret(ret: BuckyResult<i64>) -> i64 {
+    match ret {
+        Ok(v) => v,
+        Err(e) => -(e.code().as_u16() as i64),
+    }
+}
+
+pub(crate) async fn write_guest_bytes(
+    instance: &Instance,
+    store: &mut Store<WasmHost>,
+    data: &[u8],
+) -> BuckyResult<(i32, i32)> {
+    let guest = guest_memory_from_instance(instance, store)?;
+    write_bytes(&guest, store, data).await
+}
+
+pub(crate) fn read_guest_result(instance: &Instance, store: &mut Store<WasmHost>, ret: i64) -> BuckyResult<Vec<u8>> {
+    if ret < 0 {
+        let code = BuckyErrorCode::from((-ret) as u16);
+        let msg = format!("wasm app return error {}", code);
+        warn!("{}", msg);
+        return Err(BuckyError::new(code, msg));
+    }
+
+    let guest = guest_memory_from_instance(instance, store)?;
+    read_bytes(&guest.memory, &*store, (ret >> 32) as i32, ret as u32 as i32)
+}
+
+fn read_from_caller(caller: &mut Caller<'_, WasmHost>, ptr: i32, len: i32) -> BuckyResult<Vec<u8>> {
+    let guest = guest_memory_from_caller(caller)?;
+    read_bytes(&guest.memory, &*caller, ptr, len)
+}
+
+fn read_string(caller: &mut Caller<'_, WasmHost>, ptr: i32, len: i32) -> BuckyResult<String> {
+    let buf = read_from_caller(caller, ptr, len)?;
+    String::from_utf8(buf).map_err(|e| invalid_guest(format!("wasm app pass invalid utf8 string, {}", e)))
+}
+
+fn read_json<T: DeserializeOwned>(caller: &mut Caller<'_, WasmHost>, ptr: i32, len: i32) -> BuckyResult<T> {
+    let buf = read_from_caller(caller, ptr, len)?;
+    serde_json::from_slice(&buf).map_err(|e| {
+        let msg = format!("wasm app pass invalid json {}, {}", String::from_utf8_lossy(&buf), e);
+        error!("{}", msg);
+        BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+    })
+}
+
+async fn write_result(caller: &mut Caller<'_, WasmHost>, data: &[u8]) -> BuckyResult<i64> {
+    let guest = guest_memory_from_caller(caller)?;
+    let (ptr, len) = write_bytes(&guest, &mut *caller, data).await?;
+    Ok(pack_ret(ptr, len))
+}
+
+fn parse_target(target: &Option<String>) -> BuckyResult<Option<ObjectId>> {
+    match target {
+        Some(target) => Ok(Some(ObjectId::from_str(target)?)),
+        None => Ok(None),
+    }
+}
+
+fn log_from_guest(caller: &mut Caller<'_, WasmHost>, level: i32, ptr: i32, len: i32) {
+    let app_id = caller.data().app_id.clone();
+    match read_string(caller, ptr, len) {
+        Ok(msg) => match level {
+            0 => error!("[wasm {}] {}", app_id, msg),
+            1 => warn!("[wasm {}] {}", app_id, msg),
+            2 => info!("[wasm {}] {}", app_id, msg),
+            _ => debug!("[wasm {}] {}", app_id, msg),
+        },
+        Err(e) => warn!("wasm app {} log err {}", app_id, e),
+    }
+}
+
+async fn non_get_object(caller: &mut Caller<'_, WasmHost>, ptr: i32, len: i32) -> BuckyResult<i64> {
+    caller.data().check_permission(WasmPermission::NONGet)?;
+    let req: WasmObjectRequest = read_json(caller, ptr, len)?;
+    let object_id = ObjectId::from_str(&req.object_id)?;
+    let target = parse_target(&req.target)?;
+
+    let stack = caller.data().stack.clone();
+    let resp = stack
+        .non_service()
+        .get_object(NONGetObjectOutputRequest::new_router(target, object_id, req.inner_path))
+        .await?;
+    write_result(caller, &resp.object.object_raw).await
+}
+
+async fn non_put_object(caller: &mut Caller<'_, WasmHost>, ptr: i32, len: i32) -> BuckyResult<i64> {
+    caller.data().check_permission(WasmPermission::NONPut)?;
+    let object_raw = read_from_caller(caller, ptr, len)?;
+    let object = NONObjectInfo::new_from_object_raw(object_raw)?;
+
+    let stack = caller.data().stack.clone();
+    stack
+        .non_service()
+        .put_object(NONPutObjectOutputRequest::new_router(None, object.object_id, object.object_raw))
+        .await?;
+    Ok(0)
+}
+
+fn get_op_env(caller: &mut Caller<'_, WasmHost>, handle: i32) -> BuckyResult<PathOpEnvStub> {
+    caller.data().check_permission(WasmPermission::RootState)?;
+    caller.data().op_envs.get(&handle).cloned().ok_or_else(|| {
+        let msg = format!("wasm app op env {} not found", handle);
+        warn!("{}", msg);
+        BuckyError::new(BuckyErrorCode::NotFound, msg)
+    })
+}
+
+// op env只能操作app自己dec的root state
+async fn op_env_create(caller: &mut Caller<'_, WasmHost>) -> BuckyResult<i64> {
+    caller.data().check_permission(WasmPermission::RootState)?;
+    let op_env = caller.data().stack.root_state_stub(None, None).create_path_op_env().await?;
+
+    let host = caller.data_mut();
+    let handle = host.next_op_env;
+    host.next_op_env += 1;
+    host.op_envs.insert(handle, op_env);
+    Ok(handle as i64)
+}
+
+async fn op_env_get_by_path(caller: &mut Caller<'_, WasmHost>, handle: i32, ptr: i32, len: i32) -> BuckyResult<i64> {
+    let op_env = get_op_env(caller, handle)?;
+    let path = read_string(caller, ptr, len)?;
+    match op_env.get_by_path(path).await? {
+        Some(id) => write_result(caller, id.to_string().as_bytes()).await,
+        None => Ok(0),
+    }
+}
+
+async fn op_env_set_with_path(
+    caller: &mut Caller<'_, WasmHost>,
+    handle: i32,
+    path_ptr: i32,
+    path_len: i32,
+    id_ptr: i32,
+    id_len: i32,
+) -> BuckyResult<i64> {
+    let op_env = get_op_env(caller, handle)?;
+    let path = read_string(caller, path_ptr, path_len)?;
+    let id = ObjectId::from_str(&read_string(caller, id_ptr, id_len)?)?;
+    op_env.set_with_path(path, &id, None, true).await?;
+    Ok(0)
+}
+
+async fn op_env_remove_with_path(caller: &mut Caller<'_, WasmHost>, handle: i32, ptr: i32, len: i32) -> BuckyResult<i64> {
+    let op_env = get_op_env(caller, handle)?;
+    let path = read_string(caller, ptr, len)?;
+    op_env.remove_with_path(path, None).await?;
+    Ok(0)
+}
+
+async fn op_env_commit(caller: &mut Caller<'_, WasmHost>, handle: i32) -> BuckyResult<i64> {
+    get_op_env(caller, handle)?;
+    let op_env = caller.data_mut().op_envs.remove(&handle).unwrap();
+    let root = op_env.commit().await?;
+    write_result(caller, root.dec_root.to_string().as_bytes()).await
+}
+
+async fn op_env_abort(caller: &mut Caller<'_, WasmHost>, handle: i32) -> BuckyResult<i64> {
+    get_op_env(caller, handle)?;
+    let op_env = caller.data_mut().op_envs.remove(&handle).unwrap();
+    op_env.abort().await?;
+    Ok(0)
+}
+
+async fn ndn_get_data(caller: &mut Caller<'_, WasmHost>, ptr: i32, len: i32) -> BuckyResult<i64> {
+    caller.data().check_permission(WasmPermission::NDNRead)?;
+    let req: WasmObjectRequest = read_json(caller, ptr, len)?;
+    let object_id = ObjectId::from_str(&req.object_id)?;
+    let target = parse_target(&req.target)?;
+
+    let stack = caller.data().stack.clone();
+    let mut resp = stack
+        .ndn_service()
+        .get_data(NDNGetDataOutputRequest::new_router(target, object_id, req.inner_path))
+        .await?;
+    if resp.length > WASM_MAX_DATA_SIZE {
+        let msg = format!("wasm app get data {} too large, {}", resp.object_id, resp.length);
+        warn!("{}", msg);
+        return Err(BuckyError::new(BuckyErrorCode::OutOfLimit, msg));
+    }
+
+    let mut data = Vec::with_capacity(resp.length as usize);
+    resp.data.read_to_end(&mut data).await?;
+    write_result(caller, &data).await
+}
+
+async fn router_add_handler(caller: &mut Caller<'_, WasmHost>, ptr: i32, len: i32) -> BuckyResult<i64> {
+    caller.data().check_permission(WasmPermission::RouterHandler)?;
+    let req: WasmAddHandlerRequest = read_json(caller, ptr, len)?;
+    let chain = RouterHandlerChain::from_str(&req.chain)?;
+    let default_action = RouterHandlerAction::from_str(&req.default_action)?;
+
+    let host = caller.data();
+    let instance = host.instance.get().cloned().ok_or_else(|| {
+        let msg = format!("wasm app {} instance not ready", host.app_id);
+        error!("{}", msg);
+        BuckyError::new(BuckyErrorCode::ErrorState, msg)
+    })?;
+    let routine = WasmPostObjectHandler {
+        app_id: host.app_id.clone(),
+        id: req.id.clone(),
+        instance,
+    };
+    host.stack.router_handlers().add_handler(
+        chain,
+        &req.id,
+        req.index,
+        req.filter,
+        req.req_path,
+        default_action,
+        Some(Box::new(routine)),
+    )?;
+
+    caller.data_mut().handlers.push((chain, req.id));
+    Ok(0)
+}
+
+async fn router_remove_handler(caller: &mut Caller<'_, WasmHost>, ptr: i32, len: i32) -> BuckyResult<i64> {
+    caller.data().check_permission(WasmPermission::RouterHandler)?;
+    let req: WasmRemoveHandlerRequest = read_json(caller, ptr, len)?;
+    let chain = RouterHandlerChain::from_str(&req.chain)?;
+
+    let stack = caller.data().stack.clone();
+    stack
+        .router_handlers()
+        .remove_handler(chain, RouterHandlerCategory::PostObject, &req.id)
+        .await?;
+    caller.data_mut().handlers.retain(|(c, id)| !(*c == chain && *id == req.id));
+    Ok(0)
+}
+
+struct WasmPostObjectHandler {
+    app_id: DecAppId,
+    id: String,
+    instance: Weak<WasmInstance>,
+}
+
+#[async_trait]
+impl EventListenerAsyncRoutine<RouterHandlerPostObjectRequest, RouterHandlerPostObjectResult>
+    for WasmPostObjectHandler
+{
+    async fn call(&self, param: &RouterHandlerPostObjectRequest) -> BuckyResult<RouterHandlerPostObjectResult> {
+        let instance = self.instance.upgrade().ok_or_else(|| {
+            let msg = format!("wasm app {} stopped, handler {}", self.app_id, self.id);
+            warn!("{}", msg);
+            BuckyError::new(BuckyErrorCode::NotFound, msg)
+        })?;
+
+        let resp = instance
+            .call_handler(
+                WASM_EXPORT_POST_OBJECT,
+                &self.id,
+                &param.encode_string(),
+                &param.request.common.source.dec,
+            )
+            .await?;
+        RouterHandlerPostObjectResult::decode_string(&resp)
+    }
+}
+
+pub(crate) fn add_host_api(linker: &mut Linker<WasmHost>) -> BuckyResult<()> {
+    let map_err = |e: wasmtime::Error| {
+        let msg = format!("add wasm host api err {}", e);
+        error!("{}", msg);
+        BuckyError::new(BuckyErrorCode::Failed, msg)
+    };
+
+    linker
+        .func_wrap(WASM_HOST_MODULE, "log", |mut caller: Caller<'_, WasmHost>, level: i32, ptr: i32, len: i32| {
+            log_from_guest(&mut caller, level, ptr, len)
+        })
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(WASM_HOST_MODULE, "non_get_object", |mut caller: Caller<'_, WasmHost>, (ptr, len): (i32, i32)| {
+            Box::new(async move { to_ret(non_get_object(&mut caller, ptr, len).await) })
+        })
+        .map_err(map_err)?;
+    linker
+        .func_wrap_async(WASM_HOST_MODULE, "non_put_object", |mut caller: Caller<'_, WasmHost>, (ptr, len): (i32, i32)| {
+            Box::new(async move { to_ret(non_put_object(&mut caller, ptr, len).await) })
+        })
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(WASM_HOST_MODULE, "op_env_create", |mut caller: Caller<'_, WasmHost>, (): ()| {
+            Box::new(async move { to_ret(op_env_create(&mut caller).await) })
+        })
+        .map_err(map_err)?;
+    linker
+        .func_wrap_async(
+            WASM_HOST_MODULE,
+            "op_env_get_by_path",
+            |mut caller: Caller<'_, WasmHost>, (handle, ptr, len): (i32, i32, i32)| {
+                Box::new(async move { to_ret(op_env_get_by_path(&mut caller, handle, ptr, len).await) })
+            },
+        )
+        .map_err(map_err)?;
+    linker
+        .func_wrap_async(
+            WASM_HOST_MODULE,
+            "op_env_set_with_path",
+            |mut caller: Caller<'_, WasmHost>,
+             (handle, path_ptr, path_len, id_ptr, id_len): (i32, i32, i32, i32, i32)| {
+                Box::new(async move {
+                    to_ret(op_env_set_with_path(&mut caller, handle, path_ptr, path_len, id_ptr, id_len).await)
+                })
+            },
+        )
+        .map_err(map_err)?;
+    linker
+        .func_wrap_async(
+            WASM_HOST_MODULE,
+            "op_env_remove_with_path",
+            |mut caller: Caller<'_, WasmHost>, (handle, ptr, len): (i32, i32, i32)| {
+                Box::new(async move { to_ret(op_env_remove_with_path(&mut caller, handle, ptr, len).await) })
+            },
+        )
+        .map_err(map_err)?;
+    linker
+        .func_wrap_async(WASM_HOST_MODULE, "op_env_commit", |mut caller: Caller<'_, WasmHost>, (handle,): (i32,)| {
+            Box::new(async move { to_ret(op_env_commit(&mut caller, handle).await) })
+        })
+        .map_err(map_err)?;
+    linker
+        .func_wrap_async(WASM_HOST_MODULE, "op_env_abort", |mut caller: Caller<'_, WasmHost>, (handle,): (i32,)| {
+            Box::new(async move { to_ret(op_env_abort(&mut caller, handle).await) })
+        })
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(WASM_HOST_MODULE, "ndn_get_data", |mut caller: Caller<'_, WasmHost>, (ptr, len): (i32, i32)| {
+            Box::new(async move { to_ret(ndn_get_data(&mut caller, ptr, len).await) })
+        })
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            WASM_HOST_MODULE,
+            "router_add_handler",
+            |mut caller: Caller<'_, WasmHost>, (ptr, len): (i32, i32)| {
+                Box::new(async move { to_ret(router_add_handler(&mut caller, ptr, len).await) })
+            },
+        )
+        .map_err(map_err)?;
+    linker
+        .func_wrap_async(
+            WASM_HOST_MODULE,
+            "router_remove_handler",
+            |mut caller: Caller<'_, WasmHost>, (ptr, len): (i32, i32)| {
+                Box::new(async move { to_ret(router_remove_handler(&mut caller, ptr, len).await) })
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_ret() {
+        let ret = pack_ret(0x1000, 32);
+        assert!(ret > 0);
+        assert_eq!((ret >> 32) as i32, 0x1000);
+        assert_eq!(ret as u32 as i32, 32);
+
+        let ret = to_ret(Err(BuckyError::from(BuckyErrorCode::PermissionDenied)));
+        assert!(ret < 0);
+        assert_eq!(BuckyErrorCode::from((-ret) as u16), BuckyErrorCode::PermissionDenied);
+    }
+
+    #[test]
+    fn test_read_bytes() {
+        let engine = Engine::default();
+        let mut store = Store::new(&engine, ());
+        let memory = Memory::new(&mut store, MemoryType::new(1, None)).unwrap();
+        memory.write(&mut store, 16, b"hello").unwrap();
+
+        assert_eq!(read_bytes(&memory, &store, 16, 5).unwrap(), b"hello");
+        assert_eq!(read_bytes(&memory, &store, 65536, 0).unwrap(), b"");
+
+        // 越界和超大的长度在分配内存之前就拒绝
+        assert!(read_bytes(&memory, &store, 65530, 16).is_err());
+        assert!(read_bytes(&memory, &store, -1, 1).is_err());
+        assert!(read_bytes(&memory, &store, 0, i32::MAX).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use crate::dapp::{DAppWasmInfo, WasmPermission};
+use crate::docker_api::RunConfig;
+use crate::wasm_host_api::*;
+use async_std::sync::Mutex as AsyncMutex;
+use cyfs_base::*;
+use cyfs_core::DecAppId;
+use cyfs_lib::*;
+use cyfs_util::get_app_dir;
+use log::*;
+use once_cell::sync::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use wasmtime::*;
+
+// wasm app的约定：
+// 1. 导出memory和cyfs_alloc(len) -> ptr, 宿主通过cyfs_alloc把返回数据写入guest内存
+// 2. 导出cyfs_main() -> i32, 启动时调用一次，用来注册router handler等，返回非0表示启动失败
+// 3. 导出cyfs_on_post_object(id_ptr, id_len, req_ptr, req_len) -> i64, 处理注册的post_object handler
+// 宿主接口见wasm_host_api, 只能使用package.cfg里声明过的权限
+
+const WASM_EXPORT_MAIN: &str = "cyfs_main";
+const WASM_EPOCH_TICK_IN_MILLIS: u64 = 10;
+// 单次调用guest的最长时间，超时后trap
+const WASM_CALL_TIMEOUT_IN_SECS: u64 = 30;
+// 没有配置内存配额时的默认上限，单位MiB
+const WASM_DEFAULT_MEMORY_IN_MB: usize = 256;
+
+pub(crate) struct WasmHost {
+    pub(crate) app_id: DecAppId,
+    pub(crate) stack: SharedCyfsStack,
+    permissions: HashSet<WasmPermission>,
+    limits: StoreLimits,
+
+    pub(crate) op_envs: HashMap<i32, PathOpEnvStub>,
+    pub(crate) next_op_env: i32,
+
+    // 注册过的router handler, 停止时移除
+    pub(crate) handlers: Vec<(RouterHandlerChain, String)>,
+    pub(crate) instance: OnceCell<Weak<WasmInstance>>,
+
+    // 停止时设置，正在执行的guest代码在下一个epoch检查点trap, 释放store
+    stopped: Arc<AtomicBool>,
+    // 当前这次调用的截止时间
+    deadline: Option<Instant>,
+}
+
+impl WasmHost {
+    pub(crate) fn check_permission(&self, permission: WasmPermission) -> BuckyResult<()> {
+        if self.permissions.contains(&permission) {
+            Ok(())
+        } else {
+            let msg = format!("wasm app {} not declare permission {:?}", self.app_id, permission);
+            warn!("{}", msg);
+            Err(BuckyError::new(BuckyErrorCode::PermissionDenied, msg))
+        }
+    }
+}
+
+// epoch检查点: 已经停止或者超时就trap, 否则让出执行权
+fn on_epoch_deadline(ctx: StoreContextMut<'_, WasmHost>) -> wasmtime::Result<UpdateDeadline> {
+    let host = ctx.data();
+    if host.stopped.load(Ordering::SeqCst) {
+        return Err(wasmtime::Error::msg(format!("wasm app {} stopped", host.app_id)));
+    }
+    if let Some(deadline) = host.deadline {
+        if Instant::now() > deadline {
+            return Err(wasmtime::Error::msg(format!(
+                "wasm app {} call timeout after {} secs",
+                host.app_id, WASM_CALL_TIMEOUT_IN_SECS
+            )));
+        }
+    }
+
+    Ok(UpdateDeadline::Yield(1))
+}
+
+pub(crate) struct WasmInstance {
+    app_id: DecAppId,
+    store: AsyncMutex<Store<WasmHost>>,
+    instance: Instance,
+    stopped: Arc<AtomicBool>,
+}
+
+impl WasmInstance {
+    // router handler触发时调用guest的导出函数，请求和返回都是json
+    pub(crate) async fn call_handler(
+        &self,
+        export: &str,
+        handler_id: &str,
+        param: &str,
+        source_dec: &ObjectId,
+    ) -> BuckyResult<String> {
+        if self.stopped.load(Ordering::SeqCst) {
+            let msg = format!("wasm app {} stopped, handler {}", self.app_id, handler_id);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+        }
+
+        let mut store = match self.store.try_lock() {
+            Some(store) => store,
+            None => {
+                // guest自己发出的请求又路由回自己的handler, store正被发起请求的调用占用，等待会死锁
+                if source_dec == self.app_id.object_id() {
+                    let msg = format!("wasm app {} call its own handler {} reentrantly", self.app_id, handler_id);
+                    warn!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::ErrorState, msg));
+                }
+
+                let timeout = Duration::from_secs(WASM_CALL_TIMEOUT_IN_SECS);
+                async_std::future::timeout(timeout, self.store.lock()).await.map_err(|_| {
+                    let msg = format!("wasm app {} busy, handler {} wait timeout", self.app_id, handler_id);
+                    warn!("{}", msg);
+                    BuckyError::new(BuckyErrorCode::Timeout, msg)
+                })?
+            }
+        };
+        store.data_mut().deadline = Some(Instant::now() + Duration::from_secs(WASM_CALL_TIMEOUT_IN_SECS));
+        let func = self
+            .instance
+            .get_typed_func::<(i32, i32, i32, i32), i64>(&mut *store, export)
+            .map_err(|e| {
+                let msg = format!("wasm app {} not export {}, {}", self.app_id, export, e);
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::NotSupport, msg)
+            })?;
+
+        let (id_ptr, id_len) = write_guest_bytes(&self.instance, &mut *store, handler_id.as_bytes()).await?;
+        let (ptr, len) = write_guest_bytes(&self.instance, &mut *store, param.as_bytes()).await?;
+        let ret = func
+            .call_async(&mut *store, (id_ptr, id_len, ptr, len))
+            .await
+            .map_err(|e| {
+                let msg = format!("wasm app {} call {} trap, {}", self.app_id, export, e);
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::ExecuteError, msg)
+            })?;
+
+        let data = read_guest_result(&self.instance, &mut *store, ret)?;
+        String::from_utf8(data).map_err(|e| {
+            let msg = format!("wasm app {} {} return invalid utf8, {}", self.app_id, export, e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidData, msg)
+        })
+    }
+
+    async fn release(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        let mut store = self.store.lock().await;
+        let host = store.data_mut();
+        for (chain, id) in host.handlers.drain(..) {
+            let ret = host
+                .stack
+                .router_handlers()
+                .remove_handler(chain, RouterHandlerCategory::PostObject, &id)
+                .await;
+            info!("wasm app {} remove handler {} {}, ret {:?}", self.app_id, chain, id, ret);
+        }
+        for (_, op_env) in host.op_envs.drain() {
+            let _ = op_env.abort().await;
+        }
+    }
+}
+
+pub struct WasmRuntime {
+    engine: Engine,
+    linker: Linker<WasmHost>,
+    instances: Mutex<HashMap<DecAppId, Arc<WasmInstance>>>,
+}
+
+impl WasmRuntime {
+    pub fn new() -> BuckyResult<Self> {
+        let mut config = Config::new();
+        config.async_support(true);
+        // 长时间运行的guest代码定期让出执行权，避免阻塞app-manager的其它任务, 停止或者超时后trap
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config).map_err(|e| {
+            let msg = format!("create wasm engine err {}", e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::Failed, msg)
+        })?;
+
+        let mut linker = Linker::new(&engine);
+        add_host_api(&mut linker)?;
+
+        let ticker = engine.clone();
+        async_std::task::spawn(async move {
+            loop {
+                async_std::task::sleep(Duration::from_millis(WASM_EPOCH_TICK_IN_MILLIS)).await;
+                ticker.increment_epoch();
+            }
+        });
+
+        Ok(Self {
+            engine,
+            linker,
+            instances: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub async fn start(&self, app_id: &DecAppId, info: &DAppWasmInfo, config: RunConfig) -> BuckyResult<()> {
+        if self.is_running(app_id) {
+            info!("wasm app {} is already running", app_id);
+            return Ok(());
+        }
+
+        let module_path = get_app_dir(&app_id.to_string()).join(&info.module);
+        info!("wasm runtime run app {}, module {}, permissions {:?}, config {:?}",
+            app_id, module_path.display(), info.permissions, config);
+        let module = Module::from_file(&self.engine, &module_path).map_err(|e| {
+            let msg = format!("load wasm module {} err {}", module_path.display(), e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+        })?;
+
+        // 以app自己的dec身份连接协议栈, 协议栈上的acl同样生效
+        let stack = SharedCyfsStack::open_default(Some(app_id.object_id().clone())).await?;
+        stack.wait_online(None).await?;
+
+        let memory = config
+            .memory
+            .map(|m| m as usize)
+            .unwrap_or(WASM_DEFAULT_MEMORY_IN_MB);
+        let limits = StoreLimitsBuilder::new()
+            .instances(1)
+            .memories(1)
+            .memory_size(memory * 1048576);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let host = WasmHost {
+            app_id: app_id.clone(),
+            stack,
+            permissions: info.permissions.clone(),
+            limits: limits.build(),
+            op_envs: HashMap::new(),
+            next_op_env: 1,
+            handlers: vec![],
+            instance: OnceCell::new(),
+            stopped: stopped.clone(),
+            deadline: Some(Instant::now() + Duration::from_secs(WASM_CALL_TIMEOUT_IN_SECS)),
+        };
+        let mut store = Store::new(&self.engine, host);
+        store.limiter(|host| &mut host.limits);
+        store.set_epoch_deadline(1);
+        store.epoch_deadline_callback(on_epoch_deadline);
+
+        let instance = self.linker.instantiate_async(&mut store, &module).await.map_err(|e| {
+            let msg = format!("instantiate wasm app {} err {}", app_id, e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::Failed, msg)
+        })?;
+        let main = instance
+            .get_typed_func::<(), i32>(&mut store, WASM_EXPORT_MAIN)
+            .map_err(|e| {
+                let msg = format!("wasm app {} not export {}, {}", app_id, WASM_EXPORT_MAIN, e);
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::NotSupport, msg)
+            })?;
+
+        let wasm = Arc::new(WasmInstance {
+            app_id: app_id.clone(),
+            store: AsyncMutex::new(store),
+            instance,
+            stopped,
+        });
+
+        let ret = {
+            let mut store = wasm.store.lock().await;
+            let _ = store.data().instance.set(Arc::downgrade(&wasm));
+            store.data_mut().deadline = Some(Instant::now() + Duration::from_secs(WASM_CALL_TIMEOUT_IN_SECS));
+            main.call_async(&mut *store, ()).await
+        };
+        match ret {
+            Ok(0) => {
+                info!("wasm app {} start success", app_id);
+                self.instances.lock().unwrap().insert(app_id.clone(), wasm);
+                Ok(())
+            }
+            Ok(code) => {
+                wasm.release().await;
+                let msg = format!("wasm app {} {} return {}", app_id, WASM_EXPORT_MAIN, code);
+                error!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::Failed, msg))
+            }
+            Err(e) => {
+                wasm.release().await;
+                let msg = format!("wasm app {} {} trap, {}", app_id, WASM_EXPORT_MAIN, e);
+                error!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::ExecuteError, msg))
+            }
+        }
+    }
+
+    pub async fn stop(&self, app_id: &DecAppId) -> BuckyResult<()> {
+        let wasm = self.instances.lock().unwrap().remove(app_id);
+        match wasm {
+            Some(wasm) => {
+                wasm.release().await;
+                info!("wasm app {} stopped", app_id);
+            }
+            None => {
+                info!("wasm app {} not running", app_id);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_running(&self, app_id: &DecAppId) -> bool {
+        self.instances.lock().unwrap().contains_key(app_id)
+    }
+}
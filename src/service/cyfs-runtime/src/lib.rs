@@ -7,6 +7,7 @@ mod stack;
 mod mime;
 mod anonymous;
 mod file_cache;
+mod webdav;
 
 // use once_cell::sync::OnceCell;
 // use crate::runtime::CyfsRuntime;
@@ -10,6 +10,7 @@ mod mime;
 mod proxy;
 mod runtime;
 mod stack;
+mod webdav;
 
 use std::str::FromStr;
 
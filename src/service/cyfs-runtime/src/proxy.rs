@@ -1,6 +1,7 @@
 use super::stack::CyfsStackInsConfig;
 use crate::file_cache::FileCacheRecevier;
 use crate::mime::*;
+use crate::webdav::{WebDAVConfig, WebDAVServer};
 use cyfs_base::*;
 use cyfs_stack_loader::{CyfsStack, HttpRequestSource, HttpServerHandlerRef};
 use ood_control::OOD_CONTROLLER;
@@ -160,6 +161,7 @@ where
 pub(crate) struct CyfsProxyInner {
     static_root: PathBuf,
     non_http_server: OnceCell<CyfsHttpServer>,
+    webdav: OnceCell<WebDAVServer>,
 }
 
 impl CyfsProxyInner {
@@ -184,6 +186,7 @@ impl CyfsProxyInner {
         Self {
             static_root,
             non_http_server: OnceCell::new(),
+            webdav: OnceCell::new(),
         }
     }
 }
@@ -203,7 +206,18 @@ impl CyfsProxy {
         }
     }
 
+    // should be called before start
+    pub fn enable_webdav(&self, config: WebDAVConfig) {
+        if let Err(_) = self.inner.webdav.set(WebDAVServer::new(config)) {
+            unreachable!();
+        }
+    }
+
     pub fn bind_non_stack(&self, cyfs_stack: CyfsStack) {
+        if let Some(webdav) = self.inner.webdav.get() {
+            webdav.bind_non_stack(cyfs_stack.clone());
+        }
+
         let server = CyfsHttpServer::new(cyfs_stack);
         if let Err(_) = self.inner.non_http_server.set(server) {
             unreachable!();
@@ -244,6 +258,11 @@ impl CyfsProxy {
             }
         });
 
+        // webdav is served on its own listener without the cors of the proxy
+        if let Some(webdav) = self.inner.webdav.get() {
+            webdav.start().await?;
+        }
+
         Ok(())
     }
 
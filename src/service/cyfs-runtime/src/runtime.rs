@@ -1,5 +1,6 @@
 use super::proxy::CyfsProxy;
 use super::stack::{CyfsStackIns, CyfsStackInsConfig, UpdateStackNetworkParams};
+use super::webdav::WebDAVConfig;
 use cyfs_base::*;
 use ood_control::*;
 
@@ -125,6 +126,18 @@ impl CyfsRuntime {
             self.stack.set_config(v).await?;
         }
 
+        if let Some(v) = cfg_node.remove("webdav") {
+            let config = WebDAVConfig::load(v)?;
+            if config.enable && config.check().is_ok() {
+                info!(
+                    "will enable webdav service: port={}, root={}",
+                    config.port,
+                    config.root_path()
+                );
+                self.proxy.enable_webdav(config);
+            }
+        }
+
         // TODO 遍历加载其余节点
         Ok(())
     }
@@ -0,0 +1,86 @@
+use cyfs_base::*;
+
+use serde::Deserialize;
+use std::str::FromStr;
+
+/*
+[webdav]
+enable = true
+dec_id = "9tGpLNnQnReSYJhrgrLMjz2bFoRDVKP9Dp8Crqy1bjzY"
+root = "/webdav"
+port = 1333
+username = "cyfs"
+password = "xxx"
+*/
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct WebDAVConfig {
+    pub enable: bool,
+
+    // the dec whose global state stores the files, default is system dec
+    pub dec_id: Option<String>,
+
+    // the global state path served as the webdav root collection
+    pub root: String,
+
+    // chunk size in bytes used to build the uploaded files
+    pub chunk_size: u32,
+
+    // the webdav listener is bound to 127.0.0.1:{port}, separated from the runtime proxy
+    pub port: u16,
+
+    // http basic auth, the service will not be enabled without password
+    pub username: String,
+    pub password: Option<String>,
+}
+
+impl Default for WebDAVConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            dec_id: None,
+            root: "/webdav".to_owned(),
+            chunk_size: 1024 * 1024 * 4,
+            port: CYFS_RUNTIME_WEBDAV_PORT,
+            username: "cyfs".to_owned(),
+            password: None,
+        }
+    }
+}
+
+impl WebDAVConfig {
+    pub fn load(node: toml::Value) -> BuckyResult<Self> {
+        node.try_into().map_err(|e| {
+            let msg = format!("invalid webdav config! {}", e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+        })
+    }
+
+    pub fn check(&self) -> BuckyResult<()> {
+        match &self.password {
+            Some(password) if !password.is_empty() => Ok(()),
+            _ => {
+                let msg = "webdav password is not configured!".to_owned();
+                error!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg))
+            }
+        }
+    }
+
+    pub fn dec_id(&self) -> BuckyResult<ObjectId> {
+        match &self.dec_id {
+            Some(id) => ObjectId::from_str(id).map_err(|e| {
+                let msg = format!("invalid dec_id in webdav config: {}, {}", id, e);
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+            }),
+            None => Ok(cyfs_core::get_system_dec_app().to_owned()),
+        }
+    }
+
+    // root path without trailing '/', such as /webdav
+    pub fn root_path(&self) -> String {
+        format!("/{}", self.root.trim_matches('/'))
+    }
+}
@@ -0,0 +1,6 @@
+mod config;
+mod server;
+mod storage;
+
+pub(crate) use config::*;
+pub(crate) use server::*;
@@ -0,0 +1,588 @@
+use super::config::WebDAVConfig;
+use super::storage::*;
+use cyfs_base::*;
+use cyfs_lib::{RequestorHelper, RequestorRangeHelper, WebHelper};
+use cyfs_stack_loader::CyfsStack;
+
+use async_std::io::BufReader;
+use http_types::{Method, StatusCode, Url};
+use once_cell::sync::OnceCell;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tide::Response;
+
+// the webdav root collection's url path on the webdav listener
+pub const WEBDAV_PREFIX: &str = "/dav";
+
+// Only class 1 is supported, LOCK and UNLOCK are not implemented
+const ALLOW_METHODS: &str = "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, MKCOL, MOVE";
+
+// Only the unreserved chars are kept in href
+const HREF_ENCODE_SET: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+// Parse the url path to the normalized path relative to the webdav root, such as /docs/a.txt
+fn parse_path(url_path: &str) -> Option<String> {
+    let path = url_path.strip_prefix(WEBDAV_PREFIX)?;
+    if !path.is_empty() && !path.starts_with('/') {
+        return None;
+    }
+
+    let path = percent_encoding::percent_decode_str(path)
+        .decode_utf8()
+        .ok()?;
+
+    let mut segs = vec![];
+    for seg in path.split('/').filter(|v| !v.is_empty()) {
+        if seg == "." || seg == ".." {
+            return None;
+        }
+        segs.push(seg);
+    }
+
+    Some(format!("/{}", segs.join("/")))
+}
+
+fn parent_path(path: &str) -> Option<&str> {
+    match path.trim_end_matches('/').rfind('/') {
+        Some(0) if path.len() > 1 => Some("/"),
+        Some(pos) if pos > 0 => Some(&path[..pos]),
+        _ => None,
+    }
+}
+
+fn href(path: &str, is_collection: bool) -> String {
+    let mut href = WEBDAV_PREFIX.to_owned();
+    for seg in path.split('/').filter(|v| !v.is_empty()) {
+        href.push('/');
+        href.push_str(&percent_encoding::utf8_percent_encode(seg, HREF_ENCODE_SET).to_string());
+    }
+    if is_collection {
+        href.push('/');
+    }
+
+    href
+}
+
+fn status_response(status: StatusCode) -> Response {
+    Response::new(status)
+}
+
+fn secret_eq(left: &str, right: &str) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+
+    left.bytes()
+        .zip(right.bytes())
+        .fold(0u8, |acc, (l, r)| acc | (l ^ r))
+        == 0
+}
+
+struct WebDAVServerInner {
+    config: WebDAVConfig,
+    temp_dir: PathBuf,
+    storage: OnceCell<WebDAVStorage>,
+    index: AtomicU64,
+}
+
+// WebDAV service on a loopback only listener with basic auth, which serves a global state path as the root collection
+#[derive(Clone)]
+pub(crate) struct WebDAVServer(Arc<WebDAVServerInner>);
+
+impl WebDAVServer {
+    pub fn new(config: WebDAVConfig) -> Self {
+        let temp_dir = cyfs_util::get_temp_path().join("webdav");
+
+        Self(Arc::new(WebDAVServerInner {
+            config,
+            temp_dir,
+            storage: OnceCell::new(),
+            index: AtomicU64::new(0),
+        }))
+    }
+
+    // The webdav is not served on the runtime proxy, which allows any origin with credentials for the
+    // web pages, so the browser pages can not access the files with the user's credentials
+    pub async fn start(&self) -> BuckyResult<()> {
+        let mut server = ::tide::new();
+        server.at(WEBDAV_PREFIX).all(self.clone());
+        server.at(&format!("{}/*", WEBDAV_PREFIX)).all(self.clone());
+
+        let addr = format!("127.0.0.1:{}", self.0.config.port);
+        let mut listener = server.bind(&addr).await.map_err(|e| {
+            error!("webdav bind error! addr={}, {}", addr, e);
+            e
+        })?;
+
+        info!(
+            "webdav service listening: addr={}, prefix={}, root={}",
+            addr,
+            WEBDAV_PREFIX,
+            self.0.config.root_path()
+        );
+
+        async_std::task::spawn(async move {
+            if let Err(e) = listener.accept().await {
+                error!("webdav server accept error! addr={}, {}", addr, e);
+            }
+        });
+
+        Ok(())
+    }
+
+    fn check_auth(&self, req: &tide::Request<()>) -> bool {
+        let auth = match http_types::auth::BasicAuth::from_headers(req) {
+            Ok(Some(auth)) => auth,
+            Ok(None) => return false,
+            Err(e) => {
+                warn!("invalid webdav basic auth header! {}", e);
+                return false;
+            }
+        };
+
+        let password = match &self.0.config.password {
+            Some(password) if !password.is_empty() => password,
+            _ => return false,
+        };
+
+        // check both to keep the time constant
+        let user_ok = secret_eq(auth.username(), &self.0.config.username);
+        let password_ok = secret_eq(auth.password(), password);
+        user_ok && password_ok
+    }
+
+    // The storage is available after the non stack is loaded
+    pub fn bind_non_stack(&self, cyfs_stack: CyfsStack) {
+        let this = self.clone();
+        async_std::task::spawn(async move {
+            if let Err(e) = this.init_storage(cyfs_stack).await {
+                error!("init webdav storage failed! {}", e);
+            }
+        });
+    }
+
+    async fn init_storage(&self, cyfs_stack: CyfsStack) -> BuckyResult<()> {
+        let dec_id = self.0.config.dec_id()?;
+        let stack = cyfs_stack
+            .open_shared_object_stack(Some(dec_id.clone()), None)
+            .await?;
+        stack.wait_online(None).await?;
+
+        async_std::fs::create_dir_all(&self.0.temp_dir).await?;
+
+        let storage = WebDAVStorage::new(
+            stack,
+            dec_id,
+            self.0.config.root_path(),
+            self.0.config.chunk_size,
+        );
+        storage.init().await?;
+
+        if self.0.storage.set(storage).is_err() {
+            unreachable!();
+        }
+
+        info!("webdav storage init success!");
+        Ok(())
+    }
+
+    async fn process_request(&self, req: tide::Request<()>) -> BuckyResult<Response> {
+        if !self.check_auth(&req) {
+            let mut resp = status_response(StatusCode::Unauthorized);
+            resp.insert_header("WWW-Authenticate", "Basic realm=\"cyfs webdav\"");
+            return Ok(resp);
+        }
+
+        if req.method() == Method::Options {
+            let mut resp = status_response(StatusCode::Ok);
+            resp.insert_header("DAV", "1");
+            resp.insert_header("Allow", ALLOW_METHODS);
+            resp.insert_header("MS-Author-Via", "DAV");
+            return Ok(resp);
+        }
+
+        let storage = match self.0.storage.get() {
+            Some(storage) => storage,
+            None => {
+                let mut resp = status_response(StatusCode::ServiceUnavailable);
+                resp.set_body("webdav storage is not ready yet!");
+                return Ok(resp);
+            }
+        };
+
+        let path = match parse_path(req.url().path()) {
+            Some(path) => path,
+            None => return Ok(status_response(StatusCode::BadRequest)),
+        };
+
+        debug!("webdav request: {} {}", req.method(), path);
+
+        match req.method() {
+            Method::PropFind => self.propfind(storage, &req, &path).await,
+            Method::Get | Method::Head => self.get(storage, &req, &path).await,
+            Method::Put => self.put(storage, req, &path).await,
+            Method::MkCol => self.mkcol(storage, &req, &path).await,
+            Method::Delete => self.delete(storage, &path).await,
+            Method::Move => self.rename(storage, &req, &path).await,
+            _ => {
+                let mut resp = status_response(StatusCode::MethodNotAllowed);
+                resp.insert_header("Allow", ALLOW_METHODS);
+                Ok(resp)
+            }
+        }
+    }
+
+    fn append_response(body: &mut String, path: &str, entry: &WebDAVEntry) {
+        body.push_str("<D:response><D:href>");
+        body.push_str(&WebHelper::xml_escape(&href(path, entry.is_collection)));
+        body.push_str("</D:href><D:propstat><D:prop>");
+
+        body.push_str(&format!(
+            "<D:displayname>{}</D:displayname>",
+            WebHelper::xml_escape(&entry.name)
+        ));
+        if entry.is_collection {
+            body.push_str("<D:resourcetype><D:collection/></D:resourcetype>");
+        } else {
+            body.push_str("<D:resourcetype/>");
+            body.push_str(&format!(
+                "<D:getcontentlength>{}</D:getcontentlength>",
+                entry.size
+            ));
+            body.push_str("<D:getcontenttype>application/octet-stream</D:getcontenttype>");
+        }
+        if entry.modified > 0 {
+            body.push_str(&format!(
+                "<D:getlastmodified>{}</D:getlastmodified>",
+                WebHelper::http_date(entry.modified)
+            ));
+        }
+        body.push_str(&format!(
+            "<D:getetag>{}</D:getetag>",
+            WebHelper::xml_escape(&entry.etag())
+        ));
+
+        body.push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>");
+    }
+
+    // All props are always returned, depth infinity is rejected with propfind-finite-depth as rfc4918 9.1
+    async fn propfind(
+        &self,
+        storage: &WebDAVStorage,
+        req: &tide::Request<()>,
+        path: &str,
+    ) -> BuckyResult<Response> {
+        // the missing depth is infinity
+        let depth = req
+            .header("depth")
+            .map(|v| v.last().as_str().trim().to_owned())
+            .unwrap_or_else(|| "infinity".to_owned());
+        if depth != "0" && depth != "1" {
+            let mut resp = status_response(StatusCode::Forbidden);
+            resp.set_content_type("application/xml; charset=utf-8");
+            resp.set_body(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:error xmlns:D=\"DAV:\"><D:propfind-finite-depth/></D:error>",
+            );
+            return Ok(resp);
+        }
+
+        let entry = match storage.stat(path).await? {
+            Some(entry) => entry,
+            None => return Ok(status_response(StatusCode::NotFound)),
+        };
+
+        let mut body = String::with_capacity(4096);
+        body.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">");
+        Self::append_response(&mut body, path, &entry);
+
+        if entry.is_collection && depth != "0" {
+            for child in storage.list(path).await? {
+                let child_path = format!("{}/{}", path.trim_end_matches('/'), child.name);
+                Self::append_response(&mut body, &child_path, &child);
+            }
+        }
+        body.push_str("</D:multistatus>");
+
+        let mut resp = status_response(StatusCode::MultiStatus);
+        resp.set_content_type("application/xml; charset=utf-8");
+        resp.set_body(body);
+        Ok(resp)
+    }
+
+    async fn get(
+        &self,
+        storage: &WebDAVStorage,
+        req: &tide::Request<()>,
+        path: &str,
+    ) -> BuckyResult<Response> {
+        let entry = match storage.stat(path).await? {
+            Some(entry) => entry,
+            None => return Ok(status_response(StatusCode::NotFound)),
+        };
+
+        if entry.is_collection {
+            let mut resp = status_response(StatusCode::MethodNotAllowed);
+            resp.insert_header("Allow", "OPTIONS, PROPFIND, MKCOL, DELETE, MOVE");
+            return Ok(resp);
+        }
+
+        let etag = entry.etag();
+        if let Some(value) = req.header("if-none-match") {
+            if value.iter().any(|v| v.as_str().trim() == etag) {
+                let mut resp = status_response(StatusCode::NotModified);
+                resp.insert_header("ETag", etag);
+                return Ok(resp);
+            }
+        }
+
+        let range = match req.header("range") {
+            Some(value) => {
+                match RequestorRangeHelper::parse_single_range(value.last().as_str(), entry.size) {
+                    Ok(range) => range,
+                    Err(_) => {
+                        let mut resp = status_response(StatusCode::RequestedRangeNotSatisfiable);
+                        resp.insert_header("Content-Range", format!("bytes */{}", entry.size));
+                        return Ok(resp);
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let mut resp = match &range {
+            Some(range) => {
+                let mut resp = status_response(StatusCode::PartialContent);
+                resp.insert_header(
+                    "Content-Range",
+                    format!("bytes {}-{}/{}", range.start, range.end - 1, entry.size),
+                );
+                resp
+            }
+            None => status_response(StatusCode::Ok),
+        };
+
+        let len = range.as_ref().map(|v| v.end - v.start).unwrap_or(entry.size);
+        resp.insert_header("ETag", etag);
+        resp.insert_header("Accept-Ranges", "bytes");
+        resp.insert_header("Last-Modified", WebHelper::http_date(entry.modified));
+        resp.set_content_type("application/octet-stream");
+
+        if req.method() == Method::Head || len == 0 {
+            resp.insert_header("Content-Length", len.to_string());
+            return Ok(resp);
+        }
+
+        let data = storage.read(&entry.object_id, range).await?;
+        resp.set_body(tide::Body::from_reader(
+            BufReader::new(data.data),
+            Some(len as usize),
+        ));
+        Ok(resp)
+    }
+
+    // the parent must be an exists collection, otherwise is 409 Conflict
+    async fn check_parent(storage: &WebDAVStorage, path: &str) -> BuckyResult<bool> {
+        match parent_path(path) {
+            Some(parent) => match storage.stat(parent).await? {
+                Some(entry) => Ok(entry.is_collection),
+                None => Ok(false),
+            },
+            None => Ok(false),
+        }
+    }
+
+    async fn put(
+        &self,
+        storage: &WebDAVStorage,
+        mut req: tide::Request<()>,
+        path: &str,
+    ) -> BuckyResult<Response> {
+        if !Self::check_parent(storage, path).await? {
+            return Ok(status_response(StatusCode::Conflict));
+        }
+
+        if let Some(entry) = storage.stat(path).await? {
+            if entry.is_collection {
+                return Ok(status_response(StatusCode::MethodNotAllowed));
+            }
+        }
+
+        let index = self.0.index.fetch_add(1, Ordering::SeqCst);
+        let local_path = self
+            .0
+            .temp_dir
+            .join(format!("put-{}-{}", bucky_time_now(), index));
+
+        let ret = async {
+            let mut file = async_std::fs::File::create(&local_path).await?;
+            async_std::io::copy(req.take_body(), &mut file).await?;
+            drop(file);
+
+            storage.write(path, &local_path).await
+        }
+        .await;
+
+        if let Err(e) = async_std::fs::remove_file(&local_path).await {
+            warn!("remove webdav temp file failed! file={}, {}", local_path.display(), e);
+        }
+
+        match ret? {
+            Some(_) => Ok(status_response(StatusCode::NoContent)),
+            None => Ok(status_response(StatusCode::Created)),
+        }
+    }
+
+    async fn mkcol(
+        &self,
+        storage: &WebDAVStorage,
+        req: &tide::Request<()>,
+        path: &str,
+    ) -> BuckyResult<Response> {
+        // mkcol with body is not supported
+        if req.len().unwrap_or(0) > 0 {
+            return Ok(status_response(StatusCode::UnsupportedMediaType));
+        }
+
+        if !Self::check_parent(storage, path).await? {
+            return Ok(status_response(StatusCode::Conflict));
+        }
+
+        match storage.create_collection(path).await {
+            Ok(()) => Ok(status_response(StatusCode::Created)),
+            Err(e) if e.code() == BuckyErrorCode::AlreadyExists => {
+                Ok(status_response(StatusCode::MethodNotAllowed))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn delete(&self, storage: &WebDAVStorage, path: &str) -> BuckyResult<Response> {
+        if path == "/" {
+            return Ok(status_response(StatusCode::Forbidden));
+        }
+
+        match storage.remove(path).await {
+            Ok(()) => Ok(status_response(StatusCode::NoContent)),
+            Err(e) if e.code() == BuckyErrorCode::NotFound => {
+                Ok(status_response(StatusCode::NotFound))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn rename(
+        &self,
+        storage: &WebDAVStorage,
+        req: &tide::Request<()>,
+        path: &str,
+    ) -> BuckyResult<Response> {
+        // Destination is absolute url or absolute path
+        let dest = req.header("destination").and_then(|v| {
+            let value = v.last().as_str();
+            let url_path = match Url::parse(value) {
+                Ok(url) => url.path().to_owned(),
+                Err(_) => value.to_owned(),
+            };
+            parse_path(&url_path)
+        });
+        let dest = match dest {
+            Some(dest) => dest,
+            None => return Ok(status_response(StatusCode::BadRequest)),
+        };
+
+        // can not move the root, or move into itself
+        if path == "/" || dest == path || dest.starts_with(&format!("{}/", path)) {
+            return Ok(status_response(StatusCode::Forbidden));
+        }
+
+        if !Self::check_parent(storage, &dest).await? {
+            return Ok(status_response(StatusCode::Conflict));
+        }
+
+        let overwrite = req
+            .header("overwrite")
+            .map(|v| v.last().as_str().trim() != "F")
+            .unwrap_or(true);
+
+        match storage.rename(path, &dest, overwrite).await {
+            Ok(true) => Ok(status_response(StatusCode::NoContent)),
+            Ok(false) => Ok(status_response(StatusCode::Created)),
+            Err(e) if e.code() == BuckyErrorCode::AlreadyExists => {
+                Ok(status_response(StatusCode::PreconditionFailed))
+            }
+            Err(e) if e.code() == BuckyErrorCode::NotFound => {
+                Ok(status_response(StatusCode::NotFound))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl tide::Endpoint<()> for WebDAVServer {
+    async fn call(&self, req: ::tide::Request<()>) -> tide::Result {
+        let method = req.method();
+        let url_path = req.url().path().to_owned();
+        let resp = match self.process_request(req).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                error!("webdav request failed! {} {}, {}", method, url_path, e);
+                RequestorHelper::trans_error(e)
+            }
+        };
+
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path() {
+        assert_eq!(parse_path("/dav").unwrap(), "/");
+        assert_eq!(parse_path("/dav/").unwrap(), "/");
+        assert_eq!(parse_path("/dav/a%20b//c/").unwrap(), "/a b/c");
+        assert!(parse_path("/dav/a/../b").is_none());
+        assert!(parse_path("/davx/a").is_none());
+
+        assert_eq!(parent_path("/a/b"), Some("/a"));
+        assert_eq!(parent_path("/a"), Some("/"));
+        assert_eq!(parent_path("/"), None);
+
+        assert_eq!(href("/a b/c", true), "/dav/a%20b/c/");
+    }
+
+    #[test]
+    fn test_auth() {
+        let new_request = |auth: Option<&str>| {
+            let url = Url::parse("http://127.0.0.1:1333/dav/").unwrap();
+            let mut req = http_types::Request::new(Method::PropFind, url);
+            if let Some(auth) = auth {
+                req.insert_header("authorization", auth);
+            }
+            tide::Request::<()>::from(req)
+        };
+
+        // cyfs:secret and cyfs:wrong
+        let ok = new_request(Some("Basic Y3lmczpzZWNyZXQ="));
+        let wrong = new_request(Some("Basic Y3lmczp3cm9uZw=="));
+
+        // all requests are denied without password
+        let server = WebDAVServer::new(WebDAVConfig::default());
+        assert!(!server.check_auth(&ok));
+
+        let mut config = WebDAVConfig::default();
+        config.password = Some("secret".to_owned());
+        let server = WebDAVServer::new(config);
+        assert!(server.check_auth(&ok));
+        assert!(!server.check_auth(&wrong));
+        assert!(!server.check_auth(&new_request(None)));
+    }
+}
@@ -0,0 +1,212 @@
+use cyfs_base::*;
+use cyfs_lib::*;
+
+use std::path::Path;
+
+pub(crate) struct WebDAVEntry {
+    pub name: String,
+    pub object_id: ObjectId,
+    pub is_collection: bool,
+    pub size: u64,
+
+    // bucky time, zero if unknown
+    pub modified: u64,
+}
+
+impl WebDAVEntry {
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.object_id)
+    }
+}
+
+// Collections are the object_maps under the root path of global state, and the resources are File objects
+pub(crate) struct WebDAVStorage {
+    files: StateFileStorage,
+    root_path: String,
+}
+
+impl WebDAVStorage {
+    pub fn new(stack: SharedCyfsStack, dec_id: ObjectId, root_path: String, chunk_size: u32) -> Self {
+        Self {
+            files: StateFileStorage::new(stack, dec_id, chunk_size),
+            root_path,
+        }
+    }
+
+    // the path is relative to root, such as /docs/a.txt, and "/" is the root collection
+    pub fn state_path(&self, path: &str) -> String {
+        let path = path.trim_matches('/');
+        if path.is_empty() {
+            self.root_path.clone()
+        } else {
+            format!("{}/{}", self.root_path, path)
+        }
+    }
+
+    fn state_stub(&self) -> GlobalStateStub {
+        self.files.state_stub()
+    }
+
+    pub async fn init(&self) -> BuckyResult<()> {
+        let op_env = self.state_stub().create_path_op_env().await?;
+        match op_env.get_by_path(&self.root_path).await? {
+            Some(id) if id.obj_type_code() == ObjectTypeCode::ObjectMap => {
+                let _ = op_env.abort().await;
+                Ok(())
+            }
+            Some(id) => {
+                let _ = op_env.abort().await;
+                let msg = format!(
+                    "webdav root path is not an object_map! path={}, value={}",
+                    self.root_path, id
+                );
+                error!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg))
+            }
+            None => {
+                op_env
+                    .create_new_with_path(&self.root_path, ObjectMapSimpleContentType::Map)
+                    .await?;
+                op_env.commit().await?;
+
+                info!("create webdav root path: {}", self.root_path);
+                Ok(())
+            }
+        }
+    }
+
+    async fn load_entry(&self, name: String, object_id: ObjectId) -> BuckyResult<Option<WebDAVEntry>> {
+        let entry = match object_id.obj_type_code() {
+            ObjectTypeCode::ObjectMap => WebDAVEntry {
+                name,
+                object_id,
+                is_collection: true,
+                size: 0,
+                modified: 0,
+            },
+            ObjectTypeCode::File => {
+                let file = self.files.load_file(&object_id).await?;
+
+                WebDAVEntry {
+                    name,
+                    object_id,
+                    is_collection: false,
+                    size: file.len(),
+                    modified: file.desc().create_time(),
+                }
+            }
+            _ => {
+                debug!("non file object will be ignored in webdav: {}, {}", name, object_id);
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(entry))
+    }
+
+    pub async fn stat(&self, path: &str) -> BuckyResult<Option<WebDAVEntry>> {
+        let name = path.trim_matches('/').rsplit('/').next().unwrap_or("").to_owned();
+        match self.files.get_by_path(&self.state_path(path)).await? {
+            Some(id) => self.load_entry(name, id).await,
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list(&self, path: &str) -> BuckyResult<Vec<WebDAVEntry>> {
+        let list = self.files.list(&self.state_path(path)).await?;
+
+        let mut entries = Vec::with_capacity(list.len());
+        for (name, id) in list {
+            if let Some(entry) = self.load_entry(name, id).await? {
+                entries.push(entry);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    pub async fn read(
+        &self,
+        file_id: &ObjectId,
+        range: Option<std::ops::Range<u64>>,
+    ) -> BuckyResult<NDNGetDataOutputResponse> {
+        self.files.read(file_id, range).await
+    }
+
+    // Publish the local file and set the File object to the path, return the prev value
+    pub async fn write(&self, path: &str, local_path: &Path) -> BuckyResult<Option<ObjectId>> {
+        let file_id = self.files.publish(local_path).await.map_err(|e| {
+            error!("publish file for webdav failed! path={}, {}", path, e);
+            e
+        })?;
+
+        let prev = self.files.set(&self.state_path(path), &file_id).await?;
+
+        info!("webdav put file: path={}, file={}", path, file_id);
+        Ok(prev)
+    }
+
+    pub async fn create_collection(&self, path: &str) -> BuckyResult<()> {
+        self.files.create_dir(&self.state_path(path)).await?;
+
+        info!("webdav create collection: {}", path);
+        Ok(())
+    }
+
+    pub async fn remove(&self, path: &str) -> BuckyResult<()> {
+        if self.files.remove(&self.state_path(path)).await?.is_none() {
+            let msg = format!("webdav path not found: {}", path);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+        }
+
+        info!("webdav remove: {}", path);
+        Ok(())
+    }
+
+    // Move the value in one op_env, return whether the dest is overwritten
+    pub async fn rename(&self, from: &str, to: &str, overwrite: bool) -> BuckyResult<bool> {
+        let from_path = self.state_path(from);
+        let to_path = self.state_path(to);
+
+        let op_env = self.state_stub().create_path_op_env().await?;
+        let ret = async {
+            let exists = op_env.get_by_path(&to_path).await?.is_some();
+            if exists && !overwrite {
+                let msg = format!("webdav move dest already exists: {}", to);
+                warn!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::AlreadyExists, msg));
+            }
+
+            let value = match op_env.remove_with_path(&from_path, None).await? {
+                Some(value) => value,
+                None => {
+                    let msg = format!("webdav move source not found: {}", from);
+                    warn!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+                }
+            };
+
+            if exists {
+                op_env.remove_with_path(&to_path, None).await?;
+            }
+            op_env.insert_with_path(&to_path, &value).await?;
+
+            Ok(exists)
+        }
+        .await;
+
+        match ret {
+            Ok(exists) => {
+                op_env.commit().await?;
+
+                info!("webdav move: {} -> {}", from, to);
+                Ok(exists)
+            }
+            Err(e) => {
+                let _ = op_env.abort().await;
+                Err(e)
+            }
+        }
+    }
+}
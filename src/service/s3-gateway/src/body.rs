@@ -0,0 +1,196 @@
+use crate::auth::{S3ChunkSigner, S3Payload};
+use cyfs_base::*;
+
+use async_std::io::{BufReader, Read};
+use async_std::prelude::*;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// max size of single chunk in aws-chunked payload
+const MAX_CHUNK_SIZE: usize = 1024 * 1024 * 16;
+
+// The request body saved in temp file, will be removed on drop
+pub struct S3Body {
+    pub path: PathBuf,
+    pub len: u64,
+
+    // sha256 hex string of the decoded content
+    pub sha256: String,
+}
+
+impl Drop for S3Body {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("remove s3 body temp file failed! file={}, {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+static TEMP_INDEX: AtomicU64 = AtomicU64::new(0);
+
+fn temp_file_path(temp_dir: &Path) -> PathBuf {
+    let index = TEMP_INDEX.fetch_add(1, Ordering::SeqCst);
+    temp_dir.join(format!("body-{}-{}", bucky_time_now(), index))
+}
+
+struct BodyWriter {
+    file: async_std::fs::File,
+    hasher: Sha256,
+    len: u64,
+}
+
+impl BodyWriter {
+    async fn write(&mut self, data: &[u8]) -> BuckyResult<()> {
+        self.file.write_all(data).await?;
+        self.hasher.update(data);
+        self.len += data.len() as u64;
+        Ok(())
+    }
+}
+
+// Save the body to temp file and verify the content with the signed payload
+pub async fn receive_body(
+    reader: impl Read + Unpin,
+    payload: S3Payload,
+    decoded_len: Option<u64>,
+    temp_dir: &Path,
+) -> BuckyResult<S3Body> {
+    let path = temp_file_path(temp_dir);
+    let file = async_std::fs::File::create(&path).await.map_err(|e| {
+        let msg = format!("create s3 body temp file failed! file={}, {}", path.display(), e);
+        error!("{}", msg);
+        BuckyError::new(BuckyErrorCode::IoError, msg)
+    })?;
+
+    // created body will remove the temp file on any error below
+    let mut body = S3Body {
+        path,
+        len: 0,
+        sha256: String::new(),
+    };
+
+    let mut writer = BodyWriter {
+        file,
+        hasher: Sha256::new(),
+        len: 0,
+    };
+
+    let mut reader = BufReader::new(reader);
+    let expected_hash = match payload {
+        S3Payload::Streaming(signer) => {
+            receive_chunked(&mut reader, signer, &mut writer).await?;
+            None
+        }
+        S3Payload::Sha256(hash) => {
+            receive_plain(&mut reader, &mut writer).await?;
+            Some(hash)
+        }
+        S3Payload::Unsigned => {
+            receive_plain(&mut reader, &mut writer).await?;
+            None
+        }
+    };
+
+    writer.file.flush().await?;
+
+    body.len = writer.len;
+    body.sha256 = hex::encode(writer.hasher.finalize());
+
+    if let Some(hash) = expected_hash {
+        if hash != body.sha256 {
+            let msg = format!(
+                "s3 body sha256 not match! expected={}, got={}",
+                hash, body.sha256
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidData, msg));
+        }
+    }
+
+    if let Some(len) = decoded_len {
+        if len != body.len {
+            let msg = format!(
+                "s3 body decoded content length not match! expected={}, got={}",
+                len, body.len
+            );
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidData, msg));
+        }
+    }
+
+    Ok(body)
+}
+
+async fn receive_plain(
+    reader: &mut (impl Read + Unpin),
+    writer: &mut BodyWriter,
+) -> BuckyResult<()> {
+    let mut buf = vec![0u8; 1024 * 64];
+    loop {
+        let len = reader.read(&mut buf).await?;
+        if len == 0 {
+            break Ok(());
+        }
+
+        writer.write(&buf[..len]).await?;
+    }
+}
+
+// aws-chunked: {hex_size};chunk-signature={signature}\r\n{data}\r\n ... 0;chunk-signature={signature}\r\n\r\n
+async fn receive_chunked<R: Read + Unpin>(
+    reader: &mut BufReader<R>,
+    mut signer: S3ChunkSigner,
+    writer: &mut BodyWriter,
+) -> BuckyResult<()> {
+    let mut line = String::new();
+    let mut buf = vec![];
+    loop {
+        line.clear();
+        reader.read_line(&mut line).await?;
+        let (size, signature) = parse_chunk_header(&line)?;
+
+        buf.resize(size, 0);
+        reader.read_exact(&mut buf).await?;
+        signer.verify_chunk(&signature, &buf)?;
+
+        if size == 0 {
+            break Ok(());
+        }
+
+        writer.write(&buf).await?;
+
+        let mut crlf = [0u8; 2];
+        reader.read_exact(&mut crlf).await?;
+        if &crlf != b"\r\n" {
+            let msg = "invalid aws-chunked payload, chunk not end with crlf".to_owned();
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidFormat, msg));
+        }
+    }
+}
+
+fn parse_chunk_header(line: &str) -> BuckyResult<(usize, String)> {
+    let line = line.trim_end_matches(|c| c == '\r' || c == '\n');
+    let ret = line.split_once(";chunk-signature=").and_then(|(size, signature)| {
+        usize::from_str_radix(size, 16)
+            .ok()
+            .map(|size| (size, signature.to_owned()))
+    });
+
+    match ret {
+        Some((size, _)) if size > MAX_CHUNK_SIZE => {
+            let msg = format!("aws-chunked chunk size out of limit! size={}", size);
+            warn!("{}", msg);
+            Err(BuckyError::new(BuckyErrorCode::OutOfLimit, msg))
+        }
+        Some(ret) => Ok(ret),
+        None => {
+            let msg = format!("invalid aws-chunked chunk header: {:?}", line);
+            warn!("{}", msg);
+            Err(BuckyError::new(BuckyErrorCode::InvalidFormat, msg))
+        }
+    }
+}
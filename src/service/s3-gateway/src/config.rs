@@ -0,0 +1,136 @@
+use cyfs_base::*;
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::path::Path;
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3CredentialConfig {
+    // the zone device which the credential belongs to
+    pub device: String,
+
+    // default is the device id
+    pub access_key: Option<String>,
+    pub secret_key: String,
+}
+
+/*
+listen = "127.0.0.1:1332"
+region = "us-east-1"
+dec_id = "9tGpLNnQnReSYJhrgrLMjz2bFoRDVKP9Dp8Crqy1bjzY"
+root_path = "/s3"
+
+[[credential]]
+device = "5aSixgLwnWbmcDKwBcTjiMbMcS4SEKZYr3F2PZuLE1Cr"
+secret_key = "xxxx"
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct S3GatewayConfig {
+    pub listen: String,
+    pub region: String,
+
+    // the dec whose global state stores the buckets, default is system dec
+    pub dec_id: Option<String>,
+
+    // buckets are the direct children of this global state path
+    pub root_path: String,
+
+    // chunk size in bytes used to publish the uploaded objects
+    pub chunk_size: u32,
+
+    pub temp_dir: Option<String>,
+
+    #[serde(rename = "credential")]
+    pub credentials: Vec<S3CredentialConfig>,
+}
+
+impl Default for S3GatewayConfig {
+    fn default() -> Self {
+        Self {
+            listen: format!("127.0.0.1:{}", S3_GATEWAY_PORT),
+            region: "us-east-1".to_owned(),
+            dec_id: None,
+            root_path: "/s3".to_owned(),
+            chunk_size: 1024 * 1024 * 4,
+            temp_dir: None,
+            credentials: vec![],
+        }
+    }
+}
+
+impl S3GatewayConfig {
+    pub fn load() -> Self {
+        let config_file = cyfs_util::get_service_config_dir(S3_GATEWAY_NAME).join(CONFIG_FILE_NAME);
+        let config = Self::load_from_file(&config_file)
+            .map_err(|e| {
+                error!("load config file {} err {}, use default", config_file.display(), e);
+                e
+            })
+            .unwrap_or(Self::default());
+
+        info!(
+            "final use s3 gateway config: listen={}, region={}, dec={:?}, root={}, credentials={}",
+            config.listen,
+            config.region,
+            config.dec_id,
+            config.root_path,
+            config.credentials.len()
+        );
+        config
+    }
+
+    fn load_from_file(path: &Path) -> BuckyResult<Self> {
+        if !path.exists() {
+            return Err(BuckyError::from(BuckyErrorCode::NotFound));
+        }
+
+        toml::from_slice(&std::fs::read(path)?).map_err(|e| {
+            let msg = format!("parse s3 gateway config err {}", e);
+            error!("{}", &msg);
+            BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+        })
+    }
+
+    pub fn dec_id(&self) -> BuckyResult<ObjectId> {
+        match &self.dec_id {
+            Some(id) => ObjectId::from_str(id).map_err(|e| {
+                let msg = format!("invalid dec_id in s3 gateway config: {}, {}", id, e);
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+            }),
+            None => Ok(cyfs_core::get_system_dec_app().to_owned()),
+        }
+    }
+
+    // root path without trailing '/', such as /s3
+    pub fn root_path(&self) -> String {
+        format!("/{}", self.root_path.trim_matches('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let config_str = r#"
+        listen = "0.0.0.0:1332"
+        root_path = "/backup/"
+
+        [[credential]]
+        device = "5aSixgLwnWbmcDKwBcTjiMbMcS4SEKZYr3F2PZuLE1Cr"
+        secret_key = "secret"
+        "#;
+
+        let config: S3GatewayConfig = toml::from_str(config_str).unwrap();
+        assert_eq!(config.listen, "0.0.0.0:1332");
+        assert_eq!(config.region, "us-east-1");
+        assert_eq!(config.root_path(), "/backup");
+        assert_eq!(config.credentials.len(), 1);
+        assert!(config.credentials[0].access_key.is_none());
+    }
+}
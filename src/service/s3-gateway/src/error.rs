@@ -0,0 +1,105 @@
+use crate::xml::XmlWriter;
+use cyfs_base::*;
+
+use tide::{Response, StatusCode};
+
+// Error in s3 protocol, with the s3 error code such as NoSuchKey
+#[derive(Debug)]
+pub struct S3Error {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+}
+
+pub type S3Result<T> = Result<T, S3Error>;
+
+impl S3Error {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn access_denied(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::Forbidden, "AccessDenied", message)
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BadRequest, "InvalidArgument", message)
+    }
+
+    pub fn not_implemented(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NotImplemented, "NotImplemented", message)
+    }
+
+    // not_found_code is the s3 code for NotFound in current context, such as NoSuchBucket or NoSuchUpload,
+    // and InnerPathNotFound is always NoSuchKey
+    pub fn from_bucky(e: BuckyError, not_found_code: &'static str) -> Self {
+        let (status, code) = match e.code() {
+            BuckyErrorCode::NotFound => (StatusCode::NotFound, not_found_code),
+            BuckyErrorCode::InnerPathNotFound => (StatusCode::NotFound, "NoSuchKey"),
+            BuckyErrorCode::AlreadyExists => (StatusCode::Conflict, "BucketAlreadyOwnedByYou"),
+            BuckyErrorCode::Conflict => (StatusCode::Conflict, "BucketNotEmpty"),
+            BuckyErrorCode::PermissionDenied => (StatusCode::Forbidden, "AccessDenied"),
+            BuckyErrorCode::InvalidSignature => (StatusCode::Forbidden, "SignatureDoesNotMatch"),
+            BuckyErrorCode::InvalidParam | BuckyErrorCode::InvalidInput => {
+                (StatusCode::BadRequest, "InvalidArgument")
+            }
+            BuckyErrorCode::InvalidFormat => (StatusCode::BadRequest, "MalformedXML"),
+            BuckyErrorCode::InvalidData => (StatusCode::BadRequest, "XAmzContentSHA256Mismatch"),
+            BuckyErrorCode::Unmatch => (StatusCode::BadRequest, "InvalidPart"),
+            BuckyErrorCode::OutOfLimit => (StatusCode::BadRequest, "EntityTooLarge"),
+            BuckyErrorCode::RangeNotSatisfiable => {
+                (StatusCode::RequestedRangeNotSatisfiable, "InvalidRange")
+            }
+            BuckyErrorCode::NotSupport
+            | BuckyErrorCode::UnSupport
+            | BuckyErrorCode::NotImplement => (StatusCode::NotImplemented, "NotImplemented"),
+            _ => (StatusCode::InternalServerError, "InternalError"),
+        };
+
+        Self::new(status, code, e.msg().to_owned())
+    }
+
+    // Errors on receiving and verifying the request body, the io errors here are the broken body
+    // from client or the temp file failures, never the NotFound of s3 resources
+    pub fn from_body_error(e: BuckyError) -> Self {
+        let (status, code) = match e.code() {
+            BuckyErrorCode::InvalidSignature => (StatusCode::Forbidden, "SignatureDoesNotMatch"),
+            BuckyErrorCode::InvalidData => (StatusCode::BadRequest, "XAmzContentSHA256Mismatch"),
+            BuckyErrorCode::OutOfLimit => (StatusCode::BadRequest, "EntityTooLarge"),
+            BuckyErrorCode::InvalidFormat => (StatusCode::BadRequest, "InvalidRequest"),
+            BuckyErrorCode::UnexpectedEof
+            | BuckyErrorCode::ConnectionReset
+            | BuckyErrorCode::ConnectionAborted
+            | BuckyErrorCode::BrokenPipe
+            | BuckyErrorCode::Interrupted
+            | BuckyErrorCode::Timeout => (StatusCode::BadRequest, "IncompleteBody"),
+            _ => (StatusCode::InternalServerError, "InternalError"),
+        };
+
+        Self::new(status, code, e.msg().to_owned())
+    }
+
+    pub fn into_response(self, resource: &str) -> Response {
+        let mut writer = XmlWriter::new("Error");
+        writer
+            .elem("Code", self.code)
+            .elem("Message", &self.message)
+            .elem("Resource", resource);
+
+        let mut resp = Response::new(self.status);
+        resp.set_body(writer.finish());
+        resp.set_content_type("application/xml");
+
+        resp
+    }
+}
+
+impl std::fmt::Display for S3Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}: {}", self.status, self.code, self.message)
+    }
+}
@@ -0,0 +1,71 @@
+#![windows_subsystem = "windows"]
+
+mod auth;
+mod body;
+mod config;
+mod error;
+mod multipart;
+mod server;
+mod storage;
+mod xml;
+
+#[macro_use]
+extern crate log;
+
+use auth::S3Auth;
+use config::S3GatewayConfig;
+use cyfs_base::*;
+use cyfs_lib::*;
+use multipart::S3MultipartManager;
+use server::S3Gateway;
+use storage::S3Storage;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+async fn run() -> BuckyResult<()> {
+    let config = S3GatewayConfig::load();
+    let dec_id = config.dec_id()?;
+
+    let stack = SharedCyfsStack::open_default(Some(dec_id.clone())).await?;
+    stack.wait_online(None).await?;
+
+    let temp_dir = match &config.temp_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => cyfs_util::get_temp_path().join(S3_GATEWAY_NAME),
+    };
+    std::fs::create_dir_all(&temp_dir).map_err(|e| {
+        let msg = format!("create temp dir failed! dir={}, {}", temp_dir.display(), e);
+        error!("{}", msg);
+        BuckyError::new(BuckyErrorCode::IoError, msg)
+    })?;
+
+    let auth = S3Auth::load(&stack, &config.region, &config.credentials).await?;
+    let storage = S3Storage::new(stack.clone(), dec_id, config.root_path(), config.chunk_size);
+    let multipart = S3MultipartManager::new(temp_dir.clone());
+
+    let gateway = S3Gateway::new(config.region.clone(), temp_dir, auth, storage, multipart);
+    Arc::new(gateway).run(&config.listen).await
+}
+
+#[async_std::main]
+async fn main() {
+    cyfs_util::process::check_cmd_and_exec(S3_GATEWAY_NAME);
+
+    cyfs_debug::CyfsLoggerBuilder::new_service(S3_GATEWAY_NAME)
+        .level("info")
+        .console("info")
+        .enable_bdt(Some("warn"), Some("warn"))
+        .build()
+        .unwrap()
+        .start();
+
+    cyfs_debug::PanicBuilder::new("cyfs-service", S3_GATEWAY_NAME)
+        .build()
+        .start();
+
+    if let Err(e) = run().await {
+        error!("s3 gateway exit with error: {}", e);
+        std::process::exit(e.code().as_u16() as i32);
+    }
+}
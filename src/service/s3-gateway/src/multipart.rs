@@ -0,0 +1,252 @@
+use crate::body::S3Body;
+use crate::xml;
+use cyfs_base::*;
+
+use async_std::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const UPLOAD_FILE_NAME: &str = "upload.toml";
+const MAX_PART_NUMBER: u32 = 10000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3UploadInfo {
+    pub bucket: String,
+    pub key: String,
+
+    // bucky time
+    pub create_time: u64,
+}
+
+pub struct S3PartInfo {
+    pub number: u32,
+    pub etag: String,
+    pub size: u64,
+    pub last_modified: u64,
+}
+
+// The parts of the multipart uploads are saved in local dir until completed or aborted,
+// {data_dir}/multipart/{upload_id}/upload.toml and part-{number}
+pub struct S3MultipartManager {
+    dir: PathBuf,
+    temp_dir: PathBuf,
+    index: AtomicU64,
+}
+
+impl S3MultipartManager {
+    pub fn new(temp_dir: PathBuf) -> Self {
+        let dir = cyfs_util::get_service_data_dir(S3_GATEWAY_NAME).join("multipart");
+
+        Self {
+            dir,
+            temp_dir,
+            index: AtomicU64::new(0),
+        }
+    }
+
+    fn upload_dir(&self, upload_id: &str) -> BuckyResult<PathBuf> {
+        // upload_id is generated by us, so only hex string is valid
+        if upload_id.is_empty() || !upload_id.chars().all(|c| c.is_ascii_hexdigit()) {
+            let msg = format!("invalid s3 upload id: {}", upload_id);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+        }
+
+        let dir = self.dir.join(upload_id);
+        if !dir.is_dir() {
+            let msg = format!("s3 upload not found: {}", upload_id);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+        }
+
+        Ok(dir)
+    }
+
+    fn part_path(dir: &Path, number: u32) -> PathBuf {
+        dir.join(format!("part-{:05}", number))
+    }
+
+    fn part_hash_path(dir: &Path, number: u32) -> PathBuf {
+        dir.join(format!("part-{:05}.sha256", number))
+    }
+
+    fn check_part_number(number: u32) -> BuckyResult<()> {
+        if number == 0 || number > MAX_PART_NUMBER {
+            let msg = format!("invalid s3 part number: {}", number);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        Ok(())
+    }
+
+    pub async fn create(&self, bucket: &str, key: &str) -> BuckyResult<String> {
+        let info = S3UploadInfo {
+            bucket: bucket.to_owned(),
+            key: key.to_owned(),
+            create_time: bucky_time_now(),
+        };
+
+        let index = self.index.fetch_add(1, Ordering::SeqCst);
+        let seed = format!("{}/{}/{}/{}", bucket, key, info.create_time, index);
+        let upload_id = hash_data(seed.as_bytes()).to_hex_string();
+
+        let dir = self.dir.join(&upload_id);
+        async_std::fs::create_dir_all(&dir).await.map_err(|e| {
+            let msg = format!("create s3 upload dir failed! dir={}, {}", dir.display(), e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
+
+        let content = toml::to_string(&info).unwrap();
+        async_std::fs::write(dir.join(UPLOAD_FILE_NAME), content).await?;
+
+        info!("create s3 multipart upload: bucket={}, key={}, upload_id={}", bucket, key, upload_id);
+        Ok(upload_id)
+    }
+
+    pub async fn load_upload(&self, upload_id: &str) -> BuckyResult<S3UploadInfo> {
+        let dir = self.upload_dir(upload_id)?;
+        let content = async_std::fs::read(dir.join(UPLOAD_FILE_NAME)).await?;
+
+        toml::from_slice(&content).map_err(|e| {
+            let msg = format!("parse s3 upload info failed! upload_id={}, {}", upload_id, e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::InvalidFormat, msg)
+        })
+    }
+
+    pub async fn upload_part(&self, upload_id: &str, number: u32, body: S3Body) -> BuckyResult<String> {
+        Self::check_part_number(number)?;
+        let dir = self.upload_dir(upload_id)?;
+
+        // the temp dir maybe on another file system, then fallback to copy
+        let path = Self::part_path(&dir, number);
+        if async_std::fs::rename(&body.path, &path).await.is_err() {
+            async_std::fs::copy(&body.path, &path).await.map_err(|e| {
+                let msg = format!(
+                    "save s3 part failed! upload_id={}, part={}, {}",
+                    upload_id, number, e
+                );
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::IoError, msg)
+            })?;
+        }
+        async_std::fs::write(Self::part_hash_path(&dir, number), &body.sha256).await?;
+
+        debug!(
+            "upload s3 part: upload_id={}, part={}, size={}",
+            upload_id, number, body.len
+        );
+        Ok(format!("\"{}\"", body.sha256))
+    }
+
+    pub async fn list_parts(&self, upload_id: &str) -> BuckyResult<Vec<S3PartInfo>> {
+        let dir = self.upload_dir(upload_id)?;
+
+        let mut parts = vec![];
+        let mut entries = async_std::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next().await {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let number = match name.strip_prefix("part-").and_then(|v| v.parse::<u32>().ok()) {
+                Some(number) => number,
+                None => continue,
+            };
+
+            let meta = entry.metadata().await?;
+            let hash = async_std::fs::read_to_string(Self::part_hash_path(&dir, number)).await?;
+            let last_modified = meta
+                .modified()
+                .ok()
+                .and_then(|v| v.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|v| unix_time_to_bucky_time(v.as_micros() as u64))
+                .unwrap_or(0);
+
+            parts.push(S3PartInfo {
+                number,
+                etag: format!("\"{}\"", hash.trim()),
+                size: meta.len(),
+                last_modified,
+            });
+        }
+
+        parts.sort_by_key(|v| v.number);
+        Ok(parts)
+    }
+
+    // Parse the CompleteMultipartUpload xml and merge the parts into temp file in order
+    pub async fn complete(&self, upload_id: &str, doc: &str) -> BuckyResult<PathBuf> {
+        let dir = self.upload_dir(upload_id)?;
+
+        let mut parts = vec![];
+        for part in xml::elements(doc, "Part") {
+            let number = xml::element(part, "PartNumber").and_then(|v| v.parse::<u32>().ok());
+            let etag = xml::element(part, "ETag");
+            match (number, etag) {
+                (Some(number), Some(etag)) => parts.push((number, etag)),
+                _ => {
+                    let msg = format!("invalid part in complete multipart upload: {}", part);
+                    warn!("{}", msg);
+                    return Err(BuckyError::new(BuckyErrorCode::InvalidFormat, msg));
+                }
+            }
+        }
+
+        if parts.is_empty() {
+            let msg = format!("complete multipart upload without parts: {}", upload_id);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidFormat, msg));
+        }
+
+        let mut prev = 0;
+        for (number, etag) in &parts {
+            if *number <= prev {
+                let msg = format!("parts not in ascending order: {} after {}", number, prev);
+                warn!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+            }
+            prev = *number;
+
+            let hash = async_std::fs::read_to_string(Self::part_hash_path(&dir, *number))
+                .await
+                .unwrap_or_default();
+            if hash.trim() != etag.trim_matches('"') {
+                let msg = format!("part not match! upload_id={}, part={}, etag={}", upload_id, number, etag);
+                warn!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+            }
+        }
+
+        let path = self.temp_dir.join(format!("multipart-{}", upload_id));
+        let mut file = async_std::fs::File::create(&path).await?;
+        for (number, _) in &parts {
+            let mut part = async_std::fs::File::open(Self::part_path(&dir, *number)).await?;
+            if let Err(e) = async_std::io::copy(&mut part, &mut file).await {
+                let msg = format!(
+                    "merge s3 part failed! upload_id={}, part={}, {}",
+                    upload_id, number, e
+                );
+                error!("{}", msg);
+                let _ = async_std::fs::remove_file(&path).await;
+                return Err(BuckyError::new(BuckyErrorCode::IoError, msg));
+            }
+        }
+        file.flush().await?;
+
+        Ok(path)
+    }
+
+    pub async fn remove(&self, upload_id: &str) -> BuckyResult<()> {
+        let dir = self.upload_dir(upload_id)?;
+        async_std::fs::remove_dir_all(&dir).await.map_err(|e| {
+            let msg = format!("remove s3 upload dir failed! dir={}, {}", dir.display(), e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
+
+        info!("remove s3 multipart upload: {}", upload_id);
+        Ok(())
+    }
+}
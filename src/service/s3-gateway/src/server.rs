@@ -0,0 +1,710 @@
+use crate::auth::{S3Auth, S3Payload};
+use crate::body::{receive_body, S3Body};
+use crate::error::*;
+use crate::multipart::S3MultipartManager;
+use crate::storage::*;
+use crate::xml::XmlWriter;
+use cyfs_base::*;
+use cyfs_lib::{RequestorRangeHelper, WebHelper};
+
+use async_std::io::BufReader;
+use chrono::{DateTime, Utc};
+use http_types::Method;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tide::{Request, Response, StatusCode};
+
+const DEFAULT_MAX_KEYS: usize = 1000;
+
+const LIST_QUERY_KEYS: &[&str] = &[
+    "list-type",
+    "prefix",
+    "delimiter",
+    "marker",
+    "max-keys",
+    "continuation-token",
+    "start-after",
+    "encoding-type",
+    "fetch-owner",
+];
+
+fn iso8601_time(time: u64) -> String {
+    let time: DateTime<Utc> = bucky_time_to_system_time(time).into();
+    time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+fn xml_response(status: StatusCode, body: String) -> Response {
+    let mut resp = Response::new(status);
+    resp.set_body(body);
+    resp.set_content_type("application/xml");
+    resp
+}
+
+pub struct S3Gateway {
+    region: String,
+    temp_dir: PathBuf,
+    auth: S3Auth,
+    storage: S3Storage,
+    multipart: S3MultipartManager,
+}
+
+impl S3Gateway {
+    pub fn new(
+        region: String,
+        temp_dir: PathBuf,
+        auth: S3Auth,
+        storage: S3Storage,
+        multipart: S3MultipartManager,
+    ) -> Self {
+        Self {
+            region,
+            temp_dir,
+            auth,
+            storage,
+            multipart,
+        }
+    }
+
+    pub async fn run(self: Arc<Self>, listen: &str) -> BuckyResult<()> {
+        let mut app = tide::new();
+
+        let gateway = self.clone();
+        app.at("/").all(move |req: Request<()>| {
+            let gateway = gateway.clone();
+            async move { Ok(gateway.on_request(req).await) }
+        });
+
+        let gateway = self.clone();
+        app.at("/*").all(move |req: Request<()>| {
+            let gateway = gateway.clone();
+            async move { Ok(gateway.on_request(req).await) }
+        });
+
+        info!("s3 gateway will listen on {}", listen);
+        app.listen(listen.to_owned()).await.map_err(|e| {
+            let msg = format!("s3 gateway listen failed! addr={}, {}", listen, e);
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })
+    }
+
+    async fn on_request(&self, req: Request<()>) -> Response {
+        let resource = req.url().path().to_owned();
+        let method = req.method();
+
+        match self.process_request(req).await {
+            Ok(resp) => {
+                debug!("s3 request: {} {} -> {}", method, resource, resp.status());
+                resp
+            }
+            Err(e) => {
+                warn!("s3 request failed: {} {}, {}", method, resource, e);
+                e.into_response(&resource)
+            }
+        }
+    }
+
+    // path-style request: /{bucket}/{key}
+    fn parse_path(url: &http_types::Url) -> (Option<String>, Option<String>) {
+        let path = percent_encoding::percent_decode_str(url.path())
+            .decode_utf8_lossy()
+            .to_string();
+        let path = path.trim_start_matches('/');
+
+        match path.split_once('/') {
+            Some((bucket, key)) if !key.is_empty() => {
+                (Some(bucket.to_owned()), Some(key.to_owned()))
+            }
+            Some((bucket, _)) => (Some(bucket.to_owned()), None),
+            None if !path.is_empty() => (Some(path.to_owned()), None),
+            None => (None, None),
+        }
+    }
+
+    async fn process_request(&self, mut req: Request<()>) -> S3Result<Response> {
+        let auth = self.auth.verify(req.as_ref())?;
+
+        let (bucket, key) = Self::parse_path(req.url());
+        let querys: HashMap<String, String> = req.url().query_pairs().into_owned().collect();
+        debug!(
+            "s3 request from {}: {} {:?}/{:?}",
+            auth.identity.device_id,
+            req.method(),
+            bucket,
+            key
+        );
+
+        let payload = auth.payload;
+        match (bucket, key) {
+            (None, _) => match req.method() {
+                Method::Get => self.list_buckets(&auth.identity.access_key).await,
+                method => Err(S3Error::not_implemented(format!("{} /", method))),
+            },
+            (Some(bucket), None) => {
+                self.process_bucket(&mut req, payload, &bucket, &querys)
+                    .await
+            }
+            (Some(bucket), Some(key)) => {
+                self.process_object(&mut req, payload, &bucket, &key, &querys)
+                    .await
+            }
+        }
+    }
+
+    async fn receive_body(&self, req: &mut Request<()>, payload: S3Payload) -> S3Result<S3Body> {
+        let decoded_len = match &payload {
+            S3Payload::Streaming(_) => req
+                .header("x-amz-decoded-content-length")
+                .and_then(|v| v.last().as_str().parse().ok()),
+            _ => None,
+        };
+
+        let body = req.take_body();
+        receive_body(body, payload, decoded_len, &self.temp_dir)
+            .await
+            .map_err(S3Error::from_body_error)
+    }
+
+    async fn receive_xml(&self, req: &mut Request<()>, payload: S3Payload) -> S3Result<String> {
+        let body = self.receive_body(req, payload).await?;
+        async_std::fs::read_to_string(&body.path)
+            .await
+            .map_err(|e| S3Error::new(StatusCode::BadRequest, "MalformedXML", e.to_string()))
+    }
+
+    async fn list_buckets(&self, access_key: &str) -> S3Result<Response> {
+        let buckets = self
+            .storage
+            .list_buckets()
+            .await
+            .map_err(|e| S3Error::from_bucky(e, "NoSuchBucket"))?;
+
+        let mut writer = XmlWriter::new("ListAllMyBucketsResult");
+        writer
+            .start("Owner")
+            .elem("ID", access_key)
+            .elem("DisplayName", access_key)
+            .end();
+
+        writer.start("Buckets");
+        for bucket in buckets {
+            writer
+                .start("Bucket")
+                .elem("Name", &bucket.name)
+                .elem("CreationDate", iso8601_time(bucket.create_time))
+                .end();
+        }
+        writer.end();
+
+        Ok(xml_response(StatusCode::Ok, writer.finish()))
+    }
+
+    async fn process_bucket(
+        &self,
+        req: &mut Request<()>,
+        payload: S3Payload,
+        bucket: &str,
+        querys: &HashMap<String, String>,
+    ) -> S3Result<Response> {
+        let from_bucky = |e| S3Error::from_bucky(e, "NoSuchBucket");
+
+        match req.method() {
+            Method::Put => {
+                // the location constraint in body is ignored, the bucket is always in current zone
+                let _ = self.receive_body(req, payload).await?;
+                self.storage.create_bucket(bucket).await.map_err(from_bucky)?;
+
+                let mut resp = Response::new(StatusCode::Ok);
+                resp.insert_header("Location", format!("/{}", bucket));
+                Ok(resp)
+            }
+            Method::Head => {
+                self.storage.head_bucket(bucket).await.map_err(from_bucky)?;
+
+                let mut resp = Response::new(StatusCode::Ok);
+                resp.insert_header("x-amz-bucket-region", self.region.as_str());
+                Ok(resp)
+            }
+            Method::Delete => {
+                self.storage.delete_bucket(bucket).await.map_err(from_bucky)?;
+                Ok(Response::new(StatusCode::NoContent))
+            }
+            Method::Get if querys.contains_key("location") => {
+                self.storage.head_bucket(bucket).await.map_err(from_bucky)?;
+
+                let body = format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<LocationConstraint xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">{}</LocationConstraint>",
+                    WebHelper::xml_escape(&self.region)
+                );
+                Ok(xml_response(StatusCode::Ok, body))
+            }
+            Method::Get if Self::is_list_request(querys) => self.list_objects(bucket, querys).await,
+            Method::Post if querys.contains_key("delete") => {
+                let doc = self.receive_xml(req, payload).await?;
+                self.delete_objects(bucket, &doc).await
+            }
+            method => Err(S3Error::not_implemented(format!(
+                "{} /{}?{}",
+                method,
+                bucket,
+                req.url().query().unwrap_or("")
+            ))),
+        }
+    }
+
+    // other sub resources of bucket such as ?acl and ?uploads are not supported
+    fn is_list_request(querys: &HashMap<String, String>) -> bool {
+        querys.keys().all(|key| LIST_QUERY_KEYS.contains(&key.as_str()))
+    }
+
+    async fn list_objects(&self, bucket: &str, querys: &HashMap<String, String>) -> S3Result<Response> {
+        let v2 = querys.get("list-type").map(|v| v.as_str()) == Some("2");
+
+        let max_keys = match querys.get("max-keys") {
+            Some(v) => v
+                .parse::<usize>()
+                .map_err(|_| S3Error::invalid_argument(format!("invalid max-keys: {}", v)))?,
+            None => DEFAULT_MAX_KEYS,
+        };
+
+        // continuation-token is the hex of the last returned key
+        let marker = if v2 {
+            match querys.get("continuation-token") {
+                Some(token) => {
+                    let key = hex::decode(token)
+                        .ok()
+                        .and_then(|v| String::from_utf8(v).ok())
+                        .ok_or_else(|| {
+                            S3Error::invalid_argument(format!("invalid continuation-token: {}", token))
+                        })?;
+                    Some(key)
+                }
+                None => querys.get("start-after").cloned(),
+            }
+        } else {
+            querys.get("marker").cloned()
+        };
+
+        let list_req = S3ListRequest {
+            prefix: querys.get("prefix").cloned().unwrap_or_default(),
+            delimiter: querys.get("delimiter").cloned(),
+            marker,
+            max_keys: std::cmp::min(max_keys, DEFAULT_MAX_KEYS),
+        };
+
+        let result = self
+            .storage
+            .list_objects(bucket, &list_req)
+            .await
+            .map_err(|e| S3Error::from_bucky(e, "NoSuchBucket"))?;
+
+        let mut writer = XmlWriter::new("ListBucketResult");
+        writer
+            .elem("Name", bucket)
+            .elem("Prefix", &list_req.prefix)
+            .opt_elem("Delimiter", list_req.delimiter.as_ref())
+            .elem("MaxKeys", max_keys)
+            .elem("IsTruncated", result.is_truncated);
+
+        if v2 {
+            writer
+                .elem("KeyCount", result.objects.len() + result.common_prefixes.len())
+                .opt_elem("ContinuationToken", querys.get("continuation-token"))
+                .opt_elem("StartAfter", querys.get("start-after"));
+            if result.is_truncated {
+                writer.opt_elem(
+                    "NextContinuationToken",
+                    result.next_marker.as_ref().map(hex::encode),
+                );
+            }
+        } else {
+            writer.elem("Marker", list_req.marker.as_deref().unwrap_or(""));
+            if result.is_truncated && list_req.delimiter.is_some() {
+                writer.opt_elem("NextMarker", result.next_marker.as_ref());
+            }
+        }
+
+        for object in &result.objects {
+            writer
+                .start("Contents")
+                .elem("Key", &object.key)
+                .elem("LastModified", iso8601_time(object.last_modified))
+                .elem("ETag", object.etag())
+                .elem("Size", object.size)
+                .elem("StorageClass", "STANDARD")
+                .end();
+        }
+
+        for prefix in &result.common_prefixes {
+            writer.start("CommonPrefixes").elem("Prefix", prefix).end();
+        }
+
+        Ok(xml_response(StatusCode::Ok, writer.finish()))
+    }
+
+    async fn delete_objects(&self, bucket: &str, doc: &str) -> S3Result<Response> {
+        self.storage
+            .head_bucket(bucket)
+            .await
+            .map_err(|e| S3Error::from_bucky(e, "NoSuchBucket"))?;
+
+        let quiet = crate::xml::element(doc, "Quiet").as_deref() == Some("true");
+
+        let mut writer = XmlWriter::new("DeleteResult");
+        for object in crate::xml::elements(doc, "Object") {
+            let key = match crate::xml::element(object, "Key") {
+                Some(key) => key,
+                None => {
+                    return Err(S3Error::new(
+                        StatusCode::BadRequest,
+                        "MalformedXML",
+                        "object key missing in delete request",
+                    ));
+                }
+            };
+
+            match self.storage.delete_object(bucket, &key).await {
+                Ok(()) => {
+                    if !quiet {
+                        writer.start("Deleted").elem("Key", &key).end();
+                    }
+                }
+                Err(e) => {
+                    let e = S3Error::from_bucky(e, "NoSuchBucket");
+                    writer
+                        .start("Error")
+                        .elem("Key", &key)
+                        .elem("Code", e.code)
+                        .elem("Message", &e.message)
+                        .end();
+                }
+            }
+        }
+
+        Ok(xml_response(StatusCode::Ok, writer.finish()))
+    }
+
+    async fn process_object(
+        &self,
+        req: &mut Request<()>,
+        payload: S3Payload,
+        bucket: &str,
+        key: &str,
+        querys: &HashMap<String, String>,
+    ) -> S3Result<Response> {
+        let from_bucky = |e| S3Error::from_bucky(e, "NoSuchBucket");
+
+        match req.method() {
+            Method::Put => {
+                if let Some(upload_id) = querys.get("uploadId") {
+                    let number = querys
+                        .get("partNumber")
+                        .and_then(|v| v.parse::<u32>().ok())
+                        .ok_or_else(|| S3Error::invalid_argument("invalid partNumber"))?;
+                    return self
+                        .upload_part(req, payload, bucket, key, upload_id, number)
+                        .await;
+                }
+
+                if let Some(source) = req.header("x-amz-copy-source") {
+                    let source = source.last().as_str().to_owned();
+                    return self.copy_object(&source, bucket, key).await;
+                }
+
+                self.put_object(req, payload, bucket, key).await
+            }
+            Method::Get if querys.contains_key("uploadId") => {
+                self.list_parts(bucket, key, &querys["uploadId"]).await
+            }
+            Method::Get | Method::Head => self.get_object(req, bucket, key).await,
+            Method::Delete => {
+                match querys.get("uploadId") {
+                    Some(upload_id) => {
+                        self.check_upload(upload_id, bucket, key).await?;
+                        self.multipart
+                            .remove(upload_id)
+                            .await
+                            .map_err(|e| S3Error::from_bucky(e, "NoSuchUpload"))?;
+                    }
+                    None => {
+                        self.storage
+                            .delete_object(bucket, key)
+                            .await
+                            .map_err(from_bucky)?;
+                    }
+                }
+
+                Ok(Response::new(StatusCode::NoContent))
+            }
+            Method::Post if querys.contains_key("uploads") => {
+                S3Storage::check_key(key).map_err(from_bucky)?;
+                self.storage.head_bucket(bucket).await.map_err(from_bucky)?;
+
+                let upload_id = self
+                    .multipart
+                    .create(bucket, key)
+                    .await
+                    .map_err(from_bucky)?;
+
+                let mut writer = XmlWriter::new("InitiateMultipartUploadResult");
+                writer
+                    .elem("Bucket", bucket)
+                    .elem("Key", key)
+                    .elem("UploadId", &upload_id);
+                Ok(xml_response(StatusCode::Ok, writer.finish()))
+            }
+            Method::Post if querys.contains_key("uploadId") => {
+                let doc = self.receive_xml(req, payload).await?;
+                self.complete_upload(bucket, key, &querys["uploadId"], &doc)
+                    .await
+            }
+            method => Err(S3Error::not_implemented(format!(
+                "{} /{}/{}?{}",
+                method,
+                bucket,
+                key,
+                req.url().query().unwrap_or("")
+            ))),
+        }
+    }
+
+    async fn put_object(
+        &self,
+        req: &mut Request<()>,
+        payload: S3Payload,
+        bucket: &str,
+        key: &str,
+    ) -> S3Result<Response> {
+        let from_bucky = |e| S3Error::from_bucky(e, "NoSuchBucket");
+
+        let body = self.receive_body(req, payload).await?;
+        let info = if key.ends_with('/') {
+            if body.len > 0 {
+                return Err(S3Error::invalid_argument(format!(
+                    "dir marker object must be empty: {}",
+                    key
+                )));
+            }
+
+            self.storage.put_dir_marker(bucket, key).await.map_err(from_bucky)?;
+            self.storage
+                .get_object_info(bucket, key)
+                .await
+                .map_err(from_bucky)?
+        } else {
+            self.storage
+                .put_object(bucket, key, &body.path)
+                .await
+                .map_err(from_bucky)?
+        };
+
+        let mut resp = Response::new(StatusCode::Ok);
+        resp.insert_header("ETag", info.etag());
+        Ok(resp)
+    }
+
+    async fn copy_object(&self, source: &str, bucket: &str, key: &str) -> S3Result<Response> {
+        // /{bucket}/{key} with url encoded, and maybe with versionId query
+        let source = source.split('?').next().unwrap();
+        let source = percent_encoding::percent_decode_str(source)
+            .decode_utf8_lossy()
+            .to_string();
+        let (src_bucket, src_key) = source
+            .trim_start_matches('/')
+            .split_once('/')
+            .filter(|(_, key)| !key.is_empty())
+            .ok_or_else(|| S3Error::invalid_argument(format!("invalid copy source: {}", source)))?;
+
+        let info = self
+            .storage
+            .copy_object(src_bucket, src_key, bucket, key)
+            .await
+            .map_err(|e| S3Error::from_bucky(e, "NoSuchBucket"))?;
+
+        let mut writer = XmlWriter::new("CopyObjectResult");
+        writer
+            .elem("LastModified", iso8601_time(info.last_modified))
+            .elem("ETag", info.etag());
+        Ok(xml_response(StatusCode::Ok, writer.finish()))
+    }
+
+    async fn get_object(&self, req: &Request<()>, bucket: &str, key: &str) -> S3Result<Response> {
+        let info = self
+            .storage
+            .get_object_info(bucket, key)
+            .await
+            .map_err(|e| S3Error::from_bucky(e, "NoSuchBucket"))?;
+
+        let etag = info.etag();
+        if let Some(value) = req.header("if-none-match") {
+            if value.iter().any(|v| v.as_str().trim() == etag || v.as_str().trim() == "*") {
+                let mut resp = Response::new(StatusCode::NotModified);
+                resp.insert_header("ETag", etag);
+                return Ok(resp);
+            }
+        }
+
+        let range = match req.header("range") {
+            // multiple ranges are not supported by s3
+            Some(value) => {
+                RequestorRangeHelper::parse_single_range(value.last().as_str(), info.size)
+                    .map_err(|e| S3Error::from_bucky(e, "NoSuchKey"))?
+            }
+            None => None,
+        };
+
+        let mut resp = match &range {
+            Some(range) => {
+                let mut resp = Response::new(StatusCode::PartialContent);
+                resp.insert_header(
+                    "Content-Range",
+                    format!("bytes {}-{}/{}", range.start, range.end - 1, info.size),
+                );
+                resp
+            }
+            None => Response::new(StatusCode::Ok),
+        };
+
+        let len = range.as_ref().map(|v| v.end - v.start).unwrap_or(info.size);
+        resp.insert_header("ETag", etag);
+        resp.insert_header("Accept-Ranges", "bytes");
+        resp.insert_header("Last-Modified", WebHelper::http_date(info.last_modified));
+        resp.set_content_type("application/octet-stream");
+
+        if req.method() == Method::Head || len == 0 {
+            resp.insert_header("Content-Length", len.to_string());
+            return Ok(resp);
+        }
+
+        let data = self
+            .storage
+            .read_object(&info.file_id, range)
+            .await
+            .map_err(|e| S3Error::from_bucky(e, "NoSuchKey"))?;
+
+        resp.set_body(tide::Body::from_reader(BufReader::new(data.data), Some(len as usize)));
+        Ok(resp)
+    }
+
+    async fn check_upload(&self, upload_id: &str, bucket: &str, key: &str) -> S3Result<()> {
+        let info = self
+            .multipart
+            .load_upload(upload_id)
+            .await
+            .map_err(|e| S3Error::from_bucky(e, "NoSuchUpload"))?;
+
+        if info.bucket != bucket || info.key != key {
+            return Err(S3Error::new(
+                StatusCode::NotFound,
+                "NoSuchUpload",
+                format!("upload not match the object: {}", upload_id),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn upload_part(
+        &self,
+        req: &mut Request<()>,
+        payload: S3Payload,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        number: u32,
+    ) -> S3Result<Response> {
+        self.check_upload(upload_id, bucket, key).await?;
+
+        let body = self.receive_body(req, payload).await?;
+        let etag = self
+            .multipart
+            .upload_part(upload_id, number, body)
+            .await
+            .map_err(|e| S3Error::from_bucky(e, "NoSuchUpload"))?;
+
+        let mut resp = Response::new(StatusCode::Ok);
+        resp.insert_header("ETag", etag);
+        Ok(resp)
+    }
+
+    async fn list_parts(&self, bucket: &str, key: &str, upload_id: &str) -> S3Result<Response> {
+        self.check_upload(upload_id, bucket, key).await?;
+
+        let parts = self
+            .multipart
+            .list_parts(upload_id)
+            .await
+            .map_err(|e| S3Error::from_bucky(e, "NoSuchUpload"))?;
+
+        let mut writer = XmlWriter::new("ListPartsResult");
+        writer
+            .elem("Bucket", bucket)
+            .elem("Key", key)
+            .elem("UploadId", upload_id)
+            .elem("IsTruncated", false);
+        for part in parts {
+            writer
+                .start("Part")
+                .elem("PartNumber", part.number)
+                .elem("LastModified", iso8601_time(part.last_modified))
+                .elem("ETag", &part.etag)
+                .elem("Size", part.size)
+                .end();
+        }
+
+        Ok(xml_response(StatusCode::Ok, writer.finish()))
+    }
+
+    async fn complete_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        doc: &str,
+    ) -> S3Result<Response> {
+        self.check_upload(upload_id, bucket, key).await?;
+
+        let path = self
+            .multipart
+            .complete(upload_id, doc)
+            .await
+            .map_err(|e| S3Error::from_bucky(e, "NoSuchUpload"))?;
+
+        let ret = self.storage.put_object(bucket, key, &path).await;
+        let _ = async_std::fs::remove_file(&path).await;
+        let info = ret.map_err(|e| S3Error::from_bucky(e, "NoSuchBucket"))?;
+
+        if let Err(e) = self.multipart.remove(upload_id).await {
+            warn!("remove completed upload failed! upload_id={}, {}", upload_id, e);
+        }
+
+        let mut writer = XmlWriter::new("CompleteMultipartUploadResult");
+        writer
+            .elem("Location", format!("/{}/{}", bucket, key))
+            .elem("Bucket", bucket)
+            .elem("Key", key)
+            .elem("ETag", info.etag());
+        Ok(xml_response(StatusCode::Ok, writer.finish()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path() {
+        let url = http_types::Url::parse("http://127.0.0.1:1332/bucket/a/b%20c.txt").unwrap();
+        assert_eq!(
+            S3Gateway::parse_path(&url),
+            (Some("bucket".to_owned()), Some("a/b c.txt".to_owned()))
+        );
+
+        let url = http_types::Url::parse("http://127.0.0.1:1332/bucket/").unwrap();
+        assert_eq!(S3Gateway::parse_path(&url), (Some("bucket".to_owned()), None));
+
+        let url = http_types::Url::parse("http://127.0.0.1:1332/").unwrap();
+        assert_eq!(S3Gateway::parse_path(&url), (None, None));
+    }
+}
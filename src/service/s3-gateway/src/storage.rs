@@ -0,0 +1,512 @@
+use cyfs_base::*;
+use cyfs_lib::*;
+
+use std::path::Path;
+
+const MAX_KEY_LENGTH: usize = 1024;
+
+pub struct S3BucketInfo {
+    pub name: String,
+    pub create_time: u64,
+}
+
+pub struct S3ObjectInfo {
+    pub key: String,
+    pub file_id: ObjectId,
+    pub size: u64,
+
+    // bucky time
+    pub last_modified: u64,
+}
+
+impl S3ObjectInfo {
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.file_id)
+    }
+}
+
+pub struct S3ListRequest {
+    pub prefix: String,
+    pub delimiter: Option<String>,
+
+    // list the keys after the marker, exclusive
+    pub marker: Option<String>,
+    pub max_keys: usize,
+}
+
+pub struct S3ListResult {
+    pub objects: Vec<S3ObjectInfo>,
+    pub common_prefixes: Vec<String>,
+    pub is_truncated: bool,
+
+    // the last key or common prefix in result
+    pub next_marker: Option<String>,
+}
+
+enum S3ListEntry {
+    Object(String, ObjectId),
+
+    // the dir without any children, used as the dir marker object such as "photos/"
+    EmptyDir(String, ObjectId),
+
+    // the dir not walked into, only used as common prefix
+    Dir(String),
+}
+
+impl S3ListEntry {
+    fn key(&self) -> &str {
+        match self {
+            Self::Object(key, _) | Self::EmptyDir(key, _) | Self::Dir(key) => key,
+        }
+    }
+}
+
+// Buckets are the object_maps under the root path of the dec's global state, and the keys in bucket
+// are mapped to the sub paths with '/' as the separator, the values are the published File objects
+pub struct S3Storage {
+    files: StateFileStorage,
+    root_path: String,
+}
+
+impl S3Storage {
+    pub fn new(stack: SharedCyfsStack, dec_id: ObjectId, root_path: String, chunk_size: u32) -> Self {
+        Self {
+            files: StateFileStorage::new(stack, dec_id, chunk_size),
+            root_path,
+        }
+    }
+
+    pub fn check_bucket_name(name: &str) -> BuckyResult<()> {
+        let valid = name.len() >= 3
+            && name.len() <= 63
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '-')
+            && !name.starts_with('.')
+            && !name.starts_with('-');
+
+        if !valid {
+            let msg = format!("invalid bucket name: {}", name);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        Ok(())
+    }
+
+    // The key is mapped to global state path, so empty segment is not allowed except the trailing '/'
+    pub fn check_key(key: &str) -> BuckyResult<()> {
+        let path = key.strip_suffix('/').unwrap_or(key);
+        let valid = !key.is_empty()
+            && key.len() <= MAX_KEY_LENGTH
+            && path
+                .split('/')
+                .all(|seg| !seg.is_empty() && seg != "." && seg != "..");
+
+        if !valid {
+            let msg = format!("invalid object key: {}", key);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        Ok(())
+    }
+
+    fn bucket_path(&self, bucket: &str) -> String {
+        format!("{}/{}", self.root_path, bucket)
+    }
+
+    fn object_path(&self, bucket: &str, key: &str) -> String {
+        format!("{}/{}/{}", self.root_path, bucket, key.trim_end_matches('/'))
+    }
+
+    fn state_stub(&self) -> GlobalStateStub {
+        self.files.state_stub()
+    }
+
+    async fn get_by_path(&self, path: &str) -> BuckyResult<Option<ObjectId>> {
+        self.files.get_by_path(path).await
+    }
+
+    async fn list_path(&self, path: &str) -> BuckyResult<Vec<(String, ObjectId)>> {
+        self.files.list(path).await
+    }
+
+    pub async fn list_buckets(&self) -> BuckyResult<Vec<S3BucketInfo>> {
+        let list = match self.list_path(&self.root_path).await {
+            Ok(list) => list,
+            Err(e) if e.code() == BuckyErrorCode::NotFound => vec![],
+            Err(e) => return Err(e),
+        };
+
+        let mut buckets = vec![];
+        for (name, id) in list {
+            if id.obj_type_code() != ObjectTypeCode::ObjectMap {
+                continue;
+            }
+
+            // the create time is optional for list buckets
+            let create_time = match self.files.load_object(&id).await {
+                Ok(object) => ObjectMap::clone_from_slice(&object.object_raw)
+                    .map(|v| v.desc().create_time())
+                    .unwrap_or(0),
+                Err(_) => 0,
+            };
+
+            buckets.push(S3BucketInfo { name, create_time });
+        }
+
+        buckets.sort_by(|left, right| left.name.cmp(&right.name));
+        Ok(buckets)
+    }
+
+    pub async fn head_bucket(&self, bucket: &str) -> BuckyResult<()> {
+        Self::check_bucket_name(bucket)?;
+
+        let path = self.bucket_path(bucket);
+        match self.get_by_path(&path).await? {
+            Some(id) if id.obj_type_code() == ObjectTypeCode::ObjectMap => Ok(()),
+            _ => {
+                let msg = format!("bucket not found: {}", bucket);
+                warn!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::NotFound, msg))
+            }
+        }
+    }
+
+    pub async fn create_bucket(&self, bucket: &str) -> BuckyResult<()> {
+        Self::check_bucket_name(bucket)?;
+
+        self.files.create_dir(&self.bucket_path(bucket)).await?;
+
+        info!("create s3 bucket: {}", bucket);
+        Ok(())
+    }
+
+    pub async fn delete_bucket(&self, bucket: &str) -> BuckyResult<()> {
+        self.head_bucket(bucket).await?;
+
+        let path = self.bucket_path(bucket);
+        if !self.list_path(&path).await?.is_empty() {
+            let msg = format!("bucket is not empty: {}", bucket);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Conflict, msg));
+        }
+
+        self.files.remove(&path).await?;
+
+        info!("delete s3 bucket: {}", bucket);
+        Ok(())
+    }
+
+    pub async fn get_object_info(&self, bucket: &str, key: &str) -> BuckyResult<S3ObjectInfo> {
+        Self::check_key(key)?;
+
+        let path = self.object_path(bucket, key);
+        let file_id = match self.get_by_path(&path).await? {
+            Some(id) => id,
+            None => {
+                // bucket not found is NotFound, and key not found is InnerPathNotFound
+                self.head_bucket(bucket).await?;
+
+                let msg = format!("object not found: bucket={}, key={}", bucket, key);
+                warn!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::InnerPathNotFound, msg));
+            }
+        };
+
+        match (file_id.obj_type_code(), key.ends_with('/')) {
+            (ObjectTypeCode::File, false) => self.load_file_info(key, &file_id).await,
+            (ObjectTypeCode::ObjectMap, true) => Ok(S3ObjectInfo {
+                key: key.to_owned(),
+                file_id,
+                size: 0,
+                last_modified: 0,
+            }),
+            _ => {
+                let msg = format!(
+                    "object not found: bucket={}, key={}, type={:?}",
+                    bucket,
+                    key,
+                    file_id.obj_type_code()
+                );
+                warn!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::InnerPathNotFound, msg))
+            }
+        }
+    }
+
+    async fn load_file_info(&self, key: &str, file_id: &ObjectId) -> BuckyResult<S3ObjectInfo> {
+        let file = self.files.load_file(file_id).await?;
+
+        Ok(S3ObjectInfo {
+            key: key.to_owned(),
+            file_id: file_id.to_owned(),
+            size: file.len(),
+            last_modified: file.desc().create_time(),
+        })
+    }
+
+    pub async fn read_object(
+        &self,
+        file_id: &ObjectId,
+        range: Option<std::ops::Range<u64>>,
+    ) -> BuckyResult<NDNGetDataOutputResponse> {
+        self.files.read(file_id, range).await
+    }
+
+    pub async fn put_object(&self, bucket: &str, key: &str, local_path: &Path) -> BuckyResult<S3ObjectInfo> {
+        Self::check_key(key)?;
+        self.head_bucket(bucket).await?;
+
+        let file_id = self.files.publish(local_path).await.map_err(|e| {
+            error!("publish s3 object failed! bucket={}, key={}, {}", bucket, key, e);
+            e
+        })?;
+
+        self.set_object(bucket, key, &file_id).await?;
+
+        info!("put s3 object: bucket={}, key={}, file={}", bucket, key, file_id);
+        self.load_file_info(key, &file_id).await
+    }
+
+    // the key ends with '/' is the dir marker, will be an empty object_map
+    pub async fn put_dir_marker(&self, bucket: &str, key: &str) -> BuckyResult<()> {
+        Self::check_key(key)?;
+        self.head_bucket(bucket).await?;
+
+        let path = self.object_path(bucket, key);
+        let op_env = self.state_stub().create_path_op_env().await?;
+        match op_env.get_by_path(&path).await? {
+            Some(id) if id.obj_type_code() == ObjectTypeCode::ObjectMap => {
+                let _ = op_env.abort().await;
+                return Ok(());
+            }
+            Some(id) => {
+                let _ = op_env.abort().await;
+
+                let msg = format!("s3 key already exists as object: {}, {}", key, id);
+                warn!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::AlreadyExists, msg));
+            }
+            None => {}
+        }
+
+        op_env
+            .create_new_with_path(&path, ObjectMapSimpleContentType::Map)
+            .await?;
+        op_env.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn set_object(&self, bucket: &str, key: &str, file_id: &ObjectId) -> BuckyResult<()> {
+        self.files
+            .set(&self.object_path(bucket, key), file_id)
+            .await?;
+        Ok(())
+    }
+
+    // delete not exists object is not an error in s3
+    pub async fn delete_object(&self, bucket: &str, key: &str) -> BuckyResult<()> {
+        Self::check_key(key)?;
+        self.head_bucket(bucket).await?;
+
+        let path = self.object_path(bucket, key);
+        let op_env = self.state_stub().create_path_op_env().await?;
+        match op_env.get_by_path(&path).await? {
+            Some(id) if id.obj_type_code() == ObjectTypeCode::File && !key.ends_with('/') => {}
+            Some(id) if id.obj_type_code() == ObjectTypeCode::ObjectMap && key.ends_with('/') => {
+                // only empty dir marker can be deleted
+                if !self.list_path(&path).await?.is_empty() {
+                    let _ = op_env.abort().await;
+                    return Ok(());
+                }
+            }
+            _ => {
+                let _ = op_env.abort().await;
+                return Ok(());
+            }
+        }
+
+        op_env.remove_with_path(&path, None).await?;
+        op_env.commit().await?;
+
+        info!("delete s3 object: bucket={}, key={}", bucket, key);
+        Ok(())
+    }
+
+    pub async fn copy_object(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        bucket: &str,
+        key: &str,
+    ) -> BuckyResult<S3ObjectInfo> {
+        Self::check_key(key)?;
+
+        let src = self.get_object_info(src_bucket, src_key).await?;
+        if src.file_id.obj_type_code() != ObjectTypeCode::File {
+            let msg = format!("only file object can be copied: {}/{}", src_bucket, src_key);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        self.head_bucket(bucket).await?;
+
+        // the File object is immutable, so the copy only needs to set the same id at the new path
+        self.set_object(bucket, key, &src.file_id).await?;
+
+        info!(
+            "copy s3 object: {}/{} -> {}/{}, file={}",
+            src_bucket, src_key, bucket, key, src.file_id
+        );
+        Ok(S3ObjectInfo {
+            key: key.to_owned(),
+            ..src
+        })
+    }
+
+    // Walk the keys under the dir of prefix, the sub dirs will not be walked into if delimiter is '/'
+    async fn walk(&self, bucket: &str, prefix: &str, recursive: bool) -> BuckyResult<Vec<S3ListEntry>> {
+        let start = match prefix.rfind('/') {
+            Some(pos) => &prefix[..pos + 1],
+            None => "",
+        };
+
+        let mut entries = vec![];
+        let mut dirs = vec![(start.to_owned(), None)];
+        while let Some((dir, dir_id)) = dirs.pop() {
+            let path = if dir.is_empty() {
+                self.bucket_path(bucket)
+            } else {
+                self.object_path(bucket, &dir)
+            };
+
+            let list = match self.list_path(&path).await {
+                Ok(list) => list,
+                Err(e) if e.code() == BuckyErrorCode::NotFound && !dir.is_empty() => continue,
+                Err(e) => return Err(e),
+            };
+
+            if list.is_empty() {
+                if let Some(dir_id) = dir_id {
+                    entries.push(S3ListEntry::EmptyDir(dir, dir_id));
+                }
+                continue;
+            }
+
+            for (name, id) in list {
+                let key = format!("{}{}", dir, name);
+                match id.obj_type_code() {
+                    ObjectTypeCode::ObjectMap => {
+                        let key = format!("{}/", key);
+                        if !key.starts_with(prefix) && !prefix.starts_with(&key) {
+                            continue;
+                        }
+
+                        if recursive || (prefix.starts_with(&key) && prefix.len() > key.len()) {
+                            dirs.push((key, Some(id)));
+                        } else {
+                            entries.push(S3ListEntry::Dir(key));
+                        }
+                    }
+                    ObjectTypeCode::File => {
+                        if key.starts_with(prefix) {
+                            entries.push(S3ListEntry::Object(key, id));
+                        }
+                    }
+                    _ => {
+                        debug!("non file object in s3 bucket will be ignored: {}, {}", key, id);
+                    }
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    pub async fn list_objects(&self, bucket: &str, req: &S3ListRequest) -> BuckyResult<S3ListResult> {
+        self.head_bucket(bucket).await?;
+
+        let delimiter = req.delimiter.as_deref().filter(|v| !v.is_empty());
+        let mut entries = self
+            .walk(bucket, &req.prefix, delimiter != Some("/"))
+            .await?;
+        entries.sort_by(|left, right| left.key().cmp(right.key()));
+
+        let mut ret = S3ListResult {
+            objects: vec![],
+            common_prefixes: vec![],
+            is_truncated: false,
+            next_marker: None,
+        };
+
+        let mut count = 0;
+        let mut files = vec![];
+        for entry in entries {
+            let key = entry.key();
+            if let Some(marker) = &req.marker {
+                if key <= marker.as_str() {
+                    continue;
+                }
+
+                // the keys grouped in the marker common prefix are already returned
+                if let Some(delimiter) = delimiter {
+                    if marker.ends_with(delimiter) && key.starts_with(marker.as_str()) {
+                        continue;
+                    }
+                }
+            }
+
+            let common_prefix = delimiter.and_then(|delimiter| {
+                key[req.prefix.len()..]
+                    .find(delimiter)
+                    .map(|pos| key[..req.prefix.len() + pos + delimiter.len()].to_owned())
+            });
+
+            if let Some(common_prefix) = &common_prefix {
+                if ret.common_prefixes.last() == Some(common_prefix) {
+                    continue;
+                }
+            }
+
+            if count >= req.max_keys {
+                ret.is_truncated = true;
+                break;
+            }
+            count += 1;
+
+            match common_prefix {
+                Some(common_prefix) => {
+                    ret.next_marker = Some(common_prefix.clone());
+                    ret.common_prefixes.push(common_prefix);
+                }
+                None => {
+                    ret.next_marker = Some(key.to_owned());
+                    match entry {
+                        S3ListEntry::Object(key, id) => files.push((key, id)),
+                        S3ListEntry::EmptyDir(key, id) => files.push((key, id)),
+                        S3ListEntry::Dir(_) => unreachable!(),
+                    }
+                }
+            }
+        }
+
+        // only load the file objects in current page
+        for (key, id) in files {
+            let info = match id.obj_type_code() {
+                ObjectTypeCode::File => self.load_file_info(&key, &id).await?,
+                _ => S3ObjectInfo {
+                    key,
+                    file_id: id,
+                    size: 0,
+                    last_modified: 0,
+                },
+            };
+            ret.objects.push(info);
+        }
+
+        Ok(ret)
+    }
+}
@@ -0,0 +1,130 @@
+// Minimal xml support for s3 protocol, only the simple documents used by s3 requests and responses
+use cyfs_lib::WebHelper;
+
+pub struct XmlWriter {
+    buf: String,
+    stack: Vec<&'static str>,
+}
+
+impl XmlWriter {
+    pub fn new(root: &'static str) -> Self {
+        let mut ret = Self {
+            buf: String::with_capacity(1024),
+            stack: vec![],
+        };
+
+        ret.buf
+            .push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        ret.buf.push('<');
+        ret.buf.push_str(root);
+        ret.buf
+            .push_str(" xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">");
+        ret.stack.push(root);
+
+        ret
+    }
+
+    pub fn start(&mut self, tag: &'static str) -> &mut Self {
+        self.buf.push('<');
+        self.buf.push_str(tag);
+        self.buf.push('>');
+        self.stack.push(tag);
+        self
+    }
+
+    pub fn end(&mut self) -> &mut Self {
+        let tag = self.stack.pop().unwrap();
+        self.buf.push_str("</");
+        self.buf.push_str(tag);
+        self.buf.push('>');
+        self
+    }
+
+    pub fn elem(&mut self, tag: &str, value: impl ToString) -> &mut Self {
+        self.buf.push('<');
+        self.buf.push_str(tag);
+        self.buf.push('>');
+        self.buf.push_str(&WebHelper::xml_escape(&value.to_string()));
+        self.buf.push_str("</");
+        self.buf.push_str(tag);
+        self.buf.push('>');
+        self
+    }
+
+    pub fn opt_elem(&mut self, tag: &str, value: Option<impl ToString>) -> &mut Self {
+        if let Some(value) = value {
+            self.elem(tag, value);
+        }
+        self
+    }
+
+    pub fn finish(mut self) -> String {
+        while !self.stack.is_empty() {
+            self.end();
+        }
+
+        self.buf
+    }
+}
+
+pub fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// Returns the inner content of all the tag elements in order, nested same tags are not supported
+pub fn elements<'a>(doc: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+
+    let mut ret = vec![];
+    let mut pos = 0;
+    while let Some(start) = doc[pos..].find(&open) {
+        let content_start = pos + start + open.len();
+        match doc[content_start..].find(&close) {
+            Some(len) => {
+                ret.push(&doc[content_start..content_start + len]);
+                pos = content_start + len + close.len();
+            }
+            None => break,
+        }
+    }
+
+    ret
+}
+
+pub fn element(doc: &str, tag: &str) -> Option<String> {
+    elements(doc, tag)
+        .first()
+        .map(|v| unescape(v.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml() {
+        let mut writer = XmlWriter::new("ListBucketResult");
+        writer.elem("Name", "test").start("Contents");
+        writer.elem("Key", "a<b>&c").end();
+        let doc = writer.finish();
+        assert!(doc.ends_with(
+            "<Name>test</Name><Contents><Key>a&lt;b&gt;&amp;c</Key></Contents></ListBucketResult>"
+        ));
+
+        let doc = r#"<CompleteMultipartUpload>
+            <Part><PartNumber>1</PartNumber><ETag>"a"</ETag></Part>
+            <Part><PartNumber>2</PartNumber><ETag>&quot;b&quot;</ETag></Part>
+        </CompleteMultipartUpload>"#;
+        let parts = elements(doc, "Part");
+        assert_eq!(parts.len(), 2);
+        assert_eq!(element(parts[0], "PartNumber").unwrap(), "1");
+        assert_eq!(element(parts[1], "ETag").unwrap(), "\"b\"");
+        assert!(element(doc, "Key").is_none());
+    }
+}
@@ -182,6 +182,7 @@ impl TransBench {
             chunk_size: 1024 * 1024 * 4,
             // 关联的dirs
             file_id: None,
+            base_file_id: None,
             dirs: None,
 
             access: None,
@@ -261,6 +262,7 @@ impl TransBench {
             chunk_size: 1024 * 1024 * 4,
             // 关联的dirs
             file_id: None,
+            base_file_id: None,
             dirs: None,
 
             access: None,
@@ -330,6 +332,7 @@ impl TransBench {
             chunk_size: 1024 * 1024 * 4,
             // 关联的dirs
             file_id: Some(file_id),
+            base_file_id: None,
             dirs: None,
 
             access: None,
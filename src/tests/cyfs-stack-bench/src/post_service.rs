@@ -270,6 +270,7 @@ impl EventListenerAsyncRoutine<RouterHandlerPostObjectRequest, RouterHandlerPost
                 
                         // 关联的dirs
                         file_id: None,
+                        base_file_id: None,
                         dirs: None,
 
                         access: None,
@@ -43,6 +43,7 @@ async fn publish_file(dec_id: &ObjectId) -> (DeviceId, FileId) {
         
         // 关联的dirs
         file_id: None,
+        base_file_id: None,
         dirs: None,
     };
 
@@ -64,6 +64,7 @@ pub async fn test() {
     root_state::test().await;
     mime::test().await;
     ndn::test().await;
+    storage::test().await;
     call::test().await;
     object_meta_access::test().await;
 
@@ -89,6 +89,7 @@ async fn add_dir(dec_id: &ObjectId) -> (DirId, FileId, String, ChunkId) {
 
         // 关联的dirs
         file_id: None,
+        base_file_id: None,
         dirs: None,
     };
 
@@ -476,6 +477,7 @@ async fn test_range_file(dec_id: &ObjectId) {
 
         // 关联的dirs
         file_id: None,
+        base_file_id: None,
         dirs: None,
     };
 
@@ -596,6 +598,7 @@ pub async fn test_chunk_in_bundle() {
 
         // 关联的dirs
         file_id: None,
+        base_file_id: None,
         dirs: None,
     };
 
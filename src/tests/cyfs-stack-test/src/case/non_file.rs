@@ -54,6 +54,7 @@ async fn add_dir(_dec_id: &ObjectId) -> (DirId, FileId) {
 
         // 关联的dirs
         file_id: None,
+        base_file_id: None,
         dirs: None,
     };
 
@@ -3,6 +3,8 @@ use cyfs_core::*;
 use cyfs_lib::*;
 use zone_simulator::*;
 
+use async_std::io::ReadExt;
+
 fn new_dec(name: &str) -> ObjectId {
     let owner_id = &USER1_DATA.get().unwrap().people_id;
 
@@ -20,10 +22,50 @@ pub async fn test() {
     let device_stack = TestLoader::get_shared_stack(DeviceIndex::User1Device1);
 
     test_storage(&device_stack).await;
+
+    let ood_stack = TestLoader::get_shared_stack(DeviceIndex::User1OOD);
+    test_state_file_put(&ood_stack).await;
 }
 
 async fn test_storage(_stack: &SharedCyfsStack) {}
 
+// 和s3-gateway/webdav的PUT走同一条路径：publish(Copy)后挂到root_state，再通过ndn读回
+async fn test_state_file_put(stack: &SharedCyfsStack) {
+    let dec_id = new_dec("test-state-file");
+    let files = StateFileStorage::new(stack.clone(), dec_id, 1024 * 64);
+
+    let local_path = std::env::temp_dir().join("test_state_file_put");
+    let data: Vec<u8> = (0..1024 * 200).map(|_| rand::random::<u8>()).collect();
+    async_std::fs::write(&local_path, &data).await.unwrap();
+
+    let file_id = files.publish(&local_path).await.unwrap();
+
+    // Copy模式下publish返回后源文件已经不再需要
+    async_std::fs::remove_file(&local_path).await.unwrap();
+
+    let path = "/test/put/object.bin";
+    files.set(path, &file_id).await.unwrap();
+    assert_eq!(files.get_by_path(path).await.unwrap(), Some(file_id.clone()));
+
+    let file = files.load_file(&file_id).await.unwrap();
+    assert_eq!(file.len(), data.len() as u64);
+
+    let mut resp = files.read(&file_id, None).await.unwrap();
+    let mut buf = vec![];
+    resp.data.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, data);
+
+    let mut resp = files.read(&file_id, Some(1000..5000)).await.unwrap();
+    let mut buf = vec![];
+    resp.data.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, data[1000..5000]);
+
+    let ret = files.remove(path).await.unwrap();
+    assert_eq!(ret, Some(file_id.clone()));
+
+    info!("test state file put success! file={}", file_id);
+}
+
 
 #[test]
 fn tes_sort() {
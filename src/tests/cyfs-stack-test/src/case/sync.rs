@@ -166,6 +166,7 @@ async fn add_file(stack: &SharedCyfsStack) -> FileId {
         
         // 关联的dirs
         file_id: None,
+        base_file_id: None,
         dirs: None,
     };
 
@@ -170,6 +170,7 @@ async fn add_dir_impl(
 
         // 关联的dirs
         file_id: None,
+        base_file_id: None,
         dirs: None,
     };
 
@@ -250,6 +251,7 @@ async fn add_file_impl(
 
         // 关联的dirs
         file_id: None,
+        base_file_id: None,
         dirs: None,
     };
 
@@ -321,6 +323,7 @@ async fn add_file_impl2(
         
         // 关联的dirs
         file_id: Some(file_id),
+        base_file_id: None,
         dirs: None,
     };
 
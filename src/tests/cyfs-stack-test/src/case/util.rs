@@ -319,6 +319,7 @@ async fn build_file() {
                 owner: Default::default(),
                 chunk_size: 4 * 1024 * 1024, 
                 chunk_method: TransPublishChunkMethod::Track,
+                chunk_cdc: None,
                 access: None,
             }).await.unwrap();
             info!("build file {}", resp.object_id.to_string());
@@ -74,9 +74,10 @@ mod group_dec_common {
     use cyfs_lib::{BrowserSanboxMode, NONObjectInfo, SharedCyfsStack};
     use cyfs_meta_lib::MetaMinerTarget;
     use cyfs_stack::{
-        CyfsStack, CyfsStackConfigParams, CyfsStackFrontParams, CyfsStackInterfaceParams,
-        CyfsStackKnownObjects, CyfsStackKnownObjectsInitMode, CyfsStackMetaParams,
-        CyfsStackNOCParams, CyfsStackParams,
+        CyfsStack, CyfsStackConfigParams, CyfsStackErasureParams, CyfsStackEvictParams,
+        CyfsStackFrontParams,
+        CyfsStackInterfaceParams, CyfsStackKnownObjects, CyfsStackKnownObjectsInitMode,
+        CyfsStackMetaParams, CyfsStackNOCParams, CyfsStackParams, CyfsStackScrubParams,
     };
 
     // |--root
@@ -442,6 +443,7 @@ mod group_dec_common {
             udp_sn_only: None,
             sn_mode: SNMode::Normal,
             ping_interval: None,
+            chunk_compress: None,
         };
 
         let stack_param = CyfsStackParams {
@@ -470,6 +472,9 @@ mod group_dec_common {
                 enable: false,
                 browser_mode: BrowserSanboxMode::None,
             },
+            erasure: CyfsStackErasureParams::default(),
+            scrub: CyfsStackScrubParams::default(),
+            evict: CyfsStackEvictParams::default(),
         };
 
         let mut known_objects = CyfsStackKnownObjects {
@@ -9,6 +9,7 @@ use cyfs_core::{AppList, AppListObj, AppStatus, AppStatusObj, DecApp, DecAppId,
 use cyfs_meta_lib::{MetaClient, MetaMinerTarget};
 use lazy_static::lazy_static;
 use log::*;
+use std::collections::HashMap;
 use std::path::Path;
 use std::str::FromStr;
 
@@ -389,6 +390,13 @@ async fn main_run() -> BuckyResult<()> {
                                 .required(true)
                                 .takes_value(true)
                                 .help("fileid add to app source"))
+                        .arg(Arg::with_name("dep")
+                                .short("d")
+                                .long("dep")
+                                .takes_value(true)
+                                .multiple(true)
+                                .number_of_values(1)
+                                .help("dependency of this ver, format: <dec_id>=<semver req>, e.g. 9tGpLNn...=^1.2"))
                         .arg(meta_arg.clone()),
                 )
                 .subcommand(add_id_or_file_arg(SubCommand::with_name("remove").about("remove source from app"))
@@ -647,7 +655,17 @@ async fn main_run() -> BuckyResult<()> {
 
                     let source = ObjectId::from_str(matches.value_of("source").unwrap()).unwrap();
                     let ver = matches.value_of("appver").unwrap().to_owned();
-                    app.set_source(ver, source, None);
+                    let mut deps = HashMap::new();
+                    for dep in matches.values_of("dep").into_iter().flatten() {
+                        let (dec_id, req) = dep.split_once('=').ok_or_else(|| {
+                            let msg = format!("invalid dep {}, expect <dec_id>=<semver req>", dep);
+                            error!("{}", msg);
+                            BuckyError::new(BuckyErrorCode::InvalidParam, msg)
+                        })?;
+                        deps.insert(ObjectId::from_str(dec_id)?, req.to_owned());
+                    }
+                    app.set_source(ver.clone(), source, None);
+                    app.set_deps(ver, deps);
 
                     target.save_obj(&app).await
                 })
@@ -685,6 +703,9 @@ async fn main_run() -> BuckyResult<()> {
                     println!("name {}", app.name());
                     for (ver, source) in app.source() {
                         println!("app have source: {} : {}", ver, source);
+                        for (dec_id, req) in app.find_deps(ver).into_iter().flatten() {
+                            println!("    depend on: {} {}", dec_id, req);
+                        }
                     }
                     Ok(())
                 })
@@ -0,0 +1,319 @@
+use cyfs_base::*;
+
+use lru_time_cache::LruCache;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+pub const DEFAULT_BLOCK_SIZE: u64 = 1024 * 1024;
+pub const DEFAULT_MEMORY_CACHE_SIZE: u64 = 1024 * 1024 * 256;
+pub const DEFAULT_DISK_CACHE_SIZE: u64 = 1024 * 1024 * 1024 * 10;
+
+// Lru index of the block files in the disk cache, ordered by the last access seq
+struct DiskIndex {
+    limit: u64,
+    used: u64,
+    next_seq: u64,
+    files: HashMap<PathBuf, (u64, u64)>,
+    order: BTreeMap<u64, PathBuf>,
+}
+
+impl DiskIndex {
+    fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: 0,
+            next_seq: 0,
+            files: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn touch(&mut self, file: &Path, size: u64) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        match self.files.insert(file.to_owned(), (seq, size)) {
+            Some((old_seq, old_size)) => {
+                self.order.remove(&old_seq);
+                self.used -= old_size;
+            }
+            None => {}
+        }
+        self.order.insert(seq, file.to_owned());
+        self.used += size;
+    }
+
+    // Pop the least recently used files until the used size is under the limit
+    fn evict(&mut self) -> Vec<PathBuf> {
+        let mut list = vec![];
+        while self.used > self.limit {
+            let (seq, file) = match self.order.iter().next() {
+                Some((seq, file)) => (*seq, file.to_owned()),
+                None => break,
+            };
+            self.order.remove(&seq);
+            if let Some((_, size)) = self.files.remove(&file) {
+                self.used -= size;
+            }
+            list.push(file);
+        }
+
+        list
+    }
+}
+
+// The content of a file or chunk is immutable for the same object_id, so blocks can be cached
+// by (object_id, index) without any invalidation. The disk cache may be reused by mounts with
+// another block size, so the block size is part of the path
+pub struct BlockCache {
+    block_size: u64,
+    memory: Mutex<LruCache<(ObjectId, u64), Arc<Vec<u8>>>>,
+
+    // Optional disk cache, blocks are stored as {dir}/{block_size}/{object_id}/{index}
+    disk_dir: Option<PathBuf>,
+
+    // The total size of the disk cache is limited to disk_size, blocks of all block sizes are
+    // counted and the least recently used ones are removed first
+    disk: Mutex<DiskIndex>,
+}
+
+impl BlockCache {
+    pub fn new(
+        block_size: u64,
+        memory_size: u64,
+        disk_dir: Option<PathBuf>,
+        disk_size: u64,
+    ) -> Self {
+        assert!(block_size > 0);
+        let capacity = std::cmp::max(memory_size / block_size, 1) as usize;
+
+        let mut disk = DiskIndex::new(disk_size);
+        if let Some(dir) = &disk_dir {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                error!(
+                    "create mount cache dir failed! dir={}, {}",
+                    dir.display(),
+                    e
+                );
+            }
+
+            // Blocks left by previous mounts are ordered by their modified time
+            let mut list = Self::scan_disk(dir);
+            list.sort_by_key(|(time, _, _)| *time);
+            for (_, file, size) in list {
+                disk.touch(&file, size);
+            }
+            let evicted = disk.evict();
+            Self::remove_block_files(evicted);
+
+            info!(
+                "load mount disk cache: dir={}, used={}, limit={}",
+                dir.display(),
+                disk.used,
+                disk.limit
+            );
+        }
+
+        Self {
+            block_size,
+            memory: Mutex::new(LruCache::with_capacity(capacity)),
+            disk_dir,
+            disk: Mutex::new(disk),
+        }
+    }
+
+    pub fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    pub fn get(&self, object_id: &ObjectId, index: u64) -> Option<Arc<Vec<u8>>> {
+        let key = (object_id.to_owned(), index);
+        if let Some(block) = self.memory.lock().unwrap().get(&key) {
+            return Some(block.clone());
+        }
+
+        let file = self.block_file(object_id, index)?;
+        match std::fs::read(&file) {
+            Ok(buf) => {
+                self.disk.lock().unwrap().touch(&file, buf.len() as u64);
+
+                let block = Arc::new(buf);
+                self.memory.lock().unwrap().insert(key, block.clone());
+                Some(block)
+            }
+            Err(_) => None,
+        }
+    }
+
+    pub fn put(&self, object_id: &ObjectId, index: u64, block: Arc<Vec<u8>>) {
+        if let Some(file) = self.block_file(object_id, index) {
+            // Write to a tmp file first, so readers never see a partial block
+            let tmp = file.with_extension("tmp");
+            let ret = std::fs::create_dir_all(file.parent().unwrap())
+                .and_then(|_| std::fs::write(&tmp, block.as_slice()))
+                .and_then(|_| std::fs::rename(&tmp, &file));
+            match ret {
+                Ok(()) => {
+                    let evicted = {
+                        let mut disk = self.disk.lock().unwrap();
+                        disk.touch(&file, block.len() as u64);
+                        disk.evict()
+                    };
+                    Self::remove_block_files(evicted);
+                }
+                Err(e) => {
+                    warn!(
+                        "save block to disk cache failed! id={}, index={}, {}",
+                        object_id, index, e
+                    );
+                }
+            }
+        }
+
+        self.memory
+            .lock()
+            .unwrap()
+            .insert((object_id.to_owned(), index), block);
+    }
+
+    fn block_file(&self, object_id: &ObjectId, index: u64) -> Option<PathBuf> {
+        self.disk_dir.as_ref().map(|dir| {
+            dir.join(self.block_size.to_string())
+                .join(object_id.to_string())
+                .join(index.to_string())
+        })
+    }
+
+    // List the block files as (modified time, path, size), the tmp files of interrupted puts
+    // are removed
+    fn scan_disk(dir: &Path) -> Vec<(SystemTime, PathBuf, u64)> {
+        let read_dirs = |dir: &Path| -> Vec<PathBuf> {
+            match std::fs::read_dir(dir) {
+                Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+                Err(_) => vec![],
+            }
+        };
+
+        let mut list = vec![];
+        for size_dir in read_dirs(dir) {
+            for object_dir in read_dirs(&size_dir) {
+                for path in read_dirs(&object_dir) {
+                    if path.extension().is_some() {
+                        let _ = std::fs::remove_file(&path);
+                        continue;
+                    }
+
+                    if let Ok(meta) = std::fs::metadata(&path) {
+                        if meta.is_file() {
+                            let time = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                            list.push((time, path, meta.len()));
+                        }
+                    }
+                }
+            }
+        }
+
+        list
+    }
+
+    fn remove_block_files(list: Vec<PathBuf>) {
+        for file in list {
+            if let Err(e) = std::fs::remove_file(&file) {
+                warn!(
+                    "remove block from disk cache failed! file={}, {}",
+                    file.display(),
+                    e
+                );
+                continue;
+            }
+
+            // Remove the object dir once its last block is gone, fails if not empty
+            if let Some(dir) = file.parent() {
+                let _ = std::fs::remove_dir(dir);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_memory_cache() {
+        let id = ObjectIdDataBuilder::new().data("block").build().unwrap();
+
+        // Capacity is memory_size / block_size = 2
+        let cache = BlockCache::new(4, 8, None, 0);
+        assert!(cache.get(&id, 0).is_none());
+
+        for i in 0..3 {
+            cache.put(&id, i, Arc::new(vec![i as u8; 4]));
+        }
+
+        assert!(cache.get(&id, 0).is_none());
+        assert_eq!(*cache.get(&id, 1).unwrap(), vec![1u8; 4]);
+        assert_eq!(*cache.get(&id, 2).unwrap(), vec![2u8; 4]);
+    }
+
+    #[test]
+    fn test_disk_cache() {
+        let id = ObjectIdDataBuilder::new().data("block").build().unwrap();
+        let dir = cyfs_util::get_temp_path()
+            .join("test-cyfs-mount-cache")
+            .join(std::process::id().to_string());
+
+        {
+            let cache = BlockCache::new(4, 4, Some(dir.clone()), 1024);
+            cache.put(&id, 0, Arc::new(vec![1u8; 4]));
+            cache.put(&id, 1, Arc::new(vec![2u8; 4]));
+        }
+
+        // Blocks evicted from memory or from a previous mount are loaded from disk
+        let cache = BlockCache::new(4, 4, Some(dir.clone()), 1024);
+        assert_eq!(*cache.get(&id, 0).unwrap(), vec![1u8; 4]);
+        assert_eq!(*cache.get(&id, 1).unwrap(), vec![2u8; 4]);
+        assert!(cache.get(&id, 2).is_none());
+
+        // Blocks of another block size are not shared
+        let cache = BlockCache::new(2, 4, Some(dir.clone()), 1024);
+        assert!(cache.get(&id, 0).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_disk_cache_limit() {
+        let id = ObjectIdDataBuilder::new().data("block").build().unwrap();
+        let dir = cyfs_util::get_temp_path()
+            .join("test-cyfs-mount-cache-limit")
+            .join(std::process::id().to_string());
+
+        // Disk keeps at most 3 blocks, memory keeps 1
+        let cache = BlockCache::new(4, 4, Some(dir.clone()), 12);
+        for i in 0..3 {
+            cache.put(&id, i, Arc::new(vec![i as u8; 4]));
+        }
+
+        // Access block 0 so block 1 becomes the least recently used one
+        assert_eq!(*cache.get(&id, 0).unwrap(), vec![0u8; 4]);
+        cache.put(&id, 3, Arc::new(vec![3u8; 4]));
+
+        assert!(cache.block_file(&id, 0).unwrap().exists());
+        assert!(!cache.block_file(&id, 1).unwrap().exists());
+        assert!(cache.block_file(&id, 2).unwrap().exists());
+        assert!(cache.block_file(&id, 3).unwrap().exists());
+        drop(cache);
+
+        // A smaller limit on the next mount evicts the oldest blocks on load
+        let cache = BlockCache::new(4, 4, Some(dir.clone()), 8);
+        let left = (0..4)
+            .filter(|i| cache.block_file(&id, *i).unwrap().exists())
+            .count();
+        assert_eq!(left, 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1068 @@
+use crate::reader::DataReader;
+use crate::source::{MountSource, NodeKind};
+use crate::writer::StateWriter;
+use cyfs_base::*;
+
+use fuser::{
+    FileAttr, FileType, Filesystem, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory, ReplyEmpty,
+    ReplyEntry, ReplyOpen, ReplyWrite, Request, TimeOrNow, FUSE_ROOT_ID,
+};
+use libc::c_int;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::future::Future;
+use std::os::unix::fs::FileExt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+const TTL: Duration = Duration::from_secs(1);
+const BLOCK_SIZE: u32 = 512;
+
+struct Inode {
+    parent: u64,
+    kind: NodeKind,
+
+    // Full global state path, only exists for nodes under a global state mount
+    state_path: Option<String>,
+
+    // Lazily resolved content length of data nodes
+    size: Option<u64>,
+
+    // Lazily loaded children of dir nodes, name -> ino
+    children: Option<BTreeMap<String, u64>>,
+
+    // Kernel lookup count, decreased by forget
+    lookups: u64,
+}
+
+// The temp file of a write handle is a sparse copy of the original content, blocks are copied
+// from the original only when they are touched by a partial write or a read, and the untouched
+// ones are filled just before the commit
+struct WriteHandle {
+    ino: u64,
+    state_path: String,
+    local_path: PathBuf,
+    file: Arc<std::fs::File>,
+    size: u64,
+
+    // The original content, only [0, base_len) is valid and it shrinks on truncate
+    base: NodeKind,
+    base_len: u64,
+
+    // Index of the blocks that the temp file holds the content of
+    loaded: HashSet<u64>,
+
+    // Serialize the block loads with writes, so a loaded block never overwrites newer data
+    io_lock: Arc<async_std::sync::Mutex<()>>,
+
+    // Bumped on every write, the handle is dirty while version != committed
+    version: u64,
+    committed: u64,
+
+    // Serialize the commits of the same handle, such as flush and release
+    commit_lock: Arc<async_std::sync::Mutex<()>>,
+}
+
+struct FsState {
+    inodes: HashMap<u64, Inode>,
+    next_ino: u64,
+
+    handles: HashMap<u64, WriteHandle>,
+    next_fh: u64,
+}
+
+impl FsState {
+    fn alloc_inode(&mut self, parent: u64, name: &str, kind: NodeKind) -> u64 {
+        let state_path = self.inodes[&parent]
+            .state_path
+            .as_ref()
+            .map(|parent| MountSource::join_path(parent, name));
+
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        self.inodes.insert(
+            ino,
+            Inode {
+                parent,
+                kind,
+                state_path,
+                size: None,
+                children: None,
+                lookups: 0,
+            },
+        );
+
+        ino
+    }
+
+    fn is_reachable(&self, ino: u64) -> bool {
+        if ino == FUSE_ROOT_ID {
+            return true;
+        }
+
+        let parent = match self.inodes.get(&ino) {
+            Some(inode) => inode.parent,
+            None => return false,
+        };
+        self.inodes
+            .get(&parent)
+            .and_then(|inode| inode.children.as_ref())
+            .map(|children| children.values().any(|child| *child == ino))
+            .unwrap_or(false)
+    }
+
+    // Free the inode and its loaded children, the ones still referenced by the kernel are kept
+    // and freed later by forget
+    fn free_inode(&mut self, ino: u64) {
+        if ino == FUSE_ROOT_ID {
+            return;
+        }
+        match self.inodes.get(&ino) {
+            Some(inode) if inode.lookups == 0 => {}
+            _ => return,
+        }
+
+        if let Some(children) = self.inodes.remove(&ino).and_then(|inode| inode.children) {
+            for child in children.into_values() {
+                self.free_inode(child);
+            }
+        }
+    }
+}
+
+struct CyfsFsInner {
+    source: MountSource,
+    reader: DataReader,
+    writer: Option<StateWriter>,
+
+    // Never held across an await, the stack requests are made without the lock
+    state: Mutex<FsState>,
+
+    uid: u32,
+    gid: u32,
+    mount_time: SystemTime,
+}
+
+// Fuse callbacks are dispatched from a single session thread, so every callback that requests
+// the stack is moved to an async task and replies from there, and never blocks the session loop
+pub struct CyfsFs(Arc<CyfsFsInner>);
+
+fn to_errno(e: &BuckyError) -> c_int {
+    match e.code() {
+        BuckyErrorCode::NotFound => libc::ENOENT,
+        BuckyErrorCode::PermissionDenied => libc::EACCES,
+        BuckyErrorCode::AlreadyExists => libc::EEXIST,
+        BuckyErrorCode::NotSupport => libc::ENOTSUP,
+        _ => libc::EIO,
+    }
+}
+
+impl CyfsFsInner {
+    async fn load_children(&self, ino: u64) -> Result<(), c_int> {
+        let kind = {
+            let state = self.state.lock().unwrap();
+            let inode = state.inodes.get(&ino).ok_or(libc::ENOENT)?;
+            if !inode.kind.is_dir() {
+                return Err(libc::ENOTDIR);
+            }
+            if inode.children.is_some() {
+                return Ok(());
+            }
+            inode.kind.clone()
+        };
+
+        let list = self.source.list(&kind).await.map_err(|e| to_errno(&e))?;
+
+        let mut state = self.state.lock().unwrap();
+        match state.inodes.get(&ino) {
+            // Maybe loaded by another request during the list
+            Some(inode) if inode.children.is_some() => return Ok(()),
+            Some(_) => {}
+            None => return Err(libc::ENOENT),
+        }
+
+        let mut children = BTreeMap::new();
+        for (name, kind) in list {
+            let child = state.alloc_inode(ino, &name, kind);
+            children.insert(name, child);
+        }
+        state.inodes.get_mut(&ino).unwrap().children = Some(children);
+
+        Ok(())
+    }
+
+    async fn lookup_child(&self, parent: u64, name: &str) -> Result<u64, c_int> {
+        self.load_children(parent).await?;
+
+        let state = self.state.lock().unwrap();
+        state
+            .inodes
+            .get(&parent)
+            .and_then(|inode| inode.children.as_ref())
+            .and_then(|children| children.get(name))
+            .cloned()
+            .ok_or(libc::ENOENT)
+    }
+
+    async fn list_children(&self, ino: u64) -> Result<Vec<(String, u64, bool)>, c_int> {
+        self.load_children(ino).await?;
+
+        let state = self.state.lock().unwrap();
+        let children = state
+            .inodes
+            .get(&ino)
+            .and_then(|inode| inode.children.as_ref())
+            .ok_or(libc::ENOENT)?;
+
+        Ok(children
+            .iter()
+            .map(|(name, child)| {
+                let is_dir = state
+                    .inodes
+                    .get(child)
+                    .map(|inode| inode.kind.is_dir())
+                    .unwrap_or(false);
+                (name.clone(), *child, is_dir)
+            })
+            .collect())
+    }
+
+    fn invalidate_children(&self, ino: u64) {
+        let mut state = self.state.lock().unwrap();
+        let children = state
+            .inodes
+            .get_mut(&ino)
+            .and_then(|inode| inode.children.take());
+        if let Some(children) = children {
+            for child in children.into_values() {
+                state.free_inode(child);
+            }
+        }
+    }
+
+    fn forget(&self, ino: u64, nlookup: u64) {
+        let mut state = self.state.lock().unwrap();
+        let lookups = match state.inodes.get_mut(&ino) {
+            Some(inode) => {
+                inode.lookups = inode.lookups.saturating_sub(nlookup);
+                inode.lookups
+            }
+            None => return,
+        };
+
+        // Inodes still in the parent's children are kept as the cache of the dir list
+        if lookups == 0 && !state.is_reachable(ino) {
+            state.free_inode(ino);
+        }
+    }
+
+    fn inc_lookup(&self, ino: u64) -> Result<(), c_int> {
+        let mut state = self.state.lock().unwrap();
+        state.inodes.get_mut(&ino).ok_or(libc::ENOENT)?.lookups += 1;
+        Ok(())
+    }
+
+    fn child_state_path(&self, parent: u64, name: &OsStr) -> Result<String, c_int> {
+        if self.writer.is_none() {
+            return Err(libc::EROFS);
+        }
+
+        let name = name.to_str().ok_or(libc::EINVAL)?;
+        let state = self.state.lock().unwrap();
+        let inode = state.inodes.get(&parent).ok_or(libc::ENOENT)?;
+        let parent_path = inode.state_path.as_ref().ok_or(libc::EROFS)?;
+
+        Ok(MountSource::join_path(parent_path, name))
+    }
+
+    fn make_attr(&self, state: &FsState, ino: u64, is_dir: bool, size: u64) -> FileAttr {
+        let (kind, size, perm) = if is_dir {
+            (FileType::Directory, 0, 0o755)
+        } else {
+            // Files being written report the size of the local temp file
+            let size = state
+                .handles
+                .values()
+                .find(|h| h.ino == ino)
+                .map(|h| h.size)
+                .unwrap_or(size);
+
+            (FileType::RegularFile, size, 0o644)
+        };
+
+        let perm = if self.writer.is_some() {
+            perm
+        } else {
+            perm & 0o555
+        };
+
+        FileAttr {
+            ino,
+            size,
+            blocks: (size + BLOCK_SIZE as u64 - 1) / BLOCK_SIZE as u64,
+            atime: self.mount_time,
+            mtime: self.mount_time,
+            ctime: self.mount_time,
+            crtime: self.mount_time,
+            kind,
+            perm,
+            nlink: if kind == FileType::Directory { 2 } else { 1 },
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: BLOCK_SIZE,
+            flags: 0,
+        }
+    }
+
+    async fn attr(&self, ino: u64) -> Result<FileAttr, c_int> {
+        let kind = {
+            let state = self.state.lock().unwrap();
+            let inode = state.inodes.get(&ino).ok_or(libc::ENOENT)?;
+            if inode.kind.is_dir() {
+                return Ok(self.make_attr(&state, ino, true, 0));
+            }
+            if let Some(size) = inode.size {
+                return Ok(self.make_attr(&state, ino, false, size));
+            }
+            inode.kind.clone()
+        };
+
+        let size = self
+            .source
+            .data_len(&kind)
+            .await
+            .map_err(|e| to_errno(&e))?;
+
+        let mut state = self.state.lock().unwrap();
+        state.inodes.get_mut(&ino).ok_or(libc::ENOENT)?.size = Some(size);
+        Ok(self.make_attr(&state, ino, false, size))
+    }
+
+    async fn lookup(&self, parent: u64, name: &OsStr) -> Result<FileAttr, c_int> {
+        let name = name.to_str().ok_or(libc::ENOENT)?;
+        let ino = self.lookup_child(parent, name).await?;
+        let attr = self.attr(ino).await?;
+        self.inc_lookup(ino)?;
+        Ok(attr)
+    }
+
+    async fn remove(&self, parent: u64, name: &OsStr) -> Result<(), c_int> {
+        let path = self.child_state_path(parent, name)?;
+
+        let writer = self.writer.as_ref().unwrap();
+        writer.remove(&path).await.map_err(|e| to_errno(&e))?;
+
+        self.invalidate_children(parent);
+        Ok(())
+    }
+
+    async fn open_write_handle(&self, ino: u64, truncate: bool) -> Result<u64, c_int> {
+        let writer = self.writer.as_ref().ok_or(libc::EROFS)?;
+        let (state_path, kind) = {
+            let state = self.state.lock().unwrap();
+            let inode = state.inodes.get(&ino).ok_or(libc::ENOENT)?;
+            let state_path = inode.state_path.clone().ok_or(libc::EROFS)?;
+            (state_path, inode.kind.clone())
+        };
+
+        let (local_path, file) = writer.new_temp_file().map_err(|e| to_errno(&e))?;
+
+        // Modify an exists file in place, the blocks of the original content are loaded on demand
+        let size = if truncate {
+            0
+        } else {
+            self.attr(ino).await?.size
+        };
+        if let Err(e) = file.set_len(size) {
+            error!(
+                "resize temp file failed! file={}, {}",
+                local_path.display(),
+                e
+            );
+            let _ = std::fs::remove_file(&local_path);
+            return Err(libc::EIO);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let fh = state.next_fh;
+        state.next_fh += 1;
+        state.handles.insert(
+            fh,
+            WriteHandle {
+                ino,
+                state_path,
+                local_path,
+                file: Arc::new(file),
+                size,
+                base: kind,
+                base_len: size,
+                loaded: HashSet::new(),
+                io_lock: Arc::new(async_std::sync::Mutex::new(())),
+                version: if truncate { 1 } else { 0 },
+                committed: 0,
+                commit_lock: Arc::new(async_std::sync::Mutex::new(())),
+            },
+        );
+
+        Ok(fh)
+    }
+
+    // Copy the original blocks overlapped with [offset, end) to the temp file, the blocks fully
+    // covered by [offset, end) are skipped if the range is going to be overwritten.
+    // Must be called with the io_lock of the handle held
+    async fn load_blocks(
+        &self,
+        fh: u64,
+        offset: u64,
+        end: u64,
+        overwrite: bool,
+    ) -> Result<(), c_int> {
+        if offset >= end {
+            return Ok(());
+        }
+
+        let block_size = self.reader.block_size();
+        for index in offset / block_size..=(end - 1) / block_size {
+            let block_start = index * block_size;
+            let (base, base_len) = {
+                let mut state = self.state.lock().unwrap();
+                let handle = state.handles.get_mut(&fh).ok_or(libc::EBADF)?;
+                if handle.loaded.contains(&index) {
+                    continue;
+                }
+
+                // Blocks beyond the original content are zero in the sparse temp file already
+                let block_end = std::cmp::min(block_start + block_size, handle.base_len);
+                if block_start >= block_end
+                    || (overwrite && offset <= block_start && end >= block_end)
+                {
+                    handle.loaded.insert(index);
+                    continue;
+                }
+                (handle.base.clone(), handle.base_len)
+            };
+
+            let data = self
+                .reader
+                .read(&base, base_len, block_start, block_size)
+                .await
+                .map_err(|e| to_errno(&e))?;
+
+            // The handle maybe truncated during the load, only the part still valid is copied
+            let mut state = self.state.lock().unwrap();
+            let handle = state.handles.get_mut(&fh).ok_or(libc::EBADF)?;
+            let valid = std::cmp::min(block_start + data.len() as u64, handle.base_len);
+            if valid > block_start {
+                let len = (valid - block_start) as usize;
+                if let Err(e) = handle.file.write_all_at(&data[..len], block_start) {
+                    error!(
+                        "write temp file failed! file={}, {}",
+                        handle.local_path.display(),
+                        e
+                    );
+                    return Err(libc::EIO);
+                }
+            }
+            handle.loaded.insert(index);
+        }
+
+        Ok(())
+    }
+
+    async fn read_handle(&self, fh: u64, offset: u64, size: u32) -> Result<Vec<u8>, c_int> {
+        let io_lock = match self.state.lock().unwrap().handles.get(&fh) {
+            Some(handle) => handle.io_lock.clone(),
+            None => return Err(libc::EBADF),
+        };
+        let _guard = io_lock.lock().await;
+
+        self.load_blocks(fh, offset, offset + size as u64, false)
+            .await?;
+
+        let file = match self.state.lock().unwrap().handles.get(&fh) {
+            Some(handle) => handle.file.clone(),
+            None => return Err(libc::EBADF),
+        };
+        let mut buf = vec![0; size as usize];
+        let len = file.read_at(&mut buf, offset).map_err(|_| libc::EIO)?;
+        buf.truncate(len);
+
+        Ok(buf)
+    }
+
+    async fn write_handle(&self, fh: u64, offset: u64, data: &[u8]) -> Result<u32, c_int> {
+        let io_lock = match self.state.lock().unwrap().handles.get(&fh) {
+            Some(handle) => handle.io_lock.clone(),
+            None => return Err(libc::EBADF),
+        };
+        let _guard = io_lock.lock().await;
+
+        let end = offset + data.len() as u64;
+        self.load_blocks(fh, offset, end, true).await?;
+
+        let mut state = self.state.lock().unwrap();
+        let handle = state.handles.get_mut(&fh).ok_or(libc::EBADF)?;
+        if let Err(e) = handle.file.write_all_at(data, offset) {
+            error!(
+                "write temp file failed! file={}, {}",
+                handle.local_path.display(),
+                e
+            );
+            return Err(libc::EIO);
+        }
+        handle.size = std::cmp::max(handle.size, end);
+        handle.version += 1;
+
+        Ok(data.len() as u32)
+    }
+
+    async fn commit_handle(&self, fh: u64) -> Result<(), c_int> {
+        let (commit_lock, io_lock) = match self.state.lock().unwrap().handles.get(&fh) {
+            Some(handle) => (handle.commit_lock.clone(), handle.io_lock.clone()),
+            None => return Ok(()),
+        };
+        let _guard = commit_lock.lock().await;
+
+        // The whole content is needed by publish, fill the untouched blocks block by block
+        {
+            let _guard = io_lock.lock().await;
+            let base_len = match self.state.lock().unwrap().handles.get(&fh) {
+                Some(handle) if handle.version != handle.committed => handle.base_len,
+                _ => return Ok(()),
+            };
+            self.load_blocks(fh, 0, base_len, false).await?;
+        }
+
+        let (ino, state_path, local_path, size, version) = {
+            let state = self.state.lock().unwrap();
+            let handle = match state.handles.get(&fh) {
+                Some(handle) => handle,
+                None => return Ok(()),
+            };
+            if handle.version == handle.committed {
+                return Ok(());
+            }
+            (
+                handle.ino,
+                handle.state_path.clone(),
+                handle.local_path.clone(),
+                handle.size,
+                handle.version,
+            )
+        };
+
+        let writer = self.writer.as_ref().ok_or(libc::EROFS)?;
+        let file_id = writer
+            .commit_file(&state_path, &local_path)
+            .await
+            .map_err(|e| to_errno(&e))?;
+
+        // Writes during the commit bump the version again and will be committed next time
+        let mut state = self.state.lock().unwrap();
+        if let Some(handle) = state.handles.get_mut(&fh) {
+            handle.committed = version;
+        }
+        if let Some(inode) = state.inodes.get_mut(&ino) {
+            inode.kind = NodeKind::Data(file_id);
+            inode.size = Some(size);
+        }
+
+        Ok(())
+    }
+
+    async fn release(&self, fh: u64) -> Result<(), c_int> {
+        let ret = self.commit_handle(fh).await;
+
+        let handle = self.state.lock().unwrap().handles.remove(&fh);
+        if let Some(handle) = handle {
+            let _ = std::fs::remove_file(&handle.local_path);
+        }
+
+        ret
+    }
+}
+
+impl CyfsFs {
+    pub fn new(
+        source: MountSource,
+        root: NodeKind,
+        reader: DataReader,
+        writer: Option<StateWriter>,
+    ) -> Self {
+        let state_path = match &root {
+            NodeKind::StatePath(path) => Some(path.to_owned()),
+            _ => None,
+        };
+
+        let mut inodes = HashMap::new();
+        inodes.insert(
+            FUSE_ROOT_ID,
+            Inode {
+                parent: FUSE_ROOT_ID,
+                kind: root,
+                state_path,
+                size: None,
+                children: None,
+                lookups: 0,
+            },
+        );
+
+        let state = FsState {
+            inodes,
+            next_ino: FUSE_ROOT_ID + 1,
+            handles: HashMap::new(),
+            next_fh: 1,
+        };
+
+        Self(Arc::new(CyfsFsInner {
+            source,
+            reader,
+            writer,
+            state: Mutex::new(state),
+            uid: unsafe { libc::getuid() },
+            gid: unsafe { libc::getgid() },
+            mount_time: SystemTime::now(),
+        }))
+    }
+
+    fn spawn<F, Fut>(&self, f: F)
+    where
+        F: FnOnce(Arc<CyfsFsInner>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        async_std::task::spawn(f(self.0.clone()));
+    }
+}
+
+impl Filesystem for CyfsFs {
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let name = name.to_owned();
+        self.spawn(|fs| async move {
+            match fs.lookup(parent, &name).await {
+                Ok(attr) => reply.entry(&TTL, &attr, 0),
+                Err(e) => reply.error(e),
+            }
+        });
+    }
+
+    fn forget(&mut self, _req: &Request<'_>, ino: u64, nlookup: u64) {
+        self.0.forget(ino, nlookup);
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyAttr) {
+        self.spawn(|fs| async move {
+            match fs.attr(ino).await {
+                Ok(attr) => reply.attr(&TTL, &attr),
+                Err(e) => reply.error(e),
+            }
+        });
+    }
+
+    fn setattr(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        fh: Option<u64>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<u32>,
+        reply: ReplyAttr,
+    ) {
+        // Only truncate on an opened write handle is supported, other attrs are ignored
+        if let Some(size) = size {
+            let mut state = self.0.state.lock().unwrap();
+            let handle = match fh.and_then(|fh| state.handles.get_mut(&fh)) {
+                Some(handle) => handle,
+                None => return reply.error(libc::EROFS),
+            };
+
+            if let Err(e) = handle.file.set_len(size) {
+                error!(
+                    "truncate temp file failed! file={}, {}",
+                    handle.local_path.display(),
+                    e
+                );
+                return reply.error(libc::EIO);
+            }
+            let block_size = self.0.reader.block_size();
+            handle.size = size;
+            handle.base_len = std::cmp::min(handle.base_len, size);
+            handle.loaded.retain(|index| index * block_size < size);
+            handle.version += 1;
+        }
+
+        self.spawn(|fs| async move {
+            match fs.attr(ino).await {
+                Ok(attr) => reply.attr(&TTL, &attr),
+                Err(e) => reply.error(e),
+            }
+        });
+    }
+
+    fn mkdir(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        reply: ReplyEntry,
+    ) {
+        let path = match self.0.child_state_path(parent, name) {
+            Ok(path) => path,
+            Err(e) => return reply.error(e),
+        };
+
+        let name = name.to_owned();
+        self.spawn(|fs| async move {
+            let writer = fs.writer.as_ref().unwrap();
+            if let Err(e) = writer.create_dir(&path).await {
+                return reply.error(to_errno(&e));
+            }
+
+            fs.invalidate_children(parent);
+            match fs.lookup(parent, &name).await {
+                Ok(attr) => reply.entry(&TTL, &attr, 0),
+                Err(e) => reply.error(e),
+            }
+        });
+    }
+
+    fn unlink(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let name = name.to_owned();
+        self.spawn(|fs| async move {
+            match fs.remove(parent, &name).await {
+                Ok(()) => reply.ok(),
+                Err(e) => reply.error(e),
+            }
+        });
+    }
+
+    fn rmdir(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let name = name.to_owned();
+        self.spawn(|fs| async move {
+            let ino = match name.to_str() {
+                Some(v) => fs.lookup_child(parent, v).await,
+                None => Err(libc::ENOENT),
+            };
+            let ino = match ino {
+                Ok(ino) => ino,
+                Err(e) => return reply.error(e),
+            };
+
+            match fs.list_children(ino).await {
+                Ok(children) if !children.is_empty() => return reply.error(libc::ENOTEMPTY),
+                Ok(_) => {}
+                Err(e) => return reply.error(e),
+            }
+
+            match fs.remove(parent, &name).await {
+                Ok(()) => reply.ok(),
+                Err(e) => reply.error(e),
+            }
+        });
+    }
+
+    fn rename(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        newparent: u64,
+        newname: &OsStr,
+        _flags: u32,
+        reply: ReplyEmpty,
+    ) {
+        let from = match self.0.child_state_path(parent, name) {
+            Ok(path) => path,
+            Err(e) => return reply.error(e),
+        };
+        let to = match self.0.child_state_path(newparent, newname) {
+            Ok(path) => path,
+            Err(e) => return reply.error(e),
+        };
+
+        self.spawn(|fs| async move {
+            let writer = fs.writer.as_ref().unwrap();
+            match writer.rename(&from, &to).await {
+                Ok(()) => {
+                    fs.invalidate_children(parent);
+                    fs.invalidate_children(newparent);
+                    reply.ok()
+                }
+                Err(e) => reply.error(to_errno(&e)),
+            }
+        });
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        if flags & libc::O_ACCMODE == libc::O_RDONLY {
+            return reply.opened(0, 0);
+        }
+
+        self.spawn(|fs| async move {
+            match fs.open_write_handle(ino, flags & libc::O_TRUNC != 0).await {
+                Ok(fh) => reply.opened(fh, 0),
+                Err(e) => reply.error(e),
+            }
+        });
+    }
+
+    fn create(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        if let Err(e) = self.0.child_state_path(parent, name) {
+            return reply.error(e);
+        }
+
+        let name = name.to_str().unwrap().to_owned();
+        self.spawn(|fs| async move {
+            if let Err(e) = fs.load_children(parent).await {
+                return reply.error(e);
+            }
+
+            let ino = {
+                let mut state = fs.state.lock().unwrap();
+                let ino = state.alloc_inode(parent, &name, NodeKind::Empty);
+                let old = match state
+                    .inodes
+                    .get_mut(&parent)
+                    .and_then(|inode| inode.children.as_mut())
+                {
+                    Some(children) => children.insert(name, ino),
+                    None => {
+                        state.inodes.remove(&ino);
+                        return reply.error(libc::ENOENT);
+                    }
+                };
+                if let Some(old) = old {
+                    state.free_inode(old);
+                }
+                ino
+            };
+
+            let fh = match fs.open_write_handle(ino, true).await {
+                Ok(fh) => fh,
+                Err(e) => return reply.error(e),
+            };
+
+            match fs.attr(ino).await {
+                Ok(attr) => match fs.inc_lookup(ino) {
+                    Ok(()) => reply.created(&TTL, &attr, 0, fh, 0),
+                    Err(e) => reply.error(e),
+                },
+                Err(e) => reply.error(e),
+            }
+        });
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        // Files being written are read from the local temp file
+        if self.0.state.lock().unwrap().handles.contains_key(&fh) {
+            return self.spawn(|fs| async move {
+                match fs.read_handle(fh, offset as u64, size).await {
+                    Ok(data) => reply.data(&data),
+                    Err(e) => reply.error(e),
+                }
+            });
+        }
+
+        self.spawn(|fs| async move {
+            let len = match fs.attr(ino).await {
+                Ok(attr) => attr.size,
+                Err(e) => return reply.error(e),
+            };
+
+            let kind = match fs.state.lock().unwrap().inodes.get(&ino) {
+                Some(inode) => inode.kind.clone(),
+                None => return reply.error(libc::ENOENT),
+            };
+            match fs.reader.read(&kind, len, offset as u64, size as u64).await {
+                Ok(data) => reply.data(&data),
+                Err(e) => reply.error(to_errno(&e)),
+            }
+        });
+    }
+
+    fn write(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        offset: i64,
+        data: &[u8],
+        _write_flags: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        let data = data.to_owned();
+        self.spawn(|fs| async move {
+            match fs.write_handle(fh, offset as u64, &data).await {
+                Ok(len) => reply.written(len),
+                Err(e) => reply.error(e),
+            }
+        });
+    }
+
+    fn flush(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        _lock_owner: u64,
+        reply: ReplyEmpty,
+    ) {
+        self.spawn(|fs| async move {
+            match fs.commit_handle(fh).await {
+                Ok(()) => reply.ok(),
+                Err(e) => reply.error(e),
+            }
+        });
+    }
+
+    fn release(
+        &mut self,
+        _req: &Request<'_>,
+        _ino: u64,
+        fh: u64,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        self.spawn(|fs| async move {
+            match fs.release(fh).await {
+                Ok(()) => reply.ok(),
+                Err(e) => reply.error(e),
+            }
+        });
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        self.spawn(|fs| async move {
+            let parent = match fs.state.lock().unwrap().inodes.get(&ino) {
+                Some(inode) => inode.parent,
+                None => return reply.error(libc::ENOENT),
+            };
+
+            let children = match fs.list_children(ino).await {
+                Ok(children) => children,
+                Err(e) => return reply.error(e),
+            };
+
+            let mut entries = vec![
+                (ino, FileType::Directory, ".".to_owned()),
+                (parent, FileType::Directory, "..".to_owned()),
+            ];
+            for (name, child, is_dir) in children {
+                let kind = if is_dir {
+                    FileType::Directory
+                } else {
+                    FileType::RegularFile
+                };
+                entries.push((child, kind, name));
+            }
+
+            for (i, (ino, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+                if reply.add(ino, (i + 1) as i64, kind, name) {
+                    break;
+                }
+            }
+
+            reply.ok();
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_free_inode() {
+        let mut inodes = HashMap::new();
+        inodes.insert(
+            FUSE_ROOT_ID,
+            Inode {
+                parent: FUSE_ROOT_ID,
+                kind: NodeKind::StatePath("/".to_owned()),
+                state_path: Some("/".to_owned()),
+                size: None,
+                children: None,
+                lookups: 0,
+            },
+        );
+        let mut state = FsState {
+            inodes,
+            next_ino: FUSE_ROOT_ID + 1,
+            handles: HashMap::new(),
+            next_fh: 1,
+        };
+
+        let a = state.alloc_inode(FUSE_ROOT_ID, "a", NodeKind::Empty);
+        let b = state.alloc_inode(FUSE_ROOT_ID, "b", NodeKind::Empty);
+        let mut children = BTreeMap::new();
+        children.insert("a".to_owned(), a);
+        children.insert("b".to_owned(), b);
+        state.inodes.get_mut(&FUSE_ROOT_ID).unwrap().children = Some(children);
+        state.inodes.get_mut(&a).unwrap().lookups = 1;
+        assert!(state.is_reachable(a));
+
+        // Invalidate the dir list, only the inode referenced by the kernel is kept
+        let children = state
+            .inodes
+            .get_mut(&FUSE_ROOT_ID)
+            .unwrap()
+            .children
+            .take()
+            .unwrap();
+        for child in children.into_values() {
+            state.free_inode(child);
+        }
+        assert!(state.inodes.contains_key(&a));
+        assert!(!state.inodes.contains_key(&b));
+        assert!(!state.is_reachable(a));
+
+        state.inodes.get_mut(&a).unwrap().lookups = 0;
+        state.free_inode(a);
+        assert!(!state.inodes.contains_key(&a));
+
+        state.free_inode(FUSE_ROOT_ID);
+        assert!(state.inodes.contains_key(&FUSE_ROOT_ID));
+    }
+}
@@ -0,0 +1,355 @@
+#[cfg(target_os = "linux")]
+mod cache;
+#[cfg(target_os = "linux")]
+mod fs;
+#[cfg(target_os = "linux")]
+mod reader;
+#[cfg(target_os = "linux")]
+mod source;
+#[cfg(target_os = "linux")]
+mod writer;
+
+#[cfg(target_os = "linux")]
+#[macro_use]
+extern crate log;
+
+#[cfg(target_os = "linux")]
+mod mount {
+    use crate::cache::*;
+    use crate::fs::CyfsFs;
+    use crate::reader::DataReader;
+    use crate::source::*;
+    use crate::writer::StateWriter;
+    use cyfs_base::*;
+    use cyfs_lib::*;
+
+    use clap::{App, Arg, ArgGroup, ArgMatches};
+    use fuser::MountOption;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    const SERVICE_NAME: &str = "cyfs-mount";
+
+    fn parse_object_id(matches: &ArgMatches, name: &str) -> BuckyResult<Option<ObjectId>> {
+        match matches.value_of(name) {
+            Some(v) => {
+                let id = ObjectId::from_str(v).map_err(|e| {
+                    let msg = format!("invalid object id for {}: {}, {}", name, v, e);
+                    error!("{}", msg);
+                    BuckyError::new(BuckyErrorCode::InvalidParam, msg)
+                })?;
+                Ok(Some(id))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn parse_size(matches: &ArgMatches, name: &str, unit: u64, default: u64) -> BuckyResult<u64> {
+        match matches.value_of(name) {
+            Some(v) => {
+                let size = v.parse::<u64>().map_err(|e| {
+                    let msg = format!("invalid size for {}: {}, {}", name, v, e);
+                    error!("{}", msg);
+                    BuckyError::new(BuckyErrorCode::InvalidParam, msg)
+                })?;
+                size.checked_mul(unit).ok_or_else(|| {
+                    let msg = format!("size for {} out of range: {}", name, v);
+                    error!("{}", msg);
+                    BuckyError::new(BuckyErrorCode::OutOfLimit, msg)
+                })
+            }
+            None => Ok(default),
+        }
+    }
+
+    pub async fn main_run() -> BuckyResult<()> {
+        let matches = App::new("cyfs-mount")
+            .version(cyfs_base::get_version())
+            .about("mount cyfs dir, object_map or global state path as a local filesystem")
+            .arg(
+                Arg::with_name("mountpoint")
+                    .required(true)
+                    .index(1)
+                    .help("local mount point"),
+            )
+            .arg(
+                Arg::with_name("dir")
+                    .long("dir")
+                    .takes_value(true)
+                    .help("mount a dir object"),
+            )
+            .arg(
+                Arg::with_name("object_map")
+                    .long("object-map")
+                    .takes_value(true)
+                    .help("mount an object_map"),
+            )
+            .arg(
+                Arg::with_name("global_state")
+                    .long("global-state")
+                    .takes_value(true)
+                    .help("mount a path of dec's global state, such as /a/b"),
+            )
+            .group(
+                ArgGroup::with_name("root")
+                    .args(&["dir", "object_map", "global_state"])
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("local_cache")
+                    .long("local-cache")
+                    .help("use local-cache instead of root-state for global state"),
+            )
+            .arg(
+                Arg::with_name("target")
+                    .long("target")
+                    .takes_value(true)
+                    .help("the target device or zone to get from, default is local stack"),
+            )
+            .arg(
+                Arg::with_name("dec_id")
+                    .long("dec-id")
+                    .takes_value(true)
+                    .help("the dec id used to open the stack"),
+            )
+            .arg(
+                Arg::with_name("target_dec_id")
+                    .long("target-dec-id")
+                    .takes_value(true)
+                    .help("the dec whose global state will be mounted"),
+            )
+            .arg(
+                Arg::with_name("runtime")
+                    .long("runtime")
+                    .help("use cyfs-runtime instead of ood stack"),
+            )
+            .arg(
+                Arg::with_name("rw")
+                    .long("rw")
+                    .requires("global_state")
+                    .help("mount as read-write, changes are committed to global state with op env"),
+            )
+            .arg(
+                Arg::with_name("allow_other")
+                    .long("allow-other")
+                    .help("allow other users to access the mount"),
+            )
+            .arg(
+                Arg::with_name("cache_dir")
+                    .long("cache-dir")
+                    .takes_value(true)
+                    .help("dir to cache fetched blocks on disk, default only cache in memory"),
+            )
+            .arg(
+                Arg::with_name("memory_cache")
+                    .long("memory-cache")
+                    .takes_value(true)
+                    .help("memory cache size in MB, default is 256"),
+            )
+            .arg(
+                Arg::with_name("disk_cache")
+                    .long("disk-cache")
+                    .takes_value(true)
+                    .requires("cache_dir")
+                    .help("disk cache size limit in MB, default is 10240"),
+            )
+            .arg(
+                Arg::with_name("block_size")
+                    .long("block-size")
+                    .takes_value(true)
+                    .help("range read block size in KB, default is 1024"),
+            )
+            .arg(
+                Arg::with_name("chunk_size")
+                    .long("chunk-size")
+                    .takes_value(true)
+                    .help("chunk size in KB used for files written in rw mode, default is 4096"),
+            )
+            .get_matches();
+
+        cyfs_debug::CyfsLoggerBuilder::new_app(SERVICE_NAME)
+            .level("info")
+            .console("warn")
+            .build()
+            .unwrap()
+            .start();
+
+        cyfs_debug::PanicBuilder::new("cyfs-tools", SERVICE_NAME)
+            .build()
+            .start();
+
+        let mountpoint = PathBuf::from(matches.value_of("mountpoint").unwrap());
+        let root = if let Some(id) = parse_object_id(&matches, "dir")? {
+            MountRoot::Dir(id)
+        } else if let Some(id) = parse_object_id(&matches, "object_map")? {
+            MountRoot::ObjectMap(id)
+        } else {
+            let path = matches.value_of("global_state").unwrap();
+            MountRoot::GlobalState(format!("/{}", path.trim_matches('/')))
+        };
+
+        let dec_id = parse_object_id(&matches, "dec_id")?;
+        let stack = if matches.is_present("runtime") {
+            SharedCyfsStack::open_runtime(dec_id).await
+        } else {
+            SharedCyfsStack::open_default(dec_id).await
+        };
+        let stack = stack.map_err(|e| {
+            error!("open stack failed! {}", e);
+            e
+        })?;
+        stack.online().await.map_err(|e| {
+            error!("stack online failed! {}", e);
+            e
+        })?;
+
+        let category = if matches.is_present("local_cache") {
+            GlobalStateCategory::LocalCache
+        } else {
+            GlobalStateCategory::RootState
+        };
+        let param = MountSourceParam {
+            category,
+            target: parse_object_id(&matches, "target")?,
+            target_dec_id: parse_object_id(&matches, "target_dec_id")?,
+        };
+        let source = MountSource::new(stack.clone(), param);
+
+        let block_size = parse_size(&matches, "block_size", 1024, DEFAULT_BLOCK_SIZE)?;
+        if block_size == 0 {
+            let msg = format!("invalid block size: {}", block_size);
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+        }
+
+        let cache_dir = matches.value_of("cache_dir").map(PathBuf::from);
+        let cache = BlockCache::new(
+            block_size,
+            parse_size(
+                &matches,
+                "memory_cache",
+                1024 * 1024,
+                DEFAULT_MEMORY_CACHE_SIZE,
+            )?,
+            cache_dir.as_ref().map(|dir| dir.join("blocks")),
+            parse_size(&matches, "disk_cache", 1024 * 1024, DEFAULT_DISK_CACHE_SIZE)?,
+        );
+        let reader = DataReader::new(source.clone(), Arc::new(cache));
+
+        let rw = matches.is_present("rw");
+        let writer = if rw {
+            let device = stack.local_device();
+            let owner = device
+                .desc()
+                .owner()
+                .to_owned()
+                .unwrap_or_else(|| stack.local_device_id().object_id().to_owned());
+            let temp_dir = match &cache_dir {
+                Some(dir) => dir.join("upload"),
+                None => cyfs_util::get_temp_path().join(SERVICE_NAME),
+            };
+            let chunk_size = parse_size(&matches, "chunk_size", 1024, 1024 * 1024 * 4)?;
+            if chunk_size == 0 || chunk_size > u32::MAX as u64 {
+                let msg = format!("invalid chunk size: {}", chunk_size);
+                error!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::InvalidParam, msg));
+            }
+
+            Some(StateWriter::new(
+                source.clone(),
+                owner,
+                chunk_size as u32,
+                temp_dir,
+            ))
+        } else {
+            None
+        };
+
+        let fs = CyfsFs::new(source.clone(), source.root_node(&root), reader, writer);
+
+        let mut options = vec![
+            MountOption::FSName("cyfs".to_owned()),
+            MountOption::Subtype(SERVICE_NAME.to_owned()),
+            MountOption::AutoUnmount,
+        ];
+        options.push(if rw { MountOption::RW } else { MountOption::RO });
+        if matches.is_present("allow_other") {
+            options.push(MountOption::AllowOther);
+        }
+
+        info!(
+            "will mount {:?} at {}, rw={}",
+            root,
+            mountpoint.display(),
+            rw
+        );
+
+        // The session loop reads the fuse device synchronously, so run it in a blocking thread,
+        // the callbacks are dispatched to async tasks by CyfsFs
+        async_std::task::spawn_blocking(move || fuser::mount2(fs, &mountpoint, &options))
+            .await
+            .map_err(|e| {
+                let msg = format!("mount failed! {}", e);
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::IoError, msg)
+            })
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        fn matches(args: &[&str]) -> ArgMatches<'static> {
+            App::new("test")
+                .arg(Arg::with_name("size").long("size").takes_value(true))
+                .arg(Arg::with_name("id").long("id").takes_value(true))
+                .get_matches_from(std::iter::once("test").chain(args.iter().cloned()))
+        }
+
+        #[test]
+        fn test_parse_size() {
+            assert_eq!(parse_size(&matches(&[]), "size", 1024, 7).unwrap(), 7);
+            assert_eq!(
+                parse_size(&matches(&["--size", "4"]), "size", 1024, 7).unwrap(),
+                4096
+            );
+
+            let e = parse_size(&matches(&["--size", "x"]), "size", 1024, 7).unwrap_err();
+            assert_eq!(e.code(), BuckyErrorCode::InvalidParam);
+
+            let max = u64::MAX.to_string();
+            let e = parse_size(&matches(&["--size", &max]), "size", 1024, 7).unwrap_err();
+            assert_eq!(e.code(), BuckyErrorCode::OutOfLimit);
+        }
+
+        #[test]
+        fn test_parse_object_id() {
+            assert!(parse_object_id(&matches(&[]), "id").unwrap().is_none());
+
+            let id = ObjectIdDataBuilder::new().data("test").build().unwrap();
+            let s = id.to_string();
+            let ret = parse_object_id(&matches(&["--id", &s]), "id").unwrap();
+            assert_eq!(ret, Some(id));
+
+            let e = parse_object_id(&matches(&["--id", "invalid"]), "id").unwrap_err();
+            assert_eq!(e.code(), BuckyErrorCode::InvalidParam);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    cyfs_debug::ProcessDeadHelper::patch_task_min_thread();
+
+    if let Err(e) = async_std::task::block_on(mount::main_run()) {
+        error!("cyfs-mount exit with error: {}", e);
+        std::process::exit(-1);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    println!("cyfs-mount only support linux now!");
+    std::process::exit(1);
+}
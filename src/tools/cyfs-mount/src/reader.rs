@@ -0,0 +1,162 @@
+use crate::cache::BlockCache;
+use crate::source::{MountSource, NodeKind};
+use cyfs_base::*;
+
+use std::sync::Arc;
+
+pub struct DataReader {
+    source: MountSource,
+    cache: Arc<BlockCache>,
+}
+
+impl DataReader {
+    pub fn new(source: MountSource, cache: Arc<BlockCache>) -> Self {
+        Self { source, cache }
+    }
+
+    pub fn block_size(&self) -> u64 {
+        self.cache.block_size()
+    }
+
+    // Read [offset, offset + size) of the node's content, len is the total content length
+    pub async fn read(
+        &self,
+        node: &NodeKind,
+        len: u64,
+        offset: u64,
+        size: u64,
+    ) -> BuckyResult<Vec<u8>> {
+        if offset >= len {
+            return Ok(vec![]);
+        }
+        let size = std::cmp::min(size, len - offset);
+
+        match node {
+            NodeKind::Data(id) => self.read_object(id, len, offset, size).await,
+            NodeKind::ChunkSlice {
+                chunk_id,
+                offset: base,
+                ..
+            } => {
+                self.read_object(
+                    chunk_id.as_object_id(),
+                    chunk_id.len() as u64,
+                    base + offset,
+                    size,
+                )
+                .await
+            }
+            NodeKind::Object(id) => {
+                let object = self.source.get_object(id).await?;
+                Ok(Self::slice(&object.object_raw, offset, size).to_vec())
+            }
+            _ => {
+                let msg = format!("read on non-data node! node={:?}", node);
+                error!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::NotSupport, msg))
+            }
+        }
+    }
+
+    // The len of the node maybe stale with the object got, so clamp both ends to the buf
+    fn slice(buf: &[u8], offset: u64, size: u64) -> &[u8] {
+        let start = std::cmp::min(offset, buf.len() as u64) as usize;
+        let end = std::cmp::min(offset.saturating_add(size), buf.len() as u64) as usize;
+        &buf[start..end]
+    }
+
+    async fn read_object(
+        &self,
+        object_id: &ObjectId,
+        total: u64,
+        offset: u64,
+        size: u64,
+    ) -> BuckyResult<Vec<u8>> {
+        let end = std::cmp::min(offset + size, total);
+        let block_size = self.cache.block_size();
+
+        let mut ret = Vec::with_capacity((end - offset) as usize);
+        let mut index = offset / block_size;
+        while index * block_size < end {
+            let block = self.load_block(object_id, total, index).await?;
+
+            let block_start = index * block_size;
+            let from = std::cmp::max(offset, block_start) - block_start;
+            let to = std::cmp::min(end, block_start + block_size) - block_start;
+            if to > block.len() as u64 {
+                let msg = format!(
+                    "read block but got short block! id={}, index={}, len={}, need={}",
+                    object_id,
+                    index,
+                    block.len(),
+                    to
+                );
+                error!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+            }
+            ret.extend_from_slice(&block[from as usize..to as usize]);
+
+            index += 1;
+        }
+
+        Ok(ret)
+    }
+
+    async fn load_block(
+        &self,
+        object_id: &ObjectId,
+        total: u64,
+        index: u64,
+    ) -> BuckyResult<Arc<Vec<u8>>> {
+        let start = index * self.cache.block_size();
+        let end = std::cmp::min(start + self.cache.block_size(), total);
+
+        // A truncated block file in the disk cache is ignored and loaded again
+        if let Some(block) = self.cache.get(object_id, index) {
+            if block.len() as u64 == end - start {
+                return Ok(block);
+            }
+
+            warn!(
+                "cached block len unmatch, will reload! id={}, index={}, len={}, expect={}",
+                object_id,
+                index,
+                block.len(),
+                end - start
+            );
+        }
+        let buf = self.source.get_data(object_id, start, end).await?;
+        if buf.len() as u64 != end - start {
+            let msg = format!(
+                "read block but got unmatched len! id={}, range={}-{}, got={}",
+                object_id,
+                start,
+                end,
+                buf.len()
+            );
+            error!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::Unmatch, msg));
+        }
+
+        let block = Arc::new(buf);
+        self.cache.put(object_id, index, block.clone());
+
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DataReader;
+
+    #[test]
+    fn test_slice() {
+        let buf: Vec<u8> = (0..10).collect();
+
+        assert_eq!(DataReader::slice(&buf, 0, 4), &[0, 1, 2, 3]);
+        assert_eq!(DataReader::slice(&buf, 8, 4), &[8, 9]);
+        assert!(DataReader::slice(&buf, 10, 4).is_empty());
+        assert!(DataReader::slice(&buf, 20, 4).is_empty());
+        assert_eq!(DataReader::slice(&buf, 5, u64::MAX), &[5, 6, 7, 8, 9]);
+    }
+}
@@ -0,0 +1,422 @@
+use cyfs_base::*;
+use cyfs_lib::*;
+
+use async_std::io::ReadExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// What to mount as the root of the filesystem
+#[derive(Clone, Debug)]
+pub enum MountRoot {
+    Dir(ObjectId),
+    ObjectMap(ObjectId),
+    GlobalState(String),
+}
+
+#[derive(Clone, Debug)]
+pub enum NodeKind {
+    // Sub tree of a dir object, prefix is the inner path without leading and trailing '/'
+    DirTree {
+        dir_id: ObjectId,
+        prefix: String,
+    },
+
+    // Standalone object_map, listed with a single op env
+    ObjectMap(ObjectId),
+
+    // Full path in global state, such as /a/b
+    StatePath(String),
+
+    // File or chunk content, read through ndn with range
+    Data(ObjectId),
+
+    // Small file packed in the dir's parent chunk
+    ChunkSlice {
+        chunk_id: ChunkId,
+        offset: u64,
+        len: u64,
+    },
+
+    // Other named objects, exposed as their raw encoded buffer
+    Object(ObjectId),
+
+    // File created in mount but not committed yet
+    Empty,
+}
+
+impl NodeKind {
+    pub fn is_dir(&self) -> bool {
+        match self {
+            Self::DirTree { .. } | Self::ObjectMap(_) | Self::StatePath(_) => true,
+            _ => false,
+        }
+    }
+}
+
+struct LoadedDir {
+    list: NDNObjectList,
+    body: Option<DirBodyContentObjectList>,
+}
+
+pub struct MountSourceParam {
+    pub category: GlobalStateCategory,
+    pub target: Option<ObjectId>,
+    pub target_dec_id: Option<ObjectId>,
+}
+
+#[derive(Clone)]
+pub struct MountSource {
+    stack: SharedCyfsStack,
+    param: Arc<MountSourceParam>,
+    dirs: Arc<Mutex<HashMap<ObjectId, Arc<LoadedDir>>>>,
+}
+
+impl MountSource {
+    pub fn new(stack: SharedCyfsStack, param: MountSourceParam) -> Self {
+        Self {
+            stack,
+            param: Arc::new(param),
+            dirs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn stack(&self) -> &SharedCyfsStack {
+        &self.stack
+    }
+
+    pub fn target(&self) -> Option<ObjectId> {
+        self.param.target.clone()
+    }
+
+    pub fn root_node(&self, root: &MountRoot) -> NodeKind {
+        match root {
+            MountRoot::Dir(id) => NodeKind::DirTree {
+                dir_id: id.to_owned(),
+                prefix: "".to_owned(),
+            },
+            MountRoot::ObjectMap(id) => NodeKind::ObjectMap(id.to_owned()),
+            MountRoot::GlobalState(path) => NodeKind::StatePath(path.to_owned()),
+        }
+    }
+
+    pub fn state_stub(&self) -> GlobalStateStub {
+        match self.param.category {
+            GlobalStateCategory::RootState => self
+                .stack
+                .root_state_stub(self.param.target.clone(), self.param.target_dec_id.clone()),
+            GlobalStateCategory::LocalCache => self
+                .stack
+                .local_cache_stub(self.param.target_dec_id.clone()),
+        }
+    }
+
+    fn state_accessor(&self) -> GlobalStateAccessorStub {
+        match self.param.category {
+            GlobalStateCategory::RootState => self.stack.root_state_accessor_stub(
+                self.param.target.clone(),
+                self.param.target_dec_id.clone(),
+            ),
+            GlobalStateCategory::LocalCache => self.stack.local_cache_accessor_stub(
+                self.param.target.clone(),
+                self.param.target_dec_id.clone(),
+            ),
+        }
+    }
+
+    pub async fn list(&self, node: &NodeKind) -> BuckyResult<Vec<(String, NodeKind)>> {
+        match node {
+            NodeKind::DirTree { dir_id, prefix } => self.list_dir(dir_id, prefix).await,
+            NodeKind::ObjectMap(id) => self.list_object_map(id).await,
+            NodeKind::StatePath(path) => self.list_state_path(path).await,
+            _ => {
+                let msg = format!("list on non-dir node! node={:?}", node);
+                error!("{}", msg);
+                Err(BuckyError::new(BuckyErrorCode::NotSupport, msg))
+            }
+        }
+    }
+
+    // Returns the content length of a non-dir node
+    pub async fn data_len(&self, node: &NodeKind) -> BuckyResult<u64> {
+        match node {
+            NodeKind::Data(id) => match id.obj_type_code() {
+                ObjectTypeCode::Chunk => Ok(ChunkId::try_from(id)?.len() as u64),
+                _ => {
+                    let object = self.get_object(id).await?;
+                    let file = File::clone_from_slice(&object.object_raw)?;
+                    Ok(file.len())
+                }
+            },
+            NodeKind::ChunkSlice { len, .. } => Ok(*len),
+            NodeKind::Object(id) => {
+                let object = self.get_object(id).await?;
+                Ok(object.object_raw.len() as u64)
+            }
+            _ => Ok(0),
+        }
+    }
+
+    pub async fn get_object(&self, object_id: &ObjectId) -> BuckyResult<NONObjectInfo> {
+        let req = NONGetObjectOutputRequest::new_router(self.target(), object_id.to_owned(), None);
+        let resp = self
+            .stack
+            .non_service()
+            .get_object(req)
+            .await
+            .map_err(|e| {
+                error!("get object from stack failed! id={}, {}", object_id, e);
+                e
+            })?;
+
+        Ok(resp.object)
+    }
+
+    pub async fn get_data(
+        &self,
+        object_id: &ObjectId,
+        start: u64,
+        end: u64,
+    ) -> BuckyResult<Vec<u8>> {
+        let mut req =
+            NDNGetDataOutputRequest::new_router(self.target(), object_id.to_owned(), None);
+        req.range = Some(NDNDataRequestRange::new_range(vec![start..end]));
+
+        let mut resp = self.stack.ndn_service().get_data(req).await.map_err(|e| {
+            error!(
+                "get data from stack failed! id={}, range={}-{}, {}",
+                object_id, start, end, e
+            );
+            e
+        })?;
+
+        let mut buf = Vec::with_capacity(resp.length as usize);
+        resp.data.read_to_end(&mut buf).await.map_err(|e| {
+            let msg = format!(
+                "read data from stack failed! id={}, range={}-{}, {}",
+                object_id, start, end, e
+            );
+            error!("{}", msg);
+            BuckyError::new(BuckyErrorCode::IoError, msg)
+        })?;
+
+        Ok(buf)
+    }
+
+    fn node_from_id(object_id: &ObjectId, state_path: Option<String>) -> NodeKind {
+        match object_id.obj_type_code() {
+            ObjectTypeCode::ObjectMap => match state_path {
+                Some(path) => NodeKind::StatePath(path),
+                None => NodeKind::ObjectMap(object_id.to_owned()),
+            },
+            ObjectTypeCode::Dir => NodeKind::DirTree {
+                dir_id: object_id.to_owned(),
+                prefix: "".to_owned(),
+            },
+            ObjectTypeCode::File | ObjectTypeCode::Chunk => NodeKind::Data(object_id.to_owned()),
+            _ => NodeKind::Object(object_id.to_owned()),
+        }
+    }
+
+    fn items_to_nodes(
+        list: Vec<ObjectMapContentItem>,
+        parent_path: Option<&str>,
+    ) -> Vec<(String, NodeKind)> {
+        let mut ret = Vec::with_capacity(list.len());
+        for item in list {
+            let (name, id) = match item {
+                ObjectMapContentItem::Map((key, id)) => (key, id),
+                ObjectMapContentItem::Set(id) => (id.to_string(), id),
+                _ => {
+                    debug!("diff item will be ignored in mount: {}", item);
+                    continue;
+                }
+            };
+
+            let state_path = parent_path.map(|parent| Self::join_path(parent, &name));
+            let node = Self::node_from_id(&id, state_path);
+            ret.push((name, node));
+        }
+
+        ret
+    }
+
+    pub fn join_path(parent: &str, name: &str) -> String {
+        format!("{}/{}", parent.trim_end_matches('/'), name)
+    }
+
+    async fn list_object_map(&self, id: &ObjectId) -> BuckyResult<Vec<(String, NodeKind)>> {
+        let op_env = self.state_stub().create_single_op_env().await?;
+        op_env.load(id.to_owned()).await?;
+        let list = op_env.list().await;
+
+        // Read-only env, just drop it
+        let _ = op_env.abort().await;
+
+        Ok(Self::items_to_nodes(list?, None))
+    }
+
+    async fn list_state_path(&self, path: &str) -> BuckyResult<Vec<(String, NodeKind)>> {
+        let list = self.state_accessor().list(path).await.map_err(|e| {
+            error!("list global state path failed! path={}, {}", path, e);
+            e
+        })?;
+
+        Ok(Self::items_to_nodes(list, Some(path)))
+    }
+
+    async fn list_dir(
+        &self,
+        dir_id: &ObjectId,
+        prefix: &str,
+    ) -> BuckyResult<Vec<(String, NodeKind)>> {
+        let dir = self.load_dir(dir_id).await?;
+
+        let mut sub_dirs = std::collections::HashSet::new();
+        let mut ret = vec![];
+        for (inner_path, info) in dir.list.object_map() {
+            let inner_path = inner_path.trim_start_matches('/');
+            let rel = if prefix.is_empty() {
+                inner_path
+            } else {
+                match inner_path
+                    .strip_prefix(prefix)
+                    .and_then(|v| v.strip_prefix('/'))
+                {
+                    Some(rel) => rel,
+                    None => continue,
+                }
+            };
+
+            if let Some(pos) = rel.find('/') {
+                let name = &rel[..pos];
+                if sub_dirs.insert(name.to_owned()) {
+                    let sub_prefix = if prefix.is_empty() {
+                        name.to_owned()
+                    } else {
+                        format!("{}/{}", prefix, name)
+                    };
+                    ret.push((
+                        name.to_owned(),
+                        NodeKind::DirTree {
+                            dir_id: dir_id.to_owned(),
+                            prefix: sub_prefix,
+                        },
+                    ));
+                }
+                continue;
+            }
+
+            let node = match info.node() {
+                InnerNode::ObjId(id) => Self::node_from_id(id, None),
+                InnerNode::Chunk(chunk_id) => NodeKind::Data(chunk_id.object_id()),
+                InnerNode::IndexInParentChunk(offset, len) => match dir.list.parent_chunk() {
+                    Some(chunk_id) => NodeKind::ChunkSlice {
+                        chunk_id: chunk_id.to_owned(),
+                        offset: *offset as u64,
+                        len: *len as u64,
+                    },
+                    None => {
+                        warn!(
+                            "dir inner node index in parent chunk but parent chunk not exists! dir={}, path={}",
+                            dir_id, inner_path
+                        );
+                        continue;
+                    }
+                },
+            };
+
+            ret.push((rel.to_owned(), node));
+        }
+
+        Ok(ret)
+    }
+
+    async fn load_dir(&self, dir_id: &ObjectId) -> BuckyResult<Arc<LoadedDir>> {
+        if let Some(dir) = self.dirs.lock().unwrap().get(dir_id) {
+            return Ok(dir.clone());
+        }
+
+        let object = self.get_object(dir_id).await?;
+        let dir = Dir::clone_from_slice(&object.object_raw)?;
+
+        let body = match dir.body() {
+            Some(body) => match body.content() {
+                DirBodyContent::ObjList(list) => Some(list.to_owned()),
+                DirBodyContent::Chunk(chunk_id) => {
+                    let buf = self.load_chunk(chunk_id).await?;
+                    let (list, _) = DirBodyContentObjectList::raw_decode(&buf)?;
+                    Some(list)
+                }
+            },
+            None => None,
+        };
+
+        let list = match dir.desc().content().obj_list() {
+            NDNObjectInfo::ObjList(list) => list.to_owned(),
+            NDNObjectInfo::Chunk(chunk_id) => {
+                // Try load from dir body first, then from ndn
+                let buf = match body
+                    .as_ref()
+                    .and_then(|body| body.get(chunk_id.as_object_id()))
+                {
+                    Some(buf) => buf.to_owned(),
+                    None => self.load_chunk(chunk_id).await?,
+                };
+                let (list, _) = NDNObjectList::raw_decode(&buf)?;
+                list
+            }
+        };
+
+        let dir = Arc::new(LoadedDir { list, body });
+        self.dirs
+            .lock()
+            .unwrap()
+            .insert(dir_id.to_owned(), dir.clone());
+
+        Ok(dir)
+    }
+
+    async fn load_chunk(&self, chunk_id: &ChunkId) -> BuckyResult<Vec<u8>> {
+        self.get_data(chunk_id.as_object_id(), 0, chunk_id.len() as u64)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_join_path() {
+        assert_eq!(MountSource::join_path("/", "a"), "/a");
+        assert_eq!(MountSource::join_path("/a", "b"), "/a/b");
+        assert_eq!(MountSource::join_path("/a/", "b"), "/a/b");
+    }
+
+    #[test]
+    fn test_items_to_nodes() {
+        let chunk_id = ChunkId::calculate_sync("cyfs-mount".as_bytes()).unwrap();
+        let data_id = ObjectIdDataBuilder::new().data("test").build().unwrap();
+
+        let list = vec![
+            ObjectMapContentItem::Map(("chunk".to_owned(), chunk_id.object_id())),
+            ObjectMapContentItem::Set(data_id.clone()),
+        ];
+        let nodes = MountSource::items_to_nodes(list, Some("/root"));
+        assert_eq!(nodes.len(), 2);
+
+        assert_eq!(nodes[0].0, "chunk");
+        match &nodes[0].1 {
+            NodeKind::Data(id) => assert_eq!(*id, chunk_id.object_id()),
+            node => unreachable!("{:?}", node),
+        }
+
+        // Set items are named by the object id
+        assert_eq!(nodes[1].0, data_id.to_string());
+        match &nodes[1].1 {
+            NodeKind::Object(id) => assert_eq!(*id, data_id),
+            node => unreachable!("{:?}", node),
+        }
+        assert!(!nodes[1].1.is_dir());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::source::MountSource;
+use cyfs_base::*;
+use cyfs_lib::*;
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Writes are only supported for global state mounts: each changed file is published as a new
+// File object and then bound to its path with a path op env commit
+pub struct StateWriter {
+    source: MountSource,
+    owner: ObjectId,
+    chunk_size: u32,
+    temp_dir: PathBuf,
+    next_temp: AtomicU64,
+}
+
+impl StateWriter {
+    pub fn new(source: MountSource, owner: ObjectId, chunk_size: u32, temp_dir: PathBuf) -> Self {
+        Self {
+            source,
+            owner,
+            chunk_size,
+            temp_dir,
+            next_temp: AtomicU64::new(0),
+        }
+    }
+
+    pub fn new_temp_file(&self) -> BuckyResult<(PathBuf, std::fs::File)> {
+        std::fs::create_dir_all(&self.temp_dir)?;
+
+        let index = self.next_temp.fetch_add(1, Ordering::SeqCst);
+        let path = self
+            .temp_dir
+            .join(format!("{}-{}", std::process::id(), index));
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .map_err(|e| {
+                let msg = format!("create temp file failed! file={}, {}", path.display(), e);
+                error!("{}", msg);
+                BuckyError::new(BuckyErrorCode::IoError, msg)
+            })?;
+
+        Ok((path, file))
+    }
+
+    pub async fn commit_file(&self, path: &str, local_path: &Path) -> BuckyResult<ObjectId> {
+        let req = TransPublishFileOutputRequest {
+            common: NDNOutputRequestCommon::new(NDNAPILevel::NDC),
+            owner: self.owner.clone(),
+            local_path: local_path.to_owned(),
+            chunk_size: self.chunk_size,
+            // Temp files are removed on release, the chunks can't be read from the local file later
+            chunk_method: TransPublishChunkMethod::Copy,
+            chunk_cdc: None,
+            access: None,
+            file_id: None,
+            base_file_id: None,
+            dirs: None,
+        };
+
+        let resp = self.source.stack().trans().publish_file(req).await?;
+        let file_id = resp.file_id;
+
+        let op_env = self.source.state_stub().create_path_op_env().await?;
+        op_env.set_with_path(path, &file_id, None, true).await?;
+        op_env.commit().await?;
+
+        info!(
+            "commit file to global state: path={}, file={}",
+            path, file_id
+        );
+        Ok(file_id)
+    }
+
+    pub async fn create_dir(&self, path: &str) -> BuckyResult<()> {
+        let op_env = self.source.state_stub().create_path_op_env().await?;
+        op_env
+            .create_new_with_path(path, ObjectMapSimpleContentType::Map)
+            .await?;
+        op_env.commit().await?;
+
+        info!("create dir in global state: path={}", path);
+        Ok(())
+    }
+
+    pub async fn remove(&self, path: &str) -> BuckyResult<()> {
+        let op_env = self.source.state_stub().create_path_op_env().await?;
+        let ret = op_env.remove_with_path(path, None).await?;
+        if ret.is_none() {
+            let _ = op_env.abort().await;
+            let msg = format!("remove from global state but not found! path={}", path);
+            warn!("{}", msg);
+            return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+        }
+        op_env.commit().await?;
+
+        info!("remove from global state: path={}", path);
+        Ok(())
+    }
+
+    pub async fn rename(&self, from: &str, to: &str) -> BuckyResult<()> {
+        let op_env = self.source.state_stub().create_path_op_env().await?;
+        let value = match op_env.remove_with_path(from, None).await? {
+            Some(value) => value,
+            None => {
+                let _ = op_env.abort().await;
+                let msg = format!("rename in global state but not found! path={}", from);
+                warn!("{}", msg);
+                return Err(BuckyError::new(BuckyErrorCode::NotFound, msg));
+            }
+        };
+        op_env.set_with_path(to, &value, None, true).await?;
+        op_env.commit().await?;
+
+        info!("rename in global state: {} -> {}", from, to);
+        Ok(())
+    }
+}
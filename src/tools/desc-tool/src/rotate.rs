@@ -0,0 +1,314 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use cyfs_base::{
+    bucky_time_now, sign_and_set_named_object_body, AnyNamedObject, DeviceId, FileDecoder,
+    FileEncoder, NamedObject, ObjectDesc, ObjectLink, ObjectTypeCode, PrivateKey, PublicKeyRef,
+    RsaCPUObjectSigner, SignatureSource,
+};
+use cyfs_core::{DeviceRevocationList, DeviceRevocationListObj, KeyRotation, KeyRotationObj};
+
+use log::*;
+use std::path::Path;
+use std::str::FromStr;
+
+// .\desc-tool rotate ${desc-path} -s=${current-secret-path} [--prev=${prev-rotation-path}] -p=rsa1024 --savepath="./"
+// .\desc-tool revoke ${owner-desc-path} -s=${owner-secret-path} -d=${device-id} [-l=${list-path}] [-r=${reason}]
+
+pub fn rotate_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("rotate")
+        .about("rotate people/device key, create signed key rotation record and new secret")
+        .arg(
+            Arg::with_name("desc")
+                .takes_value(true)
+                .index(1)
+                .required(true)
+                .help("people/device desc file to rotate"),
+        )
+        .arg(
+            Arg::with_name("secret")
+                .takes_value(true)
+                .short("s")
+                .long("secret")
+                .required(true)
+                .help("current secret of the object, or owner's secret for device"),
+        )
+        .arg(
+            Arg::with_name("prev")
+                .takes_value(true)
+                .long("prev")
+                .help("last rotation record file, rotate from desc's key if not set"),
+        )
+        .arg(
+            Arg::with_name("pktype")
+                .long("pktype")
+                .short("p")
+                .default_value("rsa1024")
+                .possible_values(&["rsa1024", "rsa2048", "rsa3072", "secp"])
+                .help("new private key type"),
+        )
+        .arg(
+            Arg::with_name("effective_time")
+                .long("effective")
+                .takes_value(true)
+                .help("bucky time the new key takes effect, default now"),
+        )
+        .arg(
+            Arg::with_name("save_path")
+                .long("savepath")
+                .takes_value(true)
+                .help("save file path"),
+        )
+}
+
+pub fn revoke_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("revoke")
+        .about("revoke devices, create or update owner's signed device revocation list")
+        .arg(
+            Arg::with_name("desc")
+                .takes_value(true)
+                .index(1)
+                .required(true)
+                .help("owner desc file"),
+        )
+        .arg(
+            Arg::with_name("secret")
+                .takes_value(true)
+                .short("s")
+                .long("secret")
+                .required(true)
+                .help("owner's current secret"),
+        )
+        .arg(
+            Arg::with_name("devices")
+                .short("d")
+                .long("devices")
+                .takes_value(true)
+                .required(true)
+                .value_delimiter(";")
+                .help("device ids to revoke"),
+        )
+        .arg(
+            Arg::with_name("list")
+                .short("l")
+                .long("list")
+                .takes_value(true)
+                .help("existing revocation list file to update"),
+        )
+        .arg(
+            Arg::with_name("reason")
+                .short("r")
+                .long("reason")
+                .takes_value(true)
+                .help("revoke reason"),
+        )
+        .arg(
+            Arg::with_name("save_path")
+                .long("savepath")
+                .takes_value(true)
+                .help("save file path"),
+        )
+}
+
+fn load_secret(matches: &ArgMatches) -> Option<PrivateKey> {
+    match PrivateKey::decode_from_file(matches.value_of("secret").unwrap().as_ref(), &mut vec![]) {
+        Ok((secret, _)) => Some(secret),
+        Err(e) => {
+            error!("invalid secret! {}", e);
+            None
+        }
+    }
+}
+
+pub async fn rotate_key(matches: &ArgMatches<'_>) {
+    let subject = match AnyNamedObject::decode_from_file(
+        matches.value_of("desc").unwrap().as_ref(),
+        &mut vec![],
+    ) {
+        Ok((subject, _)) => subject,
+        Err(e) => {
+            error!("invalid desc file, {}", e);
+            return;
+        }
+    };
+    let subject_id = subject.object_id();
+    let origin = match subject.public_key() {
+        Some(PublicKeyRef::Single(pk)) => pk.clone(),
+        _ => {
+            error!(
+                "only object with single public key can rotate: {}",
+                subject_id
+            );
+            return;
+        }
+    };
+
+    let secret = match load_secret(matches) {
+        Some(secret) => secret,
+        None => return,
+    };
+
+    // 接在上一次轮换之后，否则从desc里的原始公钥开始
+    let (seq, old_key) = match matches.value_of("prev") {
+        Some(file) => match KeyRotation::decode_from_file(file.as_ref(), &mut vec![]) {
+            Ok((prev, _)) => {
+                if *prev.subject() != subject_id {
+                    error!(
+                        "prev rotation's subject unmatch! expect={}, got={}",
+                        subject_id,
+                        prev.subject()
+                    );
+                    return;
+                }
+                (prev.seq() + 1, prev.new_key().clone())
+            }
+            Err(e) => {
+                error!("invalid prev rotation file, {}", e);
+                return;
+            }
+        },
+        None => (1, origin),
+    };
+
+    // 旧密钥签名；device也可以由owner签名
+    let sign_source = if secret.public() == old_key {
+        subject_id.clone()
+    } else if subject_id.obj_type_code() == ObjectTypeCode::Device && subject.owner().is_some() {
+        warn!("secret is not the current key, will sign as device's owner");
+        subject.owner().clone().unwrap()
+    } else {
+        error!("secret is not the current key of {}", subject_id);
+        return;
+    };
+
+    let new_secret = match matches.value_of("pktype").unwrap() {
+        "rsa2048" => PrivateKey::generate_rsa(2048),
+        "rsa3072" => PrivateKey::generate_rsa(3072),
+        "secp" => PrivateKey::generate_secp256k1(),
+        _ => PrivateKey::generate_rsa(1024),
+    }
+    .unwrap();
+
+    let effective_time = matches
+        .value_of("effective_time")
+        .map(|v| u64::from_str(v).unwrap())
+        .unwrap_or(bucky_time_now());
+
+    let mut rotation = KeyRotation::create(
+        subject_id.clone(),
+        seq,
+        old_key,
+        new_secret.public(),
+        effective_time,
+    );
+
+    let signer = RsaCPUObjectSigner::new(secret.public(), secret);
+    let sign_source = SignatureSource::Object(ObjectLink {
+        obj_id: sign_source,
+        obj_owner: None,
+    });
+    if let Err(e) = sign_and_set_named_object_body(&signer, &mut rotation, &sign_source).await {
+        error!("sign key rotation failed, {}", e);
+        return;
+    }
+
+    let file_path = Path::new(matches.value_of("save_path").unwrap_or(""))
+        .join(format!("{}_{}", subject_id, seq));
+    if let Err(e) = rotation.encode_to_file(&file_path.with_extension("rotation"), true) {
+        error!("write key rotation file failed, err {}", e);
+        return;
+    }
+    if let Err(e) = new_secret.encode_to_file(&file_path.with_extension("sec"), true) {
+        error!("write new secret failed, err {}", e);
+        return;
+    }
+
+    info!(
+        "rotate key succ: subject={}, seq={}, rotation={}, file={}",
+        subject_id,
+        seq,
+        rotation.desc().calculate_id(),
+        file_path.display()
+    );
+}
+
+pub async fn revoke_device(matches: &ArgMatches<'_>) {
+    let owner = match AnyNamedObject::decode_from_file(
+        matches.value_of("desc").unwrap().as_ref(),
+        &mut vec![],
+    ) {
+        Ok((owner, _)) => owner,
+        Err(e) => {
+            error!("invalid desc file, {}", e);
+            return;
+        }
+    };
+    let owner_id = owner.object_id();
+
+    let secret = match load_secret(matches) {
+        Some(secret) => secret,
+        None => return,
+    };
+
+    let mut list = match matches.value_of("list") {
+        Some(file) => match DeviceRevocationList::decode_from_file(file.as_ref(), &mut vec![]) {
+            Ok((list, _)) => {
+                if *list.owner() != owner_id {
+                    error!(
+                        "revocation list's owner unmatch! expect={}, got={}",
+                        owner_id,
+                        list.owner()
+                    );
+                    return;
+                }
+                list
+            }
+            Err(e) => {
+                error!("invalid revocation list file, {}", e);
+                return;
+            }
+        },
+        None => DeviceRevocationList::create(owner_id.clone()),
+    };
+
+    let reason = matches.value_of("reason").map(|v| v.to_owned());
+    let now = bucky_time_now();
+    for id in matches.values_of_lossy("devices").unwrap() {
+        let device_id = match DeviceId::from_str(&id) {
+            Ok(device_id) => device_id,
+            Err(_) => {
+                error!("{} not valid deviceid, ignore", id);
+                continue;
+            }
+        };
+        if list.revoke(device_id.clone(), now, reason.clone()) {
+            info!("revoke device: {}", device_id);
+        } else {
+            warn!("device already revoked: {}", device_id);
+        }
+    }
+
+    let signer = RsaCPUObjectSigner::new(secret.public(), secret);
+    let sign_source = SignatureSource::Object(ObjectLink {
+        obj_id: owner_id.clone(),
+        obj_owner: None,
+    });
+    if let Err(e) = sign_and_set_named_object_body(&signer, &mut list, &sign_source).await {
+        error!("sign revocation list failed, {}", e);
+        return;
+    }
+
+    let file_path = Path::new(matches.value_of("save_path").unwrap_or(""))
+        .join(owner_id.to_string())
+        .with_extension("revocation");
+    if let Err(e) = list.encode_to_file(&file_path, true) {
+        error!("write revocation list file failed, err {}", e);
+        return;
+    }
+
+    info!(
+        "write revocation list succ: owner={}, count={}, file={}",
+        owner_id,
+        list.list().len(),
+        file_path.display()
+    );
+}